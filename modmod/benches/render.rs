@@ -0,0 +1,162 @@
+//! Benchmarks of what is done for every deck, page and exercise of a render: making slugs
+//! and tags of titles, filling in the decks and book pages of the fixture tracks, and
+//! rendering tracks of 50 decks on one thread and on four. Run with `cargo bench`,
+//! and compare a change with `--save-baseline` and `--baseline`.
+
+use std::{fmt::Write, fs, hint::black_box, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use modmod::{context::RunContext, slug, RenderOptions, RenderTarget, Track};
+
+const TRACKS: [(&str, &str); 2] = [
+    (
+        "basic",
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/basic/basic.track.toml"
+        ),
+    ),
+    (
+        "decks",
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/decks/decks.track.toml"
+        ),
+    ),
+];
+
+fn slugs(c: &mut Criterion) {
+    let _context = RunContext::default().enter();
+    let mut group = c.benchmark_group("slug");
+    // Names of files and folders, which are mostly slugs already
+    group.bench_function("slug", |b| {
+        b.iter(|| slug::slug_of(black_box("1_2-ownership-and-borrowing")))
+    });
+    group.bench_function("title", |b| {
+        b.iter(|| slug::slug_of(black_box("Ownership and Borrowing: Part 2")))
+    });
+    group.bench_function("accented title", |b| {
+        b.iter(|| slug::slug_of(black_box("Propriété et emprunt: Déjà vu")))
+    });
+    group.bench_function("long title", |b| {
+        let title = "Advanced Traits and Generic Programming with Lifetimes ".repeat(4);
+        b.iter(|| slug::slug_of(black_box(&title)))
+    });
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let _context = RunContext::default().enter();
+    let mut group = c.benchmark_group("render");
+    for (name, path) in TRACKS {
+        let track = Track::load(path).unwrap();
+        let mut options = RenderOptions::new(std::env::temp_dir().join("modmod-bench"));
+        options.targets = vec![RenderTarget::Slides, RenderTarget::Book];
+        // In memory, so that the benchmark measures filling in the decks and pages rather
+        // than the file system
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let (context, _memory) = RunContext::in_memory();
+                let _context = context.enter();
+                track.render(&options, &mut ()).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The number of decks of the tracks of [`write_many_decks`], about that of a full course
+const DECKS: usize = 50;
+
+/// Writes a track with a unit of one topic for each of [`DECKS`] decks, of 20 slides with
+/// code each, and returns the path of its definition. With `shared`, every unit also has
+/// a topic of 1 MiB of slides that all of them share, which is read once for all decks.
+fn write_many_decks(shared: bool) -> PathBuf {
+    let name = if shared { "shared" } else { "many" };
+    let dir = std::env::temp_dir().join(format!("modmod-bench-{name}"));
+    let topics = dir.join("mods/A-many/topics");
+    let slides = |title: &str, count: usize| {
+        let mut slides = String::from("---\nlayout: default\n---\n");
+        for slide in 0..count {
+            write!(
+                slides,
+                "\n# Slide {slide} of {title}\n\n```rust\nfn main() {{\n    println!(\"{slide}\");\n}}\n```\n\n---\n"
+            )
+            .unwrap();
+        }
+        slides
+    };
+    let common = topics.join("common");
+    fs::create_dir_all(&common).unwrap();
+    fs::write(common.join("topic.toml"), "name = \"Common\"\n").unwrap();
+    // About 1 MiB
+    fs::write(common.join("slides.md"), slides("the common topic", 15_000)).unwrap();
+    let mut units = String::new();
+    for deck in 0..DECKS {
+        let topic = topics.join(format!("topic-{deck}"));
+        fs::create_dir_all(&topic).unwrap();
+        fs::write(
+            topic.join("topic.toml"),
+            format!("name = \"Topic {deck}\"\n"),
+        )
+        .unwrap();
+        fs::write(
+            topic.join("slides.md"),
+            slides(&format!("topic {deck}"), 20),
+        )
+        .unwrap();
+        let common = if shared {
+            "\"topics/common/topic.toml\", "
+        } else {
+            ""
+        };
+        write!(
+            units,
+            "\n[[units]]\nname = \"Unit {deck}\"\ntemplate = \"intro.md\"\ntopics = [{common}\"topics/topic-{deck}/topic.toml\"]\n"
+        )
+        .unwrap();
+    }
+    fs::write(
+        dir.join("mods/A-many/intro.md"),
+        "---\nlayout: cover\n---\n\n# #[modmod:unit_title]\n\n#[modmod:content]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("mods/A-many/mod.toml"),
+        format!("name = \"Many\"\ndescription = \"{DECKS} decks\"\n{units}"),
+    )
+    .unwrap();
+    let track = dir.join(format!("{name}.track.toml"));
+    fs::write(
+        &track,
+        "name = \"Many decks\"\nmodules = [\"mods/A-many/mod.toml\"]\n",
+    )
+    .unwrap();
+    track
+}
+
+/// Renders the decks on one thread and on four, which shows whether they wait for each
+/// other, like on the cache of the topic files that they share
+fn many_decks(c: &mut Criterion) {
+    let _context = RunContext::default().enter();
+    let mut options = RenderOptions::new(std::env::temp_dir().join("modmod-bench"));
+    options.targets = vec![RenderTarget::Slides];
+    let mut group = c.benchmark_group("decks");
+    for (name, shared) in [("own topics", false), ("a shared topic", true)] {
+        let track = Track::load(write_many_decks(shared)).unwrap();
+        for jobs in [1, 4] {
+            let id = format!("{DECKS} decks with {name} on {jobs} thread(s)");
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    let (context, _memory) = RunContext::in_memory();
+                    let _context = context.jobs(jobs).enter();
+                    track.render(&options, &mut ()).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, slugs, render, many_decks);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+// Generated by modmod. Reports to the LMS that the unit of the page that loads it was viewed,
+// through the SCORM 2004 API if the LMS has one, or else the SCORM 1.2 one.
+(function () {
+  function find(name) {
+    for (var w = window; w; w = w === w.parent ? w.opener : w.parent) {
+      try {
+        if (w[name]) {
+          return w[name];
+        }
+      } catch (e) {
+        // A window of another origin, which can't have the API
+      }
+    }
+    return null;
+  }
+
+  var api2004 = find("API_1484_11");
+  var api12 = api2004 ? null : find("API");
+  if (!api2004 && !api12) {
+    return;
+  }
+
+  if (api2004) {
+    api2004.Initialize("");
+    api2004.SetValue("cmi.completion_status", "completed");
+    api2004.Commit("");
+  } else {
+    api12.LMSInitialize("");
+    api12.LMSSetValue("cmi.core.lesson_status", "completed");
+    api12.LMSCommit("");
+  }
+
+  var finished = false;
+  function finish() {
+    if (finished) {
+      return;
+    }
+    finished = true;
+    if (api2004) {
+      api2004.Terminate("");
+    } else {
+      api12.LMSFinish("");
+    }
+  }
+  window.addEventListener("pagehide", finish);
+  window.addEventListener("beforeunload", finish);
+})();
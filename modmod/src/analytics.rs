@@ -0,0 +1,143 @@
+//! Usage numbers of the deployed course from a privacy-friendly analytics service, set in
+//! the `[analytics]` table of a track definition. Without it, nothing is added to the output.
+//!
+//! The tracker is loaded by a script that both the slides and the book get. Slidev merges the
+//! `index.html` of the slides package into the page of every deck, so that's where the slides
+//! get it, and the book loads [`BOOK_SCRIPT_FILE`] with `additional-js`. The script doesn't
+//! load the tracker on the pages the PDFs are exported from, which are Slidev's print and
+//! export views and the print page of mdbook.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scaffold::GENERATED;
+
+/// The script of the book that loads the tracker, relative to the book folder
+pub const BOOK_SCRIPT_FILE: &str = "analytics.js";
+
+/// The page of the slides package that Slidev merges into the page of every deck
+pub const SLIDES_INDEX_FILE: &str = "index.html";
+
+/// The analytics service of a track, as set in its `[analytics]` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analytics {
+    pub provider: AnalyticsProvider,
+    /// The domain the site is registered with for Plausible, or the id of the site in Matomo
+    pub site_id: String,
+    /// For Plausible, the URL of its script, `https://plausible.io/js/script.js` by default.
+    /// For Matomo, the URL of the Matomo instance, which is required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsProvider {
+    Plausible,
+    Matomo,
+}
+
+impl fmt::Display for AnalyticsProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Plausible => "plausible",
+            Self::Matomo => "matomo",
+        })
+    }
+}
+
+impl Analytics {
+    const PLAUSIBLE_SCRIPT: &'static str = "https://plausible.io/js/script.js";
+
+    /// The problems with the settings, which fail loading the track
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.site_id.trim().is_empty() {
+            problems.push("The site_id of [analytics] is empty".to_string());
+        }
+        match self.script_url.as_deref().map(str::trim) {
+            None if self.provider == AnalyticsProvider::Matomo => problems.push(
+                "[analytics] with provider matomo needs the URL of the Matomo instance as script_url"
+                    .to_string(),
+            ),
+            Some(url) if !(url.starts_with("https://") || url.starts_with("http://")) => {
+                problems.push(format!(
+                    "The script_url of [analytics] is '{url}', which isn't an http or https URL"
+                ))
+            }
+            _ => {}
+        }
+        problems
+    }
+
+    /// The script that loads the tracker, unless the page is one that PDFs are exported from
+    pub(crate) fn script(&self) -> String {
+        let site_id = js_string(self.site_id.trim());
+        let tracker = match self.provider {
+            AnalyticsProvider::Plausible => {
+                let src = self.script_url.as_deref().unwrap_or(Self::PLAUSIBLE_SCRIPT);
+                format!(
+                    "  var script = document.createElement('script');
+  script.defer = true;
+  script.dataset.domain = {site_id};
+  script.src = {};
+  document.head.appendChild(script);
+",
+                    js_string(src.trim())
+                )
+            }
+            AnalyticsProvider::Matomo => {
+                let url = self.script_url.as_deref().unwrap_or_default().trim();
+                let url = format!("{}/", url.trim_end_matches('/'));
+                format!(
+                    "  var _paq = (window._paq = window._paq || []);
+  _paq.push(['trackPageView']);
+  _paq.push(['enableLinkTracking']);
+  var url = {};
+  _paq.push(['setTrackerUrl', url + 'matomo.php']);
+  _paq.push(['setSiteId', {site_id}]);
+  var script = document.createElement('script');
+  script.async = true;
+  script.src = url + 'matomo.js';
+  document.head.appendChild(script);
+",
+                    js_string(&url)
+                )
+            }
+        };
+        format!(
+            "// {GENERATED}, to load {} analytics
+(function () {{
+  // The pages the slides and the book are exported to PDF from
+  if (/[?&]print\\b/.test(location.search) || /\\/(print|export)(\\/|\\.html)?$/.test(location.pathname)) {{
+    return;
+  }}
+{tracker}}})();
+",
+            self.provider
+        )
+    }
+
+    /// The `index.html` of the slides package, with the script in its head
+    pub(crate) fn slides_index(&self) -> String {
+        let script: String = self
+            .script()
+            .lines()
+            .map(|line| match line.is_empty() {
+                true => "\n".to_string(),
+                false => format!("    {line}\n"),
+            })
+            .collect();
+        format!("<!-- {GENERATED} -->\n<head>\n  <script>\n{script}  </script>\n</head>\n")
+    }
+}
+
+/// `s` as a string literal of JavaScript
+fn js_string(s: &str) -> String {
+    // JSON strings are JavaScript strings, and `</` is escaped so that `</script>` in a
+    // setting can't end the script of the slides early
+    serde_json::to_string(s)
+        .unwrap_or_default()
+        .replace("</", "<\\/")
+}
@@ -0,0 +1,724 @@
+//! Flashcards of a track, for students to review the course with spaced repetition in Anki.
+//!
+//! Each topic with a summary gets a card that asks for its key points, with its objectives as
+//! a hint, and each term of its glossary a card with the term on the front and its definition
+//! on the back. With [`SummaryCards::Cloze`], summary points that have bold or code text
+//! become cloze cards of their own instead, with that text hidden. The Markdown of the cards
+//! is converted to HTML, and cards are tagged with the module and unit they come from. Topics
+//! that are part of more than one unit give the same cards, which are merged into one with the
+//! tags of all of them.
+//!
+//! The cards are written as a text file that Anki imports, with the note type of each card
+//! and the deck they go in set in its header, or as an Anki package, an `.apkg` file, that has
+//! the deck and the note types of the cards in it. The notes of a package are identified by
+//! their note type and front, so that importing a package of a later version of the track
+//! updates the notes that it has too, keeping their review history.
+
+use std::{
+    fmt,
+    fmt::Write,
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use rusqlite::{params_from_iter, types::Value, Connection, DatabaseName};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+
+use crate::{
+    archive::{self, ArchiveFormat, Entry, EntryContent},
+    cartridge::{escape, inline},
+    list::{check_module, module_matches},
+    update::{fnv1a, FNV_OFFSET},
+    Topic, Track,
+};
+
+/// The ids of the note types of packages, which are the same in each, so that Anki keeps
+/// using the note types it imported from an earlier one
+const BASIC_ID: i64 = 1_700_000_000_001;
+const CLOZE_ID: i64 = 1_700_000_000_002;
+/// The id of the deck every Anki collection has
+const DEFAULT_DECK_ID: i64 = 1;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct AnkiError {}
+
+impl fmt::Display for AnkiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to export the flashcards of the track")
+    }
+}
+
+impl error_stack::Context for AnkiError {}
+
+/// How the summaries of topics are turned into cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryCards {
+    /// A card per topic that asks for its summary
+    Qa,
+    /// A cloze card per summary point with bold or code text, and a card per topic with the
+    /// other points
+    Cloze,
+}
+
+impl FromStr for SummaryCards {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "qa" => Ok(Self::Qa),
+            "cloze" => Ok(Self::Cloze),
+            _ => Err(format!("unknown card form '{s}', expected qa or cloze")),
+        }
+    }
+}
+
+/// The note type of a card, as Anki names it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteType {
+    Basic,
+    Cloze,
+}
+
+impl NoteType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Basic => "Basic",
+            Self::Cloze => "Cloze",
+        }
+    }
+
+    fn id(self) -> i64 {
+        match self {
+            Self::Basic => BASIC_ID,
+            Self::Cloze => CLOZE_ID,
+        }
+    }
+}
+
+/// A card, with HTML fields. Cloze cards have their text on the front, and nothing on the back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    pub note_type: NoteType,
+    pub front: String,
+    pub back: String,
+    /// Like `module::A-foundations` and `unit::basic-syntax`
+    pub tags: Vec<String>,
+}
+
+/// The cards of a track, see the [module docs](self)
+#[derive(Debug)]
+pub struct AnkiDeck {
+    pub track: String,
+    pub cards: Vec<Card>,
+    /// The number of cards that were the same as an earlier one, and merged into it
+    pub merged: usize,
+}
+
+impl AnkiDeck {
+    /// Collects the cards of the track, or only of the module `module`, which is selected
+    /// like with `modmod list --module`
+    pub fn collect(
+        track: &Track,
+        module: Option<&str>,
+        summaries: SummaryCards,
+    ) -> Result<Self, AnkiError> {
+        if let Some(module) = module {
+            check_module::<AnkiError>(track, module)?;
+        }
+        let mut deck = Self {
+            track: track.name.clone(),
+            cards: vec![],
+            merged: 0,
+        };
+        let modules = track
+            .modules
+            .iter()
+            .filter(|m| module.is_none_or(|module| module_matches(&m.data, module)));
+        for module in modules {
+            for unit in module.data.units.iter() {
+                let tags = [
+                    format!("module::{}", module.data.id()),
+                    format!("unit::{}", unit.data.id()),
+                ];
+                for topic in unit.data.topics.iter() {
+                    for card in topic_cards(&topic.data, summaries) {
+                        deck.add(card, &tags);
+                    }
+                }
+            }
+        }
+        Ok(deck)
+    }
+
+    /// Adds the card, or the tags to the same card if it's there already
+    fn add(&mut self, card: (NoteType, String, String), tags: &[String]) {
+        let (note_type, front, back) = card;
+        let existing = self
+            .cards
+            .iter_mut()
+            .find(|c| c.note_type == note_type && c.front == front && c.back == back);
+        match existing {
+            Some(existing) => {
+                self.merged += 1;
+                for tag in tags {
+                    if !existing.tags.contains(tag) {
+                        existing.tags.push(tag.clone());
+                    }
+                }
+            }
+            None => self.cards.push(Card {
+                note_type,
+                front,
+                back,
+                tags: tags.to_vec(),
+            }),
+        }
+    }
+
+    /// The cards as a file for the import of Anki, which puts them in a deck named after
+    /// the track
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!(
+            "#separator:Comma\n#html:true\n#notetype column:1\n#deck:{}\n#tags column:4\n",
+            self.track.replace(['\n', ','], " ")
+        );
+        for card in self.cards.iter() {
+            let fields = [
+                card.note_type.name(),
+                &card.front,
+                &card.back,
+                &card.tags.join(" "),
+            ];
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|field| format!("\"{}\"", field.replace('"', "\"\"")))
+                .collect();
+            let _ = writeln!(csv, "{}", fields.join(","));
+        }
+        csv
+    }
+
+    /// Writes the cards as an Anki package, with a deck named after the track. `modified` is
+    /// the time the notes are marked as changed at, as Anki only updates notes it imported
+    /// earlier from a package with notes that changed later.
+    pub fn write_apkg(&self, path: &Path, modified: SystemTime) -> Result<(), AnkiError> {
+        let collection = write_database(&self.collection(modified))
+            .into_report()
+            .change_context(AnkiError::default())?;
+        let entries = [
+            Entry {
+                path: "collection.anki2".to_string(),
+                executable: false,
+                content: EntryContent::Bytes(collection),
+            },
+            // The media files of the cards, by their number in the package, of which there
+            // are none
+            Entry {
+                path: "media".to_string(),
+                executable: false,
+                content: EntryContent::Bytes(b"{}".to_vec()),
+            },
+        ];
+        archive::write_archive(&entries, path, ArchiveFormat::Zip)
+            .change_context(AnkiError::default())
+            .attach_printable_lazy(|| format!("Error writing {}", path.display()))
+    }
+
+    /// The tables of the collection of an Anki package with the cards, in the schema of
+    /// collections of Anki 2.1, which later versions import
+    fn collection(&self, modified: SystemTime) -> Vec<Table> {
+        let millis = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let secs = millis / 1000;
+        let deck_id = (fnv1a(FNV_OFFSET, self.track.as_bytes()) >> 24) as i64;
+
+        let mut notes = vec![];
+        let mut cards = vec![];
+        for (card, position) in self.cards.iter().zip(0..) {
+            let note_id = millis + position;
+            let fields = [card.front.as_str(), card.back.as_str()];
+            let sort_field = plain_text(&card.front);
+            let checksum = Sha1::digest(sort_field.as_bytes());
+            let checksum = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+            let guid = fnv1a(
+                FNV_OFFSET,
+                format!("{}\u{1f}{}", card.note_type.name(), card.front).as_bytes(),
+            );
+            notes.push(vec![
+                Value::from(note_id),
+                Value::from(format!("{guid:016x}")),
+                Value::from(card.note_type.id()),
+                Value::from(secs),
+                Value::from(-1),
+                Value::from(format!(" {} ", card.tags.join(" "))),
+                Value::from(fields.join("\u{1f}")),
+                Value::from(sort_field),
+                Value::from(i64::from(checksum)),
+                Value::from(0),
+                text(""),
+            ]);
+            // A cloze note has a card for each cloze number
+            let templates = match card.note_type {
+                NoteType::Basic => 1,
+                NoteType::Cloze => cloze_count(&card.front),
+            };
+            for template in 0..templates {
+                let mut card = vec![
+                    Value::from(millis + cards.len() as i64),
+                    Value::from(note_id),
+                    Value::from(deck_id),
+                    Value::from(template),
+                    Value::from(secs),
+                    Value::from(-1),
+                ];
+                // New, and due in the order of the cards, without reviews
+                card.extend([0, 0, position + 1].map(Value::from));
+                card.extend([0; 8].map(Value::from));
+                card.push(text(""));
+                cards.push(card);
+            }
+        }
+
+        let deck = |id: i64, name: &str| {
+            json!({
+                "id": id, "name": name, "mod": secs, "usn": -1, "desc": "", "dyn": 0, "conf": 1,
+                "collapsed": false, "browserCollapsed": false, "extendNew": 0, "extendRev": 0,
+                "newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0],
+            })
+        };
+        let decks = json!({
+            DEFAULT_DECK_ID.to_string(): deck(DEFAULT_DECK_ID, "Default"),
+            deck_id.to_string(): deck(deck_id, &self.track),
+        });
+        let models = json!({
+            BASIC_ID.to_string(): note_type(
+                NoteType::Basic,
+                deck_id,
+                secs,
+                ["Front", "Back"],
+                ("Card 1", "{{Front}}", "{{FrontSide}}<hr id=answer>{{Back}}"),
+            ),
+            CLOZE_ID.to_string(): note_type(
+                NoteType::Cloze,
+                deck_id,
+                secs,
+                ["Text", "Back Extra"],
+                ("Cloze", "{{cloze:Text}}", "{{cloze:Text}}<br>{{Back Extra}}"),
+            ),
+        });
+        let conf = json!({
+            "activeDecks": [deck_id], "curDeck": deck_id, "curModel": BASIC_ID,
+            "nextPos": notes.len() + 1, "sortType": "noteFld", "sortBackwards": false,
+            "addToCur": true, "newSpread": 0, "collapseTime": 1200, "timeLim": 0,
+            "estTimes": true, "dueCounts": true,
+        });
+        let deck_config = json!({
+            "1": {
+                "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "timer": 0,
+                "autoplay": true, "replayq": true, "dyn": false,
+                "new": {
+                    "delays": [1, 10], "ints": [1, 4, 0], "initialFactor": 2500, "order": 1,
+                    "perDay": 20, "bury": false,
+                },
+                "rev": {
+                    "perDay": 200, "ease4": 1.3, "ivlFct": 1, "maxIvl": 36500, "bury": false,
+                    "hardFactor": 1.2,
+                },
+                "lapse": {
+                    "delays": [10], "mult": 0, "minInt": 1, "leechFails": 8, "leechAction": 1,
+                },
+            },
+        });
+        let collection = vec![
+            Value::from(1),
+            Value::from(secs),
+            Value::from(millis),
+            Value::from(millis),
+            // The version of the schema
+            Value::from(11),
+            Value::from(0),
+            Value::from(0),
+            Value::from(0),
+            Value::from(conf.to_string()),
+            Value::from(models.to_string()),
+            Value::from(decks.to_string()),
+            Value::from(deck_config.to_string()),
+            text("{}"),
+        ];
+
+        let table = |name: &str, columns: &str, rows| Table {
+            name: name.to_string(),
+            sql: format!("CREATE TABLE {name} ({columns})"),
+            rows,
+        };
+        let integers = |names: &[&str]| {
+            let columns: Vec<_> = names
+                .iter()
+                .map(|name| format!("{name} integer not null"))
+                .collect();
+            columns.join(", ")
+        };
+        vec![
+            table(
+                "col",
+                &format!(
+                    "id integer primary key, {}, conf text not null, models text not null, decks text not null, dconf text not null, tags text not null",
+                    integers(&["crt", "mod", "scm", "ver", "dty", "usn", "ls"])
+                ),
+                vec![collection],
+            ),
+            table(
+                "notes",
+                "id integer primary key, guid text not null, mid integer not null, mod integer not null, usn integer not null, tags text not null, flds text not null, sfld integer not null, csum integer not null, flags integer not null, data text not null",
+                notes,
+            ),
+            table(
+                "cards",
+                &format!(
+                    "id integer primary key, {}, data text not null",
+                    integers(&[
+                        "nid", "did", "ord", "mod", "usn", "type", "queue", "due", "ivl",
+                        "factor", "reps", "lapses", "left", "odue", "odid", "flags"
+                    ])
+                ),
+                cards,
+            ),
+            table(
+                "revlog",
+                &format!(
+                    "id integer primary key, {}",
+                    integers(&[
+                        "cid", "usn", "ease", "ivl", "lastIvl", "factor", "time", "type"
+                    ])
+                ),
+                vec![],
+            ),
+            table("graves", &integers(&["usn", "oid", "type"]), vec![]),
+        ]
+    }
+
+    pub fn count(&self, note_type: NoteType) -> usize {
+        self.cards
+            .iter()
+            .filter(|card| card.note_type == note_type)
+            .count()
+    }
+}
+
+/// A table of the collection of a package, with its rows
+#[derive(Debug)]
+struct Table {
+    name: String,
+    /// The `create table` statement of the table
+    sql: String,
+    rows: Vec<Vec<Value>>,
+}
+
+/// Writes a database with the tables, in their order
+fn write_database(tables: &[Table]) -> rusqlite::Result<Vec<u8>> {
+    let mut database = Connection::open_in_memory()?;
+    let transaction = database.transaction()?;
+    for table in tables {
+        transaction.execute(&table.sql, [])?;
+        for row in table.rows.iter() {
+            let values = vec!["?"; row.len()].join(", ");
+            transaction.execute(
+                &format!("INSERT INTO {} VALUES ({values})", table.name),
+                params_from_iter(row),
+            )?;
+        }
+    }
+    transaction.commit()?;
+    let file = database.serialize(DatabaseName::Main)?;
+    Ok(file.to_vec())
+}
+
+fn text(text: &str) -> Value {
+    Value::Text(text.to_string())
+}
+
+/// A note type of a package, with the two fields of the cards and its template, as its name,
+/// front and back
+fn note_type(
+    kind: NoteType,
+    deck_id: i64,
+    secs: i64,
+    fields: [&str; 2],
+    (template, front, back): (&str, &str, &str),
+) -> serde_json::Value {
+    let fields: Vec<_> = fields
+        .iter()
+        .zip(0..)
+        .map(|(name, ord)| {
+            json!({
+                "name": name, "ord": ord, "sticky": false, "rtl": false, "font": "Arial",
+                "size": 20, "media": [],
+            })
+        })
+        .collect();
+    json!({
+        "id": kind.id(),
+        "name": format!("modmod {}", kind.name()),
+        "type": match kind {
+            NoteType::Basic => 0,
+            NoteType::Cloze => 1,
+        },
+        "mod": secs,
+        "usn": -1,
+        "sortf": 0,
+        "did": deck_id,
+        "flds": fields,
+        "tmpls": [{
+            "name": template, "ord": 0, "qfmt": front, "afmt": back, "did": null,
+            "bqfmt": "", "bafmt": "",
+        }],
+        "css": ".card { font-family: arial; font-size: 20px; text-align: center; color: black; background-color: white; }\n.cloze { font-weight: bold; color: blue; }",
+        "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+        "latexPost": "\\end{document}",
+        "latexsvg": false,
+        "req": [[0, "any", [0]]],
+        "tags": [],
+        "vers": [],
+    })
+}
+
+/// The text of HTML without its tags, as Anki compares and sorts notes by
+fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find('>')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    text.push_str(rest);
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The number of clozes of a cloze card, which are numbered from 1 by [`cloze`]
+fn cloze_count(text: &str) -> i64 {
+    text.match_indices("{{c")
+        .filter_map(|(start, _)| {
+            let number = &text[start + 3..];
+            number[..number.find("::")?].parse::<i64>().ok()
+        })
+        .max()
+        .unwrap_or(1)
+}
+
+/// The cards of a topic, as their note type, front and back
+fn topic_cards(topic: &Topic, summaries: SummaryCards) -> Vec<(NoteType, String, String)> {
+    let html = |markdown: &str| inline(markdown.trim(), &|url| url.to_string());
+    let mut cards = vec![];
+    let mut points = vec![];
+    for point in topic.summary.iter().map(|point| html(point)) {
+        match summaries {
+            SummaryCards::Cloze => match cloze(&point) {
+                Some(cloze) => cards.push((NoteType::Cloze, cloze, String::new())),
+                None => points.push(point),
+            },
+            SummaryCards::Qa => points.push(point),
+        }
+    }
+    if !points.is_empty() {
+        let mut front = format!("What are the key points of <b>{}</b>?", escape(&topic.name));
+        if !topic.objectives.is_empty() {
+            front.push_str("<br><br>After it, you can:");
+            front.push_str(&list(topic.objectives.iter().map(|o| html(o))));
+        }
+        cards.insert(0, (NoteType::Basic, front, list(points.into_iter())));
+    }
+    for (term, definition) in topic.glossary.iter() {
+        cards.push((NoteType::Basic, html(term), html(definition)));
+    }
+    cards
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    let items: String = items.map(|item| format!("<li>{item}</li>")).collect();
+    format!("<ul>{items}</ul>")
+}
+
+/// Hides the bold and code text of `html` in clozes, or `None` if it has none. Text that has
+/// the `}}` that ends a cloze is left as it is.
+fn cloze(html: &str) -> Option<String> {
+    let mut cloze = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut number = 0;
+    loop {
+        let next = ["<strong>", "<code>"]
+            .into_iter()
+            .filter_map(|open| rest.find(open).map(|start| (start, open)))
+            .min();
+        let Some((start, open)) = next else {
+            break;
+        };
+        let close = format!("</{}", &open[1..]);
+        let Some(length) = rest[start..].find(&close).map(|end| end + close.len()) else {
+            break;
+        };
+        let hidden = &rest[start..start + length];
+        cloze.push_str(&rest[..start]);
+        match hidden.contains("}}") {
+            true => cloze.push_str(hidden),
+            false => {
+                number += 1;
+                let _ = write!(cloze, "{{{{c{number}::{hidden}}}}}");
+            }
+        }
+        rest = &rest[start + length..];
+    }
+    cloze.push_str(rest);
+    (number > 0).then_some(cloze)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn deck() -> AnkiDeck {
+        let card = |note_type, front: &str, back: &str| Card {
+            note_type,
+            front: front.to_string(),
+            back: back.to_string(),
+            tags: vec![
+                "module::A-basics".to_string(),
+                "unit::first-steps".to_string(),
+            ],
+        };
+        AnkiDeck {
+            track: "Rust intro".to_string(),
+            cards: vec![
+                card(
+                    NoteType::Basic,
+                    "<b>Ownership</b> &amp; borrowing",
+                    "<ul></ul>",
+                ),
+                card(
+                    NoteType::Cloze,
+                    "{{c1::<strong>Moves</strong>}} and {{c2::<code>Copy</code>}}",
+                    "",
+                ),
+            ],
+            merged: 0,
+        }
+    }
+
+    /// The rows of the table, by rowid, read back from the file of the database
+    fn table(tables: &[Table], name: &str) -> Vec<(i64, Vec<Value>)> {
+        let file = write_database(tables).unwrap();
+        let dir = crate::io::scratch_dir::<AnkiError>("anki").unwrap();
+        let path = dir.path().join("collection.anki2");
+        std::fs::write(&path, file).unwrap();
+        let database = Connection::open(&path).unwrap();
+        let mut rows = database
+            .prepare(&format!("SELECT rowid, * FROM {name} ORDER BY rowid"))
+            .unwrap();
+        let columns = rows.column_count();
+        rows.query_map([], |row| {
+            let values = (1..columns)
+                .map(|i| row.get(i))
+                .collect::<rusqlite::Result<_>>()?;
+            Ok((row.get(0)?, values))
+        })
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap()
+    }
+
+    #[test]
+    fn collection_has_the_notes_and_their_cards() {
+        let modified = UNIX_EPOCH + Duration::from_millis(1_750_000_000_123);
+        let tables = deck().collection(modified);
+
+        let notes = table(&tables, "notes");
+        assert_eq!(notes.len(), 2);
+        let (id, note) = &notes[0];
+        assert_eq!(*id, 1_750_000_000_123);
+        assert_eq!(note[2], Value::from(BASIC_ID));
+        assert_eq!(note[3], Value::from(1_750_000_000));
+        assert_eq!(note[5], text(" module::A-basics unit::first-steps "));
+        assert_eq!(
+            note[6],
+            text("<b>Ownership</b> &amp; borrowing\u{1f}<ul></ul>")
+        );
+        assert_eq!(note[7], text("Ownership & borrowing"));
+        // The first 8 hex digits of the SHA-1 of the sort field, 92bd0fc4c6...
+        assert_eq!(note[8], Value::from(0x92bd_0fc4_i64));
+        assert_eq!(notes[1].1[2], Value::from(CLOZE_ID));
+
+        // A card for the basic note, and one for each cloze of the cloze note
+        let cards = table(&tables, "cards");
+        let of_notes: Vec<_> = cards
+            .iter()
+            .map(|(_, card)| (card[1].clone(), card[3].clone()))
+            .collect();
+        let note_ids = [notes[0].0, notes[1].0].map(Value::from);
+        assert_eq!(
+            of_notes,
+            [
+                (note_ids[0].clone(), Value::from(0)),
+                (note_ids[1].clone(), Value::from(0)),
+                (note_ids[1].clone(), Value::from(1)),
+            ]
+        );
+
+        let [(1, collection)] = &table(&tables, "col")[..] else {
+            panic!("The collection isn't one row");
+        };
+        let Value::Text(decks) = &collection[10] else {
+            panic!("The decks aren't text");
+        };
+        let decks: serde_json::Value = serde_json::from_str(decks).unwrap();
+        let deck_id = &cards[0].1[2];
+        let Value::Integer(deck_id) = deck_id else {
+            panic!("The deck of the card isn't an id");
+        };
+        assert_eq!(decks[deck_id.to_string()]["name"], "Rust intro");
+    }
+
+    #[test]
+    fn notes_keep_their_identity_across_exports() {
+        let guids = |modified| {
+            let tables = deck().collection(modified);
+            table(&tables, "notes")
+                .into_iter()
+                .map(|(_, note)| note[1].clone())
+                .collect::<Vec<_>>()
+        };
+        let later = UNIX_EPOCH + Duration::from_secs(2_000_000_000);
+        let earlier = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(guids(earlier), guids(later));
+        let mut other = deck();
+        other.cards.swap(0, 1);
+        let tables = other.collection(later);
+        let mut swapped: Vec<_> = table(&tables, "notes")
+            .into_iter()
+            .map(|(_, note)| note[1].clone())
+            .collect();
+        swapped.reverse();
+        assert_eq!(swapped, guids(later));
+    }
+
+    #[test]
+    fn plain_text_of_fields() {
+        assert_eq!(
+            plain_text("<b>a &lt;b&gt;</b> <br>&quot;c&apos;&amp;"),
+            "a <b> \"c'&"
+        );
+        assert_eq!(cloze_count("{{c1::a}} {{c3::b}} {{c2::c}}"), 3);
+    }
+}
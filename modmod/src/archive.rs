@@ -0,0 +1,366 @@
+//! Packing a rendered course into a single zip or tar.gz archive, for handing it over.
+//!
+//! Archives are deterministic: entries are sorted by path, and have no timestamps or
+//! owners. They are written and read with the zip and tar crates, and compressed with
+//! deflate. Files are streamed into the archive from disk, so that large assets aren't held
+//! in memory.
+
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+use crate::{
+    cache::CACHE_FILE,
+    instructor::INSTRUCTOR_DIR,
+    io::{same_bytes, PathExt, CHUNK_SIZE, JOURNAL_FILE},
+    manifest::{self, RenderManifest, RENDER_MANIFEST_FILE},
+    progress::{self, Phase},
+    update::{EDITS_DIR, HASHES_FILE},
+    version::CourseVersion,
+};
+
+/// Folders that are never archived: installed packages and caches of build tools, and the
+/// local changes a render saved
+pub const EXCLUDED_DIRS: &[&str] = &["node_modules", ".cache", "target", EDITS_DIR];
+
+/// Files that are never archived: the render cache, the hashes `update` goes by and the
+/// journal of a render, which are about the output folder they were written in
+pub const EXCLUDED_FILES: &[&str] = &[CACHE_FILE, HASHES_FILE, JOURNAL_FILE];
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ArchiveError {}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to archive output")
+    }
+}
+
+impl error_stack::Context for ArchiveError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// The format of an archive by its extension: `.zip`, or `.tar.gz` or `.tgz`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// The path of the archive with the course version added to its name, like
+/// `rust-2025.1.zip` for `rust.zip`, unless the name already has the version in it
+pub fn versioned_path(path: &Path, version: &CourseVersion) -> PathBuf {
+    let version = version.file_name_part();
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return path.to_path_buf();
+    };
+    if name.contains(&version) {
+        return path.to_path_buf();
+    }
+    let extension = [".tar.gz", ".tgz", ".zip"]
+        .into_iter()
+        .find(|extension| name.ends_with(extension))
+        .unwrap_or_default();
+    let stem = &name[..name.len() - extension.len()];
+    path.with_file_name(format!("{stem}-{version}{extension}"))
+}
+
+/// A file in an archive
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Path relative to the root of the archive, with forward slashes
+    pub path: String,
+    pub executable: bool,
+    pub content: EntryContent,
+}
+
+/// The content of an [`Entry`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum EntryContent {
+    /// Content in memory, like that of generated files and of entries read from an archive
+    Bytes(Vec<u8>),
+    /// A file of `size` bytes, which is read as the archive is written
+    File { path: PathBuf, size: u64 },
+}
+
+impl EntryContent {
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes.len() as u64,
+            Self::File { size, .. } => *size,
+        }
+    }
+
+    /// The content, which is read into memory if it's in a file
+    pub fn bytes(&self) -> Result<Cow<'_, [u8]>, ArchiveError> {
+        match self {
+            Self::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+            Self::File { path, .. } => fs::read(path)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+                .change_context(ArchiveError::default())
+                .map(Cow::Owned),
+        }
+    }
+
+    /// Whether both hold the same bytes. Files are read a chunk at a time.
+    pub fn same_as(&self, other: &Self) -> Result<bool, ArchiveError> {
+        if self.size() != other.size() {
+            return Ok(false);
+        }
+        let same = self
+            .reader()
+            .and_then(|a| same_bytes(a, other.reader()?))
+            .into_report()
+            .change_context(ArchiveError::default())?;
+        Ok(same)
+    }
+
+    /// Reads the content. Fails if a file no longer has the size it had when its entry was
+    /// made, as the size is already in the header of the entry.
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(match self {
+            Self::Bytes(bytes) => Box::new(bytes.as_slice()),
+            Self::File { path, size } => Box::new(SizedFile {
+                file: File::open(path)?.take(*size),
+                left: *size,
+                path,
+            }),
+        })
+    }
+}
+
+/// A file that is read up to the size it had when its entry was made
+struct SizedFile<'a> {
+    file: io::Take<File>,
+    left: u64,
+    path: &'a Path,
+}
+
+impl Read for SizedFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.file.read(buf)?;
+        if read == 0 && self.left > 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} changed while it was archived", self.path.display()),
+            ));
+        }
+        self.left -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Whether both list the same files, with the same content
+pub fn same_entries(a: &[Entry], b: &[Entry]) -> Result<bool, ArchiveError> {
+    if a.len() != b.len() {
+        return Ok(false);
+    }
+    for (a, b) in a.iter().zip(b) {
+        if a.path != b.path || a.executable != b.executable || !a.content.same_as(&b.content)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Lists the files in `dir` as entries, sorted by path, leaving out the
+/// [`EXCLUDED_DIRS`], the [`EXCLUDED_FILES`] and the instructor notes, which aren't for
+/// students. Their content is only read when the archive is written, except for that of
+/// the render manifests, which are archived without their timestamps, see
+/// [`RenderManifest::archived`].
+pub fn entries_of_dir(dir: &Path) -> Result<Vec<Entry>, ArchiveError> {
+    let mut entries = vec![];
+    for path in dir.get_dir_content::<ArchiveError>()?.files {
+        let path = PathBuf::from(path);
+        let relative = path.strip_prefix(dir).unwrap();
+        if relative.starts_with(INSTRUCTOR_DIR) {
+            continue;
+        }
+        if relative.components().any(|c| {
+            EXCLUDED_DIRS
+                .iter()
+                .any(|excluded| c.as_os_str() == *excluded)
+        }) {
+            continue;
+        }
+        let name = relative.file_name().unwrap_or_default();
+        if EXCLUDED_FILES.iter().any(|excluded| name == *excluded) {
+            continue;
+        }
+        if name == RENDER_MANIFEST_FILE {
+            let content = RenderManifest::archived(&path).change_context(ArchiveError::default())?;
+            entries.push(Entry {
+                path: manifest::relative_path(relative),
+                executable: false,
+                content: EntryContent::Bytes(content),
+            });
+            continue;
+        }
+        let size = fs::metadata(&path)
+            .into_report()
+            .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+            .change_context(ArchiveError::default())?
+            .len();
+        entries.push(Entry {
+            path: manifest::relative_path(relative),
+            executable: is_executable(&path),
+            content: EntryContent::File { path, size },
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Whether the file at `path` has an executable bit set. Only Unix has those.
+#[cfg(unix)]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+/// Writes the entries into an archive at `path`
+pub fn write_archive(
+    entries: &[Entry],
+    path: &Path,
+    format: ArchiveFormat,
+) -> Result<(), ArchiveError> {
+    let _phase = progress::start(Phase::Archive, None);
+    let mut file = path.create_file()?;
+    {
+        let mut out = io::BufWriter::with_capacity(CHUNK_SIZE, &mut file);
+        match format {
+            ArchiveFormat::Zip => zip(entries, &mut out),
+            ArchiveFormat::TarGz => tar(entries, GzEncoder::new(&mut out, Compression::default())),
+        }
+        .and_then(|_| write(out.flush()))
+        .attach_printable_lazy(|| format!("Error writing archive {}", path.display()))?;
+    }
+    file.commit()
+}
+
+/// Reads the entries of an archive, like one written by [`write_archive`]. Folders and
+/// links in it are left out.
+pub fn read_archive(path: &Path, format: ArchiveFormat) -> Result<Vec<Entry>, ArchiveError> {
+    let bytes = fs::read(path)
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading archive {}", path.display()))
+        .change_context(ArchiveError::default())?;
+    let entries = match format {
+        ArchiveFormat::Zip => unzip(&bytes),
+        ArchiveFormat::TarGz => untar(GzDecoder::new(bytes.as_slice())),
+    };
+    entries
+        .into_report()
+        .attach_printable_lazy(|| format!("{} is not an archive that can be read", path.display()))
+        .change_context(ArchiveError::default())
+}
+
+fn write(result: io::Result<()>) -> Result<(), ArchiveError> {
+    result.into_report().change_context(ArchiveError::default())
+}
+
+fn zip(entries: &[Entry], out: impl Write) -> Result<(), ArchiveError> {
+    let mut zip = ZipWriter::new_stream(out);
+    for entry in entries {
+        let mode = if entry.executable { 0o755 } else { 0o644 };
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            // 1980-01-01, the earliest date zip can store
+            .last_modified_time(DateTime::default())
+            .unix_permissions(mode)
+            .large_file(entry.content.size() >= u64::from(u32::MAX));
+        zip.start_file(entry.path.as_str(), options)
+            .map_err(io::Error::other)
+            .and_then(|_| io::copy(&mut entry.content.reader()?, &mut zip))
+            .into_report()
+            .attach_printable_lazy(|| format!("Error archiving {}", entry.path))
+            .change_context(ArchiveError::default())?;
+    }
+    write(zip.finish().map(drop).map_err(io::Error::other))
+}
+
+fn unzip(bytes: &[u8]) -> io::Result<Vec<Entry>> {
+    let mut archive = ZipArchive::new(io::Cursor::new(bytes)).map_err(io::Error::other)?;
+    let mut entries = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        if !file.is_file() {
+            continue;
+        }
+        let mut content = vec![];
+        file.read_to_end(&mut content)?;
+        entries.push(Entry {
+            path: file.name().map_err(io::Error::other)?.to_string(),
+            executable: file.unix_mode().is_some_and(|mode| mode & 0o111 != 0),
+            content: EntryContent::Bytes(content),
+        });
+    }
+    Ok(entries)
+}
+
+fn tar(entries: &[Entry], gzip: GzEncoder<impl Write>) -> Result<(), ArchiveError> {
+    let mut tar = tar::Builder::new(gzip);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(entry.content.size());
+        header.set_mode(if entry.executable { 0o755 } else { 0o644 });
+        header.set_mtime(0);
+        // Long paths are written as an extension of GNU tar
+        entry
+            .content
+            .reader()
+            .and_then(|content| tar.append_data(&mut header, &entry.path, content))
+            .into_report()
+            .attach_printable_lazy(|| format!("Error archiving {}", entry.path))
+            .change_context(ArchiveError::default())?;
+    }
+    write(tar.into_inner().and_then(GzEncoder::finish).map(drop))
+}
+
+fn untar(tar: impl Read) -> io::Result<Vec<Entry>> {
+    let mut archive = tar::Archive::new(tar);
+    let mut entries = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = manifest::relative_path(&entry.path()?);
+        let executable = entry.header().mode()? & 0o111 != 0;
+        let mut content = vec![];
+        entry.read_to_end(&mut content)?;
+        entries.push(Entry {
+            path,
+            executable,
+            content: EntryContent::Bytes(content),
+        });
+    }
+    Ok(entries)
+}
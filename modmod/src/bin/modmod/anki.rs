@@ -0,0 +1,73 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    anki::{AnkiDeck, NoteType, SummaryCards},
+    io::PathExt,
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "summaries",
+        default_value = "qa",
+        help = "How summaries become cards: qa for a card per topic that asks for its summary, or cloze for a cloze card per summary point with bold or code text"
+    )]
+    summaries: SummaryCards,
+    #[arg(
+        long = "module",
+        value_name = "ID",
+        help = "Only export the cards of this module: the name of the folder of its definition, the part of that before the first dash, like B, or its label"
+    )]
+    module: Option<String>,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "Write the cards to this file instead of printing them, as an Anki package if it ends in .apkg"
+    )]
+    out_file: Option<PathBuf>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        summaries,
+        module,
+        out_file,
+    } = args;
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let deck = AnkiDeck::collect(&track, module.as_deref(), summaries)
+        .change_context(ModModError::default())?;
+
+    let Some(out_file) = out_file else {
+        print!("{}", deck.to_csv());
+        return Ok(());
+    };
+    match out_file.extension().is_some_and(|e| e == "apkg") {
+        true => deck
+            .write_apkg(&out_file, SystemTime::now())
+            .change_context(ModModError::default())?,
+        false => out_file.write_text_file(deck.to_csv())?,
+    }
+    println!(
+        "Wrote {} card(s) and {} cloze card(s) to {}, merging {} duplicate(s)",
+        deck.count(NoteType::Basic),
+        deck.count(NoteType::Cloze),
+        out_file.display(),
+        deck.merged
+    );
+    Ok(())
+}
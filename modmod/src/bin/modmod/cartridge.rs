@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    archive::{self, ArchiveFormat},
+    cartridge::{check_references, Cartridge, CartridgeOptions},
+    url::BaseUrl,
+    FailurePolicy, RenderOptions, RenderTarget, Track,
+};
+
+use crate::{gen::LogReporter, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The cartridge to write, usually a .imscc file",
+        default_value = "course.imscc"
+    )]
+    out_file: PathBuf,
+    #[arg(
+        long = "base-url",
+        value_name = "URL",
+        help = "The absolute URL the slides and book are deployed at, like https://example.com/training/. The cartridge links to them"
+    )]
+    base_url: BaseUrl,
+    #[arg(
+        long = "profile",
+        add = crate::completions::profiles(),
+        help = "Resolve conditional content for this profile, as defined in the track definition"
+    )]
+    profile: Option<String>,
+    #[arg(
+        long = "skip-optional",
+        help = "Leave out topics and exercises marked as optional"
+    )]
+    skip_optional: bool,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        out_file,
+        base_url,
+        profile,
+        skip_optional,
+    } = args;
+    if base_url.origin().is_none() {
+        return Err(ModModError::usage(format!(
+            "--base-url must be an absolute URL like https://example.com/training/, as the cartridge links to the hosted slides and book, not {base_url}"
+        )));
+    }
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_cartridge_tmp")?;
+    let cartridge = cartridge(&track, &tmp_dir, &base_url, profile, skip_optional)?;
+
+    archive::write_archive(&cartridge.entries, &out_file, ArchiveFormat::Zip)
+        .change_context(ModModError::default())?;
+    if !modmod::io::writes_discarded_at(&out_file) {
+        // Catches the cartridge not matching what was collected before it's imported
+        let packed = archive::read_archive(&out_file, ArchiveFormat::Zip)
+            .change_context(ModModError::default())?;
+        check_references(&packed).change_context(ModModError::default())?;
+        if !archive::same_entries(&packed, &cartridge.entries)
+            .change_context(ModModError::default())?
+        {
+            return Err(ModModError::report().attach_printable(format!(
+                "The cartridge at {} doesn't match the rendered book",
+                out_file.display()
+            )));
+        }
+    }
+    println!(
+        "Wrote cartridge {} with {} chapter(s), {} slide link(s) and {} assignment(s)",
+        out_file.display(),
+        cartridge.chapters,
+        cartridge.slide_links,
+        cartridge.assignments
+    );
+    Ok(())
+}
+
+/// Renders the book of the track into `tmp_dir`, and collects the cartridge from it
+fn cartridge(
+    track: &Track,
+    tmp_dir: &Path,
+    base_url: &BaseUrl,
+    profile: Option<String>,
+    skip_optional: bool,
+) -> Result<Cartridge, ModModError> {
+    let mut opts = RenderOptions::new(tmp_dir);
+    opts.base_url = base_url.clone();
+    opts.clear_output_dir = true;
+    opts.skip_optional = skip_optional;
+    opts.profile = profile.clone();
+    // The slides are linked to where they are hosted, so only the book is needed
+    opts.targets = vec![RenderTarget::Book];
+    track
+        .render(&opts, &mut LogReporter)
+        .change_context(ModModError::default())?;
+    track
+        .cartridge(
+            tmp_dir,
+            CartridgeOptions {
+                base_url,
+                layout: &track.output,
+                profile: profile.as_deref(),
+            },
+        )
+        .change_context(ModModError::default())
+}
@@ -0,0 +1,306 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    check::{find_orphans, CheckOptions, Orphans},
+    context,
+    external_links::ExternalLinkOptions,
+    log,
+    report::{Warning, WarningCode},
+    FailurePolicy, RenderOptions, Topic, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(required = true)]
+    track_toml_paths: Vec<PathBuf>,
+    #[arg(
+        long = "review-max-age",
+        value_name = "DAYS",
+        help = "Warn about topics that were last reviewed longer ago than this many days, or that don't record when they were reviewed"
+    )]
+    review_max_age_days: Option<i64>,
+    #[arg(
+        long = "orphans",
+        help = "List content in the track folders that none of the tracks refer to"
+    )]
+    orphans: bool,
+    #[arg(
+        long = "json",
+        help = "Output the list of orphans as JSON",
+        requires = "orphans"
+    )]
+    json: bool,
+    #[arg(long = "templates", help = "List the slide template used by each deck")]
+    templates: bool,
+    #[arg(
+        long = "derived-summaries",
+        help = "List the topics whose summary was derived from their headings, with the derived items",
+        conflicts_with = "structure_only"
+    )]
+    derived_summaries: bool,
+    #[arg(
+        long = "print-merged",
+        help = "Print the effective track, after merging in the tracks it extends"
+    )]
+    print_merged: bool,
+    #[arg(
+        long = "allow-index-gaps",
+        help = "Don't warn about gaps in the numbering of modules and units"
+    )]
+    allow_index_gaps: bool,
+    #[arg(
+        long = "structure-only",
+        help = "Only check the structure of the tracks, without reading the slides and exercise descriptions"
+    )]
+    structure_only: bool,
+    #[arg(
+        long = "check-external",
+        visible_alias = "external-links",
+        help = "Request the external links in slides and exercise descriptions to see whether they can be reached",
+        conflicts_with = "structure_only"
+    )]
+    check_external: bool,
+    #[arg(
+        long = "link-cache-max-age",
+        value_name = "HOURS",
+        help = "Request external links again that could be reached longer ago than this many hours, 0 to request all of them",
+        default_value_t = 24,
+        requires = "check_external"
+    )]
+    link_cache_max_age_hours: u64,
+    #[arg(
+        long = "offline",
+        help = "Fail on exercises from git repositories that aren't cached yet, instead of fetching them"
+    )]
+    offline: bool,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_paths,
+        review_max_age_days,
+        orphans,
+        json,
+        templates,
+        derived_summaries,
+        print_merged,
+        allow_index_gaps,
+        structure_only,
+        check_external,
+        link_cache_max_age_hours,
+        offline,
+    } = args;
+    let started = Instant::now();
+    // Checking must be safe in a hook or on a read-only checkout, so nothing is written:
+    // not the rendered output, and not exercises fetched into the cache either. Only the
+    // external link cache is written, which the check of external links does itself.
+    let _context = context::current().discard_writes().enter();
+
+    let mut loader = crate::loader(FailurePolicy::Strict, content_dir);
+    if structure_only {
+        loader = loader.without_content();
+    }
+    if offline {
+        loader = loader.offline();
+    }
+    let tracks = Track::load_toml_defs_with(&track_toml_paths, loader)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .change_context(ModModError::default())?;
+
+    if orphans {
+        let orphans = find_orphans(&tracks).change_context(ModModError::default())?;
+        if json {
+            let json = serde_json::to_string_pretty(&orphans)
+                .into_report()
+                .change_context(ModModError::default())?;
+            println!("{json}");
+            return Ok(());
+        }
+        print_orphans(&orphans);
+    }
+
+    if print_merged {
+        tracks.iter().for_each(print_merged_track);
+    }
+
+    if templates {
+        tracks.iter().for_each(print_templates);
+    }
+
+    if derived_summaries {
+        tracks.iter().for_each(print_derived_summaries);
+    }
+
+    let opts = CheckOptions {
+        review_max_age_days,
+        allow_index_gaps,
+        structure_only,
+        ..Default::default()
+    };
+    let link_opts = ExternalLinkOptions {
+        cache_max_age: Duration::from_secs(link_cache_max_age_hours * 60 * 60),
+        ..Default::default()
+    };
+    let mut warning_count = 0;
+    for track in tracks.iter() {
+        let mut warnings = track.check(&opts);
+        if !structure_only {
+            // Rendering finds problems in the content too, like unknown placeholders
+            for warning in render_warnings(track, allow_index_gaps)? {
+                // Broken links are reported by the checks already, at their source
+                if warning.code != WarningCode::BrokenLink && !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+        for warning in warnings {
+            println!("warning: {warning}");
+            log::record_warning(&warning);
+            warning_count += 1;
+        }
+        if check_external {
+            let links = track
+                .check_external_links(&link_opts)
+                .attach_printable_lazy(|| format!("Checking track '{}'", track.name))
+                .change_context(ModModError::default())?;
+            for warning in &links.warnings {
+                println!("warning: {warning}");
+                log::record_warning(warning);
+            }
+            print!("{links}");
+            for link in &links.unreachable {
+                let message = format!("Link '{}' can't be reached: {}", link.url, link.error);
+                let warning = Warning::new(WarningCode::UnreachableLink, message, Some(&link.file));
+                log::record_warning(&warning.at_line(link.line));
+            }
+            warning_count += links.warnings.len() + links.unreachable.len();
+        }
+    }
+    println!("{warning_count} warning(s) found");
+    println!(
+        "Checked {} track(s) in {:.2}s",
+        tracks.len(),
+        started.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Runs the render pipelines of the track, which don't write anything as writes are
+/// discarded, and returns the warnings they found
+fn render_warnings(track: &Track, allow_index_gaps: bool) -> Result<Vec<Warning>, ModModError> {
+    // Shown in warnings about the rendered output, which isn't written anywhere
+    let mut opts = RenderOptions::new("<output>");
+    opts.allow_index_gaps = allow_index_gaps;
+    // Reported by the checks instead, as a free-form version may be intended
+    opts.free_form_version = true;
+    let report = track
+        .render(&opts, &mut ())
+        .attach_printable_lazy(|| format!("Rendering track '{}'", track.name))
+        .change_context(ModModError::default())?;
+    Ok(report.warnings)
+}
+
+fn print_merged_track(track: &Track) {
+    println!("Track '{}' ({})", track.name, track.definition.display());
+    for base in track.bases.iter() {
+        println!("  extends {}", base.display());
+    }
+    if !track.profiles.is_empty() {
+        println!("  profiles: {}", track.profiles.join(", "));
+    }
+    if let Some(version) = &track.version {
+        println!("  course version: {version}");
+    }
+    if let Some(template) = &track.default_slide_template {
+        println!("  default slide template: {}", template.display());
+    }
+    for (field, value) in track.course.entries() {
+        println!("  {field}: {value}");
+    }
+    for module in track.modules.iter() {
+        println!("  {} {}", module.index, module.data.name);
+        for unit in module.data.units.iter() {
+            println!("    {}.{} {}", module.index, unit.index, unit.data.name);
+            for topic in unit.data.topics.iter() {
+                println!("      - {}", topic.data.name);
+            }
+        }
+    }
+}
+
+fn print_templates(track: &Track) {
+    println!("Slide templates of track '{}':", track.name);
+    for module in track.modules.iter() {
+        for unit in module.data.units.iter() {
+            let template = match track.slide_template(&unit.data) {
+                _ if !unit.data.render_slides => "no deck".to_string(),
+                Some(path) => path.display().to_string(),
+                None => "built-in default".to_string(),
+            };
+            println!(
+                "  {}.{} {}: {template}",
+                module.index, unit.index, unit.data.name
+            );
+        }
+    }
+}
+
+fn print_derived_summaries(track: &Track) {
+    // Topics can be included in more than one unit
+    let mut topics: Vec<&Topic> = vec![];
+    for topic in track.topics().filter(|t| t.summary_derived) {
+        if !topics
+            .iter()
+            .any(|t| t.definition == topic.definition && t.name == topic.name)
+        {
+            topics.push(topic);
+        }
+    }
+    if topics.is_empty() {
+        println!("No derived summaries in track '{}'", track.name);
+        return;
+    }
+    println!("Derived summaries in track '{}':", track.name);
+    for topic in topics {
+        println!("  {} ({})", topic.name, topic.definition.display());
+        for item in topic.summary.iter() {
+            println!("    - {item}");
+        }
+    }
+}
+
+fn print_orphans(orphans: &Orphans) {
+    if orphans.is_empty() {
+        println!("No orphaned content found");
+        return;
+    }
+    let Orphans {
+        topics,
+        exercises,
+        images,
+        templates,
+    } = orphans;
+    for (kind, paths) in [
+        ("topics", topics),
+        ("exercises", exercises),
+        ("images", images),
+        ("templates", templates),
+    ] {
+        if paths.is_empty() {
+            continue;
+        }
+        println!("Orphaned {kind}:");
+        for path in paths {
+            println!("  {}", path.display());
+        }
+    }
+}
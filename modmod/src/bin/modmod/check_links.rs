@@ -0,0 +1,132 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    context, external_links::ExternalLinkOptions, link_check::LinkCheckOptions, log, slug::slug,
+    FailurePolicy, RenderOptions, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(required = true)]
+    track_toml_paths: Vec<PathBuf>,
+    #[arg(
+        long = "online",
+        help = "Request the external links as well, to see whether they can still be reached"
+    )]
+    online: bool,
+    #[arg(
+        long = "concurrency",
+        value_name = "N",
+        help = "The number of external links that are requested at the same time",
+        default_value_t = 8,
+        requires = "online"
+    )]
+    concurrency: usize,
+    #[arg(
+        long = "timeout",
+        value_name = "SECS",
+        help = "How many seconds a request of an external link may take",
+        default_value_t = 10,
+        requires = "online"
+    )]
+    timeout_secs: u64,
+    #[arg(
+        long = "link-cache-max-age",
+        value_name = "HOURS",
+        help = "Request external links again that could be reached longer ago than this many hours, 0 to request all of them",
+        default_value_t = 24,
+        requires = "online"
+    )]
+    link_cache_max_age_hours: u64,
+    #[arg(
+        long = "offline",
+        help = "Fail on exercises from git repositories that aren't cached yet, instead of fetching them"
+    )]
+    offline: bool,
+}
+
+/// Checks the links of the tracks, and returns whether any of them are broken
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<bool, ModModError> {
+    let Args {
+        track_toml_paths,
+        online,
+        concurrency,
+        timeout_secs,
+        link_cache_max_age_hours,
+        offline,
+    } = args;
+    // The tracks are rendered to check the links of the output, which is kept in memory.
+    // With --dry-run writes are already recorded instead, and only the content is checked.
+    let in_memory = context::current().keep_writes_in_memory();
+    if in_memory.is_none() {
+        tracing::warn!(
+            "The output isn't rendered on dry runs, so only the links in the content are checked"
+        );
+    }
+    let (_context, memory) = in_memory
+        .map(|(context, memory)| (context.enter(), memory))
+        .unzip();
+
+    let mut loader = crate::loader(FailurePolicy::Strict, content_dir);
+    if offline {
+        loader = loader.offline();
+    }
+    let tracks = Track::load_toml_defs_with(&track_toml_paths, loader)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .change_context(ModModError::default())?;
+
+    let link_opts = ExternalLinkOptions {
+        cache_max_age: Duration::from_secs(link_cache_max_age_hours * 60 * 60),
+        concurrency,
+        timeout: Duration::from_secs(timeout_secs),
+        ..Default::default()
+    };
+    let mut broken = false;
+    for track in tracks.iter() {
+        // Shown in the links that are reported in the output, which isn't written anywhere
+        let out_dir = match tracks.len() {
+            1 => PathBuf::from("<output>"),
+            _ => PathBuf::from("<output>").join(slug(&track.name)),
+        };
+        let files = match &memory {
+            Some(memory) => {
+                let mut opts = RenderOptions::new(&out_dir);
+                // Checking links isn't about the version
+                opts.free_form_version = true;
+                track
+                    .render(&opts, &mut ())
+                    .attach_printable_lazy(|| format!("Rendering track '{}'", track.name))
+                    .change_context(ModModError::default())?;
+                Some(memory.files())
+            }
+            None => None,
+        };
+        let opts = LinkCheckOptions {
+            output: files.as_ref().map(|files| (out_dir.as_path(), files)),
+            online: online.then(|| link_opts.clone()),
+        };
+        let report = track
+            .check_links(&opts)
+            .attach_printable_lazy(|| format!("Checking track '{}'", track.name))
+            .change_context(ModModError::default())?;
+        println!("Links of track '{}'", track.name);
+        print!("{report}");
+        for warning in report.external.iter().flat_map(|e| e.warnings.iter()) {
+            println!("warning: {warning}");
+            log::record_warning(warning);
+        }
+        for link in &report.broken {
+            log::record_warning(&link.warning());
+        }
+        broken |= !report.is_empty();
+    }
+    Ok(broken)
+}
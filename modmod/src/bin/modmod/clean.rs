@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder with the generated output to remove"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "force",
+        help = "Also remove generated files that were changed since they were generated"
+    )]
+    force: bool,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args { out_dir, force } = args;
+    let report = modmod::update::clean(&out_dir, force).change_context(ModModError::default())?;
+    println!("{report}");
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::BTreeSet,
+    ffi::{OsStr, OsString},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use clap_complete::{
+    engine::{ArgValueCandidates, ArgValueCompleter, CompletionCandidate},
+    env::Shells,
+};
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{FailurePolicy, RenderTarget, Track};
+
+use crate::ModModError;
+
+/// The environment variable the completion scripts set to ask modmod for the candidates
+/// of a command line, see [`clap_complete::CompleteEnv`]
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        help = "The shell to write the completion script for: bash, zsh, fish, elvish or powershell"
+    )]
+    shell: String,
+}
+
+/// Writes the script that registers the completions of modmod with the shell. The
+/// commands and options are completed by clap from the definition of the command line,
+/// and the values of the options that have a completer below by these.
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let shells = Shells::builtins();
+    let Some(shell) = shells.completer(&args.shell) else {
+        let names: Vec<_> = shells.names().collect();
+        return Err(ModModError::usage(format!(
+            "Unknown shell '{}', expected {}",
+            args.shell,
+            names.join(", ")
+        )));
+    };
+    let mut script = vec![];
+    shell
+        .write_registration(COMPLETE_VAR, "modmod", "modmod", "modmod", &mut script)
+        .and_then(|()| std::io::stdout().write_all(&script))
+        .into_report()
+        .change_context(ModModError::default())
+}
+
+/// Completes the labels of the modules and units, and the ids and tags of the units, of the
+/// tracks on the command line, as `--units` takes them
+pub fn units() -> ArgValueCompleter {
+    ArgValueCompleter::new(|current: &OsStr| {
+        from_tracks(current, |track| {
+            let labels = track.modules.iter().flat_map(|module| {
+                let label = &module.data.label.path;
+                let units = module
+                    .data
+                    .units
+                    .iter()
+                    .map(|u| format!("{label}.{}", u.index));
+                [label.clone()].into_iter().chain(units).collect::<Vec<_>>()
+            });
+            let units = track.units().flat_map(|unit| {
+                let tags = unit.tags.iter().map(|tag| format!("tag:{tag}"));
+                [unit.id()].into_iter().chain(tags).collect::<Vec<_>>()
+            });
+            labels.chain(units).collect()
+        })
+    })
+}
+
+/// Completes the profiles of the tracks on the command line
+pub fn profiles() -> ArgValueCompleter {
+    ArgValueCompleter::new(|current: &OsStr| from_tracks(current, |track| track.profiles.clone()))
+}
+
+/// The parts of the output `--only` and `--skip` take
+pub fn render_targets() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        RenderTarget::ALL
+            .iter()
+            .map(|target| CompletionCandidate::new(target.to_string()))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// The formats of `--log-format` and `--error-format`
+pub fn log_formats() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        ["human", "json"]
+            .map(CompletionCandidate::new)
+            .into_iter()
+            .collect::<Vec<_>>()
+    })
+}
+
+/// The values that `values` gives for the tracks on the command line being completed,
+/// that start with `current`. The tracks are loaded without their content, leniently, as
+/// they are often being worked on.
+fn from_tracks(current: &OsStr, values: fn(&Track) -> Vec<String>) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+    // The completion engine doesn't pass the other words, but the scripts do, after `--`
+    let words: Vec<OsString> = std::env::args_os().skip_while(|arg| arg != "--").collect();
+    let track_paths: Vec<_> = words
+        .iter()
+        .filter(|word| word.to_string_lossy().ends_with(".track.toml"))
+        .map(PathBuf::from)
+        .collect();
+    let content_dir = content_dir(&words);
+    let loader = crate::loader(FailurePolicy::Lenient, content_dir.as_deref()).without_content();
+    let values: BTreeSet<_> = Track::load_toml_defs_with(&track_paths, loader)
+        .into_iter()
+        .flatten()
+        .flat_map(|track| values(&track))
+        .filter(|value| value.starts_with(current))
+        .collect();
+    values.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// The `--content-dir` on the command line being completed
+fn content_dir(words: &[OsString]) -> Option<PathBuf> {
+    words.iter().enumerate().find_map(|(i, word)| {
+        let word = word.to_str()?;
+        match word.strip_prefix("--content-dir") {
+            Some("") => words.get(i + 1).map(|dir| Path::new(dir).to_path_buf()),
+            Some(value) => value.strip_prefix('=').map(PathBuf::from),
+            None => None,
+        }
+    })
+}
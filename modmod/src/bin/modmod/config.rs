@@ -0,0 +1,226 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use clap::{Command, Parser, Subcommand};
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    io::PathExt,
+    report::{Warning, WarningCode},
+};
+
+use crate::ModModError;
+
+/// Name of the file with defaults for the command line options. It's looked up in the
+/// folders of the given track files and the working directory, and their parents.
+pub const CONFIG_FILE: &str = ".modmod.toml";
+
+/// A command line option that can get its default from the configuration
+struct Setting {
+    /// Key in the configuration file
+    key: &'static str,
+    /// Environment variable, which overrides the configuration file
+    env: &'static str,
+    /// Id of the argument, which is the same for all subcommands that have it
+    arg: &'static str,
+    /// Whether the value is a path, which in the configuration file is relative to the file
+    is_path: bool,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "output",
+        env: "MODMOD_OUTPUT",
+        arg: "out_dir",
+        is_path: true,
+    },
+    Setting {
+        key: "theme",
+        env: "MODMOD_THEME",
+        arg: "slide_theme",
+        is_path: false,
+    },
+    Setting {
+        key: "url_base",
+        env: "MODMOD_URL_BASE",
+        arg: "base_url",
+        is_path: false,
+    },
+    Setting {
+        key: "profile",
+        env: "MODMOD_PROFILE",
+        arg: "profile",
+        is_path: false,
+    },
+    Setting {
+        key: "locale",
+        env: "MODMOD_LOCALE",
+        arg: "locale",
+        is_path: false,
+    },
+];
+
+enum Source {
+    File,
+    Env(&'static str),
+}
+
+/// The defaults from the configuration file and the environment, which options given on
+/// the command line override
+pub struct Config {
+    file: Option<PathBuf>,
+    values: Vec<(&'static Setting, String, Source)>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Config {
+    /// Looks up the configuration file, starting from the folders of the track files among
+    /// `args`, and reads the environment
+    pub fn discover(args: &[OsString]) -> Result<Self, ModModError> {
+        let track_dirs = args
+            .iter()
+            .map(Path::new)
+            .filter(|path| path.extension() == Some("toml".as_ref()) && path.is_file())
+            .filter_map(|path| path.canonicalize().ok()?.parent().map(Path::to_path_buf));
+        let start_dirs: Vec<_> = track_dirs.chain(std::env::current_dir().ok()).collect();
+        let file = start_dirs.iter().find_map(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(CONFIG_FILE))
+                .find(|file| file.is_file())
+        });
+
+        let mut config = Config {
+            file: None,
+            values: vec![],
+            warnings: vec![],
+        };
+        if let Some(file) = file {
+            config.read_file(&file)?;
+            config.file = Some(file);
+        }
+        for setting in SETTINGS {
+            let Some(value) = std::env::var_os(setting.env).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let value = value.to_string_lossy().into_owned();
+            config.values.retain(|(s, _, _)| s.key != setting.key);
+            config
+                .values
+                .push((setting, value, Source::Env(setting.env)));
+        }
+        Ok(config)
+    }
+
+    fn read_file(&mut self, file: &Path) -> Result<(), ModModError> {
+        let table: toml::Table = toml::from_str(&file.read_to_string::<ModModError>()?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to parse {}", file.display()))
+            .change_context(ModModError::default())?;
+        for (key, value) in table {
+            let Some(setting) = SETTINGS.iter().find(|s| s.key == key) else {
+                // Configuration written for newer versions of modmod should still work
+                self.warnings.push(Warning::new(
+                    WarningCode::UnknownConfigKey,
+                    format!("Unknown key `{key}` in {CONFIG_FILE} is ignored"),
+                    Some(file),
+                ));
+                continue;
+            };
+            let toml::Value::String(value) = value else {
+                return Err(ModModError::usage(format!(
+                    "`{key}` in {} must be a string",
+                    file.display()
+                )));
+            };
+            let value = match setting.is_path {
+                true => file.parent().unwrap().join(value).display().to_string(),
+                false => value,
+            };
+            self.values.push((setting, value, Source::File));
+        }
+        Ok(())
+    }
+
+    /// Uses the configured values as the defaults of the options of the command and its
+    /// subcommands
+    pub fn apply(&self, mut command: Command) -> Command {
+        for (setting, value, _) in self.values.iter() {
+            // clap wants the defaults to outlive the command, which they do anyway
+            let value: &'static str = Box::leak(value.clone().into_boxed_str());
+            command = with_default(command, setting.arg, value);
+        }
+        command
+    }
+}
+
+fn with_default(mut command: Command, arg: &str, value: &'static str) -> Command {
+    if command.get_arguments().any(|a| a.get_id() == arg) {
+        command = command.mut_arg(arg, |a| a.default_value(value).required(false));
+    }
+    let subcommands: Vec<_> = command
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, |c| with_default(c, arg, value));
+    }
+    command
+}
+
+/// The built-in default of an argument, from the first subcommand that has one
+fn builtin_default(command: &Command, arg: &str) -> Option<String> {
+    let own = command
+        .get_arguments()
+        .find(|a| a.get_id() == arg)
+        .and_then(|a| a.get_default_values().first())
+        .map(|v| v.to_string_lossy().into_owned());
+    own.or_else(|| {
+        command
+            .get_subcommands()
+            .find_map(|c| builtin_default(c, arg))
+    })
+}
+
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the defaults that are in effect, and where each comes from
+    Show,
+}
+
+pub fn run(args: Args, config: &Config, command: &Command) -> Result<(), ModModError> {
+    let ConfigCommand::Show = args.command;
+    match &config.file {
+        Some(file) => println!("# Configuration file: {}", file.display()),
+        None => println!("# No {CONFIG_FILE} found"),
+    }
+    for setting in SETTINGS {
+        let configured = config.values.iter().find(|(s, _, _)| s.key == setting.key);
+        let (value, source) = match configured {
+            Some((_, value, Source::File)) => {
+                let file = config.file.as_ref().unwrap();
+                (value.clone(), file.display().to_string())
+            }
+            Some((_, value, Source::Env(var))) => (value.clone(), format!("${var}")),
+            None => match builtin_default(command, setting.arg) {
+                Some(value) => (value, "default".to_string()),
+                None => {
+                    println!("# {} is not set", setting.key);
+                    continue;
+                }
+            },
+        };
+        println!(
+            "{} = {}  # {source}",
+            setting.key,
+            toml::Value::String(value)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{container, manifest::RenderManifest, update::is_output_dir};
+
+use crate::{gen, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder to write the build context into: the rendered track, with a Dockerfile that builds and serves it"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "build",
+        help = "Build the image with docker build, and print its tag"
+    )]
+    build: bool,
+    #[arg(
+        long = "tag",
+        help = "Tag the image with this, instead of <track>:<course version>, or <track>:latest for a track without a version"
+    )]
+    tag: Option<String>,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        out_dir,
+        build,
+        tag,
+        mut render,
+    } = args;
+    // The context is rendered again with each run, which clears the folder
+    if !is_output_dir(&out_dir).change_context(ModModError::default())? {
+        return Err(ModModError::report().attach_printable(format!(
+            "{} isn't empty, and holds no earlier output of modmod, so it may not be a build context. Writing the context into it would remove its files",
+            out_dir.display()
+        )));
+    }
+    render.container = true;
+    gen::render_tracks(render, &out_dir, true, false, content_dir)?;
+
+    // On a dry run, these are the manifests of the last render, if there was one
+    let contexts = RenderManifest::read_all(&out_dir).change_context(ModModError::default())?;
+    if tag.is_some() && contexts.len() > 1 {
+        return Err(ModModError::usage(
+            "--tag can only be given for a single track, as each track gets an image of its own",
+        ));
+    }
+    for (dir, manifest) in contexts {
+        let context = out_dir.join(dir);
+        if !build {
+            println!(
+                "Wrote the build context of track '{}' into {}",
+                manifest.track,
+                context.display()
+            );
+            continue;
+        }
+        let tag = tag.clone().unwrap_or_else(|| {
+            container::image_tag(&manifest.track, manifest.course_version.as_deref())
+        });
+        container::build_image(&context, &tag).change_context(ModModError::default())?;
+        if !modmod::io::writes_discarded() {
+            println!("Built image {tag} of track '{}'", manifest.track);
+        }
+    }
+    Ok(())
+}
@@ -1,92 +1,115 @@
-use std::ffi::OsStr;
+use std::{ffi::OsStr, path::Path};
 
 use error_stack::IntoReport;
-use modmod::load::ExerciseDef;
+use modmod::load::{PathTo, TopicDef};
 
 use super::imports::*;
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateExercise {
-    topic: PathBuf,
+    pub(super) topic: PathBuf,
     #[arg(
         short = 'i',
         long = "index",
         help = "The index of the exercise in the list of exercises for the given topic. Defaults to the last one"
     )]
-    index: Option<usize>,
-    name: String,
+    pub(super) index: Option<usize>,
+    pub(super) name: String,
 }
 
 impl CreateExercise {
     pub fn create(self, common_args: &CommonArgs) -> Result<(), ModModError> {
-        let mut topic = modmod::load::TopicDef::load(&self.topic, None)
-            .change_context(ModModError::default())?
-            .data;
+        let PathTo {
+            data: topic,
+            path: topic_path,
+        } = TopicDef::load(&self.topic, None).change_context(ModModError::default())?;
+        let topic_dir = topic_path.parent().unwrap();
 
         let index = self
             .index
             .unwrap_or(topic.exercises.len())
             .min(topic.exercises.len());
 
-        let exercises_path = self.topic.parent().unwrap().join("exercises");
+        let exercises_path = topic_dir.join("exercises");
         exercises_path.create_dir_all()?;
 
-        let exercise_crate_path = exercises_path
-            .canonicalize()
-            .unwrap()
-            .join(self.name.to_lowercase());
-        if common_args.force {
-            fs_extra::dir::remove(&exercise_crate_path)
-                .into_report()
-                .change_context(ModModError::default())?;
+        let exercise_crate_path = exercises_path.join(self.name.to_lowercase());
+        if common_args.force && exercise_crate_path.exists() {
+            exercise_crate_path.remove_dir_all()?;
         }
 
-        let output = std::process::Command::new("cargo")
-            .args([
-                OsStr::new("new"),
-                OsStr::new("--name"),
-                OsStr::new(&self.name),
-                OsStr::new("--bin"),
-                exercise_crate_path.as_os_str(),
-            ])
-            .output()
-            .into_report()
-            .change_context(ModModError::default())?;
+        // `cargo new` can't be told to leave the file system alone
+        if modmod::io::writes_discarded_at(&exercise_crate_path) {
+            exercise_crate_path.create_dir_all()?;
+        } else {
+            cargo_new(&self.name, &exercise_crate_path)?;
+        }
 
-        if !output.status.success() {
-            let e = ModModError::report()
-                .attach_printable("`cargo new` command process exited unsuccessfully")
-                .attach_printable(format!(
-                    r#"Stdout: "{}""#,
-                    String::from_utf8_lossy(&output.stdout)
-                ))
-                .attach_printable(format!(
-                    r#"Stderr: "{}""#,
-                    String::from_utf8_lossy(&output.stderr)
-                ))
-                .attach_printable(output.status);
+        exercise_crate_path.join("description.md").try_write_file(
+            common_args.force,
+            format!("The instructions of the {} exercise go here.\n", self.name),
+        )?;
 
-            return Err(e);
+        let path = exercise_crate_path
+            .strip_prefix(topic_dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut doc = read_definition(&topic_path)?;
+        let exercises = array_of_tables(&mut doc, "exercises")?;
+        let mut exercise = Table::new();
+        exercise["name"] = value(self.name);
+        exercise["path"] = value(&path);
+        // Arrays of tables can only be appended to, so they are rebuilt. With --force,
+        // the exercise replaces the one at the same path.
+        let mut tables: Vec<Table> = exercises
+            .iter()
+            .filter(|e| e.get("path").and_then(Item::as_str) != Some(&path))
+            .cloned()
+            .collect();
+        tables.insert(index.min(tables.len()), exercise);
+        exercises.clear();
+        for table in tables {
+            exercises.push(table);
         }
 
-        topic.exercises.insert(
-            index,
-            ExerciseDef {
-                name: self.name,
-                path: exercise_crate_path
-                    .strip_prefix(&self.topic.parent().unwrap().canonicalize().unwrap())
-                    .unwrap()
-                    .to_owned(),
-                ..Default::default()
-            },
-        );
+        write_definition(&topic_path, &doc)?;
+
+        Ok(())
+    }
+}
 
-        topic.exercises.dedup_by(|lhs, rhs| lhs.path == rhs.path);
+/// Creates the crate of an exercise at `path`
+fn cargo_new(name: &str, path: &Path) -> Result<(), ModModError> {
+    let output = std::process::Command::new("cargo")
+        .args([
+            OsStr::new("new"),
+            OsStr::new("--name"),
+            OsStr::new(name),
+            OsStr::new("--bin"),
+            // The exercise is part of the repository of the content
+            OsStr::new("--vcs"),
+            OsStr::new("none"),
+            path.as_os_str(),
+        ])
+        .output()
+        .into_report()
+        .change_context(ModModError::default())?;
 
-        self.topic
-            .create_file()?
-            .write_all(toml::to_string_pretty(&topic).unwrap())?;
+    if !output.status.success() {
+        let e = ModModError::report()
+            .attach_printable("`cargo new` command process exited unsuccessfully")
+            .attach_printable(format!(
+                r#"Stdout: "{}""#,
+                String::from_utf8_lossy(&output.stdout)
+            ))
+            .attach_printable(format!(
+                r#"Stderr: "{}""#,
+                String::from_utf8_lossy(&output.stderr)
+            ))
+            .attach_printable(output.status);
 
-        Ok(())
+        return Err(e);
     }
+    Ok(())
 }
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use clap::Parser;
+
+use super::{
+    exercise::CreateExercise, imports::*, module::CreateModule, topic::CreateTopic,
+    unit::CreateUnit,
+};
+
+#[derive(Debug, Clone, Parser)]
+pub struct InitArgs {
+    #[arg(help = "The folder to create the track in")]
+    dir: PathBuf,
+    #[arg(
+        long = "name",
+        help = "The name of the track",
+        default_value = "My course"
+    )]
+    name: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+/// Creates a minimal track, with one module, unit, topic and exercise, that passes
+/// `modmod check` and can be built upon with `modmod create`
+pub fn init(args: InitArgs) -> Result<(), ModModError> {
+    let InitArgs { dir, name, common } = args;
+    // Creating the unit, topic and exercise reads the definitions that were created before
+    // them, so the track is created in a scratch folder first, which a dry run writes too,
+    // and then written into `dir`
+    let scratch = modmod::io::scratch_dir::<ModModError>("modmod_init_tmp")?;
+    create_track(&scratch, &name, &common)?;
+    dir.create_dir_all()?;
+    let mut files = scratch.get_dir_content::<ModModError>()?.files;
+    files.sort();
+    for file in files {
+        let file = Path::new(&file);
+        let to = dir.join(file.strip_prefix(&*scratch).unwrap());
+        to.parent().unwrap().create_dir_all()?;
+        to.try_write_file(common.force, file.read_to_string::<ModModError>()?)?;
+    }
+    Ok(())
+}
+
+fn create_track(dir: &Path, name: &str, common: &CommonArgs) -> Result<(), ModModError> {
+    dir.join("course.track.toml").try_write_file(
+        common.force,
+        format!(
+            r#"name = "{}"
+modules = ["mods/A-introduction/mod.toml"]
+
+# Change this to the license of your material
+[license]
+spdx = "CC-BY-SA-4.0"
+attribution = "The authors of this course"
+text = "LICENSE"
+"#,
+            name.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+    )?;
+    // Copied into the output, which tells students how they may use the material
+    dir.join("LICENSE").try_write_file(
+        common.force,
+        "This course is licensed under the Creative Commons Attribution-ShareAlike 4.0
+International License (CC BY-SA 4.0).
+
+You are free to share and adapt it for any purpose, as long as you give appropriate
+credit, indicate if changes were made, and distribute your contributions under the same
+license. The full text of the license is at
+https://creativecommons.org/licenses/by-sa/4.0/legalcode
+",
+    )?;
+
+    let module_dir = dir.join("mods").join("A-introduction");
+    let module_toml = module_dir.join("mod.toml");
+    CreateModule {
+        path: module_dir.clone(),
+        name: "Introduction".to_string(),
+        description: "Getting to know the course".to_string(),
+    }
+    .create(common)?;
+    CreateUnit {
+        module: module_toml.clone(),
+        name: "Getting started".to_string(),
+        index: None,
+    }
+    .create(common)?;
+    CreateTopic {
+        module: module_toml,
+        unit_index: None,
+        dir: "hello".into(),
+        name: "Hello".to_string(),
+        description: None,
+    }
+    .create(common)?;
+    CreateExercise {
+        topic: module_dir.join("topics").join("hello").join("topic.toml"),
+        index: None,
+        name: "hello".to_string(),
+    }
+    .create(common)
+}
@@ -1,9 +1,17 @@
+use std::path::Path;
+
 use clap::{Parser, Subcommand};
-use error_stack::Result;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    format::{format_definition, DefinitionKind},
+    io::PathExt,
+};
+use toml_edit::{ArrayOfTables, Document, Item};
 
 use crate::ModModError;
 
 mod exercise;
+mod init;
 mod module;
 mod topic;
 mod unit;
@@ -41,17 +49,57 @@ impl What {
     }
 }
 
+pub use init::{init, InitArgs};
+
 pub fn run(args: Args) -> Result<(), ModModError> {
     args.what.create(&args.common)
 }
 
+/// Reads a definition file to edit it without losing its comments
+fn read_definition(path: &Path) -> Result<Document, ModModError> {
+    path.read_to_string()?
+        .parse()
+        .into_report()
+        .attach_printable_lazy(|| format!("Invalid TOML in {}", path.display()))
+        .change_context(ModModError::default())
+}
+
+/// The list of tables at `key`, which is added if it's not there yet
+fn array_of_tables<'d>(
+    doc: &'d mut Document,
+    key: &str,
+) -> Result<&'d mut ArrayOfTables, ModModError> {
+    // An empty list is written as `key = []` when the definition is serialized
+    if doc
+        .get(key)
+        .and_then(Item::as_array)
+        .is_some_and(|array| array.is_empty())
+    {
+        doc.remove(key);
+    }
+    doc.entry(key)
+        .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| {
+            ModModError::report().attach_printable(format!("`{key}` is not a list of tables"))
+        })
+}
+
+/// Writes an edited definition file, in the form `modmod fmt` gives it
+fn write_definition(path: &Path, doc: &Document) -> Result<(), ModModError> {
+    let kind = DefinitionKind::from_path(path).unwrap_or(DefinitionKind::Module);
+    let content =
+        format_definition(&doc.to_string(), kind).change_context(ModModError::default())?;
+    path.write_file(content)
+}
+
 mod imports {
     pub(crate) use crate::create::CommonArgs;
     pub(crate) use crate::ModModError;
     pub use error_stack::{Result, ResultExt};
-    pub use modmod::{
-        io::{PathExt, WriteExt},
-        load::Load,
-    };
+    pub use modmod::{io::PathExt, load::Load};
     pub use std::path::PathBuf;
+    pub use toml_edit::{value, Array, Item, Table};
+
+    pub(super) use super::{array_of_tables, read_definition, write_definition};
 }
@@ -4,9 +4,9 @@ use super::imports::*;
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateModule {
-    path: PathBuf,
-    name: String,
-    description: String,
+    pub(super) path: PathBuf,
+    pub(super) name: String,
+    pub(super) description: String,
 }
 
 impl CreateModule {
@@ -14,15 +14,17 @@ impl CreateModule {
         let mod_toml_path = self.path.join("mod.toml");
 
         self.path.create_dir_all()?;
-        let mut mod_toml = mod_toml_path.try_create_file(common_args.force)?;
 
         let module = ModuleDef {
             name: self.name,
             description: self.description,
+            index: None,
+            label: None,
             units: vec![],
         };
 
-        mod_toml.write_all(toml::to_string_pretty(&module).unwrap().as_bytes())?;
+        mod_toml_path
+            .try_write_file(common_args.force, toml::to_string_pretty(&module).unwrap())?;
 
         Ok(())
     }
@@ -1,63 +1,89 @@
-use modmod::load::{ModuleDef, TopicDef};
+use modmod::{
+    date::Date,
+    i18n::LocalizedText,
+    load::{ModuleDef, PathTo, TopicDef},
+};
 
 use super::imports::*;
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateTopic {
-    module: PathBuf,
+    pub(super) module: PathBuf,
     #[arg(
         short = 'i',
         long = "index",
         help = "The unit to which to add the topic to. Defaults to the last unit"
     )]
-    unit_index: Option<usize>,
-    dir: PathBuf,
-    name: String,
-    description: Option<String>,
+    pub(super) unit_index: Option<usize>,
+    pub(super) dir: PathBuf,
+    pub(super) name: String,
+    pub(super) description: Option<String>,
 }
 
 impl CreateTopic {
     pub fn create(self, common_args: &CommonArgs) -> Result<(), ModModError> {
-        let mut module = ModuleDef::load(&self.module, None)
-            .change_context(ModModError::default())?
-            .data;
+        let PathTo {
+            data: module,
+            path: module_path,
+        } = ModuleDef::load(&self.module, None).change_context(ModModError::default())?;
 
         if module.units.is_empty() {
-            return Err(ModModError::report().attach_printable(
+            return Err(ModModError::usage(
                 "There are no units to attach the topic to. Create units first.",
             ));
         }
 
         let max_unit_index = module.units.len() - 1;
         let unit_index = self.unit_index.unwrap_or(max_unit_index);
-        let Some(unit) = module.units.get_mut(unit_index) else {
-            return Err(ModModError::report().attach_printable(format!(
+        if unit_index > max_unit_index {
+            return Err(ModModError::usage(format!(
                 "No unit at that index yet. Pick a number between 0 and {max_unit_index} or create more units first"
             )));
-        };
+        }
 
-        let topic_toml_path = self.module.parent().unwrap().join("topics").join(&self.dir);
+        // Topic paths in the module definition are relative to the module definition
+        let topic_dir = PathBuf::from("topics").join(&self.dir);
+        let topic_toml_path = module_path.parent().unwrap().join(&topic_dir);
         topic_toml_path.create_dir_all()?;
-        let mut topic_toml = topic_toml_path
-            .join("topic.toml")
-            .try_create_file(common_args.force)?;
 
         let topic = TopicDef {
-            name: self.name,
+            name: self.name.clone(),
+            summary: self
+                .description
+                .into_iter()
+                .map(LocalizedText::from)
+                .collect(),
+            // It was written just now
+            last_reviewed: Some(Date::today()),
             ..Default::default()
         };
-        unit.topics.push(self.dir.join("mod.toml"));
-        unit.topics.dedup();
-
-        topic_toml.write_all(toml::to_string_pretty(&topic).unwrap().as_bytes())?;
         topic_toml_path
-            .join("slides.md")
-            .try_create_file(common_args.force)?;
-
-        self.module
-            .create_file()?
-            .write_all(toml::to_string_pretty(&module).unwrap())?;
+            .join("topic.toml")
+            .try_write_file(common_args.force, toml::to_string_pretty(&topic).unwrap())?;
+        topic_toml_path.join("slides.md").try_write_file(
+            common_args.force,
+            format!("# {}\n\nThe slides of this topic go here.\n", self.name),
+        )?;
 
-        Ok(())
+        let mut doc = read_definition(&module_path)?;
+        let unit = doc["units"]
+            .as_array_of_tables_mut()
+            .and_then(|units| units.get_mut(unit_index))
+            .ok_or_else(|| {
+                ModModError::report().attach_printable("`units` is not a list of tables")
+            })?;
+        let topics = unit
+            .entry("topics")
+            .or_insert(value(Array::new()))
+            .as_array_mut()
+            .ok_or_else(|| ModModError::report().attach_printable("`topics` is not a list"))?;
+        let topic_ref = topic_dir
+            .join("topic.toml")
+            .to_string_lossy()
+            .replace('\\', "/");
+        if !topics.iter().any(|t| t.as_str() == Some(&topic_ref)) {
+            topics.push(topic_ref);
+        }
+        write_definition(&module_path, &doc)
     }
 }
@@ -1,38 +1,34 @@
-use modmod::load::{ModuleDef, UnitDef};
+use modmod::load::{ModuleDef, PathTo};
 
 use super::imports::*;
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateUnit {
-    module: PathBuf,
-    name: String,
+    pub(super) module: PathBuf,
+    pub(super) name: String,
     #[arg(short, long)]
-    index: Option<usize>,
+    pub(super) index: Option<usize>,
 }
 
 impl CreateUnit {
     pub fn create(self, _common_args: &CommonArgs) -> Result<(), ModModError> {
-        let mut module = ModuleDef::load(&self.module, None)
-            .change_context(ModModError::default())?
-            .data;
+        let PathTo {
+            path: module_path, ..
+        } = ModuleDef::load(&self.module, None).change_context(ModModError::default())?;
+        let mut doc = read_definition(&module_path)?;
 
-        let index = self
-            .index
-            .unwrap_or(module.units.len())
-            .min(module.units.len());
+        let units = array_of_tables(&mut doc, "units")?;
+        let mut unit = Table::new();
+        unit["name"] = value(self.name);
+        // Arrays of tables can only be appended to, so they are rebuilt
+        let mut tables: Vec<Table> = units.iter().cloned().collect();
+        let index = self.index.unwrap_or(tables.len()).min(tables.len());
+        tables.insert(index, unit);
+        units.clear();
+        for table in tables {
+            units.push(table);
+        }
 
-        module.units.insert(
-            index,
-            UnitDef {
-                name: self.name,
-                template: None,
-                topics: vec![],
-            },
-        );
-
-        self.module
-            .create_file()?
-            .write_all(toml::to_string_pretty(&module).unwrap().as_bytes())?;
-        Ok(())
+        write_definition(&module_path, &doc)
     }
 }
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+
+use crate::{gen, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder with the earlier generated output to compare with"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "full",
+        help = "Print the changes to text files as unified diffs"
+    )]
+    full: bool,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+/// Returns whether the output differs from the folder
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<bool, ModModError> {
+    let Args {
+        out_dir,
+        full,
+        render,
+    } = args;
+
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_diff_tmp")?;
+    gen::render_tracks(render, &tmp_dir, true, false, content_dir)?;
+    let diff =
+        modmod::update::diff(&tmp_dir, &out_dir, full).change_context(ModModError::default())?;
+    println!("{diff}");
+
+    Ok(!diff.is_empty())
+}
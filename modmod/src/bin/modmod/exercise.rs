@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::exercise_test::{test_exercises, ExerciseTestOptions};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ExerciseCommand,
+}
+
+#[derive(Subcommand)]
+enum ExerciseCommand {
+    /// Run the tests of each exercise package in an output folder, and summarize the results
+    Test {
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "The output folder with the exercises to test"
+        )]
+        out_dir: PathBuf,
+        #[arg(
+            long = "solutions",
+            help = "The folder holds the solutions, so the tests of all exercises are expected to pass"
+        )]
+        solutions: bool,
+        #[arg(long = "offline", help = "Run cargo without accessing the network")]
+        offline: bool,
+        #[arg(
+            long = "target-dir",
+            value_name = "DIR",
+            help = "Build all exercises into this folder, so that their dependencies are built only once"
+        )]
+        target_dir: Option<PathBuf>,
+        #[arg(long = "json", help = "Output the results as JSON")]
+        json: bool,
+    },
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    match args.command {
+        ExerciseCommand::Test {
+            out_dir,
+            solutions,
+            offline,
+            target_dir,
+            json,
+        } => {
+            let options = ExerciseTestOptions {
+                solutions,
+                offline,
+                target_dir: target_dir.as_deref(),
+            };
+            let report = test_exercises(&out_dir, options).change_context(ModModError::default())?;
+            if json {
+                let json = serde_json::to_string_pretty(&report)
+                    .into_report()
+                    .change_context(ModModError::default())?;
+                println!("{json}");
+            } else {
+                println!("{report}");
+            }
+            if !report.as_expected() {
+                return Err(ModModError::report().attach_printable(format!(
+                    "The tests of {} exercise(s) didn't pass or fail as expected",
+                    report.totals.unexpected
+                )));
+            }
+        }
+    }
+    Ok(())
+}
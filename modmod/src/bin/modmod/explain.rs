@@ -0,0 +1,43 @@
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::catalog::{self, CatalogEntry};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        help = "The code of the error or warning to explain, like E0101 or W0102, or the name of a warning, like empty-topic. Lists all codes when left out"
+    )]
+    code: Option<String>,
+    #[arg(long = "json", help = "Output the entries as JSON")]
+    json: bool,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args { code, json } = args;
+
+    let entries: Vec<CatalogEntry> = match code {
+        Some(code) => vec![catalog::explain(&code).ok_or_else(|| {
+            ModModError::usage(format!(
+                "There is no error or warning with code '{code}'. Run `modmod explain` to list them"
+            ))
+        })?],
+        None => catalog::catalog(),
+    };
+
+    if json {
+        let json = serde_json::to_string_pretty(&entries)
+            .into_report()
+            .change_context(ModModError::default())?;
+        println!("{json}");
+    } else if let [entry] = entries.as_slice() {
+        println!("{} {}: {}\n", entry.code, entry.name, entry.title);
+        println!("{}", entry.explanation);
+    } else {
+        for entry in entries {
+            println!("{} {:<28} {}", entry.code, entry.name, entry.title);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use error_stack::{Result, ResultExt};
+use modmod::{
+    classroom::{ClassroomOptions, TemplateScope, CLASSROOM_DIR, VENDOR_DIR},
+    docx::{Converter, DocxOptions, DOCX_DIR},
+    io::PathExt,
+    manifest::{RenderManifest, RENDER_MANIFEST_FILE},
+    url::BaseUrl,
+    version::CourseVersion,
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ExportCommand,
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Write a track as an IMS Common Cartridge for learning management systems, which
+    /// links to its hosted slides and book
+    Cartridge(crate::cartridge::Args),
+    /// Package the built slides and book of a rendered track for learning management systems
+    /// that import SCORM
+    Scorm(crate::scorm::Args),
+    /// Export flashcards of the topics of a track for Anki, as a file to import or an Anki
+    /// package
+    Anki(crate::anki::Args),
+    /// Convert the exercise descriptions of a rendered track to Word documents with pandoc,
+    /// one for each exercise and one for each module
+    Docx {
+        track_toml_path: PathBuf,
+        #[arg(
+            long = "exercises",
+            required = true,
+            help = "Export the exercise descriptions, as they are in the book. The only content that can be exported for now"
+        )]
+        exercises: bool,
+        #[arg(
+            long = "from",
+            value_name = "DIR",
+            help = "The output folder the track was rendered into"
+        )]
+        rendered_dir: PathBuf,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "The folder to write the documents into",
+            default_value = DOCX_DIR
+        )]
+        out_dir: PathBuf,
+        #[arg(
+            long = "pandoc",
+            value_name = "PATH",
+            help = "The pandoc to convert with, which then has to be usable. Without it, pandoc is looked up in the PATH, and the documents are written as HTML if it can't be found"
+        )]
+        pandoc: Option<PathBuf>,
+        #[arg(
+            long = "base-url",
+            value_name = "URL",
+            help = "The URL the output is deployed at, like https://example.com/training/, to point links into the book to"
+        )]
+        base_url: Option<BaseUrl>,
+    },
+    /// Write the exercises of a rendered track into template repositories for GitHub
+    /// Classroom, one for each exercise or module
+    Classroom {
+        track_toml_path: PathBuf,
+        #[arg(
+            long = "from",
+            value_name = "DIR",
+            help = "The output folder the track was rendered into"
+        )]
+        rendered_dir: PathBuf,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "The folder to write the templates into",
+            default_value = CLASSROOM_DIR
+        )]
+        out_dir: PathBuf,
+        #[arg(
+            long = "per",
+            value_name = "SCOPE",
+            help = "Whether a template holds a single exercise or all exercises of a module: exercise or module",
+            default_value_t = TemplateScope::Exercise
+        )]
+        scope: TemplateScope,
+        #[arg(
+            long = "shared-git",
+            value_name = "URL",
+            help = "The git repository the shared crates are pushed to, for the templates to depend on. Without it, the shared crates are copied into the templates"
+        )]
+        shared_git: Option<String>,
+        #[arg(
+            long = "git-init",
+            help = "Make each template a git repository with an initial commit, ready to push"
+        )]
+        git_init: bool,
+    },
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    match args.command {
+        ExportCommand::Cartridge(args) => crate::cartridge::run(args, content_dir)
+            .attach_printable("Error exporting common cartridge")?,
+        ExportCommand::Scorm(args) => crate::scorm::run(args, content_dir)
+            .attach_printable("Error exporting SCORM package")?,
+        ExportCommand::Anki(args) => {
+            crate::anki::run(args, content_dir).attach_printable("Error exporting flashcards")?
+        }
+        ExportCommand::Docx {
+            track_toml_path,
+            exercises: _,
+            rendered_dir,
+            out_dir,
+            pandoc,
+            base_url,
+        } => {
+            let manifest = read_render_manifest(&rendered_dir)?;
+            if base_url.as_ref().is_some_and(|url| url.origin().is_none()) {
+                return Err(ModModError::usage(
+                    "--base-url must be an absolute URL like https://example.com/training/, as the documents are read outside of the site",
+                ));
+            }
+            // It was checked when the track was rendered
+            let course_version = manifest
+                .course_version
+                .as_deref()
+                .and_then(|version| CourseVersion::parse(version, true).ok());
+
+            let converter =
+                Converter::detect(pandoc.as_deref()).change_context(ModModError::default())?;
+            if let Converter::Html { reason } = &converter {
+                tracing::warn!(
+                    "{reason}. Writing the documents as standalone HTML instead, install pandoc 2.0 or later to get .docx"
+                );
+            }
+
+            let loader = crate::loader(FailurePolicy::Strict, content_dir);
+            let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+                .pop()
+                .unwrap()
+                .change_context(ModModError::default())?;
+            let export = track
+                .export_docx(
+                    &rendered_dir,
+                    DocxOptions {
+                        layout: &track.output,
+                        converter: &converter,
+                        base_url: base_url.as_ref(),
+                        course_version: course_version.as_ref(),
+                    },
+                )
+                .change_context(ModModError::default())?;
+            for (file_name, warning) in export.warnings.iter() {
+                tracing::warn!(file = file_name, "{file_name}: {warning}");
+            }
+
+            out_dir.create_dir_all()?;
+            for document in export.documents.iter() {
+                out_dir
+                    .join(&document.file_name)
+                    .write_file(&document.content)?;
+            }
+            let modules = export.documents.iter().filter(|d| d.module).count();
+            match export.documents.len() - modules {
+                0 => println!("The track has no exercises to export"),
+                exercises => println!(
+                    "Wrote {exercises} exercise(s) and {modules} module document(s) into {} with {converter}",
+                    out_dir.display()
+                ),
+            }
+        }
+        ExportCommand::Classroom {
+            track_toml_path,
+            rendered_dir,
+            out_dir,
+            scope,
+            shared_git,
+            git_init,
+        } => {
+            let manifest = read_render_manifest(&rendered_dir)?;
+            // It was checked when the track was rendered
+            let course_version = manifest
+                .course_version
+                .as_deref()
+                .and_then(|version| CourseVersion::parse(version, true).ok());
+
+            let loader = crate::loader(FailurePolicy::Strict, content_dir);
+            let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+                .pop()
+                .unwrap()
+                .change_context(ModModError::default())?;
+            let export = track
+                .export_classroom(
+                    &rendered_dir,
+                    &manifest,
+                    &out_dir,
+                    ClassroomOptions {
+                        layout: &track.output,
+                        scope,
+                        shared_git: shared_git.as_deref(),
+                        git_init,
+                        course_version: course_version.as_ref(),
+                    },
+                )
+                .change_context(ModModError::default())?;
+            for (template, warning) in export.warnings.iter() {
+                tracing::warn!(file = template, "{template}: {warning}");
+            }
+
+            for template in export.templates.iter() {
+                match template.vendored.is_empty() {
+                    true => println!("{}", template.dir.display()),
+                    false => println!(
+                        "{} (with {} in {VENDOR_DIR})",
+                        template.dir.display(),
+                        template.vendored.join(", ")
+                    ),
+                }
+            }
+            match export.templates.len() {
+                0 => println!("The track has no exercises to export"),
+                templates => println!(
+                    "Wrote {templates} template(s) with {} exercise(s) into {}",
+                    export.templates.iter().map(|t| t.exercises).sum::<usize>(),
+                    out_dir.display()
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The manifest of the track rendered into `rendered_dir`, which has to be there
+fn read_render_manifest(rendered_dir: &Path) -> Result<RenderManifest, ModModError> {
+    let Some(manifest) =
+        RenderManifest::read(rendered_dir).change_context(ModModError::default())?
+    else {
+        return Err(ModModError::usage(format!(
+            "{} has no {RENDER_MANIFEST_FILE}, so it isn't the output folder of a track",
+            rendered_dir.display()
+        )));
+    };
+    Ok(manifest)
+}
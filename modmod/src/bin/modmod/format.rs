@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    format::{definition_files, format_definition, DefinitionKind},
+    io::PathExt,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        required = true,
+        help = "Definition files to format. Directories are searched for track, module and topic definitions"
+    )]
+    paths: Vec<PathBuf>,
+    #[arg(
+        long = "check",
+        help = "Don't write the files, but fail if any of them is not formatted"
+    )]
+    check: bool,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args { paths, check } = args;
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(definition_files(&path).change_context(ModModError::default())?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    let mut unformatted = 0;
+    for file in files {
+        let Some(kind) = DefinitionKind::from_path(&file) else {
+            return Err(ModModError::usage(format!(
+                "Unable to tell what kind of definition {} is. Definition files are named *.track.toml, mod.toml, topic.toml or *.workspace.toml",
+                file.display()
+            )));
+        };
+        let content = file.read_to_string()?;
+        let formatted = format_definition(&content, kind)
+            .attach_printable_lazy(|| format!("In definition file {}", file.display()))
+            .change_context(ModModError::default())?;
+        if formatted == content {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            println!("Would reformat {}", file.display());
+        } else {
+            file.write_file(formatted)?;
+            println!("Reformatted {}", file.display());
+        }
+    }
+
+    if check && unformatted > 0 {
+        return Err(ModModError::report().attach_printable(format!(
+            "{unformatted} definition file(s) are not formatted"
+        )));
+    }
+    Ok(())
+}
@@ -1,11 +1,31 @@
-use std::{fs, path::PathBuf};
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
 use error_stack::{IntoReport, Result, ResultExt};
-use modmod::{patch::GenPatchOptions, SlidesRenderOptions, TrackRenderOptions};
+use modmod::{
+    changes::ChangedContent,
+    course_catalog,
+    deploy::CiProvider,
+    load::{Load, WorkspaceDef},
+    log,
+    patch::GenPatchOptions,
+    qr::QrTarget,
+    quiz::QuizMode,
+    report::{RenderReport, Reporter, Warning, DEFAULT_MAX_ASSET_SIZE, DEFAULT_MAX_SECTION_SIZE},
+    search::SearchIndexOptions,
+    update::{FileHashes, UpdateReport, CONFLICT_SUFFIX},
+    url::BaseUrl,
+    FailurePolicy, LoadTrackError, OutputLayout, RenderOptions, RenderOverrides, RenderTarget,
+    Track,
+};
 
 use crate::ModModError;
 
+const MIB: u64 = 1024 * 1024;
+
 #[derive(Parser)]
 pub struct Args {
     #[arg(
@@ -14,82 +34,773 @@ pub struct Args {
         help = "The folder the output will be written to"
     )]
     out_dir: PathBuf,
-    #[arg(short = 'c', long = "clear", help = "Clear the output folder")]
+    #[arg(
+        short = 'c',
+        long = "clear",
+        help = "Clear the output folder. Files that are rendered with the same content again are left as they are"
+    )]
     clear_output_dir: bool,
     #[arg(
-        long,
-        help = "Use this as a base when deploying the slides to a web server",
-        default_value = "/"
+        short = 'f',
+        long = "force",
+        help = "With --clear, render into the output folder even if it isn't empty and holds no earlier output of modmod, and overwrite the files in it that were changed since it was rendered"
     )]
-    slide_url_base: String,
+    force: bool,
     #[arg(
         short = 'p',
         long = "patch",
         help = "Generate patch file to update output dir at given path"
     )]
     patch_file: Option<PathBuf>,
-    track_toml_path: PathBuf,
+    #[arg(
+        short = 'i',
+        long = "interactive",
+        help = "Pick the units to render from a tree of the modules, units and exercises of the track. Units given with --units start out selected"
+    )]
+    interactive: bool,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+// The options that select what is rendered, and how. Shared with the `update`, `diff` and
+// `watch` subcommands.
+#[derive(clap::Args, Clone)]
+pub struct RenderArgs {
+    #[arg(
+        long = "base-url",
+        alias = "slide-url-base",
+        value_name = "URL",
+        help = "The URL the output is deployed at, like /training/rust-2025/ or https://example.com/training/. Used in the slides build scripts, the links from the book to the slides and the book's site-url",
+        default_value = "/"
+    )]
+    base_url: BaseUrl,
+    #[arg(
+        required_unless_present = "workspace",
+        help = "The track definitions to render, as files, - to read one from stdin, or https URLs. When more than one track is given, each track is rendered into its own subfolder of the output folder"
+    )]
+    track_toml_paths: Vec<PathBuf>,
+    #[arg(
+        short = 'w',
+        long = "workspace",
+        help = "Render all tracks listed in the given workspace definition"
+    )]
+    workspace: Option<PathBuf>,
+    #[arg(
+        long = "fail-fast",
+        help = "Stop rendering at the first deck, book page, exercise or track that fails, instead of continuing with the others and reporting all errors at the end"
+    )]
+    fail_fast: bool,
     #[arg(
         long = "theme",
-        help = "The name of the Slidev theme to use in generated slide decks",
+        help = "The Slidev theme to use in generated slide decks: the name of a theme package, or a theme folder to copy into the slides package if it starts with . or /",
         default_value = "teach-rs"
     )]
     slide_theme: String,
+    #[arg(
+        long = "slide-template",
+        value_name = "PATH",
+        help = "Use this slide template for the units that don't set their own, instead of the default template of the track"
+    )]
+    slide_template: Option<PathBuf>,
+    #[arg(
+        long = "book-theme",
+        value_name = "DIR",
+        help = "Copy this mdbook theme folder into the book"
+    )]
+    book_theme: Option<PathBuf>,
+    #[arg(
+        long = "book-notes",
+        help = "Also write the slides of the topics into the book page of their unit, as notes to read after the lectures, with the objectives, summary and further reading of the unit"
+    )]
+    book_notes: bool,
     #[arg(
         long = "json-stub",
         help = "The path of the package.json stub to use when generating the slide package"
     )]
     package_json: Option<PathBuf>,
+    #[arg(
+        long = "skip-optional",
+        help = "Leave out topics and exercises that are marked as optional"
+    )]
+    skip_optional: bool,
+    #[arg(
+        long = "profile",
+        add = crate::completions::profiles(),
+        help = "Render the variant of the track for this profile, as defined in the track definition"
+    )]
+    profile: Option<String>,
+    // Not named `locale`, which `modmod i18n` reads from the configuration
+    #[arg(
+        long = "locale",
+        value_name = "LOCALE",
+        help = "Render the track in this locale from the translations of its content, into folders with the locale as suffix like slides-nl, or in each of its locales with all"
+    )]
+    render_locale: Option<String>,
+    #[arg(
+        long = "allow-index-gaps",
+        help = "Don't warn about gaps in the numbering of modules and units"
+    )]
+    allow_index_gaps: bool,
+    #[arg(
+        long = "max-asset-size",
+        value_name = "MIB",
+        default_value_t = DEFAULT_MAX_ASSET_SIZE / MIB,
+        help = "Warn about images and exercise files larger than this many MiB, which usually shouldn't be committed. 0 to not warn"
+    )]
+    max_asset_size: u64,
+    #[arg(
+        long = "max-section-size",
+        value_name = "MIB",
+        default_value_t = DEFAULT_MAX_SECTION_SIZE / MIB,
+        help = "Warn about topics and exercise descriptions larger than this many MiB, which make large decks and book pages. 0 to not warn"
+    )]
+    max_section_size: u64,
+    #[arg(
+        long = "force-scaffold",
+        help = "Replace the rust-toolchain.toml, devcontainer and deploy workflow files, even if modmod didn't generate them"
+    )]
+    force_scaffold: bool,
+    #[arg(
+        long = "deploy-workflow",
+        value_name = "CI",
+        help = "Write a workflow into the output folder that builds the slides and the book and deploys them to Pages: github or gitlab"
+    )]
+    deploy_workflow: Option<CiProvider>,
+    #[arg(
+        long = "course-json",
+        help = "Write course.json into the output folder, which describes the modules, units and exercises of the course for websites and learning management systems"
+    )]
+    course_json: bool,
+    #[arg(
+        long = "schedule-md",
+        help = "Write schedule.md into the output folder, with the lecture and exercise time of each module and unit as set by the duration_minutes of their topics and exercises"
+    )]
+    schedule_md: bool,
+    #[arg(
+        long = "quiz",
+        value_name = "MODE",
+        default_value_t = QuizMode::Static,
+        help = "How the quizzes of the topics are rendered into the book: static, with their answers folded away, or mdbook-quiz, which has them answered in the browser and needs mdbook-quiz to build the book"
+    )]
+    quiz: QuizMode,
+    #[arg(
+        long = "instructor-notes",
+        help = "Write the speaker notes of the decks into instructor/notes-<module>.md, a document per module for trainers that isn't deployed or archived"
+    )]
+    instructor_notes: bool,
+    #[arg(
+        long = "instructor-handout",
+        help = "Write a handout per deck into instructor/<deck>.md for trainers, with the objectives, summary, speaker notes and exercises of its unit"
+    )]
+    instructor_handout: bool,
+    #[arg(
+        long = "thumbnails",
+        help = "Export a preview of the title slide of each deck into slides/thumbs/, shown on the landing page and the catalog. Needs the slides to be installed with npm, and skips decks that didn't change"
+    )]
+    thumbnails: bool,
+    #[arg(
+        long = "export-combined",
+        help = "Also write all decks into one, slides/all.md, with an export-all script in the package.json of the slides that exports it as one PDF"
+    )]
+    export_combined: bool,
+    #[arg(
+        long = "qr-code",
+        value_name = "TARGET",
+        help = "Put a slide with QR codes at the end of each deck that link to what goes with it: exercises, its chapter in the book, or feedback, the form in the feedback_url variable. Can be given more than once"
+    )]
+    qr_codes: Vec<QrTarget>,
+    #[arg(
+        long = "credits",
+        help = "Write CREDITS.md and credits.json into the slides, book and exercises folders, which credit the authors and license of each topic and exercise"
+    )]
+    credits: bool,
+    #[arg(
+        long = "with-solutions",
+        help = "Also write the exercises with their solutions into the solutions folder, instead of only the starter code into the exercises folder"
+    )]
+    with_solutions: bool,
+    #[arg(
+        long = "units",
+        add = crate::completions::units(),
+        value_name = "SELECTOR",
+        value_delimiter = ',',
+        help = "Only render these units, given by id, by tag as tag:<tag>, by the label or id of their module like B, or by the label of their module and their number like B.2. What is left is numbered again from 1, so the output has no gaps. Can be given more than once, or as a list like A,B.2,E"
+    )]
+    units: Vec<String>,
+    #[arg(
+        long = "strict",
+        help = "Fail when content is missing. This is the default",
+        conflicts_with = "lenient"
+    )]
+    _strict: bool,
+    #[arg(
+        long = "lenient",
+        help = "Render placeholders for missing content and report it as a warning, instead of failing"
+    )]
+    lenient: bool,
+    #[arg(
+        long = "offline",
+        help = "Fail on exercises from git repositories that aren't cached yet, instead of fetching them"
+    )]
+    offline: bool,
+    #[arg(
+        long = "search-index",
+        help = "Write a search index of the slides and the book into the output folder, with a page to search it"
+    )]
+    search_index: bool,
+    #[arg(
+        long = "search-skip-code",
+        help = "Leave code blocks out of the search index",
+        requires = "search_index"
+    )]
+    search_skip_code: bool,
+    #[arg(
+        long = "slides-dir",
+        help = "Write the slides package into this folder, relative to the output folder, instead of the one the track sets"
+    )]
+    slides_dir: Option<PathBuf>,
+    #[arg(
+        long = "book-dir",
+        help = "Write the book into this folder, relative to the output folder, instead of the one the track sets"
+    )]
+    book_dir: Option<PathBuf>,
+    #[arg(
+        long = "exercises-dir",
+        help = "Write the exercises into this folder, relative to the output folder, instead of the one the track sets"
+    )]
+    exercises_dir: Option<PathBuf>,
+    #[arg(
+        long = "solutions-dir",
+        help = "Write the exercises with their solutions into this folder, relative to the output folder, instead of the one the track sets"
+    )]
+    solutions_dir: Option<PathBuf>,
+    #[arg(
+        long = "images-dir",
+        help = "Copy the slide images into this folder, relative to the slides folder, instead of the one the track sets"
+    )]
+    images_dir: Option<PathBuf>,
+    #[arg(
+        long = "dist-dir",
+        help = "Have the slides build scripts write into this folder, relative to the slides folder, instead of the one the track sets"
+    )]
+    dist_dir: Option<PathBuf>,
+    #[arg(
+        long = "merge-similar-objectives",
+        help = "Also leave out objectives of a unit that are nearly the same as an earlier one, not only exact repeats"
+    )]
+    merge_similar_objectives: bool,
+    #[arg(
+        long = "only",
+        value_name = "TARGET",
+        add = crate::completions::render_targets(),
+        help = "Only render this part of the output: slides, book or exercises. Can be given more than once"
+    )]
+    only: Vec<RenderTarget>,
+    #[arg(
+        long = "skip",
+        value_name = "TARGET",
+        add = crate::completions::render_targets(),
+        help = "Don't render this part of the output: slides, book or exercises. Can be given more than once"
+    )]
+    skip: Vec<RenderTarget>,
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        value_name = "N",
+        help = "Load and render on at most this many threads. Defaults to the number of CPUs"
+    )]
+    jobs: Option<NonZeroUsize>,
+    #[arg(
+        long = "course-version",
+        value_name = "VERSION",
+        help = "The version of the course to stamp the output with, like 2025.1, instead of the version the track sets"
+    )]
+    course_version: Option<String>,
+    #[arg(
+        long = "free-form-version",
+        help = "Accept any course version, not only ones like 2025.1 or 1.2.0-rc.1"
+    )]
+    free_form_version: bool,
+    #[arg(
+        long = "locked",
+        help = "Fail if the content of a track differs from its lock in modmod.lock, as written by modmod lock"
+    )]
+    locked: bool,
+    #[arg(
+        long = "changed-since",
+        value_name = "REV",
+        help = "Only render the units affected by the changes in git since this revision, like origin/main. Changes to the track definition affect all units",
+        conflicts_with = "units"
+    )]
+    changed_since: Option<String>,
+    /// Write the build context of a container, as `modmod container` does
+    #[arg(skip)]
+    pub(crate) container: bool,
+}
+
+impl RenderArgs {
+    /// The track and workspace definition files that were given
+    pub fn definition_paths(&self) -> impl Iterator<Item = &Path> {
+        self.track_toml_paths
+            .iter()
+            .chain(self.workspace.iter())
+            .map(PathBuf::as_path)
+    }
 }
 
-pub fn run(args: Args) -> Result<(), ModModError> {
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
     let Args {
         out_dir,
         clear_output_dir,
-        track_toml_path,
-        slide_url_base,
-        slide_theme,
-        package_json,
+        force,
         patch_file,
+        interactive,
+        mut render,
     } = args;
 
-    let slide_opts = SlidesRenderOptions {
-        theme: &slide_theme,
-        package_json,
-        url_base: slide_url_base.as_str(),
-    };
+    if interactive {
+        let Some(units) = select_units(&render, content_dir)? else {
+            println!("Selection cancelled, nothing was rendered");
+            return Ok(());
+        };
+        println!(
+            "To render this selection again, run:\n  {}",
+            crate::select::command_line(&units)
+        );
+        render.units = units;
+    }
 
     let (out_dir, patch_opts) = if let Some(patch_file) = patch_file {
-        let tmp_dir = std::env::temp_dir().join("modmod_tmp");
+        let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_tmp")?;
         let patch_opts = GenPatchOptions {
-            new_dir: tmp_dir.clone(),
+            new_dir: tmp_dir.to_path_buf(),
             old_dir: out_dir,
             patch_file,
         };
-        (tmp_dir, Some(patch_opts))
+        (tmp_dir.to_path_buf(), Some((patch_opts, tmp_dir)))
     } else {
         (out_dir, None)
     };
 
-    let track_opts = TrackRenderOptions {
-        out_dir,
-        slide_opts,
-        clear_output_dir,
+    // The files of a render that was interrupted are incomplete output, which would be
+    // taken for a finished render. Patches are rendered into a scratch folder, which
+    // isn't output.
+    let journaled = patch_opts.is_none();
+    let journal = match journaled {
+        true => modmod::io::read_journal::<ModModError>(&out_dir)?,
+        false => None,
     };
+    if let (Some(files), false) = (journal, clear_output_dir) {
+        return Err(ModModError::report().attach_printable(format!(
+            "An earlier render into {} didn't finish after writing {} file(s), which are listed in {}. Render again with --clear, or remove them with `modmod clean`",
+            out_dir.display(),
+            files.len(),
+            modmod::io::journal_path(&out_dir).display()
+        )));
+    }
+    if journaled {
+        modmod::io::begin_journal::<ModModError>(&out_dir)?;
+    }
+    // Changes made by hand to the output are kept by the render, see `RenderOptions::force`
+    let rendered = render_tracks(render, &out_dir, clear_output_dir, force, content_dir);
+    // A render that fails reports why, so its journal isn't left to tell it didn't finish
+    let ended = modmod::io::end_journal::<ModModError>();
+    rendered?;
+    ended?;
 
-    let track =
-        modmod::Track::load_toml_def(track_toml_path).change_context(ModModError::default())?;
-    track
-        .render(track_opts)
+    // The scratch folder is removed once the patch is made of it
+    if let Some((patch_opts, _tmp_dir)) = patch_opts {
+        modmod::patch::Patch::render(patch_opts).change_context(ModModError::default())?;
+    }
+
+    Ok(())
+}
+
+/// Tells which files with local changes `generate --clear` kept
+fn log_local_edits(report: &UpdateReport) {
+    for path in report.kept.iter() {
+        tracing::info!(
+            file = path,
+            "Kept the local changes to {path}, as its generated version didn't change"
+        );
+    }
+    for path in report.conflicts.iter() {
+        tracing::warn!(
+            file = path,
+            "{path} was changed locally, and kept. Its new version is written to {path}{CONFLICT_SUFFIX}, pass --force to overwrite it"
+        );
+    }
+    for path in report.stale.iter() {
+        tracing::warn!(
+            file = path,
+            "{path} was changed locally, and kept, though it's no longer generated"
+        );
+    }
+}
+
+/// Lets the user pick the units of the one track in `render`, see
+/// [`crate::select::select_units`]
+fn select_units(
+    render: &RenderArgs,
+    content_dir: Option<&Path>,
+) -> Result<Option<Vec<String>>, ModModError> {
+    crate::select::ensure_terminal()?;
+    let [track_toml_path] = render.track_toml_paths.as_slice() else {
+        return Err(ModModError::usage(
+            "--interactive selects units of a single track, so give exactly one track definition",
+        ));
+    };
+    if render.workspace.is_some() {
+        return Err(ModModError::usage(
+            "--interactive selects units of a single track, and can't be used with --workspace",
+        ));
+    }
+    let loader = crate::loader(FailurePolicy::Lenient, content_dir).without_content();
+    let track = modmod::Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
         .change_context(ModModError::default())?;
+    crate::select::select_units(&track, &render.units)
+}
 
-    if let Some(patch_opts) = patch_opts {
-        let tmp_dir = patch_opts.new_dir.clone();
-        modmod::patch::Patch::render(patch_opts).change_context(ModModError::default())?;
-        fs::remove_dir_all(tmp_dir)
-            .into_report()
+/// Prints which units of `track` are rendered for the changes since `rev`
+fn print_changes(track: &Track, rev: &str, changed: &ChangedContent) {
+    if changed.affects_all() {
+        println!(
+            "Rendering all units of track '{}', as {} changed since {rev}",
+            track.name,
+            match changed.track_files.is_empty() {
+                true => "all of them".to_string(),
+                false => changed
+                    .track_files
+                    .iter()
+                    .map(|f| f.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }
+        );
+    } else if changed.units.is_empty() {
+        println!(
+            "Skipping track '{}', as none of its units changed since {rev}",
+            track.name
+        );
+    } else {
+        println!(
+            "Rendering {} of the {} units of track '{}' that changed since {rev}: {}",
+            changed.units.len(),
+            changed.units.len() + changed.unchanged_units.len(),
+            track.name,
+            changed.units.join(", ")
+        );
+        println!("  unchanged: {}", changed.unchanged_units.join(", "));
+    }
+}
+
+/// Prints how long the phases took, and which artifacts were slowest
+/// Logs the warnings of a render
+pub(crate) struct LogReporter;
+
+impl Reporter for LogReporter {
+    fn warning(&mut self, warning: &Warning) {
+        log::warning(warning);
+    }
+
+    fn rendered(&mut self, report: &RenderReport) {
+        if let Some(edits) = &report.local_edits {
+            log_local_edits(edits);
+        }
+    }
+}
+
+fn print_timings(report: &RenderReport) {
+    println!("  timings:");
+    for timing in report.timings.iter().filter(|t| t.item.is_none()) {
+        println!("    {timing}");
+    }
+    let slowest = report.slowest(10);
+    if !slowest.is_empty() {
+        println!("  slowest artifacts:");
+        for timing in slowest {
+            println!("    {timing}");
+        }
+    }
+}
+
+/// Renders the tracks into `out_dir`, each into its own subfolder if there's more than one.
+/// With `clear_output_dir`, folders that hold no earlier output are only rendered into with
+/// `force`, see [`RenderOptions::force`].
+pub fn render_tracks(
+    args: RenderArgs,
+    out_dir: &Path,
+    clear_output_dir: bool,
+    force: bool,
+    content_dir: Option<&Path>,
+) -> Result<(), ModModError> {
+    let RenderArgs {
+        mut track_toml_paths,
+        workspace,
+        fail_fast,
+        base_url,
+        slide_theme,
+        slide_template,
+        book_theme,
+        book_notes,
+        package_json,
+        skip_optional,
+        profile,
+        render_locale,
+        allow_index_gaps,
+        max_asset_size,
+        max_section_size,
+        force_scaffold,
+        deploy_workflow,
+        course_json,
+        schedule_md,
+        quiz,
+        instructor_notes,
+        instructor_handout,
+        thumbnails,
+        export_combined,
+        qr_codes,
+        credits,
+        with_solutions,
+        units,
+        _strict,
+        lenient,
+        offline,
+        search_index,
+        search_skip_code,
+        slides_dir,
+        book_dir,
+        exercises_dir,
+        solutions_dir,
+        images_dir,
+        dist_dir,
+        merge_similar_objectives,
+        only,
+        skip,
+        jobs,
+        course_version,
+        free_form_version,
+        locked,
+        changed_since,
+        container,
+    } = args;
+    let context = modmod::context::current();
+    let mut render_context = context.for_render().fail_fast(fail_fast);
+    // Dry runs render on one thread, see `main`
+    if let Some(jobs) = jobs.filter(|_| !context.writes_discarded()) {
+        render_context = render_context.jobs(jobs.get());
+    }
+    let _context = render_context.enter();
+    let targets: Vec<_> = RenderTarget::ALL
+        .into_iter()
+        .filter(|t| (only.is_empty() || only.contains(t)) && !skip.contains(t))
+        .collect();
+    if targets.is_empty() {
+        return Err(ModModError::usage("All targets are skipped"));
+    }
+    let layout = OutputLayout {
+        slides: slides_dir,
+        book: book_dir,
+        exercises: exercises_dir,
+        solutions: solutions_dir,
+        images: images_dir,
+        dist: dist_dir,
+    };
+    let policy = if lenient {
+        FailurePolicy::Lenient
+    } else {
+        FailurePolicy::Strict
+    };
+
+    // Paths given on the command line are relative to the working directory
+    let canonicalize = |path: Option<PathBuf>, what: &str| {
+        path.map(|path| {
+            path.canonicalize()
+                .into_report()
+                .attach_printable_lazy(|| format!("Unable to find {what} at {}", path.display()))
+                .change_context(ModModError::default())
+        })
+        .transpose()
+    };
+    let package_json = canonicalize(package_json, "package.json stub")?;
+    let overrides = RenderOverrides {
+        slide_template: canonicalize(slide_template, "slide template")?,
+        book_theme: canonicalize(book_theme, "book theme")?,
+    };
+
+    if track_toml_paths
+        .iter()
+        .filter(|p| *p == Path::new("-"))
+        .count()
+        > 1
+    {
+        return Err(ModModError::usage(
+            "Only one track definition can be read from stdin",
+        ));
+    }
+
+    if let Some(workspace) = workspace {
+        let workspace =
+            WorkspaceDef::load(&workspace, None).change_context(ModModError::default())?;
+        track_toml_paths.extend(workspace.track_paths());
+    }
+
+    let mut loader = crate::loader(policy, content_dir);
+    if offline {
+        loader = loader.offline();
+    }
+    let tracks = modmod::Track::load_toml_defs_with(&track_toml_paths, loader);
+    // Tracks only get their own subfolder when there's more than one of them, and then a
+    // catalog that lists them, which is made from their course.json
+    let catalog = track_toml_paths.len() > 1;
+    let track_out_dir = |track_path: &Path| {
+        if track_toml_paths.len() > 1 {
+            out_dir.join(track_dir_name(track_path))
+        } else {
+            out_dir.to_path_buf()
+        }
+    };
+
+    let mut failed = vec![];
+    for (track, track_path) in tracks.into_iter().zip(track_toml_paths.iter()) {
+        let mut track_units = vec![];
+        let mut unaffected = false;
+        let track = track.and_then(|mut track| {
+            if !units.is_empty() {
+                // The lock is of the whole track, so it's verified before the selection
+                if locked {
+                    track.verify_lock().change_context(LoadTrackError)?;
+                }
+                track.select_units(&units)?;
+            }
+            if let Some(rev) = &changed_since {
+                let changed = track.changed_since(rev).change_context(LoadTrackError)?;
+                print_changes(&track, rev, &changed);
+                if !changed.affects_all() {
+                    unaffected = changed.units.is_empty();
+                    track_units = changed.units;
+                }
+            }
+            Ok(track)
+        });
+        if unaffected {
+            continue;
+        }
+        let mut track_opts = RenderOptions::new(track_out_dir(track_path));
+        track_opts.theme = slide_theme.clone();
+        track_opts.package_json = package_json.clone();
+        track_opts.base_url = base_url.clone();
+        track_opts.locked = locked && units.is_empty();
+        track_opts.clear_output_dir = clear_output_dir;
+        track_opts.force = force;
+        track_opts.skip_optional = skip_optional;
+        track_opts.profile = profile.clone();
+        track_opts.allow_index_gaps = allow_index_gaps;
+        track_opts.units = track_units;
+        track_opts.search_index = search_index.then_some(SearchIndexOptions {
+            skip_code: search_skip_code,
+        });
+        track_opts.layout = layout.clone();
+        track_opts.merge_similar_objectives = merge_similar_objectives;
+        track_opts.targets = targets.clone();
+        track_opts.course_version = course_version.clone();
+        track_opts.free_form_version = free_form_version;
+        track_opts.overrides = overrides.clone();
+        track_opts.max_asset_size = (max_asset_size > 0).then(|| max_asset_size * MIB);
+        track_opts.max_section_size = (max_section_size > 0).then(|| max_section_size * MIB);
+        track_opts.force_scaffold = force_scaffold;
+        track_opts.deploy_workflow = deploy_workflow;
+        track_opts.container = container;
+        track_opts.course_json = course_json || catalog;
+        track_opts.schedule_md = schedule_md;
+        track_opts.quiz = quiz;
+        track_opts.instructor_notes = instructor_notes;
+        track_opts.instructor_handout = instructor_handout;
+        track_opts.thumbnails = thumbnails;
+        track_opts.export_combined = export_combined;
+        track_opts.qr_codes = qr_codes.clone();
+        track_opts.credits = credits;
+        track_opts.book_notes = book_notes;
+        track_opts.with_solutions = with_solutions;
+        let result = track.and_then(|track| {
+            // The locale of the track goes last, so that the files in the root of the output,
+            // like the landing page, are those of its render
+            let locales: Vec<_> = match render_locale.as_deref() {
+                Some("all") => track
+                    .translations
+                    .iter()
+                    .cloned()
+                    .map(Some)
+                    .chain([None])
+                    .collect(),
+                locale => vec![locale.map(str::to_string)],
+            };
+            for (locale, i) in locales.into_iter().zip(0..) {
+                track_opts.locale = locale;
+                // Next to the output of the locales before it
+                track_opts.clear_output_dir |= i > 0;
+                let report = track.render(&track_opts, &mut LogReporter)?;
+                // The summary isn't wanted with --quiet
+                if log::enabled(tracing::Level::WARN) {
+                    println!("{report}");
+                    if log::enabled(tracing::Level::INFO) {
+                        print_timings(&report);
+                    }
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => {}
+            Err(e) if fail_fast => return Err(e.change_context(ModModError::default())),
+            Err(e) => {
+                let message = format!("Error rendering track {}", track_path.to_string_lossy());
+                let code = log::report(&message, &e);
+                failed.push((track_path, code));
+            }
+        }
+    }
+
+    // Tracks that failed are listed as they were last rendered, if they were
+    if catalog && !modmod::io::writes_discarded_at(out_dir) {
+        let track_dirs: Vec<_> = track_toml_paths.iter().map(|p| track_dir_name(p)).collect();
+        let report = course_catalog::write_catalog(out_dir, &track_dirs)
             .change_context(ModModError::default())?;
+        if log::enabled(tracing::Level::WARN) {
+            println!("{report}");
+        }
+        // Each track wrote the hashes of its own folder, which `update` and `clean` of the
+        // whole output folder go by
+        FileHashes::of_dir(out_dir)
+            .and_then(|hashes| hashes.write(out_dir))
+            .change_context(ModModError::default())?;
+    }
+
+    if let Some((_, code)) = failed.first() {
+        // Exit as the first track that failed did
+        let mut report = ModModError::report().attach(*code);
+        for (track_path, _) in failed {
+            report = report.attach_printable(format!(
+                "Track {} failed to render",
+                track_path.to_string_lossy()
+            ));
+        }
+        return Err(report);
     }
 
     Ok(())
 }
+
+/// Name of the output subfolder for a track, derived from its definition file name,
+/// e.g. `rust-intro` for `rust-intro.track.toml`
+fn track_dir_name(track_path: &Path) -> String {
+    let file_name = track_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = file_name.strip_suffix(".toml").unwrap_or(&file_name);
+    let name = name.strip_suffix(".track").unwrap_or(name);
+    name.to_string()
+}
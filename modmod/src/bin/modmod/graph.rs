@@ -0,0 +1,106 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{graph::TrackGraph, io::PathExt, FailurePolicy, Track};
+
+use crate::ModModError;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown graph format '{s}', expected dot, mermaid or json"
+            )),
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "format",
+        default_value = "dot",
+        help = "The format of the graph: dot for Graphviz, mermaid or json"
+    )]
+    format: Format,
+    #[arg(
+        long = "module",
+        value_name = "ID",
+        help = "Only include the module with this id, which is the name of the folder of its definition"
+    )]
+    module: Option<String>,
+    #[arg(
+        long = "chapter",
+        help = "With --format mermaid, write a book chapter with the diagram, to add as an appendix to the book"
+    )]
+    chapter: bool,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "Write the graph to this file instead of printing it"
+    )]
+    out_file: Option<PathBuf>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        format,
+        module,
+        chapter,
+        out_file,
+    } = args;
+    if chapter && format != Format::Mermaid {
+        return Err(ModModError::usage("--chapter requires --format mermaid"));
+    }
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let graph =
+        TrackGraph::collect(&track, module.as_deref()).change_context(ModModError::default())?;
+    let output = match format {
+        Format::Dot => graph.to_dot(),
+        Format::Mermaid if chapter => graph.to_mermaid_chapter(),
+        Format::Mermaid => graph.to_mermaid(),
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&graph)
+                .into_report()
+                .change_context(ModModError::default())?;
+            json + "\n"
+        }
+    };
+
+    match out_file {
+        Some(out_file) => {
+            out_file.write_text_file(output)?;
+            println!(
+                "Wrote the graph of {} node(s) and {} edge(s) to {}",
+                graph.nodes.len(),
+                graph.edges.len(),
+                out_file.display()
+            );
+        }
+        None => print!("{output}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    date::Date,
+    handout::{HandoutOptions, SlidePdfs, HANDOUTS_DIR},
+    io::PathExt,
+    manifest::{RenderManifest, RENDER_MANIFEST_FILE},
+    version::CourseVersion,
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "from",
+        value_name = "DIR",
+        help = "The output folder the track was rendered into"
+    )]
+    rendered_dir: PathBuf,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder to write the handouts into",
+        default_value = HANDOUTS_DIR
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "export",
+        help = "Export the PDFs of the slides with their npm scripts first, into the dist folder of the slides",
+        conflicts_with = "slides_pdfs"
+    )]
+    export: bool,
+    #[arg(
+        long = "slides-pdfs",
+        value_name = "DIR",
+        help = "The folder the slides were exported into, as <deck>.pdf like 2_1-basic-syntax.pdf. Defaults to the dist folder of the slides"
+    )]
+    slides_pdfs: Option<PathBuf>,
+    #[arg(
+        long = "date",
+        value_name = "YYYY-MM-DD",
+        help = "The date on the cover pages. Defaults to today"
+    )]
+    date: Option<Date>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        rendered_dir,
+        out_dir,
+        export,
+        slides_pdfs,
+        date,
+    } = args;
+    let Some(manifest) =
+        RenderManifest::read(&rendered_dir).change_context(ModModError::default())?
+    else {
+        return Err(ModModError::usage(format!(
+            "{} has no {RENDER_MANIFEST_FILE}, so it isn't the output folder of a track",
+            rendered_dir.display()
+        )));
+    };
+    // It was checked when the track was rendered
+    let course_version = manifest
+        .course_version
+        .as_deref()
+        .and_then(|version| CourseVersion::parse(version, true).ok());
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let slides = match (export, &slides_pdfs) {
+        (true, _) => SlidePdfs::Export,
+        (false, Some(dir)) => SlidePdfs::Dir(dir),
+        (false, None) => SlidePdfs::Exported,
+    };
+    let handouts = track
+        .handouts(
+            &rendered_dir,
+            HandoutOptions {
+                layout: &track.output,
+                slides,
+                date: date.unwrap_or_else(Date::today),
+                course_version: course_version.as_ref(),
+            },
+        )
+        .change_context(ModModError::default())?;
+
+    out_dir.create_dir_all()?;
+    for handout in handouts.iter() {
+        let path = out_dir.join(&handout.file_name);
+        path.write_file(&handout.pdf)?;
+        println!(
+            "Wrote {} with {} page(s), from {} deck(s) and {} exercise(s)",
+            path.display(),
+            handout.pages,
+            handout.decks,
+            handout.exercises
+        );
+    }
+    if handouts.is_empty() {
+        println!("The track has no slides or exercises to make handouts of");
+    }
+    Ok(())
+}
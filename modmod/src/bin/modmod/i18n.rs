@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use error_stack::{Result, ResultExt};
+use modmod::{i18n, FailurePolicy, Track};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: I18nCommand,
+}
+
+#[derive(Subcommand)]
+enum I18nCommand {
+    /// Write the translatable strings of each topic of a track into a translation file
+    Extract {
+        track_toml_path: PathBuf,
+        #[arg(
+            short = 'l',
+            long = "locale",
+            help = "The locale the strings are translated to"
+        )]
+        locale: String,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "The folder the translation files are written to"
+        )]
+        out_dir: PathBuf,
+    },
+    /// Write the translations in a folder of translation files next to the topics they belong to
+    Apply {
+        track_toml_path: PathBuf,
+        #[arg(help = "The folder with the translation files")]
+        dir: PathBuf,
+    },
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let load = |path: &PathBuf| {
+        Track::load_toml_defs_with(&[path], crate::loader(FailurePolicy::Strict, content_dir))
+            .pop()
+            .unwrap()
+            .change_context(ModModError::default())
+    };
+    match args.command {
+        I18nCommand::Extract {
+            track_toml_path,
+            locale,
+            out_dir,
+        } => {
+            let track = load(&track_toml_path)?;
+            let report =
+                i18n::extract(&track, &locale, &out_dir).change_context(ModModError::default())?;
+            println!("{report}");
+            report.warnings.iter().for_each(modmod::log::record_warning);
+        }
+        I18nCommand::Apply {
+            track_toml_path,
+            dir,
+        } => {
+            let track = load(&track_toml_path)?;
+            let report = i18n::apply(&track, &dir).change_context(ModModError::default())?;
+            println!("{report}");
+            // The report shows the warnings, which count towards --deny-warnings all the same
+            report.warnings.iter().for_each(modmod::log::record_warning);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    context,
+    lint::{self, Severity, CHECKS},
+    suggest, FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(required_unless_present = "list_checks")]
+    track_toml_paths: Vec<PathBuf>,
+    #[arg(
+        long = "list-checks",
+        help = "List the checks and what they report, and exit"
+    )]
+    list_checks: bool,
+    #[arg(
+        long = "allow",
+        value_name = "CHECK",
+        help = "Don't run this check, or don't report the warnings with this code of the check check. Can be given more than once"
+    )]
+    allow: Vec<String>,
+    #[arg(
+        long = "offline",
+        help = "Fail on exercises from git repositories that aren't cached yet, instead of fetching them"
+    )]
+    offline: bool,
+}
+
+/// Lints the tracks, and returns whether any of them has errors
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<bool, ModModError> {
+    let Args {
+        track_toml_paths,
+        list_checks,
+        allow,
+        offline,
+    } = args;
+    if list_checks {
+        for check in CHECKS {
+            println!("{:<18} {}", check.name(), check.description());
+        }
+        return Ok(false);
+    }
+    for name in allow.iter() {
+        if !lint::allowable().any(|allowable| allowable == name) {
+            return Err(ModModError::usage(format!(
+                "There is no check or warning code '{name}'.{} Run `modmod lint --list-checks` to list the checks",
+                suggest::did_you_mean(name, lint::allowable())
+            )));
+        }
+    }
+    let allow: BTreeSet<String> = allow.into_iter().collect();
+    // Linting writes nothing, not even exercises fetched into the cache
+    let _context = context::current().discard_writes().enter();
+
+    // Content that can't be found is reported by the checks, all of it at once
+    let mut loader = crate::loader(FailurePolicy::Lenient, content_dir);
+    if offline {
+        loader = loader.offline();
+    }
+    let tracks = Track::load_toml_defs_with(&track_toml_paths, loader)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .change_context(ModModError::default())?;
+
+    let mut failed = false;
+    for track in tracks.iter() {
+        let findings = track.lint(&allow);
+        for finding in findings.iter() {
+            println!("{finding}");
+        }
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        println!(
+            "Track '{}': {errors} error(s), {} warning(s)",
+            track.name,
+            findings.len() - errors
+        );
+        failed |= errors > 0;
+    }
+    Ok(failed)
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    list::{ListDepth, TrackListing},
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        default_value = "exercises",
+        help = "How deep to list the track: modules, units, topics or exercises"
+    )]
+    depth: ListDepth,
+    #[arg(
+        long = "module",
+        value_name = "ID",
+        help = "Only list this module: the name of the folder of its definition, the part of that before the first dash, like B, or its label"
+    )]
+    module: Option<String>,
+    #[arg(
+        long = "paths",
+        help = "Show the source file or folder of each module, unit, topic and exercise"
+    )]
+    paths: bool,
+    #[arg(long = "json", help = "Output the listing as JSON")]
+    json: bool,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        depth,
+        module,
+        paths,
+        json,
+    } = args;
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let listing = TrackListing::collect(&track, depth, module.as_deref(), paths)
+        .change_context(ModModError::default())?;
+
+    if json {
+        let json = serde_json::to_string_pretty(&listing)
+            .into_report()
+            .change_context(ModModError::default())?;
+        println!("{json}");
+    } else {
+        print!("{listing}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    lock::{Lockfile, LOCK_VERSION},
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        required = true,
+        help = "The track definitions to lock, each in the modmod.lock in its folder"
+    )]
+    track_toml_paths: Vec<PathBuf>,
+}
+
+/// Locks the tracks, or with `refresh`, updates the locks of tracks that were locked before
+pub fn run(args: Args, refresh: bool, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args { track_toml_paths } = args;
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let tracks = Track::load_toml_defs_with(&track_toml_paths, loader)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .change_context(ModModError::default())?;
+
+    // Tracks with their definition in the same folder share a lockfile
+    let mut lockfiles: BTreeMap<PathBuf, Lockfile> = BTreeMap::new();
+    for track in &tracks {
+        let lock = track.lock().change_context(ModModError::default())?;
+        let path = Lockfile::path_of(&track.definition);
+        if !lockfiles.contains_key(&path) {
+            let lockfile = Lockfile::load(&path)
+                .change_context(ModModError::default())?
+                .unwrap_or_default();
+            lockfiles.insert(path.clone(), lockfile);
+        }
+        let lockfile = lockfiles.get_mut(&path).unwrap();
+        let key = Lockfile::key_of(&track.definition);
+        match (lockfile.tracks.get(&key), refresh) {
+            (Some(_), false) => {
+                return Err(ModModError::report().attach_printable(format!(
+                    "Track '{}' is already locked in {}. Refresh its lock with `modmod update-lock`",
+                    track.name,
+                    path.display()
+                )));
+            }
+            (None, true) => {
+                return Err(ModModError::report().attach_printable(format!(
+                    "Track '{}' isn't locked in {}. Lock it with `modmod lock`",
+                    track.name,
+                    path.display()
+                )));
+            }
+            (None, false) => println!(
+                "Locked {} file(s) of track '{}' in {}",
+                lock.files.len(),
+                track.name,
+                path.display()
+            ),
+            (Some(locked), true) => {
+                let lines = locked.diff(&lock).lines();
+                if lines.is_empty() {
+                    println!(
+                        "The lock of track '{}' in {} is up to date",
+                        track.name,
+                        path.display()
+                    );
+                } else {
+                    println!(
+                        "Updated the lock of track '{}' in {}:",
+                        track.name,
+                        path.display()
+                    );
+                    for line in lines {
+                        println!("  - {line}");
+                    }
+                }
+            }
+        }
+        lockfile.version = LOCK_VERSION;
+        lockfile.tracks.insert(key, lock);
+    }
+    for (path, lockfile) in lockfiles {
+        lockfile
+            .write(&path)
+            .change_context(ModModError::default())?;
+    }
+    Ok(())
+}
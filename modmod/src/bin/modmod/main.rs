@@ -1,9 +1,61 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
 use error_stack::Report;
-use std::{fmt, process::exit};
+use modmod::{
+    context::RunContext,
+    failure::{
+        ErrorCode, UsageError, BROKEN_LINKS_EXIT_CODE, DENIED_WARNINGS_EXIT_CODE,
+        DIFFERENCES_EXIT_CODE, LINT_ERRORS_EXIT_CODE, SUCCESS_EXIT_CODE,
+    },
+    io::{LineEnding, PlannedWrite},
+    load::Loader,
+    log::{self, LogFormat},
+    progress::ProgressBar,
+    report::{format_bytes, WarningCode},
+    FailurePolicy,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::OnceLock,
+};
 
+mod anki;
+mod cartridge;
+mod check;
+mod check_links;
+mod clean;
+mod completions;
+mod config;
+mod container;
 mod create;
+mod diff;
+mod exercise;
+mod explain;
+mod export;
+mod format;
 mod gen;
+mod graph;
+mod handout;
+mod i18n;
+mod lint;
+mod list;
+mod lock;
+mod migrate;
+mod package;
+mod publish;
+mod render_deck;
+mod schedule;
+mod scorm;
+mod select;
+mod serve;
+mod stats;
+mod template;
+mod update;
+mod verify_output;
+mod watch;
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -13,6 +65,13 @@ impl ModModError {
     fn report() -> Report<Self> {
         Report::from(Self::default())
     }
+
+    /// An error in the arguments that were given, which exits with [`ErrorCode::Usage`]
+    fn usage(message: impl fmt::Display + fmt::Debug + Send + Sync + 'static) -> Report<Self> {
+        Report::new(UsageError::default())
+            .attach_printable(message)
+            .change_context(Self::default())
+    }
 }
 
 impl error_stack::Context for ModModError {}
@@ -27,31 +86,568 @@ impl fmt::Display for ModModError {
 struct App {
     #[command(subcommand)]
     command: Command,
+    #[arg(
+        short = 'v',
+        long = "verbose",
+        action = ArgAction::Count,
+        global = true,
+        help = "Log what is being rendered. Give twice to log more details"
+    )]
+    verbose: u8,
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        conflicts_with = "verbose",
+        help = "Only print errors"
+    )]
+    quiet: bool,
+    #[arg(
+        long = "log-format",
+        value_name = "FORMAT",
+        add = completions::log_formats(),
+        global = true,
+        default_value_t = LogFormat::Human,
+        help = "Log as human readable lines, or as one JSON object per line: human or json"
+    )]
+    log_format: LogFormat,
+    #[arg(
+        long = "error-format",
+        value_name = "FORMAT",
+        add = completions::log_formats(),
+        global = true,
+        default_value_t = LogFormat::Human,
+        help = "With json, end with a JSON object on stderr that lists all errors and warnings: human or json"
+    )]
+    error_format: LogFormat,
+    #[arg(
+        long = "dry-run",
+        global = true,
+        help = "Run the command fully, but print the changes it would make to the file system instead of making them"
+    )]
+    dry_run: bool,
+    #[arg(
+        long = "fsync",
+        global = true,
+        help = "Sync each written file to disk before moving it into place, so that output survives a crash of the system. Slower"
+    )]
+    fsync: bool,
+    #[arg(
+        long = "unicode-slugs",
+        global = true,
+        help = "Keep letters that aren't ASCII in the names of generated files and the ids of units, instead of transliterating them or leaving them out"
+    )]
+    unicode_slugs: bool,
+    #[arg(
+        long = "line-endings",
+        value_name = "ENDING",
+        global = true,
+        default_value_t = LineEnding::Lf,
+        help = "End the lines of generated text files with lf or crlf. Copied files keep theirs"
+    )]
+    line_endings: LineEnding,
+    #[arg(
+        long = "content-dir",
+        value_name = "DIR",
+        global = true,
+        help = "Resolve the paths to the modules and other content of the tracks against this folder, instead of their content_root"
+    )]
+    content_dir: Option<PathBuf>,
+    #[arg(
+        long = "deny-warnings",
+        global = true,
+        help = "Exit with code 5 if there were warnings, like for CI. Same as --deny warnings"
+    )]
+    deny_warnings: bool,
+    #[arg(
+        long = "deny",
+        value_name = "WARNINGS",
+        global = true,
+        help = "Exit with code 5 if there were warnings: warnings for all of them, or warnings=<code>,... for the ones with these codes. Can be given more than once"
+    )]
+    deny: Vec<String>,
+}
+
+/// The warnings that make a run fail, set by `--deny-warnings` and `--deny`
+enum Denied {
+    All,
+    Codes(BTreeSet<WarningCode>),
+}
+
+impl Denied {
+    fn parse(deny_warnings: bool, deny: &[String]) -> Result<Option<Self>, Report<ModModError>> {
+        let mut codes = BTreeSet::new();
+        let mut all = deny_warnings;
+        for value in deny {
+            match value.split_once('=') {
+                None if value == "warnings" => all = true,
+                Some(("warnings", list)) => {
+                    for code in list.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                        codes.insert(code.parse().map_err(ModModError::usage)?);
+                    }
+                }
+                _ => {
+                    return Err(ModModError::usage(format!(
+                        "--deny takes warnings or warnings=<code>,..., not '{value}'"
+                    )))
+                }
+            }
+        }
+        Ok(match (all, codes.is_empty()) {
+            (true, _) => Some(Self::All),
+            (false, false) => Some(Self::Codes(codes)),
+            (false, true) => None,
+        })
+    }
+
+    fn denies(&self, code: WarningCode) -> bool {
+        match self {
+            Self::All => true,
+            Self::Codes(codes) => codes.contains(&code),
+        }
+    }
 }
 
+static DENIED: OnceLock<Denied> = OnceLock::new();
+
 #[derive(Subcommand)]
 enum Command {
-    Generate(gen::Args),
+    Generate(Box<gen::Args>),
+    Update(Box<update::Args>),
+    Diff(Box<diff::Args>),
+    VerifyOutput(Box<verify_output::Args>),
+    Clean(clean::Args),
+    Publish(publish::Args),
+    Package(Box<package::Args>),
+    // Exports, as `export cartridge`, `export scorm` and `export anki` do
+    #[command(hide = true)]
+    Cartridge(cartridge::Args),
+    #[command(hide = true)]
+    Scorm(scorm::Args),
+    #[command(hide = true)]
+    Anki(anki::Args),
+    Handout(handout::Args),
+    Export(export::Args),
+    Container(Box<container::Args>),
+    Watch(Box<watch::Args>),
+    Serve(Box<serve::Args>),
+    Init(create::InitArgs),
+    Config(config::Args),
+    Completions(completions::Args),
+    #[command(alias = "new")]
     Create(create::Args),
+    Check(check::Args),
+    CheckLinks(check_links::Args),
+    Lint(lint::Args),
+    Exercise(exercise::Args),
+    Fmt(format::Args),
+    Migrate(migrate::Args),
+    Lock(lock::Args),
+    UpdateLock(lock::Args),
+    Stats(stats::Args),
+    Schedule(schedule::Args),
+    List(list::Args),
+    Graph(graph::Args),
+    Explain(explain::Args),
+    Template(template::Args),
+    RenderDeck(render_deck::Args),
+    I18n(i18n::Args),
 }
 
 fn main() {
-    let app = App::parse();
+    // The completions and the configuration are read before the options set up the context
+    // of the run, so they write to the file system as it is
+    let _defaults = RunContext::default().enter();
+    // Answers the completion scripts of `modmod completions`, and exits
+    clap_complete::CompleteEnv::with_factory(App::command)
+        .var(completions::COMPLETE_VAR)
+        .complete();
+    let args: Vec<_> = std::env::args_os().collect();
+    let config = match config::Config::discover(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            // The options aren't parsed yet, so the error is reported the default way
+            log::init(tracing::Level::WARN, LogFormat::Human);
+            fail("Error reading configuration", e);
+        }
+    };
+    let parsed = config
+        .apply(App::command())
+        .try_get_matches_from(&args)
+        .and_then(|matches| App::from_arg_matches(&matches));
+    let app = match parsed {
+        Ok(app) => app,
+        // Help and version requests aren't errors
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            let _ = e.print();
+            // The arguments couldn't be parsed, so look for the error format by hand
+            let json = args
+                .windows(2)
+                .any(|w| w[0] == "--error-format" && w[1] == "json")
+                || args.iter().any(|a| a == "--error-format=json");
+            let _context = match json {
+                true => RunContext::default().collect_failures(),
+                false => RunContext::default(),
+            }
+            .enter();
+            if json {
+                let rendered = e.render().to_string();
+                let message = rendered.lines().next().unwrap_or_default();
+                let message = message.trim_start_matches("error: ").to_string();
+                // Nothing is logged yet, so this only adds the error to the summary
+                log::report("Invalid arguments", &ModModError::usage(message));
+            }
+            finish(ErrorCode::Usage.exit_code());
+        }
+    };
+    let level = match (app.quiet, app.verbose) {
+        (true, _) => tracing::Level::ERROR,
+        (false, 0) => tracing::Level::WARN,
+        (false, 1) => tracing::Level::INFO,
+        (false, _) => tracing::Level::DEBUG,
+    };
+    log::init(level, app.log_format);
+    let mut context = match app.dry_run {
+        // Rendering on one thread lists the writes in the order of the output
+        true => RunContext::recording().jobs(1),
+        false => RunContext::default(),
+    };
+    context = context
+        .line_ending(app.line_endings)
+        .sync_writes(app.fsync)
+        .unicode_slugs(app.unicode_slugs);
+    if app.error_format == LogFormat::Json {
+        context = context.collect_failures();
+    }
+    if !app.quiet && std::io::stderr().is_terminal() {
+        context = context.progress(ProgressBar::default());
+    }
+    // Left entered until the command exits, see `finish`
+    let _context = context.enter();
+    // Watching renders again and again, and serving needs the files on disk
+    if app.dry_run && matches!(app.command, Command::Watch(_) | Command::Serve(_)) {
+        let e = ModModError::usage("--dry-run can't be used with watch or serve");
+        fail("Invalid arguments", e);
+    }
+    match Denied::parse(app.deny_warnings, &app.deny) {
+        Ok(Some(denied)) => {
+            let _ = DENIED.set(denied);
+        }
+        Ok(None) => {}
+        Err(e) => fail("Invalid arguments", e),
+    }
+    config.warnings.iter().for_each(log::warning);
 
+    let content_dir = app.content_dir.as_deref();
     match app.command {
         Command::Generate(args) => {
-            if let Err(e) = gen::run(args) {
-                eprintln!("Error rendering track: {e:?}");
-                exit(1);
+            if let Err(e) = gen::run(*args, content_dir) {
+                fail("Error rendering track", e);
+            }
+        }
+        Command::Update(args) => {
+            if let Err(e) = update::run(*args, content_dir) {
+                fail("Error updating output folder", e);
+            }
+        }
+        Command::Clean(args) => {
+            if let Err(e) = clean::run(args) {
+                fail("Error cleaning output folder", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Package(args) => {
+            if let Err(e) = package::run(*args, content_dir) {
+                fail("Error packaging course", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Cartridge(args) => {
+            if let Err(e) = cartridge::run(args, content_dir) {
+                fail("Error exporting common cartridge", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Scorm(args) => {
+            if let Err(e) = scorm::run(args, content_dir) {
+                fail("Error exporting SCORM package", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Anki(args) => {
+            if let Err(e) = anki::run(args, content_dir) {
+                fail("Error exporting flashcards", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Handout(args) => {
+            if let Err(e) = handout::run(args, content_dir) {
+                fail("Error making handouts", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Export(args) => {
+            if let Err(e) = export::run(args, content_dir) {
+                fail("Error exporting", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Container(args) => {
+            if let Err(e) = container::run(*args, content_dir) {
+                fail("Error writing container", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Publish(args) => {
+            if let Err(e) = publish::run(args) {
+                fail("Error publishing output", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Diff(args) => {
+            match diff::run(*args, content_dir) {
+                Ok(false) => finish(SUCCESS_EXIT_CODE),
+                // Lets CI detect drift between the content and the published output
+                Ok(true) => finish(DIFFERENCES_EXIT_CODE),
+                Err(e) => fail("Error comparing output", e),
+            }
+        }
+        Command::VerifyOutput(args) => {
+            match verify_output::run(*args, content_dir) {
+                Ok(false) => finish(SUCCESS_EXIT_CODE),
+                // Lets CI catch edits to the published output before they are overwritten
+                Ok(true) => finish(DIFFERENCES_EXIT_CODE),
+                Err(e) => fail("Error verifying output", e),
+            }
+        }
+        Command::Watch(args) => {
+            if let Err(e) = watch::run(*args, content_dir) {
+                fail("Error watching track", e);
+            }
+        }
+        Command::Serve(args) => {
+            if let Err(e) = serve::run(*args, content_dir) {
+                fail("Error serving track", e);
+            }
+        }
+        Command::Check(args) => {
+            if let Err(e) = check::run(args, content_dir) {
+                fail("Error checking track", e);
+            }
+            // Don't garble the possibly machine-readable output
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::CheckLinks(args) => {
+            match check_links::run(args, content_dir) {
+                Ok(false) => finish(SUCCESS_EXIT_CODE),
+                // Lets CI fail on broken links without denying other warnings
+                Ok(true) => finish(BROKEN_LINKS_EXIT_CODE),
+                Err(e) => fail("Error checking links", e),
+            }
+        }
+        Command::Lint(args) => {
+            match lint::run(args, content_dir) {
+                Ok(false) => finish(SUCCESS_EXIT_CODE),
+                // Lets CI fail on errors in the content without denying warnings
+                Ok(true) => finish(LINT_ERRORS_EXIT_CODE),
+                Err(e) => fail("Error linting track", e),
+            }
+        }
+        Command::Fmt(args) => {
+            if let Err(e) = format::run(args) {
+                fail("Error formatting definition files", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Migrate(args) => {
+            if let Err(e) = migrate::run(args, content_dir) {
+                fail("Error migrating definition files", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Lock(args) => {
+            if let Err(e) = lock::run(args, false, content_dir) {
+                fail("Error locking track content", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::UpdateLock(args) => {
+            if let Err(e) = lock::run(args, true, content_dir) {
+                fail("Error updating track lock", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Stats(args) => {
+            if let Err(e) = stats::run(args, content_dir) {
+                fail("Error collecting track statistics", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Schedule(args) => {
+            if let Err(e) = schedule::run(args, content_dir) {
+                fail("Error scheduling track", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Exercise(args) => {
+            if let Err(e) = exercise::run(args) {
+                fail("Error testing exercises", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::List(args) => {
+            if let Err(e) = list::run(args, content_dir) {
+                fail("Error listing track", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Explain(args) => {
+            if let Err(e) = explain::run(args) {
+                fail("Error explaining code", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Graph(args) => {
+            if let Err(e) = graph::run(args, content_dir) {
+                fail("Error exporting track graph", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Template(args) => {
+            if let Err(e) = template::run(args, content_dir) {
+                fail("Error inspecting slide template", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::RenderDeck(args) => {
+            if let Err(e) = render_deck::run(args, content_dir) {
+                fail("Error rendering deck", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::I18n(args) => {
+            if let Err(e) = i18n::run(args, content_dir) {
+                fail("Error processing translations", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Config(args) => {
+            if let Err(e) = config::run(args, &config, &App::command()) {
+                fail("Error showing configuration", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Completions(args) => {
+            if let Err(e) = completions::run(args) {
+                fail("Error writing completion script", e);
+            }
+            finish(SUCCESS_EXIT_CODE);
+        }
+        Command::Init(args) => {
+            if let Err(e) = create::init(args) {
+                fail("Error creating track", e);
             }
         }
         Command::Create(args) => {
             if let Err(e) = create::run(args) {
-                eprintln!("Error creating content stub: {e:?}");
-                exit(1);
+                fail("Error creating content stub", e);
             }
         }
     }
 
-    println!("Done!");
+    if !app.quiet {
+        println!("Done!");
+    }
+    finish(SUCCESS_EXIT_CODE);
+}
+
+/// A loader with `policy` for the tracks of a command, which resolves their content against
+/// `content_dir`, the folder given with `--content-dir`, if there is one
+pub(crate) fn loader(policy: FailurePolicy, content_dir: Option<&Path>) -> Loader {
+    let loader = Loader::with_policy(policy);
+    match content_dir {
+        Some(dir) => loader.with_content_dir(dir.to_path_buf()),
+        None => loader,
+    }
+}
+
+fn fail(message: &str, report: Report<ModModError>) -> ! {
+    let code = log::report(message, &report);
+    finish(code.exit_code())
+}
+
+/// Exits, after printing what a dry run would have changed, how many warnings of each code
+/// there were, and the summary of errors and warnings if it was asked for. Fails a run that
+/// would succeed if it had warnings that were denied.
+fn finish(exit_code: i32) -> ! {
+    if let Some(writes) = modmod::context::current().take_recorded_writes() {
+        match writes.is_empty() {
+            true => println!("Dry run, nothing would be changed"),
+            false => println!("Dry run, nothing was changed. The command would:"),
+        }
+        let (mut created, mut overwritten, mut bytes) = (0, 0, 0);
+        for write in writes.iter() {
+            println!("  {write}");
+            if let PlannedWrite::WriteFile {
+                size, overwrite, ..
+            }
+            | PlannedWrite::Copy {
+                size, overwrite, ..
+            } = write
+            {
+                match overwrite {
+                    true => overwritten += 1,
+                    false => created += 1,
+                }
+                bytes += size;
+            }
+        }
+        if created + overwritten > 0 {
+            println!(
+                "It would create {created} file(s) and overwrite {overwritten}, writing {} in all",
+                format_bytes(bytes)
+            );
+        }
+    }
+    let counts = log::warning_counts();
+    if !counts.is_empty() {
+        let total: usize = counts.values().sum();
+        let described = describe_counts(&counts);
+        tracing::warn!(
+            warnings = total,
+            counts = described,
+            "{total} warning(s): {described}"
+        );
+    }
+    let mut exit_code = exit_code;
+    if let Some(denied) = DENIED.get() {
+        let counts: BTreeMap<_, _> = counts
+            .into_iter()
+            .filter(|(code, _)| denied.denies(*code))
+            .collect();
+        if !counts.is_empty() && matches!(exit_code, SUCCESS_EXIT_CODE | DIFFERENCES_EXIT_CODE) {
+            tracing::error!(
+                "Failing because of denied warnings: {}",
+                describe_counts(&counts)
+            );
+            exit_code = DENIED_WARNINGS_EXIT_CODE;
+        }
+    }
+    if let Some(mut summary) = log::take_failures() {
+        summary.exit_code = exit_code;
+        if let Ok(json) = serde_json::to_string(&summary) {
+            eprintln!("{json}");
+        }
+    }
+    exit(exit_code)
+}
+
+/// Like `3 broken-link, 1 never-reviewed`
+fn describe_counts(counts: &BTreeMap<WarningCode, usize>) -> String {
+    counts
+        .iter()
+        .map(|(code, count)| format!("{count} {code}"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    format::DefinitionKind,
+    io::{writes_discarded, PathExt},
+    migrate::{migrate_definition, CURRENT_SCHEMA_VERSION},
+    FailurePolicy, Track,
+};
+use similar::TextDiff;
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        required = true,
+        help = "The track definitions to migrate, together with the definitions of the tracks they extend and of their modules and topics"
+    )]
+    track_toml_paths: Vec<PathBuf>,
+    #[arg(
+        long = "to",
+        value_name = "VERSION",
+        help = "The schema version to migrate to, instead of the latest one"
+    )]
+    to: Option<u32>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_paths,
+        to,
+    } = args;
+    let to = to.unwrap_or(CURRENT_SCHEMA_VERSION);
+    if !(1..=CURRENT_SCHEMA_VERSION).contains(&to) {
+        return Err(ModModError::usage(format!(
+            "--to must be a schema version from 1 to {CURRENT_SCHEMA_VERSION}"
+        )));
+    }
+    let dry_run = writes_discarded();
+
+    // Missing content doesn't matter, only the definitions are rewritten
+    let loader = crate::loader(FailurePolicy::Lenient, content_dir)
+        .without_content()
+        .offline();
+    let tracks = Track::load_toml_defs_with(&track_toml_paths, loader)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .change_context(ModModError::default())?;
+    let mut files: Vec<PathBuf> = vec![];
+    for track in &tracks {
+        for file in track.definition_files() {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+
+    let (mut migrated, mut current, mut newer) = (0, 0, 0);
+    for file in files {
+        let Some(kind) = DefinitionKind::from_path(&file) else {
+            continue;
+        };
+        let content = file.read_to_string()?;
+        let migration = migrate_definition(&content, kind, to)
+            .attach_printable_lazy(|| format!("In definition file {}", file.display()))
+            .change_context(ModModError::default())?;
+        if migration.from == to {
+            current += 1;
+            continue;
+        }
+        if migration.from > to {
+            println!(
+                "{} is at schema version {}, which is past {to}, so it is left as it is",
+                file.display(),
+                migration.from
+            );
+            newer += 1;
+            continue;
+        }
+        migrated += 1;
+        println!(
+            "{} {} from schema version {} to {to}:",
+            match dry_run {
+                true => "Would migrate",
+                false => "Migrated",
+            },
+            file.display(),
+            migration.from
+        );
+        for change in &migration.changes {
+            println!("  - {change}");
+        }
+        if dry_run {
+            let name = file.display().to_string();
+            print!(
+                "{}",
+                TextDiff::from_lines(&content, &migration.content)
+                    .unified_diff()
+                    .header(&name, &name)
+            );
+        }
+        file.write_file(migration.content)?;
+    }
+    println!(
+        "{migrated} file(s) migrated to schema version {to}, {current} file(s) already at it{}",
+        match newer {
+            0 => String::new(),
+            n => format!(", {n} file(s) past it"),
+        }
+    );
+    Ok(())
+}
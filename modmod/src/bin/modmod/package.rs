@@ -0,0 +1,95 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    archive::{self, ArchiveFormat},
+    manifest::RenderManifest,
+    version::CourseVersion,
+};
+
+use crate::{gen, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'a',
+        long = "archive",
+        help = "The archive to write the course into: a .zip, .tar.gz or .tgz file"
+    )]
+    archive: PathBuf,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args { archive, render } = args;
+    let Some(format) = ArchiveFormat::from_path(&archive) else {
+        return Err(ModModError::usage(format!(
+            "Unknown archive format of {}, expected a .zip, .tar.gz or .tgz file",
+            archive.display()
+        )));
+    };
+
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_package_tmp")?;
+    let (files, archive) = package(render, &tmp_dir, &archive, format, content_dir)?;
+    println!("Packed {files} file(s) into {}", archive.display());
+    Ok(())
+}
+
+/// Renders into `tmp_dir` and packs it into the archive, returning the number of files and
+/// the path of the archive, which has the course version in its name
+fn package(
+    render: gen::RenderArgs,
+    tmp_dir: &Path,
+    archive: &Path,
+    format: ArchiveFormat,
+    content_dir: Option<&Path>,
+) -> Result<(usize, PathBuf), ModModError> {
+    gen::render_tracks(render, tmp_dir, true, false, content_dir)?;
+    let archive = &match course_version(tmp_dir)? {
+        Some(version) => archive::versioned_path(archive, &version),
+        None => archive.to_path_buf(),
+    };
+    let entries = archive::entries_of_dir(tmp_dir).change_context(ModModError::default())?;
+    let start = Instant::now();
+    archive::write_archive(&entries, archive, format).change_context(ModModError::default())?;
+    let millis = start.elapsed().as_secs_f64() * 1000.0;
+    tracing::info!(
+        path = %archive.display(),
+        ms = format_args!("{millis:.3}"),
+        "Wrote archive {} in {millis:.1} ms",
+        archive.display()
+    );
+    if modmod::io::writes_discarded_at(archive) {
+        return Ok((entries.len(), archive.clone()));
+    }
+    // Catches the archive not matching the rendered output before it's handed over
+    let packed = archive::read_archive(archive, format).change_context(ModModError::default())?;
+    if !archive::same_entries(&packed, &entries).change_context(ModModError::default())? {
+        return Err(ModModError::report().attach_printable(format!(
+            "The archive at {} doesn't match the rendered output",
+            archive.display()
+        )));
+    }
+    Ok((entries.len(), archive.clone()))
+}
+
+/// The course version the tracks in `out_dir` were rendered as, if they were all rendered
+/// as the same one
+fn course_version(out_dir: &Path) -> Result<Option<CourseVersion>, ModModError> {
+    let manifests = RenderManifest::read_all(out_dir).change_context(ModModError::default())?;
+    let versions: BTreeSet<_> = manifests
+        .into_iter()
+        .map(|(_, manifest)| manifest.course_version)
+        .collect();
+    let mut versions = versions.into_iter();
+    let (Some(Some(version)), None) = (versions.next(), versions.next()) else {
+        return Ok(None);
+    };
+    Ok(CourseVersion::parse(&version, true).ok())
+}
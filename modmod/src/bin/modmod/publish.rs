@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::publish::PublishOptions;
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The output folder of the track to publish"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "branch",
+        help = "The branch to commit the built output to",
+        default_value = "gh-pages"
+    )]
+    branch: String,
+    #[arg(
+        long = "remote",
+        help = "The remote to push the branch to",
+        default_value = "origin"
+    )]
+    remote: String,
+    #[arg(
+        long = "build",
+        help = "Build the slides with their npm scripts and the book with mdbook first"
+    )]
+    build: bool,
+    #[arg(
+        long = "force",
+        help = "Publish even if the repository in the working directory has uncommitted changes"
+    )]
+    force: bool,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args {
+        out_dir,
+        branch,
+        remote,
+        build,
+        force,
+    } = args;
+    let report = modmod::publish::publish(PublishOptions {
+        out_dir: &out_dir,
+        repo_dir: ".".as_ref(),
+        branch: &branch,
+        remote: &remote,
+        build,
+        force,
+    })
+    .change_context(ModModError::default())?;
+    println!("{report}");
+    Ok(())
+}
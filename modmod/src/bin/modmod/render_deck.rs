@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{io::PathExt, FailurePolicy, Track};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "unit",
+        visible_alias = "deck",
+        help = "The deck to render: its number like 3.2, its prefix like 3_2, or the id of its unit"
+    )]
+    unit: String,
+    #[arg(
+        long = "stdout",
+        help = "Print the deck, which is the default",
+        conflicts_with = "out_file"
+    )]
+    stdout: bool,
+    #[arg(short = 'o', long = "output", help = "Write the deck to this file")]
+    out_file: Option<PathBuf>,
+    #[arg(
+        long = "theme",
+        help = "The name of the Slidev theme to use in generated slide decks",
+        default_value = "teach-rs"
+    )]
+    slide_theme: String,
+    #[arg(
+        long = "profile",
+        add = crate::completions::profiles(),
+        help = "Resolve conditional content for this profile, as defined in the track definition"
+    )]
+    profile: Option<String>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        unit,
+        stdout: _,
+        out_file,
+        slide_theme,
+        profile,
+    } = args;
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let rendered = track
+        .render_deck(&unit, &slide_theme, profile.as_deref())
+        .change_context(ModModError::default())?;
+    rendered.warnings.iter().for_each(modmod::log::warning);
+
+    let mut markdown = rendered.markdown;
+    if !rendered.images.is_empty() {
+        // The images aren't copied, so say where they come from instead
+        markdown.push_str(&format!(
+            "\n<!--\nImages, which rendering copies into the images folder of the slides:\n{}-->\n",
+            rendered
+                .images
+                .iter()
+                .map(|image| format!("- {}\n", image.display()))
+                .collect::<String>()
+        ));
+    }
+    match out_file {
+        Some(out_file) => {
+            out_file.write_text_file(markdown)?;
+            println!(
+                "Wrote deck {} to {}, which rendering writes to {}",
+                rendered.deck,
+                out_file.display(),
+                rendered.path.display()
+            );
+        }
+        None => print!("{markdown}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    date::Date,
+    io::PathExt,
+    schedule::{Schedule, ScheduleOptions, WorkingHours},
+    url::BaseUrl,
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ics,
+    Markdown,
+    Agenda,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ics" => Ok(Self::Ics),
+            "md" => Ok(Self::Markdown),
+            "agenda" => Ok(Self::Agenda),
+            _ => Err(format!(
+                "unknown schedule format '{s}', expected ics, md or agenda"
+            )),
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "format",
+        default_value = "ics",
+        help = "The format of the schedule: ics for calendars, md for a Markdown agenda, or agenda for a run-sheet with the times of the topics and exercises of each session"
+    )]
+    format: Format,
+    #[arg(
+        long = "start-date",
+        value_name = "YYYY-MM-DD",
+        help = "The first day of the course"
+    )]
+    start_date: Date,
+    #[arg(
+        long = "hours",
+        value_name = "HH:MM-HH:MM",
+        default_value_t = WorkingHours::default(),
+        help = "The hours of each day sessions are scheduled in"
+    )]
+    hours: WorkingHours,
+    #[arg(
+        long = "minutes-per-day",
+        value_name = "MINUTES",
+        help = "The minutes of sessions per day, if less than the working hours, to leave room for breaks"
+    )]
+    minutes_per_day: Option<u32>,
+    #[arg(
+        long = "weekends",
+        help = "Schedule sessions on Saturdays and Sundays too"
+    )]
+    weekends: bool,
+    #[arg(
+        long = "skip-date",
+        value_name = "YYYY-MM-DD",
+        help = "A day without sessions, like a holiday. Can be given more than once"
+    )]
+    skip_dates: Vec<Date>,
+    #[arg(
+        long = "base-url",
+        value_name = "URL",
+        default_value = "/",
+        help = "The URL the output is deployed at, for the links to the slides and the book"
+    )]
+    base_url: BaseUrl,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "Write the schedule to this file instead of printing it"
+    )]
+    out_file: Option<PathBuf>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        format,
+        start_date,
+        hours,
+        minutes_per_day,
+        weekends,
+        skip_dates,
+        base_url,
+        out_file,
+    } = args;
+    if minutes_per_day == Some(0) {
+        return Err(ModModError::usage("--minutes-per-day must be more than 0"));
+    }
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let options = ScheduleOptions {
+        start_date,
+        hours,
+        minutes_per_day,
+        weekends,
+        skip_dates,
+        base_url,
+    };
+    let schedule = Schedule::lay_out(&track, &options).change_context(ModModError::default())?;
+    let output = match format {
+        Format::Ics => schedule.to_ics(Date::today()),
+        Format::Markdown => schedule.to_markdown(),
+        Format::Agenda => {
+            for (session, warning) in schedule.warnings() {
+                tracing::warn!("{session}: {warning}");
+            }
+            schedule.to_agenda()
+        }
+    };
+
+    match out_file {
+        Some(out_file) => {
+            out_file.write_text_file(output)?;
+            println!(
+                "Wrote the schedule of {} session(s) over {} day(s) to {}",
+                schedule.events.len(),
+                schedule.days(),
+                out_file.display()
+            );
+        }
+        None => print!("{output}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    archive::{self, ArchiveFormat},
+    manifest::RENDER_MANIFEST_FILE,
+    scorm::{check_manifest, ScormOptions, ScormVersion},
+    FailurePolicy, Track,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "from",
+        value_name = "DIR",
+        help = "The output folder the track was rendered into, with its slides and book built"
+    )]
+    rendered_dir: PathBuf,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The package to write, a .zip file",
+        default_value = "course-scorm.zip"
+    )]
+    out_file: PathBuf,
+    #[arg(
+        long = "scorm-version",
+        value_name = "VERSION",
+        default_value = "2004",
+        help = "The version of SCORM the package is made for: 1.2 or 2004"
+    )]
+    version: ScormVersion,
+    #[arg(
+        long = "build",
+        help = "Build the slides with their npm scripts and the book with mdbook first"
+    )]
+    build: bool,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        rendered_dir,
+        out_file,
+        version,
+        build,
+    } = args;
+    if !rendered_dir.join(RENDER_MANIFEST_FILE).exists() {
+        return Err(ModModError::usage(format!(
+            "{} has no {RENDER_MANIFEST_FILE}, so it isn't the output folder of a track",
+            rendered_dir.display()
+        )));
+    }
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let package = track
+        .scorm(
+            &rendered_dir,
+            ScormOptions {
+                layout: &track.output,
+                version,
+                build,
+            },
+        )
+        .change_context(ModModError::default())?;
+
+    archive::write_archive(&package.entries, &out_file, ArchiveFormat::Zip)
+        .change_context(ModModError::default())?;
+    if !modmod::io::writes_discarded_at(&out_file) {
+        // Catches the package not matching what was collected before it's imported
+        let packed = archive::read_archive(&out_file, ArchiveFormat::Zip)
+            .change_context(ModModError::default())?;
+        check_manifest(&packed).change_context(ModModError::default())?;
+        if !archive::same_entries(&packed, &package.entries)
+            .change_context(ModModError::default())?
+        {
+            return Err(ModModError::report().attach_printable(format!(
+                "The package at {} doesn't match the built slides and book",
+                out_file.display()
+            )));
+        }
+    }
+    println!(
+        "Wrote SCORM {version} package {} with {} unit(s), {} chapter(s) and {} deck(s)",
+        out_file.display(),
+        package.units,
+        package.chapters,
+        package.decks
+    );
+    Ok(())
+}
@@ -0,0 +1,417 @@
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::Track;
+
+use crate::ModModError;
+
+const HELP: &str =
+    "up/down move, space select, right/left expand/collapse, a all, enter render, q cancel";
+
+/// Lines of the terminal taken by the title, the help and the message below the tree
+const CHROME_LINES: usize = 4;
+
+/// Fails with guidance on what to do instead if stdin or stdout isn't a terminal
+pub fn ensure_terminal() -> Result<(), ModModError> {
+    if io::stdin().is_terminal() && io::stdout().is_terminal() {
+        return Ok(());
+    }
+    Err(ModModError::usage(
+        "--interactive needs a terminal to show the selection in. Pass the units to render with --units <SELECTOR> instead",
+    ))
+}
+
+/// Lets the user pick units of the track in a tree of its modules, units and exercises.
+/// The units that match `preselected` start out selected. Returns the ids of the selected
+/// units, which is empty if all of them are selected, or `None` if the selection was
+/// cancelled.
+pub fn select_units(
+    track: &Track,
+    preselected: &[String],
+) -> Result<Option<Vec<String>>, ModModError> {
+    let mut selection = Selection::new(track, preselected);
+    if selection.modules.iter().all(|m| m.units.is_empty()) {
+        return Err(ModModError::usage("The track has no units to select"));
+    }
+
+    let terminal = RawTerminal::enter()?;
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        selection
+            .draw(&mut stdout, terminal.rows)
+            .into_report()
+            .change_context(ModModError::default())?;
+        selection.message = None;
+        match read_key(&mut stdin)? {
+            Key::Up => selection.move_cursor(false),
+            Key::Down => selection.move_cursor(true),
+            Key::Left => selection.set_expanded(false),
+            Key::Right => selection.set_expanded(true),
+            Key::Toggle => selection.toggle(),
+            Key::All => selection.toggle_all(),
+            Key::Confirm if selection.selected().is_empty() => {
+                selection.message = Some("Select at least one unit, or press q to cancel");
+            }
+            Key::Confirm => break,
+            Key::Cancel => return Ok(None),
+            Key::Other => {}
+        }
+    }
+    drop(terminal);
+
+    if selection.all_selected() {
+        return Ok(Some(vec![]));
+    }
+    Ok(Some(
+        selection.selected().into_iter().map(String::from).collect(),
+    ))
+}
+
+/// The command line that renders the same as this one, with the selected units instead of
+/// `--interactive`
+pub fn command_line(units: &[String]) -> String {
+    let mut args = vec!["modmod".to_string()];
+    let mut given = std::env::args().skip(1);
+    while let Some(arg) = given.next() {
+        match arg.as_str() {
+            "-i" | "--interactive" => {}
+            "--units" => {
+                given.next();
+            }
+            _ if arg.starts_with("--units=") => {}
+            _ => args.push(arg),
+        }
+    }
+    for unit in units {
+        args.extend(["--units".to_string(), unit.clone()]);
+    }
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quotes an argument for a POSIX shell, if it needs to be
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+struct ModuleItem {
+    title: String,
+    units: Vec<UnitItem>,
+    expanded: bool,
+}
+
+struct UnitItem {
+    id: String,
+    title: String,
+    exercises: Vec<String>,
+    selected: bool,
+    expanded: bool,
+}
+
+/// A line of the tree, by the indices of what it shows
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Module(usize),
+    Unit(usize, usize),
+    Exercise(usize, usize, usize),
+}
+
+struct Selection {
+    modules: Vec<ModuleItem>,
+    /// The row the cursor is on, which is never an exercise
+    cursor: Row,
+    /// The first row that is shown, when the tree doesn't fit the terminal
+    offset: usize,
+    message: Option<&'static str>,
+}
+
+impl Selection {
+    fn new(track: &Track, preselected: &[String]) -> Self {
+        let modules = track
+            .modules
+            .iter()
+            .map(|module| {
+                let label = &module.data.label;
+                let units = module
+                    .data
+                    .units
+                    .iter()
+                    .map(|unit| UnitItem {
+                        id: unit.data.id(),
+                        title: format!("{} {}", label.qualify(unit.index), unit.data.name),
+                        exercises: unit
+                            .data
+                            .topics
+                            .iter()
+                            .flat_map(|topic| topic.data.exercises.iter())
+                            .map(|exercise| exercise.data.name.clone())
+                            .collect(),
+                        selected: preselected.iter().any(|s| module.data.selects(unit, s)),
+                        expanded: false,
+                    })
+                    .collect();
+                ModuleItem {
+                    title: format!("{} {}", label.display, module.data.name)
+                        .trim()
+                        .to_string(),
+                    units,
+                    expanded: true,
+                }
+            })
+            .collect();
+        Self {
+            modules,
+            cursor: Row::Module(0),
+            offset: 0,
+            message: None,
+        }
+    }
+
+    /// The rows that are shown, given which modules and units are expanded
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        for (m, module) in self.modules.iter().enumerate() {
+            rows.push(Row::Module(m));
+            if !module.expanded {
+                continue;
+            }
+            for (u, unit) in module.units.iter().enumerate() {
+                rows.push(Row::Unit(m, u));
+                if unit.expanded {
+                    rows.extend((0..unit.exercises.len()).map(|e| Row::Exercise(m, u, e)));
+                }
+            }
+        }
+        rows
+    }
+
+    fn move_cursor(&mut self, down: bool) {
+        let rows: Vec<_> = self
+            .rows()
+            .into_iter()
+            .filter(|row| !matches!(row, Row::Exercise(..)))
+            .collect();
+        let Some(i) = rows.iter().position(|row| *row == self.cursor) else {
+            return;
+        };
+        let i = match down {
+            true => (i + 1).min(rows.len() - 1),
+            false => i.saturating_sub(1),
+        };
+        self.cursor = rows[i];
+    }
+
+    /// Expands or collapses the row under the cursor. Collapsing a unit that is already
+    /// collapsed collapses its module.
+    fn set_expanded(&mut self, expanded: bool) {
+        match self.cursor {
+            Row::Module(m) => self.modules[m].expanded = expanded,
+            Row::Unit(m, u) => {
+                let unit = &mut self.modules[m].units[u];
+                if expanded || unit.expanded {
+                    unit.expanded = expanded;
+                } else {
+                    self.modules[m].expanded = false;
+                    self.cursor = Row::Module(m);
+                }
+            }
+            Row::Exercise(..) => {}
+        }
+    }
+
+    /// Selects or deselects the unit under the cursor, or all units of the module under it
+    fn toggle(&mut self) {
+        match self.cursor {
+            Row::Module(m) => {
+                let units = &mut self.modules[m].units;
+                let selected = !units.iter().all(|unit| unit.selected);
+                units.iter_mut().for_each(|unit| unit.selected = selected);
+            }
+            Row::Unit(m, u) => {
+                let unit = &mut self.modules[m].units[u];
+                unit.selected = !unit.selected;
+            }
+            Row::Exercise(..) => {}
+        }
+    }
+
+    fn toggle_all(&mut self) {
+        let selected = !self.all_selected();
+        self.modules
+            .iter_mut()
+            .flat_map(|module| module.units.iter_mut())
+            .for_each(|unit| unit.selected = selected);
+    }
+
+    fn all_selected(&self) -> bool {
+        self.modules
+            .iter()
+            .flat_map(|module| module.units.iter())
+            .all(|unit| unit.selected)
+    }
+
+    /// The ids of the selected units, in track order
+    fn selected(&self) -> Vec<&str> {
+        self.modules
+            .iter()
+            .flat_map(|module| module.units.iter())
+            .filter(|unit| unit.selected)
+            .map(|unit| unit.id.as_str())
+            .collect()
+    }
+
+    /// Redraws the whole screen. Lines end in `\r\n`, as the terminal is in raw mode.
+    fn draw(&mut self, out: &mut impl Write, terminal_rows: usize) -> io::Result<()> {
+        let rows = self.rows();
+        let cursor = rows.iter().position(|row| *row == self.cursor).unwrap_or(0);
+        let height = terminal_rows.saturating_sub(CHROME_LINES).max(1);
+        if cursor < self.offset {
+            self.offset = cursor;
+        } else if cursor >= self.offset + height {
+            self.offset = cursor + 1 - height;
+        }
+
+        write!(out, "\x1b[H\x1b[2J")?;
+        write!(out, "Select the units to render\r\n{HELP}\r\n\r\n")?;
+        for (i, row) in rows.iter().enumerate().skip(self.offset).take(height) {
+            let pointer = if i == cursor { ">" } else { " " };
+            match *row {
+                Row::Module(m) => {
+                    let module = &self.modules[m];
+                    let selected = module.units.iter().filter(|unit| unit.selected).count();
+                    let check = match selected {
+                        0 => "[ ]",
+                        n if n == module.units.len() => "[x]",
+                        _ => "[-]",
+                    };
+                    let fold = if module.expanded { "" } else { " ..." };
+                    write!(out, "{pointer} {check} {}{fold}\r\n", module.title)?;
+                }
+                Row::Unit(m, u) => {
+                    let unit = &self.modules[m].units[u];
+                    let check = if unit.selected { "[x]" } else { "[ ]" };
+                    let fold = match (unit.expanded, unit.exercises.len()) {
+                        (false, n) if n > 0 => format!(" ({n} exercise(s))"),
+                        _ => String::new(),
+                    };
+                    write!(out, "{pointer}     {check} {}{fold}\r\n", unit.title)?;
+                }
+                Row::Exercise(m, u, e) => {
+                    let exercise = &self.modules[m].units[u].exercises[e];
+                    write!(out, "            exercise {exercise}\r\n")?;
+                }
+            }
+        }
+        if let Some(message) = self.message {
+            write!(out, "\r\n{message}\r\n")?;
+        }
+        out.flush()
+    }
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Toggle,
+    All,
+    Confirm,
+    Cancel,
+    Other,
+}
+
+fn read_key(stdin: &mut impl Read) -> Result<Key, ModModError> {
+    let mut read_byte = || {
+        let mut byte = [0];
+        stdin
+            .read_exact(&mut byte)
+            .into_report()
+            .change_context(ModModError::default())
+            .map(|_| byte[0])
+    };
+    let key = match read_byte()? {
+        b'k' => Key::Up,
+        b'j' => Key::Down,
+        b'h' => Key::Left,
+        b'l' => Key::Right,
+        b' ' => Key::Toggle,
+        b'a' => Key::All,
+        b'\r' | b'\n' => Key::Confirm,
+        // Ctrl-C doesn't interrupt in raw mode, so it's read like any other key
+        b'q' | 3 => Key::Cancel,
+        // The arrow keys send `ESC [ A` to `ESC [ D`
+        0x1b if read_byte()? == b'[' => match read_byte()? {
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            _ => Key::Other,
+        },
+        _ => Key::Other,
+    };
+    Ok(key)
+}
+
+/// Keeps the terminal in raw mode, on the alternate screen, until it's dropped
+struct RawTerminal {
+    /// The settings to restore, as given by `stty -g`
+    saved: String,
+    rows: usize,
+}
+
+impl RawTerminal {
+    fn enter() -> Result<Self, ModModError> {
+        let saved = stty(&["-g"])?;
+        let rows = stty(&["size"])?
+            .split_whitespace()
+            .next()
+            .and_then(|rows| rows.parse().ok())
+            // Terminals that don't know their size report 0
+            .filter(|&rows| rows > 0)
+            .unwrap_or(24);
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        Ok(Self { saved, rows })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Runs `stty` on the terminal, which is how the keys are read one at a time without a
+/// dependency on a terminal library
+fn stty(args: &[&str]) -> Result<String, ModModError> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .into_report()
+        .attach_printable(
+            "Unable to run stty, which --interactive needs. Pass the units to render with --units <SELECTOR> instead",
+        )
+        .change_context(ModModError::default())?;
+    if !output.status.success() {
+        return Err(ModModError::report().attach_printable(format!(
+            "`stty {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,403 @@
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    io::PathExt,
+    manifest::{ArtifactKind, RenderManifest, RENDER_MANIFEST_FILE},
+    update::UpdateReport,
+};
+
+use crate::{gen, watch, ModModError};
+
+/// How long children get to stop on their own after Ctrl-C, before they are killed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder the output will be written to, and kept up to date"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "deck",
+        help = "The deck to serve, by its prefix like 3_2 or its unit id. The first deck by default"
+    )]
+    deck: Option<String>,
+    #[arg(
+        long = "port",
+        help = "Port of the index page that links to the slides and the book",
+        default_value_t = 8000
+    )]
+    port: u16,
+    #[arg(long = "slides-port", default_value_t = 3030)]
+    slides_port: u16,
+    #[arg(long = "book-port", default_value_t = 3000)]
+    book_port: u16,
+    #[arg(
+        long = "slides-command",
+        help = "Command that serves the deck, run in the slides folder. {deck} and {port} are replaced",
+        default_value = "npx slidev {deck} --port {port}"
+    )]
+    slides_command: String,
+    #[arg(
+        long = "book-command",
+        help = "Command that serves the book, run in the book folder. {port} is replaced",
+        default_value = "mdbook serve --port {port}"
+    )]
+    book_command: String,
+    #[arg(
+        long = "debounce",
+        value_name = "MILLISECONDS",
+        help = "Wait until the content hasn't changed for this long before rendering",
+        default_value_t = 300
+    )]
+    debounce: u64,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        out_dir,
+        deck,
+        port,
+        slides_port,
+        book_port,
+        slides_command,
+        book_command,
+        debounce,
+        render,
+    } = args;
+    if render.definition_paths().count() != 1 {
+        return Err(ModModError::usage("serve renders exactly one track"));
+    }
+
+    let index = Arc::new(Mutex::new(Index {
+        slides_port,
+        book_port,
+        decks: vec![],
+        deck: None,
+    }));
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .into_report()
+        .attach_printable_lazy(|| format!("Unable to serve the index on port {port}"))
+        .change_context(ModModError::default())?;
+    let shared = index.clone();
+    thread::spawn(move || serve_index(listener, &shared));
+    println!("Serving the index at http://localhost:{port}/");
+
+    interrupt::catch();
+    let server = RefCell::new(Server {
+        out_dir: out_dir.clone(),
+        deck,
+        slides: Child::new("slides", slides_command, slides_port),
+        book: Child::new("book", book_command, book_port),
+        index,
+        failure: None,
+    });
+    let result = watch::watch(
+        &render,
+        content_dir,
+        &out_dir,
+        Duration::from_millis(debounce),
+        |report| {
+            let mut server = server.borrow_mut();
+            if let Err(e) = server.rendered(report) {
+                server.failure = Some(e);
+            }
+        },
+        || {
+            if interrupt::interrupted() {
+                return false;
+            }
+            let mut server = server.borrow_mut();
+            server.slides.check();
+            server.book.check();
+            server.failure.is_none()
+        },
+    );
+
+    let mut server = server.into_inner();
+    println!("Stopping the dev servers");
+    server.slides.stop(SHUTDOWN_TIMEOUT);
+    server.book.stop(SHUTDOWN_TIMEOUT);
+    match server.failure {
+        Some(e) => Err(e),
+        None => result,
+    }
+}
+
+struct Server {
+    out_dir: PathBuf,
+    deck: Option<String>,
+    slides: Child,
+    book: Child,
+    index: Arc<Mutex<Index>>,
+    /// Why the dev servers couldn't be started, which stops serving
+    failure: Option<error_stack::Report<ModModError>>,
+}
+
+impl Server {
+    /// (Re)starts the dev servers that are affected by the changes of a render. Slidev and
+    /// mdbook pick up changes to decks and book pages themselves, but not to the slides
+    /// package and the book configuration.
+    fn rendered(&mut self, report: &UpdateReport) -> Result<(), ModModError> {
+        let manifest: RenderManifest = serde_json::from_str(
+            &self
+                .out_dir
+                .join(RENDER_MANIFEST_FILE)
+                .read_to_string::<ModModError>()?,
+        )
+        .into_report()
+        .change_context(ModModError::default())?;
+        // Paths in the manifest and the report have forward slashes
+        let decks: Vec<_> = manifest
+            .main_artifacts()
+            .filter(|a| a.kind == ArtifactKind::Deck)
+            .map(|a| a.path.as_str())
+            .collect();
+        let deck = match &self.deck {
+            Some(selector) => decks
+                .iter()
+                .find(|d| selects(d, selector))
+                .ok_or_else(|| ModModError::usage(format!("No deck matches '{selector}'")))?,
+            None => decks.first().ok_or_else(|| {
+                ModModError::report().attach_printable("The track has no slide decks")
+            })?,
+        };
+        let changed = |path: &str| {
+            let path = path.to_string();
+            report.added.contains(&path) || report.updated.contains(&path)
+        };
+
+        let (slides_dir, deck_file) = deck.rsplit_once('/').unwrap_or((".", deck));
+        if !self.slides.is_running()
+            || changed(&format!("{slides_dir}/package.json"))
+            || report.added.contains(&deck.to_string())
+        {
+            self.slides
+                .restart(&self.out_dir.join(slides_dir), deck_file)?;
+        }
+        // Chapters are written to the `src` folder of the book
+        let book_dir = manifest
+            .main_artifacts()
+            .find(|a| a.kind == ArtifactKind::Chapter)
+            .and_then(|a| Path::new(&a.path).parent()?.parent().map(Path::to_path_buf));
+        if let Some(book_dir) = book_dir {
+            let book_toml = book_dir.join("book.toml");
+            if !self.book.is_running() || changed(&book_toml.to_string_lossy()) {
+                self.book.restart(&self.out_dir.join(book_dir), deck_file)?;
+            }
+        }
+
+        let mut index = self.index.lock().unwrap();
+        index.deck = Some(file_stem(deck));
+        index.decks = decks.iter().map(|d| file_stem(d)).collect();
+        Ok(())
+    }
+}
+
+/// Whether the deck at `path` is selected by its prefix, like `3_2`, or by its unit id
+fn selects(path: &str, selector: &str) -> bool {
+    let stem = file_stem(path);
+    stem == selector
+        || stem.starts_with(&format!("{selector}-"))
+        || stem.ends_with(&format!("-{selector}"))
+}
+
+fn file_stem(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap();
+    name.strip_suffix(".md").unwrap_or(name).to_string()
+}
+
+/// A dev server that is run as a child process. Its output is forwarded, with its name
+/// in front of each line.
+struct Child {
+    name: &'static str,
+    command: String,
+    port: u16,
+    process: Option<process::Child>,
+}
+
+impl Child {
+    fn new(name: &'static str, command: String, port: u16) -> Self {
+        Self {
+            name,
+            command,
+            port,
+            process: None,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.process.is_some()
+    }
+
+    fn restart(&mut self, dir: &Path, deck: &str) -> Result<(), ModModError> {
+        if self.is_running() {
+            println!("Restarting the {} server", self.name);
+            self.stop(Duration::ZERO);
+        }
+        let words: Vec<String> = self
+            .command
+            .split_whitespace()
+            .map(|word| {
+                word.replace("{deck}", deck)
+                    .replace("{port}", &self.port.to_string())
+            })
+            .collect();
+        let Some((program, args)) = words.split_first() else {
+            return Err(ModModError::usage(format!(
+                "The {} command is empty",
+                self.name
+            )));
+        };
+        let mut child = process::Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to run `{}`", words.join(" ")))
+            .change_context(ModModError::default())?;
+        let name = self.name;
+        let stdout = child.stdout.take().unwrap();
+        thread::spawn(move || forward(name, stdout, false));
+        let stderr = child.stderr.take().unwrap();
+        thread::spawn(move || forward(name, stderr, true));
+        println!(
+            "Started the {} server on http://localhost:{}/",
+            self.name, self.port
+        );
+        self.process = Some(child);
+        Ok(())
+    }
+
+    /// Reports it when the child stopped on its own. It isn't restarted until the next
+    /// change that would restart it, so that a broken command doesn't loop.
+    fn check(&mut self) {
+        let Some(process) = self.process.as_mut() else {
+            return;
+        };
+        if let Ok(Some(status)) = process.try_wait() {
+            tracing::error!("The {} server stopped: {status}", self.name);
+            self.process = None;
+        }
+    }
+
+    /// Gives the child `timeout` to exit, which it does on Ctrl-C as it gets the signal
+    /// too, and kills it otherwise
+    fn stop(&mut self, timeout: Duration) {
+        let Some(mut process) = self.process.take() else {
+            return;
+        };
+        let started = Instant::now();
+        while started.elapsed() < timeout {
+            if let Ok(Some(_)) = process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        self.stop(Duration::ZERO);
+    }
+}
+
+fn forward(name: &str, output: impl Read, is_stderr: bool) {
+    for line in BufReader::new(output).lines().map_while(|line| line.ok()) {
+        if is_stderr {
+            eprintln!("[{name}] {line}");
+        } else {
+            println!("[{name}] {line}");
+        }
+    }
+}
+
+/// What the index page shows
+struct Index {
+    slides_port: u16,
+    book_port: u16,
+    decks: Vec<String>,
+    /// The deck that is served
+    deck: Option<String>,
+}
+
+impl Index {
+    fn html(&self) -> String {
+        let decks: String = self
+            .decks
+            .iter()
+            .map(|deck| match Some(deck) == self.deck.as_ref() {
+                true => format!(
+                    "<li><a href=\"http://localhost:{}/\">{deck}</a> (served)</li>\n",
+                    self.slides_port
+                ),
+                false => format!("<li>{deck}</li>\n"),
+            })
+            .collect();
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>modmod serve</title></head>\n<body>\n\
+            <h1>modmod serve</h1>\n\
+            <p><a href=\"http://localhost:{}/\">Book</a></p>\n\
+            <h2>Slides</h2>\n<ul>\n{decks}</ul>\n\
+            <p>Pass <code>--deck</code> to serve another deck.</p>\n</body>\n</html>\n",
+            self.book_port
+        )
+    }
+}
+
+/// Answers every request with the index page
+fn serve_index(listener: TcpListener, index: &Mutex<Index>) {
+    for mut stream in listener.incoming().flatten() {
+        // The request itself doesn't matter
+        let mut request = [0; 4096];
+        let _ = stream.read(&mut request);
+        let html = index.lock().unwrap().html();
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{html}",
+            html.len()
+        );
+    }
+}
+
+/// Notices Ctrl-C, so that the dev servers can be shut down before exiting
+mod interrupt {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    /// Catches Ctrl-C and termination requests. If they can't be caught, they stop modmod
+    /// as usual, which leaves the dev servers running.
+    pub fn catch() {
+        if let Err(e) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+            tracing::error!(
+                "Unable to catch Ctrl-C, so the dev servers won't be stopped with modmod: {e}"
+            );
+        }
+    }
+
+    pub fn interrupted() -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
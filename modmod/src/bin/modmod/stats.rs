@@ -0,0 +1,39 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{stats::TrackStats, FailurePolicy, Track};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(long = "json", help = "Output the statistics as JSON")]
+    json: bool,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        json,
+    } = args;
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let stats = TrackStats::collect(&track).change_context(ModModError::default())?;
+
+    if json {
+        let json = serde_json::to_string_pretty(&stats)
+            .into_report()
+            .change_context(ModModError::default())?;
+        println!("{json}");
+    } else {
+        print!("{stats}");
+    }
+
+    Ok(())
+}
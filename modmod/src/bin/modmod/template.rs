@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use error_stack::{Result, ResultExt};
+use modmod::{FailurePolicy, Track};
+
+use crate::ModModError;
+
+/// Lines of each value that are shown
+const PREVIEW_LINES: usize = 2;
+
+#[derive(Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: TemplateCommand,
+}
+
+#[derive(Subcommand)]
+enum TemplateCommand {
+    /// List the placeholders of the slide template of a deck, and what they're replaced by
+    Inspect(InspectArgs),
+}
+
+#[derive(Parser)]
+struct InspectArgs {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "deck",
+        help = "The deck to inspect: its number like 3.2, its prefix like 3_2, or the id of its unit"
+    )]
+    deck: String,
+    #[arg(
+        long = "theme",
+        help = "The name of the Slidev theme to use in generated slide decks",
+        default_value = "teach-rs"
+    )]
+    slide_theme: String,
+    #[arg(
+        long = "profile",
+        add = crate::completions::profiles(),
+        help = "Resolve conditional content for this profile, as defined in the track definition"
+    )]
+    profile: Option<String>,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let TemplateCommand::Inspect(InspectArgs {
+        track_toml_path,
+        deck,
+        slide_theme,
+        profile,
+    }) = args.command;
+
+    let loader = crate::loader(FailurePolicy::Strict, content_dir);
+    let track = Track::load_toml_defs_with(&[track_toml_path], loader)
+        .pop()
+        .unwrap()
+        .change_context(ModModError::default())?;
+    let inspection = track
+        .inspect_template(&deck, &slide_theme, profile.as_deref())
+        .change_context(ModModError::default())?;
+
+    println!("Deck {}", inspection.deck);
+    match &inspection.template {
+        Some(template) => println!("Template: {}", template.display()),
+        None => println!("Template: built-in"),
+    }
+    for placeholder in inspection.placeholders.iter() {
+        let used = if placeholder.used { "used" } else { "unused" };
+        println!(
+            "\n{} ({used})\n  {}",
+            placeholder.marker, placeholder.description
+        );
+        let lines: Vec<_> = placeholder.value.lines().collect();
+        if lines.iter().all(|line| line.trim().is_empty()) {
+            println!("  | (empty)");
+            continue;
+        }
+        for line in lines.iter().take(PREVIEW_LINES) {
+            println!("  | {line}");
+        }
+        if lines.len() > PREVIEW_LINES {
+            println!("  | ... {} more line(s)", lines.len() - PREVIEW_LINES);
+        }
+    }
+    inspection.warnings.iter().for_each(modmod::log::warning);
+    Ok(())
+}
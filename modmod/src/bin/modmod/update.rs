@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+
+use modmod::log;
+
+use crate::{gen, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder with the earlier generated output to update"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "emit-patches",
+        value_name = "PATCH_DIR",
+        help = "Don't change the output folder, but write the changes as patches into this folder, with a series file that lists them in order"
+    )]
+    patch_dir: Option<PathBuf>,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        out_dir,
+        patch_dir,
+        render,
+    } = args;
+
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_update_tmp")?;
+    gen::render_tracks(render, &tmp_dir, true, false, content_dir)?;
+    modmod::update::check_course_versions(&tmp_dir, &out_dir)
+        .change_context(ModModError::default())?
+        .iter()
+        .for_each(log::warning);
+    let report = match &patch_dir {
+        Some(patch_dir) => modmod::update::emit_patches(&tmp_dir, &out_dir, patch_dir)
+            .map(|series| series.to_string()),
+        None => modmod::update::update(&tmp_dir, &out_dir).map(|report| report.to_string()),
+    }
+    .change_context(ModModError::default())?;
+    println!("{report}");
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+
+use crate::{gen, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder with the published output to verify"
+    )]
+    out_dir: PathBuf,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+/// Returns whether the published output drifted from its sources
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<bool, ModModError> {
+    let Args { out_dir, render } = args;
+
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_verify_tmp")?;
+    gen::render_tracks(render, &tmp_dir, true, false, content_dir)?;
+    let drift = modmod::update::verify(&tmp_dir, &out_dir).change_context(ModModError::default())?;
+    println!("{drift}");
+
+    Ok(!drift.is_empty())
+}
@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use clap::Parser;
+use error_stack::{IntoReport, Result, ResultExt};
+use notify_debouncer_mini::{
+    new_debouncer_opt,
+    notify::{self, PollWatcher, RecommendedWatcher, RecursiveMode},
+    Config, DebounceEventResult, Debouncer,
+};
+
+use modmod::update::UpdateReport;
+
+use crate::{gen, ModModError};
+
+/// How often `keep_watching` is asked whether to go on while nothing changes
+const CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the content is scanned for changes when the file system doesn't report them
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Folders in the content tree that don't hold content
+const IGNORED_DIRS: &[&str] = &["target", "node_modules"];
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder the output will be written to, and kept up to date"
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "debounce",
+        value_name = "MILLISECONDS",
+        help = "Wait until the content hasn't changed for this long before rendering",
+        default_value_t = 300
+    )]
+    debounce: u64,
+    #[command(flatten)]
+    render: gen::RenderArgs,
+}
+
+pub fn run(args: Args, content_dir: Option<&Path>) -> Result<(), ModModError> {
+    let Args {
+        out_dir,
+        debounce,
+        render,
+    } = args;
+    watch(
+        &render,
+        content_dir,
+        &out_dir,
+        Duration::from_millis(debounce),
+        |_| {},
+        || true,
+    )
+}
+
+/// Renders the tracks into `out_dir`, and again whenever their content changes.
+/// `on_render` gets what changed in the output after each render that succeeded, and
+/// `keep_watching` is asked between looking for changes whether to go on.
+pub fn watch(
+    render: &gen::RenderArgs,
+    content_dir: Option<&Path>,
+    out_dir: &Path,
+    debounce: Duration,
+    mut on_render: impl FnMut(&UpdateReport),
+    mut keep_watching: impl FnMut() -> bool,
+) -> Result<(), ModModError> {
+    if render
+        .definition_paths()
+        .any(|path| path == Path::new("-") || modmod::load::is_synthetic_source(path))
+    {
+        return Err(ModModError::usage(
+            "Tracks that are read from stdin or a URL can't be watched, as they aren't in a folder",
+        ));
+    }
+    let tmp_dir = modmod::io::scratch_dir::<ModModError>("modmod_watch_tmp")?;
+    // Watch the folders the definitions are in, which hold the content they refer to
+    let roots: Vec<PathBuf> = render
+        .definition_paths()
+        .map(|path| {
+            path.canonicalize()
+                .into_report()
+                .attach_printable_lazy(|| format!("Unable to find {}", path.display()))
+                .change_context(ModModError::default())
+                .map(|path| path.parent().unwrap().to_path_buf())
+        })
+        .collect::<Result<_, _>>()?;
+
+    if let Some(report) = rerender(render, content_dir, &tmp_dir, out_dir, true) {
+        on_render(&report);
+    }
+    // Don't react to our own output, in case it's written into the content tree
+    let ignored = [out_dir, &tmp_dir].map(|dir| dir.canonicalize().unwrap_or(dir.to_path_buf()));
+    let (sender, events) = mpsc::channel();
+    // Kept for as long as the content is watched
+    let _watcher = ContentWatcher::new(&roots, debounce, sender)?;
+    println!("Watching {} for changes", display_paths(&roots));
+    while keep_watching() {
+        let Some(changed) = changes(&events, &roots, &ignored)? else {
+            continue;
+        };
+        let definitions = changed.iter().any(|path| is_definition(path));
+        println!(
+            "\n{} changed: {}",
+            if definitions {
+                "Definitions"
+            } else {
+                "Content"
+            },
+            display_paths(&changed)
+        );
+        if definitions {
+            println!("Reloading the track");
+        }
+        if let Some(report) = rerender(render, content_dir, &tmp_dir, out_dir, false) {
+            on_render(&report);
+        }
+    }
+    Ok(())
+}
+
+/// Watches the content for changes, through the file system's notifications. Where
+/// those aren't available, like when the limit of inotify watches is reached, it falls
+/// back to scanning the content for changes every [`POLL_INTERVAL`].
+enum ContentWatcher {
+    Notify(Debouncer<RecommendedWatcher>),
+    Poll(Debouncer<PollWatcher>),
+}
+
+impl ContentWatcher {
+    /// Watches `roots`, and sends the changes to `sender` once a path hasn't changed for
+    /// `debounce`
+    fn new(
+        roots: &[PathBuf],
+        debounce: Duration,
+        sender: mpsc::Sender<DebounceEventResult>,
+    ) -> Result<Self, ModModError> {
+        let config = Config::default().with_timeout(debounce);
+        let notified = new_debouncer_opt(config.clone(), sender.clone())
+            .and_then(|debouncer| Self::Notify(debouncer).watch(roots));
+        match notified {
+            Ok(watcher) => Ok(watcher),
+            Err(e) => {
+                tracing::warn!(
+                    "Unable to be notified of changes to the content ({e}), so it is scanned for changes every {}s instead",
+                    POLL_INTERVAL.as_secs()
+                );
+                let config = config.with_notify_config(
+                    notify::Config::default().with_poll_interval(POLL_INTERVAL),
+                );
+                new_debouncer_opt(config, sender)
+                    .and_then(|debouncer| Self::Poll(debouncer).watch(roots))
+                    .into_report()
+                    .attach_printable("Unable to watch the content for changes")
+                    .change_context(ModModError::default())
+            }
+        }
+    }
+
+    fn watch(mut self, roots: &[PathBuf]) -> notify::Result<Self> {
+        let watcher = match &mut self {
+            Self::Notify(debouncer) => debouncer.watcher(),
+            Self::Poll(debouncer) => debouncer.watcher(),
+        };
+        for root in roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+        Ok(self)
+    }
+}
+
+/// Waits up to [`CHECK_INTERVAL`] for changes to the content, and gives the files that
+/// changed, if any did. Changes to hidden files, to files in [`IGNORED_DIRS`] and to
+/// the `ignored` folders aren't content.
+fn changes(
+    events: &Receiver<DebounceEventResult>,
+    roots: &[PathBuf],
+    ignored: &[PathBuf],
+) -> Result<Option<Vec<PathBuf>>, ModModError> {
+    let first = match events.recv_timeout(CHECK_INTERVAL) {
+        Ok(events) => events,
+        Err(RecvTimeoutError::Timeout) => return Ok(None),
+        Err(RecvTimeoutError::Disconnected) => {
+            return Err(ModModError::report().attach_printable("Stopped watching the content"))
+        }
+    };
+    let mut changed = vec![];
+    // Changes to many files, like those of a checkout, may come in more than one batch
+    for result in [first].into_iter().chain(events.try_iter()) {
+        match result {
+            Ok(events) => changed.extend(events.into_iter().map(|event| event.path)),
+            Err(e) => tracing::error!("Error watching the content: {e}"),
+        }
+    }
+    changed.retain(|path| is_content(path, roots, ignored));
+    changed.sort();
+    changed.dedup();
+    Ok((!changed.is_empty()).then_some(changed))
+}
+
+fn is_content(path: &Path, roots: &[PathBuf], ignored: &[PathBuf]) -> bool {
+    if ignored.iter().any(|dir| path.starts_with(dir)) {
+        return false;
+    }
+    let Some(relative) = roots.iter().find_map(|root| path.strip_prefix(root).ok()) else {
+        return false;
+    };
+    relative.components().all(|component| match component {
+        Component::Normal(name) => {
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && !IGNORED_DIRS.contains(&name.as_ref())
+        }
+        _ => true,
+    })
+}
+
+/// Renders the tracks and writes the files that changed into `out_dir`. Errors are reported,
+/// and not returned, so that the watcher keeps running.
+/// Only the number of files is printed for the `initial` render.
+fn rerender(
+    render: &gen::RenderArgs,
+    content_dir: Option<&Path>,
+    tmp_dir: &Path,
+    out_dir: &Path,
+    initial: bool,
+) -> Option<UpdateReport> {
+    let result =
+        gen::render_tracks(render.clone(), tmp_dir, true, false, content_dir).and_then(|()| {
+            modmod::update::update(tmp_dir, out_dir).change_context(ModModError::default())
+        });
+    let _ = fs::remove_dir_all(tmp_dir);
+    match result {
+        Ok(report) => {
+            let regenerated: Vec<_> = report.added.iter().chain(&report.updated).collect();
+            if initial {
+                println!(
+                    "Wrote {} file(s) into {}",
+                    regenerated.len(),
+                    out_dir.display()
+                );
+            } else if regenerated.is_empty() {
+                println!("No output changed");
+            } else {
+                for path in regenerated {
+                    println!("  regenerated {path}");
+                }
+            }
+            for path in report.conflicts.iter() {
+                println!(
+                    "  warning: {path} was changed in the output folder, new version written to {path}{}",
+                    modmod::update::CONFLICT_SUFFIX
+                );
+            }
+            Some(report)
+        }
+        Err(e) => {
+            tracing::error!("Error rendering track: {e:?}");
+            println!("Waiting for changes");
+            None
+        }
+    }
+}
+
+fn is_definition(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,15 +1,31 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use indoc::indoc;
 use std::{
-    collections::HashMap,
-    fmt,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt, io,
     path::{Path, PathBuf},
 };
 
-use error_stack::Result;
+use error_stack::{IntoReport, Report, Result, ResultExt};
 
 use crate::{
-    io::{PathExt, WriteExt},
+    analytics::{Analytics, BOOK_SCRIPT_FILE},
+    cache::{self, Inputs},
+    conditional::{self, Conditions},
+    diagnostic::Location,
+    estimates::Estimate,
+    io::{copy_tree, slash_path, CopyTreeOptions, PathExt, WriteExt},
+    parallel,
+    progress::{self, Phase},
+    quiz::{self, QuizMode, QuizQuestion, QUIZZES_DIR},
+    report::{self, UnitObjective, Warning},
+    slides::{slide_notes, split_slides, topic_slides},
     to_tag,
+    url::BaseUrl,
+    version::CourseVersion,
+    Course, License, ModuleLabel, OutputLayout, Session, Topic,
 };
 
 #[derive(Debug, Default)]
@@ -24,22 +40,47 @@ impl fmt::Display for RenderBookError {
 
 impl error_stack::Context for RenderBookError {}
 
-pub struct BookRenderOptions<'e, 'u> {
+pub struct BookRenderOptions<'e, 'u, 'l, 'c> {
     pub exercise_paths: &'e HashMap<PathBuf, PathBuf>,
-    pub slides_url_base: &'u str,
+    /// The URL the output folder is deployed at
+    pub base_url: &'u BaseUrl,
+    pub layout: &'l OutputLayout,
+    pub conditions: Conditions<'c>,
+    /// Whether the slides are rendered as well, so that the book can link to them
+    pub with_slides: bool,
+    /// Folder with an mdbook theme to copy into the book
+    pub theme: Option<&'l Path>,
+    /// How the quizzes at the end of the pages are rendered
+    pub quiz: QuizMode,
+    /// Whether the pages hold the slides of their topics as notes, with the objectives,
+    /// summary and further reading of their unit, see [`write_notes`]
+    pub notes: bool,
+    /// The locale the book is written in, like `en`
+    pub language: &'l str,
 }
 
 #[derive(Debug)]
 pub struct Book<'track> {
     pub title: &'track str,
+    pub course: Option<&'track Course>,
+    pub license: Option<&'track License>,
+    pub course_version: Option<&'track CourseVersion>,
+    pub analytics: Option<&'track Analytics>,
+    /// Whether near-duplicate objectives of a unit are listed once, like exact duplicates
+    pub merge_similar_objectives: bool,
     pub chapters: Vec<Chapter<'track>>,
 }
 
 impl<'track> Book<'track> {
-    pub fn builder(title: &'track str) -> BookBuilder {
+    pub fn builder(title: &'track str) -> BookBuilder<'track> {
         BookBuilder {
             book: Book {
                 title,
+                course: None,
+                license: None,
+                course_version: None,
+                analytics: None,
+                merge_similar_objectives: false,
                 chapters: vec![],
             },
         }
@@ -47,122 +88,791 @@ impl<'track> Book<'track> {
 
     pub fn render(
         &self,
-        BookRenderOptions {
-            exercise_paths,
-            slides_url_base,
-        }: BookRenderOptions,
+        options: BookRenderOptions,
         out_dir: impl AsRef<Path>,
-    ) -> Result<(), RenderBookError> {
-        let slides_url_base = slides_url_base.trim_matches('/');
-        let slides_url_base_separator = if slides_url_base.is_empty() { "" } else { "/" };
-        let book_out_dir = out_dir.as_ref().join("book");
+        warnings: &mut Vec<Warning>,
+    ) -> Result<usize, RenderBookError> {
+        let layout = options.layout;
+        let book_out_dir = out_dir.as_ref().join(layout.book());
         let book_src_dir = book_out_dir.join("src");
         book_src_dir.create_dir_all()?;
 
         let book_toml_path = book_out_dir.join("book.toml");
-        let mut book_toml = book_toml_path.create_file()?;
+        let mut book_toml = book_toml_path.create_text_file()?;
+        book_toml.write_fmt(format_args!("# Generated by modmod {}\n", crate::VERSION))?;
+        if let Some(license) = self.license {
+            book_toml.write_fmt(format_args!(
+                "# SPDX-License-Identifier: {}\n",
+                license.spdx
+            ))?;
+        }
         book_toml.write_all(format!(
             indoc! {r#"
+
                 [book]
                 title = "{}"
-                language = "en"
+                language = "{}"
                 multilingual = false
 
                 [build]
                 build-dir = "./target"
             "#},
-            self.title
+            self.title, options.language
         ))?;
 
+        // mdbook numbers the chapters itself, unless the modules are labeled otherwise
+        let own_numbering = self
+            .chapters
+            .iter()
+            .any(|c| c.label.display != c.module_index.to_string());
+        let mut html = vec![];
+        if own_numbering {
+            html.push("no-section-label = true".to_string());
+        }
+        // Lets mdbook link the 404 page correctly when the book isn't at the root of the site
+        if !options.base_url.is_root() {
+            let book_path = OutputLayout::dir_url_path(layout.book());
+            html.push(format!(
+                "site-url = \"{}\"",
+                options.base_url.join_path(&book_path)
+            ));
+        }
+        if options.notes {
+            self.copy_images(&book_src_dir.join(IMAGES_DIR))?;
+        }
+        if let Some(theme) = options.theme {
+            copy_theme(theme, &book_out_dir.join("theme"))?;
+            html.push("theme = \"theme\"".to_string());
+        }
+        if let Some(analytics) = self.analytics {
+            book_out_dir
+                .join(BOOK_SCRIPT_FILE)
+                .write_text_file(analytics.script())?;
+            html.push(format!("additional-js = [\"{BOOK_SCRIPT_FILE}\"]"));
+        }
+        if !html.is_empty() {
+            book_toml.write_all(format!("\n[output.html]\n{}\n", html.join("\n")))?;
+        }
+        if options.quiz == QuizMode::MdbookQuiz && self.has_quiz() {
+            book_toml.write_all("\n[preprocessor.quiz]\n")?;
+        }
+
+        let redirects = self.redirects();
+        if !redirects.is_empty() {
+            book_toml.write_all("\n[output.html.redirect]\n")?;
+            for (from, to) in redirects {
+                book_toml.write_fmt(format_args!("\"/{from}.html\" = \"{to}\"\n"))?;
+            }
+        }
+        book_toml.commit()?;
+
         let summary_md_path = book_src_dir.join("SUMMARY.md");
 
-        let mut summary_md = summary_md_path.create_file()?;
+        let mut summary_md = summary_md_path.create_text_file()?;
         summary_md.write_all("# Summary\n\n")?;
 
-        for (chapter, chapter_i) in self.chapters.iter().zip(1..) {
+        // Only add a preface if there's something to put in it
+        let course_entries = self.course.map(Course::entries).unwrap_or_default();
+        if !course_entries.is_empty() {
+            let mut preface = book_src_dir.join("preface.md").create_text_file()?;
+            preface.write_fmt(format_args!("# {}\n\n", self.title))?;
+            for (label, value) in course_entries {
+                preface.write_fmt(format_args!("- **{label}:** {value}\n"))?;
+            }
+            preface.commit()?;
+            summary_md.write_all("[Course information](preface.md)\n\n")?;
+        }
+
+        let mut section_files = HashSet::new();
+        let mut pages = vec![];
+        for chapter in self.chapters.iter() {
+            let label = chapter.label;
             // Sadly, at the time of writing, mdbook does not allow for custom section numbering.
             // Therefore, we insert a draft chapter to keep the section numbering in sync
-            summary_md.write_fmt(format_args!("- [{}]()\n", chapter.title))?;
+            let chapter_title = match own_numbering && !label.display.is_empty() {
+                true => format!("{} {}", label.display, chapter.title),
+                false => chapter.title.to_string(),
+            };
+            summary_md.write_fmt(format_args!("- [{chapter_title}]()\n"))?;
 
-            for (section, section_i) in chapter.sections.iter().zip(1..) {
+            for section in chapter.sections.iter() {
+                let unit_label = label.qualify(section.unit_index);
+                let section_title = match own_numbering {
+                    true => format!("{unit_label} {}", section.title),
+                    false => section.title.to_string(),
+                };
+                if !section.has_page {
+                    // Keep the numbering of the other units in sync
+                    summary_md
+                        .write_fmt(format_args!("\t- [{section_title} (no book page)]()\n"))?;
+                    continue;
+                }
                 let section_file_name = Path::new(&to_tag(section.title)).with_extension("md");
+                // Section file names are made unique when the track is loaded
+                debug_assert!(
+                    section_files.insert(section_file_name.clone()),
+                    "Duplicate book section file name {}",
+                    section_file_name.display()
+                );
                 summary_md.write_fmt(format_args!(
-                    "\t- [{}]({})\n",
-                    section.title,
-                    section_file_name.to_str().unwrap()
+                    "\t- [{section_title}]({})\n",
+                    slash_path::<RenderBookError>(&section_file_name)?
                 ))?;
+                pages.push((chapter, section, book_src_dir.join(&section_file_name)));
+            }
+            summary_md.write_all("\n")?;
+        }
+        summary_md.commit()?;
 
-                let section_file_path = book_src_dir.join(&section_file_name);
-                let mut section_file = section_file_path.create_file()?;
-                section_file.write_fmt(format_args!(
-                    indoc! {r#"
-                        # Unit {chapter_i}.{section_i} - {}
+        // The pages don't depend on each other, so they are written in parallel. Their
+        // warnings are put together in the order of the pages.
+        let _phase = progress::start(Phase::Book, Some(pages.len()));
+        let page_warnings = parallel::map(&pages, |(chapter, section, path)| {
+            let mut warnings = vec![];
+            self.render_page(chapter, section, path, &options, &mut warnings)
+                .attach_printable_lazy(|| {
+                    format!(
+                        "In book page {} {} of chapter '{}'",
+                        chapter.label.qualify(section.unit_index),
+                        section.title,
+                        chapter.title
+                    )
+                })?;
+            Ok(warnings)
+        })?;
+        warnings.extend(page_warnings.into_iter().flatten());
+
+        Ok(pages.len())
+    }
+
+    fn render_page(
+        &self,
+        chapter: &Chapter,
+        section: &Section,
+        section_file_path: &Path,
+        BookRenderOptions {
+            exercise_paths,
+            base_url,
+            layout,
+            conditions,
+            with_slides,
+            theme: _,
+            quiz,
+            notes,
+            language: _,
+        }: &BookRenderOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(), RenderBookError> {
+        let label = chapter.label;
+        let section_i = section.unit_index;
+        let unit_label = label.qualify(section_i);
+        progress::advance(Phase::Book, section.title);
+        let timing = progress::time(Phase::Book, section.title);
+        // The page is skipped if it's made of the same parts of the track and exercise
+        // descriptions as when it was last rendered
+        let mut inputs = Inputs::new(section_file_path)
+            .value(&(label, section, self.license, self.course_version))
+            .value(&(base_url, layout, conditions, with_slides, quiz, notes))
+            .value(&self.merge_similar_objectives);
+        let topic_files = section.topics.iter().filter_map(|t| t.content.path());
+        for path in topic_files.filter(|_| *notes) {
+            inputs = inputs
+                .file(path)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+                .change_context(RenderBookError::default())?;
+        }
+        for subsection in section.subsections.iter() {
+            inputs = inputs.value(&exercise_paths.get(subsection.exercise_path));
+            if let Some(path) = subsection.content {
+                inputs = inputs
+                    .file(path)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error reading file at path {}", path.display())
+                    })
+                    .change_context(RenderBookError::default())?;
+            }
+        }
+        if let Some(cached) = cache::cached(&inputs) {
+            warnings.extend(cached.into_iter().flatten());
+            return Ok(());
+        }
+        let mut outputs = vec![section_file_path.to_path_buf()];
+        tracing::info!(
+            kind = "chapter",
+            unit = %section.title,
+            path = %section_file_path.display(),
+            "Rendering book page {}",
+            section_file_path.display()
+        );
+        // Written as it's put together, so that only one exercise description is held in
+        // memory at a time
+        let mut file = section_file_path.create_text_file()?;
+        let mut section_file = PageWriter {
+            out: io::BufWriter::new(&mut file),
+            output: section_file_path,
+            line: 1,
+            pending: vec![],
+            bytes: 0,
+            warnings: vec![],
+        };
+        section_file.write_fmt(format_args!("# Unit {unit_label} - {}\n\n", section.title))?;
+        if section.has_slides && *with_slides {
+            section_file.write_fmt(format_args!(
+                indoc! {r#"
+                    <a href="{url}" target="_blank">Slides</a>
 
-                        <a href="/{url_base}{url_base_separator}slides/{chapter_i}_{section_i}/" target="_blank">Slides</a>
 
+                    "#},
+                url = layout.deck_url(base_url, &format!("{}_{section_i}", label.path)),
+            ))?;
+        }
 
-                        "#},
-                    section.title,
-                    chapter_i = chapter_i,
-                    section_i = section_i,
-                    url_base = slides_url_base,
-                    url_base_separator = slides_url_base_separator,
-                ))?;
+        if !section.tags.is_empty() {
+            section_file.write_fmt(format_args!("*Tags: {}*\n\n", section.tags.join(", ")))?;
+        }
 
-                if !section.subsections.is_empty() {
-                    for (subsection, subsection_i) in section.subsections.iter().zip(1..) {
-                        section_file.write_fmt(format_args!(
-                            "## Exercise {chapter_i}.{section_i}.{subsection_i}: {}\n\n",
-                            subsection.title
-                        ))?;
-                        let exercise_out_dir = &exercise_paths[subsection.exercise_path];
-                        let content = subsection.content.read_to_string()?;
-                        let content = content
-                            // Insert exercise directory paths
-                            .replace(
-                                "#[modmod:exercise_dir]",
-                                &exercise_out_dir.to_string_lossy(),
-                            )
-                            // Insert exercise references
-                            .replace(
-                                "#[modmod:exercise_ref]",
-                                &format!("{chapter_i}.{section_i}.{subsection_i}"),
-                            )
-                            // Convert exercise sections into subsubsections
-                            .replace("\n# ", "\n### ");
-                        section_file.write_fmt(format_args!("{}\n", content.trim()))?;
-                    }
+        if section.sessions.iter().any(|s| !s.implicit) {
+            section_file.write_all("## Sessions\n\n")?;
+            for session in section.sessions.iter() {
+                section_file.write_fmt(format_args!("- **{}**", session.name))?;
+                if let Some(minutes) = session.duration_minutes {
+                    section_file.write_fmt(format_args!(" ({minutes} minutes)"))?;
+                }
+                section_file.write_all("\n")?;
+                if !session.topics.is_empty() {
+                    section_file
+                        .write_fmt(format_args!("\t- Topics: {}\n", session.topics.join(", ")))?;
+                }
+                if !session.exercises.is_empty() {
+                    section_file.write_fmt(format_args!(
+                        "\t- Exercises: {}\n",
+                        session.exercises.join(", ")
+                    ))?;
+                }
+            }
+            section_file.write_all("\n")?;
+        }
+
+        // Duplicate objectives are reported when rendering the slides, unless the unit has none
+        let mut objectives = || {
+            let objectives = section
+                .objectives
+                .iter()
+                .map(|&(text, topic)| UnitObjective {
+                    text,
+                    topic,
+                    optional: false,
+                })
+                .collect();
+            let mut ignored = vec![];
+            let objectives = report::dedupe_objectives(
+                objectives,
+                section.title,
+                self.merge_similar_objectives,
+                if section.has_slides && *with_slides {
+                    &mut ignored
                 } else {
-                    section_file.write_all("*There are no exercises for this unit*")?;
+                    &mut *warnings
+                },
+            );
+            objectives.into_iter().map(|o| o.text).collect::<Vec<_>>()
+        };
+        if *notes {
+            write_notes(&mut section_file, section, &objectives(), conditions)?;
+        }
+
+        if !section.subsections.is_empty() {
+            // Once one exercise of the unit has an estimate, those without one are marked
+            let estimated = section
+                .subsections
+                .iter()
+                .any(|s| s.duration_minutes.is_some());
+            for (subsection, subsection_i) in section.subsections.iter().zip(1..) {
+                section_file.write_fmt(format_args!(
+                    "## {}\n\n",
+                    exercise_heading(label, section_i, subsection_i, subsection)
+                ))?;
+                if estimated {
+                    section_file.write_fmt(format_args!(
+                        "*Estimated time: {}*\n\n",
+                        Estimate::of(subsection.duration_minutes)
+                    ))?;
                 }
+                let exercise_out_dir = &exercise_paths[subsection.exercise_path];
+                let content = match subsection.content {
+                    Some(path) => path.read_to_string()?,
+                    None => format!(
+                        "TODO: missing description for exercise {}",
+                        subsection.title
+                    ),
+                };
+                let resolved = match subsection.content {
+                    Some(path) => conditional::resolve(&content, conditions)
+                        .change_context(RenderBookError::default())
+                        .attach_printable_lazy(|| {
+                            format!("In exercise description at {}", path.display())
+                        })
+                        .attach_lazy(|| Location::file(path))?,
+                    None => Cow::Borrowed(content.as_str()),
+                };
+                // Insert exercise directory paths and references
+                write_description(
+                    &mut section_file,
+                    &resolved,
+                    &[
+                        (
+                            "#[modmod:exercise_dir]",
+                            &slash_path::<RenderBookError>(exercise_out_dir)?,
+                        ),
+                        (
+                            "#[modmod:exercise_ref]",
+                            &label.qualify(format!("{section_i}.{subsection_i}")),
+                        ),
+                    ],
+                )
+                .into_report()
+                .change_context(RenderBookError::default())?;
+                section_file.write_all("\n")?;
             }
-            summary_md.write_all("\n")?;
+        } else if !*notes {
+            // Without exercises, the page tells what the unit is about instead
+            let objectives = objectives();
+            for (heading, items) in [("Objectives", &objectives), ("Summary", &section.summary)] {
+                if items.is_empty() {
+                    continue;
+                }
+                section_file.write_fmt(format_args!("## {heading}\n\n"))?;
+                for item in items.iter() {
+                    section_file.write_fmt(format_args!("- {}\n", item.trim()))?;
+                }
+                section_file.write_all("\n")?;
+            }
+            section_file.write_all("*No exercises for this unit*")?;
+        } else {
+            section_file.write_all("*No exercises for this unit*")?;
+        }
+
+        if !section.quiz.is_empty() {
+            let markdown = match quiz {
+                QuizMode::Static => quiz::static_markdown(&section.quiz),
+                QuizMode::MdbookQuiz => {
+                    let toml = quiz::mdbook_quiz_toml(&section.quiz)
+                        .into_report()
+                        .change_context(RenderBookError::default())?;
+                    let quizzes_dir = section_file_path.with_file_name(QUIZZES_DIR);
+                    quizzes_dir.create_dir_all()?;
+                    let file_name = Path::new(&to_tag(section.title)).with_extension("toml");
+                    quizzes_dir.join(&file_name).write_text_file(toml)?;
+                    outputs.push(quizzes_dir.join(&file_name));
+                    quiz::mdbook_quiz_markdown(&format!(
+                        "{QUIZZES_DIR}/{}",
+                        slash_path::<RenderBookError>(&file_name)?
+                    ))
+                }
+            };
+            // After the exercises, or the text that tells there are none
+            let separator = match section.subsections.is_empty() {
+                true => "\n\n",
+                false => "\n",
+            };
+            section_file.write_fmt(format_args!("{separator}{}\n", markdown.trim_end()))?;
         }
 
+        if !section.attributions.is_empty() {
+            section_file.write_all("\n---\n\n*Material in this unit:*\n\n")?;
+            for attribution in section.attributions.iter() {
+                section_file.write_fmt(format_args!("- *{attribution}*\n"))?;
+            }
+        }
+
+        let footer: Vec<_> = self
+            .license
+            .map(|license| format!("{} ({})", license.attribution, license.spdx))
+            .into_iter()
+            .chain(self.course_version.map(|v| format!("Course version {v}")))
+            .collect();
+        if !footer.is_empty() {
+            section_file.write_fmt(format_args!(
+                "\n\n---\n\n<small>{}</small>\n",
+                footer.join(" · ")
+            ))?;
+        }
+        let (bytes, leftovers) = section_file
+            .finish()
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Error writing book page {}", section_file_path.display())
+            })
+            .change_context(RenderBookError::default())?;
+        warnings.extend(leftovers);
+        timing.record("path", section_file_path.display());
+        timing.record("bytes", bytes);
+        file.commit()?;
+        cache::record(inputs, &outputs, &[warnings]);
         Ok(())
     }
 }
 
+/// Writes a book page to `out`, reporting the placeholders that are left in it a line at
+/// a time, so that the page doesn't have to be held in memory to check it
+struct PageWriter<'a, W> {
+    out: W,
+    output: &'a Path,
+    /// The line of the page that is being written
+    line: usize,
+    /// What was written of that line so far
+    pending: Vec<u8>,
+    /// The number of bytes that were written
+    bytes: usize,
+    warnings: Vec<Warning>,
+}
+
+impl<W: io::Write> io::Write for PageWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf)?;
+        self.bytes += written;
+        let mut rest = &buf[..written];
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            match self.pending.is_empty() {
+                true => self.check(&rest[..end]),
+                false => {
+                    self.pending.extend_from_slice(&rest[..end]);
+                    let line = std::mem::take(&mut self.pending);
+                    self.check(&line);
+                }
+            }
+            self.line += 1;
+            rest = &rest[end + 1..];
+        }
+        self.pending.extend_from_slice(rest);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: io::Write> PageWriter<'_, W> {
+    fn check(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        report::leftover_placeholders_from_line(&line, self.line, self.output, &mut self.warnings);
+    }
+
+    /// Flushes the page, and returns the number of bytes that were written and the
+    /// warnings about placeholders that are left in it
+    fn finish(mut self) -> io::Result<(usize, Vec<Warning>)> {
+        let line = std::mem::take(&mut self.pending);
+        self.check(&line);
+        self.out.flush()?;
+        Ok((self.bytes, self.warnings))
+    }
+}
+
+/// Writes an exercise description, trimmed, with the `markers` replaced by their values in
+/// one pass, so that values with markers in them are left as they are, and with its
+/// sections as subsubsections of the page
+fn write_description(
+    out: &mut impl io::Write,
+    content: &str,
+    markers: &[(&str, &str)],
+) -> io::Result<()> {
+    const START: &str = "#[modmod:";
+    let mut rest = content.trim();
+    // A section heading after blank lines is on a line of its own as well
+    if content[..content.len() - content.trim_start().len()].ends_with('\n')
+        && rest.starts_with("# ")
+    {
+        out.write_all(b"##")?;
+    }
+    while let Some(start) = rest.find(START) {
+        write_sections(out, &rest[..start])?;
+        rest = &rest[start..];
+        match markers.iter().find(|(marker, _)| rest.starts_with(marker)) {
+            Some((marker, value)) => {
+                out.write_all(value.as_bytes())?;
+                rest = &rest[marker.len()..];
+            }
+            None => {
+                out.write_all(START.as_bytes())?;
+                rest = &rest[START.len()..];
+            }
+        }
+    }
+    write_sections(out, rest)
+}
+
+/// Writes the notes of `section` as [`BookRenderOptions::notes`] has them: its `objectives`,
+/// the slides of each of its topics under the name of the topic, and its summary and further
+/// reading. The slides are written without their separators, frontmatter, speaker notes and
+/// slot markers, with their headings two levels deeper, and with their images in [`IMAGES_DIR`].
+fn write_notes(
+    out: &mut impl WriteExt,
+    section: &Section,
+    objectives: &[&str],
+    conditions: &Conditions,
+) -> Result<(), RenderBookError> {
+    write_list(out, "Objectives", objectives)?;
+    for topic in section.topics.iter() {
+        let slides = topic_slides(&topic.content, &topic.name, conditions)
+            .change_context(RenderBookError::default())?;
+        let slides = split_slides(&slides);
+        if slides.iter().all(|slide| slide.trim().is_empty()) {
+            continue;
+        }
+        let optional_marker = if topic.optional { " *(optional)*" } else { "" };
+        out.write_fmt(format_args!("## {}{optional_marker}\n\n", topic.name))?;
+        for slide in slides {
+            let slide = match slide_notes(slide) {
+                Some(_) => &slide[..slide.rfind("<!--").unwrap_or(slide.len())],
+                None => slide,
+            };
+            let slide = slide.trim();
+            if slide.is_empty() {
+                continue;
+            }
+            out.write_fmt(format_args!("{}\n\n", note_markdown(slide)))?;
+        }
+    }
+    write_list(out, "Summary", &section.summary)?;
+    // Topics of a unit often point to the same material, which is listed once
+    let mut further_reading: Vec<&str> = vec![];
+    for item in section.topics.iter().flat_map(|t| t.further_reading.iter()) {
+        let item = item.trim();
+        if !item.is_empty() && !further_reading.contains(&item) {
+            further_reading.push(item);
+        }
+    }
+    write_list(out, "Further reading", &further_reading)
+}
+
+/// Whether `line` is a Slidev slot marker like `::right::`, which has no meaning in the book
+fn is_slot_marker(line: &str) -> bool {
+    let Some(name) = line
+        .trim()
+        .strip_prefix("::")
+        .and_then(|l| l.strip_suffix("::"))
+    else {
+        return false;
+    };
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Writes `items` as a list under a `## {heading}`, unless there are none
+fn write_list(
+    out: &mut impl WriteExt,
+    heading: &str,
+    items: &[&str],
+) -> Result<(), RenderBookError> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    out.write_fmt(format_args!("## {heading}\n\n"))?;
+    for item in items.iter() {
+        out.write_fmt(format_args!("- {}\n", item.trim()))?;
+    }
+    out.write_all("\n")
+}
+
+/// The markdown of a slide as a part of a page: with its headings two levels deeper, and the
+/// references to `/images/<name>` pointing to [`IMAGES_DIR`] next to the page
+fn note_markdown(slide: &str) -> String {
+    let mut markdown = String::with_capacity(slide.len());
+    let mut fence = false;
+    for line in slide.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = !fence;
+        } else if !fence && is_slot_marker(line) {
+            continue;
+        } else if !fence && line.starts_with('#') && line.trim_start_matches('#').starts_with(' ') {
+            markdown.push_str("##");
+        }
+        match fence {
+            true => markdown.push_str(line),
+            false => markdown.push_str(
+                &line
+                    .replace("](/images/", &format!("]({IMAGES_DIR}/"))
+                    .replace("src=\"/images/", &format!("src=\"{IMAGES_DIR}/")),
+            ),
+        }
+        markdown.push('\n');
+    }
+    markdown.truncate(markdown.trim_end().len());
+    markdown
+}
+
+/// Writes `text` with its `# ` headings as `### ` ones
+fn write_sections(out: &mut impl io::Write, text: &str) -> io::Result<()> {
+    let mut from = 0;
+    for (i, _) in text.match_indices("\n# ") {
+        out.write_all(&text.as_bytes()[from..i])?;
+        out.write_all(b"\n### ")?;
+        from = i + "\n# ".len();
+    }
+    out.write_all(&text.as_bytes()[from..])
+}
+
 #[derive(Debug)]
 pub struct Chapter<'track> {
     pub title: &'track str,
     pub sections: Vec<Section<'track>>,
     pub module_index: usize,
+    pub label: &'track ModuleLabel,
 }
 
 #[derive(Debug)]
 pub struct Section<'track> {
     pub title: &'track str,
     pub subsections: Vec<SubSection<'track>>,
-    pub module_index: usize,
     pub unit_index: usize,
+    pub attributions: Vec<String>,
+    pub sessions: &'track [Session],
+    /// Delivery metadata of the unit
+    pub tags: &'track [String],
+    /// Former ids of the topics in this section, that should redirect to it
+    pub aliases: Vec<&'track str>,
+    /// Learning objectives of the topics, with the name of their topic.
+    /// They are listed on pages without exercises.
+    pub objectives: Vec<(&'track str, &'track str)>,
+    /// Summary items of the topics, listed on pages without exercises
+    pub summary: Vec<&'track str>,
+    /// Questions of the quizzes of the topics, asked at the end of the page
+    pub quiz: Vec<&'track QuizQuestion>,
+    /// Whether the unit gets a page. Units without one are only listed in the summary.
+    pub has_page: bool,
+    /// Whether the unit has a slide deck to link to
+    pub has_slides: bool,
+    /// The topics of the unit, whose slides can be written into the page as notes
+    pub topics: Vec<&'track Topic>,
 }
 
 #[derive(Debug)]
 pub struct SubSection<'track> {
     pub title: &'track str,
-    pub content: &'track Path,
+    /// The exercise description, if there is one
+    pub content: Option<&'track Path>,
     pub exercise_path: &'track Path,
+    pub optional: bool,
+    /// The minutes the exercise takes to do, if it sets them
+    pub duration_minutes: Option<u32>,
+    /// Former ids of the exercise, that should redirect to it
+    pub aliases: &'track [String],
+}
+
+impl Book<'_> {
+    /// Copies the images of the topics on the pages into `images_dir`, which the notes refer
+    /// to, see [`BookRenderOptions::notes`]
+    fn copy_images(&self, images_dir: &Path) -> Result<(), RenderBookError> {
+        let mut copied = HashSet::new();
+        let topics = self
+            .chapters
+            .iter()
+            .flat_map(|chapter| chapter.sections.iter())
+            .filter(|section| section.has_page)
+            .flat_map(|section| section.topics.iter());
+        for image in topics.flat_map(|topic| topic.images.iter()) {
+            // Image names are unique within a track, as the slides have them in one folder too
+            let Some(name) = image.path.file_name() else {
+                continue;
+            };
+            if copied.insert(name) {
+                images_dir.create_dir_all()?;
+                image.path.copy(images_dir.join(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any of the pages has a quiz
+    pub(crate) fn has_quiz(&self) -> bool {
+        self.chapters
+            .iter()
+            .flat_map(|chapter| chapter.sections.iter())
+            .any(|section| section.has_page && !section.quiz.is_empty())
+    }
+
+    /// Redirects from the former ids of topics and exercises to the page they are on now,
+    /// as pairs of the old id and the new location relative to the book root
+    fn redirects(&self) -> Vec<(&str, String)> {
+        let mut redirects = vec![];
+        for chapter in self.chapters.iter() {
+            for section in chapter.sections.iter().filter(|s| s.has_page) {
+                let page = format!("{}.html", to_tag(section.title));
+                for alias in section.aliases.iter() {
+                    redirects.push((*alias, page.clone()));
+                }
+                for (subsection, subsection_i) in section.subsections.iter().zip(1..) {
+                    let heading = exercise_heading(
+                        chapter.label,
+                        section.unit_index,
+                        subsection_i,
+                        subsection,
+                    );
+                    for alias in subsection.aliases {
+                        redirects.push((alias, format!("{page}#{}", heading_anchor(&heading))));
+                    }
+                }
+            }
+        }
+        // Topics can be included in more than one unit, redirect to the first
+        let mut seen = HashSet::new();
+        redirects.retain(|(alias, _)| seen.insert(*alias));
+        redirects
+    }
+}
+
+/// Copies the files of an mdbook theme into the book
+fn copy_theme(theme: &Path, to: &Path) -> Result<(), RenderBookError> {
+    if !theme.is_dir() {
+        return Err(Report::new(RenderBookError::default())
+            .attach_printable(format!("Book theme {} is not a folder", theme.display())));
+    }
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        ..Default::default()
+    };
+    copy_tree::<RenderBookError>(theme, to, &options)?;
+    Ok(())
+}
+
+fn exercise_heading(
+    label: &ModuleLabel,
+    section_i: usize,
+    subsection_i: usize,
+    subsection: &SubSection,
+) -> String {
+    let optional_marker = if subsection.optional {
+        " *(optional)*"
+    } else {
+        ""
+    };
+    format!(
+        "Exercise {}: {}{optional_marker}",
+        label.qualify(format!("{section_i}.{subsection_i}")),
+        subsection.title
+    )
+}
+
+/// The folder next to the pages that the images of the notes are copied into
+pub(crate) const IMAGES_DIR: &str = "images";
+
+/// The id mdbook gives to a heading, so that it can be linked to
+pub(crate) fn heading_anchor(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' || c == '-' => Some(c.to_ascii_lowercase()),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
 }
 
 pub struct BookBuilder<'track> {
@@ -170,16 +880,38 @@ pub struct BookBuilder<'track> {
 }
 
 impl<'track> BookBuilder<'track> {
+    pub fn course(&mut self, course: &'track Course) {
+        self.book.course = Some(course);
+    }
+
+    pub fn license(&mut self, license: &'track License) {
+        self.book.license = Some(license);
+    }
+
+    pub fn course_version(&mut self, version: &'track CourseVersion) {
+        self.book.course_version = Some(version);
+    }
+
+    pub fn analytics(&mut self, analytics: &'track Analytics) {
+        self.book.analytics = Some(analytics);
+    }
+
+    pub fn merge_similar_objectives(&mut self) {
+        self.book.merge_similar_objectives = true;
+    }
+
     pub fn chapter<'b>(
         &'b mut self,
         title: &'track str,
         module_index: usize,
+        label: &'track ModuleLabel,
     ) -> ChapterBuilder<'track, 'b> {
         ChapterBuilder {
             book_builder: self,
             chapter: Chapter {
                 title,
                 module_index,
+                label,
                 sections: vec![],
             },
         }
@@ -198,7 +930,6 @@ pub struct ChapterBuilder<'track, 'b> {
 impl<'track, 'b> ChapterBuilder<'track, 'b> {
     pub fn section<'c>(
         &'c mut self,
-        module_index: usize,
         unit_index: usize,
         title: &'track str,
     ) -> SectionBuilder<'track, 'b, 'c> {
@@ -206,9 +937,18 @@ impl<'track, 'b> ChapterBuilder<'track, 'b> {
             chapter_builder: self,
             section: Section {
                 title,
-                module_index,
                 unit_index,
                 subsections: vec![],
+                attributions: vec![],
+                sessions: &[],
+                tags: &[],
+                aliases: vec![],
+                objectives: vec![],
+                summary: vec![],
+                quiz: vec![],
+                has_page: true,
+                has_slides: true,
+                topics: vec![],
             },
         }
     }
@@ -225,17 +965,75 @@ pub struct SectionBuilder<'track, 'b, 'c> {
 }
 
 impl<'track, 'b, 'c> SectionBuilder<'track, 'b, 'c> {
+    /// Adds an exercise to the section, and returns the anchor of its heading in the page of
+    /// the section, if the section has a page
     pub fn subsection(
         &mut self,
         title: &'track str,
-        content: &'track Path,
+        content: Option<&'track Path>,
         exercise_path: &'track Path,
-    ) {
-        self.section.subsections.push(SubSection {
+        optional: bool,
+        duration_minutes: Option<u32>,
+        aliases: &'track [String],
+    ) -> Option<String> {
+        let subsection = SubSection {
             title,
             content,
             exercise_path,
-        })
+            optional,
+            duration_minutes,
+            aliases,
+        };
+        let heading = exercise_heading(
+            self.chapter_builder.chapter.label,
+            self.section.unit_index,
+            self.section.subsections.len() + 1,
+            &subsection,
+        );
+        self.section.subsections.push(subsection);
+        self.section.has_page.then(|| heading_anchor(&heading))
+    }
+
+    pub fn aliases(&mut self, aliases: &'track [String]) {
+        self.section
+            .aliases
+            .extend(aliases.iter().map(String::as_str));
+    }
+
+    pub fn sessions(&mut self, sessions: &'track [Session]) {
+        self.section.sessions = sessions;
+    }
+
+    pub fn tags(&mut self, tags: &'track [String]) {
+        self.section.tags = tags;
+    }
+
+    pub fn attribution(&mut self, attribution: String) {
+        self.section.attributions.push(attribution);
+    }
+
+    pub fn objective(&mut self, topic: &'track str, objective: &'track str) {
+        self.section.objectives.push((objective, topic));
+    }
+
+    pub fn summary(&mut self, summary: &'track str) {
+        self.section.summary.push(summary);
+    }
+
+    pub fn topic(&mut self, topic: &'track Topic) {
+        self.section.topics.push(topic);
+    }
+
+    pub fn quiz(&mut self, questions: &'track [QuizQuestion]) {
+        self.section.quiz.extend(questions);
+    }
+
+    pub fn skip_page(&mut self) {
+        self.section.has_page = false;
+    }
+
+    pub fn skip_slides(&mut self) {
+        self.section.has_slides = false;
     }
 
     pub fn add(self) -> &'c mut ChapterBuilder<'track, 'b> {
@@ -0,0 +1,286 @@
+//! The render cache, which lets a render into an output folder skip the decks, book pages
+//! and exercise packages whose inputs didn't change since they were last rendered there.
+//! It's kept in [`CACHE_FILE`] in the root of the output.
+//!
+//! The cache is content-addressed: an entry is found by the hash of everything its item is
+//! rendered from, which is the content of the files it reads, the parts of the track it's
+//! made of, and the options of the render that change the output, like the theme, the base
+//! URL and the profile. The entry lists the files that were written for the item with their
+//! hash, and the warnings that came up. The item is only skipped if those files are still as
+//! they were written, so that a file that was edited or removed is rendered again. The cache
+//! holds nothing that changes between renders of the same content, so that the output stays
+//! reproducible.
+//!
+//! A cache file that is missing, can't be read or is of another version is ignored, which
+//! renders everything. Renders that don't write into the output folder, and renders that
+//! rewrite files that stay the same, see
+//! [`RunContext::rewrite_unchanged`](crate::context::RunContext::rewrite_unchanged), don't use the cache.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Write},
+    fs,
+    path::{Path, PathBuf},
+    sync::PoisonError,
+};
+
+use error_stack::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    archive, context,
+    io::{self, PathExt},
+    manifest,
+    report::Warning,
+    sha256,
+};
+
+/// Name of the render cache, in the root of the output of a track
+pub const CACHE_FILE: &str = ".modmod-cache.json";
+
+/// Version of the format of the render cache. Caches of another version are ignored.
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// The entries, by the hash of the inputs of their item
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    outputs: Vec<Output>,
+    /// The warnings that came up, in the groups the renderer keeps them in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<Vec<Warning>>,
+}
+
+/// A file that was written for an item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Output {
+    /// Path of the file relative to the output folder, as in the render manifest
+    path: String,
+    hash: String,
+    size: u64,
+    /// Whether it has an executable bit set, so that a script that lost it is copied again
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    executable: bool,
+}
+
+pub(crate) struct Cache {
+    out_dir: PathBuf,
+    /// Hash of the options of the render, which is part of the key of every entry
+    options: String,
+    /// The entries of the earlier render
+    earlier: BTreeMap<String, Entry>,
+    /// The entries of this render, which replace those of the earlier one
+    entries: BTreeMap<String, Entry>,
+    /// Number of files that were kept from the earlier render
+    kept: usize,
+}
+
+/// Starts using the cache in `out_dir` for the render with `options`, unless writes into it
+/// are discarded or files that stay the same are rewritten. The cache belongs to the
+/// current context, so that only the render it was opened for uses it.
+pub(crate) fn open(out_dir: &Path, options: &impl Debug) {
+    let context = context::current();
+    let mut cache = context.cache.lock().unwrap_or_else(PoisonError::into_inner);
+    *cache = None;
+    if io::writes_discarded_at(out_dir) || !io::skips_unchanged() {
+        return;
+    }
+    let path = out_dir.join(CACHE_FILE);
+    let earlier = match fs::read(&path) {
+        Ok(bytes) => match serde_json::from_slice::<CacheFile>(&bytes) {
+            Ok(file) if file.version == CACHE_VERSION => file.entries,
+            Ok(file) => {
+                ignored(&path, &format!("it's of version {}", file.version));
+                BTreeMap::new()
+            }
+            Err(e) => {
+                ignored(&path, &e.to_string());
+                BTreeMap::new()
+            }
+        },
+        Err(_) => BTreeMap::new(),
+    };
+    *cache = Some(Cache {
+        out_dir: out_dir.to_path_buf(),
+        options: Inputs::default().value(options).digest(),
+        earlier,
+        entries: BTreeMap::new(),
+        kept: 0,
+    });
+}
+
+fn ignored(path: &Path, reason: &str) {
+    tracing::debug!(
+        path = %path.display(),
+        "Ignoring the render cache at {}, as {reason}",
+        path.display()
+    );
+}
+
+/// Writes the entries of this render into the cache file, and stops using the cache.
+/// Returns the number of files that were kept from the earlier render.
+pub(crate) fn close<C: Context + Default>() -> Result<usize, C> {
+    let context = context::current();
+    let Some(cache) = context
+        .cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+    else {
+        return Ok(0);
+    };
+    let file = CacheFile {
+        version: CACHE_VERSION,
+        entries: cache.entries,
+    };
+    // The cache only saves work, so a render doesn't fail on it
+    if let Ok(json) = serde_json::to_string(&file) {
+        cache.out_dir.join(CACHE_FILE).write_file(json)?;
+    }
+    Ok(cache.kept)
+}
+
+/// What an item is rendered from, hashed with SHA-256 as it's added
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Inputs {
+    hash: Sha256,
+}
+
+impl Inputs {
+    /// The inputs of the item that is rendered to `path`
+    pub(crate) fn new(path: &Path) -> Self {
+        Self::default().value(&path)
+    }
+
+    /// Adds a part of the track or an option, by its debug representation. Its value must
+    /// not depend on the order of a hash map. The representation is hashed as it's
+    /// formatted, as parts of the track can hold large inline content.
+    pub(crate) fn value(mut self, value: &impl Debug) -> Self {
+        struct Hasher<'a>(&'a mut Inputs, u64);
+        impl fmt::Write for Hasher<'_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.hash.update(s.as_bytes());
+                self.1 += s.len() as u64;
+                Ok(())
+            }
+        }
+        let mut hasher = Hasher(&mut self, 0);
+        // Hashing doesn't fail
+        let _ = write!(hasher, "{value:?}");
+        let len = hasher.1;
+        self.hash.update(len.to_le_bytes());
+        self
+    }
+
+    /// Adds the path, the permissions and the content of a file
+    pub(crate) fn file(self, path: &Path) -> std::io::Result<Self> {
+        let mut inputs = self.value(&path).value(&fs::metadata(path)?.permissions());
+        let mut len = 0u64;
+        io::read_chunks(path, |chunk| {
+            inputs.hash.update(chunk);
+            len += chunk.len() as u64;
+        })?;
+        inputs.hash.update(len.to_le_bytes());
+        Ok(inputs)
+    }
+
+    /// The key of the entry of the item in `cache`
+    fn key(&self, cache: &Cache) -> String {
+        self.clone().value(&cache.options).digest()
+    }
+
+    fn digest(self) -> String {
+        sha256::hex(&self.hash.finalize())
+    }
+}
+
+/// The warnings of the item rendered from `inputs`, if it was rendered from them before and
+/// the files that were written for it are still as they were. Its files are kept, see
+/// [`io::keep_file`].
+pub(crate) fn cached(inputs: &Inputs) -> Option<Vec<Vec<Warning>>> {
+    let context = context::current();
+    // The outputs are read back without holding the lock, as other items are rendered
+    // at the same time
+    let (out_dir, key, entry) = {
+        let guard = context.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let cache = guard.as_ref()?;
+        let key = inputs.key(cache);
+        let entry = cache.earlier.get(&key)?.clone();
+        (cache.out_dir.clone(), key, entry)
+    };
+    let paths: Vec<_> = entry
+        .outputs
+        .iter()
+        .map(|output| out_dir.join(&output.path))
+        .collect();
+    let unchanged = entry
+        .outputs
+        .iter()
+        .zip(paths.iter())
+        .all(|(output, path)| {
+            fs::metadata(path).is_ok_and(|m| m.len() == output.size)
+                && archive::is_executable(path) == output.executable
+                && sha256::hash_file(path).is_ok_and(|hash| hash == output.hash)
+        });
+    if !unchanged {
+        return None;
+    }
+    for path in paths.iter() {
+        io::keep_file(path);
+    }
+    let mut guard = context.cache.lock().unwrap_or_else(PoisonError::into_inner);
+    let cache = guard.as_mut()?;
+    cache.kept += entry.outputs.len();
+    let warnings = entry.warnings.clone();
+    cache.entries.insert(key, entry);
+    Some(warnings)
+}
+
+/// Records the `outputs` that were written for the item rendered from `inputs`, with the
+/// `warnings` that came up. Items with outputs that can't be read back aren't recorded, so
+/// that they are rendered again the next time.
+pub(crate) fn record(inputs: Inputs, outputs: &[PathBuf], warnings: &[&[Warning]]) {
+    let context = context::current();
+    // The outputs are read back without holding the lock, as other items are rendered
+    // at the same time
+    let Some((out_dir, key)) = context
+        .cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map(|cache| (cache.out_dir.clone(), inputs.key(cache)))
+    else {
+        return;
+    };
+    let outputs: Option<Vec<_>> = outputs
+        .iter()
+        .map(|path| {
+            Some(Output {
+                path: manifest::relative_path(path.strip_prefix(&out_dir).ok()?),
+                hash: sha256::hash_file(path).ok()?,
+                size: fs::metadata(path).ok()?.len(),
+                executable: archive::is_executable(path),
+            })
+        })
+        .collect();
+    let Some(outputs) = outputs else {
+        return;
+    };
+    let mut guard = context.cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(cache) = guard.as_mut() {
+        let entry = Entry {
+            outputs,
+            warnings: match warnings.iter().all(|group| group.is_empty()) {
+                true => vec![],
+                false => warnings.iter().map(|group| group.to_vec()).collect(),
+            },
+        };
+        cache.entries.insert(key, entry);
+    }
+}
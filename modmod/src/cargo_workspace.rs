@@ -0,0 +1,172 @@
+//! The files that make the exercises output one project: a `Cargo.toml` with a workspace of
+//! the exercise packages, so that `cargo test --workspace` in the root of the output builds
+//! and tests all of them, and the `.vscode` settings that make rust-analyzer pick them up.
+//!
+//! Exercises whose starter code doesn't pass its tests, or doesn't compile at all, are
+//! excluded from the workspace instead, as they would fail it, and rust-analyzer is pointed
+//! at them on their own. So are packages that are a workspace of their own, as workspaces
+//! can't be nested, and packages with the name of an earlier one. Like the toolchain files,
+//! these are only replaced if modmod generated them, see [`crate::scaffold`].
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde_json::json;
+
+use crate::{
+    io::{self, PathExt},
+    report::Warning,
+    scaffold::{Scaffold, GENERATED},
+    Track,
+};
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct WriteWorkspaceError;
+
+impl fmt::Display for WriteWorkspaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write the Cargo workspace of the exercises")
+    }
+}
+
+impl error_stack::Context for WriteWorkspaceError {}
+
+/// Says where the generated files come from, at their top
+const HEADER: &str = "from the exercises of the track";
+
+/// The crates of the exercises output, as folders relative to its root
+pub(crate) struct Crates {
+    /// Exercise packages and shared crates that build and pass their tests
+    pub(crate) members: Vec<String>,
+    /// Exercise packages that don't, that are a workspace of their own, or that have the
+    /// name of a member
+    pub(crate) excluded: Vec<String>,
+}
+
+impl Track {
+    /// The crates of the exercises output in `exercises_dir`, from the folders the exercise
+    /// packages were rendered to by the path of their source. Packages without a manifest in
+    /// their root aren't crates.
+    pub(crate) fn exercise_crates(
+        &self,
+        exercise_paths: &HashMap<PathBuf, PathBuf>,
+        exercises_dir: &Path,
+    ) -> Result<Crates, WriteWorkspaceError> {
+        let starter_fails: HashMap<&Path, bool> = self
+            .exercises()
+            .map(|e| (e.path.as_path(), e.starter_fails_tests))
+            .collect();
+        let mut crates = Crates {
+            members: vec![],
+            excluded: vec![],
+        };
+        // In the order of the output, so that the first of the packages with the same name is
+        // the one that's a member
+        let mut packages: Vec<_> = exercise_paths.iter().collect();
+        packages.sort_by_key(|(_, rendered)| *rendered);
+        let mut names: Vec<String> = vec![];
+        for (source, rendered) in packages {
+            let Ok(manifest) = fs::read_to_string(source.join("Cargo.toml")) else {
+                continue;
+            };
+            let Ok(folder) = rendered.strip_prefix(exercises_dir) else {
+                continue;
+            };
+            let folder = io::slash_path::<WriteWorkspaceError>(folder)?;
+            let manifest = toml::from_str::<toml::Table>(&manifest).unwrap_or_default();
+            let own_workspace = manifest.contains_key("workspace");
+            // The packages of a workspace need names of their own
+            let name = manifest
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(|name| name.as_str());
+            let taken = name.is_some_and(|name| names.contains(&name.to_string()));
+            match own_workspace || taken || starter_fails.get(source.as_path()) == Some(&true) {
+                true => crates.excluded.push(folder),
+                false => {
+                    names.extend(name.map(str::to_string));
+                    crates.members.push(folder)
+                }
+            }
+        }
+        crates.members.extend(
+            self.shared_crates
+                .iter()
+                .map(|shared_crate| format!("shared/{}", shared_crate.name)),
+        );
+        crates.members.sort();
+        crates.excluded.sort();
+        Ok(crates)
+    }
+}
+
+/// Writes the workspace of `crates` into `exercises_dir`, the root of the exercises output,
+/// with the `.vscode` settings that recommend `extensions`. Files that modmod didn't generate
+/// are only replaced with `force`.
+pub(crate) fn write(
+    crates: &Crates,
+    extensions: &[String],
+    exercises_dir: &Path,
+    force: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), WriteWorkspaceError> {
+    let mut scaffold = Scaffold { force, warnings };
+    scaffold.write::<WriteWorkspaceError>(
+        &exercises_dir.join("Cargo.toml"),
+        &cargo_toml(crates)?,
+        None,
+    )?;
+    let vscode = exercises_dir.join(".vscode");
+    vscode.create_dir_all()?;
+    scaffold.write::<WriteWorkspaceError>(
+        &vscode.join("settings.json"),
+        &settings_json(crates)?,
+        None,
+    )?;
+    scaffold.write::<WriteWorkspaceError>(
+        &vscode.join("extensions.json"),
+        &extensions_json(extensions)?,
+        None,
+    )
+}
+
+fn cargo_toml(crates: &Crates) -> Result<String, WriteWorkspaceError> {
+    let file = json!({
+        "workspace": {
+            "resolver": "2",
+            "members": crates.members,
+            "exclude": crates.excluded,
+        }
+    });
+    let content = toml::to_string_pretty(&file)
+        .into_report()
+        .change_context(WriteWorkspaceError)?;
+    Ok(format!("# {GENERATED} {HEADER}\n{content}"))
+}
+
+fn settings_json(crates: &Crates) -> Result<String, WriteWorkspaceError> {
+    // Once projects are linked, rust-analyzer only loads those, so the workspace is too
+    let projects: Vec<_> = std::iter::once("Cargo.toml".to_string())
+        .chain(crates.excluded.iter().map(|c| format!("{c}/Cargo.toml")))
+        .collect();
+    let settings = json!({ "rust-analyzer.linkedProjects": projects });
+    let content = serde_json::to_string_pretty(&settings)
+        .into_report()
+        .change_context(WriteWorkspaceError)?;
+    // The settings of VS Code are JSON with comments
+    Ok(format!("// {GENERATED} {HEADER}\n{content}\n"))
+}
+
+fn extensions_json(extensions: &[String]) -> Result<String, WriteWorkspaceError> {
+    let content = serde_json::to_string_pretty(&json!({ "recommendations": extensions }))
+        .into_report()
+        .change_context(WriteWorkspaceError)?;
+    Ok(format!("// {GENERATED} {HEADER}\n{content}\n"))
+}
@@ -0,0 +1,742 @@
+//! Exporting a rendered track as an IMS Common Cartridge, which learning management systems
+//! like Canvas, Moodle and Blackboard import as a course.
+//!
+//! The cartridge follows the structure of the track: each module is a folder in the
+//! organization of the course, with a folder for each of its units. A unit holds its book
+//! chapter as a web page, a link to its hosted slide deck, and an assignment for each of
+//! its exercises. The chapters are converted from the Markdown of the rendered book, and
+//! their relative links point to the hosted book.
+
+use std::{fmt, fmt::Write, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    archive::{Entry, EntryContent},
+    book::heading_anchor,
+    conditional::Conditions,
+    io::PathExt,
+    stats::unit_counts,
+    to_tag,
+    url::BaseUrl,
+    OutputLayout, Track, Unit,
+};
+
+/// Name of the manifest of a cartridge, in its root
+pub const CARTRIDGE_MANIFEST_FILE: &str = "imsmanifest.xml";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct CartridgeError {}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to export the track as common cartridge")
+    }
+}
+
+impl error_stack::Context for CartridgeError {}
+
+pub struct CartridgeOptions<'a> {
+    /// The URL the slides and book are deployed at. It must have an origin, as the links
+    /// are followed from the learning management system.
+    pub base_url: &'a BaseUrl,
+    /// The layout the book was rendered with
+    pub layout: &'a OutputLayout,
+    /// The profile the book was rendered for
+    pub profile: Option<&'a str>,
+}
+
+/// The files of a cartridge, and what it holds
+#[derive(Debug)]
+pub struct Cartridge {
+    pub entries: Vec<Entry>,
+    pub chapters: usize,
+    pub slide_links: usize,
+    pub assignments: usize,
+}
+
+/// A resource of the cartridge and the file it is in
+struct Resource {
+    identifier: String,
+    kind: &'static str,
+    file: String,
+    /// Whether the file is the page that is shown for the resource
+    web_content: bool,
+}
+
+impl Track {
+    /// Collects the cartridge of the track from its book, as rendered into `out_dir` with
+    /// the layout of `options`. Units without a book chapter are left out.
+    pub fn cartridge(
+        &self,
+        out_dir: &Path,
+        options: CartridgeOptions,
+    ) -> Result<Cartridge, CartridgeError> {
+        let CartridgeOptions {
+            base_url,
+            layout,
+            profile,
+        } = options;
+        if base_url.origin().is_none() {
+            return Err(
+                Report::new(CartridgeError::default()).attach_printable(format!(
+                    "The base URL {base_url} has no origin, but the cartridge links to the hosted slides and book. Use an absolute URL like https://example.com/training/"
+                )),
+            );
+        }
+        let conditions = Conditions {
+            profile,
+            known_profiles: &self.profiles,
+            variables: &self.variables,
+        };
+        let book_url = base_url.join(&OutputLayout::dir_url_path(layout.book()));
+        let link = |url: &str| absolute_url(url, base_url, &book_url);
+
+        let mut cartridge = Cartridge {
+            entries: vec![],
+            chapters: 0,
+            slide_links: 0,
+            assignments: 0,
+        };
+        let mut resources = vec![];
+        let mut organization = String::new();
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut units = String::new();
+            for unit in module.data.units.iter() {
+                let page = layout
+                    .book()
+                    .join("src")
+                    .join(to_tag(&unit.data.name))
+                    .with_extension("md");
+                if !out_dir.join(&page).exists() {
+                    continue;
+                }
+                let markdown: String = out_dir.join(&page).read_to_string()?;
+                let id = identifier(&format!("{}_{}", label.path, unit.index));
+                let title = format!("Unit {} - {}", label.qualify(unit.index), unit.data.name);
+                let mut items = String::new();
+
+                let chapter = Resource {
+                    identifier: format!("chapter_{id}"),
+                    kind: "webcontent",
+                    file: format!("chapters/{}.html", to_tag(&unit.data.name)),
+                    web_content: true,
+                };
+                cartridge.entries.push(entry(
+                    &chapter.file,
+                    html_page(&title, &markdown_to_html(&markdown, &link)),
+                ));
+                item(&mut items, &chapter, &title);
+                resources.push(chapter);
+                cartridge.chapters += 1;
+
+                if unit.data.render_slides {
+                    let slides = Resource {
+                        identifier: format!("slides_{id}"),
+                        kind: "imswl_xmlv1p3",
+                        file: format!("slides/{id}.xml"),
+                        web_content: false,
+                    };
+                    let slides_title = format!("Slides {}", label.qualify(unit.index));
+                    let url = layout.deck_url(base_url, &id);
+                    cartridge
+                        .entries
+                        .push(entry(&slides.file, web_link(&slides_title, &url)));
+                    item(&mut items, &slides, &slides_title);
+                    resources.push(slides);
+                    cartridge.slide_links += 1;
+                }
+
+                let unit_minutes = unit_counts(&unit.data, &conditions)
+                    .change_context(CartridgeError::default())?
+                    .estimated_minutes;
+                let sections = exercise_sections(&markdown);
+                for ((heading, description), i) in sections.iter().zip(1..) {
+                    let assignment = Resource {
+                        identifier: format!("assignment_{id}_{i}"),
+                        kind: "assignment_xmlv1p0",
+                        file: format!("assignments/{id}_{i}/assignment.xml"),
+                        web_content: false,
+                    };
+                    let (title, optional) = match heading.strip_suffix(" *(optional)*") {
+                        Some(title) => (format!("{title} (optional)"), true),
+                        None => (heading.to_string(), false),
+                    };
+                    let name = heading
+                        .split_once(": ")
+                        .map_or(heading.as_str(), |(_, name)| name)
+                        .trim_end_matches(" *(optional)*");
+                    let minutes = exercise_minutes(&unit.data, name, unit_minutes, sections.len());
+                    let mut text = String::new();
+                    if let Some(minutes) = minutes {
+                        let _ = writeln!(text, "<p><em>Estimated time: {minutes} minutes</em></p>");
+                    }
+                    if optional {
+                        text.push_str("<p><em>This exercise is optional.</em></p>\n");
+                    }
+                    text.push_str(&markdown_to_html(description, &link));
+                    cartridge.entries.push(entry(
+                        &assignment.file,
+                        assignment_xml(&assignment.identifier, &title, &text),
+                    ));
+                    item(&mut items, &assignment, &title);
+                    resources.push(assignment);
+                    cartridge.assignments += 1;
+                }
+
+                let _ = write!(
+                    units,
+                    "<item identifier=\"unit_{id}\">\n<title>{}</title>\n{items}</item>\n",
+                    escape(&title)
+                );
+            }
+            if units.is_empty() {
+                continue;
+            }
+            let title = match label.display.is_empty() {
+                true => module.data.name.clone(),
+                false => format!("Module {} - {}", label.display, module.data.name),
+            };
+            let _ = write!(
+                organization,
+                "<item identifier=\"module_{}\">\n<title>{}</title>\n{units}</item>\n",
+                identifier(&label.path),
+                escape(&title)
+            );
+        }
+
+        cartridge.entries.insert(
+            0,
+            entry(
+                CARTRIDGE_MANIFEST_FILE,
+                manifest_xml(&self.name, &organization, &resources),
+            ),
+        );
+        check_references(&cartridge.entries)?;
+        Ok(cartridge)
+    }
+}
+
+/// Checks that the cartridge has a manifest, and every file the manifest refers to
+pub fn check_references(entries: &[Entry]) -> Result<(), CartridgeError> {
+    let Some(manifest) = entries.iter().find(|e| e.path == CARTRIDGE_MANIFEST_FILE) else {
+        return Err(Report::new(CartridgeError::default())
+            .attach_printable(format!("The cartridge has no {CARTRIDGE_MANIFEST_FILE}")));
+    };
+    let manifest = manifest
+        .content
+        .bytes()
+        .change_context(CartridgeError::default())?;
+    let manifest = String::from_utf8_lossy(&manifest);
+    let missing: Vec<_> = manifest
+        .split("<file href=\"")
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"'))
+        .map(|(href, _)| unescape(href))
+        .filter(|href| !entries.iter().any(|e| &e.path == href))
+        .collect();
+    if !missing.is_empty() {
+        return Err(
+            Report::new(CartridgeError::default()).attach_printable(format!(
+                "The manifest of the cartridge refers to files it doesn't have: {}",
+                missing.join(", ")
+            )),
+        );
+    }
+    Ok(())
+}
+
+fn entry(path: &str, content: String) -> Entry {
+    Entry {
+        path: path.to_string(),
+        executable: false,
+        content: EntryContent::Bytes(content.into_bytes()),
+    }
+}
+
+/// Adds an item for the resource to the organization
+fn item(items: &mut String, resource: &Resource, title: &str) {
+    let _ = write!(
+        items,
+        "<item identifier=\"item_{0}\" identifierref=\"{0}\">\n<title>{1}</title>\n</item>\n",
+        resource.identifier,
+        escape(title)
+    );
+}
+
+/// The exercise sections of a book chapter, as their heading and the Markdown below it
+fn exercise_sections(markdown: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = vec![];
+    let mut in_exercise = false;
+    let mut fence = None;
+    for line in markdown.lines() {
+        if let Some(marker) = fence_marker(line) {
+            fence = match fence {
+                Some(open) if marker.starts_with(open) => None,
+                Some(open) => Some(open),
+                None => Some(marker),
+            };
+        }
+        if fence.is_none() {
+            if let Some(heading) = line.strip_prefix("## ") {
+                in_exercise = heading.starts_with("Exercise ");
+                if in_exercise {
+                    sections.push((heading.trim().to_string(), String::new()));
+                }
+                continue;
+            }
+        }
+        if let (true, Some((_, description))) = (in_exercise, sections.last_mut()) {
+            description.push_str(line);
+            description.push('\n');
+        }
+    }
+    sections
+}
+
+/// How long an exercise takes: its share of the sessions of the unit that list it, or
+/// otherwise its share of the estimated duration of the unit
+pub(crate) fn exercise_minutes(
+    unit: &Unit,
+    name: &str,
+    unit_minutes: u32,
+    exercises: usize,
+) -> Option<u32> {
+    let scheduled: u32 = unit
+        .sessions
+        .iter()
+        .filter(|s| s.exercises.iter().any(|e| e == name))
+        .filter_map(|s| s.duration_minutes.map(|m| m / s.exercises.len() as u32))
+        .sum();
+    match scheduled {
+        0 if unit_minutes == 0 => None,
+        0 => Some((unit_minutes / exercises as u32).max(1)),
+        minutes => Some(minutes),
+    }
+}
+
+/// Makes a link of the book absolute: relative ones point into the hosted book, with their
+/// Markdown pages as HTML pages, and those from the root of the site get the origin
+pub(crate) fn absolute_url(url: &str, base_url: &BaseUrl, book_url: &str) -> String {
+    if url.starts_with('#') || url.contains("://") || url.starts_with("mailto:") {
+        return url.to_string();
+    }
+    if url.starts_with('/') {
+        return format!("{}{url}", base_url.origin().unwrap_or_default());
+    }
+    let (path, anchor) = match url.split_once('#') {
+        Some((path, anchor)) => (path, format!("#{anchor}")),
+        None => (url, String::new()),
+    };
+    let path = match path.strip_suffix(".md") {
+        Some(page) => format!("{page}.html"),
+        None => path.to_string(),
+    };
+    format!("{book_url}{path}{anchor}")
+}
+
+fn manifest_xml(track: &str, organization: &str, resources: &[Resource]) -> String {
+    let identifier = format!("modmod_{}", identifier(&to_tag(track)));
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="{identifier}" xmlns="http://www.imsglobal.org/xsd/imsccv1p3/imscp_v1p1" xmlns:lomimscc="http://ltsc.ieee.org/xsd/imsccv1p3/LOM/manifest" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imsccv1p3/imscp_v1p1 http://www.imsglobal.org/profile/cc/ccv1p3/ccv1p3_imscp_v1p2_v1p0.xsd http://ltsc.ieee.org/xsd/imsccv1p3/LOM/manifest http://www.imsglobal.org/profile/cc/ccv1p3/LOM/ccv1p3_lommanifest_v1p0.xsd">
+<metadata>
+<schema>IMS Common Cartridge</schema>
+<schemaversion>1.3.0</schemaversion>
+<lomimscc:lom>
+<lomimscc:general>
+<lomimscc:title>
+<lomimscc:string>{}</lomimscc:string>
+</lomimscc:title>
+</lomimscc:general>
+</lomimscc:lom>
+</metadata>
+<organizations>
+<organization identifier="organization" structure="rooted-hierarchy">
+<item identifier="root">
+{organization}</item>
+</organization>
+</organizations>
+<resources>
+"#,
+        escape(track)
+    );
+    for resource in resources {
+        let href = match resource.web_content {
+            true => format!(" href=\"{}\"", escape(&resource.file)),
+            false => String::new(),
+        };
+        let _ = write!(
+            xml,
+            "<resource identifier=\"{}\" type=\"{}\"{href}>\n<file href=\"{}\"/>\n</resource>\n",
+            resource.identifier,
+            resource.kind,
+            escape(&resource.file)
+        );
+    }
+    xml.push_str("</resources>\n</manifest>\n");
+    xml
+}
+
+fn web_link(title: &str, url: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<webLink xmlns="http://www.imsglobal.org/xsd/imsccv1p3/imswl_v1p3" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imsccv1p3/imswl_v1p3 http://www.imsglobal.org/profile/cc/ccv1p3/ccv1p3_imswl_v1p3.xsd">
+<title>{}</title>
+<url href="{}" target="_blank"/>
+</webLink>
+"#,
+        escape(title),
+        escape(url)
+    )
+}
+
+fn assignment_xml(identifier: &str, title: &str, html: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<assignment identifier="{identifier}" xmlns="http://www.imsglobal.org/xsd/imscc_extensions/assignment" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imscc_extensions/assignment http://www.imsglobal.org/profile/cc/cc_extensions/cc_extresource_assignmentv1p0_v1p0.xsd">
+<title>{}</title>
+<text texttype="text/html">{}</text>
+<gradable>false</gradable>
+</assignment>
+"#,
+        escape(title),
+        escape(html)
+    )
+}
+
+pub(crate) fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// Identifiers in the manifest are XML names, so anything but letters, digits, dashes and
+/// underscores is replaced
+pub(crate) fn identifier(text: &str) -> String {
+    text.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect()
+}
+
+/// Escapes text for XML and HTML
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The marker of a line that opens or closes a fenced code block, like ```` ``` ````
+fn fence_marker(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    ["```", "~~~"].into_iter().find_map(|fence| {
+        line.starts_with(fence).then(|| {
+            let length = line.len() - line.trim_start_matches(fence.as_bytes()[0] as char).len();
+            &line[..length]
+        })
+    })
+}
+
+/// Converts the Markdown of the book to HTML. It knows what the book is written in:
+/// headings, paragraphs, fenced code, lists, quotes, emphasis, code, links and images, and
+/// lines of HTML, which are passed through. Links are passed through `link`.
+pub(crate) fn markdown_to_html(markdown: &str, link: &dyn Fn(&str) -> String) -> String {
+    let mut html = String::new();
+    let mut paragraph = false;
+    // The indentation and tag of the open lists, which each have an open item
+    let mut lists: Vec<(usize, &str)> = vec![];
+    let mut quote: Vec<&str> = vec![];
+    let mut lines = markdown.lines().peekable();
+
+    let close_paragraph = |html: &mut String, paragraph: &mut bool| {
+        if std::mem::take(paragraph) {
+            html.push_str("</p>\n");
+        }
+    };
+    let close_lists = |html: &mut String, lists: &mut Vec<(usize, &str)>, indent: Option<usize>| {
+        while let Some((open, tag)) = lists.last() {
+            if indent.is_some_and(|indent| *open <= indent) {
+                break;
+            }
+            let _ = writeln!(html, "</li>\n</{tag}>");
+            lists.pop();
+        }
+    };
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            quote.push(rest.strip_prefix(' ').unwrap_or(rest));
+            if lines
+                .peek()
+                .is_some_and(|l| l.trim_start().starts_with('>'))
+            {
+                continue;
+            }
+            close_paragraph(&mut html, &mut paragraph);
+            let _ = write!(
+                html,
+                "<blockquote>\n{}</blockquote>\n",
+                markdown_to_html(&quote.join("\n"), link)
+            );
+            quote.clear();
+            continue;
+        }
+        if let Some(marker) = fence_marker(line) {
+            close_paragraph(&mut html, &mut paragraph);
+            let language = trimmed[marker.len()..].trim();
+            let mut code = String::new();
+            for line in lines.by_ref() {
+                if fence_marker(line).is_some_and(|m| m.starts_with(marker)) {
+                    break;
+                }
+                code.push_str(line);
+                code.push('\n');
+            }
+            let class = match language.split([',', ' ']).next() {
+                Some(language) if !language.is_empty() => {
+                    format!(" class=\"language-{}\"", escape(language))
+                }
+                _ => String::new(),
+            };
+            let _ = writeln!(html, "<pre><code{class}>{}</code></pre>", escape(&code));
+            continue;
+        }
+        if trimmed.is_empty() {
+            close_paragraph(&mut html, &mut paragraph);
+            if lines
+                .peek()
+                .is_some_and(|next| !next.starts_with([' ', '\t']) && list_item(next).is_none())
+            {
+                close_lists(&mut html, &mut lists, None);
+            }
+            continue;
+        }
+        if let Some((level, heading)) = heading(line) {
+            close_paragraph(&mut html, &mut paragraph);
+            close_lists(&mut html, &mut lists, None);
+            let _ = writeln!(
+                html,
+                "<h{level} id=\"{}\">{}</h{level}>",
+                escape(&heading_anchor(heading)),
+                inline(heading, link)
+            );
+            continue;
+        }
+        if let Some((indent, tag, text)) = list_item(line) {
+            close_paragraph(&mut html, &mut paragraph);
+            close_lists(&mut html, &mut lists, Some(indent));
+            match lists.last() {
+                Some((open, open_tag)) if *open == indent && *open_tag == tag => {
+                    html.push_str("</li>\n<li>");
+                }
+                Some((open, open_tag)) if *open == indent => {
+                    let _ = write!(html, "</li>\n</{open_tag}>\n<{tag}>\n<li>");
+                    lists.pop();
+                    lists.push((indent, tag));
+                }
+                _ => {
+                    let _ = write!(html, "<{tag}>\n<li>");
+                    lists.push((indent, tag));
+                }
+            }
+            html.push_str(&inline(text.trim_start(), link));
+            html.push('\n');
+            continue;
+        }
+        if !paragraph && trimmed.starts_with('<') {
+            // HTML blocks go on until the end of a comment, or else the next blank line
+            let comment = trimmed.starts_with("<!--");
+            let mut block = line;
+            loop {
+                html.push_str(block);
+                html.push('\n');
+                let ended = match comment {
+                    true => block.contains("-->"),
+                    false => lines.peek().is_none_or(|next| next.trim().is_empty()),
+                };
+                match lines.next_if(|_| !ended) {
+                    Some(next) => block = next,
+                    None => break,
+                }
+            }
+            continue;
+        }
+        if !paragraph && lists.is_empty() {
+            html.push_str("<p>");
+            paragraph = true;
+        }
+        html.push_str(&inline(trimmed, link));
+        html.push('\n');
+    }
+    close_paragraph(&mut html, &mut paragraph);
+    close_lists(&mut html, &mut lists, None);
+    html
+}
+
+/// The level and text of an ATX heading
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6)
+        .contains(&level)
+        .then(|| (level, text.trim().trim_end_matches('#').trim_end()))
+}
+
+/// The indentation, list tag and text of a list item
+fn list_item(line: &str) -> Option<(usize, &'static str, &str)> {
+    let trimmed = line.trim_start();
+    let indent: usize = line[..line.len() - trimmed.len()]
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    if let Some(text) = ["- ", "* ", "+ "]
+        .into_iter()
+        .find_map(|bullet| trimmed.strip_prefix(bullet))
+    {
+        return Some((indent, "ul", text));
+    }
+    let digits = trimmed.len()
+        - trimmed
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let text = trimmed[digits..].strip_prefix(". ")?;
+    (digits > 0).then_some((indent, "ol", text))
+}
+
+/// Converts the inline Markdown of a line to HTML
+pub(crate) fn inline(text: &str, link: &dyn Fn(&str) -> String) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+        match c {
+            '\\' if after.starts_with(|c: char| c.is_ascii_punctuation()) => {
+                let escaped = after.chars().next().unwrap_or_default();
+                html.push_str(&escape(&escaped.to_string()));
+                rest = &after[escaped.len_utf8()..];
+            }
+            '`' => {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let fence = &rest[..ticks];
+                match rest[ticks..].find(fence) {
+                    Some(end) => {
+                        let code = &rest[ticks..ticks + end];
+                        let _ = write!(html, "<code>{}</code>", escape(code.trim()));
+                        rest = &rest[ticks + end + ticks..];
+                    }
+                    None => {
+                        html.push_str(fence);
+                        rest = &rest[ticks..];
+                    }
+                }
+            }
+            '!' if after.starts_with('[') => match link_parts(after) {
+                Some((alt, url, end)) => {
+                    let _ = write!(
+                        html,
+                        "<img src=\"{}\" alt=\"{}\">",
+                        escape(&link(url)),
+                        escape(alt)
+                    );
+                    rest = &after[end..];
+                }
+                None => {
+                    html.push('!');
+                    rest = after;
+                }
+            },
+            '[' => match link_parts(rest) {
+                Some((text, url, end)) => {
+                    let _ = write!(
+                        html,
+                        "<a href=\"{}\">{}</a>",
+                        escape(&link(url)),
+                        inline(text, link)
+                    );
+                    rest = &rest[end..];
+                }
+                None => {
+                    html.push('[');
+                    rest = after;
+                }
+            },
+            '*' => {
+                let marker = if after.starts_with('*') { "**" } else { "*" };
+                let inner = &rest[marker.len()..];
+                let end = match marker {
+                    "**" => inner.find("**"),
+                    _ => inner
+                        .char_indices()
+                        .find(|(i, c)| *c == '*' && !inner[i + 1..].starts_with('*'))
+                        .map(|(i, _)| i),
+                };
+                match end {
+                    Some(end) if end > 0 && !inner.starts_with(char::is_whitespace) => {
+                        let tag = if marker == "**" { "strong" } else { "em" };
+                        let _ = write!(html, "<{tag}>{}</{tag}>", inline(&inner[..end], link));
+                        rest = &inner[end + marker.len()..];
+                    }
+                    _ => {
+                        html.push_str(marker);
+                        rest = inner;
+                    }
+                }
+            }
+            '<' if after.starts_with("http") && after.contains('>') => {
+                let (url, remainder) = after.split_once('>').unwrap_or((after, ""));
+                let _ = write!(html, "<a href=\"{0}\">{0}</a>", escape(url));
+                rest = remainder;
+            }
+            c => {
+                html.push_str(&escape(&c.to_string()));
+                rest = after;
+            }
+        }
+    }
+    html
+}
+
+/// The text and URL of a link like `[text](url)` at the start of `text`, and where it ends
+fn link_parts(text: &str) -> Option<(&str, &str, usize)> {
+    let mut depth = 0;
+    let close = text.char_indices().find_map(|(i, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(i)
+    })?;
+    let url_part = text[close + 1..].strip_prefix('(')?;
+    let url_end = url_part.find(')')?;
+    let url = url_part[..url_end]
+        .split_once(' ')
+        .map_or(&url_part[..url_end], |(url, _)| url);
+    Some((&text[1..close], url, close + 2 + url_end + 1))
+}
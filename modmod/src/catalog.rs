@@ -0,0 +1,460 @@
+//! Stable codes of the errors and warnings of modmod, like `E0101` for a file a definition
+//! refers to that doesn't exist, so that tools can tell failures apart without parsing
+//! their messages, and `modmod explain` can describe each of them.
+//!
+//! Error codes start with `E` and warning codes with `W`, and the first two digits group
+//! them: `01` is about definitions and content, `02` about names and numbering, `03` about
+//! the output, and so on. Like the names of [`WarningCode`]s, the codes are stable: a code
+//! is never renumbered or given to another problem, and the codes of problems that are
+//! gone stay unused.
+
+use error_stack::{Frame, Report};
+use serde::Serialize;
+
+use crate::{
+    conditional::ConditionalError, failure::ErrorCode, frontmatter::FrontmatterError,
+    report::WarningCode,
+};
+
+/// What kind of problem an error is about. Attached to the reports of errors that tools may
+/// want to react to; other errors get the code of their [`ErrorCode`], see
+/// [`ErrorKind::of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    /// Rendering, copying or writing the output failed
+    RenderFailed,
+    /// A definition or its content is invalid
+    InvalidContent,
+    /// The command line arguments are wrong
+    InvalidArguments,
+    /// A file or folder a definition refers to doesn't exist
+    MissingContentFile,
+    /// A definition that can't be parsed
+    InvalidDefinition,
+    /// A frontmatter block that can't be parsed
+    InvalidFrontmatter,
+    /// A conditional block or condition that is malformed
+    InvalidCondition,
+    /// A template, profile or other item that is referred to, but not defined
+    UndefinedReference,
+    /// Definitions that include each other
+    CircularInclude,
+    /// A track that requires another version of modmod
+    UnsupportedModmodVersion,
+    /// A key in a definition that is most likely a misspelling of a key modmod knows
+    UnknownKey,
+    /// Two items that would end up under the same name in the output
+    DuplicateName,
+    /// Two modules or units with the same number
+    DuplicateIndex,
+    /// An alias of a topic or exercise that is taken
+    DuplicateAlias,
+    /// The same definition included twice
+    DuplicateInclude,
+    /// Files with different content that would be copied to the same place
+    CopyConflict,
+    /// A file or folder of the output that can't be written
+    WriteFailed,
+    /// Content that differs from its lockfile, or a track that isn't locked
+    LockMismatch,
+}
+
+impl ErrorKind {
+    pub const ALL: &'static [ErrorKind] = &[
+        Self::RenderFailed,
+        Self::InvalidContent,
+        Self::InvalidArguments,
+        Self::MissingContentFile,
+        Self::InvalidDefinition,
+        Self::InvalidFrontmatter,
+        Self::InvalidCondition,
+        Self::UndefinedReference,
+        Self::CircularInclude,
+        Self::UnsupportedModmodVersion,
+        Self::UnknownKey,
+        Self::DuplicateName,
+        Self::DuplicateIndex,
+        Self::DuplicateAlias,
+        Self::DuplicateInclude,
+        Self::CopyConflict,
+        Self::WriteFailed,
+        Self::LockMismatch,
+    ];
+
+    /// The stable code of the error, like `E0101`
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::RenderFailed => "E0001",
+            Self::InvalidContent => "E0002",
+            Self::InvalidArguments => "E0003",
+            Self::MissingContentFile => "E0101",
+            Self::InvalidDefinition => "E0102",
+            Self::InvalidFrontmatter => "E0103",
+            Self::InvalidCondition => "E0104",
+            Self::UndefinedReference => "E0105",
+            Self::CircularInclude => "E0106",
+            Self::UnsupportedModmodVersion => "E0107",
+            Self::UnknownKey => "E0108",
+            Self::DuplicateName => "E0201",
+            Self::DuplicateIndex => "E0202",
+            Self::DuplicateAlias => "E0203",
+            Self::DuplicateInclude => "E0204",
+            Self::CopyConflict => "E0301",
+            Self::WriteFailed => "E0302",
+            Self::LockMismatch => "E0401",
+        }
+    }
+
+    /// The kind of error this is, which sets the exit code. The problems with the output
+    /// are render errors, the others are about the content or the arguments.
+    pub fn code(self) -> ErrorCode {
+        match self {
+            Self::RenderFailed | Self::CopyConflict | Self::WriteFailed => ErrorCode::Render,
+            Self::InvalidArguments => ErrorCode::Usage,
+            Self::InvalidContent
+            | Self::MissingContentFile
+            | Self::InvalidDefinition
+            | Self::InvalidFrontmatter
+            | Self::InvalidCondition
+            | Self::UndefinedReference
+            | Self::CircularInclude
+            | Self::UnsupportedModmodVersion
+            | Self::UnknownKey
+            | Self::DuplicateName
+            | Self::DuplicateIndex
+            | Self::DuplicateAlias
+            | Self::DuplicateInclude
+            | Self::LockMismatch => ErrorCode::Validation,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RenderFailed => "render-failed",
+            Self::InvalidContent => "invalid-content",
+            Self::InvalidArguments => "invalid-arguments",
+            Self::MissingContentFile => "missing-content-file",
+            Self::InvalidDefinition => "invalid-definition",
+            Self::InvalidFrontmatter => "invalid-frontmatter",
+            Self::InvalidCondition => "invalid-condition",
+            Self::UndefinedReference => "undefined-reference",
+            Self::CircularInclude => "circular-include",
+            Self::UnsupportedModmodVersion => "unsupported-modmod-version",
+            Self::UnknownKey => "unknown-key",
+            Self::DuplicateName => "duplicate-name",
+            Self::DuplicateIndex => "duplicate-index",
+            Self::DuplicateAlias => "duplicate-alias",
+            Self::DuplicateInclude => "duplicate-include",
+            Self::CopyConflict => "copy-conflict",
+            Self::WriteFailed => "write-failed",
+            Self::LockMismatch => "lock-mismatch",
+        }
+    }
+
+    /// The kind attached to the report, or else the one its contexts tell, like
+    /// [`ErrorKind::InvalidCondition`] for a [`ConditionalError`], or else the general
+    /// kind of its [`ErrorCode`]
+    pub fn of<C>(report: &Report<C>) -> Self {
+        Self::of_frames(report.frames()).unwrap_or(ErrorCode::of(report).into())
+    }
+
+    /// The kind attached to one of the frames, or else the one their contexts tell, if any.
+    /// For a single error of a report that collects more than one.
+    pub fn of_frames<'f>(frames: impl Iterator<Item = &'f Frame> + Clone) -> Option<Self> {
+        frames
+            .clone()
+            .find_map(|frame| frame.downcast_ref::<ErrorKind>().copied())
+            .or_else(|| {
+                frames.clone().find_map(|frame| {
+                    if frame.downcast_ref::<ConditionalError>().is_some() {
+                        Some(Self::InvalidCondition)
+                    } else if frame.downcast_ref::<FrontmatterError>().is_some() {
+                        Some(Self::InvalidFrontmatter)
+                    } else {
+                        None
+                    }
+                })
+            })
+    }
+}
+
+impl From<ErrorCode> for ErrorKind {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::Render => Self::RenderFailed,
+            ErrorCode::Validation => Self::InvalidContent,
+            ErrorCode::Usage => Self::InvalidArguments,
+        }
+    }
+}
+
+impl Serialize for ErrorKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+/// An error or warning in the catalog, for `modmod explain`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CatalogEntry {
+    /// The stable code, like `E0101` or `W0102`
+    pub code: &'static str,
+    /// The name of the error, or the [`WarningCode`] `--deny` takes
+    pub name: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+impl From<ErrorKind> for CatalogEntry {
+    fn from(kind: ErrorKind) -> Self {
+        let (title, explanation) = match kind {
+            ErrorKind::RenderFailed => (
+                "Rendering failed",
+                "Rendering, copying or writing the output failed for a reason that has no code of its own. The message tells what went wrong.",
+            ),
+            ErrorKind::InvalidContent => (
+                "Invalid content",
+                "A definition or the content it refers to is invalid in a way that has no code of its own. The message tells what is wrong and where.",
+            ),
+            ErrorKind::InvalidArguments => (
+                "Invalid arguments",
+                "The command line arguments don't make sense together, like skipping every part of the output. Run the command with --help to see what it takes.",
+            ),
+            ErrorKind::MissingContentFile => (
+                "Missing content file",
+                "A track, module or topic definition refers to a file or folder that doesn't exist. Paths are relative to the folder of the definition that holds them. Check the path for typos, or render with --lenient to render placeholders for missing content instead.",
+            ),
+            ErrorKind::InvalidDefinition => (
+                "Invalid definition",
+                "A definition file can't be read or parsed: it isn't valid TOML, has a field of the wrong type, or has a schema version this modmod doesn't know. The message points at the line.",
+            ),
+            ErrorKind::InvalidFrontmatter => (
+                "Invalid frontmatter",
+                "The `---` block with topic metadata at the start of a content file can't be parsed, or sets keys other than objectives, summary and further_reading.",
+            ),
+            ErrorKind::InvalidCondition => (
+                "Invalid condition",
+                "A #[modmod:if ...] block isn't closed, has an #[modmod:else] or #[modmod:endif] without an #[modmod:if], or its condition can't be parsed or refers to a profile or variable the track doesn't define.",
+            ),
+            ErrorKind::UndefinedReference => (
+                "Undefined reference",
+                "A definition refers to a named template, profile, tag or module that the track doesn't define. The message lists the ones it does define.",
+            ),
+            ErrorKind::CircularInclude => (
+                "Circular include",
+                "Definitions include each other, directly or through others, so the track would never end. The message lists the chain of includes.",
+            ),
+            ErrorKind::UnsupportedModmodVersion => (
+                "Unsupported modmod version",
+                "The track sets a modmod_version requirement that this version of modmod doesn't meet. Upgrade modmod, or relax the requirement.",
+            ),
+            ErrorKind::UnknownKey => (
+                "Unknown key",
+                "A definition has a key that modmod doesn't know, but that is close to one it does, like objektives for objectives, so it is most likely misspelled. Keys that aren't close to any known key are ignored, so that definitions written for newer versions of modmod still load.",
+            ),
+            ErrorKind::DuplicateName => (
+                "Duplicate name",
+                "Two units, exercises or modules would end up under the same name in the output, like two units whose titles give the same slide deck file name. Rename one of them.",
+            ),
+            ErrorKind::DuplicateIndex => (
+                "Duplicate index",
+                "Two modules of a track, or two units of a module, are given the same number.",
+            ),
+            ErrorKind::DuplicateAlias => (
+                "Duplicate alias",
+                "An alias of a topic or exercise is the id of another one, or is used by two of them, so links to it can't be resolved.",
+            ),
+            ErrorKind::DuplicateInclude => (
+                "Duplicate include",
+                "A definition includes the same module or topic more than once, possibly through different paths.",
+            ),
+            ErrorKind::CopyConflict => (
+                "Copy conflict",
+                "Two files with different content would be copied to the same place in the output, like two slide images with the same file name. Rename one of them.",
+            ),
+            ErrorKind::WriteFailed => (
+                "Write failed",
+                "A file or folder of the output can't be created or written. Check that the output folder is writable and that the disk isn't full.",
+            ),
+            ErrorKind::LockMismatch => (
+                "Lock mismatch",
+                "The track was rendered with --locked, but it isn't locked or its content differs from its lockfile. Run modmod update-lock once the changes are reviewed.",
+            ),
+        };
+        Self {
+            code: kind.id(),
+            name: kind.as_str(),
+            title,
+            explanation,
+        }
+    }
+}
+
+impl From<WarningCode> for CatalogEntry {
+    fn from(code: WarningCode) -> Self {
+        let (title, explanation) = match code {
+            WarningCode::MissingContent => (
+                "Missing content",
+                "Content that can't be found while rendering with --lenient. A placeholder is rendered in its place.",
+            ),
+            WarningCode::EmptyTopic => (
+                "Empty topic",
+                "A topic has no slide content, so its section of the deck is empty.",
+            ),
+            WarningCode::InvalidModmodVersion => (
+                "Invalid modmod version requirement",
+                "The modmod_version of a track can't be parsed as a semver requirement, so it is ignored.",
+            ),
+            WarningCode::InvalidCourseVersion => (
+                "Invalid course version",
+                "A course version isn't loosely semver, like 2024.1 or 1.2.3. Pass --free-form-version to accept any version.",
+            ),
+            WarningCode::CourseVersionDowngrade => (
+                "Course version downgrade",
+                "Output is updated to an older course version than it was rendered with.",
+            ),
+            WarningCode::NoLicense => (
+                "No license",
+                "The track doesn't set a license, so the output isn't licensed for reuse, or doesn't set the text of its license, so no LICENSE is copied into the output.",
+            ),
+            WarningCode::NoExercises => (
+                "No exercises",
+                "A unit has no exercises. Set no_exercises = true in the unit if that is intended.",
+            ),
+            WarningCode::UnexpectedExercises => (
+                "Unexpected exercises",
+                "A unit sets no_exercises = true, but has exercises.",
+            ),
+            WarningCode::ReviewOverdue => (
+                "Review overdue",
+                "A topic was last reviewed longer ago than modmod check --review-max-age allows.",
+            ),
+            WarningCode::NeverReviewed => (
+                "Never reviewed",
+                "A topic doesn't record when it was last reviewed, which modmod check --review-max-age reports.",
+            ),
+            WarningCode::UnreferencedImage => (
+                "Unreferenced image",
+                "A topic lists an image that its content doesn't refer to.",
+            ),
+            WarningCode::IndexGap => (
+                "Index gap",
+                "A number is skipped in the numbering of the modules or units. Pass --allow-index-gaps if that is intended.",
+            ),
+            WarningCode::UnknownConfigKey => (
+                "Unknown configuration key",
+                "The configuration file sets a key that modmod doesn't know, which is ignored.",
+            ),
+            WarningCode::DuplicateTemplate => (
+                "Duplicate template",
+                "Slide templates have the same content, so one of them could be shared.",
+            ),
+            WarningCode::TemplateOverrideIgnored => (
+                "Template override ignored",
+                "A unit keeps its own slide template, though the render overrides the default slide template.",
+            ),
+            WarningCode::EmptyPlaceholder => (
+                "Empty placeholder",
+                "The slide template uses a placeholder that is empty for a deck.",
+            ),
+            WarningCode::MissingCourseField => (
+                "Missing course field",
+                "The slide template uses a course field that the track doesn't set.",
+            ),
+            WarningCode::UnresolvedPlaceholder => (
+                "Unresolved placeholder",
+                "A #[modmod:...] marker is left in the output: a misspelled placeholder, or a conditional marker that wasn't resolved.",
+            ),
+            WarningCode::RepeatedObjective => (
+                "Repeated objective",
+                "An objective of a unit repeats an earlier one, and is listed once.",
+            ),
+            WarningCode::NoContentPlaceholder => (
+                "No content placeholder",
+                "A slide template doesn't use #[modmod:content], so the decks it's used for have none of the slides of their topics.",
+            ),
+            WarningCode::AliasLink => (
+                "Alias link",
+                "A link uses the former id of a renamed topic or exercise. Link to its current id instead.",
+            ),
+            WarningCode::BrokenLink => (
+                "Broken link",
+                "A link points to a file or heading that doesn't exist, or is an invalid URL.",
+            ),
+            WarningCode::UnreachableLink => (
+                "Unreachable link",
+                "An external link can't be reached, or its server responds with an error.",
+            ),
+            WarningCode::LinkCache => (
+                "Link cache",
+                "The cache of external links that were checked can't be read or written, so links are checked again.",
+            ),
+            WarningCode::UntranslatableTopic => (
+                "Untranslatable topic",
+                "An inline topic can't be translated. Move it to its own topic.toml to translate it.",
+            ),
+            WarningCode::UnknownTranslation => (
+                "Unknown translation",
+                "A translation file is for a topic that isn't part of the track.",
+            ),
+            WarningCode::OutdatedTranslation => (
+                "Outdated translation",
+                "The source of a translation changed since it was translated.",
+            ),
+            WarningCode::Untranslated => (
+                "Untranslated",
+                "Strings of a topic aren't translated yet, or topics and exercise descriptions have no translation to the locale the track is rendered in.",
+            ),
+            WarningCode::LargeAsset => (
+                "Large asset",
+                "An image or exercise file is larger than the limit set with --max-asset-size.",
+            ),
+            WarningCode::KeptScaffold => (
+                "Kept scaffold",
+                "A rust-toolchain.toml, devcontainer or deploy workflow file in the output wasn't generated by modmod, so it is kept. Pass --force-scaffold to replace it.",
+            ),
+            WarningCode::ThumbnailsSkipped => (
+                "Thumbnails skipped",
+                "The previews of decks for --thumbnails can't be exported, as npm or the playwright-chromium package of the slides isn't installed, or the export of a deck failed. The decks are listed without a preview.",
+            ),
+            WarningCode::SitemapSkipped => (
+                "Sitemap skipped",
+                "The sitemap of the deployed site isn't written, as it needs absolute URLs and the base URL has no origin. Pass a --base-url like https://example.com/course/ to have one.",
+            ),
+            WarningCode::QrCodeSkipped => (
+                "QR code skipped",
+                "A QR code of --qr-code is left off the slides of the decks: the track doesn't set the feedback_url variable, the base URL has no origin that a phone can open the URL with, or the URL is too long for a QR code.",
+            ),
+            WarningCode::LargeSection => (
+                "Large section",
+                "The content of a topic or an exercise description is larger than the limit set with --max-section-size. It's rendered, but makes a deck or book page that is slow to load; large inline images are better kept as files in the images folder of the topic.",
+            ),
+            WarningCode::AttributionUnknown => (
+                "Attribution unknown",
+                "A topic that --credits lists has no authors, or no license while the track has none either, so its material can't be credited. Set authors and license in its topic.toml, or a [license] with an attribution for the track.",
+            ),
+        };
+        Self {
+            code: code.id(),
+            name: code.as_str(),
+            title,
+            explanation,
+        }
+    }
+}
+
+/// All errors and warnings, errors first, in the order of their codes
+pub fn catalog() -> Vec<CatalogEntry> {
+    let mut entries: Vec<CatalogEntry> = ErrorKind::ALL.iter().map(|&k| k.into()).collect();
+    let mut warnings: Vec<CatalogEntry> = WarningCode::ALL.iter().map(|&c| c.into()).collect();
+    warnings.sort_by_key(|entry| entry.code);
+    entries.extend(warnings);
+    entries
+}
+
+/// The entry with the code, like `E0101` or `w0101`, or with the name, like `empty-topic`
+pub fn explain(code: &str) -> Option<CatalogEntry> {
+    catalog()
+        .into_iter()
+        .find(|entry| entry.code.eq_ignore_ascii_case(code) || entry.name == code)
+}
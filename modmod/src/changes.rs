@@ -0,0 +1,118 @@
+//! Finding the units of a track that are affected by the changes since a git revision, so
+//! that CI can render only those.
+//!
+//! Changes are taken from git: files that differ between the revision and the working tree,
+//! both paths of renamed files, and files git doesn't track. A file affects the units that
+//! include the topic it belongs to, and files of the track as a whole, like its definition,
+//! affect all units.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{git, Track};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ChangesError {}
+
+impl fmt::Display for ChangesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to find the changed content of the track")
+    }
+}
+
+impl error_stack::Context for ChangesError {}
+
+/// What changed in a track since a revision
+#[derive(Debug, Default)]
+pub struct ChangedContent {
+    /// Changed files that belong to the track as a whole. When there are any, all units are
+    /// affected.
+    pub track_files: Vec<PathBuf>,
+    /// Ids of the units that are affected, in the order of the track
+    pub units: Vec<String>,
+    /// Ids of the units that are not affected
+    pub unchanged_units: Vec<String>,
+    /// All files under the content root that changed, whether the track uses them or not
+    pub files: Vec<PathBuf>,
+}
+
+impl ChangedContent {
+    /// Whether all units are affected, so that the whole track is rendered
+    pub fn affects_all(&self) -> bool {
+        self.unchanged_units.is_empty()
+    }
+}
+
+impl Track {
+    /// The content of the track that changed since the git revision `rev`, in the working
+    /// tree of the repository the content root of the track is in
+    pub fn changed_since(&self, rev: &str) -> Result<ChangedContent, ChangesError> {
+        let root = &self.content_root;
+        let toplevel = git::toplevel::<ChangesError>(root).attach_printable_lazy(|| {
+            format!(
+                "The content at {} isn't in a git repository",
+                root.display()
+            )
+        })?;
+        let files = git::changed_files::<ChangesError>(root, rev, root)?;
+        // Paths that git reports may go through symlinks the track's paths don't
+        let files: Vec<PathBuf> = files
+            .into_iter()
+            .map(|file| toplevel.join(file))
+            .map(|file| file.canonicalize().unwrap_or(file))
+            .collect();
+        Ok(self.affected_by(files))
+    }
+
+    /// The units affected by changes to `files`
+    fn affected_by(&self, files: Vec<PathBuf>) -> ChangedContent {
+        let changed = |path: &Path| files.iter().any(|f| f == path);
+        let changed_under = |dir: &Path| files.iter().any(|f| f.starts_with(dir));
+
+        let mut track_files: Vec<PathBuf> = vec![self.definition.clone()];
+        track_files.extend(self.bases.iter().cloned());
+        track_files.extend(self.default_slide_template.iter().cloned());
+        track_files.extend(self.templates.values().cloned());
+        let mut content = ChangedContent {
+            track_files: track_files.into_iter().filter(|f| changed(f)).collect(),
+            ..Default::default()
+        };
+        content.track_files.extend(
+            self.shared_crates
+                .iter()
+                .filter(|c| changed_under(&c.path))
+                .map(|c| c.path.clone()),
+        );
+
+        for module in self.modules.iter() {
+            let module_changed =
+                !content.track_files.is_empty() || changed(&module.data.definition);
+            for unit in module.data.units.iter().map(|u| &u.data) {
+                let affected = module_changed
+                    || unit.template.as_deref().is_some_and(changed)
+                    || unit.topics.iter().map(|t| &t.data).any(|topic| {
+                        // Anything in the folder of a topic, like an image that's not used
+                        // yet, is taken to be part of it
+                        topic.definition.parent().is_some_and(changed_under)
+                            || topic.content.path().is_some_and(changed)
+                            || topic.images.iter().any(|i| changed(&i.path))
+                            || topic.exercises.iter().map(|e| &e.data).any(|exercise| {
+                                changed_under(&exercise.path)
+                                    || exercise.description.as_deref().is_some_and(changed)
+                            })
+                    });
+                match affected {
+                    true => content.units.push(unit.id()),
+                    false => content.unchanged_units.push(unit.id()),
+                }
+            }
+        }
+        content.files = files;
+        content
+    }
+}
@@ -0,0 +1,944 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
+    fmt, fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    book::heading_anchor,
+    date::Date,
+    io::PathExt,
+    load::is_image,
+    report::{Warning, WarningCode},
+    version::CourseVersion,
+    Topic, TopicContent, Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct CheckError {}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to check track")
+    }
+}
+
+impl error_stack::Context for CheckError {}
+
+/// Options for the checks run by [`Track::check`]
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Topics that were last reviewed longer ago than this many days are reported, as are
+    /// topics that don't record when they were reviewed. Review dates aren't checked
+    /// without it.
+    pub review_max_age_days: Option<i64>,
+    /// The date against which review dates are compared
+    pub today: Date,
+    /// Don't report gaps in the numbering of modules and units
+    pub allow_index_gaps: bool,
+    /// Skip the checks that need to read topic content and exercise descriptions
+    pub structure_only: bool,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            review_max_age_days: None,
+            today: Date::today(),
+            allow_index_gaps: false,
+            structure_only: false,
+        }
+    }
+}
+
+impl Track {
+    /// Check the track with the default [`CheckOptions`], see [`Track::check`]
+    pub fn validate(&self) -> Vec<Warning> {
+        self.check(&CheckOptions::default())
+    }
+
+    /// Check the track for problems that don't prevent it from being rendered,
+    /// but that authors should know about.
+    pub fn check(&self, opts: &CheckOptions) -> Vec<Warning> {
+        // Problems found while loading the track
+        let mut warnings = self.warnings.clone();
+        if self.license.is_none() {
+            warnings.push(Warning::new(
+                WarningCode::NoLicense,
+                format!(
+                    "Track '{}' has no [license], so its output doesn't say how it may be used",
+                    self.name
+                ),
+                Some(&self.definition),
+            ));
+        }
+        // Renders fail on it, unless the version is accepted as a free-form one
+        if let Some(version) = &self.version {
+            if let Err(e) = CourseVersion::parse(version, false) {
+                warnings.push(Warning::new(
+                    WarningCode::InvalidCourseVersion,
+                    e.to_string(),
+                    Some(&self.definition),
+                ));
+            }
+        }
+        let mut seen_topics = HashSet::new();
+
+        for module in self.modules.iter().map(|m| &m.data) {
+            for unit in module.units.iter().map(|u| &u.data) {
+                let has_exercises = unit.topics.iter().any(|t| !t.data.exercises.is_empty());
+                let (code, message) = match (has_exercises, unit.no_exercises) {
+                    (false, false) => (
+                        WarningCode::NoExercises,
+                        "has no exercises. Set `no_exercises = true` if that is intended",
+                    ),
+                    (true, true) => (
+                        WarningCode::UnexpectedExercises,
+                        "sets `no_exercises = true`, but has exercises",
+                    ),
+                    _ => continue,
+                };
+                warnings.push(Warning::new(
+                    code,
+                    format!("Unit '{}' {message}", unit.name),
+                    Some(&module.definition),
+                ));
+            }
+        }
+
+        if !opts.allow_index_gaps {
+            warnings.extend(self.index_gaps());
+        }
+        if !opts.structure_only {
+            warnings.extend(self.alias_links());
+            warnings.extend(self.duplicate_templates());
+            warnings.extend(self.broken_links());
+        }
+
+        for topic in self.topics() {
+            // Topics can be included in more than one unit
+            if !seen_topics.insert(&topic.definition) {
+                continue;
+            }
+
+            if !opts.structure_only {
+                warnings.extend(unreferenced_images(topic));
+            }
+
+            let Some(review_max_age_days) = opts.review_max_age_days else {
+                continue;
+            };
+            match topic.last_reviewed {
+                Some(last_reviewed) => {
+                    let age = opts.today.days_after(&last_reviewed);
+                    if age > review_max_age_days {
+                        warnings.push(Warning::new(
+                            WarningCode::ReviewOverdue,
+                            format!(
+                                "Topic '{}' was last reviewed on {last_reviewed}, {age} days ago",
+                                topic.name
+                            ),
+                            Some(&topic.definition),
+                        ));
+                    }
+                }
+                None => warnings.push(Warning::new(
+                    WarningCode::NeverReviewed,
+                    format!("Topic '{}' has never been reviewed", topic.name),
+                    Some(&topic.definition),
+                )),
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Reports images that were found in the topic's images folder, but that its content never mentions
+fn unreferenced_images(topic: &Topic) -> Vec<Warning> {
+    let content = match &topic.content {
+        // Failing to read the content is reported when rendering
+        TopicContent::File(path) => fs::read_to_string(path)
+            .map(crate::io::normalize_text)
+            .unwrap_or_default(),
+        TopicContent::Inline(content) => content.clone(),
+    };
+    topic
+        .images
+        .iter()
+        .filter(|image| image.discovered)
+        .filter(|image| {
+            let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+            !content.contains(name.as_ref())
+        })
+        .map(|image| {
+            Warning::new(
+                WarningCode::UnreferencedImage,
+                format!(
+                    "Image {} is never referenced in the content of topic '{}'",
+                    image.path.display(),
+                    topic.name
+                ),
+                Some(&topic.definition),
+            )
+        })
+        .collect()
+}
+
+impl Track {
+    /// Reports missing numbers in the indices of the modules of the track
+    /// and of the units in each module
+    pub fn index_gaps(&self) -> Vec<Warning> {
+        let mut warnings = vec![];
+        let module_indices: Vec<_> = self.modules.iter().map(|m| m.index).collect();
+        for missing in missing_indices(module_indices) {
+            warnings.push(Warning::new(
+                WarningCode::IndexGap,
+                format!("There is no module with index {missing}"),
+                Some(&self.definition),
+            ));
+        }
+        for module in self.modules.iter() {
+            let unit_indices = module.data.units.iter().map(|u| u.index).collect();
+            for missing in missing_indices(unit_indices) {
+                warnings.push(Warning::new(
+                    WarningCode::IndexGap,
+                    format!(
+                        "Module '{}' has no unit with index {missing}",
+                        module.data.name
+                    ),
+                    None,
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl Track {
+    /// Reports links in topic content and exercise descriptions that still use
+    /// the former id of a renamed topic or exercise
+    pub fn alias_links(&self) -> Vec<Warning> {
+        let mut renamed = HashMap::new();
+        for topic in self.topics() {
+            renamed.extend(
+                topic
+                    .aliases
+                    .iter()
+                    .map(|a| (a.as_str(), topic.id.as_str())),
+            );
+        }
+        for exercise in self.exercises() {
+            renamed.extend(
+                exercise
+                    .aliases
+                    .iter()
+                    .map(|a| (a.as_str(), exercise.id.as_str())),
+            );
+        }
+        if renamed.is_empty() {
+            return vec![];
+        }
+
+        let mut warnings = vec![];
+        for file in self.markdown_files() {
+            // Failing to read the content is reported when rendering
+            let content = fs::read_to_string(file).unwrap_or_default();
+            for (_, target) in links(&content) {
+                let old = target
+                    .split(['/', '#', '?'])
+                    .map(|segment| segment.trim_end_matches(".html").trim_end_matches(".md"))
+                    .find_map(|segment| renamed.get_key_value(segment));
+                if let Some((old, new)) = old {
+                    warnings.push(Warning::new(
+                        WarningCode::AliasLink,
+                        format!("Link '{target}' uses '{old}', which has been renamed to '{new}'"),
+                        Some(file),
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl Track {
+    /// Reports slide templates that are different files with the same content,
+    /// which is usually a copy that was left behind
+    pub fn duplicate_templates(&self) -> Vec<Warning> {
+        let mut templates: Vec<&Path> = self
+            .templates
+            .values()
+            .map(PathBuf::as_path)
+            .chain(self.default_slide_template.as_deref())
+            .chain(self.units().filter_map(|u| u.template.as_deref()))
+            .collect();
+        templates.sort();
+        templates.dedup();
+
+        let mut by_content: HashMap<String, &Path> = HashMap::new();
+        let mut warnings = vec![];
+        for template in templates {
+            // Failing to read a template is reported when rendering
+            let Ok(content) = fs::read_to_string(template) else {
+                continue;
+            };
+            match by_content.get(&content) {
+                Some(first) => warnings.push(Warning::new(
+                    WarningCode::DuplicateTemplate,
+                    format!(
+                        "Slide template {} has the same content as {}",
+                        template.display(),
+                        first.display()
+                    ),
+                    Some(&self.definition),
+                )),
+                None => {
+                    by_content.insert(content, template);
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl Track {
+    /// The topic content and exercise description files of the track, in order
+    pub(crate) fn markdown_files(&self) -> Vec<&Path> {
+        let mut files: Vec<&Path> = self
+            .topics()
+            .filter_map(|t| t.content.path())
+            .chain(self.exercises().filter_map(|e| e.description.as_deref()))
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Reports links in topic content and exercise descriptions to files or headings that
+    /// don't exist, and URLs in them and in the further reading of the topics that aren't
+    /// valid. [`Track::check_external_links`] checks whether the URLs can still be reached.
+    pub fn broken_links(&self) -> Vec<Warning> {
+        self.broken_content_links()
+            .iter()
+            .map(BrokenLink::warning)
+            .collect()
+    }
+
+    /// The links [`Track::broken_links`] reports, sorted by file and line
+    pub(crate) fn broken_content_links(&self) -> Vec<BrokenLink> {
+        // Slide images are all copied into one folder, that slides refer to as `/images`
+        let images: HashSet<&OsStr> = self
+            .topics()
+            .flat_map(|t| t.images.iter())
+            .filter_map(|i| i.path.file_name())
+            .collect();
+
+        let mut broken = vec![];
+        for file in self.markdown_files() {
+            // Failing to read the content is reported when rendering
+            let Ok(content) = fs::read_to_string(file) else {
+                continue;
+            };
+            for (line, target) in links(&content) {
+                let problem = if is_url(target) {
+                    invalid_url(target)
+                } else {
+                    broken_local_link(file, &content, target, &images)
+                };
+                broken.extend(problem.map(|problem| BrokenLink::new(file, line, target, problem)));
+            }
+        }
+        for (file, line, url) in self.further_reading_links() {
+            if let Some(problem) = invalid_url(&url) {
+                broken.push(BrokenLink::new(&file, line, &url, problem));
+            }
+        }
+        broken.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        broken
+    }
+
+    /// The URLs in the further reading of the topics, with the file and line they are set
+    /// in: the topic definition, or the frontmatter of the topic's slides
+    pub(crate) fn further_reading_links(&self) -> Vec<(PathBuf, usize, String)> {
+        let mut found = vec![];
+        let mut files: HashMap<&Path, String> = HashMap::new();
+        for topic in self.topics() {
+            let sources = [Some(topic.definition.as_path()), topic.content.path()];
+            for url in topic.further_reading.iter().flat_map(|e| entry_urls(e)) {
+                let location = sources.iter().flatten().find_map(|&source| {
+                    let content = files
+                        .entry(source)
+                        .or_insert_with(|| fs::read_to_string(source).unwrap_or_default());
+                    let line = content.lines().position(|line| line.contains(url))?;
+                    Some((source.to_path_buf(), line + 1))
+                });
+                // Entries whose URL can't be found in a file are of an inline topic in
+                // another definition, or derived, so they are listed at the definition
+                let (file, line) = location.unwrap_or_else(|| (topic.definition.clone(), 1));
+                found.push((file, line, url.to_string()));
+            }
+        }
+        // Topics can be included in more than one unit
+        found.sort();
+        found.dedup();
+        found
+    }
+}
+
+/// A link that is broken, with the file and line it is in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    pub file: PathBuf,
+    pub line: usize,
+    pub target: String,
+    /// What is wrong with it, like `refers to a.md, which doesn't exist`
+    pub problem: String,
+    /// [`WarningCode::BrokenLink`], or [`WarningCode::UnreachableLink`] for external links
+    /// that can't be reached
+    pub code: WarningCode,
+}
+
+impl BrokenLink {
+    pub(crate) fn new(file: &Path, line: usize, target: &str, problem: String) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line,
+            target: target.to_string(),
+            problem,
+            code: WarningCode::BrokenLink,
+        }
+    }
+
+    pub fn warning(&self) -> Warning {
+        Warning::new(
+            self.code,
+            format!("Link '{}' {}", self.target, self.problem),
+            Some(&self.file),
+        )
+        .at_line(self.line)
+    }
+}
+
+/// The URLs of an entry of further reading: the targets of its Markdown links, or otherwise
+/// the words of it that are URLs
+fn entry_urls(entry: &str) -> Vec<&str> {
+    let targets: Vec<_> = links(entry).into_iter().map(|(_, target)| target).collect();
+    match targets.is_empty() {
+        true => entry
+            .split_whitespace()
+            .map(|word| word.trim_start_matches(['<', '(']))
+            .map(|word| word.trim_end_matches(['>', ')', '.', ',', ';']))
+            .filter(|word| is_url(word))
+            .collect(),
+        false => targets
+            .into_iter()
+            .filter(|target| is_url(target))
+            .collect(),
+    }
+}
+
+/// Targets of the Markdown links in the content, with the line they are on.
+/// Links in code are left out.
+pub(crate) fn links(content: &str) -> Vec<(usize, &str)> {
+    markdown_refs(content)
+        .into_iter()
+        .filter(|r| {
+            matches!(
+                r.kind,
+                RefKind::Link | RefKind::Image | RefKind::Definition(_)
+            )
+        })
+        .map(|r| (r.line, r.target))
+        .collect()
+}
+
+/// A link, image or reference definition in Markdown content, see [`markdown_refs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MarkdownRef<'c> {
+    /// The line it is on, counted from 1
+    pub(crate) line: usize,
+    pub(crate) kind: RefKind<'c>,
+    /// What it points to, or the label of the definition it uses for
+    /// [`RefKind::ImageLabel`]
+    pub(crate) target: &'c str,
+    /// Where `target` is in the content, in bytes
+    pub(crate) span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RefKind<'c> {
+    /// `[text](target)`
+    Link,
+    /// `![alt](target)`
+    Image,
+    /// `<img src="target">`, with either quotes or none
+    HtmlImage,
+    /// `![alt][label]`, `![label][]` or `![label]`, an image if there is a definition of
+    /// the label
+    ImageLabel,
+    /// `[label]: target`, with the label
+    Definition(&'c str),
+}
+
+/// The links, images and reference definitions in Markdown content, in order. Those in
+/// code are left out.
+pub(crate) fn markdown_refs(content: &str) -> Vec<MarkdownRef<'_>> {
+    let mut refs = vec![];
+    let mut in_code_block = false;
+    for (line, number) in content.lines().zip(1..) {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let mut push = |kind, target: &str| {
+            let start = target.as_ptr() as usize - content.as_ptr() as usize;
+            refs.push(MarkdownRef {
+                line: number,
+                kind,
+                target: &content[start..start + target.len()],
+                span: start..start + target.len(),
+            });
+        };
+        // Reference definitions, like `[name]: https://example.com`
+        let definition = line
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("]:"))
+            .and_then(|(label, target)| Some((label, target.split_whitespace().next()?)));
+        if let Some((label, target)) = definition {
+            push(RefKind::Definition(label), target.trim_matches(['<', '>']));
+        }
+        // Every other part of the line is outside of inline code
+        for text in line.split('`').step_by(2) {
+            for (i, _) in text.match_indices("](") {
+                let rest = &text[i + 2..];
+                // Targets in angle brackets may have spaces
+                let target = match rest.strip_prefix('<') {
+                    Some(rest) => rest.find('>').map(|end| &rest[..end]),
+                    None => rest.find([')', ' ']).map(|end| &rest[..end]),
+                };
+                let Some(target) = target else {
+                    continue;
+                };
+                let kind = match opened_as_image(&text[..i]) {
+                    true => RefKind::Image,
+                    false => RefKind::Link,
+                };
+                push(kind, target);
+            }
+            for (i, _) in text.match_indices("![") {
+                let rest = &text[i + 2..];
+                let Some(end) = closing_bracket(rest) else {
+                    continue;
+                };
+                let (alt, after) = (&rest[..end], &rest[end + 1..]);
+                if after.starts_with('(') {
+                    continue;
+                }
+                let label = after
+                    .strip_prefix('[')
+                    .and_then(|after| Some(&after[..after.find(']')?]));
+                let label = match label {
+                    Some("") | None => alt,
+                    Some(label) => label,
+                };
+                if !label.is_empty() {
+                    push(RefKind::ImageLabel, label);
+                }
+            }
+            let lowercase = text.to_ascii_lowercase();
+            for (i, _) in lowercase.match_indices("<img") {
+                if !lowercase[i + "<img".len()..].starts_with(char::is_whitespace) {
+                    continue;
+                }
+                let tag = &text[i..i + lowercase[i..].find('>').unwrap_or(lowercase.len() - i)];
+                let Some(src) = tag.to_ascii_lowercase().find(" src=") else {
+                    continue;
+                };
+                let value = &tag[src + " src=".len()..];
+                let target = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => value[1..].split(quote).next(),
+                    _ => value.split(char::is_whitespace).next(),
+                };
+                push(RefKind::HtmlImage, target.unwrap_or_default());
+            }
+        }
+    }
+    refs
+}
+
+/// Whether the `]` that `before` is followed by closes the `![` of an image, rather than
+/// the `[` of a link
+fn opened_as_image(before: &str) -> bool {
+    let mut depth = 0;
+    for (i, c) in before.char_indices().rev() {
+        match c {
+            ']' => depth += 1,
+            '[' if depth == 0 => return before[..i].ends_with('!'),
+            '[' => depth -= 1,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Where the `]` is that closes the `[` that `after` follows
+fn closing_bracket(after: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in after.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => return Some(i),
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The label of a reference definition as links match it: without case, and with the
+/// whitespace in it collapsed
+pub(crate) fn reference_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub(crate) fn is_url(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+/// What is wrong with a URL, if anything
+pub(crate) fn invalid_url(url: &str) -> Option<String> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let valid_host = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || ".-:@[]_".contains(c));
+    (!valid_host).then(|| "is not a valid URL".to_string())
+}
+
+/// What is wrong with a link to a file or heading in the content, if anything
+fn broken_local_link(
+    file: &Path,
+    content: &str,
+    target: &str,
+    images: &HashSet<&OsStr>,
+) -> Option<String> {
+    // Other schemes, like `mailto:`
+    let scheme = target.split_once(':').map(|(scheme, _)| scheme);
+    if scheme.is_some_and(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return None;
+    }
+    let target = target.split('?').next().unwrap_or_default();
+    let (path, anchor) = match target.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor)),
+        None => (target, None),
+    };
+
+    if let Some(image) = path.strip_prefix("/images/") {
+        return (!images.contains(OsStr::new(image)))
+            .then(|| "refers to an image that none of the topics in the track have".to_string());
+    }
+    if path.starts_with('/') {
+        // Links within the rendered site can't be checked against the content
+        return None;
+    }
+    let (linked, linked_content) = if path.is_empty() {
+        (file.to_path_buf(), content.to_string())
+    } else {
+        let linked = file.parent().unwrap_or(Path::new("")).join(path);
+        if !linked.exists() {
+            return Some(format!(
+                "refers to {}, which doesn't exist",
+                linked.display()
+            ));
+        }
+        let is_markdown = linked.extension().is_some_and(|e| e == "md");
+        match anchor {
+            Some(_) if is_markdown => {
+                let content = fs::read_to_string(&linked).unwrap_or_default();
+                (linked, content)
+            }
+            _ => return None,
+        }
+    };
+    let anchor = anchor?;
+    let has_heading = headings(&linked_content).any(|h| heading_anchor(h) == anchor);
+    (!has_heading).then(|| {
+        format!(
+            "refers to heading '#{anchor}', which is not in {}",
+            linked.display()
+        )
+    })
+}
+
+/// The text of the Markdown headings in the content, leaving out code blocks
+pub(crate) fn headings(content: &str) -> impl Iterator<Item = &str> {
+    let mut in_code_block = false;
+    content.lines().filter_map(move |line| {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        let heading = line.trim_start_matches('#');
+        let is_heading = !in_code_block && heading.len() < line.len() && heading.starts_with(' ');
+        is_heading.then(|| heading.trim())
+    })
+}
+
+/// The numbers missing between the lowest and highest index
+fn missing_indices(mut indices: Vec<usize>) -> Vec<usize> {
+    indices.sort_unstable();
+    indices.windows(2).flat_map(|w| w[0] + 1..w[1]).collect()
+}
+
+/// Directories that never contain content
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", ".git"];
+
+/// Content that is present in the content tree, but that isn't referenced by any track
+#[derive(Debug, Default, Serialize)]
+pub struct Orphans {
+    /// Directories containing a topic definition
+    pub topics: Vec<PathBuf>,
+    /// Directories containing an exercise crate
+    pub exercises: Vec<PathBuf>,
+    pub images: Vec<PathBuf>,
+    /// Markdown files that contain modmod placeholders
+    pub templates: Vec<PathBuf>,
+}
+
+impl Orphans {
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty()
+            && self.exercises.is_empty()
+            && self.images.is_empty()
+            && self.templates.is_empty()
+    }
+}
+
+/// Paths of all content the tracks refer to
+#[derive(Default)]
+struct Reachable {
+    topic_dirs: HashSet<PathBuf>,
+    exercise_dirs: HashSet<PathBuf>,
+    files: HashSet<PathBuf>,
+}
+
+impl Reachable {
+    fn collect<'t>(tracks: impl IntoIterator<Item = &'t Track>) -> Self {
+        let mut reachable = Self::default();
+        for track in tracks {
+            reachable.files.extend(track.default_slide_template.clone());
+            reachable.files.extend(track.templates.values().cloned());
+            reachable
+                .exercise_dirs
+                .extend(track.shared_crates.iter().map(|c| c.path.clone()));
+            for unit in track.units() {
+                reachable.files.extend(unit.template.clone());
+            }
+            for topic in track.topics() {
+                if let Some(dir) = topic.definition.parent() {
+                    reachable.topic_dirs.insert(dir.to_path_buf());
+                }
+                reachable
+                    .files
+                    .extend(topic.content.path().map(Path::to_path_buf));
+                reachable
+                    .files
+                    .extend(topic.images.iter().map(|i| i.path.clone()));
+            }
+            for exercise in track.exercises() {
+                reachable.exercise_dirs.insert(exercise.path.clone());
+                reachable.files.extend(exercise.description.clone());
+            }
+        }
+        reachable
+    }
+}
+
+/// Walk the content directories of the given tracks, and list all topics, exercises,
+/// images and templates that none of the tracks refer to.
+pub fn find_orphans<'t>(
+    tracks: impl IntoIterator<Item = &'t Track> + Clone,
+) -> Result<Orphans, CheckError> {
+    let reachable = Reachable::collect(tracks.clone());
+
+    let mut ignore = globset::GlobSetBuilder::new();
+    let mut roots = BTreeSet::new();
+    for track in tracks {
+        let root = track.content_root.clone();
+        for pattern in track.orphan_ignore.iter() {
+            ignore.add(
+                globset::Glob::new(&root.join(pattern).to_string_lossy())
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error parsing orphan ignore glob '{pattern}'")
+                    })
+                    .change_context(CheckError::default())?,
+            );
+        }
+        roots.insert(root);
+    }
+    let ignore = ignore
+        .build()
+        .into_report()
+        .change_context(CheckError::default())?;
+
+    let mut files = BTreeSet::new();
+    for root in roots.iter() {
+        collect_files(root, &mut files)?;
+    }
+    let files: Vec<_> = files.into_iter().filter(|f| !ignore.is_match(f)).collect();
+
+    let mut orphans = Orphans::default();
+
+    // Topic and exercise directories. Shallower directories are handled first,
+    // so that nested crates are not reported separately
+    let mut by_depth: Vec<_> = files.iter().collect();
+    by_depth.sort_by_key(|f| f.components().count());
+    for file in by_depth {
+        let (Some(name), Some(dir)) = (file.file_name(), file.parent()) else {
+            continue;
+        };
+        let in_orphan = |orphans: &[PathBuf]| orphans.iter().any(|o| dir.starts_with(o));
+        if name == "topic.toml" && !reachable.topic_dirs.contains(dir) {
+            if !in_orphan(&orphans.topics) {
+                orphans.topics.push(dir.to_path_buf());
+            }
+        } else if name == "Cargo.toml"
+            && !reachable.exercise_dirs.iter().any(|e| dir.starts_with(e))
+            && !in_orphan(&orphans.exercises)
+            && !in_orphan(&orphans.topics)
+        {
+            orphans.exercises.push(dir.to_path_buf());
+        }
+    }
+
+    let is_owned = |file: &Path| {
+        orphans
+            .topics
+            .iter()
+            .chain(orphans.exercises.iter())
+            .chain(reachable.exercise_dirs.iter())
+            .any(|d| file.starts_with(d))
+    };
+
+    for file in files.iter() {
+        if reachable.files.contains(file) || is_owned(file) {
+            continue;
+        }
+        if is_image(file) {
+            orphans.images.push(file.clone());
+        } else if file.extension().is_some_and(|e| e == "md") && is_template(file)? {
+            orphans.templates.push(file.clone());
+        }
+    }
+
+    orphans.topics.sort();
+    orphans.exercises.sort();
+    Ok(orphans)
+}
+
+fn is_template(file: &Path) -> Result<bool, CheckError> {
+    Ok(file.read_to_string::<CheckError>()?.contains("#[modmod:"))
+}
+
+pub(crate) fn collect_files(dir: &Path, files: &mut BTreeSet<PathBuf>) -> Result<(), CheckError> {
+    let entries = dir
+        .read_dir()
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading directory {}", dir.display()))
+        .change_context(CheckError::default())?;
+    for entry in entries {
+        let entry = entry.into_report().change_context(CheckError::default())?;
+        let path = entry.path();
+        if path.is_dir() {
+            let name = entry.file_name();
+            if !SKIPPED_DIRS.iter().any(|s| name == **s) {
+                collect_files(&path, files)?;
+            }
+        } else {
+            files.insert(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn refs(content: &str) -> Vec<(usize, RefKind<'_>, &str)> {
+        markdown_refs(content)
+            .into_iter()
+            .inspect(|r| assert_eq!(&content[r.span.clone()], r.target))
+            .map(|r| (r.line, r.kind, r.target))
+            .collect()
+    }
+
+    #[test]
+    fn links_and_images() {
+        let content = indoc! {r#"
+            [Link](https://example.com) [![Badge](badge.svg)](https://ci.example.com)
+            ![Image](<a b.png>) <img alt="x" src=c.png> <IMG src="d.png"> <image src="e.png">
+        "#};
+        assert_eq!(
+            refs(content),
+            [
+                (1, RefKind::Link, "https://example.com"),
+                (1, RefKind::Image, "badge.svg"),
+                (1, RefKind::Link, "https://ci.example.com"),
+                (2, RefKind::Image, "a b.png"),
+                (2, RefKind::HtmlImage, "c.png"),
+                (2, RefKind::HtmlImage, "d.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reference_style_images() {
+        let content = indoc! {"
+            ![Alt][Stack  Diagram] ![heap][] ![stack]
+            [Stack diagram]: <stack.svg> \"Title\"
+        "};
+        assert_eq!(
+            refs(content),
+            [
+                (1, RefKind::ImageLabel, "Stack  Diagram"),
+                (1, RefKind::ImageLabel, "heap"),
+                (1, RefKind::ImageLabel, "stack"),
+                (2, RefKind::Definition("Stack diagram"), "stack.svg"),
+            ]
+        );
+        assert_eq!(
+            reference_label("Stack  Diagram"),
+            reference_label("stack diagram")
+        );
+    }
+
+    #[test]
+    fn code_is_left_out() {
+        let content = indoc! {"
+            ```md
+            ![Image](a.png)
+            ```
+            `[Link](b.md)` [Link](c.md)
+        "};
+        assert_eq!(links(content), [(4, "c.md")]);
+    }
+}
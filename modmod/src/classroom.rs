@@ -0,0 +1,571 @@
+//! Template repositories for GitHub Classroom, made from the exercises of a rendered track.
+//!
+//! Each template is a folder that can be pushed as a repository as it is, for a single
+//! exercise or for all exercises of a module, see [`TemplateScope`]. It holds the starter
+//! code of its exercises with a README that has their descriptions as they are in the book,
+//! an autograding workflow that runs their tests, and a `.gitignore`.
+//!
+//! As a template stands on its own, the manifests of its exercises are made workspace roots,
+//! so that cargo doesn't look for a workspace above them, and their path dependencies on
+//! the shared crates of the track are rewritten. Either the shared crates are copied into
+//! [`VENDOR_DIR`] of the template, or the dependencies point to a git repository the shared
+//! crates are pushed to, in which cargo finds them by name. Other path dependencies that lead
+//! out of an exercise can't be resolved in a template, and fail the export.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use git2::{IndexAddOption, Repository, RepositoryInitOptions};
+
+use crate::{
+    exercises::{dependency_tables_mut, find_manifests, relative_path},
+    git::failed,
+    handout::exercise_sections,
+    io::{copy_tree, writes_discarded_at, CopyTreeOptions, PathExt},
+    manifest::{Artifact, ArtifactKind, RenderManifest},
+    to_prefixed_tag,
+    version::CourseVersion,
+    OutputLayout, Track,
+};
+
+/// The folder the templates are written into by default
+pub const CLASSROOM_DIR: &str = "dist/classroom";
+
+/// The folder of a template the shared crates are copied into
+pub const VENDOR_DIR: &str = "vendor";
+
+/// The workflow that GitHub Classroom reads the autograding tests from
+pub const AUTOGRADING_WORKFLOW: &str = ".github/workflows/classroom.yml";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ClassroomError {}
+
+impl fmt::Display for ClassroomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to export the exercises as GitHub Classroom templates")
+    }
+}
+
+impl error_stack::Context for ClassroomError {}
+
+/// What a template is made for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemplateScope {
+    /// A single exercise, which is the root of the template
+    #[default]
+    Exercise,
+    /// All exercises of a module, each in a folder of the template
+    Module,
+}
+
+impl TemplateScope {
+    pub const ALL: [Self; 2] = [Self::Exercise, Self::Module];
+}
+
+impl fmt::Display for TemplateScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Exercise => "exercise",
+            Self::Module => "module",
+        })
+    }
+}
+
+impl std::str::FromStr for TemplateScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.to_string() == s)
+            .ok_or_else(|| format!("unknown template scope '{s}', expected exercise or module"))
+    }
+}
+
+pub struct ClassroomOptions<'a> {
+    /// The layout the track was rendered with
+    pub layout: &'a OutputLayout,
+    pub scope: TemplateScope,
+    /// The git repository the shared crates are pushed to, or `None` to copy them into the
+    /// templates
+    pub shared_git: Option<&'a str>,
+    /// Whether each template is made a git repository with an initial commit
+    pub git_init: bool,
+    /// The version of the course the track was rendered as, added to the names of the
+    /// module templates
+    pub course_version: Option<&'a CourseVersion>,
+}
+
+/// A template that was written
+#[derive(Debug)]
+pub struct Template {
+    pub name: String,
+    pub dir: PathBuf,
+    /// The number of exercises in it
+    pub exercises: usize,
+    /// The shared crates that were copied into it
+    pub vendored: Vec<String>,
+}
+
+/// The templates of the exercises, and what came up making them
+#[derive(Debug)]
+pub struct ClassroomExport {
+    pub templates: Vec<Template>,
+    /// Exercises without a description in the book, with the template they are in
+    pub warnings: Vec<(String, String)>,
+}
+
+/// An exercise as it goes into a template
+struct TemplateExercise<'m> {
+    artifact: &'m Artifact,
+    /// The folder of the exercise relative to the template, empty if it is the template
+    folder: String,
+    /// The heading of the description, without the markup for optional exercises
+    title: String,
+    readme: String,
+}
+
+struct PlannedTemplate<'m> {
+    name: String,
+    /// The README of a module template, which lists its exercises
+    readme: Option<String>,
+    exercises: Vec<TemplateExercise<'m>>,
+}
+
+impl Track {
+    /// Writes the exercises of the track, as rendered into `rendered_dir` and listed in its
+    /// `manifest`, into a template for each exercise or module in `out_dir`. Fails without
+    /// writing anything if one of the template folders already exists and isn't empty.
+    pub fn export_classroom(
+        &self,
+        rendered_dir: &Path,
+        manifest: &RenderManifest,
+        out_dir: &Path,
+        options: ClassroomOptions,
+    ) -> Result<ClassroomExport, ClassroomError> {
+        let ClassroomOptions {
+            layout,
+            scope,
+            shared_git,
+            git_init,
+            course_version,
+        } = options;
+        let mut warnings = vec![];
+        let mut planned: Vec<PlannedTemplate> = vec![];
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let module_name = to_prefixed_tag(&module.data.name, &label.path);
+            let module_name = match course_version {
+                Some(version) => format!("{module_name}-{}", version.file_name_part()),
+                None => module_name,
+            };
+            let mut module_exercises = vec![];
+            for unit in module.data.units.iter() {
+                let of_unit = |kind| {
+                    manifest
+                        .main_artifacts()
+                        .filter(move |a| a.kind == kind)
+                        .filter(|a| (a.module, a.unit) == (module.index, unit.index))
+                };
+                let sections = match of_unit(ArtifactKind::Chapter).next() {
+                    Some(chapter) => exercise_sections(
+                        &rendered_dir
+                            .join(&chapter.path)
+                            .read_to_string::<ClassroomError>()?,
+                    ),
+                    None => vec![],
+                };
+                for (artifact, i) in of_unit(ArtifactKind::Exercise).zip(1..) {
+                    let (name, folder) = match scope {
+                        TemplateScope::Exercise => (
+                            to_prefixed_tag(
+                                &artifact.title,
+                                format!("{}_{}_{i}", label.path, unit.index),
+                            ),
+                            String::new(),
+                        ),
+                        TemplateScope::Module => (
+                            module_name.clone(),
+                            to_prefixed_tag(&artifact.title, format!("{}_{i}", unit.index)),
+                        ),
+                    };
+                    let section = sections.iter().find(|section| {
+                        section_title(section).is_some_and(|(_, exercise)| {
+                            exercise.trim_end_matches(" *(optional)*") == artifact.title
+                        })
+                    });
+                    let (title, readme) = match section {
+                        Some(section) => {
+                            let (heading, _) = section_title(section).unwrap_or_default();
+                            let readme = exercise_readme(section, &artifact.path, &folder);
+                            (heading.replace(" *(optional)*", ""), readme)
+                        }
+                        None => {
+                            warnings.push((
+                                name.clone(),
+                                format!(
+                                    "Exercise '{}' has no description in the book, so its README only has its name",
+                                    artifact.title
+                                ),
+                            ));
+                            (artifact.title.clone(), format!("# {}\n", artifact.title))
+                        }
+                    };
+                    let exercise = TemplateExercise {
+                        artifact,
+                        folder,
+                        title,
+                        readme,
+                    };
+                    match scope {
+                        TemplateScope::Exercise => planned.push(PlannedTemplate {
+                            name,
+                            readme: None,
+                            exercises: vec![exercise],
+                        }),
+                        TemplateScope::Module => module_exercises.push(exercise),
+                    }
+                }
+            }
+            if module_exercises.is_empty() {
+                continue;
+            }
+            let title = match label.display.is_empty() {
+                true => format!("{} - Exercises", module.data.name),
+                false => format!(
+                    "Module {} - {} - Exercises",
+                    label.display, module.data.name
+                ),
+            };
+            let readme = module_readme(&title, &module_exercises);
+            planned.push(PlannedTemplate {
+                name: module_name,
+                readme: Some(readme),
+                exercises: module_exercises,
+            });
+        }
+
+        let taken: Vec<_> = planned
+            .iter()
+            .map(|template| out_dir.join(&template.name))
+            .filter(|dir| {
+                dir.read_dir()
+                    .is_ok_and(|mut entries| entries.next().is_some())
+            })
+            .map(|dir| dir.display().to_string())
+            .collect();
+        if !taken.is_empty() {
+            return Err(Report::new(ClassroomError::default()).attach_printable(format!(
+                "These template folders already exist, remove them or write the templates somewhere else: {}",
+                taken.join(", ")
+            )));
+        }
+
+        let shared_dir = rendered_dir.join(layout.exercises()).join("shared");
+        let mut templates = vec![];
+        for template in planned {
+            let dir = out_dir.join(&template.name);
+            let vendor_dir = dir.join(VENDOR_DIR);
+            let mut standalone = Standalone {
+                shared_dir: shared_dir.canonicalize().ok(),
+                vendor_dir: &vendor_dir,
+                shared_git,
+                needed: vec![],
+            };
+            let mut tests = vec![];
+            for exercise in template.exercises.iter() {
+                let from = rendered_dir.join(&exercise.artifact.path);
+                let to = dir.join(&exercise.folder);
+                to.create_dir_all()?;
+                standalone.copy_package(&from, &to, true)?;
+                to.join("README.md").write_file(&exercise.readme)?;
+                let command = match exercise.folder.is_empty() {
+                    true => "cargo test".to_string(),
+                    false => format!("cargo test --manifest-path {}/Cargo.toml", exercise.folder),
+                };
+                tests.push((exercise.title.as_str(), command));
+            }
+            let mut vendored: Vec<String> = vec![];
+            while let Some(name) = standalone.needed.pop() {
+                if vendored.contains(&name) {
+                    continue;
+                }
+                let from = shared_dir.join(&name);
+                standalone.copy_package(&from, &vendor_dir.join(&name), false)?;
+                vendored.push(name);
+            }
+            vendored.sort_unstable();
+
+            if let Some(readme) = &template.readme {
+                dir.join("README.md").write_file(readme)?;
+            }
+            dir.join(".gitignore").write_file("target/\n")?;
+            let workflow = dir.join(AUTOGRADING_WORKFLOW);
+            if let Some(workflows_dir) = workflow.parent() {
+                workflows_dir.create_dir_all()?;
+            }
+            workflow.write_file(autograding_workflow(&tests))?;
+            if git_init && !writes_discarded_at(&dir) {
+                init_repository(&dir)?;
+            }
+            templates.push(Template {
+                name: template.name,
+                dir,
+                exercises: template.exercises.len(),
+                vendored,
+            });
+        }
+        Ok(ClassroomExport {
+            templates,
+            warnings,
+        })
+    }
+}
+
+/// The heading of an exercise section, and the name of the exercise in it
+fn section_title(section: &str) -> Option<(&str, &str)> {
+    let heading = section.lines().next()?.strip_prefix("## ")?.trim();
+    let (_, name) = heading.split_once(": ")?;
+    Some((heading, name))
+}
+
+/// The README of an exercise, with the description from the book. Its headings are moved up
+/// a level, and the paths into the rendered exercise are made relative to the template.
+fn exercise_readme(section: &str, exercise_path: &str, folder: &str) -> String {
+    let within = match folder.is_empty() {
+        true => String::new(),
+        false => format!("{folder}/"),
+    };
+    let section = section
+        .replace(&format!("{exercise_path}/"), &within)
+        .replace(exercise_path, if folder.is_empty() { "." } else { folder });
+    let mut readme = String::new();
+    let mut in_code = false;
+    for line in section.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        }
+        match (in_code, line.strip_prefix('#')) {
+            (false, Some(rest)) if rest.starts_with('#') => readme.push_str(rest),
+            _ => readme.push_str(line),
+        }
+        readme.push('\n');
+    }
+    let check = match folder.is_empty() {
+        true => "Run `cargo test` to check your work.".to_string(),
+        false => format!("Run `cargo test` in `{folder}` to check your work."),
+    };
+    readme.push_str(&format!(
+        "\n## Checking your work\n\n{check}\nThe tests also run on every push, and their results show up in GitHub Classroom.\n"
+    ));
+    readme
+}
+
+/// The README of a module template, which points to its exercises
+fn module_readme(title: &str, exercises: &[TemplateExercise]) -> String {
+    let mut readme = format!("# {title}\n\n");
+    readme.push_str("Each exercise is in its own folder, with a README that describes it:\n\n");
+    for exercise in exercises {
+        readme.push_str(&format!("- [{}]({}/)\n", exercise.title, exercise.folder));
+    }
+    readme.push_str("\nThe tests of all exercises run on every push, and their results show up in GitHub Classroom.\n");
+    readme
+}
+
+/// The workflow that runs each of `tests`, a name and a command, as an autograding test
+fn autograding_workflow(tests: &[(&str, String)]) -> String {
+    // JSON strings are YAML strings, which keeps names with colons in one piece
+    let quote = |s: &str| serde_json::Value::from(s).to_string();
+    let mut workflow = String::from(
+        "name: Autograding Tests\n\
+         on:\n  - push\n  - repository_dispatch\n\
+         permissions:\n  checks: write\n  actions: read\n  contents: read\n\
+         jobs:\n  run-autograding-tests:\n    runs-on: ubuntu-latest\n\
+         \x20   if: github.actor != 'github-classroom[bot]'\n\
+         \x20   steps:\n      - name: Checkout code\n        uses: actions/checkout@v4\n",
+    );
+    let ids: Vec<_> = (1..=tests.len()).map(|i| format!("test-{i}")).collect();
+    for ((name, command), id) in tests.iter().zip(ids.iter()) {
+        workflow.push_str(&format!(
+            "      - name: {name}\n        id: {id}\n        uses: classroom-resources/autograding-command-grader@v1\n        with:\n          test-name: {name}\n          setup-command: ''\n          command: {command}\n          timeout: 10\n          max-score: 1\n",
+            name = quote(name),
+            command = quote(command),
+        ));
+    }
+    workflow.push_str("      - name: Autograding Reporter\n        uses: classroom-resources/autograding-grading-reporter@v1\n        env:\n");
+    for id in ids.iter() {
+        workflow.push_str(&format!(
+            "          {}_RESULTS: \"${{{{steps.{id}.outputs.result}}}}\"\n",
+            id.to_uppercase()
+        ));
+    }
+    workflow.push_str(&format!(
+        "        with:\n          runners: {}\n",
+        ids.join(",")
+    ));
+    workflow
+}
+
+/// Makes `dir` a git repository with everything in it in an initial commit
+fn init_repository(dir: &Path) -> Result<(), ClassroomError> {
+    let repo = Repository::init_opts(dir, RepositoryInitOptions::new().initial_head("main"))
+        .map_err(failed::<ClassroomError>("creating a repository"))?;
+    let mut index = repo
+        .index()
+        .map_err(failed::<ClassroomError>("opening the index"))?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .and_then(|()| index.write())
+        .map_err(failed::<ClassroomError>("adding the files"))?;
+    let tree = index
+        .write_tree()
+        .and_then(|tree| repo.find_tree(tree))
+        .map_err(failed::<ClassroomError>("writing the tree"))?;
+    repo.signature()
+        .and_then(|author| {
+            repo.commit(Some("HEAD"), &author, &author, "Initial commit", &tree, &[])
+        })
+        .map_err(failed::<ClassroomError>("committing"))
+        .attach_printable_lazy(|| format!("Initializing a git repository in {}", dir.display()))?;
+    Ok(())
+}
+
+/// Copies packages into a template, rewriting their manifests so that they build there
+struct Standalone<'a> {
+    /// Where the shared crates were rendered, if the track has them
+    shared_dir: Option<PathBuf>,
+    vendor_dir: &'a Path,
+    shared_git: Option<&'a str>,
+    /// The shared crates that were depended on and have to be copied into the template
+    needed: Vec<String>,
+}
+
+impl Standalone<'_> {
+    /// Copies the package in `from` to `to`, leaving out its build output. If `root`, its
+    /// manifest is made a workspace root.
+    fn copy_package(&mut self, from: &Path, to: &Path, root: bool) -> Result<(), ClassroomError> {
+        let options = CopyTreeOptions {
+            recursive: true,
+            exclude: &["target", "target/**"],
+            preserve_structure: true,
+            ..Default::default()
+        };
+        copy_tree::<ClassroomError>(from, to, &options)?;
+        let package_dir = from
+            .canonicalize()
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to read {}", from.display()))
+            .change_context(ClassroomError::default())?;
+        let mut manifests = vec![];
+        find_manifests(from, &mut manifests);
+        for manifest_path in manifests {
+            let relative = manifest_path.strip_prefix(from).unwrap_or(&manifest_path);
+            let dest = to.join(relative);
+            let is_root = root && relative == Path::new("Cargo.toml");
+            let content = self.manifest(&manifest_path, &package_dir, &dest, is_root)?;
+            dest.write_file(content)?;
+        }
+        Ok(())
+    }
+
+    /// The manifest at `manifest_path`, of a package in `package_dir`, as it is written to
+    /// `dest`
+    fn manifest(
+        &mut self,
+        manifest_path: &Path,
+        package_dir: &Path,
+        dest: &Path,
+        root: bool,
+    ) -> Result<String, ClassroomError> {
+        let content = manifest_path.read_to_string()?;
+        let mut manifest: toml_edit::Document = content
+            .parse()
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to parse {}", manifest_path.display()))
+            .change_context(ClassroomError::default())?;
+        let manifest_dir = manifest_path.parent().unwrap_or(Path::new(""));
+        let dest_dir = dest.parent().unwrap_or(Path::new(""));
+
+        let inherits =
+            |item: &toml_edit::Item| item.get("workspace").and_then(|w| w.as_bool()) == Some(true);
+        let inherited = manifest
+            .get("package")
+            .and_then(|p| p.as_table_like())
+            .and_then(|package| package.iter().find(|(_, item)| inherits(item)))
+            .map(|(key, _)| key.to_string());
+        if let Some(key) = inherited {
+            return Err(
+                Report::new(ClassroomError::default()).attach_printable(format!(
+                    "{} inherits '{key}' from a workspace, which a template doesn't have",
+                    manifest_path.display()
+                )),
+            );
+        }
+
+        for table in dependency_tables_mut(&mut manifest) {
+            let Some(table) = table.as_table_like_mut() else {
+                continue;
+            };
+            for (key, dep) in table.iter_mut() {
+                if inherits(dep) {
+                    return Err(Report::new(ClassroomError::default()).attach_printable(format!(
+                        "{} inherits dependency '{}' from a workspace, which a template doesn't have",
+                        manifest_path.display(),
+                        key.get()
+                    )));
+                }
+                let Some(declared) = dep.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                let declared_path = manifest_dir.join(declared);
+                let canonical = declared_path.canonicalize().ok();
+                if canonical
+                    .as_ref()
+                    .is_some_and(|c| c.starts_with(package_dir))
+                {
+                    continue;
+                }
+                let shared = canonical.as_ref().zip(self.shared_dir.as_ref()).and_then(
+                    |(canonical, shared_dir)| {
+                        let within = canonical.strip_prefix(shared_dir).ok()?;
+                        let name = within.components().next()?;
+                        Some((name.as_os_str().to_string_lossy().to_string(), within))
+                    },
+                );
+                let Some((name, within)) = shared else {
+                    return Err(Report::new(ClassroomError::default()).attach_printable(format!(
+                        "{} depends on crate '{}' at {}, which is neither in the exercise nor a shared crate, so the template can't have it",
+                        manifest_path.display(),
+                        key.get(),
+                        declared_path.display()
+                    )));
+                };
+                let Some(dep) = dep.as_table_like_mut() else {
+                    continue;
+                };
+                match self.shared_git {
+                    Some(url) => {
+                        dep.remove("path");
+                        dep.insert("git", toml_edit::value(url));
+                    }
+                    None => {
+                        let vendored = relative_path(dest_dir, &self.vendor_dir.join(within));
+                        let vendored = vendored.to_string_lossy().replace('\\', "/");
+                        dep.insert("path", toml_edit::value(vendored));
+                        self.needed.push(name);
+                    }
+                }
+            }
+        }
+
+        if root && manifest.contains_key("package") && !manifest.contains_key("workspace") {
+            manifest["workspace"] = toml_edit::table();
+        }
+        Ok(manifest.to_string())
+    }
+}
@@ -0,0 +1,585 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+use error_stack::{Report, Result};
+
+use crate::{diagnostic::Location, suggest};
+
+const IF_START: &str = "#[modmod:if ";
+const ELSE: &str = "#[modmod:else]";
+const ENDIF: &str = "#[modmod:endif]";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ConditionalError {}
+
+impl fmt::Display for ConditionalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to resolve conditional content blocks")
+    }
+}
+
+impl error_stack::Context for ConditionalError {}
+
+static NO_VARIABLES: BTreeMap<String, String> = BTreeMap::new();
+
+/// The values conditional blocks are evaluated against
+#[derive(Debug, Clone, Copy)]
+pub struct Conditions<'a> {
+    /// The profile that is being rendered, if any
+    pub profile: Option<&'a str>,
+    /// The profiles defined by the track. Conditions on any other profile are an error.
+    pub known_profiles: &'a [String],
+    /// The variables of the track, that conditions refer to as `var.<name>`
+    pub variables: &'a BTreeMap<String, String>,
+}
+
+impl Default for Conditions<'_> {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            known_profiles: &[],
+            variables: &NO_VARIABLES,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'s> {
+    Text(&'s str),
+    If { condition: &'s str, line: usize },
+    Else { line: usize },
+    EndIf { line: usize },
+}
+
+/// Splits content into text and markers. Markers that are on a line of their own
+/// take the whole line with them, so that they don't leave empty lines behind.
+fn tokenize(content: &str, first_line: usize) -> Result<Vec<Token<'_>>, ConditionalError> {
+    let mut tokens = vec![];
+    let mut rest = content;
+    let mut line = first_line;
+
+    while let Some(start) = rest.find("#[modmod:") {
+        let marker = &rest[start..];
+        let marker_line = line + rest[..start].matches('\n').count();
+        let (len, token) = if let Some(condition) = marker.strip_prefix(IF_START) {
+            let Some(end) = condition.find(']') else {
+                return Err(Report::new(ConditionalError::default())
+                    .attach_printable(format!("Unclosed conditional marker at line {marker_line}"))
+                    .attach(Location::line(marker_line)));
+            };
+            let condition = &condition[..end];
+            let token = Token::If {
+                condition,
+                line: marker_line,
+            };
+            (IF_START.len() + end + 1, token)
+        } else if marker.starts_with(ELSE) {
+            (ELSE.len(), Token::Else { line: marker_line })
+        } else if marker.starts_with(ENDIF) {
+            (ENDIF.len(), Token::EndIf { line: marker_line })
+        } else {
+            // Some other placeholder, leave it be
+            let len = "#[modmod:".len();
+            tokens.push(Token::Text(&rest[..start + len]));
+            line += rest[..start + len].matches('\n').count();
+            rest = &rest[start + len..];
+            continue;
+        };
+
+        let before = &rest[..start];
+        let after = &rest[start + len..];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let own_line = before[line_start..].trim().is_empty()
+            && after
+                .split('\n')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .is_empty();
+
+        let (before, after_len) = if own_line {
+            let newline = after.find('\n').map(|i| i + 1).unwrap_or(after.len());
+            (&before[..line_start], newline)
+        } else {
+            (before, 0)
+        };
+
+        tokens.push(Token::Text(before));
+        tokens.push(token);
+        line += rest[..start + len + after_len].matches('\n').count();
+        rest = &rest[start + len + after_len..];
+    }
+    tokens.push(Token::Text(rest));
+    Ok(tokens)
+}
+
+/// A parsed condition
+#[derive(Debug)]
+enum Expr<'s> {
+    Not(Box<Expr<'s>>),
+    And(Box<Expr<'s>>, Box<Expr<'s>>),
+    Or(Box<Expr<'s>>, Box<Expr<'s>>),
+    /// Whether a variable is set, or a profile is being rendered
+    Defined(Operand<'s>),
+    Compare {
+        operand: Operand<'s>,
+        equal: bool,
+        value: &'s str,
+    },
+}
+
+#[derive(Debug)]
+enum Operand<'s> {
+    Profile,
+    Variable(&'s str),
+}
+
+/// Splits a condition into words, quoted strings, parentheses and comparison operators
+fn lex(condition: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut words = vec![];
+    let mut rest = condition.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '(' | ')' | '=' => {
+                if rest.starts_with("==") {
+                    2
+                } else {
+                    1
+                }
+            }
+            '!' if rest.starts_with("!=") => 2,
+            '"' => match rest[1..].find('"') {
+                Some(end) => end + 2,
+                None => return Err("Unclosed quote".to_string()),
+            },
+            c if c.is_alphanumeric() || "_-.".contains(c) => rest
+                .find(|c: char| !(c.is_alphanumeric() || "_-.".contains(c)))
+                .unwrap_or(rest.len()),
+            c => return Err(format!("Unexpected character '{c}'")),
+        };
+        words.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    Ok(words)
+}
+
+/// Parses conditions of the form `profile = <name>`, `var.<name> = <value>`, `var.<name> != <value>`
+/// and `defined(var.<name>)`, combined with `and`, `or`, `not` and parentheses.
+/// `and` binds more strongly than `or`.
+struct Parser<'s, 'c> {
+    words: Vec<&'s str>,
+    pos: usize,
+    conditions: &'c Conditions<'c>,
+}
+
+impl<'s> Parser<'s, '_> {
+    fn peek(&self) -> Option<&'s str> {
+        self.words.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> std::result::Result<&'s str, String> {
+        let word = self
+            .peek()
+            .ok_or_else(|| "Unexpected end of condition".to_string())?;
+        self.pos += 1;
+        Ok(word)
+    }
+
+    fn expect(&mut self, expected: &str) -> std::result::Result<(), String> {
+        match self.next()? {
+            word if word == expected => Ok(()),
+            word => Err(format!("Expected '{expected}', found '{word}'")),
+        }
+    }
+
+    fn parse(mut self) -> std::result::Result<Expr<'s>, String> {
+        let expr = self.or()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(word) => Err(format!("Unexpected '{word}'")),
+        }
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr<'s>, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr<'s>, String> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> std::result::Result<Expr<'s>, String> {
+        match self.next()? {
+            "not" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "(" => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            "defined" => {
+                self.expect("(")?;
+                let operand = self.operand()?;
+                self.expect(")")?;
+                Ok(Expr::Defined(operand))
+            }
+            _ => {
+                self.pos -= 1;
+                let operand = self.operand()?;
+                let equal = match self.next()? {
+                    "=" | "==" => true,
+                    "!=" => false,
+                    word => return Err(format!("Expected '=' or '!=', found '{word}'")),
+                };
+                let value = self.next()?;
+                if ["(", ")", "=", "==", "!="].contains(&value) {
+                    return Err(format!("Expected a value, found '{value}'"));
+                }
+                let value = value.trim_matches('"');
+                if matches!(operand, Operand::Profile)
+                    && !self.conditions.known_profiles.iter().any(|p| p == value)
+                {
+                    return Err(format!(
+                        "Unknown profile '{value}'.{}",
+                        suggest::did_you_mean(value, self.conditions.known_profiles)
+                    ));
+                }
+                Ok(Expr::Compare {
+                    operand,
+                    equal,
+                    value,
+                })
+            }
+        }
+    }
+
+    fn operand(&mut self) -> std::result::Result<Operand<'s>, String> {
+        match self.next()? {
+            "profile" => Ok(Operand::Profile),
+            word => match word.strip_prefix("var.") {
+                Some(name) if !name.is_empty() => Ok(Operand::Variable(name)),
+                _ => Err(format!(
+                    "Unknown condition variable '{word}', expected 'profile' or 'var.<name>'"
+                )),
+            },
+        }
+    }
+}
+
+impl Expr<'_> {
+    /// Evaluates the condition. Parts that don't affect the outcome are not evaluated,
+    /// so that `defined(var.x) and var.x = y` works when `x` is not set.
+    fn evaluate(&self, conditions: &Conditions) -> std::result::Result<bool, String> {
+        let value = |operand: &Operand| match operand {
+            Operand::Profile => conditions.profile,
+            Operand::Variable(name) => conditions.variables.get(*name).map(String::as_str),
+        };
+        Ok(match self {
+            Expr::Not(expr) => !expr.evaluate(conditions)?,
+            Expr::And(a, b) => a.evaluate(conditions)? && b.evaluate(conditions)?,
+            Expr::Or(a, b) => a.evaluate(conditions)? || b.evaluate(conditions)?,
+            Expr::Defined(operand) => value(operand).is_some(),
+            Expr::Compare {
+                operand: Operand::Variable(name),
+                ..
+            } if !conditions.variables.contains_key(*name) => {
+                return Err(format!(
+                    "Variable '{name}' is not set (`defined(var.{name})` checks whether it is)"
+                ))
+            }
+            Expr::Compare {
+                operand,
+                equal,
+                value: expected,
+            } => (value(operand) == Some(*expected)) == *equal,
+        })
+    }
+}
+
+/// Parses a condition and, if `evaluate` is set, evaluates it.
+/// Conditions in excluded blocks must be valid too, but are not evaluated.
+fn evaluate(
+    condition: &str,
+    line: usize,
+    conditions: &Conditions,
+    evaluate: bool,
+) -> Result<bool, ConditionalError> {
+    let result = lex(condition).and_then(|words| {
+        let parser = Parser {
+            words,
+            pos: 0,
+            conditions,
+        };
+        let expr = parser.parse()?;
+        match evaluate {
+            true => expr.evaluate(conditions),
+            false => Ok(false),
+        }
+    });
+    result.map_err(|msg| {
+        Report::new(ConditionalError::default())
+            .attach_printable(format!("{msg} in condition '{condition}' at line {line}"))
+            .attach(Location::line(line))
+    })
+}
+
+/// Resolves the `#[modmod:if <condition>] ... #[modmod:else] ... #[modmod:endif]` blocks
+/// in the content, keeping the content of the branches whose condition holds. Blocks can be nested.
+/// Content without blocks is returned as it is, without copying it.
+pub fn resolve<'c>(
+    content: &'c str,
+    conditions: &Conditions,
+) -> Result<Cow<'c, str>, ConditionalError> {
+    resolve_from_line(content, 1, conditions)
+}
+
+/// Like [`resolve`], for content that starts at `first_line` of its file,
+/// so that errors refer to the right line
+pub fn resolve_from_line<'c>(
+    content: &'c str,
+    first_line: usize,
+    conditions: &Conditions,
+) -> Result<Cow<'c, str>, ConditionalError> {
+    if !content.contains(IF_START) && !content.contains(ELSE) && !content.contains(ENDIF) {
+        return Ok(Cow::Borrowed(content));
+    }
+
+    let mut output = String::with_capacity(content.len());
+    /// A block that has been opened, but not closed yet
+    struct Open {
+        line: usize,
+        /// Whether the enclosing blocks are included
+        outer: bool,
+        holds: bool,
+        in_else: bool,
+    }
+    let mut open: Vec<Open> = vec![];
+    let unmatched = |marker: &str, line: usize| {
+        Err(Report::new(ConditionalError::default())
+            .attach_printable(format!(
+                "Found `{marker}` without matching `{IF_START}...]` at line {line}"
+            ))
+            .attach(Location::line(line)))
+    };
+
+    for token in tokenize(content, first_line)? {
+        let included = open
+            .last()
+            .map(|o| o.outer && (o.holds != o.in_else))
+            .unwrap_or(true);
+        match token {
+            Token::Text(text) if included => output.push_str(text),
+            Token::Text(_) => {}
+            Token::If { condition, line } => {
+                let holds = evaluate(condition, line, conditions, included)?;
+                open.push(Open {
+                    line,
+                    outer: included,
+                    holds,
+                    in_else: false,
+                });
+            }
+            Token::Else { line } => match open.last_mut() {
+                Some(block) if !block.in_else => block.in_else = true,
+                Some(block) => {
+                    return Err(Report::new(ConditionalError::default())
+                        .attach_printable(format!(
+                            "Found a second `{ELSE}` at line {line} in the block opened at line {}",
+                            block.line
+                        ))
+                        .attach(Location::line(line)))
+                }
+                None => return unmatched(ELSE, line),
+            },
+            Token::EndIf { line } => {
+                if open.pop().is_none() {
+                    return unmatched(ENDIF, line);
+                }
+            }
+        }
+    }
+
+    if let Some(block) = open.last() {
+        return Err(Report::new(ConditionalError::default())
+            .attach_printable(format!(
+                "Conditional block opened at line {} is never closed",
+                block.line
+            ))
+            .attach(Location::line(block.line)));
+    }
+
+    Ok(Cow::Owned(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn resolve_with(content: &str, variables: &BTreeMap<String, String>) -> String {
+        let conditions = Conditions {
+            variables,
+            ..Conditions::default()
+        };
+        resolve(content, &conditions).unwrap().into_owned()
+    }
+
+    fn error_message(content: &str) -> String {
+        let report = resolve(content, &Conditions::default()).unwrap_err();
+        format!("{report:?}")
+    }
+
+    const NESTED: &str = indoc! {"
+        start
+        #[modmod:if defined(var.outer)]
+        outer
+        #[modmod:if var.inner = yes]
+        inner
+        #[modmod:endif]
+        #[modmod:endif]
+        end
+    "};
+
+    #[test]
+    fn nested_blocks() {
+        let both = variables(&[("outer", "1"), ("inner", "yes")]);
+        assert_eq!(resolve_with(NESTED, &both), "start\nouter\ninner\nend\n");
+
+        let outer = variables(&[("outer", "1"), ("inner", "no")]);
+        assert_eq!(resolve_with(NESTED, &outer), "start\nouter\nend\n");
+
+        // The inner condition isn't evaluated when the outer one doesn't hold,
+        // so `inner` doesn't have to be set
+        assert_eq!(resolve_with(NESTED, &variables(&[])), "start\nend\n");
+    }
+
+    #[test]
+    fn else_branch() {
+        let content = indoc! {"
+            #[modmod:if var.lang = nl]
+            Hallo
+            #[modmod:else]
+            Hello
+            #[modmod:endif]
+        "};
+        assert_eq!(
+            resolve_with(content, &variables(&[("lang", "nl")])),
+            "Hallo\n"
+        );
+        assert_eq!(
+            resolve_with(content, &variables(&[("lang", "en")])),
+            "Hello\n"
+        );
+    }
+
+    #[test]
+    fn inline_markers_keep_the_line() {
+        let content =
+            "Say #[modmod:if defined(var.loud)]HELLO#[modmod:else]hello#[modmod:endif]!\n";
+        assert_eq!(
+            resolve_with(content, &variables(&[("loud", "1")])),
+            "Say HELLO!\n"
+        );
+    }
+
+    #[test]
+    fn content_without_blocks_is_borrowed() {
+        let content = "No #[modmod:exercises] blocks here";
+        let resolved = resolve(content, &Conditions::default()).unwrap();
+        assert!(matches!(resolved, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn unterminated_block() {
+        let message = error_message("one\ntwo\n#[modmod:if defined(var.x)]\nthree\n");
+        assert!(
+            message.contains("Conditional block opened at line 3 is never closed"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn unclosed_marker() {
+        let message = error_message("#[modmod:if defined(var.x)\n");
+        assert!(
+            message.contains("Unclosed conditional marker at line 1"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn unmatched_markers() {
+        let message = error_message("text\n#[modmod:endif]\n");
+        assert!(
+            message
+                .contains("Found `#[modmod:endif]` without matching `#[modmod:if ...]` at line 2"),
+            "{message}"
+        );
+
+        let message = error_message(indoc! {"
+            #[modmod:if defined(var.x)]
+            #[modmod:else]
+            #[modmod:else]
+            #[modmod:endif]
+        "});
+        assert!(
+            message.contains(
+                "Found a second `#[modmod:else]` at line 3 in the block opened at line 1"
+            ),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn errors_refer_to_the_line_in_the_file() {
+        let report = resolve_from_line(
+            "\n#[modmod:if defined(var.x)]\n",
+            10,
+            &Conditions::default(),
+        )
+        .unwrap_err();
+        let message = format!("{report:?}");
+        assert!(
+            message.contains("Conditional block opened at line 11 is never closed"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn unknown_profile() {
+        let profiles = ["online".to_string()];
+        let conditions = Conditions {
+            known_profiles: &profiles,
+            ..Conditions::default()
+        };
+        let content = "#[modmod:if profile = onlin]\n#[modmod:endif]\n";
+        let message = format!("{:?}", resolve(content, &conditions).unwrap_err());
+        assert!(
+            message.contains("Unknown profile 'onlin'") && message.contains("online"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn unset_variable() {
+        let message = error_message("#[modmod:if var.x = 1]\n#[modmod:endif]\n");
+        assert!(message.contains("Variable 'x' is not set"), "{message}");
+    }
+}
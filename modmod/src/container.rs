@@ -0,0 +1,172 @@
+//! A Docker build context for a server that serves a track: the output of its render, with a
+//! Dockerfile that builds the slides and the book in one stage, and serves them with a landing
+//! page from a small static server in the next. `modmod container` renders a track into the
+//! context and can build the image too.
+//!
+//! The site follows the output layout, like the deploy workflows of [`crate::deploy`], and is
+//! served under the path of the base URL the track was rendered for, as the slides are built
+//! for it. The `.dockerignore` leaves out the manifests of the render, which hold the time it
+//! was rendered at, so that rendering the same content gives the same context.
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{fmt, path::Path, process::Command};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+
+use crate::{
+    deploy::{shell_path, Site},
+    instructor::INSTRUCTOR_DIR,
+    manifest::RENDER_MANIFEST_FILE,
+    report::Warning,
+    scaffold::{Scaffold, GENERATED},
+    toolchain,
+    update::HASHES_FILE,
+    url::BaseUrl,
+};
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct ContainerError;
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write or build the container")
+    }
+}
+
+impl error_stack::Context for ContainerError {}
+
+/// The image the slides and the book are built in
+const BUILD_IMAGE: &str = "rust:1";
+
+/// The image that serves the site, which has a static web server
+const SERVER_IMAGE: &str = "busybox:1.36";
+
+/// The port the server listens on in the container
+const PORT: u16 = 8080;
+
+/// Writes the `Dockerfile`, `.dockerignore` and landing page of the build context into
+/// `out_dir`, the output folder of the track. Files that modmod didn't generate are only
+/// replaced with `force`.
+pub(crate) fn write(
+    site: &Site,
+    base_url: &BaseUrl,
+    out_dir: &Path,
+    force: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), ContainerError> {
+    if let Some(toolchain) = site.toolchain {
+        toolchain::validate(toolchain).change_context(ContainerError)?;
+    }
+    let mut scaffold = Scaffold { force, warnings };
+    scaffold.write::<ContainerError>(
+        &out_dir.join("Dockerfile"),
+        &dockerfile(site, base_url),
+        None,
+    )?;
+    scaffold.write::<ContainerError>(&out_dir.join(".dockerignore"), &dockerignore(site), None)?;
+    scaffold.write::<ContainerError>(&out_dir.join("index.html"), &site.landing_page(), None)
+}
+
+fn dockerfile(site: &Site, base_url: &BaseUrl) -> String {
+    // The site is put together under the path it's served at
+    let site_dir = shell_path(Path::new("_site").join(base_url.path().trim_matches('/')));
+    let mut file = format!(
+        "# {GENERATED} from the render of track '{}', to serve its slides and book.
+# Changes to it are lost when it is generated again.
+FROM {BUILD_IMAGE} AS build
+",
+        site.track_name
+    );
+    if !site.decks.is_empty() {
+        file.push_str(&format!(
+            "RUN curl -fsSL https://deb.nodesource.com/setup_{}.x | bash - \\
+    && apt-get install -y nodejs
+",
+            site.node()
+        ));
+    }
+    if site.book {
+        file.push_str(&format!("RUN {}\n", site.install_mdbook()));
+    }
+    file.push_str("WORKDIR /course\nCOPY . .\n");
+    let build: Vec<_> = site
+        .build_slides()
+        .into_iter()
+        .chain(site.build_book())
+        .collect();
+    if !build.is_empty() {
+        file.push_str(&format!("RUN {}\n", build.join(" \\\n    && ")));
+    }
+    let mut assemble = site.assemble(&site_dir);
+    assemble.push(format!("cp index.html {site_dir}/"));
+    file.push_str(&format!("RUN {}\n", assemble.join(" \\\n    && ")));
+    file.push_str(&format!(
+        "
+FROM {SERVER_IMAGE}
+COPY --from=build /course/_site /site
+EXPOSE {PORT}
+CMD [\"httpd\", \"-f\", \"-v\", \"-p\", \"{PORT}\", \"-h\", \"/site\"]
+"
+    ));
+    file
+}
+
+/// Leaves out what changes between renders of the same content, earlier builds, and the
+/// instructor notes
+fn dockerignore(site: &Site) -> String {
+    let slides = shell_path(site.layout.slides());
+    let ignored = [
+        RENDER_MANIFEST_FILE.to_string(),
+        HASHES_FILE.to_string(),
+        "_site".to_string(),
+        INSTRUCTOR_DIR.to_string(),
+        format!("{slides}/node_modules"),
+        shell_path(site.layout.slides().join(site.layout.dist())),
+        shell_path(site.layout.book().join("book")),
+    ];
+    format!("# {GENERATED}\n{}\n", ignored.join("\n"))
+}
+
+/// The tag of the image of a track that isn't given one: the slug of its name, with the
+/// course version or else `latest` as the tag, like `rust-intro:2025.1`
+pub fn image_tag(track_name: &str, course_version: Option<&str>) -> String {
+    let name = crate::to_tag(track_name);
+    // Tags can have letters, digits, `_`, `.` and `-`, so a version like `1.0.0+build.1`
+    // becomes `1.0.0-build.1`
+    let version: String = course_version
+        .unwrap_or("latest")
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect();
+    format!("{name}:{version}")
+}
+
+/// Builds the image of the build context in `context_dir` with `docker build`, tagged `tag`.
+/// A dry run only records the build, see [`PlannedWrite::Run`](crate::io::PlannedWrite::Run).
+pub fn build_image(context_dir: &Path, tag: &str) -> Result<(), ContainerError> {
+    if crate::io::writes_discarded() {
+        crate::io::record_run(format!("docker build --tag {tag} ."), context_dir);
+        return Ok(());
+    }
+    let status = Command::new("docker")
+        .args(["build", "--tag", tag, "."])
+        .current_dir(context_dir)
+        .status()
+        .into_report()
+        .attach_printable("Unable to run docker. Is it installed?")
+        .change_context(ContainerError)?;
+    if !status.success() {
+        return Err(Report::new(ContainerError).attach_printable(format!(
+            "`docker build --tag {tag} .` in {} failed: {status}",
+            context_dir.display()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,318 @@
+//! The state of a run of modmod: where the writes of [`PathExt`](crate::io::PathExt) go and
+//! how they are made, who is told about the progress, and what a render keeps track of
+//! while it writes its output.
+//!
+//! A [`RunContext`] is entered on a thread with [`RunContext::enter`], and the writes of that
+//! thread, and of the threads it renders on, go through it until the returned guard is
+//! dropped. Every render makes its own context from the one it's started in, see
+//! [`RunContext::for_render`], so that two renders in one process don't see each other's
+//! files. A thread without a context, like a task of an async runtime or a thread that a
+//! tool spawns, uses one default context of the process, which writes to the file system
+//! as it is.
+//!
+//! The context also holds the settings of a run that apply to everything it renders, like
+//! the number of threads and how slugs are made, and collects its warnings and errors, so
+//! that renders in other contexts of the same process have settings and failures of their
+//! own.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicUsize, Arc, Mutex, OnceLock},
+};
+
+use crate::{
+    cache::Cache,
+    failure::FailureSummary,
+    io::{DiscardingFs, FsSink, Journal, LineEnding, MemoryFs, PlannedWrite, RealFs, RecordingFs},
+    progress::{ProgressReporter, Timing},
+    report::{Assets, WarningCode},
+};
+
+thread_local! {
+    static STACK: RefCell<Vec<Arc<RunContext>>> = const { RefCell::new(Vec::new()) };
+}
+
+pub struct RunContext {
+    pub(crate) sink: Arc<dyn FsSink>,
+    recorder: Option<Arc<RecordingFs>>,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) sync_writes: bool,
+    pub(crate) rewrite_unchanged: bool,
+    pub(crate) progress: Option<Arc<dyn ProgressReporter>>,
+    /// The number of threads to load and render on, see [`RunContext::jobs`]
+    pub(crate) jobs: Option<usize>,
+    pub(crate) fail_fast: bool,
+    pub(crate) unicode_slugs: bool,
+    /// The errors and warnings that are logged, if they are collected, see
+    /// [`RunContext::collect_failures`]. Shared with the contexts of the renders of the
+    /// run, like the warning counts.
+    pub(crate) failures: Arc<Mutex<Option<FailureSummary>>>,
+    pub(crate) warning_counts: Arc<Mutex<BTreeMap<WarningCode, usize>>>,
+    /// Folders in the temporary directory that commands render into before comparing or
+    /// copying the output, which are written to even when writes are discarded. Shared
+    /// with the contexts of the renders of the run, like the journal.
+    pub(crate) scratch_dirs: Arc<Mutex<Vec<PathBuf>>>,
+    pub(crate) journal: Arc<Mutex<Option<Journal>>>,
+    pub(crate) written: AtomicUsize,
+    pub(crate) unchanged: AtomicUsize,
+    /// The files that were written or left unchanged, while they are tracked
+    pub(crate) tracked: Mutex<Option<HashSet<PathBuf>>>,
+    /// The files that were copied, keyed on their destination
+    pub(crate) copies: Mutex<BTreeMap<PathBuf, PathBuf>>,
+    pub(crate) timings: Mutex<Vec<Timing>>,
+    /// The assets copied for each deck and exercise package
+    pub(crate) assets: Mutex<Vec<(String, Assets)>>,
+    pub(crate) cache: Mutex<Option<Cache>>,
+}
+
+impl Default for RunContext {
+    fn default() -> Self {
+        Self::with_sink(RealFs)
+    }
+}
+
+impl fmt::Debug for RunContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunContext")
+            .field("writes_discarded", &self.writes_discarded())
+            .field("line_ending", &self.line_ending)
+            .field("sync_writes", &self.sync_writes)
+            .field("rewrite_unchanged", &self.rewrite_unchanged)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RunContext {
+    /// A context that sends the writes of [`PathExt`](crate::io::PathExt) to `sink`
+    pub fn with_sink(sink: impl FsSink + 'static) -> Self {
+        Self::from_sink(Arc::new(sink), None)
+    }
+
+    fn from_sink(sink: Arc<dyn FsSink>, recorder: Option<Arc<RecordingFs>>) -> Self {
+        Self {
+            sink,
+            recorder,
+            line_ending: LineEnding::Lf,
+            sync_writes: false,
+            rewrite_unchanged: false,
+            progress: None,
+            jobs: None,
+            fail_fast: false,
+            unicode_slugs: false,
+            failures: Arc::default(),
+            warning_counts: Arc::default(),
+            scratch_dirs: Arc::default(),
+            journal: Arc::default(),
+            written: AtomicUsize::new(0),
+            unchanged: AtomicUsize::new(0),
+            tracked: Mutex::new(None),
+            copies: Mutex::new(BTreeMap::new()),
+            timings: Mutex::new(vec![]),
+            assets: Mutex::new(vec![]),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// A context that leaves the file system alone: directories aren't created, files
+    /// aren't copied, and created files discard what is written to them. Used to run the
+    /// render pipelines of a track only to see whether they succeed.
+    pub fn discarding() -> Self {
+        Self::with_sink(DiscardingFs)
+    }
+
+    /// Like [`RunContext::discarding`], but keeps a list of the writes, which
+    /// [`RunContext::take_recorded_writes`] returns. Used for dry runs.
+    pub fn recording() -> Self {
+        let recorder = Arc::new(RecordingFs::default());
+        Self::from_sink(recorder.clone(), Some(recorder))
+    }
+
+    /// A context that keeps the files that are written in memory, see [`MemoryFs`], along
+    /// with those files
+    pub fn in_memory() -> (Self, Arc<MemoryFs>) {
+        let memory = Arc::new(MemoryFs::default());
+        (Self::from_sink(memory.clone(), None), memory)
+    }
+
+    /// Makes the text files created by
+    /// [`PathExt::create_text_file`](crate::io::PathExt::create_text_file) and
+    /// [`PathExt::write_text_file`](crate::io::PathExt::write_text_file) end their lines
+    /// with `line_ending`. Copied files are left as they are.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Makes written and copied files be synced to disk before they are moved into place,
+    /// so that they survive a crash of the system and not only of modmod. Slows down
+    /// rendering.
+    pub fn sync_writes(mut self, sync_writes: bool) -> Self {
+        self.sync_writes = sync_writes;
+        self
+    }
+
+    /// Makes files be written even if the file at their path has the same content.
+    /// Otherwise those are left alone, so that their modification time doesn't change,
+    /// which keeps the caches of build tools and incremental deploys from seeing a change.
+    pub fn rewrite_unchanged(mut self, rewrite_unchanged: bool) -> Self {
+        self.rewrite_unchanged = rewrite_unchanged;
+        self
+    }
+
+    /// Loads and renders on at most `jobs` threads, instead of on as many as there are CPUs
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs.max(1));
+        self
+    }
+
+    /// Makes renders stop at the first item that fails, rather than rendering the others
+    /// and reporting the errors of all items that failed together
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Makes slugs keep letters and digits that aren't ASCII, lowercased, rather than
+    /// transliterating or leaving them out, see [`crate::slug`]
+    pub fn unicode_slugs(mut self, unicode_slugs: bool) -> Self {
+        self.unicode_slugs = unicode_slugs;
+        self
+    }
+
+    /// Collects the errors and warnings that are logged in this context, also the ones
+    /// below the level that is logged, which [`crate::log::take_failures`] returns
+    pub fn collect_failures(self) -> Self {
+        *self.failures.lock().unwrap() = Some(FailureSummary::default());
+        self
+    }
+
+    /// Reports the progress of the renders in this context to `reporter`
+    pub fn progress(mut self, reporter: impl ProgressReporter + 'static) -> Self {
+        self.progress = Some(Arc::new(reporter));
+        self
+    }
+
+    /// A context for a render in this one. It writes to the same place in the same way,
+    /// but counts, tracks and remembers the copies of only the files of the render.
+    pub fn for_render(&self) -> Self {
+        Self {
+            line_ending: self.line_ending,
+            sync_writes: self.sync_writes,
+            rewrite_unchanged: self.rewrite_unchanged,
+            progress: self.progress.clone(),
+            jobs: self.jobs,
+            fail_fast: self.fail_fast,
+            unicode_slugs: self.unicode_slugs,
+            failures: self.failures.clone(),
+            warning_counts: self.warning_counts.clone(),
+            scratch_dirs: self.scratch_dirs.clone(),
+            journal: self.journal.clone(),
+            ..Self::from_sink(self.sink.clone(), self.recorder.clone())
+        }
+    }
+
+    /// Like [`RunContext::for_render`], for a command that leaves the file system alone,
+    /// see [`RunContext::discarding`]. Writes that are discarded already stay as they are,
+    /// so that those of a dry run are still recorded.
+    pub fn discard_writes(&self) -> Self {
+        match self.writes_discarded() {
+            true => self.for_render(),
+            false => Self {
+                sink: Arc::new(DiscardingFs),
+                recorder: None,
+                ..self.for_render()
+            },
+        }
+    }
+
+    /// Like [`RunContext::for_render`], for a command that keeps the files it writes in
+    /// memory, see [`RunContext::in_memory`]. Returns `None` if writes already go elsewhere.
+    pub fn keep_writes_in_memory(&self) -> Option<(Self, Arc<MemoryFs>)> {
+        if self.writes_discarded() {
+            return None;
+        }
+        let memory = Arc::new(MemoryFs::default());
+        let context = Self {
+            sink: memory.clone(),
+            ..self.for_render()
+        };
+        Some((context, memory))
+    }
+
+    /// Makes this the context of the current thread, until the returned guard is dropped
+    pub fn enter(self) -> Entered {
+        enter(Arc::new(self))
+    }
+
+    /// Whether writes go anywhere but the file system
+    pub fn writes_discarded(&self) -> bool {
+        !self.sink.is_real()
+    }
+
+    /// The writes recorded so far, or `None` if this isn't a [recording](Self::recording)
+    /// context
+    pub fn take_recorded_writes(&self) -> Option<Vec<PlannedWrite>> {
+        self.recorder.as_ref().map(|recorder| recorder.take())
+    }
+
+    /// Records `write` if this is a [recording](Self::recording) context
+    pub(crate) fn record(&self, write: PlannedWrite) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(write);
+        }
+    }
+
+    /// Whether writes to `path` are discarded: when they are in general, and it isn't in a
+    /// [`scratch_dir`](crate::io::scratch_dir)
+    pub(crate) fn writes_discarded_at(&self, path: &Path) -> bool {
+        self.writes_discarded()
+            && !self
+                .scratch_dirs
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|dir| path.starts_with(dir))
+    }
+}
+
+/// The context of the current thread, or the default context of the process if none was
+/// entered. The default is made once, so that its settings and counts hold across calls.
+pub fn current() -> Arc<RunContext> {
+    static DEFAULT: OnceLock<Arc<RunContext>> = OnceLock::new();
+    STACK
+        .with_borrow(|stack| stack.last().cloned())
+        .unwrap_or_else(|| DEFAULT.get_or_init(Arc::default).clone())
+}
+
+/// Makes `context` the context of the current thread, until the returned guard is dropped.
+/// Used to continue the context of a thread on the threads it renders on.
+pub(crate) fn enter(context: Arc<RunContext>) -> Entered {
+    STACK.with_borrow_mut(|stack| stack.push(context.clone()));
+    Entered(context)
+}
+
+/// Keeps a [`RunContext`] entered, see [`RunContext::enter`]
+pub struct Entered(Arc<RunContext>);
+
+impl Entered {
+    /// The context that was entered
+    pub fn context(&self) -> &RunContext {
+        &self.0
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        STACK.with_borrow_mut(|stack| {
+            if let Some(i) = stack
+                .iter()
+                .rposition(|context| Arc::ptr_eq(context, &self.0))
+            {
+                stack.remove(i);
+            }
+        });
+    }
+}
@@ -0,0 +1,138 @@
+//! A page that lists the tracks rendered into one output folder, each in its own subfolder,
+//! written to [`CATALOG_DIR`]`/index.html` by `generate` when it renders more than one track.
+//!
+//! The page is made from the `course.json` of each track, so it
+//! lists the tracks as they were last rendered, also those that weren't rendered again this
+//! time. Tracks without one yet are left out of it, and listed as such in its report. Tracks
+//! rendered with `--thumbnails` are shown with the preview of their first deck.
+
+use std::{fmt, path::Path};
+
+use error_stack::{IntoReport, Result, ResultExt};
+
+use crate::{
+    cartridge::escape,
+    course_manifest::{CourseManifest, COURSE_MANIFEST_FILE},
+    io::PathExt,
+    list::duration,
+    scaffold::GENERATED,
+};
+
+/// Folder of the output the catalog page is written into
+pub const CATALOG_DIR: &str = "catalog";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct CatalogError {}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write the catalog of tracks")
+    }
+}
+
+impl error_stack::Context for CatalogError {}
+
+/// What went into the catalog page
+#[derive(Debug)]
+pub struct CatalogReport {
+    /// The subfolders of the tracks that are listed
+    pub listed: Vec<String>,
+    /// The subfolders of the tracks that have no course manifest, and are left out
+    pub not_rendered: Vec<String>,
+}
+
+impl fmt::Display for CatalogReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.listed.len() {
+            0 => write!(f, "No catalog of the tracks written")?,
+            listed => write!(
+                f,
+                "Catalog of {listed} track(s) written into {CATALOG_DIR}/index.html"
+            )?,
+        }
+        if !self.not_rendered.is_empty() {
+            write!(
+                f,
+                "\n  left out, as they haven't been rendered yet: {}",
+                self.not_rendered.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the catalog page of the tracks in the subfolders `track_dirs` of `out_dir`, in
+/// that order. Without any tracks to list, no page is written.
+pub fn write_catalog(out_dir: &Path, track_dirs: &[String]) -> Result<CatalogReport, CatalogError> {
+    let mut report = CatalogReport {
+        listed: vec![],
+        not_rendered: vec![],
+    };
+    let mut tracks = vec![];
+    for dir in track_dirs {
+        let path = out_dir.join(dir).join(COURSE_MANIFEST_FILE);
+        if !path.exists() {
+            report.not_rendered.push(dir.clone());
+            continue;
+        }
+        let json = path.read_to_string::<CatalogError>()?;
+        let manifest: CourseManifest = serde_json::from_str(&json)
+            .into_report()
+            .change_context(CatalogError::default())
+            .attach_printable_lazy(|| format!("Unable to read {}", path.display()))?;
+        report.listed.push(dir.clone());
+        tracks.push((dir, manifest));
+    }
+    if tracks.is_empty() {
+        return Ok(report);
+    }
+
+    let mut page = format!(
+        "<!DOCTYPE html>
+<!-- {GENERATED} -->
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>Courses</title>
+</head>
+<body>
+<h1>Courses</h1>
+<ul>
+"
+    );
+    for (dir, manifest) in tracks {
+        let track = &manifest.track;
+        page.push_str(&format!(
+            "<li>\n<h2><a href=\"../{}/\">{}</a></h2>\n",
+            escape(dir),
+            escape(&track.name)
+        ));
+        let thumbnail = manifest
+            .modules
+            .iter()
+            .flat_map(|m| m.units.iter())
+            .find_map(|u| u.thumbnail_url.as_ref());
+        if let Some(url) = thumbnail {
+            page.push_str(&format!(
+                "<img src=\"{}\" alt=\"\" loading=\"lazy\" width=\"320\">\n",
+                escape(url)
+            ));
+        }
+        if let Some(description) = &track.description {
+            page.push_str(&format!("<p>{}</p>\n", escape(description)));
+        }
+        page.push_str(&format!(
+            "<p>{} module(s), about {}</p>\n</li>\n",
+            manifest.modules.len(),
+            duration(track.estimated_minutes)
+        ));
+    }
+    page.push_str("</ul>\n</body>\n</html>\n");
+    let catalog_dir = out_dir.join(CATALOG_DIR);
+    catalog_dir.create_dir_all::<CatalogError>()?;
+    catalog_dir
+        .join("index.html")
+        .write_text_file::<CatalogError>(page)?;
+    Ok(report)
+}
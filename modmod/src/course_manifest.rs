@@ -0,0 +1,409 @@
+//! A description of a rendered course for websites and learning management systems, written
+//! to [`COURSE_MANIFEST_FILE`] in the output folder of a track by `generate --course-json`.
+//!
+//! Where the render manifest of [`crate::manifest`] lists the files of a render for tools
+//! that work on the output, this one describes the course for readers of it: the track, its
+//! modules and units with their objectives, summaries and estimated durations, their topics
+//! and exercises, and the URLs the slides and book pages are deployed at. The URLs are made from
+//! the base URL of the render, so they are absolute if it has an origin, and start at the
+//! root of the site otherwise.
+//!
+//! The types are the schema of the file. Fields are only added to it in a way that readers
+//! of an earlier version can ignore; anything else bumps [`COURSE_MANIFEST_VERSION`].
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cartridge::exercise_minutes, conditional::Conditions, exercises, io::PathExt, manifest,
+    stats::unit_counts, thumbnail::thumbnail_path, to_prefixed_tag, to_tag, url::BaseUrl,
+    version::CourseVersion, ContentFilter, Course, LoadTrackError, OutputLayout, Track,
+};
+
+/// Name of the course manifest in the output folder of a track
+pub const COURSE_MANIFEST_FILE: &str = "course.json";
+
+/// Version of the schema of [`CourseManifest`], bumped on changes that readers of an earlier
+/// version can't ignore
+pub const COURSE_MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseManifest {
+    /// See [`COURSE_MANIFEST_VERSION`]
+    pub schema_version: u32,
+    pub track: TrackEntry,
+    pub modules: Vec<ModuleEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackEntry {
+    /// Identifies the track, derived from its name
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The version of the course the track was rendered as, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The `[course]` table of the track definition
+    pub course: Course,
+    /// SPDX identifier of the license of the material, if the track has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The URL the output is deployed at
+    pub base_url: String,
+    /// The URL of the book, if it was rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book_url: Option<String>,
+    pub estimated_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleEntry {
+    /// The name of the folder of the module's definition
+    pub id: String,
+    /// The label of the module, in the numbering style of the track
+    pub label: String,
+    pub name: String,
+    pub description: String,
+    pub estimated_minutes: u32,
+    pub units: Vec<UnitEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitEntry {
+    /// The id `--units` selects the unit with
+    pub id: String,
+    /// The number of the unit in titles, like `2.1`
+    pub number: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The objectives of its topics, in order
+    pub objectives: Vec<String>,
+    /// The summaries of its topics, in order
+    pub summary: Vec<String>,
+    pub estimated_minutes: u32,
+    /// The URL of its deck, if one was rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slides_url: Option<String>,
+    /// The name of its deck in the slides folder without the extension, like
+    /// `2_1-basic-syntax`, if one was rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deck: Option<String>,
+    /// The URL of the preview of the title slide of its deck, if one was exported with
+    /// `generate --thumbnails`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    /// The URL of its page in the book, if one was rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chapter_url: Option<String>,
+    /// The topics it's made of, in order
+    #[serde(default)]
+    pub topics: Vec<TopicEntry>,
+    pub exercises: Vec<ExerciseEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicEntry {
+    pub id: String,
+    pub name: String,
+    /// The file the topic is defined in, relative to the content of the track
+    pub source: String,
+    pub objectives: Vec<String>,
+    pub summary: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub further_reading: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExerciseEntry {
+    pub id: String,
+    pub name: String,
+    pub optional: bool,
+    /// The folder of its package relative to the output folder, if it was rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Its share of the sessions that list it, or else of the estimated duration of the unit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_minutes: Option<u32>,
+}
+
+/// What the course manifest is made from, besides the track
+pub(crate) struct CourseManifestOptions<'a> {
+    pub(crate) layout: &'a OutputLayout,
+    pub(crate) base_url: &'a BaseUrl,
+    pub(crate) filter: ContentFilter<'a>,
+    pub(crate) conditions: &'a Conditions<'a>,
+    pub(crate) course_version: Option<&'a CourseVersion>,
+    /// The folders the exercises were rendered to, by the path of their source
+    pub(crate) exercise_paths: &'a HashMap<PathBuf, PathBuf>,
+}
+
+impl Track {
+    /// Describes the course as rendered into `out_dir`, and writes the description there.
+    /// Units that were left out of the render are left out, and so are the links to decks
+    /// and book pages that weren't written.
+    pub(crate) fn write_course_manifest(
+        &self,
+        out_dir: &Path,
+        options: CourseManifestOptions,
+    ) -> Result<CourseManifest, LoadTrackError> {
+        let CourseManifestOptions {
+            layout,
+            base_url,
+            filter,
+            conditions,
+            course_version,
+            exercise_paths,
+        } = options;
+        let source = |path: &Path| {
+            manifest::relative_path(&exercises::relative_path(&self.content_root, path))
+        };
+        let mut manifest = CourseManifest {
+            schema_version: COURSE_MANIFEST_VERSION,
+            track: TrackEntry {
+                id: to_tag(&self.name),
+                name: self.name.clone(),
+                description: self.description.clone(),
+                version: course_version.map(|v| v.to_string()),
+                course: self.course.clone(),
+                license: self.license.as_ref().map(|l| l.spdx.clone()),
+                base_url: base_url.to_string(),
+                book_url: None,
+                estimated_minutes: 0,
+            },
+            modules: vec![],
+        };
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut entry = ModuleEntry {
+                id: module.data.id(),
+                label: label.display.clone(),
+                name: module.data.name.clone(),
+                description: module.data.description.clone(),
+                estimated_minutes: 0,
+                units: vec![],
+            };
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| filter.includes_unit(&u.data));
+            for unit in units {
+                let name = &unit.data.name;
+                let topics: Vec<_> = unit
+                    .data
+                    .topics
+                    .iter()
+                    .map(|t| &t.data)
+                    .filter(|t| filter.includes(t.optional, &t.profiles))
+                    .collect();
+                let minutes = unit_counts(&unit.data, conditions)
+                    .change_context(LoadTrackError)?
+                    .estimated_minutes;
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let deck = layout
+                    .slides()
+                    .join(to_prefixed_tag(name, &prefix))
+                    .with_extension("md");
+                // Decks without content are not written
+                let slides_url = (unit.data.render_slides && out_dir.join(&deck).exists())
+                    .then(|| layout.deck_url(base_url, &prefix));
+                let deck = slides_url.as_ref().map(|_| to_prefixed_tag(name, &prefix));
+                let thumbnail = thumbnail_path(layout, &prefix);
+                let thumbnail_url = (slides_url.is_some() && out_dir.join(&thumbnail).exists())
+                    .then(|| base_url.join(&OutputLayout::url_path(&thumbnail)));
+                let page = to_tag(name);
+                let chapter = layout.book().join("src").join(&page).with_extension("md");
+                let chapter_url =
+                    (unit.data.render_book && out_dir.join(chapter).exists()).then(|| {
+                        base_url
+                            .join(&(OutputLayout::url_path(&layout.book().join(&page)) + ".html"))
+                    });
+                if chapter_url.is_some() {
+                    manifest.track.book_url =
+                        Some(base_url.join(&OutputLayout::dir_url_path(layout.book())));
+                }
+                let exercises: Vec<_> = topics
+                    .iter()
+                    .flat_map(|t| t.exercises.iter().map(|e| &e.data))
+                    .filter(|e| filter.includes(e.optional, &e.profiles))
+                    .collect();
+                entry.estimated_minutes += minutes;
+                entry.units.push(UnitEntry {
+                    id: unit.data.id(),
+                    number: label.qualify(unit.index),
+                    name: name.clone(),
+                    tags: unit.data.tags.clone(),
+                    objectives: topics.iter().flat_map(|t| t.objectives.clone()).collect(),
+                    summary: topics.iter().flat_map(|t| t.summary.clone()).collect(),
+                    estimated_minutes: minutes,
+                    slides_url,
+                    deck,
+                    thumbnail_url,
+                    chapter_url,
+                    topics: topics
+                        .iter()
+                        .map(|topic| TopicEntry {
+                            id: topic.id.clone(),
+                            name: topic.name.clone(),
+                            source: source(&topic.definition),
+                            objectives: topic.objectives.clone(),
+                            summary: topic.summary.clone(),
+                            further_reading: topic.further_reading.clone(),
+                        })
+                        .collect(),
+                    exercises: exercises
+                        .iter()
+                        .map(|exercise| ExerciseEntry {
+                            id: exercise.id.clone(),
+                            name: exercise.name.clone(),
+                            optional: exercise.optional,
+                            path: exercise_paths
+                                .get(&exercise.path)
+                                .filter(|rendered| out_dir.join(rendered).exists())
+                                .map(|rendered| {
+                                    manifest::relative_path(
+                                        rendered.strip_prefix(out_dir).unwrap_or(rendered),
+                                    )
+                                }),
+                            estimated_minutes: exercise.duration_minutes.or_else(|| {
+                                exercise_minutes(
+                                    &unit.data,
+                                    &exercise.name,
+                                    minutes,
+                                    exercises.len(),
+                                )
+                            }),
+                        })
+                        .collect(),
+                });
+            }
+            if entry.units.is_empty() {
+                continue;
+            }
+            manifest.track.estimated_minutes += entry.estimated_minutes;
+            manifest.modules.push(entry);
+        }
+        let json = serde_json::to_string_pretty(&manifest)
+            .into_report()
+            .change_context(LoadTrackError)?;
+        out_dir
+            .join(COURSE_MANIFEST_FILE)
+            .write_text_file::<LoadTrackError>(json + "\n")?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A manifest with each of its fields set
+    fn full_manifest() -> serde_json::Value {
+        json!({
+            "schema_version": COURSE_MANIFEST_VERSION,
+            "track": {
+                "id": "rust-intro",
+                "name": "Rust intro",
+                "description": "An introduction to Rust",
+                "version": "2025.1",
+                "course": {
+                    "code": "RUST-101",
+                    "institution": "Tweede golf",
+                    "start_date": "2025-02-03",
+                    "end_date": "2025-04-25",
+                    "cohort": "spring",
+                    "contact_email": "teach@example.com",
+                },
+                "license": "CC-BY-4.0",
+                "base_url": "https://example.com/rust/",
+                "book_url": "https://example.com/rust/book/",
+                "estimated_minutes": 90,
+            },
+            "modules": [{
+                "id": "A-basics",
+                "label": "A",
+                "name": "Basics",
+                "description": "The basics",
+                "estimated_minutes": 90,
+                "units": [{
+                    "id": "basic-syntax",
+                    "number": "A.1",
+                    "name": "Basic syntax",
+                    "tags": ["intro"],
+                    "objectives": ["Write a function"],
+                    "summary": ["Functions return their last expression"],
+                    "estimated_minutes": 90,
+                    "slides_url": "https://example.com/rust/slides/A_1/",
+                    "deck": "A_1-basic-syntax",
+                    "thumbnail_url": "https://example.com/rust/slides/A_1.png",
+                    "chapter_url": "https://example.com/rust/book/basic-syntax.html",
+                    "topics": [{
+                        "id": "functions",
+                        "name": "Functions",
+                        "source": "mods/A-basics/topics/functions/topic.toml",
+                        "objectives": ["Write a function"],
+                        "summary": ["Functions return their last expression"],
+                        "further_reading": ["https://doc.rust-lang.org/book/ch03-03-how-functions-work.html"],
+                    }],
+                    "exercises": [{
+                        "id": "fizzbuzz",
+                        "name": "FizzBuzz",
+                        "optional": true,
+                        "path": "exercises/A-basics/1-basic-syntax/1-fizzbuzz",
+                        "estimated_minutes": 30,
+                    }],
+                }],
+            }],
+        })
+    }
+
+    #[test]
+    fn manifest_survives_a_round_trip() {
+        let json = full_manifest();
+        let manifest: CourseManifest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(manifest.schema_version, COURSE_MANIFEST_VERSION);
+        assert_eq!(serde_json::to_value(&manifest).unwrap(), json);
+
+        let text = serde_json::to_string_pretty(&manifest).unwrap();
+        let manifest: CourseManifest = serde_json::from_str(&text).unwrap();
+        assert_eq!(manifest.schema_version, COURSE_MANIFEST_VERSION);
+        assert_eq!(serde_json::to_string_pretty(&manifest).unwrap(), text);
+    }
+
+    #[test]
+    fn fields_that_are_not_set_are_left_out() {
+        let mut json = full_manifest();
+        let unit = &mut json["modules"][0]["units"][0];
+        for field in ["tags", "slides_url", "deck", "thumbnail_url", "chapter_url"] {
+            unit.as_object_mut().unwrap().remove(field);
+        }
+        unit["exercises"][0].as_object_mut().unwrap().remove("path");
+        let manifest: CourseManifest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&manifest).unwrap(), json);
+
+        // Topics were added to units after the first manifests were written
+        json["modules"][0]["units"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("topics");
+        let manifest: CourseManifest = serde_json::from_value(json).unwrap();
+        assert!(manifest.modules[0].units[0].topics.is_empty());
+    }
+
+    #[test]
+    fn manifest_without_a_schema_version_is_refused() {
+        let mut json = full_manifest();
+        json.as_object_mut().unwrap().remove("schema_version");
+        assert!(serde_json::from_value::<CourseManifest>(json).is_err());
+    }
+}
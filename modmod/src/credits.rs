@@ -0,0 +1,266 @@
+//! The credits of the material of a track, written by `generate --credits` as
+//! [`CREDITS_FILE`] and [`CREDITS_JSON_FILE`] into the root of each output that is rendered,
+//! like the text of the license.
+//!
+//! Every topic that is rendered is listed with its exercises, its authors, where it comes
+//! from, and its license, grouped by license. A topic without a license of its own has the
+//! license of the track, and a topic without authors is credited with the attribution of
+//! the track's license. Topics that still have no authors or no license are listed under
+//! attribution unknown, with a warning for each, as their material can't be credited.
+//!
+//! The types are the schema of the JSON file. Fields are only added to it in a way that
+//! readers of an earlier version can ignore; anything else bumps [`CREDITS_VERSION`].
+
+use std::{collections::BTreeMap, path::Path};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    exercises,
+    io::PathExt,
+    manifest,
+    report::{Warning, WarningCode},
+    scaffold::GENERATED,
+    ContentFilter, LoadTrackError, Track,
+};
+
+/// Name of the credits in the root of each output
+pub const CREDITS_FILE: &str = "CREDITS.md";
+
+/// Name of the credits for tools, next to [`CREDITS_FILE`]
+pub const CREDITS_JSON_FILE: &str = "credits.json";
+
+/// Version of the schema of [`Credits`], bumped on changes that readers of an earlier
+/// version can't ignore
+pub const CREDITS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credits {
+    /// See [`CREDITS_VERSION`]
+    pub schema_version: u32,
+    /// The name of the track
+    pub track: String,
+    /// The credits of each license, by its SPDX identifier
+    pub licenses: Vec<LicenseCredits>,
+    /// The topics and exercises that have no authors or no license
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<Credit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseCredits {
+    /// SPDX identifier of the license
+    pub license: String,
+    /// The attribution of the track, if this is its license
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    pub credits: Vec<Credit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credit {
+    pub kind: CreditKind,
+    pub name: String,
+    /// The authors of the topic, or of the topic of the exercise. Empty if it is credited
+    /// with the attribution of the track's license.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Where the material comes from: the folder of a topic or exercise relative to the
+    /// content of the track, or the repository of an exercise that is hosted in one
+    pub source: String,
+    /// The commit of the repository, for an exercise that is hosted in one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreditKind {
+    Topic,
+    Exercise,
+}
+
+impl Track {
+    /// Writes the credits of the topics and exercises `filter` includes into each of the
+    /// `roots` of `out_dir`, and returns them
+    pub(crate) fn write_credits(
+        &self,
+        out_dir: &Path,
+        roots: &[&Path],
+        filter: ContentFilter,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Credits, LoadTrackError> {
+        let source = |path: &Path| {
+            manifest::relative_path(&exercises::relative_path(&self.content_root, path))
+        };
+        let track_license = self.license.as_ref();
+        let mut licenses: BTreeMap<&str, Vec<Credit>> = BTreeMap::new();
+        let mut unknown = vec![];
+        // Topics can be included in more than one unit
+        let mut listed = vec![];
+        let topics = self
+            .units()
+            .filter(|u| filter.includes_unit(u))
+            .flat_map(|u| u.topics.iter().map(|t| &t.data))
+            .filter(|t| filter.includes(t.optional, &t.profiles));
+        for topic in topics {
+            if listed.contains(&&topic.definition) {
+                continue;
+            }
+            listed.push(&topic.definition);
+            let license = topic
+                .license
+                .as_deref()
+                .or(track_license.map(|l| l.spdx.as_str()));
+            let credited = !topic.authors.is_empty()
+                || (topic.license.is_none()
+                    && track_license.is_some_and(|l| !l.attribution.trim().is_empty()));
+            let folder = topic.definition.parent().unwrap_or(&topic.definition);
+            let mut credits = vec![Credit {
+                kind: CreditKind::Topic,
+                name: topic.name.clone(),
+                authors: topic.authors.clone(),
+                source: source(folder),
+                commit: None,
+                license: license.map(str::to_string),
+            }];
+            let topic_exercises = topic
+                .exercises
+                .iter()
+                .map(|e| &e.data)
+                .filter(|e| filter.includes(e.optional, &e.profiles));
+            for exercise in topic_exercises {
+                credits.push(Credit {
+                    kind: CreditKind::Exercise,
+                    name: exercise.name.clone(),
+                    authors: topic.authors.clone(),
+                    source: match &exercise.git {
+                        Some(git) => git.url.clone(),
+                        None => source(&exercise.path),
+                    },
+                    commit: exercise.git.as_ref().map(|git| git.commit.clone()),
+                    license: license.map(str::to_string),
+                });
+            }
+            match license.filter(|_| credited) {
+                Some(license) => licenses.entry(license).or_default().extend(credits),
+                None => {
+                    let missing = match (topic.authors.is_empty(), license.is_none()) {
+                        (true, true) => "no authors and no license",
+                        (true, false) => "no authors",
+                        _ => "no license",
+                    };
+                    warnings.push(Warning::new(
+                        WarningCode::AttributionUnknown,
+                        format!(
+                            "Topic '{}' has {missing}, so its material is listed under attribution unknown in {CREDITS_FILE}",
+                            topic.name
+                        ),
+                        Some(&topic.definition),
+                    ));
+                    unknown.extend(credits);
+                }
+            }
+        }
+        let credits = Credits {
+            schema_version: CREDITS_VERSION,
+            track: self.name.clone(),
+            licenses: licenses
+                .into_iter()
+                .map(|(license, credits)| LicenseCredits {
+                    license: license.to_string(),
+                    attribution: track_license
+                        .filter(|l| l.spdx == license)
+                        .map(|l| l.attribution.clone()),
+                    credits,
+                })
+                .collect(),
+            unknown,
+        };
+
+        let markdown = credits.markdown();
+        let json = serde_json::to_string_pretty(&credits)
+            .into_report()
+            .change_context(LoadTrackError)
+            .attach_printable("Unable to serialize the credits")?;
+        for root in roots {
+            let root = out_dir.join(root);
+            root.join(CREDITS_FILE).write_text_file(&markdown)?;
+            root.join(CREDITS_JSON_FILE).write_text_file(&json)?;
+        }
+        Ok(credits)
+    }
+}
+
+impl Credits {
+    /// The credits as [`CREDITS_FILE`]
+    fn markdown(&self) -> String {
+        let mut markdown = format!(
+            "<!-- {GENERATED} -->\n# Credits\n\nThe material of {} is made of these topics and exercises, by license.\n",
+            self.track
+        );
+        for license in self.licenses.iter() {
+            markdown.push_str(&format!("\n## {}\n\n", license.license));
+            if let Some(attribution) = &license.attribution {
+                markdown.push_str(&format!("{attribution}\n\n"));
+            }
+            markdown.push_str(&table(&license.credits, false));
+        }
+        if !self.unknown.is_empty() {
+            markdown.push_str(
+                "\n## Attribution unknown\n\nThese have no authors or no license in their topic definition, so they can't be credited yet.\n\n",
+            );
+            markdown.push_str(&table(&self.unknown, true));
+        }
+        markdown
+    }
+}
+
+/// The credits as a table, with their license if `with_license`
+fn table(credits: &[Credit], with_license: bool) -> String {
+    let mut table = match with_license {
+        true => {
+            "| | Name | Authors | Source | License |\n| - | ---- | ------- | ------ | ------- |\n"
+        }
+        false => "| | Name | Authors | Source |\n| - | ---- | ------- | ------ |\n",
+    }
+    .to_string();
+    for credit in credits {
+        let kind = match credit.kind {
+            CreditKind::Topic => "Topic",
+            CreditKind::Exercise => "Exercise",
+        };
+        let authors = match credit.authors.is_empty() {
+            true => "-".to_string(),
+            false => cell(&credit.authors.join(", ")),
+        };
+        let source = match &credit.commit {
+            Some(commit) => format!("{} at `{commit}`", cell(&credit.source)),
+            None => format!("`{}`", cell(&credit.source)),
+        };
+        table.push_str(&format!(
+            "| {kind} | {} | {authors} | {source} |",
+            cell(&credit.name)
+        ));
+        if with_license {
+            table.push_str(&format!(
+                " {} |",
+                credit
+                    .license
+                    .as_deref()
+                    .map(cell)
+                    .unwrap_or("-".to_string())
+            ));
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// `text` as the cell of a markdown table
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,165 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A calendar date, as used in definition files.
+///
+/// Dates can be written either as a TOML local date (`2024-05-01`) or as a
+/// string in the same format (`"2024-05-01"`), and are validated when loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u8, day: u8) -> Result<Self, InvalidDate> {
+        if !(1..=12).contains(&month) {
+            return Err(InvalidDate(format!("month {month} is out of range")));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(InvalidDate(format!(
+                "day {day} is out of range for {year}-{month:02}"
+            )));
+        }
+        Ok(Self { year, month, day })
+    }
+
+    /// The current date in UTC
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self::from_days_since_epoch((secs / 86_400) as i64)
+    }
+
+    /// Number of days since 1970-01-01
+    pub fn days_since_epoch(&self) -> i64 {
+        // Algorithm from http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = self.year as i64 - i64::from(self.month <= 2);
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let m = self.month as i64;
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // Algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+
+    /// Number of days from `other` until `self`
+    pub fn days_after(&self, other: &Date) -> i64 {
+        self.days_since_epoch() - other.days_since_epoch()
+    }
+
+    pub fn add_days(&self, days: i64) -> Self {
+        Self::from_days_since_epoch(self.days_since_epoch() + days)
+    }
+
+    /// Day of the week, where Monday is 0 and Sunday is 6
+    pub fn weekday(&self) -> u8 {
+        // 1970-01-01 was a Thursday
+        (self.days_since_epoch() + 3).rem_euclid(7) as u8
+    }
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidDate(String);
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid date: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDate {}
+
+impl FromStr for Date {
+    type Err = InvalidDate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidDate(format!("'{s}' is not of the form YYYY-MM-DD"));
+        let mut parts = s.trim().splitn(3, '-');
+        let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(invalid());
+        }
+        let year = year.parse().map_err(|_| invalid())?;
+        let month = month.parse().map_err(|_| invalid())?;
+        let day = day.parse().map_err(|_| invalid())?;
+        Self::new(year, month, day)
+    }
+}
+
+impl TryFrom<toml::value::Datetime> for Date {
+    type Error = InvalidDate;
+
+    fn try_from(value: toml::value::Datetime) -> Result<Self, Self::Error> {
+        match value {
+            toml::value::Datetime {
+                date: Some(date),
+                time: None,
+                offset: None,
+            } => Self::new(date.year as i32, date.month, date.day),
+            other => Err(InvalidDate(format!(
+                "expected a date without time, found '{other}'"
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match toml::Value::deserialize(deserializer)? {
+            toml::Value::Datetime(d) => d.try_into(),
+            toml::Value::String(s) => s.parse(),
+            other => Err(InvalidDate(format!(
+                "expected a date, found a {}",
+                other.type_str()
+            ))),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
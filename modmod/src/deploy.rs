@@ -0,0 +1,421 @@
+//! A CI workflow that builds the slides and the book of a track and deploys them to Pages,
+//! written into the root of its output by `generate --deploy-workflow`. Everything it builds
+//! and copies is taken from the render: the folders of the output layout, the build script of
+//! each deck that was written, and the versions of the `[toolchain]` table.
+//!
+//! The site gets the layout the slides are built for, the same as [`crate::publish`] gives the
+//! branch it publishes to, with a landing page that links to the book and the decks, with the
+//! previews of the decks that have one.
+//! Jobs added below the marked line at the end of the workflow are kept when it is generated
+//! again, see [`crate::scaffold`].
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cartridge::escape,
+    io::PathExt,
+    report::Warning,
+    scaffold::{Scaffold, GENERATED, KEPT_BELOW},
+    slides::WrittenDeck,
+    thumbnail::{thumbnail_path, THUMBS_DIR},
+    toolchain, OutputLayout, Toolchain,
+};
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct WriteWorkflowError;
+
+impl fmt::Display for WriteWorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write the deploy workflow")
+    }
+}
+
+impl error_stack::Context for WriteWorkflowError {}
+
+/// The CI service a deploy workflow is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiProvider {
+    /// GitHub Actions, deploying to GitHub Pages
+    GitHub,
+    /// GitLab CI, deploying to GitLab Pages
+    GitLab,
+}
+
+impl CiProvider {
+    pub const ALL: [Self; 2] = [Self::GitHub, Self::GitLab];
+
+    /// Where the workflow is written, relative to the output folder
+    pub fn workflow_path(self) -> &'static Path {
+        Path::new(match self {
+            Self::GitHub => ".github/workflows/deploy-course.yml",
+            Self::GitLab => ".gitlab-ci.yml",
+        })
+    }
+
+    /// The folder the site is put together in, which is the one GitLab Pages deploys
+    fn site_dir(self) -> &'static str {
+        match self {
+            Self::GitHub => "_site",
+            Self::GitLab => "public",
+        }
+    }
+}
+
+impl fmt::Display for CiProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::GitHub => "github",
+            Self::GitLab => "gitlab",
+        })
+    }
+}
+
+impl std::str::FromStr for CiProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|provider| provider.to_string() == s)
+            .ok_or_else(|| format!("unknown CI provider '{s}', expected github or gitlab"))
+    }
+}
+
+/// What a render put in the output, which the workflow builds and deploys
+pub(crate) struct Site<'a> {
+    pub(crate) track_name: &'a str,
+    pub(crate) layout: &'a OutputLayout,
+    /// The decks that were written, or none if the slides weren't rendered
+    pub(crate) decks: &'a [WrittenDeck],
+    /// The prefixes of the decks that have a preview, see [`crate::thumbnail`]
+    pub(crate) thumbnails: &'a [String],
+    pub(crate) book: bool,
+    pub(crate) exercises: bool,
+    /// Whether the search page and its index were written
+    pub(crate) search: bool,
+    /// The files in the root of the output that are copied into the site, like the sitemap
+    pub(crate) root_files: Vec<&'static str>,
+    /// Whether search engines may index the site
+    pub(crate) indexable: bool,
+    /// Whether the book has quizzes for mdbook-quiz, which then has to be installed
+    pub(crate) quiz: bool,
+    pub(crate) toolchain: Option<&'a Toolchain>,
+}
+
+/// Writes the workflow for `provider` into `out_dir`. A workflow that modmod didn't generate
+/// is only replaced with `force`.
+pub(crate) fn write(
+    provider: CiProvider,
+    site: &Site,
+    out_dir: &Path,
+    force: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), WriteWorkflowError> {
+    if let Some(toolchain) = site.toolchain {
+        toolchain::validate(toolchain).change_context(WriteWorkflowError)?;
+    }
+    let path = out_dir.join(provider.workflow_path());
+    if let Some(parent) = path.parent() {
+        parent.create_dir_all::<WriteWorkflowError>()?;
+    }
+    let workflow = match provider {
+        CiProvider::GitHub => site.github(),
+        CiProvider::GitLab => site.gitlab(),
+    };
+    Scaffold { force, warnings }.write(&path, &workflow, None)
+}
+
+impl Site<'_> {
+    fn header(&self) -> String {
+        format!(
+            "# {GENERATED} from the render of track '{}', to build and deploy its slides and book.
+# Changes above the line at the end are lost when it is generated again.
+",
+            self.track_name
+        )
+    }
+
+    pub(crate) fn node(&self) -> String {
+        self.toolchain
+            .map_or_else(Toolchain::default_node, |t| t.node.clone())
+    }
+
+    pub(crate) fn install_mdbook(&self) -> String {
+        let mdbook = match self.toolchain.and_then(|t| t.mdbook.as_deref()) {
+            Some(version) => format!("cargo install mdbook --locked --version {version}"),
+            None => "cargo install mdbook --locked".to_string(),
+        };
+        match self.quiz {
+            true => format!("{mdbook} && cargo install mdbook-quiz --locked"),
+            false => mdbook,
+        }
+    }
+
+    pub(crate) fn build_slides(&self) -> Vec<String> {
+        if self.decks.is_empty() {
+            return vec![];
+        }
+        let slides = shell_path(self.layout.slides());
+        let mut commands = vec![format!("npm --prefix {slides} install")];
+        for deck in self.decks {
+            commands.push(format!("npm --prefix {slides} run build-{}", deck.prefix));
+        }
+        commands
+    }
+
+    pub(crate) fn build_book(&self) -> Vec<String> {
+        match self.book {
+            true => vec![format!("mdbook build {}", shell_path(self.layout.book()))],
+            false => vec![],
+        }
+    }
+
+    /// The commands that copy the built output into `site_dir`
+    pub(crate) fn assemble(&self, site_dir: &str) -> Vec<String> {
+        let site = Path::new(site_dir);
+        let mut copies: Vec<(PathBuf, PathBuf)> = vec![];
+        let slides_dist = self.layout.slides().join(self.layout.dist());
+        for deck in self.decks {
+            copies.push((
+                slides_dist.join(&deck.slug),
+                site.join(self.layout.slides()).join(&deck.prefix),
+            ));
+        }
+        if !self.thumbnails.is_empty() {
+            copies.push((
+                self.layout.slides().join(THUMBS_DIR),
+                site.join(self.layout.slides()).join(THUMBS_DIR),
+            ));
+        }
+        if self.book {
+            copies.push((
+                self.layout.book().join("book"),
+                site.join(self.layout.book()),
+            ));
+        }
+        if self.exercises {
+            copies.push((
+                self.layout.exercises().to_path_buf(),
+                site.join(self.layout.exercises()),
+            ));
+        }
+        let mut parents: BTreeSet<_> = copies
+            .iter()
+            .filter_map(|(_, to)| to.parent())
+            .map(shell_path)
+            .collect();
+        parents.insert(site_dir.to_string());
+        let mut commands = vec![format!(
+            "mkdir -p {}",
+            parents.into_iter().collect::<Vec<_>>().join(" ")
+        )];
+        for (from, to) in copies.iter() {
+            commands.push(format!("cp -r {} {}", shell_path(from), shell_path(to)));
+        }
+        if self.search {
+            commands.push(format!("cp search.html search-index.json {site_dir}/"));
+        }
+        if !self.root_files.is_empty() {
+            commands.push(format!("cp {} {site_dir}/", self.root_files.join(" ")));
+        }
+        commands
+    }
+
+    /// The command that writes the landing page into `site_dir`, a heredoc of several lines
+    fn write_landing_page(&self, site_dir: &str) -> Vec<String> {
+        let mut lines = vec![format!("cat > {site_dir}/index.html <<'EOF'")];
+        lines.extend(self.landing_page().lines().map(str::to_string));
+        lines.push("EOF".to_string());
+        lines
+    }
+
+    pub(crate) fn landing_page(&self) -> String {
+        let name = escape(self.track_name);
+        let mut page = format!(
+            "<!DOCTYPE html>
+<!-- {GENERATED} -->
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+{robots}<title>{name}</title>
+</head>
+<body>
+<h1>{name}</h1>
+",
+            robots = match self.indexable {
+                true => "",
+                false => "<meta name=\"robots\" content=\"noindex\">\n",
+            }
+        );
+        if self.book {
+            page.push_str(&format!(
+                "<p><a href=\"{}\">Book</a></p>\n",
+                OutputLayout::dir_url_path(self.layout.book())
+            ));
+        }
+        if !self.decks.is_empty() {
+            page.push_str("<h2>Slides</h2>\n<ul>\n");
+            for deck in self.decks {
+                let url = OutputLayout::dir_url_path(&self.layout.slides().join(&deck.prefix));
+                let name = escape(&deck.name);
+                match self.thumbnails.contains(&deck.prefix) {
+                    true => page.push_str(&format!(
+                        "<li><a href=\"{url}\"><img src=\"{}\" alt=\"\" loading=\"lazy\" width=\"320\"><br>{name}</a></li>\n",
+                        OutputLayout::url_path(&thumbnail_path(self.layout, &deck.prefix))
+                    )),
+                    false => page.push_str(&format!("<li><a href=\"{url}\">{name}</a></li>\n")),
+                }
+            }
+            page.push_str("</ul>\n");
+        }
+        page.push_str("</body>\n</html>\n");
+        page
+    }
+
+    fn github(&self) -> String {
+        let mut steps = String::from("      - uses: actions/checkout@v4\n");
+        if !self.decks.is_empty() {
+            steps.push_str(&format!(
+                "      - uses: actions/setup-node@v4
+        with:
+          node-version: \"{}\"
+",
+                self.node()
+            ));
+        }
+        let mut step = |name: &str, commands: Vec<String>| {
+            if commands.is_empty() {
+                return;
+            }
+            steps.push_str(&format!("      - name: {name}\n        run: |\n"));
+            for command in commands {
+                steps.push_str(&format!("          {command}\n"));
+            }
+        };
+        if self.book {
+            step("Install mdbook", vec![self.install_mdbook()]);
+        }
+        step("Build the slides", self.build_slides());
+        step("Build the book", self.build_book());
+        let site_dir = CiProvider::GitHub.site_dir();
+        let mut assemble = self.assemble(site_dir);
+        assemble.extend(self.write_landing_page(site_dir));
+        step("Assemble the site", assemble);
+        format!(
+            "{header}name: Deploy course
+
+on:
+  push:
+    branches: [main]
+  workflow_dispatch:
+
+permissions:
+  contents: read
+  pages: write
+  id-token: write
+
+concurrency:
+  group: pages
+  cancel-in-progress: false
+
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+{steps}      - uses: actions/configure-pages@v5
+      - uses: actions/upload-pages-artifact@v3
+        with:
+          path: {site_dir}
+
+  deploy:
+    needs: build
+    runs-on: ubuntu-latest
+    environment:
+      name: github-pages
+      url: ${{{{ steps.deployment.outputs.page_url }}}}
+    steps:
+      - id: deployment
+        uses: actions/deploy-pages@v4
+
+  # {KEPT_BELOW}
+",
+            header = self.header(),
+            site_dir = CiProvider::GitHub.site_dir(),
+        )
+    }
+
+    fn gitlab(&self) -> String {
+        let mut before = vec![];
+        if !self.decks.is_empty() {
+            before.push(format!(
+                "curl -fsSL https://deb.nodesource.com/setup_{}.x | bash -",
+                self.node()
+            ));
+            before.push("apt-get install -y nodejs".to_string());
+        }
+        if self.book {
+            before.push(self.install_mdbook());
+        }
+        let mut script: Vec<String> = self
+            .build_slides()
+            .into_iter()
+            .chain(self.build_book())
+            .collect();
+        let list = |commands: &[String]| -> String {
+            commands
+                .iter()
+                .map(|command| format!("    - {command}\n"))
+                .collect()
+        };
+        let site_dir = CiProvider::GitLab.site_dir();
+        script.extend(self.assemble(site_dir));
+        let mut script = list(&script);
+        // The heredoc that writes the landing page has to be a single command
+        script.push_str("    - |\n");
+        for line in self.write_landing_page(site_dir) {
+            script.push_str(&format!("      {line}\n"));
+        }
+        format!(
+            "{header}pages:
+  image: rust:1
+{before}  script:
+{script}  artifacts:
+    paths:
+      - {site_dir}
+  rules:
+    - if: $CI_COMMIT_BRANCH == $CI_DEFAULT_BRANCH
+
+# {KEPT_BELOW}
+",
+            header = self.header(),
+            before = match before.is_empty() {
+                true => String::new(),
+                false => format!("  before_script:\n{}", list(&before)),
+            },
+        )
+    }
+}
+
+/// `path` as a word of a shell command, quoted if it has characters the shell would treat
+/// differently
+pub(crate) fn shell_path(path: impl AsRef<Path>) -> String {
+    let path = OutputLayout::url_path(path.as_ref());
+    match path
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/'))
+    {
+        true => path,
+        false => format!("'{}'", path.replace('\'', "'\\''")),
+    }
+}
@@ -0,0 +1,199 @@
+//! Errors in definitions and content shown the way authors can act on them: the message,
+//! the file and line, and the offending line, instead of the whole error report.
+
+use std::{fmt::Write, fs, path::PathBuf};
+
+use error_stack::{AttachmentKind, Frame, FrameKind, Report};
+
+use crate::{catalog::ErrorKind, load::LoadError};
+
+/// Where in a file an error is. Attached to error reports, so that the error can be shown
+/// with the offending line, see [`Diagnostic`]. Locations attached further out fill in what
+/// the ones further in leave out, like the file of an error that only knows its line.
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    pub path: Option<PathBuf>,
+    pub line: Option<usize>,
+    /// The column the error starts at, counted in characters from 1
+    pub column: Option<usize>,
+    /// The number of characters the error spans
+    pub len: usize,
+    /// What is wrong at the location, if that is shorter than the message of the report
+    pub message: Option<String>,
+}
+
+impl Location {
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn line(line: usize) -> Self {
+        Self {
+            line: Some(line),
+            ..Default::default()
+        }
+    }
+
+    fn fill_in(&mut self, outer: &Location) {
+        self.path = self.path.take().or_else(|| outer.path.clone());
+        if self.line.is_none() {
+            self.line = outer.line;
+            self.column = outer.column;
+            self.len = outer.len;
+        }
+        self.message = self.message.take().or_else(|| outer.message.clone());
+    }
+}
+
+/// One of the errors in a report, with where it is
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Location,
+    /// The other messages attached to the error, outermost first
+    pub notes: Vec<String>,
+    /// What kind of error it is, if its report tells, see [`ErrorKind::of_frames`]
+    pub kind: Option<ErrorKind>,
+}
+
+impl Diagnostic {
+    /// The errors in the report, one for each of the errors it collects, in order
+    pub fn from_report<C>(report: &Report<C>) -> Vec<Self> {
+        let mut diagnostics = vec![];
+        collect(report.current_frames(), &mut vec![], &mut diagnostics);
+        diagnostics
+    }
+
+    /// Describes the error like `error[E0101]: <message>`, followed by the file, line and column,
+    /// the offending line with the part that is wrong underlined, and the notes. Colored
+    /// with ANSI escapes, which the log leaves out when it isn't colored.
+    pub fn render(&self) -> String {
+        const RED: &str = "\x1b[1;31m";
+        const BLUE: &str = "\x1b[1;34m";
+        const BOLD: &str = "\x1b[1m";
+        const RESET: &str = "\x1b[0m";
+
+        let Location {
+            path, line, column, ..
+        } = &self.location;
+        let source_line = path.as_ref().zip(*line).and_then(|(path, line)| {
+            let content = fs::read_to_string(path).ok()?;
+            Some(
+                content
+                    .lines()
+                    .nth(line.checked_sub(1)?)?
+                    .trim_end()
+                    .to_string(),
+            )
+        });
+        let gutter = " ".repeat(line.map_or(0, |l| l.to_string().len()));
+
+        let code = self
+            .kind
+            .map(|kind| format!("[{}]", kind.id()))
+            .unwrap_or_default();
+        let mut text = format!("{RED}error{code}{RESET}{BOLD}: {}{RESET}", self.message);
+        if let Some(path) = path {
+            let position = match (line, column) {
+                (Some(line), Some(column)) => format!(":{line}:{column}"),
+                (Some(line), None) => format!(":{line}"),
+                _ => String::new(),
+            };
+            let _ = write!(
+                text,
+                "\n{gutter}{BLUE}-->{RESET} {}{position}",
+                path.display()
+            );
+        }
+        if let (Some(source_line), Some(line)) = (source_line, line) {
+            let _ = write!(
+                text,
+                "\n{gutter} {BLUE}|{RESET}\n{BLUE}{line} |{RESET} {source_line}"
+            );
+            if let Some(column) = column {
+                let _ = write!(
+                    text,
+                    "\n{gutter} {BLUE}|{RESET} {}{RED}{}{RESET}",
+                    " ".repeat(column - 1),
+                    "^".repeat(self.location.len.max(1))
+                );
+            }
+        }
+        for note in &self.notes {
+            let _ = write!(text, "\n{gutter} {BLUE}={RESET} note: {note}");
+        }
+        text
+    }
+}
+
+/// Walks the frames depth first, adding a diagnostic for each frame without sources, from
+/// the frames on the way to it
+fn collect<'r>(frames: &'r [Frame], branch: &mut Vec<&'r Frame>, out: &mut Vec<Diagnostic>) {
+    for frame in frames {
+        branch.push(frame);
+        match frame.sources() {
+            [] => out.push(from_branch(branch)),
+            sources => collect(sources, branch, out),
+        }
+        branch.pop();
+    }
+}
+
+/// The diagnostic for the frames on the way to an error, outermost first. Like
+/// [`crate::failure::Failure`], the message is the text attached to the innermost context,
+/// or that context itself.
+fn from_branch(branch: &[&Frame]) -> Diagnostic {
+    let mut messages = vec![];
+    for frame in branch {
+        match frame.kind() {
+            FrameKind::Context(context) => {
+                messages.push((true, context.to_string().trim().to_string()))
+            }
+            FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                messages.push((false, attachment.to_string().trim().to_string()))
+            }
+            FrameKind::Attachment(_) => {}
+        }
+    }
+    let mut location = Location::default();
+    for frame in branch.iter().rev() {
+        if let Some(outer) = frame.downcast_ref::<Location>() {
+            location.fill_in(outer);
+        }
+        if let Some(error) = frame.downcast_ref::<LoadError>() {
+            location.fill_in(&Location::file(error.path()));
+        }
+    }
+    let message = match messages.iter().rposition(|(is_context, _)| *is_context) {
+        // Attachments are listed before the context they are attached to
+        Some(i) if i > 0 && !messages[i - 1].0 => messages.remove(i - 1).1,
+        Some(i) => messages.remove(i).1,
+        None => String::new(),
+    };
+    let message = location.message.clone().unwrap_or(message);
+    // Contexts are general, like `unable to load track`, attachments tell where it was,
+    // apart from the ones about the file that is shown anyway
+    let in_file = location
+        .path
+        .as_ref()
+        .map(|path| format!(" at {}", path.display()));
+    let notes = messages
+        .into_iter()
+        .filter(|(is_context, message)| {
+            !is_context
+                && !in_file
+                    .as_ref()
+                    .is_some_and(|s| message.ends_with(s.as_str()))
+        })
+        .map(|(_, message)| message)
+        .collect();
+    Diagnostic {
+        message,
+        location,
+        notes,
+        kind: ErrorKind::of_frames(branch.iter().copied()),
+    }
+}
@@ -0,0 +1,398 @@
+//! Word documents of the exercise descriptions of a rendered track, for readers that need
+//! them outside of the book.
+//!
+//! Each exercise is taken as it is in its chapter of the rendered book, so with the same
+//! exercise paths and references filled in, converted to HTML like [`crate::cartridge`]
+//! does, and then to `.docx` by pandoc. Its images are embedded in it, and its relative
+//! links point into the hosted book if a base URL with an origin is given. Each module
+//! gets a document with all of its exercises as well.
+//!
+//! Without a pandoc that can be run, or with one older than [`MIN_PANDOC_VERSION`], the
+//! documents are written as standalone HTML instead, so that they can still be opened in
+//! Word or converted later.
+
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use error_stack::{Report, Result};
+
+use crate::{
+    cartridge::{absolute_url, html_page, markdown_to_html},
+    handout::exercise_sections,
+    io::PathExt,
+    load::is_image,
+    to_prefixed_tag, to_tag,
+    url::BaseUrl,
+    version::CourseVersion,
+    OutputLayout, Track,
+};
+
+/// The folder the documents are written into by default
+pub const DOCX_DIR: &str = "dist/docx";
+
+/// The oldest pandoc that converts HTML to `.docx` well enough, as major and minor version
+pub const MIN_PANDOC_VERSION: (u32, u32) = (2, 0);
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct DocxError {}
+
+impl fmt::Display for DocxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to export the exercises as Word documents")
+    }
+}
+
+impl error_stack::Context for DocxError {}
+
+/// A pandoc that can be run, and its version
+#[derive(Debug, Clone)]
+pub struct Pandoc {
+    pub program: PathBuf,
+    pub version: String,
+}
+
+/// What the documents are converted with
+#[derive(Debug, Clone)]
+pub enum Converter {
+    Pandoc(Pandoc),
+    /// Standalone HTML, as pandoc can't be used for the given reason
+    Html {
+        reason: String,
+    },
+}
+
+impl Converter {
+    /// Finds pandoc and checks its version. A `program` that is given has to be usable,
+    /// otherwise the documents are written as HTML when `pandoc` can't be used.
+    pub fn detect(program: Option<&Path>) -> Result<Self, DocxError> {
+        let (given, program) = match program {
+            Some(program) => (true, program.to_path_buf()),
+            None => (false, PathBuf::from("pandoc")),
+        };
+        match Pandoc::find(&program) {
+            Ok(pandoc) => Ok(Self::Pandoc(pandoc)),
+            Err(reason) if given => Err(Report::new(DocxError::default()).attach_printable(reason)),
+            Err(reason) => Ok(Self::Html { reason }),
+        }
+    }
+
+    /// The extension of the documents it writes
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Pandoc(_) => "docx",
+            Self::Html { .. } => "html",
+        }
+    }
+}
+
+impl fmt::Display for Converter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pandoc(pandoc) => write!(f, "pandoc {}", pandoc.version),
+            Self::Html { .. } => f.write_str("standalone HTML"),
+        }
+    }
+}
+
+impl Pandoc {
+    /// Runs `program --version`, or tells why it can't be used
+    fn find(program: &Path) -> std::result::Result<Self, String> {
+        let output = Command::new(program)
+            .arg("--version")
+            .output()
+            .map_err(|e| format!("{} could not be run: {e}", program.display()))?;
+        if !output.status.success() {
+            return Err(format!(
+                "`{} --version` failed: {}",
+                program.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        // Like `pandoc 3.1.11.1`, or `pandoc.exe 2.19` on Windows
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let version = stdout
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+            .ok_or_else(|| {
+                format!(
+                    "{} doesn't tell its version like pandoc does, so it may not be pandoc",
+                    program.display()
+                )
+            })?;
+        let mut parts = version.split('.').map(|part| part.parse().unwrap_or(0));
+        let (major, minor) = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+        if (major, minor) < MIN_PANDOC_VERSION {
+            let (min_major, min_minor) = MIN_PANDOC_VERSION;
+            return Err(format!(
+                "pandoc {version} is older than {min_major}.{min_minor}, which is needed to convert to .docx"
+            ));
+        }
+        Ok(Self {
+            program: program.to_path_buf(),
+            version: version.to_string(),
+        })
+    }
+
+    /// Converts a standalone HTML page to `.docx`, and returns it with what pandoc wrote
+    /// to stderr
+    fn convert(&self, html: &str) -> std::result::Result<(Vec<u8>, String), String> {
+        let mut child = Command::new(&self.program)
+            .args(["--from", "html", "--to", "docx", "--output", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{} could not be run: {e}", self.program.display()))?;
+        let mut stdin = child.stdin.take();
+        // Written on another thread, so that pandoc can't block on a full stdout meanwhile
+        let output = std::thread::scope(|scope| {
+            scope.spawn(move || {
+                if let Some(stdin) = stdin.as_mut() {
+                    // A failed write shows as pandoc failing
+                    let _ = stdin.write_all(html.as_bytes());
+                }
+            });
+            child.wait_with_output()
+        })
+        .map_err(|e| format!("Waiting for pandoc failed: {e}"))?;
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if !output.status.success() {
+            return Err(match stderr.is_empty() {
+                true => format!("pandoc failed: {}", output.status),
+                false => format!("pandoc failed: {}\n{stderr}", output.status),
+            });
+        }
+        Ok((output.stdout, stderr))
+    }
+}
+
+pub struct DocxOptions<'a> {
+    /// The layout the book was rendered with
+    pub layout: &'a OutputLayout,
+    pub converter: &'a Converter,
+    /// The URL the book is deployed at, to point relative links to
+    pub base_url: Option<&'a BaseUrl>,
+    /// The version of the course the track was rendered as, added to the names of the
+    /// module documents
+    pub course_version: Option<&'a CourseVersion>,
+}
+
+/// A document that was converted
+#[derive(Debug)]
+pub struct Document {
+    pub file_name: String,
+    pub content: Vec<u8>,
+    /// The number of exercises in it
+    pub exercises: usize,
+    /// Whether it holds all exercises of a module
+    pub module: bool,
+}
+
+/// The documents of the exercises, and what came up converting them
+#[derive(Debug)]
+pub struct DocxExport {
+    pub documents: Vec<Document>,
+    /// What pandoc warned about, and images that couldn't be embedded, with the document
+    /// they are about
+    pub warnings: Vec<(String, String)>,
+}
+
+impl Track {
+    /// Converts the exercises of the track, as rendered into `out_dir` with the layout of
+    /// `options`, into a document each and a document for each module. Units without a
+    /// book chapter or exercises are left out.
+    pub fn export_docx(
+        &self,
+        out_dir: &Path,
+        options: DocxOptions,
+    ) -> Result<DocxExport, DocxError> {
+        let DocxOptions {
+            layout,
+            converter,
+            base_url,
+            course_version,
+        } = options;
+        let chapters_dir = out_dir.join(layout.book()).join("src");
+        let base_url = base_url.filter(|url| url.origin().is_some());
+        let book_url = base_url.map(|url| url.join(&OutputLayout::dir_url_path(layout.book())));
+        let missing_images = RefCell::new(vec![]);
+        let link = |url: &str| {
+            if let Some(data) = embedded_image(&chapters_dir, url) {
+                return data;
+            }
+            if is_local(url) && is_image(Path::new(url)) {
+                missing_images.borrow_mut().push(url.to_string());
+            }
+            match (base_url, &book_url) {
+                (Some(base_url), Some(book_url)) => absolute_url(url, base_url, book_url),
+                _ => url.to_string(),
+            }
+        };
+
+        let convert = |file_name: &str, title: &str, body: &str| {
+            let page = html_page(title, body);
+            let mut warnings: Vec<_> = missing_images
+                .take()
+                .into_iter()
+                .map(|image| format!("The image {image} can't be read, so it isn't embedded"))
+                .collect();
+            let content = match converter {
+                Converter::Pandoc(pandoc) => {
+                    let (content, stderr) = pandoc.convert(&page).map_err(|error| {
+                        Report::new(DocxError::default())
+                            .attach_printable(error)
+                            .attach_printable(format!("Converting {file_name}"))
+                    })?;
+                    warnings.extend(stderr.lines().map(str::to_string));
+                    content
+                }
+                Converter::Html { .. } => page.into_bytes(),
+            };
+            Ok::<_, Report<DocxError>>((content, warnings))
+        };
+        let mut export = DocxExport {
+            documents: vec![],
+            warnings: vec![],
+        };
+        let mut add = |document: Document, warnings: Vec<String>| {
+            export.warnings.extend(
+                warnings
+                    .into_iter()
+                    .map(|warning| (document.file_name.clone(), warning)),
+            );
+            export.documents.push(document);
+        };
+
+        let extension = converter.extension();
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut module_body = String::new();
+            let mut module_exercises = 0;
+            for unit in module.data.units.iter() {
+                let chapter = chapters_dir
+                    .join(to_tag(&unit.data.name))
+                    .with_extension("md");
+                if !unit.data.render_book || !chapter.exists() {
+                    continue;
+                }
+                let sections = exercise_sections(&chapter.read_to_string::<DocxError>()?);
+                if sections.is_empty() {
+                    continue;
+                }
+                let unit_title = format!("Unit {} - {}", label.qualify(unit.index), unit.data.name);
+                module_body.push_str(&markdown_to_html(&format!("# {unit_title}"), &link));
+                for (section, i) in sections.iter().zip(1..) {
+                    let heading = section
+                        .lines()
+                        .next()
+                        .and_then(|line| line.strip_prefix("## "))
+                        .unwrap_or_default()
+                        .trim();
+                    let name = heading
+                        .split_once(": ")
+                        .map_or(heading, |(_, name)| name)
+                        .trim_end_matches(" *(optional)*");
+                    let html = markdown_to_html(section, &link);
+                    module_body.push_str(&html);
+                    let prefix = format!("{}_{}_{i}", label.path, unit.index);
+                    let file_name = format!("{}.{extension}", to_prefixed_tag(name, &prefix));
+                    let title = heading.replace(" *(optional)*", " (optional)");
+                    let (content, warnings) = convert(&file_name, &title, &html)?;
+                    add(
+                        Document {
+                            file_name,
+                            content,
+                            exercises: 1,
+                            module: false,
+                        },
+                        warnings,
+                    );
+                }
+                module_exercises += sections.len();
+            }
+            if module_exercises == 0 {
+                continue;
+            }
+            let title = match label.display.is_empty() {
+                true => format!("{} - Exercises", module.data.name),
+                false => format!(
+                    "Module {} - {} - Exercises",
+                    label.display, module.data.name
+                ),
+            };
+            let name = to_prefixed_tag(&module.data.name, &label.path);
+            let file_name = match course_version {
+                Some(version) => format!("{name}-{}.{extension}", version.file_name_part()),
+                None => format!("{name}.{extension}"),
+            };
+            let (content, warnings) = convert(&file_name, &title, &module_body)?;
+            add(
+                Document {
+                    file_name,
+                    content,
+                    exercises: module_exercises,
+                    module: true,
+                },
+                warnings,
+            );
+        }
+        Ok(export)
+    }
+}
+
+/// Whether `url` refers to a file next to the chapter
+fn is_local(url: &str) -> bool {
+    !(url.is_empty()
+        || url.starts_with(['#', '/'])
+        || url.contains("://")
+        || url.starts_with("mailto:")
+        || url.starts_with("data:"))
+}
+
+/// The image at `url`, relative to the chapters, as a data URL
+fn embedded_image(chapters_dir: &Path, url: &str) -> Option<String> {
+    if !is_local(url) {
+        return None;
+    }
+    let path = url.split(['#', '?']).next().unwrap_or(url);
+    let path = chapters_dir.join(path);
+    let mime = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        _ => return None,
+    };
+    let bytes = fs::read(path).ok()?;
+    Some(format!("data:{mime};base64,{}", base64(&bytes)))
+}
+
+/// Encodes `bytes` in standard base64, with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,225 @@
+//! The estimated durations of a track, from the `duration_minutes` its topics and exercises
+//! set, and the [`SCHEDULE_FILE`] that `generate --schedule-md` writes into the output folder
+//! with the lecture and exercise time of each module and unit.
+//!
+//! The estimates of [`crate::stats`] are made of the same durations, see [`TopicEstimate`].
+//! Unlike those, these are only made of what the definitions say, so that lecturers can
+//! tell what is planned from what isn't: a topic or exercise without a duration is unknown,
+//! and shown as `?` rather than counted as no time at all. A sum of which only some parts
+//! are known is shown with a `+ ?`.
+
+use std::{fmt, fmt::Write, iter::Sum, ops::Add, path::Path};
+
+use error_stack::Result;
+
+use crate::{
+    io::PathExt, list, stats::MINUTES_PER_SLIDE, ContentFilter, LoadTrackError, Topic, Track,
+};
+
+/// Name of the schedule in the output folder of a track
+pub const SCHEDULE_FILE: &str = "schedule.md";
+
+/// A sum of estimated minutes, of parts that may not all have an estimate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Estimate {
+    /// The minutes of the parts that have an estimate
+    pub minutes: u32,
+    /// The number of parts that have an estimate
+    pub known: usize,
+    /// The number of parts that don't
+    pub unknown: usize,
+}
+
+impl Estimate {
+    /// The estimate of a single part
+    pub fn of(minutes: Option<u32>) -> Self {
+        match minutes {
+            Some(minutes) => Self {
+                minutes,
+                known: 1,
+                unknown: 0,
+            },
+            None => Self {
+                minutes: 0,
+                known: 0,
+                unknown: 1,
+            },
+        }
+    }
+
+    /// Whether any of the parts has an estimate
+    pub fn is_known(&self) -> bool {
+        self.known > 0
+    }
+
+    /// The lecture time of `topic`, and the time of its exercises that `filter` includes
+    pub(crate) fn of_topic(topic: &Topic, filter: ContentFilter) -> (Self, Self) {
+        let estimate = TopicEstimate::of(topic, filter);
+        (
+            estimate.lecture,
+            estimate.exercises + estimate.optional_exercises,
+        )
+    }
+}
+
+/// The estimated time of a topic and its exercises. The schedule, the durations in the
+/// decks and the estimates of [`crate::stats`] are all made of these, so that they agree on
+/// what the topics and exercises set.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TopicEstimate {
+    pub lecture: Estimate,
+    /// The exercises that aren't optional
+    pub exercises: Estimate,
+    pub optional_exercises: Estimate,
+}
+
+impl TopicEstimate {
+    /// The estimate of `topic`, with the exercises that `filter` includes
+    pub(crate) fn of(topic: &Topic, filter: ContentFilter) -> Self {
+        let mut estimate = Self {
+            lecture: Estimate::of(topic.duration_minutes),
+            ..Default::default()
+        };
+        for exercise in topic.exercises.iter().map(|e| &e.data) {
+            if !filter.includes(exercise.optional, &exercise.profiles) {
+                continue;
+            }
+            let part = Estimate::of(exercise.duration_minutes);
+            match exercise.optional {
+                true => estimate.optional_exercises = estimate.optional_exercises + part,
+                false => estimate.exercises = estimate.exercises + part,
+            }
+        }
+        estimate
+    }
+
+    /// The minutes of the lecture, which is estimated at [`MINUTES_PER_SLIDE`] for each of
+    /// its `slides` when the topic doesn't set its duration
+    pub(crate) fn lecture_minutes(&self, slides: usize) -> u32 {
+        match self.lecture.is_known() {
+            true => self.lecture.minutes,
+            false => slides as u32 * MINUTES_PER_SLIDE,
+        }
+    }
+
+    /// The minutes of core and of optional material, in that order, with the lecture
+    /// estimated like [`TopicEstimate::lecture_minutes`]. Exercises without a duration take
+    /// no time, and all of an optional topic is optional material.
+    pub(crate) fn minutes(&self, optional: bool, slides: usize) -> (u32, u32) {
+        let core = self.lecture_minutes(slides) + self.exercises.minutes;
+        match optional {
+            true => (0, core + self.optional_exercises.minutes),
+            false => (core, self.optional_exercises.minutes),
+        }
+    }
+}
+
+impl Add for Estimate {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            minutes: self.minutes + other.minutes,
+            known: self.known + other.known,
+            unknown: self.unknown + other.unknown,
+        }
+    }
+}
+
+impl Sum for Estimate {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Like `1h30m`, `1h30m + ?` when some parts have no estimate, `?` when none has one, and
+/// `-` when there are no parts
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.known, self.unknown) {
+            (0, 0) => f.write_str("-"),
+            (0, _) => f.write_str("?"),
+            (_, 0) => f.write_str(&list::duration(self.minutes)),
+            _ => write!(f, "{} + ?", list::duration(self.minutes)),
+        }
+    }
+}
+
+impl Track {
+    /// Writes the [`SCHEDULE_FILE`] of the modules and units `filter` includes into `out_dir`
+    pub(crate) fn write_schedule_md(
+        &self,
+        out_dir: &Path,
+        filter: ContentFilter,
+    ) -> Result<(), LoadTrackError> {
+        let mut md = format!("# Schedule of {}\n\n", self.name);
+        // Writing to a string doesn't fail
+        let _ = self.schedule_md(&mut md, filter);
+        out_dir
+            .join(SCHEDULE_FILE)
+            .write_text_file::<LoadTrackError>(md)
+    }
+
+    fn schedule_md(&self, md: &mut String, filter: ContentFilter) -> fmt::Result {
+        let mut total = (Estimate::default(), Estimate::default());
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut rows = vec![];
+            for unit in module
+                .data
+                .units
+                .iter()
+                .filter(|u| filter.includes_unit(&u.data))
+            {
+                let (lecture, exercises) = unit
+                    .data
+                    .topics
+                    .iter()
+                    .map(|t| &t.data)
+                    .filter(|t| filter.includes(t.optional, &t.profiles))
+                    .map(|t| Estimate::of_topic(t, filter))
+                    .fold(Default::default(), |(l, e), (lecture, exercises)| {
+                        (l + lecture, e + exercises)
+                    });
+                rows.push((
+                    label.qualify(unit.index),
+                    &unit.data.name,
+                    lecture,
+                    exercises,
+                ));
+            }
+            if rows.is_empty() {
+                continue;
+            }
+            match label.display.is_empty() {
+                true => writeln!(md, "## {}\n", module.data.name)?,
+                false => writeln!(md, "## Module {}: {}\n", label.display, module.data.name)?,
+            }
+            writeln!(md, "| Unit | Lecture | Exercises |")?;
+            writeln!(md, "| --- | --- | --- |")?;
+            let mut module_total = (Estimate::default(), Estimate::default());
+            for (number, name, lecture, exercises) in rows {
+                writeln!(md, "| {number} {name} | {lecture} | {exercises} |")?;
+                module_total = (module_total.0 + lecture, module_total.1 + exercises);
+            }
+            // A total of unknowns tells nothing
+            if module_total.0.is_known() || module_total.1.is_known() {
+                writeln!(
+                    md,
+                    "| **Total** | **{}** | **{}** |",
+                    module_total.0, module_total.1
+                )?;
+            }
+            writeln!(md)?;
+            total = (total.0 + module_total.0, total.1 + module_total.1);
+        }
+        if total.0.is_known() || total.1.is_known() {
+            writeln!(
+                md,
+                "In all, {} of lectures and {} of exercises.",
+                total.0, total.1
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+//! Running the tests of the exercises in an output folder, to confirm before a course is
+//! given that they pass, or fail where they are meant to.
+//!
+//! The exercises are found in the render manifests of the folder, and `cargo test` is run
+//! in the package of each. The outcome is read from the summary lines of libtest, as its
+//! JSON output is only available on nightly toolchains.
+
+use std::{fmt, path::Path, process::Command, time::Instant};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::Serialize;
+
+use crate::manifest::{ArtifactKind, RenderManifest};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ExerciseTestError {}
+
+impl fmt::Display for ExerciseTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to test the exercises")
+    }
+}
+
+impl error_stack::Context for ExerciseTestError {}
+
+#[derive(Debug, Default)]
+pub struct ExerciseTestOptions<'a> {
+    /// Whether the folder holds the solutions, so that the tests of all exercises are
+    /// expected to pass, rather than the exercises as handed out
+    pub solutions: bool,
+    /// Passed on to cargo, so that dependencies aren't fetched
+    pub offline: bool,
+    /// Build all exercises into this folder, so that their dependencies are built once
+    pub target_dir: Option<&'a Path>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expectation {
+    Pass,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Passed,
+    /// Some tests failed
+    Failed,
+    /// The package or its tests didn't compile
+    BuildFailed,
+    /// The exercise has no `Cargo.toml`, like one in C
+    Skipped,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::BuildFailed => "build failed",
+            Self::Skipped => "skipped",
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseTestResult {
+    pub title: String,
+    /// Path of the exercise package, relative to the output folder
+    pub path: String,
+    pub expected: Expectation,
+    pub outcome: Outcome,
+    /// Whether the tests passed or failed as expected. Skipped exercises count as expected.
+    pub as_expected: bool,
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub duration_ms: u128,
+    /// The first error cargo reported, for exercises that didn't build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TestTotals {
+    pub exercises: usize,
+    pub unexpected: usize,
+    pub skipped: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseTestReport {
+    pub solutions: bool,
+    pub exercises: Vec<ExerciseTestResult>,
+    pub totals: TestTotals,
+}
+
+impl ExerciseTestReport {
+    /// Whether every exercise passed or failed as expected
+    pub fn as_expected(&self) -> bool {
+        self.totals.unexpected == 0
+    }
+}
+
+/// Runs `cargo test` for each exercise in the render manifests of `out_dir`, in order
+pub fn test_exercises(
+    out_dir: &Path,
+    options: ExerciseTestOptions,
+) -> Result<ExerciseTestReport, ExerciseTestError> {
+    let manifests =
+        RenderManifest::read_all(out_dir).change_context(ExerciseTestError::default())?;
+    if manifests.is_empty() {
+        return Err(
+            Report::new(ExerciseTestError::default()).attach_printable(format!(
+                "{} has no render manifest, so it isn't the output folder of a track",
+                out_dir.display()
+            )),
+        );
+    }
+    let mut report = ExerciseTestReport {
+        solutions: options.solutions,
+        exercises: vec![],
+        totals: TestTotals::default(),
+    };
+    for (track_dir, manifest) in manifests {
+        let exercises = manifest
+            .artifacts
+            .into_iter()
+            .filter(|a| a.kind == ArtifactKind::Exercise);
+        for exercise in exercises {
+            let path = track_dir.join(&exercise.path);
+            let expected = match exercise.starter_fails_tests && !options.solutions {
+                true => Expectation::Fail,
+                false => Expectation::Pass,
+            };
+            tracing::info!(exercise = %path.display(), "Testing exercise");
+            let result = test_exercise(&out_dir.join(&path), expected, &options)?;
+            report.exercises.push(ExerciseTestResult {
+                title: exercise.title,
+                path: crate::manifest::relative_path(&path),
+                ..result
+            });
+        }
+    }
+    let totals = &mut report.totals;
+    for result in report.exercises.iter() {
+        totals.exercises += 1;
+        totals.unexpected += usize::from(!result.as_expected);
+        totals.skipped += usize::from(result.outcome == Outcome::Skipped);
+        totals.passed += result.passed;
+        totals.failed += result.failed;
+        totals.ignored += result.ignored;
+    }
+    Ok(report)
+}
+
+fn test_exercise(
+    dir: &Path,
+    expected: Expectation,
+    options: &ExerciseTestOptions,
+) -> Result<ExerciseTestResult, ExerciseTestError> {
+    let mut result = ExerciseTestResult {
+        title: String::new(),
+        path: String::new(),
+        expected,
+        outcome: Outcome::Skipped,
+        as_expected: true,
+        passed: 0,
+        failed: 0,
+        ignored: 0,
+        duration_ms: 0,
+        error: None,
+    };
+    if !dir.join("Cargo.toml").is_file() {
+        return Ok(result);
+    }
+
+    let start = Instant::now();
+    let mut command = Command::new("cargo");
+    command
+        .args(["test", "--color", "never"])
+        .current_dir(dir)
+        .env("CARGO_TERM_COLOR", "never");
+    if options.offline {
+        command.arg("--offline");
+    }
+    if let Some(target_dir) = options.target_dir {
+        // Cargo runs in the exercise, so a relative path would be relative to it
+        let target_dir = std::path::absolute(target_dir)
+            .into_report()
+            .change_context(ExerciseTestError::default())?;
+        command.arg("--target-dir").arg(target_dir);
+    }
+    let output = command
+        .output()
+        .into_report()
+        .attach_printable("Unable to run cargo")
+        .change_context(ExerciseTestError::default())?;
+    result.duration_ms = start.elapsed().as_millis();
+
+    // Each test binary ends with a line like
+    // `test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut summaries = 0;
+    for line in stdout.lines() {
+        let Some((_, counts)) = line
+            .trim()
+            .strip_prefix("test result: ")
+            .and_then(|l| l.split_once(". "))
+        else {
+            continue;
+        };
+        summaries += 1;
+        for count in counts.split(';') {
+            let mut words = count.split_whitespace();
+            let (Some(n), Some(what)) = (words.next(), words.next()) else {
+                continue;
+            };
+            let Ok(n) = n.parse::<usize>() else {
+                continue;
+            };
+            match what {
+                "passed" => result.passed += n,
+                "failed" => result.failed += n,
+                "ignored" => result.ignored += n,
+                _ => {}
+            }
+        }
+    }
+    result.outcome = match (output.status.success(), summaries) {
+        (true, _) => Outcome::Passed,
+        // Rustc errors out before any test binary runs
+        (false, 0) => Outcome::BuildFailed,
+        (false, _) => Outcome::Failed,
+    };
+    if result.outcome == Outcome::BuildFailed {
+        result.error = String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find(|line| line.starts_with("error"))
+            .map(str::to_string);
+    }
+    result.as_expected = match expected {
+        Expectation::Pass => result.outcome == Outcome::Passed,
+        // Starters may not compile yet either
+        Expectation::Fail => result.outcome != Outcome::Passed,
+    };
+    Ok(result)
+}
+
+impl fmt::Display for ExerciseTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .exercises
+            .iter()
+            .map(|e| e.path.len())
+            .max()
+            .unwrap_or(0)
+            .max("Exercise".len());
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>6} {:>6} {:>7}  Result",
+            "Exercise", "Expected", "Passed", "Failed", "Ignored"
+        )?;
+        for result in self.exercises.iter() {
+            let expected = match result.expected {
+                Expectation::Pass => "pass",
+                Expectation::Fail => "fail",
+            };
+            let mark = match (result.outcome, result.as_expected) {
+                (Outcome::Skipped, _) => "",
+                (_, true) => " (as expected)",
+                (_, false) => " (UNEXPECTED)",
+            };
+            writeln!(
+                f,
+                "{:<width$} {expected:>8} {:>6} {:>6} {:>7}  {}{mark}",
+                result.path, result.passed, result.failed, result.ignored, result.outcome
+            )?;
+            if let Some(error) = &result.error {
+                writeln!(f, "  {error}")?;
+            }
+        }
+        let TestTotals {
+            exercises,
+            unexpected,
+            skipped,
+            passed,
+            failed,
+            ignored,
+        } = self.totals;
+        write!(
+            f,
+            "{exercises} exercise(s) tested as {}: {} as expected, {unexpected} unexpected, {skipped} skipped; {passed} test(s) passed, {failed} failed, {ignored} ignored",
+            match self.solutions {
+                true => "solutions",
+                false => "handed out",
+            },
+            exercises - unexpected - skipped
+        )
+    }
+}
@@ -1,12 +1,22 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use std::{
-    collections::HashMap,
-    fmt,
+    collections::{HashMap, HashSet},
+    fmt, fs,
     path::{Path, PathBuf},
 };
 
-use error_stack::{IntoReport, Result, ResultExt};
+use error_stack::{IntoReport, Report, Result, ResultExt};
 
-use crate::{io::PathExt, to_prefixed_tag};
+use crate::{
+    cache::{self, Inputs},
+    io::{copy_tree, slash_path, walk_dir, CopyTreeOptions, DirEntry, PathExt},
+    parallel,
+    progress::{self, Phase},
+    report,
+    solutions::{self, Split},
+    to_prefixed_tag, to_tag, GitSource, SharedCrate,
+};
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -23,6 +33,7 @@ impl error_stack::Context for RenderExercisesError {}
 #[derive(Debug)]
 pub struct ExerciseCollection<'track> {
     module_exercises: Vec<ModuleExercises<'track>>,
+    shared_crates: &'track [SharedCrate],
 }
 
 impl<'track> ExerciseCollection<'track> {
@@ -30,85 +41,516 @@ impl<'track> ExerciseCollection<'track> {
         ExerciseCollectionBuilder {
             collection: ExerciseCollection {
                 module_exercises: vec![],
+                shared_crates: &[],
             },
         }
     }
 
+    /// Renders the starter code of the exercise packages into `exercises_dir`, with a README
+    /// per unit that links to the descriptions of its exercises in the book in `book_dir`, if
+    /// it is rendered. With `solutions_dir`, the solutions are rendered there as well, see
+    /// [`crate::solutions`].
     pub fn render(
         &self,
         output_dir: impl AsRef<Path>,
+        exercises_dir: &Path,
+        solutions_dir: Option<&Path>,
+        book_dir: Option<&Path>,
     ) -> Result<HashMap<PathBuf, PathBuf>, RenderExercisesError> {
         let output_dir = output_dir.as_ref();
-        let exercise_root_dir = output_dir.join("exercises");
+        let exercise_root_dir = output_dir.join(exercises_dir);
         exercise_root_dir.create_dir_all()?;
-        let mut exercise_output_paths = HashMap::new();
 
-        for mod_ex in self.module_exercises.iter() {
-            let mod_ex_out_dir = {
-                let mut d = exercise_root_dir.clone();
-                d.push(to_prefixed_tag(mod_ex.name, mod_ex.index));
-                d
-            };
-            mod_ex_out_dir.create_dir_all()?;
+        let shared_dir = exercise_root_dir.join("shared");
+        for shared_crate in self.shared_crates {
+            copy_crate(&shared_crate.path, &shared_dir.join(&shared_crate.name))?;
+        }
 
-            for unit_ex in mod_ex.unit_exercises.iter() {
-                let unit_ex_out_dir = {
-                    let mut d = mod_ex_out_dir.clone();
-                    d.push(to_prefixed_tag(unit_ex.name, unit_ex.index));
-                    d
+        let mut packages: Vec<_> = self
+            .packages(exercises_dir)
+            .into_iter()
+            .map(|(ex_pack, out_dir)| (ex_pack, out_dir, Code::Starter))
+            .collect();
+        if let Some(solutions_dir) = solutions_dir {
+            packages.extend(
+                self.packages(solutions_dir)
+                    .into_iter()
+                    .map(|(ex_pack, out_dir)| (ex_pack, out_dir, Code::Solution)),
+            );
+        }
+        let _phase = progress::start(Phase::Exercises, Some(packages.len()));
+        // The exercises whose includes match none of their files, which have an empty folder
+        let without_files = parallel::map(&packages, |(ex_pack, ex_pack_out_dir, code)| {
+            progress::advance(Phase::Exercises, ex_pack.name);
+            let _time = progress::time(Phase::Exercises, ex_pack.name);
+            let item = crate::manifest::relative_path(ex_pack_out_dir);
+            let ex_pack_out_dir = output_dir.join(ex_pack_out_dir);
+            ex_pack_out_dir.create_dir_all()?;
+            tracing::debug!(
+                kind = "exercise",
+                exercise = ex_pack.name,
+                path = %ex_pack_out_dir.display(),
+                "Copying exercise '{}' into {}",
+                ex_pack.name,
+                ex_pack_out_dir.display()
+            );
+
+            let content = walk_dir::<RenderExercisesError>(ex_pack.path, false)?;
+
+            // Create globset to match included files
+            let mut globset = globset::GlobSetBuilder::new();
+            for include in ex_pack.includes {
+                let pattern = slash_path::<RenderExercisesError>(&ex_pack.path.join(include))?;
+                globset.add(
+                    globset::Glob::new(&pattern)
+                        .into_report()
+                        .attach_printable_lazy(|| format!("Error parsing include glob '{include}'"))
+                        .change_context(RenderExercisesError::default())?,
+                );
+            }
+            let globset = globset
+                .build()
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Error building the include globs of exercise '{}'",
+                        ex_pack.name
+                    )
+                })
+                .change_context(RenderExercisesError::default())?;
+
+            let files = content.iter().map(DirEntry::path);
+            // The package is skipped if the files it's copied from and its rewritten
+            // manifests are the same as when it was last rendered
+            let mut inputs = Inputs::new(&ex_pack_out_dir);
+            let mut planned = vec![];
+            for included_file in files.filter(|f| globset.is_match(f)) {
+                let included_file_relative = included_file
+                    .strip_prefix(ex_pack.path)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "File {} matches an include glob of exercise '{}', but isn't in its folder {}",
+                            included_file.display(),
+                            ex_pack.name,
+                            ex_pack.path.display()
+                        )
+                    })
+                    .change_context(RenderExercisesError::default())?;
+                let included_file_dest = ex_pack_out_dir.join(included_file_relative);
+                let include_file_dest_dir = included_file_dest.parent().unwrap_or(&ex_pack_out_dir);
+                let is_manifest = included_file_relative.file_name() == Some("Cargo.toml".as_ref());
+                let in_exercise = || format!("In exercise '{}'", ex_pack.name);
+                let split = match included_file.extension().is_some_and(|e| e == "rs") {
+                    true => split_solution(included_file).attach_printable_lazy(in_exercise)?,
+                    false => None,
                 };
-                unit_ex_out_dir.create_dir_all()?;
+                let rewritten = if let Some(split) = split {
+                    let content = match code {
+                        Code::Starter => split.starter,
+                        Code::Solution => split.solution,
+                    };
+                    inputs = inputs.value(&included_file_relative).value(&content);
+                    Some(content)
+                } else if is_manifest && (!self.shared_crates.is_empty() || ex_pack.git.is_some()) {
+                    let mut manifest = point_to_shared_crates(
+                        Path::new(included_file),
+                        include_file_dest_dir,
+                        self.shared_crates,
+                        &shared_dir,
+                    )
+                    .attach_printable_lazy(in_exercise)?;
+                    if let Some(git) = ex_pack.git {
+                        manifest =
+                            record_git_source(&manifest, git).attach_printable_lazy(in_exercise)?;
+                    }
+                    inputs = inputs.value(&included_file_relative).value(&manifest);
+                    Some(manifest)
+                } else {
+                    inputs = inputs
+                        .file(included_file)
+                        .into_report()
+                        .attach_printable_lazy(|| {
+                            format!("Error reading file at path {}", included_file.display())
+                        })
+                        .change_context(RenderExercisesError::default())?;
+                    None
+                };
+                planned.push((included_file, included_file_dest, rewritten));
+            }
+            let without_files =
+                (planned.is_empty() && matches!(code, Code::Starter)).then_some(ex_pack.path);
+            let copied = planned
+                .iter()
+                .filter(|(_, _, rewritten)| rewritten.is_none())
+                .map(|(included_file, _, _)| *included_file);
+            if cache::cached(&inputs).is_some() {
+                report::record_assets(&item, copied);
+                return Ok(without_files);
+            }
 
-                for ex_pack in unit_ex.exercises.iter() {
-                    let ex_pack_out_dir = {
-                        let mut d = unit_ex_out_dir.clone();
-                        d.push(to_prefixed_tag(ex_pack.name, ex_pack.index));
-                        d
+            let mut outputs = vec![];
+            for (included_file, included_file_dest, rewritten) in planned.iter() {
+                if let Some(parent) = included_file_dest.parent() {
+                    parent.create_dir_all()?;
+                }
+                match rewritten {
+                    Some(rewritten) => included_file_dest.write_text_file(rewritten)?,
+                    None => included_file.copy(included_file_dest)?,
+                }
+                outputs.push(included_file_dest.clone());
+            }
+            cache::record(inputs, &outputs, &[]);
+            report::record_assets(&item, copied);
+            Ok(without_files)
+        })?;
+        let without_files: HashSet<_> = without_files.into_iter().flatten().collect();
+
+        self.write_unit_readmes(output_dir, exercises_dir, book_dir, &without_files)?;
+        Ok(self.output_paths(exercises_dir))
+    }
+
+    /// Writes a README into the folder of each unit with exercises, which lists them in order,
+    /// with links to their descriptions in the book in `book_dir`, and to their folders. The
+    /// exercises in `without_files` have an empty folder, which isn't linked to.
+    fn write_unit_readmes(
+        &self,
+        output_dir: &Path,
+        exercises_dir: &Path,
+        book_dir: Option<&Path>,
+        without_files: &HashSet<&Path>,
+    ) -> Result<(), RenderExercisesError> {
+        for mod_ex in self.module_exercises.iter() {
+            let mod_ex_out_dir = exercises_dir.join(to_prefixed_tag(mod_ex.name, mod_ex.label));
+            for unit_ex in mod_ex.unit_exercises.iter() {
+                if unit_ex.exercises.is_empty() {
+                    continue;
+                }
+                let unit_ex_out_dir =
+                    mod_ex_out_dir.join(to_prefixed_tag(unit_ex.name, unit_ex.index));
+                let page = book_dir.map(|book_dir| {
+                    let page = book_dir
+                        .join("src")
+                        .join(to_tag(unit_ex.name))
+                        .with_extension("md");
+                    relative_path(&unit_ex_out_dir, &page)
+                });
+                let mut readme = format!("# Exercises of {}\n\n", unit_ex.name);
+                readme.push_str(
+                    "Each exercise is in its own folder, in the order they are done:\n\n",
+                );
+                for (ex_pack, i) in unit_ex.exercises.iter().zip(1..) {
+                    let folder = to_prefixed_tag(ex_pack.name, ex_pack.index);
+                    let description = page.as_ref().zip(ex_pack.description.as_ref());
+                    let name = match description {
+                        Some((page, anchor)) => {
+                            let page = slash_path::<RenderExercisesError>(page)?;
+                            format!("[{}]({page}#{anchor})", ex_pack.name)
+                        }
+                        None => ex_pack.name.to_string(),
                     };
-                    ex_pack_out_dir.create_dir_all()?;
-
-                    let content = ex_pack.path.get_dir_content()?;
-
-                    // Create globset to match included files
-                    let mut globset = globset::GlobSetBuilder::new();
-                    for include in ex_pack.includes {
-                        globset.add(
-                            globset::Glob::new(ex_pack.path.join(include).to_str().unwrap())
-                                .into_report()
-                                .attach_printable_lazy(|| {
-                                    format!("Error parsing include glob '{include}'")
-                                })
-                                .change_context(RenderExercisesError::default())?,
-                        );
+                    match without_files.contains(ex_pack.path) {
+                        true => readme.push_str(&format!("{i}. {name}\n")),
+                        false => {
+                            readme.push_str(&format!("{i}. {name}, in [`{folder}`]({folder}/)\n"))
+                        }
                     }
-                    let globset = globset.build().unwrap();
-
-                    for included_file in content.files.iter().filter(|f| globset.is_match(f)) {
-                        let included_file_relative = Path::new(&included_file)
-                            .strip_prefix(ex_pack.path)
-                            .unwrap();
-                        let included_file_dest = ex_pack_out_dir.join(included_file_relative);
-                        let include_file_dest_dir = included_file_dest.parent().unwrap();
-                        include_file_dest_dir.create_dir_all()?;
-                        included_file.copy(included_file_dest)?;
+                }
+                output_dir
+                    .join(&unit_ex_out_dir)
+                    .join("README.md")
+                    .write_text_file(readme)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The folders the exercise packages are rendered to, relative to the output folder,
+    /// keyed on the folder of their source
+    pub fn output_paths(&self, exercises_dir: &Path) -> HashMap<PathBuf, PathBuf> {
+        self.packages(exercises_dir)
+            .into_iter()
+            .map(|(ex_pack, out_dir)| (ex_pack.path.to_path_buf(), out_dir))
+            .collect()
+    }
+
+    /// The exercise packages, with the folder they are rendered to relative to the output
+    /// folder. Units without exercises are left out, as are modules that only have such units.
+    fn packages(&self, exercises_dir: &Path) -> Vec<(&ExercisePackage<'track>, PathBuf)> {
+        let mut packages = vec![];
+        for mod_ex in self.module_exercises.iter() {
+            let mod_ex_out_dir = exercises_dir.join(to_prefixed_tag(mod_ex.name, mod_ex.label));
+            for unit_ex in mod_ex.unit_exercises.iter() {
+                let unit_ex_out_dir =
+                    mod_ex_out_dir.join(to_prefixed_tag(unit_ex.name, unit_ex.index));
+                for ex_pack in unit_ex.exercises.iter() {
+                    let ex_pack_out_dir =
+                        unit_ex_out_dir.join(to_prefixed_tag(ex_pack.name, ex_pack.index));
+                    packages.push((ex_pack, ex_pack_out_dir));
+                }
+            }
+        }
+        packages
+    }
+}
+
+/// Which code of the exercises a tree of their packages holds
+#[derive(Debug, Clone, Copy)]
+enum Code {
+    Starter,
+    Solution,
+}
+
+/// The starter code and solution of the Rust file at `path`, if it has solution markers.
+/// Files that aren't UTF-8 have none.
+fn split_solution(path: &Path) -> Result<Option<Split>, RenderExercisesError> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    solutions::split(path, &content).change_context(RenderExercisesError::default())
+}
+
+/// Copies a crate, leaving out its build output
+fn copy_crate(from: &Path, to: &Path) -> Result<(), RenderExercisesError> {
+    let options = CopyTreeOptions {
+        recursive: true,
+        exclude: &["target", "target/**"],
+        preserve_structure: true,
+        ..Default::default()
+    };
+    copy_tree::<RenderExercisesError>(from, to, &options)?;
+    Ok(())
+}
+
+/// Rewrites the path dependencies on shared crates in an exercise manifest, so that they point
+/// to the copies of the shared crates in the output. Fails if the exercise depends on a crate with
+/// the name of a shared crate at some other path, or on a version the shared crate doesn't have.
+fn point_to_shared_crates(
+    manifest_path: &Path,
+    dest_dir: &Path,
+    shared_crates: &[SharedCrate],
+    shared_dir: &Path,
+) -> Result<String, RenderExercisesError> {
+    let content = manifest_path.read_to_string()?;
+    let mut manifest: toml_edit::Document = content
+        .parse()
+        .into_report()
+        .attach_printable_lazy(|| format!("Unable to parse {}", manifest_path.display()))
+        .change_context(RenderExercisesError::default())?;
+    let manifest_dir = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let tables = dependency_tables_mut(&mut manifest);
+    let mut changed = false;
+    for table in tables {
+        let Some(table) = table.as_table_like_mut() else {
+            continue;
+        };
+        for (key, dep) in table.iter_mut() {
+            let Some(declared) = dep.get("path").and_then(|p| p.as_str()) else {
+                continue;
+            };
+            let name = dep
+                .get("package")
+                .and_then(|p| p.as_str())
+                .unwrap_or(key.get());
+            let declared_path = manifest_dir.join(declared);
+            let canonical = declared_path.canonicalize().ok();
+            let Some(shared) = shared_crates
+                .iter()
+                .find(|c| c.name == name || canonical.as_ref() == Some(&c.path))
+            else {
+                continue;
+            };
+
+            if canonical.as_ref() != Some(&shared.path) || shared.name != name {
+                return Err(Report::new(RenderExercisesError::default()).attach_printable(format!(
+                    "{} depends on crate '{name}' at {}, but the track provides shared crate '{}' at {}",
+                    manifest_path.display(),
+                    declared_path.display(),
+                    shared.name,
+                    shared.path.display()
+                )));
+            }
+            if let Some(required) = dep.get("version").and_then(|v| v.as_str()) {
+                let matches = semver::VersionReq::parse(required)
+                    .ok()
+                    .zip(
+                        shared
+                            .version
+                            .as_deref()
+                            .and_then(|v| semver::Version::parse(v).ok()),
+                    )
+                    .is_some_and(|(req, version)| req.matches(&version));
+                if !matches {
+                    return Err(Report::new(RenderExercisesError::default()).attach_printable(format!(
+                        "{} requires version {required} of crate '{name}' at {}, but the shared crate at {} has version {}",
+                        manifest_path.display(),
+                        declared_path.display(),
+                        shared.path.display(),
+                        shared.version.as_deref().unwrap_or("none")
+                    )));
+                }
+            }
+
+            let relative = relative_path(dest_dir, &shared_dir.join(&shared.name));
+            dep["path"] = toml_edit::value(relative.to_string_lossy().replace('\\', "/"));
+            changed = true;
+        }
+    }
+
+    Ok(if changed {
+        manifest.to_string()
+    } else {
+        content
+    })
+}
+
+/// The dependency tables of a manifest, including those for specific targets
+pub(crate) fn dependency_tables_mut(
+    manifest: &mut toml_edit::Document,
+) -> Vec<&mut toml_edit::Item> {
+    let mut tables: Vec<&mut toml_edit::Item> = vec![];
+    for (key, item) in manifest.as_table_mut().iter_mut() {
+        match key.get() {
+            "dependencies" | "dev-dependencies" | "build-dependencies" => tables.push(item),
+            "target" => {
+                let Some(targets) = item.as_table_like_mut() else {
+                    continue;
+                };
+                for (_, target) in targets.iter_mut() {
+                    let Some(target) = target.as_table_like_mut() else {
+                        continue;
+                    };
+                    for (key, item) in target.iter_mut() {
+                        if matches!(
+                            key.get(),
+                            "dependencies" | "dev-dependencies" | "build-dependencies"
+                        ) {
+                            tables.push(item);
+                        }
                     }
+                }
+            }
+            _ => {}
+        }
+    }
+    tables
+}
+
+/// Adds the paths of the manifests in `dir` and its subfolders to `found`, leaving out
+/// build output
+pub(crate) fn find_manifests(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| Some(e.ok()?.path())) {
+        if path.is_dir() && path.file_name() != Some("target".as_ref()) {
+            find_manifests(&path, found);
+        } else if path.file_name() == Some("Cargo.toml".as_ref()) {
+            found.push(path);
+        }
+    }
+}
 
-                    let ex_pack_out_dir = ex_pack_out_dir
-                        .strip_prefix(output_dir)
-                        .unwrap()
-                        .to_path_buf();
-                    exercise_output_paths.insert(ex_pack.path.to_path_buf(), ex_pack_out_dir);
+/// The shared crates that the manifests in the exercise folder depend on by path, in the
+/// order of the track. Manifests that can't be read or parsed are left to rendering to report.
+pub(crate) fn shared_crate_dependencies<'c>(
+    exercise_dir: &Path,
+    shared_crates: &'c [SharedCrate],
+) -> Vec<&'c SharedCrate> {
+    fn dependency_tables(manifest: &toml::Table) -> Vec<&toml::Table> {
+        let kinds = ["dependencies", "dev-dependencies", "build-dependencies"];
+        let targets = manifest
+            .get("target")
+            .and_then(|t| t.as_table())
+            .into_iter()
+            .flat_map(|targets| targets.values().filter_map(|t| t.as_table()));
+        std::iter::once(manifest)
+            .chain(targets)
+            .flat_map(|table| kinds.iter().filter_map(|k| table.get(*k)?.as_table()))
+            .collect()
+    }
+
+    if shared_crates.is_empty() {
+        return vec![];
+    }
+    let mut found = vec![];
+    find_manifests(exercise_dir, &mut found);
+    let mut depends_on = vec![false; shared_crates.len()];
+    for manifest_path in found {
+        let Some(manifest) = std::fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok())
+        else {
+            continue;
+        };
+        let manifest_dir = manifest_path.parent().unwrap_or(Path::new(""));
+        for (key, dep) in dependency_tables(&manifest).into_iter().flatten() {
+            let Some(declared) = dep.get("path").and_then(|p| p.as_str()) else {
+                continue;
+            };
+            let name = dep.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+            let canonical = manifest_dir.join(declared).canonicalize().ok();
+            for (i, shared) in shared_crates.iter().enumerate() {
+                if shared.name == name || canonical.as_ref() == Some(&shared.path) {
+                    depends_on[i] = true;
                 }
             }
         }
+    }
+    shared_crates
+        .iter()
+        .zip(depends_on)
+        .filter_map(|(shared, depends)| depends.then_some(shared))
+        .collect()
+}
+
+/// Records the repository and commit an exercise was checked out from in the metadata
+/// of its manifest, so that the rendered exercise can be traced back to its source
+fn record_git_source(manifest: &str, git: &GitSource) -> Result<String, RenderExercisesError> {
+    let mut manifest: toml_edit::Document = manifest
+        .parse()
+        .into_report()
+        .change_context(RenderExercisesError::default())?;
+    // Workspaces without a root package keep their metadata in the workspace table
+    let root = ["package", "workspace"]
+        .into_iter()
+        .find(|key| manifest.contains_key(key));
+    let Some(root) = root else {
+        return Ok(manifest.to_string());
+    };
+    let mut source = toml_edit::Table::new();
+    source["git"] = toml_edit::value(&git.url);
+    source["commit"] = toml_edit::value(&git.commit);
+    let metadata = manifest[root]
+        .as_table_mut()
+        .map(|table| table.entry("metadata").or_insert(toml_edit::table()));
+    if let Some(metadata) = metadata.and_then(|m| m.as_table_mut()) {
+        metadata.set_implicit(true);
+        metadata.insert("modmod", toml_edit::Item::Table(source));
+    }
+    Ok(manifest.to_string())
+}
 
-        Ok(exercise_output_paths)
+/// The path to `to`, relative to the directory `from`
+pub(crate) fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut path = PathBuf::new();
+    for _ in from.components().skip(common) {
+        path.push("..");
     }
+    path.extend(to.components().skip(common));
+    path
 }
 
 #[derive(Debug)]
 pub struct ModuleExercises<'track> {
-    index: usize,
+    /// Path label of the module, see [`crate::ModuleLabel`]
+    label: &'track str,
     name: &'track str,
     unit_exercises: Vec<UnitExercises<'track>>,
 }
@@ -126,6 +568,10 @@ pub struct ExercisePackage<'track> {
     name: &'track str,
     path: &'track Path,
     includes: &'track [String],
+    git: Option<&'track GitSource>,
+    /// The anchor of the description of the exercise in the book page of its unit, if the
+    /// unit has one
+    description: Option<String>,
 }
 
 pub struct ExerciseCollectionBuilder<'track> {
@@ -136,18 +582,22 @@ impl<'track> ExerciseCollectionBuilder<'track> {
     pub fn module(
         &mut self,
         name: &'track str,
-        index: usize,
+        label: &'track str,
     ) -> ModuleExercisesBuilder<'track, '_> {
         ModuleExercisesBuilder {
             collection_buider: self,
             module_exercises: ModuleExercises {
-                index,
+                label,
                 name,
                 unit_exercises: vec![],
             },
         }
     }
 
+    pub fn shared_crates(&mut self, shared_crates: &'track [SharedCrate]) {
+        self.collection.shared_crates = shared_crates;
+    }
+
     pub fn build(self) -> ExerciseCollection<'track> {
         self.collection
     }
@@ -189,13 +639,22 @@ pub struct UnitExercisesBuilder<'track, 'c, 'm> {
 }
 
 impl<'track, 'c, 'm> UnitExercisesBuilder<'track, 'c, 'm> {
-    pub fn package(&mut self, name: &'track str, path: &'track Path, includes: &'track [String]) {
+    pub fn package(
+        &mut self,
+        name: &'track str,
+        path: &'track Path,
+        includes: &'track [String],
+        git: Option<&'track GitSource>,
+        description: Option<String>,
+    ) {
         let index = self.unit_exercises.exercises.len() + 1;
         self.unit_exercises.exercises.push(ExercisePackage {
             index,
             name,
             path,
             includes,
+            git,
+            description,
         })
     }
 
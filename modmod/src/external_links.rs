@@ -0,0 +1,360 @@
+//! Requesting the external links in the content of a track, to find the ones that can't be
+//! reached anymore
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    check::{invalid_url, is_url, links, CheckError},
+    git::default_cache_dir,
+    parallel,
+    report::{Warning, WarningCode},
+    Track,
+};
+
+/// How long curl may take for a single request, unless the options say otherwise
+const TIMEOUT_SECS: u64 = 10;
+/// The number of links that are requested at the same time, unless the options say otherwise
+const CONCURRENCY: usize = 8;
+/// How often a request that may fail only temporarily is tried again
+const RETRIES: u32 = 2;
+
+/// Options for [`Track::check_external_links`]
+#[derive(Debug, Clone)]
+pub struct ExternalLinkOptions {
+    /// The file the results are kept in between runs, or `None` to request every link
+    pub cache_file: Option<PathBuf>,
+    /// How long a link that could be reached isn't requested again
+    pub cache_max_age: Duration,
+    /// The number of links that are requested at the same time
+    pub concurrency: usize,
+    /// How long a single request may take
+    pub timeout: Duration,
+}
+
+impl Default for ExternalLinkOptions {
+    fn default() -> Self {
+        Self {
+            cache_file: Some(default_cache_dir().join("external-links.json")),
+            cache_max_age: Duration::from_secs(24 * 60 * 60),
+            concurrency: CONCURRENCY,
+            timeout: Duration::from_secs(TIMEOUT_SECS),
+        }
+    }
+}
+
+/// Why a link can't be reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The server responded with an error status
+    Http(u16),
+    /// There was no response, because e.g. the host doesn't exist or the request timed out
+    Network(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Http(status) => write!(f, "HTTP error: status {status}"),
+            LinkError::Network(message) => write!(f, "network error: {message}"),
+        }
+    }
+}
+
+/// A link in the content that can't be reached
+#[derive(Debug, Clone)]
+pub struct UnreachableLink {
+    pub file: PathBuf,
+    pub line: usize,
+    pub url: String,
+    pub error: LinkError,
+}
+
+/// The outcome of [`Track::check_external_links`]
+#[derive(Debug, Default)]
+pub struct ExternalLinkReport {
+    /// The number of distinct URLs that were checked, including the cached ones
+    pub checked: usize,
+    /// The number of URLs that could be reached according to the cache
+    pub cached: usize,
+    /// The number of URLs that weren't checked, as they match `external_links_ignore`
+    pub ignored: usize,
+    /// The links that can't be reached, sorted by file and line
+    pub unreachable: Vec<UnreachableLink>,
+    /// Problems with the cache, which don't affect the results
+    pub warnings: Vec<Warning>,
+}
+
+impl fmt::Display for ExternalLinkReport {
+    /// Lists the unreachable links grouped by the file they are in
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (http, network): (Vec<_>, Vec<_>) = self
+            .unreachable
+            .iter()
+            .partition(|link| matches!(link.error, LinkError::Http(_)));
+        writeln!(
+            f,
+            "External links: {} URL(s) checked ({} from the cache), {} ignored, {} link(s) can't be reached ({} HTTP error(s), {} network error(s))",
+            self.checked,
+            self.cached,
+            self.ignored,
+            self.unreachable.len(),
+            http.len(),
+            network.len()
+        )?;
+        let mut file = None;
+        for link in &self.unreachable {
+            if file != Some(&link.file) {
+                writeln!(f, "  {}", link.file.display())?;
+                file = Some(&link.file);
+            }
+            writeln!(f, "    line {}: {} ({})", link.line, link.url, link.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Track {
+    /// Requests the external links in topic content, exercise descriptions and the further
+    /// reading of the topics, except the
+    /// ones matching `external_links_ignore`, to see whether they can still be reached.
+    /// Requests that may fail only temporarily are retried, and links that could be reached
+    /// recently according to the cache aren't requested again.
+    pub fn check_external_links(
+        &self,
+        opts: &ExternalLinkOptions,
+    ) -> Result<ExternalLinkReport, CheckError> {
+        if Command::new("curl").arg("--version").output().is_err() {
+            return Err(Report::new(CheckError::default())
+                .attach_printable("External links can't be checked, as curl could not be run"));
+        }
+        let mut ignore = globset::GlobSetBuilder::new();
+        for pattern in &self.external_links_ignore {
+            ignore.add(
+                globset::Glob::new(pattern)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error parsing external link ignore glob '{pattern}'")
+                    })
+                    .change_context(CheckError::default())?,
+            );
+        }
+        let ignore = ignore
+            .build()
+            .into_report()
+            .change_context(CheckError::default())?;
+
+        let mut found = vec![];
+        for file in self.markdown_files() {
+            // Failing to read the content is reported when rendering
+            let Ok(content) = fs::read_to_string(file) else {
+                continue;
+            };
+            found.extend(
+                links(&content)
+                    .into_iter()
+                    // Invalid URLs are reported by `Track::broken_links`
+                    .filter(|(_, target)| is_url(target) && invalid_url(target).is_none())
+                    .map(|(line, target)| (file.to_path_buf(), line, target.to_string())),
+            );
+        }
+        found.extend(
+            self.further_reading_links()
+                .into_iter()
+                .filter(|(_, _, url)| invalid_url(url).is_none()),
+        );
+        let mut urls: Vec<&str> = found.iter().map(|(_, _, url)| url.as_str()).collect();
+        urls.sort_unstable();
+        urls.dedup();
+        let mut report = ExternalLinkReport::default();
+        let (ignored, urls): (Vec<&str>, Vec<&str>) =
+            urls.into_iter().partition(|url| ignore.is_match(url));
+        report.ignored = ignored.len();
+        report.checked = urls.len();
+
+        let mut cache = match &opts.cache_file {
+            Some(path) => Cache::read(path, &mut report.warnings),
+            None => Cache::default(),
+        };
+        let now = unix_time();
+        let max_age = opts.cache_max_age.as_secs();
+        let (cached, requested): (Vec<&str>, Vec<&str>) = urls.into_iter().partition(|url| {
+            cache.links.get(*url).is_some_and(|link| {
+                link.error.is_none() && now.saturating_sub(link.checked_at) < max_age
+            })
+        });
+        report.cached = cached.len();
+
+        let results = parallel::map_with(&requested, opts.concurrency.max(1), |url| {
+            request(url, opts.timeout)
+        });
+        let mut failed = HashMap::new();
+        for (url, result) in requested.into_iter().zip(results) {
+            let (status, error) = match result {
+                Ok(status) => (Some(status), None),
+                Err(LinkError::Http(status)) => (Some(status), Some(LinkError::Http(status))),
+                Err(error) => (None, Some(error)),
+            };
+            cache.links.insert(
+                url.to_string(),
+                CachedLink {
+                    status,
+                    error: error.as_ref().map(ToString::to_string),
+                    checked_at: now,
+                },
+            );
+            if let Some(error) = error {
+                failed.insert(url, error);
+            }
+        }
+        if let Some(path) = &opts.cache_file {
+            cache.write(path, &mut report.warnings);
+        }
+
+        report.unreachable = found
+            .iter()
+            .filter_map(|(file, line, url)| {
+                Some(UnreachableLink {
+                    file: file.clone(),
+                    line: *line,
+                    url: url.clone(),
+                    error: failed.get(url.as_str())?.clone(),
+                })
+            })
+            .collect();
+        report
+            .unreachable
+            .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        Ok(report)
+    }
+}
+
+/// The outcome of the last request of each URL, shared by all tracks and runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    links: BTreeMap<String, CachedLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedLink {
+    /// The HTTP status of the response, if there was one
+    status: Option<u16>,
+    /// Why the link couldn't be reached, if it couldn't
+    error: Option<String>,
+    /// When the link was requested, in seconds since the Unix epoch
+    checked_at: u64,
+}
+
+impl Cache {
+    /// Reads the cache, starting with an empty one if it doesn't exist or can't be read
+    fn read(path: &Path, warnings: &mut Vec<Warning>) -> Self {
+        let Ok(json) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            warnings.push(Warning::new(
+                WarningCode::LinkCache,
+                format!("Ignoring the external link cache, as it can't be parsed: {e}"),
+                Some(path),
+            ));
+            Self::default()
+        })
+    }
+
+    /// Writes the cache with `std::fs`, as checking discards the writes made through
+    /// [`crate::io::PathExt`]. The cache isn't part of the checkout, so this is still safe on a
+    /// read-only one.
+    fn write(&self, path: &Path, warnings: &mut Vec<Warning>) {
+        let written = serde_json::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                fs::write(path, json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            warnings.push(Warning::new(
+                WarningCode::LinkCache,
+                format!("Unable to write the external link cache: {e}"),
+                Some(path),
+            ));
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Requests the URL, following redirects, and returns the status of the response. Requests
+/// without a response, and responses saying the server is busy or failing, are retried a
+/// few times.
+fn request(url: &str, timeout: Duration) -> std::result::Result<u16, LinkError> {
+    let mut attempt = 0;
+    loop {
+        let result = curl(url, true, timeout).and_then(|status| match status {
+            // Some servers don't support HEAD requests, or respond differently to them
+            400..=499 if status != 429 => curl(url, false, timeout),
+            _ => Ok(status),
+        });
+        let temporary = match &result {
+            Ok(status) => *status == 429 || *status >= 500,
+            Err(_) => true,
+        };
+        if !temporary || attempt == RETRIES {
+            return match result? {
+                status @ 400.. => Err(LinkError::Http(status)),
+                status => Ok(status),
+            };
+        }
+        attempt += 1;
+        thread::sleep(Duration::from_secs(attempt.into()));
+    }
+}
+
+/// Makes a HEAD or GET request to the URL using curl, and returns the status of the
+/// response
+fn curl(url: &str, head: bool, timeout: Duration) -> std::result::Result<u16, LinkError> {
+    let mut command = Command::new("curl");
+    if head {
+        command.arg("--head");
+    } else {
+        // Only the status is needed, not the body
+        command.args(["--range", "0-0"]);
+    }
+    let output = command
+        .args(["--silent", "--show-error", "--location", "--output"])
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .args(["--max-time", &timeout.as_secs().max(1).to_string()])
+        .args(["--write-out", "%{http_code}", url])
+        .output()
+        .map_err(|e| LinkError::Network(format!("unable to run curl: {e}")))?;
+    match String::from_utf8_lossy(&output.stdout).trim().parse() {
+        Ok(status) if status > 0 => Ok(status),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Like `curl: (6) Could not resolve host: example.com`
+            let message = stderr.trim().trim_start_matches("curl: ");
+            let message = match message.split_once(") ") {
+                Some((code, rest)) if code.starts_with('(') => rest,
+                _ => message,
+            };
+            Err(LinkError::Network(match message.is_empty() {
+                true => format!("request failed ({})", output.status),
+                false => message.to_string(),
+            }))
+        }
+    }
+}
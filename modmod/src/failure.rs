@@ -0,0 +1,251 @@
+//! What the exit code of modmod means, and a summary of the errors and warnings of a run
+//! for tools that wrap it, like CI jobs.
+
+use std::fmt;
+
+use error_stack::{AttachmentKind, Frame, FrameKind, Report};
+use serde::Serialize;
+
+use crate::{
+    catalog::ErrorKind,
+    conditional::ConditionalError,
+    diagnostic::Location,
+    format::FormatError,
+    frontmatter::FrontmatterError,
+    load::{HydrateTrackError, LoadError},
+    manifest::ArtifactKind,
+    report::{Warning, WarningCode},
+    LoadTrackError,
+};
+
+pub const SUCCESS_EXIT_CODE: i32 = 0;
+
+/// Exit code of `modmod diff` when the output would change, and of `modmod verify-output`
+/// when the published output drifted from its sources, which isn't an error
+pub const DIFFERENCES_EXIT_CODE: i32 = 4;
+
+/// Exit code of a run that succeeded, but with warnings that were denied with
+/// `--deny-warnings` or `--deny`
+pub const DENIED_WARNINGS_EXIT_CODE: i32 = 5;
+
+/// Exit code of `modmod check-links` when it found broken links
+pub const BROKEN_LINKS_EXIT_CODE: i32 = 6;
+
+/// Exit code of `modmod lint` when one of its checks found an error
+pub const LINT_ERRORS_EXIT_CODE: i32 = 7;
+
+/// The kind of error a run of modmod failed with, which sets its exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCode {
+    /// Rendering, copying or writing the output failed
+    Render,
+    /// A definition or the content it refers to is invalid: it can't be loaded or parsed,
+    /// or refers to files that don't exist
+    Validation,
+    /// The command line arguments are wrong
+    Usage,
+}
+
+impl ErrorCode {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Render => 1,
+            Self::Validation => 2,
+            Self::Usage => 3,
+        }
+    }
+
+    /// Tells the kind of error from the innermost [`ErrorKind`] attached to the report, which
+    /// is attached where the error occurred, like [`ErrorKind::WriteFailed`] by the file
+    /// system functions of [`crate::io`]. Without one, the contexts of the report tell it,
+    /// so that a write that fails while rendering is a render error, even though the error
+    /// is reported as a [`LoadTrackError`]. An `ErrorCode` attached to the report overrides
+    /// both, for reports that only summarize errors that were already logged. See
+    /// [`Failure::summarizes_others`].
+    pub fn of<C>(report: &Report<C>) -> Self {
+        if let Some(code) = report.downcast_ref::<ErrorCode>() {
+            *code
+        } else if report.contains::<UsageError>() {
+            Self::Usage
+        } else if let Some(kind) = innermost_kind(report.frames()) {
+            kind.code()
+        } else if report.contains::<LoadError>()
+            || report.contains::<LoadTrackError>()
+            || report.contains::<HydrateTrackError>()
+            || report.contains::<FormatError>()
+            || report.contains::<FrontmatterError>()
+            || report.contains::<ConditionalError>()
+        {
+            Self::Validation
+        } else {
+            Self::Render
+        }
+    }
+}
+
+/// The [`ErrorKind`] attached closest to where the first error of the frames occurred.
+/// The frames are outermost first, and the first error ends at the first frame without a
+/// source.
+fn innermost_kind<'f>(frames: impl Iterator<Item = &'f Frame>) -> Option<ErrorKind> {
+    let mut kind = None;
+    for frame in frames {
+        if let Some(attached) = frame.downcast_ref::<ErrorKind>() {
+            kind = Some(*attached);
+        }
+        if frame.sources().is_empty() {
+            break;
+        }
+    }
+    kind
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Render => "render",
+            Self::Validation => "validation",
+            Self::Usage => "usage",
+        })
+    }
+}
+
+/// Arguments that don't make sense, like skipping every render target
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct UsageError {}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid arguments")
+    }
+}
+
+impl error_stack::Context for UsageError {}
+
+/// An error or warning in a [`FailureSummary`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    /// The [`ErrorCode`] of an error, or `warning`
+    pub code: String,
+    /// The stable code of the error or warning in the [`crate::catalog`], like `E0101`
+    pub id: &'static str,
+    /// The [`WarningCode`] of a warning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<WarningCode>,
+    pub message: String,
+    /// The file the error or warning is about, if known
+    pub file: Option<String>,
+    /// The line in the file, if known
+    pub line: Option<usize>,
+    /// The kind of output a warning came up rendering, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<ArtifactKind>,
+    /// What was being done when the error occurred, outermost first
+    pub context: Vec<String>,
+}
+
+impl Failure {
+    /// The message is the most specific one in the report: the text attached to its
+    /// innermost context or, if there is none, that context itself. The other contexts and
+    /// printable attachments make up the context.
+    pub fn from_report<C>(report: &Report<C>) -> Self {
+        Self::from_frames(report.frames(), ErrorCode::of(report))
+    }
+
+    /// A failure for each of the errors the report collects, in order, like there is a
+    /// [`crate::diagnostic::Diagnostic`] for each
+    pub fn all_from_report<C>(report: &Report<C>) -> Vec<Self> {
+        let code = ErrorCode::of(report);
+        let mut branches = vec![];
+        collect_branches(report.current_frames(), &mut vec![], &mut branches);
+        branches
+            .into_iter()
+            .map(|branch| Self::from_frames(branch.into_iter(), code))
+            .collect()
+    }
+
+    fn from_frames<'f>(frames: impl Iterator<Item = &'f Frame> + Clone, code: ErrorCode) -> Self {
+        let id = ErrorKind::of_frames(frames.clone()).unwrap_or(code.into());
+        let mut messages = vec![];
+        let mut file = None;
+        let mut line = None;
+        for frame in frames {
+            match frame.kind() {
+                FrameKind::Context(context) => {
+                    messages.push((true, context.to_string().trim().to_string()))
+                }
+                FrameKind::Attachment(AttachmentKind::Printable(attachment)) => {
+                    messages.push((false, attachment.to_string().trim().to_string()))
+                }
+                FrameKind::Attachment(_) => {}
+            }
+            if let Some(error) = frame.downcast_ref::<LoadError>() {
+                file = Some(error.path().display().to_string());
+            }
+            if let Some(location) = frame.downcast_ref::<Location>() {
+                file = file.or_else(|| Some(location.path.as_ref()?.display().to_string()));
+                line = line.or(location.line);
+            }
+        }
+        let message = match messages.iter().rposition(|(is_context, _)| *is_context) {
+            // Attachments are listed before the context they are attached to
+            Some(i) if i > 0 && !messages[i - 1].0 => messages.remove(i - 1).1,
+            Some(i) => messages.remove(i).1,
+            None => String::new(),
+        };
+        Self {
+            code: code.to_string(),
+            id: id.id(),
+            warning: None,
+            message,
+            file,
+            line,
+            artifact: None,
+            context: messages.into_iter().map(|(_, message)| message).collect(),
+        }
+    }
+
+    /// Whether the report only summarizes errors that were logged before, which is when an
+    /// [`ErrorCode`] is attached to it
+    pub fn summarizes_others<C>(report: &Report<C>) -> bool {
+        report.contains::<ErrorCode>()
+    }
+
+    pub fn from_warning(warning: &Warning) -> Self {
+        Self {
+            code: "warning".to_string(),
+            id: warning.code.id(),
+            warning: Some(warning.code),
+            message: warning.message.clone(),
+            file: warning.source.as_ref().map(|p| p.display().to_string()),
+            line: warning.line,
+            artifact: warning.artifact,
+            context: vec![],
+        }
+    }
+}
+
+/// Collects the frames on the way to each frame without sources, outermost first
+fn collect_branches<'r>(
+    frames: &'r [Frame],
+    branch: &mut Vec<&'r Frame>,
+    out: &mut Vec<Vec<&'r Frame>>,
+) {
+    for frame in frames {
+        branch.push(frame);
+        match frame.sources() {
+            [] => out.push(branch.clone()),
+            sources => collect_branches(sources, branch, out),
+        }
+        branch.pop();
+    }
+}
+
+/// Everything that went wrong in a run of modmod, printed as JSON with `--error-format json`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FailureSummary {
+    pub exit_code: i32,
+    pub errors: Vec<Failure>,
+    pub warnings: Vec<Failure>,
+}
@@ -0,0 +1,618 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use toml_edit::{Array, Decor, Document, Item, Table, Value};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct FormatError {}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to format definition file")
+    }
+}
+
+impl error_stack::Context for FormatError {}
+
+/// Describes how the keys of a table in a definition file are formatted
+struct Schema {
+    /// Keys in their canonical order. Other keys follow these, in the order they were written in.
+    keys: &'static [&'static str],
+    /// Keys of lists that are written with one element per line
+    multiline: &'static [&'static str],
+    /// Keys of paths, or lists of paths
+    paths: &'static [&'static str],
+    /// Arrays of tables, with the schema of their tables
+    tables: &'static [(&'static str, &'static Schema)],
+}
+
+static TRACK: Schema = Schema {
+    keys: &[
+        "schema_version",
+        "name",
+        "description",
+        "locale",
+        "version",
+        "extends",
+        "modmod_version",
+        "modmod-version",
+        "content_root",
+        "modules",
+        "remove_modules",
+        "remove_units",
+        "module_order",
+        "module_numbering",
+        "profiles",
+        "variables",
+        "unit_tags",
+        "default_slide_template",
+        "orphan_ignore",
+        "external_links_ignore",
+        "shared_crates",
+        "indexable",
+        "course",
+        "output",
+        "license",
+        "analytics",
+        "toolchain",
+        "derive_summaries",
+        "templates",
+        "add_units",
+    ],
+    multiline: &[
+        "modules",
+        "orphan_ignore",
+        "external_links_ignore",
+        "shared_crates",
+    ],
+    paths: &[
+        "extends",
+        "content_root",
+        "modules",
+        "default_slide_template",
+        "shared_crates",
+    ],
+    tables: &[
+        ("add_units", &ADDED_UNIT),
+        ("course", &COURSE),
+        ("output", &OUTPUT),
+        ("license", &LICENSE),
+        ("analytics", &ANALYTICS),
+        ("toolchain", &TOOLCHAIN),
+        ("derive_summaries", &DERIVE_SUMMARIES),
+        ("templates", &TEMPLATES),
+        ("variables", &VARIABLES),
+    ],
+};
+
+/// Template names are chosen by the author, so they keep their order
+static TEMPLATES: Schema = Schema {
+    keys: &[],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+/// Variable names are chosen by the author too
+static VARIABLES: Schema = Schema {
+    keys: &[],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static OUTPUT: Schema = Schema {
+    keys: &["slides", "book", "exercises", "solutions", "images", "dist"],
+    multiline: &[],
+    paths: &["slides", "book", "exercises", "solutions", "images", "dist"],
+    tables: &[],
+};
+
+static COURSE: Schema = Schema {
+    keys: &[
+        "code",
+        "institution",
+        "start_date",
+        "end_date",
+        "cohort",
+        "contact_email",
+    ],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static LICENSE: Schema = Schema {
+    keys: &["spdx", "attribution", "text"],
+    multiline: &[],
+    paths: &["text"],
+    tables: &[],
+};
+
+static ANALYTICS: Schema = Schema {
+    keys: &["provider", "site_id", "script_url"],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static TOOLCHAIN: Schema = Schema {
+    keys: &[
+        "rust",
+        "components",
+        "node",
+        "mdbook",
+        "extensions",
+        "devcontainer",
+    ],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static DERIVE_SUMMARIES: Schema = Schema {
+    keys: &["max_items", "exclude"],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static MODULE: Schema = Schema {
+    keys: &[
+        "schema_version",
+        "name",
+        "description",
+        "index",
+        "label",
+        "units",
+    ],
+    multiline: &[],
+    paths: &[],
+    tables: &[("units", &UNIT)],
+};
+
+static UNIT: Schema = Schema {
+    keys: &[
+        "name",
+        "index",
+        "template",
+        "theme",
+        "render_slides",
+        "render_book",
+        "no_exercises",
+        "profiles",
+        "tags",
+        "topics",
+        "sessions",
+    ],
+    multiline: &["topics"],
+    paths: &["template", "topics"],
+    tables: &[("sessions", &SESSION)],
+};
+
+static ADDED_UNIT: Schema = Schema {
+    keys: &[
+        "module",
+        "name",
+        "index",
+        "template",
+        "theme",
+        "render_slides",
+        "render_book",
+        "no_exercises",
+        "profiles",
+        "tags",
+        "topics",
+        "sessions",
+    ],
+    multiline: &["topics"],
+    paths: &["template", "topics"],
+    tables: &[("sessions", &SESSION)],
+};
+
+static SESSION: Schema = Schema {
+    keys: &["name", "duration_minutes", "topics", "exercises"],
+    multiline: &["topics", "exercises"],
+    paths: &["topics"],
+    tables: &[],
+};
+
+static TOPIC: Schema = Schema {
+    keys: &[
+        "schema_version",
+        "name",
+        "content",
+        "optional",
+        "duration_minutes",
+        "profiles",
+        "aliases",
+        "authors",
+        "license",
+        "last_reviewed",
+        "summary",
+        "objectives",
+        "further_reading",
+        "glossary",
+        "quiz",
+        "images",
+        "exercises",
+    ],
+    multiline: &["summary", "objectives", "further_reading", "images"],
+    paths: &["content", "images"],
+    tables: &[("exercises", &EXERCISE), ("quiz", &QUIZ)],
+};
+
+static QUIZ: Schema = Schema {
+    keys: &["prompt", "answers", "distractors", "explanation"],
+    multiline: &[],
+    paths: &[],
+    tables: &[],
+};
+
+static EXERCISE: Schema = Schema {
+    keys: &[
+        "name",
+        "git",
+        "rev",
+        "path",
+        "description",
+        "includes",
+        "optional",
+        "starter_fails_tests",
+        "duration_minutes",
+        "profiles",
+        "aliases",
+    ],
+    multiline: &["includes"],
+    paths: &["path", "description"],
+    tables: &[],
+};
+
+static WORKSPACE: Schema = Schema {
+    keys: &["schema_version", "tracks"],
+    multiline: &["tracks"],
+    paths: &["tracks"],
+    tables: &[],
+};
+
+const INDENT: &str = "    ";
+
+/// The kinds of definition files modmod reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Track,
+    Module,
+    Topic,
+    Workspace,
+}
+
+impl DefinitionKind {
+    /// Tells the kind of a definition file by its name: `*.track.toml`, `mod.toml`,
+    /// `topic.toml` or `*.workspace.toml`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        match name {
+            "mod.toml" => Some(Self::Module),
+            "topic.toml" => Some(Self::Topic),
+            _ if name.ends_with(".track.toml") => Some(Self::Track),
+            _ if name.ends_with(".workspace.toml") => Some(Self::Workspace),
+            _ => None,
+        }
+    }
+
+    fn schema(self) -> &'static Schema {
+        match self {
+            Self::Track => &TRACK,
+            Self::Module => &MODULE,
+            Self::Topic => &TOPIC,
+            Self::Workspace => &WORKSPACE,
+        }
+    }
+}
+
+/// The definition files in a directory and its subdirectories, in order
+pub fn definition_files(dir: &Path) -> Result<Vec<PathBuf>, FormatError> {
+    let mut files = BTreeSet::new();
+    crate::check::collect_files(dir, &mut files).change_context(FormatError::default())?;
+    Ok(files
+        .into_iter()
+        .filter(|f| DefinitionKind::from_path(f).is_some())
+        .collect())
+}
+
+/// Rewrites a definition file in canonical form. Keys are put in a fixed order, lists of
+/// paths and longer text are written with one element per line, and paths use forward slashes.
+/// Comments stay with the key or list element they precede.
+pub fn format_definition(content: &str, kind: DefinitionKind) -> Result<String, FormatError> {
+    let mut doc: Document = content
+        .parse()
+        .into_report()
+        .change_context(FormatError::default())?;
+    format_table(doc.as_table_mut(), kind.schema(), true);
+
+    let formatted = doc.to_string();
+    Ok(format!(
+        "{}\n",
+        formatted.trim_start_matches('\n').trim_end()
+    ))
+}
+
+fn format_table(table: &mut Table, schema: &Schema, root: bool) {
+    let position = |key: &str| {
+        schema
+            .keys
+            .iter()
+            .position(|k| *k == key)
+            .unwrap_or(schema.keys.len())
+    };
+    // The sort is stable, so unknown keys keep their order
+    table.sort_values_by(|a, _, b, _| position(a.get()).cmp(&position(b.get())));
+
+    if !root {
+        // Every table header is preceded by one empty line, and the comments that belonged to it
+        let comments = comments(table.decor().prefix().and_then(|p| p.as_str()));
+        table.decor_mut().set_prefix(format!("\n{comments}"));
+    }
+
+    for (mut key, item) in table.iter_mut() {
+        let name = key.get().to_string();
+        let known = schema.keys.contains(&name.as_str());
+        match item {
+            Item::Value(value) => {
+                let comments = comments(key.decor().prefix().and_then(|p| p.as_str()));
+                let decor = key.decor_mut();
+                decor.set_prefix(comments);
+                decor.set_suffix(" ");
+                if !known {
+                    continue;
+                }
+                if schema.paths.contains(&name.as_str()) {
+                    normalize_path(value);
+                }
+                if let Value::Array(array) = value {
+                    format_array(array, schema.multiline.contains(&name.as_str()));
+                }
+                normalize_value_decor(value.decor_mut());
+            }
+            Item::Table(sub) => {
+                if let Some((_, sub_schema)) = schema.tables.iter().find(|(k, _)| *k == name) {
+                    format_table(sub, sub_schema, false);
+                }
+            }
+            Item::ArrayOfTables(tables) => {
+                if let Some((_, sub_schema)) = schema.tables.iter().find(|(k, _)| *k == name) {
+                    for sub in tables.iter_mut() {
+                        format_table(sub, sub_schema, false);
+                    }
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn format_array(array: &mut Array, multiline: bool) {
+    let has_comments = array
+        .iter()
+        .any(|v| prefix_str(v.decor()).contains('#') || suffix_str(v.decor()).contains('#'))
+        || array.trailing().as_str().unwrap_or_default().contains('#');
+    let multiline = (multiline || has_comments) && !array.is_empty();
+
+    for (value, i) in array.iter_mut().zip(0..) {
+        let decor = value.decor_mut();
+        if multiline {
+            // A comment right after the previous element's comma stays on that line
+            let (same_line, above) = line_comments(prefix_str(decor));
+            decor.set_prefix(format!("{same_line}\n{above}{INDENT}"));
+        } else {
+            decor.set_prefix(if i == 0 { "" } else { " " });
+        }
+        decor.set_suffix("");
+    }
+
+    if multiline {
+        let (same_line, above) = line_comments(array.trailing().as_str().unwrap_or_default());
+        array.set_trailing(format!("{same_line}\n{above}"));
+        array.set_trailing_comma(true);
+    } else {
+        array.set_trailing("");
+        array.set_trailing_comma(false);
+    }
+}
+
+/// Splits the comments in the whitespace before an array element into the comment on the
+/// line of the previous element, and the indented comment lines above the element
+fn line_comments(prefix: &str) -> (String, String) {
+    let (first, rest) = prefix.split_once('\n').unwrap_or((prefix, ""));
+    let same_line = match first.trim() {
+        comment if comment.starts_with('#') => format!(" {comment}"),
+        _ => String::new(),
+    };
+    let above = comments(Some(rest))
+        .lines()
+        .map(|l| format!("{INDENT}{l}\n"))
+        .collect();
+    (same_line, above)
+}
+
+/// Writes paths, or lists of paths, with forward slashes
+fn normalize_path(value: &mut Value) {
+    let normalize = |value: &mut Value| {
+        let Some(path) = value.as_str().filter(|p| p.contains('\\')) else {
+            return;
+        };
+        let decor = value.decor().clone();
+        *value = Value::from(path.replace('\\', "/"));
+        *value.decor_mut() = decor;
+    };
+    match value {
+        Value::Array(array) => array.iter_mut().for_each(normalize),
+        value => normalize(value),
+    }
+}
+
+/// Values are preceded by a single space, and only keep a trailing comment
+fn normalize_value_decor(decor: &mut Decor) {
+    let suffix = suffix_str(decor).trim().to_string();
+    decor.set_prefix(" ");
+    if suffix.starts_with('#') {
+        decor.set_suffix(format!(" {suffix}"));
+    } else {
+        decor.set_suffix("");
+    }
+}
+
+/// The comment lines in the whitespace before an item, each ending in a newline
+fn comments(prefix: Option<&str>) -> String {
+    prefix
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with('#'))
+        .map(|l| format!("{l}\n"))
+        .collect()
+}
+
+fn prefix_str(decor: &Decor) -> &str {
+    decor.prefix().and_then(|p| p.as_str()).unwrap_or_default()
+}
+
+fn suffix_str(decor: &Decor) -> &str {
+    decor.suffix().and_then(|s| s.as_str()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    /// Formats `content`, and checks that formatting the result again changes nothing
+    fn format(content: &str, kind: DefinitionKind) -> String {
+        let once = format_definition(content, kind).unwrap();
+        let twice = format_definition(&once, kind).unwrap();
+        assert_eq!(twice, once, "formatting isn't idempotent for:\n{content}");
+        once
+    }
+
+    #[test]
+    fn track_keys_paths_and_comments() {
+        let content = indoc! {r#"
+            modules = ["mods\\B/mod.toml",   "mods/A/mod.toml" ] # the modules
+            # The title
+            name = "Track"
+
+            [output]
+            book = "out\\book"
+            slides = "out/slides"
+        "#};
+        let expected = indoc! {r#"
+            # The title
+            name = "Track"
+            modules = [
+                "mods/B/mod.toml",
+                "mods/A/mod.toml",
+            ] # the modules
+
+            [output]
+            slides = "out/slides"
+            book = "out/book"
+        "#};
+        assert_eq!(format(content, DefinitionKind::Track), expected);
+    }
+
+    #[test]
+    fn module_units_and_element_comments() {
+        let content = indoc! {r#"
+            # header
+            name = "Module"
+
+            [[units]]
+            topics = ["topics/b", # second
+              # first
+              "topics/a"]
+            name = "Unit"
+            [[units.sessions]]
+            topics = ["topics/a"]
+            duration_minutes = 30
+        "#};
+        let expected = indoc! {r#"
+            # header
+            name = "Module"
+
+            [[units]]
+            name = "Unit"
+            topics = [
+                "topics/b", # second
+                # first
+                "topics/a",
+            ]
+
+            [[units.sessions]]
+            duration_minutes = 30
+            topics = [
+                "topics/a",
+            ]
+        "#};
+        assert_eq!(format(content, DefinitionKind::Module), expected);
+    }
+
+    #[test]
+    fn topic_exercises() {
+        let content = indoc! {r#"
+            summary = ["One", "Two"]
+            name = "Topic"
+            content = "slides.md"
+
+            [[exercises]]
+            path = "exercises\\greet"
+            name = "Greet"
+        "#};
+        let expected = indoc! {r#"
+            name = "Topic"
+            content = "slides.md"
+            summary = [
+                "One",
+                "Two",
+            ]
+
+            [[exercises]]
+            name = "Greet"
+            path = "exercises/greet"
+        "#};
+        assert_eq!(format(content, DefinitionKind::Topic), expected);
+    }
+
+    #[test]
+    fn unknown_keys_keep_their_order_after_the_known_ones() {
+        let content = "zebra = 1\nname = \"Topic\"\napple = 2\n";
+        assert_eq!(
+            format(content, DefinitionKind::Topic),
+            "name = \"Topic\"\nzebra = 1\napple = 2\n"
+        );
+    }
+
+    #[test]
+    fn formatted_files_stay_the_same() {
+        for content in ["name = \"Topic\"\n", "# only a comment\n"] {
+            assert_eq!(format(content, DefinitionKind::Topic), content);
+        }
+    }
+
+    #[test]
+    fn content_definitions_are_idempotent() {
+        let content_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../content");
+        let files = definition_files(&content_dir).unwrap();
+        assert!(!files.is_empty());
+        for file in files {
+            let kind = DefinitionKind::from_path(&file).unwrap();
+            let content = std::fs::read_to_string(&file).unwrap();
+            format(&content, kind);
+        }
+    }
+}
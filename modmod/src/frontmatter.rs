@@ -0,0 +1,254 @@
+use std::fmt;
+
+use error_stack::{Report, Result};
+
+use crate::diagnostic::Location;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct FrontmatterError {}
+
+impl fmt::Display for FrontmatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to parse topic frontmatter")
+    }
+}
+
+impl error_stack::Context for FrontmatterError {}
+
+/// Keys that can be set in the frontmatter of a topic content file
+const KEYS: &[&str] = &["objectives", "summary", "further_reading"];
+
+/// Topic metadata defined at the top of the topic content file. Fields are `Some`
+/// if the key is present, even if its list is empty.
+#[derive(Debug, Default)]
+pub struct Frontmatter {
+    pub objectives: Option<Vec<String>>,
+    pub summary: Option<Vec<String>>,
+    pub further_reading: Option<Vec<String>>,
+}
+
+/// Splits topic metadata frontmatter off the content.
+///
+/// Slidev uses the same `---` delimited blocks to configure slides, so a block
+/// is only considered to be topic metadata if it has one of the topic metadata keys.
+/// Returns `None` if the content doesn't start with such a block.
+pub fn split(content: &str) -> Result<Option<(Frontmatter, &str)>, FrontmatterError> {
+    let mut lines = content.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Ok(None);
+    }
+
+    let mut block = vec![];
+    let mut offset = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    let mut closed = false;
+    for line in lines {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            closed = true;
+            break;
+        }
+        block.push(line.trim_end());
+    }
+    if !closed {
+        return Ok(None);
+    }
+
+    // Top-level keys start at the beginning of the line
+    let keys: Vec<_> = block
+        .iter()
+        .filter(|l| !l.starts_with([' ', '\t', '-', '#']) && !l.is_empty())
+        .filter_map(|l| l.split_once(':').map(|(k, _)| k.trim()))
+        .collect();
+    if !keys.iter().any(|k| KEYS.contains(k)) {
+        return Ok(None);
+    }
+    let other: Vec<_> = keys.iter().filter(|k| !KEYS.contains(k)).collect();
+    if !other.is_empty() {
+        return Err(
+            Report::new(FrontmatterError::default()).attach_printable(format!(
+                "Frontmatter with topic metadata can only contain the keys {}, found {}",
+                KEYS.join(", "),
+                other
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        );
+    }
+
+    let mut frontmatter = Frontmatter::default();
+    let mut current: Option<&mut Vec<String>> = None;
+    // Line numbers start at 2, after the opening `---`
+    for (line, line_no) in block.into_iter().zip(2..) {
+        let invalid = |msg: &str| {
+            Err(Report::new(FrontmatterError::default())
+                .attach_printable(format!("{msg} at line {line_no}: '{line}'"))
+                .attach(Location::line(line_no)))
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix('-') {
+            let Some(list) = current.as_deref_mut() else {
+                return invalid("List item without a key");
+            };
+            match scalar(item.trim()) {
+                Ok(item) => list.push(item),
+                Err(msg) => return invalid(msg),
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return invalid("Expected a key or a list item");
+        };
+        let field = match key.trim() {
+            "objectives" => &mut frontmatter.objectives,
+            "summary" => &mut frontmatter.summary,
+            "further_reading" => &mut frontmatter.further_reading,
+            _ => return invalid("Unexpected key"),
+        };
+        if field.is_some() {
+            return invalid("Duplicate key");
+        }
+        let list = field.insert(vec![]);
+
+        let value = value.trim();
+        if value.is_empty() {
+            // A block list follows
+            current = Some(list);
+        } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            match flow_items(items) {
+                Ok(items) => list.extend(items.into_iter().filter(|i| !i.is_empty())),
+                Err(msg) => return invalid(msg),
+            }
+            current = None;
+        } else {
+            return invalid("Expected a list");
+        }
+    }
+
+    Ok(Some((frontmatter, &content[offset..])))
+}
+
+/// Strips topic metadata frontmatter from the content, if there is any
+pub fn strip(content: &str) -> Result<&str, FrontmatterError> {
+    Ok(split(content)?.map(|(_, rest)| rest).unwrap_or(content))
+}
+
+/// Splits the items of a flow list like `["Rust, the book", x]` at the commas that aren't
+/// quoted, and unquotes them
+fn flow_items(items: &str) -> std::result::Result<Vec<String>, &'static str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in items.char_indices() {
+        match (quote, c) {
+            (Some('"'), _) if escaped => escaped = false,
+            (Some('"'), '\\') => escaped = true,
+            // A doubled single quote is an escaped one, and ends and reopens the quote
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => {
+                parts.push(&items[start..i]);
+                start = i + 1;
+            }
+            (None, _) => {}
+        }
+    }
+    if quote.is_some() {
+        return Err("Unterminated quoted string");
+    }
+    parts.push(&items[start..]);
+    parts.into_iter().map(|part| scalar(part.trim())).collect()
+}
+
+/// Unquotes a YAML scalar. Double quoted strings can escape `"` and `\` with a backslash,
+/// single quoted ones a `'` by doubling it. Other values are taken as they are.
+fn scalar(s: &str) -> std::result::Result<String, &'static str> {
+    if let Some(inner) = s.strip_prefix('"') {
+        let mut value = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if chars.as_str().is_empty() => return Ok(value),
+                '"' => return Err("Unexpected text after a quoted string"),
+                '\\' => match chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    _ => return Err("Unsupported escape in a quoted string"),
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("Unterminated quoted string");
+    }
+    if let Some(inner) = s.strip_prefix('\'') {
+        let Some(inner) = inner.strip_suffix('\'') else {
+            return Err("Unterminated quoted string");
+        };
+        if inner.replace("''", "").contains('\'') {
+            return Err("Unexpected text after a quoted string");
+        }
+        return Ok(inner.replace("''", "'"));
+    }
+    Ok(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn further_reading(value: &str) -> Vec<String> {
+        let content = format!("---\nfurther_reading: {value}\n---\n# Slides\n");
+        split(&content).unwrap().unwrap().0.further_reading.unwrap()
+    }
+
+    #[test]
+    fn flow_lists_keep_quoted_commas() {
+        assert_eq!(
+            further_reading(r#"["Rust, the book", x]"#),
+            ["Rust, the book", "x"]
+        );
+        assert_eq!(
+            further_reading("['Rust, the book', 'it''s, quoted']"),
+            ["Rust, the book", "it's, quoted"]
+        );
+        assert_eq!(further_reading("[a, b,]"), ["a", "b"]);
+    }
+
+    #[test]
+    fn quoted_strings_unescape() {
+        assert_eq!(
+            further_reading(r#"["say \"hi\", then go", "C:\\rust"]"#),
+            [r#"say "hi", then go"#, r"C:\rust"]
+        );
+        let content = "---\nobjectives:\n  - \"a \\\"quoted\\\" one\"\n  - 'it''s'\n---\n";
+        let (frontmatter, _) = split(content).unwrap().unwrap();
+        assert_eq!(
+            frontmatter.objectives.unwrap(),
+            [r#"a "quoted" one"#, "it's"]
+        );
+    }
+
+    #[test]
+    fn broken_quotes_are_errors() {
+        for value in [
+            r#"["Rust, the book]"#,
+            r#"["ends with \"]"#,
+            r#"["a" b]"#,
+            r#"["\x"]"#,
+        ] {
+            let content = format!("---\nfurther_reading: {value}\n---\n");
+            assert!(split(&content).is_err(), "{value}");
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use git2::{
+    build::CheckoutBuilder, Commit, Config, Cred, CredentialType, Delta, DiffFindOptions,
+    DiffOptions, ErrorCode, FetchOptions, Oid, Remote, RemoteCallbacks, Repository,
+};
+
+use crate::GitSource;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct FetchError {}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to fetch git repository")
+    }
+}
+
+impl error_stack::Context for FetchError {}
+
+/// The directory repositories are cached in: `$MODMOD_CACHE_DIR`, or the `modmod`
+/// folder in the user's cache directory
+pub(crate) fn default_cache_dir() -> PathBuf {
+    let env_dir = |var: &str| {
+        std::env::var_os(var)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    if let Some(dir) = env_dir("MODMOD_CACHE_DIR") {
+        return dir;
+    }
+    env_dir("XDG_CACHE_HOME")
+        .or_else(|| env_dir("HOME").map(|home| home.join(".cache")))
+        .or_else(|| env_dir("LOCALAPPDATA"))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("modmod")
+}
+
+/// Checks out a revision of a repository into the cache, unless it is there already.
+/// Returns the directory of the checkout and the commit it is at.
+pub(crate) fn checkout(
+    url: &str,
+    rev: &str,
+    cache_dir: &Path,
+    offline: bool,
+) -> Result<(PathBuf, GitSource), FetchError> {
+    let dir = cache_dir
+        .join("git")
+        .join(file_name(url))
+        .join(file_name(rev));
+    let in_repo = || format!("Fetching revision {rev} of {url}");
+
+    if !dir.exists() {
+        if offline {
+            return Err(Report::new(FetchError::default()).attach_printable(format!(
+                "Revision {rev} of {url} is not in the cache at {}, and fetching it is not allowed offline",
+                dir.display()
+            )));
+        }
+        if crate::io::writes_discarded() {
+            return Err(Report::new(FetchError::default()).attach_printable(format!(
+                "Revision {rev} of {url} is not in the cache at {}, and fetching it would write to the cache",
+                dir.display()
+            )));
+        }
+        // Fetch next to the final location, so that an interrupted fetch doesn't look cached
+        let partial = dir.with_extension(format!("partial-{}", std::process::id()));
+        if partial.exists() {
+            std::fs::remove_dir_all(&partial)
+                .into_report()
+                .change_context(FetchError::default())?;
+        }
+        std::fs::create_dir_all(&partial)
+            .into_report()
+            .change_context(FetchError::default())?;
+        fetch_into(&partial, url, rev).attach_printable_lazy(in_repo)?;
+        std::fs::rename(&partial, &dir)
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to move checkout to {}", dir.display()))
+            .change_context(FetchError::default())?;
+    }
+
+    let repo = open::<FetchError>(&dir).attach_printable_lazy(in_repo)?;
+    let commit = head::<FetchError>(&repo).attach_printable_lazy(in_repo)?;
+    Ok((
+        dir,
+        GitSource {
+            url: url.to_string(),
+            commit: commit.id().to_string(),
+        },
+    ))
+}
+
+/// Fetches only the revision `rev` of `url` into a new repository in `dir`, and checks it out
+fn fetch_into(dir: &Path, url: &str, rev: &str) -> Result<(), FetchError> {
+    let repo = Repository::init(dir).map_err(failed::<FetchError>("creating a repository"))?;
+    let mut remote = repo
+        .remote_anonymous(url)
+        .map_err(failed::<FetchError>("fetching"))?;
+    // The local transport can't fetch a shallow history
+    let shallow = url.contains("://") && !url.starts_with("file://");
+    let fetched = match fetch(&repo, &mut remote, rev, shallow)? {
+        Some(id) => id,
+        // Fetching a commit by its id doesn't always record it in FETCH_HEAD
+        None => Oid::from_str(rev).map_err(failed::<FetchError>("finding the fetched revision"))?,
+    };
+    let commit = repo
+        .find_object(fetched, None)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(failed::<FetchError>("finding the fetched commit"))?;
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))
+        .and_then(|()| repo.set_head_detached(commit.id()))
+        .map_err(failed::<FetchError>("checking out the fetched commit"))
+}
+
+/// Fetches `refspec` from `remote`, only its last commit if `shallow`, and returns what it
+/// fetched, if anything
+pub(crate) fn fetch<C: Context + Default>(
+    repo: &Repository,
+    remote: &mut Remote,
+    refspec: &str,
+    shallow: bool,
+) -> Result<Option<Oid>, C> {
+    // What an earlier fetch left isn't what this one fetched
+    match std::fs::remove_file(repo.path().join("FETCH_HEAD")) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(Report::new(e).change_context(C::default()));
+        }
+        _ => {}
+    }
+    let mut options = fetch_options();
+    if shallow {
+        options.depth(1);
+    }
+    remote
+        .fetch(&[refspec], Some(&mut options), None)
+        .map_err(failed::<C>("fetching"))?;
+    let mut fetched = None;
+    repo.fetchhead_foreach(|_, _, id, _| {
+        fetched = Some(*id);
+        false
+    })
+    .or_else(|e| match e.code() {
+        ErrorCode::User | ErrorCode::NotFound => Ok(()),
+        _ => Err(e),
+    })
+    .map_err(failed::<C>("reading FETCH_HEAD"))?;
+    Ok(fetched)
+}
+
+/// Opens the repository that `dir` is in
+pub(crate) fn open<C: Context + Default>(dir: &Path) -> Result<Repository, C> {
+    Repository::discover(dir)
+        .into_report()
+        .attach_printable_lazy(|| format!("{} isn't in a git repository", dir.display()))
+        .change_context(C::default())
+}
+
+/// The commit the repository is at
+pub(crate) fn head<'r, C: Context + Default>(repo: &'r Repository) -> Result<Commit<'r>, C> {
+    repo.head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(failed("finding the commit of HEAD"))
+}
+
+/// The root of the working tree of the repository that `dir` is in
+pub(crate) fn toplevel<C: Context + Default>(dir: &Path) -> Result<PathBuf, C> {
+    let repo = open::<C>(dir)?;
+    repo.workdir().map(Path::to_path_buf).ok_or_else(|| {
+        Report::new(C::default()).attach_printable(format!(
+            "The repository at {} has no working tree",
+            repo.path().display()
+        ))
+    })
+}
+
+/// The files that differ between the git revision `rev` and the working tree, under
+/// `pathspec`, relative to the top level of the repository. Renamed files are there with
+/// their old and their new path, and deleted files and files git doesn't track are there
+/// as well. `rev` must be a commit.
+pub(crate) fn changed_files<C: Context + Default>(
+    dir: &Path,
+    rev: &str,
+    pathspec: &Path,
+) -> Result<BTreeSet<PathBuf>, C> {
+    // Keeps the revision from being taken as an option
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(
+            Report::new(C::default()).attach_printable(format!("Invalid git revision '{rev}'"))
+        );
+    }
+    let repo = open::<C>(dir)?;
+    let tree = repo
+        .revparse_single(&format!("{rev}^{{commit}}"))
+        .and_then(|commit| commit.peel_to_tree())
+        .into_report()
+        .attach_printable_lazy(|| format!("Unknown git revision '{rev}'"))
+        .change_context(C::default())?;
+    let toplevel = toplevel::<C>(dir)?;
+    let mut options = DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    // Pathspecs are relative to the top level, which may be reached through symlinks
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if let Ok(within) = canonical(pathspec).strip_prefix(canonical(&toplevel)) {
+        if !within.as_os_str().is_empty() {
+            options.pathspec(within);
+        }
+    }
+    let mut diff = repo
+        .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))
+        .map_err(failed::<C>("comparing the working tree"))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+        .map_err(failed::<C>("finding renamed files"))?;
+
+    // Renamed and copied files changed both at their old and their new path
+    let mut files = BTreeSet::new();
+    for delta in diff.deltas() {
+        let paths = match delta.status() {
+            Delta::Renamed | Delta::Copied => vec![delta.old_file(), delta.new_file()],
+            _ => vec![delta.new_file()],
+        };
+        files.extend(
+            paths
+                .into_iter()
+                .filter_map(|f| f.path())
+                .map(Path::to_path_buf),
+        );
+    }
+    Ok(files)
+}
+
+/// The options to fetch with, see [`callbacks`]
+fn fetch_options() -> FetchOptions<'static> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks());
+    options
+}
+
+/// Callbacks that give remotes the credentials git would: from the SSH agent, or from the
+/// credential helpers that are configured
+pub(crate) fn callbacks() -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            let config = Config::open_default()?;
+            return Cred::credential_helper(&config, url, username);
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+/// Turns an error of libgit2 into a report, saying what it was doing
+pub(crate) fn failed<C: Context + Default>(
+    doing: &str,
+) -> impl FnOnce(git2::Error) -> Report<C> + '_ {
+    move |e| {
+        Report::new(e)
+            .attach_printable(format!("Error {doing}"))
+            .change_context(C::default())
+    }
+}
+
+/// A name for a URL or revision that can be used as a file name
+fn file_name(s: &str) -> String {
+    let s = s.split_once("://").map_or(s, |(_, rest)| rest);
+    s.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
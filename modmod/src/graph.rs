@@ -0,0 +1,367 @@
+//! The structure of a track as a graph, for curriculum reviews: its modules, units, topics
+//! and exercises, which contain each other, and the shared crates exercises depend on.
+//! The graph can be written as Graphviz DOT, as a Mermaid flowchart, or as JSON.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fmt::Write,
+};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    conditional::Conditions,
+    estimates::TopicEstimate,
+    exercises::shared_crate_dependencies,
+    slides::{count_slides, topic_slides},
+    stats::unit_counts,
+    suggest, ContentFilter, Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct GraphError {}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to collect the graph of the track")
+    }
+}
+
+impl error_stack::Context for GraphError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeKind {
+    Module,
+    Unit,
+    Topic,
+    Exercise,
+    SharedCrate,
+}
+
+impl NodeKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Module => "module",
+            Self::Unit => "unit",
+            Self::Topic => "topic",
+            Self::Exercise => "exercise",
+            Self::SharedCrate => "shared-crate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    /// Identifies the node in the graph, like `topic:ownership`
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+    /// How long teaching the module, unit or topic takes: the durations of the sessions of
+    /// units, or an estimate based on the number of slides
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_minutes: Option<u32>,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// A module contains a unit, a unit a topic, and a topic an exercise
+    Contains,
+    /// An exercise depends on a shared crate
+    DependsOn,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// The graph of a track. Topics that are included in more than one unit are a single node
+/// with an edge from each of these units.
+#[derive(Debug, Serialize)]
+pub struct TrackGraph {
+    pub track: String,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl TrackGraph {
+    /// Collects the graph of the track, or of only the module with id `module`, which is
+    /// the name of the folder of its definition. Conditional blocks in the slides are
+    /// resolved as when the track is rendered without a profile.
+    pub fn collect(track: &Track, module: Option<&str>) -> Result<Self, GraphError> {
+        if let Some(id) = module {
+            if !track.modules.iter().any(|m| m.data.id() == id) {
+                let module_ids: Vec<_> = track.modules.iter().map(|m| m.data.id()).collect();
+                let ids: Vec<_> = module_ids.iter().map(|id| format!("'{id}'")).collect();
+                return Err(Report::new(GraphError::default()).attach_printable(format!(
+                    "The track has no module '{id}'. It has modules {}.{}",
+                    ids.join(", "),
+                    suggest::did_you_mean(id, &module_ids)
+                )));
+            }
+        }
+
+        let conditions = Conditions {
+            profile: None,
+            known_profiles: &track.profiles,
+            variables: &track.variables,
+        };
+        let mut graph = Self {
+            track: track.name.clone(),
+            nodes: vec![],
+            edges: vec![],
+        };
+        // Whether the node was added, rather than already in the graph
+        let mut added = HashSet::new();
+        let mut add = |graph: &mut Self, node: Node| {
+            if !added.insert(node.id.clone()) {
+                return false;
+            }
+            graph.nodes.push(node);
+            true
+        };
+        let edge = |graph: &mut Self, from: &str, to: &str, kind: EdgeKind| {
+            graph.edges.push(Edge {
+                from: from.to_string(),
+                to: to.to_string(),
+                kind,
+            });
+        };
+
+        let modules = track
+            .modules
+            .iter()
+            .filter(|m| module.is_none_or(|id| m.data.id() == id));
+        for module in modules {
+            let label = &module.data.label.display;
+            let id = module.data.id();
+            let module_node = format!("module:{id}");
+            let mut module_minutes = 0;
+            let module_position = graph.nodes.len();
+            add(
+                &mut graph,
+                Node {
+                    id: module_node.clone(),
+                    kind: NodeKind::Module,
+                    label: format!("{label} {}", module.data.name).trim().to_string(),
+                    estimated_minutes: None,
+                    optional: false,
+                },
+            );
+            for unit in module.data.units.iter() {
+                let number = match label.is_empty() {
+                    true => unit.index.to_string(),
+                    false => format!("{label}.{}", unit.index),
+                };
+                let unit_node = format!("unit:{id}/{}", unit.index);
+                let minutes = unit_counts(&unit.data, &conditions)
+                    .change_context(GraphError::default())?
+                    .estimated_minutes;
+                module_minutes += minutes;
+                add(
+                    &mut graph,
+                    Node {
+                        id: unit_node.clone(),
+                        kind: NodeKind::Unit,
+                        label: format!("{number} {}", unit.data.name),
+                        estimated_minutes: Some(minutes),
+                        optional: false,
+                    },
+                );
+                edge(&mut graph, &module_node, &unit_node, EdgeKind::Contains);
+
+                for topic in unit.data.topics.iter().map(|t| &t.data) {
+                    let topic_node = format!("topic:{}", topic.id);
+                    edge(&mut graph, &unit_node, &topic_node, EdgeKind::Contains);
+                    let slides = topic_slides(&topic.content, &topic.name, &conditions)
+                        .change_context(GraphError::default())?;
+                    let minutes = TopicEstimate::of(topic, ContentFilter::default())
+                        .lecture_minutes(count_slides(&slides));
+                    let new = add(
+                        &mut graph,
+                        Node {
+                            id: topic_node.clone(),
+                            kind: NodeKind::Topic,
+                            label: topic.name.clone(),
+                            estimated_minutes: Some(minutes),
+                            optional: topic.optional,
+                        },
+                    );
+                    if !new {
+                        continue;
+                    }
+                    for exercise in topic.exercises.iter().map(|e| &e.data) {
+                        let exercise_node = format!("exercise:{}", exercise.id);
+                        add(
+                            &mut graph,
+                            Node {
+                                id: exercise_node.clone(),
+                                kind: NodeKind::Exercise,
+                                label: exercise.name.clone(),
+                                estimated_minutes: None,
+                                optional: exercise.optional,
+                            },
+                        );
+                        edge(&mut graph, &topic_node, &exercise_node, EdgeKind::Contains);
+                        for shared in
+                            shared_crate_dependencies(&exercise.path, &track.shared_crates)
+                        {
+                            let crate_node = format!("crate:{}", shared.name);
+                            add(
+                                &mut graph,
+                                Node {
+                                    id: crate_node.clone(),
+                                    kind: NodeKind::SharedCrate,
+                                    label: shared.name.clone(),
+                                    estimated_minutes: None,
+                                    optional: false,
+                                },
+                            );
+                            edge(&mut graph, &exercise_node, &crate_node, EdgeKind::DependsOn);
+                        }
+                    }
+                }
+            }
+            graph.nodes[module_position].estimated_minutes = Some(module_minutes);
+        }
+        Ok(graph)
+    }
+
+    /// The graph in the DOT language of Graphviz, with a shape for each kind of node
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {} {{\n", dot_string(&self.track));
+        dot.push_str("  rankdir=LR;\n  node [fontname=\"sans-serif\"];\n");
+        for node in &self.nodes {
+            let (shape, color) = match node.kind {
+                NodeKind::Module => ("box3d", "#f4a261"),
+                NodeKind::Unit => ("box", "#e9c46a"),
+                NodeKind::Topic => ("ellipse", "#a8dadc"),
+                NodeKind::Exercise => ("note", "#90be6d"),
+                NodeKind::SharedCrate => ("component", "#cdb4db"),
+            };
+            let _ = write!(
+                dot,
+                "  {} [label={}, shape={shape}, style=\"filled{}\", fillcolor=\"{color}\", kind=\"{}\"",
+                dot_string(&node.id),
+                dot_string(&node_label(node, "\n")),
+                if node.optional { ",dashed" } else { "" },
+                node.kind.name()
+            );
+            if let Some(minutes) = node.estimated_minutes {
+                let _ = write!(dot, ", estimated_minutes={minutes}");
+            }
+            dot.push_str("];\n");
+        }
+        for edge in &self.edges {
+            let _ = write!(
+                dot,
+                "  {} -> {}",
+                dot_string(&edge.from),
+                dot_string(&edge.to)
+            );
+            if edge.kind == EdgeKind::DependsOn {
+                dot.push_str(" [style=dashed, label=\"depends on\"]");
+            }
+            dot.push_str(";\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as a Mermaid flowchart, with a class for each kind of node
+    pub fn to_mermaid(&self) -> String {
+        let ids: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect();
+        let mut mermaid = String::from("flowchart LR\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = node_label(node, "<br/>").replace('"', "#quot;");
+            let (open, close) = match node.kind {
+                NodeKind::Module => ("[[", "]]"),
+                NodeKind::Unit => ("[", "]"),
+                NodeKind::Topic => ("(", ")"),
+                NodeKind::Exercise => ("[/", "/]"),
+                NodeKind::SharedCrate => ("[(", ")]"),
+            };
+            let _ = writeln!(
+                mermaid,
+                "  n{i}{open}\"{label}\"{close}:::{}",
+                mermaid_class(node.kind)
+            );
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Contains => "-->",
+                EdgeKind::DependsOn => "-.->|depends on|",
+            };
+            let _ = writeln!(
+                mermaid,
+                "  n{} {arrow} n{}",
+                ids[edge.from.as_str()],
+                ids[edge.to.as_str()]
+            );
+        }
+        for (kind, color) in [
+            (NodeKind::Module, "#f4a261"),
+            (NodeKind::Unit, "#e9c46a"),
+            (NodeKind::Topic, "#a8dadc"),
+            (NodeKind::Exercise, "#90be6d"),
+            (NodeKind::SharedCrate, "#cdb4db"),
+        ] {
+            let _ = writeln!(
+                mermaid,
+                "  classDef {} fill:{color},color:#000",
+                mermaid_class(kind)
+            );
+        }
+        mermaid
+    }
+
+    /// A book chapter with the graph as Mermaid diagram, for an appendix of the book.
+    /// mdbook shows it as a diagram with the `mdbook-mermaid` preprocessor.
+    pub fn to_mermaid_chapter(&self) -> String {
+        format!(
+            "# Course structure\n\nThe modules, units, topics and exercises of {}, with the estimated time they take.\n\n```mermaid\n{}```\n",
+            self.track,
+            self.to_mermaid()
+        )
+    }
+}
+
+/// The label of the node, followed by its estimated duration on a line of its own
+fn node_label(node: &Node, line_break: &str) -> String {
+    let mut label = node.label.clone();
+    if node.optional {
+        label.push_str(" (optional)");
+    }
+    if let Some(minutes) = node.estimated_minutes {
+        let _ = write!(label, "{line_break}{minutes} min");
+    }
+    label
+}
+
+/// Mermaid class names can't have dashes
+fn mermaid_class(kind: NodeKind) -> String {
+    kind.name().replace('-', "_")
+}
+
+/// A quoted DOT string
+fn dot_string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
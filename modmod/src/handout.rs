@@ -0,0 +1,488 @@
+//! Printable handouts of a track, one PDF per module, to archive exactly what was taught.
+//!
+//! A handout starts with a cover page with the module, the course version, the date and the
+//! attributions of its material. Then, for each unit, come the pages of its slides, as
+//! exported by Slidev, and the descriptions of its exercises, as they are in its book
+//! chapter. The PDFs of the slides can be exported by running the `export-<prefix>` scripts
+//! of the slides package, or be given in a folder as `<deck>.pdf`, like `2_1-basic-syntax.pdf`.
+//! They are put together by [`crate::pdf`], which copies their pages as they are.
+//!
+//! Handouts are named after their module, like `2-foundations-of-rust.pdf`, with the course
+//! version added if there is one, and hold nothing that changes between runs but the date.
+
+use std::{fmt, fs, path::Path};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+
+use crate::{
+    date::Date,
+    io::PathExt,
+    pdf::{Document, Font, PdfBuilder, TextLayout},
+    publish::run,
+    to_prefixed_tag, to_tag,
+    version::CourseVersion,
+    Indexed, Module, OutputLayout, Track, Unit,
+};
+
+/// The folder handouts are written into by default
+pub const HANDOUTS_DIR: &str = "dist/handouts";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HandoutError {}
+
+impl fmt::Display for HandoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to make the handouts of the track")
+    }
+}
+
+impl error_stack::Context for HandoutError {}
+
+/// Where the PDFs of the slides come from
+#[derive(Debug, Clone, Copy)]
+pub enum SlidePdfs<'a> {
+    /// Exported before into the `dist` folder of the slides, as `<deck>.pdf`
+    Exported,
+    /// Export them into the `dist` folder of the slides first
+    Export,
+    /// Exported before into this folder, as `<deck>.pdf`
+    Dir(&'a Path),
+}
+
+pub struct HandoutOptions<'a> {
+    /// The layout the track was rendered with
+    pub layout: &'a OutputLayout,
+    pub slides: SlidePdfs<'a>,
+    /// The date on the cover pages
+    pub date: Date,
+    /// The version of the course the track was rendered as, if it has one
+    pub course_version: Option<&'a CourseVersion>,
+}
+
+/// The handout of a module
+#[derive(Debug)]
+pub struct Handout {
+    pub module: String,
+    pub file_name: String,
+    pub pdf: Vec<u8>,
+    pub pages: usize,
+    pub decks: usize,
+    pub exercises: usize,
+}
+
+/// What goes into the handout of a unit: the PDF of its slides and its exercises
+struct UnitContent<'a> {
+    unit: &'a Indexed<Unit>,
+    slides: Option<(String, std::path::PathBuf)>,
+    exercises: Vec<String>,
+}
+
+impl Track {
+    /// Makes a handout for each module of the track that has slides or exercises, as it was
+    /// rendered into `out_dir` with the layout of `options`. All slide PDFs that are missing
+    /// are listed before failing.
+    pub fn handouts(
+        &self,
+        out_dir: &Path,
+        options: HandoutOptions,
+    ) -> Result<Vec<Handout>, HandoutError> {
+        let HandoutOptions {
+            layout,
+            slides,
+            date,
+            course_version,
+        } = options;
+        let slides_dir = out_dir.join(layout.slides());
+        let book_dir = out_dir.join(layout.book());
+        let pdf_dir = match slides {
+            SlidePdfs::Dir(dir) => dir.to_path_buf(),
+            SlidePdfs::Exported | SlidePdfs::Export => slides_dir.join(layout.dist()),
+        };
+
+        let mut modules = vec![];
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut units = vec![];
+            for unit in module.data.units.iter() {
+                // Decks without content are not written, and units can leave out either
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let slug = to_prefixed_tag(&unit.data.name, &prefix);
+                let slides = (unit.data.render_slides
+                    && slides_dir.join(&slug).with_extension("md").exists())
+                .then(|| (prefix, pdf_dir.join(&slug).with_extension("pdf")));
+                let chapter = book_dir
+                    .join("src")
+                    .join(to_tag(&unit.data.name))
+                    .with_extension("md");
+                let exercises = match unit.data.render_book && chapter.exists() {
+                    true => exercise_sections(&chapter.read_to_string::<HandoutError>()?),
+                    false => vec![],
+                };
+                if slides.is_some() || !exercises.is_empty() {
+                    units.push(UnitContent {
+                        unit,
+                        slides,
+                        exercises,
+                    });
+                }
+            }
+            if !units.is_empty() {
+                modules.push((&module.data, units));
+            }
+        }
+
+        let decks: Vec<_> = modules
+            .iter()
+            .flat_map(|(_, units)| units.iter().filter_map(|u| u.slides.as_ref()))
+            .collect();
+        if matches!(slides, SlidePdfs::Export) && !decks.is_empty() {
+            let dry_run = crate::io::writes_discarded();
+            run::<HandoutError>(&slides_dir, "npm", &["install"], dry_run)?;
+            for (prefix, pdf) in decks.iter() {
+                let script = format!("export-{prefix}");
+                let output = pdf.to_string_lossy();
+                run::<HandoutError>(
+                    &slides_dir,
+                    "npm",
+                    &["run", &script, "--", "--output", &output],
+                    dry_run,
+                )?;
+            }
+        }
+        let missing: Vec<_> = decks
+            .iter()
+            .filter(|(_, pdf)| !pdf.exists())
+            .map(|(_, pdf)| pdf)
+            .collect();
+        if !missing.is_empty() {
+            let mut report = Report::new(HandoutError::default()).attach_printable(format!(
+                "The PDFs of {} deck(s) are missing. Use --export to export them, or --slides-pdfs to give the folder they were exported into",
+                missing.len()
+            ));
+            for pdf in missing {
+                report = report.attach_printable(format!("Missing {}", pdf.display()));
+            }
+            return Err(report);
+        }
+
+        let mut handouts = vec![];
+        for (module, units) in modules {
+            handouts.push(self.handout(module, &units, date, course_version)?);
+        }
+        Ok(handouts)
+    }
+
+    fn handout(
+        &self,
+        module: &Module,
+        units: &[UnitContent],
+        date: Date,
+        course_version: Option<&CourseVersion>,
+    ) -> Result<Handout, HandoutError> {
+        let label = &module.label;
+        let title = match label.display.is_empty() {
+            true => module.name.clone(),
+            false => format!("Module {} - {}", label.display, module.name),
+        };
+        let mut pdf = PdfBuilder::new();
+        pdf.add_text_pages(self.cover_page(module, &title, units, date, course_version));
+        let mut handout = Handout {
+            module: module.id(),
+            file_name: match course_version {
+                Some(version) => format!(
+                    "{}-{}.pdf",
+                    to_prefixed_tag(&module.name, &label.path),
+                    version.file_name_part()
+                ),
+                None => format!("{}.pdf", to_prefixed_tag(&module.name, &label.path)),
+            },
+            pdf: vec![],
+            pages: 0,
+            decks: 0,
+            exercises: 0,
+        };
+        for content in units {
+            if let Some((_, path)) = &content.slides {
+                let in_slides = || format!("In the slides PDF at {}", path.display());
+                let bytes = fs::read(path)
+                    .into_report()
+                    .change_context(HandoutError::default())
+                    .attach_printable_lazy(in_slides)?;
+                let document = Document::parse(&bytes)
+                    .change_context(HandoutError::default())
+                    .attach_printable_lazy(in_slides)?;
+                let pages = pdf
+                    .append(&document)
+                    .change_context(HandoutError::default())
+                    .attach_printable_lazy(in_slides)?;
+                if pages == 0 {
+                    return Err(Report::new(HandoutError::default())
+                        .attach_printable("The PDF has no pages")
+                        .attach_printable(in_slides()));
+                }
+                handout.decks += 1;
+            }
+            if !content.exercises.is_empty() {
+                let mut layout = TextLayout::new();
+                layout.paragraph(
+                    &format!(
+                        "Exercises of unit {} - {}",
+                        label.qualify(content.unit.index),
+                        content.unit.data.name
+                    ),
+                    Font::Bold,
+                    16.0,
+                    0.0,
+                );
+                for exercise in content.exercises.iter() {
+                    layout.space(8.0);
+                    typeset_markdown(&mut layout, exercise);
+                }
+                pdf.add_text_pages(layout.finish());
+                handout.exercises += content.exercises.len();
+            }
+        }
+        handout.pages = pdf.page_count();
+        handout.pdf = pdf
+            .finish(&format!("{} - {title}", self.name))
+            .change_context(HandoutError::default())?;
+        Ok(handout)
+    }
+
+    fn cover_page(
+        &self,
+        module: &Module,
+        title: &str,
+        units: &[UnitContent],
+        date: Date,
+        course_version: Option<&CourseVersion>,
+    ) -> Vec<Vec<u8>> {
+        let mut layout = TextLayout::new();
+        layout.space(120.0);
+        layout.paragraph(&self.name, Font::Bold, 24.0, 0.0);
+        layout.space(12.0);
+        layout.paragraph(title, Font::Bold, 18.0, 0.0);
+        layout.space(6.0);
+        layout.paragraph(&inline_text(&module.description), Font::Regular, 12.0, 0.0);
+        layout.space(24.0);
+        let course: Vec<_> = [&self.course.code, &self.course.institution]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        if !course.is_empty() {
+            layout.paragraph(&course.join(", "), Font::Regular, 11.0, 0.0);
+        }
+        if let Some(version) = course_version {
+            layout.paragraph(
+                &format!("Course version {version}"),
+                Font::Regular,
+                11.0,
+                0.0,
+            );
+        }
+        layout.paragraph(&format!("Date: {date}"), Font::Regular, 11.0, 0.0);
+        layout.space(18.0);
+        layout.paragraph("Units", Font::Bold, 13.0, 0.0);
+        for content in units {
+            let unit = content.unit;
+            let mut line = format!("{} {}", module.label.qualify(unit.index), unit.data.name);
+            if content.slides.is_none() {
+                line.push_str(" (no slides)");
+            }
+            layout.item("•", &line, Font::Regular, 11.0, 0.0);
+        }
+
+        let mut attributions = vec![];
+        for content in units {
+            let topics = content.unit.data.topics.iter();
+            for attribution in topics.filter_map(|t| t.data.attribution()) {
+                if !attributions.contains(&attribution) {
+                    attributions.push(attribution);
+                }
+            }
+        }
+        if self.license.is_some() || !attributions.is_empty() {
+            layout.space(18.0);
+            layout.paragraph("Attribution", Font::Bold, 13.0, 0.0);
+        }
+        if let Some(license) = &self.license {
+            layout.paragraph(
+                &format!("{} ({})", license.attribution, license.spdx),
+                Font::Regular,
+                11.0,
+                0.0,
+            );
+        }
+        if !attributions.is_empty() {
+            layout.space(4.0);
+            layout.paragraph("Material in this module:", Font::Italic, 11.0, 0.0);
+            for attribution in attributions {
+                layout.item("•", &inline_text(&attribution), Font::Regular, 10.0, 0.0);
+            }
+        }
+        layout.finish()
+    }
+}
+
+/// The sections of the exercises in a rendered book chapter, in order
+pub(crate) fn exercise_sections(chapter: &str) -> Vec<String> {
+    // What the book adds after the exercises
+    let end = ["\n---\n\n*Material in this unit:*", "\n\n---\n\n<small>"]
+        .into_iter()
+        .filter_map(|marker| chapter.find(marker))
+        .min()
+        .unwrap_or(chapter.len());
+    let mut sections: Vec<String> = vec![];
+    let mut in_exercise = false;
+    for line in chapter[..end].lines() {
+        // Like the quiz, which follows the exercises
+        if line.starts_with("## ") {
+            in_exercise = line.starts_with("## Exercise ");
+            if in_exercise {
+                sections.push(String::new());
+            }
+        }
+        if let (true, Some(section)) = (in_exercise, sections.last_mut()) {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    sections
+}
+
+/// Lays out the blocks of `markdown`: headings, paragraphs, lists, quotes and code
+fn typeset_markdown(layout: &mut TextLayout, markdown: &str) {
+    let mut paragraph: Vec<&str> = vec![];
+    let flush = |layout: &mut TextLayout, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            layout.paragraph(&inline_text(&paragraph.join(" ")), Font::Regular, 10.5, 0.0);
+            layout.space(4.0);
+            paragraph.clear();
+        }
+    };
+    let mut code: Option<String> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match code.take() {
+                Some(block) => {
+                    layout.preformatted(&block, 8.5, 12.0);
+                    layout.space(6.0);
+                }
+                None => {
+                    flush(layout, &mut paragraph);
+                    code = Some(String::new());
+                }
+            }
+            continue;
+        }
+        if let Some(block) = code.as_mut() {
+            block.push_str(line);
+            block.push('\n');
+            continue;
+        }
+        let heading = trimmed.len() - trimmed.trim_start_matches('#').len();
+        let indent = (line.len() - line.trim_start().len()) as f32 * 4.0;
+        if (1..=6).contains(&heading) && trimmed[heading..].starts_with(' ') {
+            flush(layout, &mut paragraph);
+            let size = [16.0, 14.0, 12.0, 11.0, 11.0, 11.0][heading - 1];
+            layout.space(4.0);
+            layout.paragraph(&inline_text(&trimmed[heading..]), Font::Bold, size, 0.0);
+            layout.space(2.0);
+        } else if trimmed.is_empty() {
+            flush(layout, &mut paragraph);
+        } else if ["---", "***", "___"].contains(&trimmed) {
+            flush(layout, &mut paragraph);
+            layout.space(8.0);
+        } else if let Some(item) = list_item(trimmed) {
+            flush(layout, &mut paragraph);
+            let (marker, text) = item;
+            layout.item(marker, &inline_text(text), Font::Regular, 10.5, indent);
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(layout, &mut paragraph);
+            layout.paragraph(&inline_text(quote), Font::Italic, 10.5, 14.0);
+        } else if trimmed.starts_with('|') {
+            flush(layout, &mut paragraph);
+            layout.preformatted(trimmed, 8.5, 0.0);
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    if let Some(block) = code {
+        layout.preformatted(&block, 8.5, 12.0);
+    }
+    flush(layout, &mut paragraph);
+}
+
+/// The marker and text of a list item, like `- item` or `1. item`
+fn list_item(line: &str) -> Option<(&str, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some(("•", text));
+        }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    match digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        true => Some((&line[..digits + 1], &rest[2..])),
+        false => None,
+    }
+}
+
+/// The text of inline Markdown, without its markup. Links are followed by their URL, and
+/// images replaced by their description.
+fn inline_text(markdown: &str) -> String {
+    let mut text = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(c) = rest.chars().next() {
+        // `[text](url)` and `![description](url)`
+        let image = rest.starts_with("![");
+        if c == '[' || image {
+            let open = usize::from(image) + 1;
+            if let Some((label, url, length)) = link(&rest[open..]) {
+                text.push_str(&inline_text(label));
+                if !image && !url.starts_with('#') && url != label {
+                    text.push_str(&format!(" ({url})"));
+                }
+                rest = &rest[open + length..];
+                continue;
+            }
+        }
+        // Code is kept as it is
+        if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            let code = &rest[ticks..];
+            if let Some(end) = code.find(&rest[..ticks]) {
+                text.push_str(code[..end].trim());
+                rest = &code[end + ticks..];
+                continue;
+            }
+        }
+        // HTML tags
+        if c == '<' {
+            let tag = rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/');
+            if let Some(end) = rest.find('>').filter(|_| tag) {
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        match c {
+            '*' | '`' => {}
+            c => text.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// The text and URL of the link `text](url)`, and its length
+fn link(rest: &str) -> Option<(&str, &str, usize)> {
+    let close = rest.find("](")?;
+    let end = close + 2 + rest[close + 2..].find(')')?;
+    Some((&rest[..close], &rest[close + 2..end], end + 1))
+}
@@ -0,0 +1,767 @@
+//! Translations of tracks: the strings of the topics are extracted into translation files
+//! with [`extract`], and [`apply`] writes the translated files next to those they translate,
+//! like `slides.nl.md` and `topic.nl.toml`, which [`Track::localized`] renders the track from.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    exercises::relative_path,
+    frontmatter,
+    io::PathExt,
+    report::{Warning, WarningCode},
+    suggest,
+    summary::SummaryDeriver,
+    LoadTrackError, Topic, TopicContent, Track,
+};
+
+/// Number of characters of a string shown in warnings about it
+const PREVIEW_LENGTH: usize = 40;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct I18nError {}
+
+impl fmt::Display for I18nError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to process translations")
+    }
+}
+
+impl error_stack::Context for I18nError {}
+
+/// The translatable strings of a topic, as handed to translators
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslationFile {
+    /// Path to the topic definition, relative to the track definition
+    pub topic: PathBuf,
+    pub locale: String,
+    #[serde(default)]
+    pub strings: Vec<TranslationString>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationString {
+    /// Hash of the source text with its whitespace normalized, so that it only
+    /// changes when the text itself does
+    pub id: String,
+    pub kind: StringKind,
+    /// The slide the text is on, for slide content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slide: Option<usize>,
+    pub source: String,
+    /// Empty until the string is translated
+    #[serde(default)]
+    pub translation: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StringKind {
+    Name,
+    Objective,
+    Summary,
+    /// A paragraph of the slides
+    Slides,
+}
+
+/// The translated name, objectives and summary of a topic, written next to its definition
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalizedTopic {
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    objectives: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    summary: Vec<String>,
+}
+
+/// An objective or summary item in a topic definition: text in the locale of the track, or
+/// the text in each locale, like `{ en = "Use traits", nl = "Traits gebruiken" }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LocalizedText {
+    Text(String),
+    Locales(BTreeMap<String, String>),
+}
+
+impl From<String> for LocalizedText {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Translations of texts, by locale and then by the text in the locale of the track
+pub type Translations = BTreeMap<String, BTreeMap<String, String>>;
+
+/// The texts in `locale`, the locale of the track, adding the translations of those that
+/// are given in each locale to `translations`
+pub(crate) fn split_localized(
+    texts: Vec<LocalizedText>,
+    locale: &str,
+    translations: &mut Translations,
+) -> Result<Vec<String>, I18nError> {
+    let mut in_locale = Vec::with_capacity(texts.len());
+    for text in texts {
+        let mut locales = match text {
+            LocalizedText::Text(text) => {
+                in_locale.push(text);
+                continue;
+            }
+            LocalizedText::Locales(locales) => locales,
+        };
+        let Some(text) = locales.remove(locale) else {
+            let given: Vec<_> = locales.keys().map(String::as_str).collect();
+            return Err(Report::new(I18nError::default()).attach_printable(format!(
+                "A text is given in {}, but not in '{locale}', the locale of the track",
+                given.join(", ")
+            )));
+        };
+        for (other, translation) in locales {
+            check_locale(&other)?;
+            translations
+                .entry(other)
+                .or_default()
+                .insert(text.clone(), translation);
+        }
+        in_locale.push(text);
+    }
+    Ok(in_locale)
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Number of translation files written
+    pub topics: usize,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    /// Number of topics translated files were written for
+    pub topics: usize,
+    pub warnings: Vec<Warning>,
+}
+
+impl fmt::Display for ExtractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extracted the strings of {} topic(s)", self.topics)?;
+        for warning in self.warnings.iter() {
+            write!(f, "\n  warning: {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ApplyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrote translations of {} topic(s)", self.topics)?;
+        for warning in self.warnings.iter() {
+            write!(f, "\n  warning: {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a translation file for each topic of the track into `out_dir`. Translations in
+/// files that are already there are kept for the strings whose source didn't change.
+pub fn extract(track: &Track, locale: &str, out_dir: &Path) -> Result<ExtractReport, I18nError> {
+    check_locale(locale)?;
+    out_dir.create_dir_all()?;
+    let track_dir = &track.content_root;
+    let mut warnings = vec![];
+    let mut seen = HashSet::new();
+    let mut file_names = HashSet::new();
+    for topic in track.topics() {
+        if !seen.insert(&topic.definition) {
+            continue;
+        }
+        let TopicContent::File(content_path) = &topic.content else {
+            warnings.push(Warning::new(
+                WarningCode::UntranslatableTopic,
+                format!(
+                    "Topic '{}' is defined inline, so it can't be translated",
+                    topic.name
+                ),
+                Some(&topic.definition),
+            ));
+            continue;
+        };
+        let content = content_path.read_to_string()?;
+
+        // Topics in different modules may have the same id
+        let mut file_name = topic.id.clone();
+        let mut n = 1;
+        while !file_names.insert(file_name.clone()) {
+            n += 1;
+            file_name = format!("{}-{n}", topic.id);
+        }
+        let path = out_dir.join(file_name).with_extension("toml");
+        let previous: HashMap<String, String> = match path.exists() {
+            true => read_translation_file(&path)?
+                .strings
+                .into_iter()
+                .map(|s| (s.id, s.translation))
+                .collect(),
+            false => HashMap::new(),
+        };
+
+        // Texts the definition gives in the locale start out translated
+        let given = topic.translations.get(locale);
+        let mut strings = topic_strings(topic, &content);
+        for string in strings.iter_mut() {
+            if let Some(translation) = previous
+                .get(&string.id)
+                .or_else(|| given?.get(&string.source))
+            {
+                string.translation = translation.clone();
+            }
+        }
+        let file = TranslationFile {
+            topic: relative_path(track_dir, &topic.definition),
+            locale: locale.to_string(),
+            strings,
+        };
+        let toml = toml::to_string_pretty(&file)
+            .into_report()
+            .change_context(I18nError::default())?;
+        path.write_file(toml)?;
+    }
+    Ok(ExtractReport {
+        topics: file_names.len(),
+        warnings,
+    })
+}
+
+/// Writes the translations in the translation files in `dir` next to the topics they belong to,
+/// as `<content>.<locale>.md` and `<definition>.<locale>.toml`. Strings whose source changed
+/// since they were extracted are reported as outdated, and their source is used instead.
+pub fn apply(track: &Track, dir: &Path) -> Result<ApplyReport, I18nError> {
+    let track_dir = &track.content_root;
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_report()
+        .change_context(I18nError::default())
+        .attach_printable_lazy(|| format!("Unable to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut report = ApplyReport::default();
+    for path in paths {
+        let file = read_translation_file(&path)?;
+        check_locale(&file.locale)
+            .attach_printable_lazy(|| format!("In translation file at {}", path.display()))?;
+        let definition = track_dir.join(&file.topic).canonicalize().ok();
+        let topic = track
+            .topics()
+            .find(|t| definition.is_some() && t.definition.canonicalize().ok() == definition);
+        let (Some(topic), Some(content_path)) = (topic, topic.and_then(|t| t.content.path()))
+        else {
+            report.warnings.push(Warning::new(
+                WarningCode::UnknownTranslation,
+                format!(
+                    "Topic {} is not part of the track, or is defined inline",
+                    file.topic.display()
+                ),
+                Some(&path),
+            ));
+            continue;
+        };
+        let content = content_path.read_to_string()?;
+        let strings = topic_strings(topic, &content);
+
+        let current: HashSet<_> = strings.iter().map(|s| s.id.as_str()).collect();
+        let mut translations = HashMap::new();
+        for string in file.strings.iter().filter(|s| !s.translation.is_empty()) {
+            if !current.contains(string.id.as_str()) {
+                report.warnings.push(Warning::new(
+                    WarningCode::OutdatedTranslation,
+                    format!(
+                        "Translation of \"{}\" is outdated, as its source changed",
+                        preview(&string.source)
+                    ),
+                    Some(&path),
+                ));
+                continue;
+            }
+            translations.insert(string.id.as_str(), string.translation.as_str());
+        }
+        let untranslated = strings
+            .iter()
+            .filter(|s| !translations.contains_key(s.id.as_str()))
+            .count();
+        if untranslated > 0 {
+            report.warnings.push(Warning::new(
+WarningCode::Untranslated,
+                format!(
+                    "{untranslated} string(s) of topic '{}' are not translated, their source is used",
+                    topic.name
+                ),
+                Some(&path),
+            ));
+        }
+        let translate = |text: &str| match translations.get(text_id(text).as_str()) {
+            Some(translation) => translation.to_string(),
+            None => text.to_string(),
+        };
+
+        let mut localized = String::with_capacity(content.len());
+        for segment in segments(&content) {
+            match segment.slide {
+                Some(_) => localized.push_str(&translate(segment.text)),
+                None => localized.push_str(segment.text),
+            }
+        }
+        localized_path(content_path, &file.locale).write_file(localized)?;
+
+        let localized_topic = LocalizedTopic {
+            name: translate(&topic.name),
+            objectives: topic.objectives.iter().map(|o| translate(o)).collect(),
+            summary: match topic.summary_derived {
+                true => vec![],
+                false => topic.summary.iter().map(|s| translate(s)).collect(),
+            },
+        };
+        let toml = toml::to_string_pretty(&localized_topic)
+            .into_report()
+            .change_context(I18nError::default())?;
+        localized_path(&topic.definition, &file.locale).write_file(toml)?;
+        report.topics += 1;
+    }
+    Ok(report)
+}
+
+impl Track {
+    /// The locales the track has translations to: those of the files next to the topic
+    /// content, topic definitions and exercise descriptions they translate, like
+    /// `slides.nl.md`, and those of the texts the definitions give in each locale
+    pub(crate) fn find_translations(&self) -> BTreeSet<String> {
+        let mut locales = BTreeSet::new();
+        // The files of each folder, as a folder often has several files that may be translated
+        let mut folders: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut find = |path: &Path| {
+            let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+                return;
+            };
+            let names = folders.entry(dir.to_path_buf()).or_insert_with(|| {
+                fs::read_dir(dir)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .collect()
+            });
+            let prefix = format!("{}.", stem.to_string_lossy());
+            let suffix = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            let found = names.iter().filter_map(|name| {
+                let locale = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+                is_locale_code(locale).then(|| locale.to_string())
+            });
+            locales.extend(found);
+        };
+        for topic in self.topics() {
+            if let TopicContent::File(content) = &topic.content {
+                find(content);
+                find(&topic.definition);
+            }
+            for description in topic
+                .exercises
+                .iter()
+                .filter_map(|e| e.data.description.as_deref())
+            {
+                find(description);
+            }
+        }
+        locales.extend(self.topics().flat_map(|t| t.translations.keys().cloned()));
+        locales
+    }
+
+    /// A copy of the track in `locale`, with the translations of its topics and exercise
+    /// descriptions instead of what they translate, where it has them. The topics and
+    /// exercise descriptions that aren't translated keep their content, and are listed in a
+    /// warning of the copy.
+    ///
+    /// A topic is translated by the files [`apply`] writes next to its content and
+    /// definition, and the objectives and summary a topic definition gives in `locale`. The
+    /// objectives, summary and further reading in the frontmatter of its translated content
+    /// replace those of the definition, and derived summaries are derived from the
+    /// translated content.
+    pub fn localized(&self, locale: &str) -> Result<Track, LoadTrackError> {
+        if !self.translations.contains(locale) {
+            let known = match self.translations.is_empty() {
+                true => "It has no translations, which modmod i18n makes".to_string(),
+                false => format!(
+                    "It has translations to {}",
+                    self.translations
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            return Err(Report::new(LoadTrackError)
+                .attach_printable(format!(
+                    "Track '{}' has no translation to '{locale}'.{}",
+                    self.name,
+                    suggest::did_you_mean(locale, &self.translations)
+                ))
+                .attach_printable(known));
+        }
+        let deriver = self
+            .derive_summaries
+            .as_ref()
+            .map(SummaryDeriver::new)
+            .transpose()
+            .change_context(LoadTrackError)?;
+
+        let mut track = self.clone();
+        let mut seen = HashSet::new();
+        let mut topics = vec![];
+        let mut descriptions = vec![];
+        let units = track
+            .modules
+            .iter_mut()
+            .flat_map(|m| m.data.units.iter_mut());
+        for topic in units
+            .flat_map(|u| u.data.topics.iter_mut())
+            .map(|t| &mut t.data)
+        {
+            // Topics can be included more than once, and inline topics share a definition
+            let first = seen.insert((topic.definition.clone(), topic.name.clone()));
+            let name = topic.name.clone();
+            let translated = localize_topic(topic, locale, deriver.as_ref())
+                .change_context(LoadTrackError)
+                .attach_printable_lazy(|| format!("Translating topic '{name}' to '{locale}'"))?;
+            if first && !translated {
+                topics.push(name);
+            }
+            for exercise in topic.exercises.iter_mut().map(|e| &mut e.data) {
+                let Some(description) = &exercise.description else {
+                    continue;
+                };
+                let localized = localized_path(description, locale);
+                match localized.is_file() {
+                    true => exercise.description = Some(localized),
+                    false if first => descriptions.push(exercise.name.clone()),
+                    false => {}
+                }
+            }
+        }
+        for (what, names) in [
+            ("topic(s)", topics),
+            ("exercise description(s)", descriptions),
+        ] {
+            if names.is_empty() {
+                continue;
+            }
+            track.warnings.push(Warning::new(
+                WarningCode::Untranslated,
+                format!(
+                    "{} {what} have no translation to '{locale}', and are rendered as they are: {}",
+                    names.len(),
+                    names.join(", ")
+                ),
+                Some(&self.definition),
+            ));
+        }
+        Ok(track)
+    }
+}
+
+/// Replaces what `topic` has of its translation to `locale`, and returns whether its content
+/// is translated
+fn localize_topic(
+    topic: &mut Topic,
+    locale: &str,
+    deriver: Option<&SummaryDeriver>,
+) -> Result<bool, I18nError> {
+    if let Some(translations) = topic.translations.get(locale) {
+        let translate = |text: &mut String| {
+            if let Some(translation) = translations.get(text.as_str()) {
+                *text = translation.clone();
+            }
+        };
+        topic.objectives.iter_mut().for_each(translate);
+        if !topic.summary_derived {
+            topic.summary.iter_mut().for_each(translate);
+        }
+    }
+    // Inline topics can only be translated in their definition
+    let TopicContent::File(content_path) = &topic.content else {
+        return Ok(false);
+    };
+
+    let definition = localized_path(&topic.definition, locale);
+    if definition.is_file() {
+        let localized: LocalizedTopic = toml::from_str(&definition.read_to_string::<I18nError>()?)
+            .into_report()
+            .change_context(I18nError::default())
+            .attach_printable_lazy(|| {
+                format!(
+                    "Unable to parse the translated topic at {}",
+                    definition.display()
+                )
+            })?;
+        topic.name = localized.name;
+        if !localized.objectives.is_empty() {
+            topic.objectives = localized.objectives;
+        }
+        if !localized.summary.is_empty() {
+            topic.summary = localized.summary;
+            topic.summary_derived = false;
+        }
+    }
+
+    let content_path = localized_path(content_path, locale);
+    if !content_path.is_file() {
+        return Ok(false);
+    }
+    let content = content_path.read_to_string::<I18nError>()?;
+    let split = frontmatter::split(&content)
+        .change_context(I18nError::default())
+        .attach_printable_lazy(|| format!("In topic content at {}", content_path.display()))?;
+    let mut slides = content.as_str();
+    if let Some((frontmatter, rest)) = split {
+        slides = rest;
+        if let Some(objectives) = frontmatter.objectives {
+            topic.objectives = objectives;
+        }
+        if let Some(summary) = frontmatter.summary {
+            topic.summary = summary;
+            topic.summary_derived = false;
+        }
+        if let Some(further_reading) = frontmatter.further_reading {
+            topic.further_reading = further_reading;
+        }
+    }
+    if let (true, Some(deriver)) = (topic.summary_derived, deriver) {
+        let summary = deriver.derive(slides);
+        if !summary.is_empty() {
+            topic.summary = summary;
+        }
+    }
+    topic.content = TopicContent::File(content_path);
+    Ok(true)
+}
+
+/// The translatable strings of a topic, in order and each only once
+fn topic_strings(topic: &Topic, content: &str) -> Vec<TranslationString> {
+    let summary: &[String] = match topic.summary_derived {
+        // Derived summaries are translated along with the headings they come from
+        true => &[],
+        false => &topic.summary,
+    };
+    let metadata = [(StringKind::Name, std::slice::from_ref(&topic.name))]
+        .into_iter()
+        .chain([
+            (StringKind::Objective, topic.objectives.as_slice()),
+            (StringKind::Summary, summary),
+        ])
+        .flat_map(|(kind, texts)| texts.iter().map(move |t| (kind, None, t.as_str())));
+    let slides = segments(content)
+        .into_iter()
+        .filter_map(|s| Some((StringKind::Slides, Some(s.slide?), s.text)));
+
+    let mut ids = HashSet::new();
+    metadata
+        .chain(slides)
+        .filter_map(|(kind, slide, text)| {
+            let id = text_id(text);
+            ids.insert(id.clone()).then(|| TranslationString {
+                id,
+                kind,
+                slide,
+                source: text.to_string(),
+                translation: String::new(),
+            })
+        })
+        .collect()
+}
+
+/// A part of topic content, which is either translated or kept as it is
+struct Segment<'a> {
+    text: &'a str,
+    /// The slide translatable text is on, or `None` for text that is kept as it is
+    slide: Option<usize>,
+}
+
+impl<'a> Segment<'a> {
+    fn kept(text: &'a str) -> Self {
+        Self { text, slide: None }
+    }
+}
+
+/// Splits slide content into paragraphs to translate and the parts around them, which are kept
+/// as they are: slide separators and frontmatter, code blocks, blank lines, conditional markers
+/// and slot names.
+/// Concatenating the segments gives back the content.
+fn segments(content: &str) -> Vec<Segment<'_>> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut segments = vec![];
+    // Content before the first separator is on the first slide, like in the rendered deck
+    let mut slide = 0;
+    let mut i = 0;
+    let offset = |line: &str| line.as_ptr() as usize - content.as_ptr() as usize;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            slide += 1;
+            let end = frontmatter_end(&lines[i + 1..]).map_or(i + 1, |n| i + 2 + n);
+            let start = offset(line);
+            let last = lines[end - 1];
+            segments.push(Segment::kept(&content[start..offset(last) + last.len()]));
+            i = end;
+            continue;
+        }
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with(fence))
+                .map_or(lines.len(), |n| i + 2 + n);
+            let last = lines[end - 1];
+            segments.push(Segment::kept(
+                &content[offset(line)..offset(last) + last.len()],
+            ));
+            i = end;
+            continue;
+        }
+        if is_kept_line(trimmed) {
+            segments.push(Segment::kept(line));
+            i += 1;
+            continue;
+        }
+
+        // A paragraph runs until a line that is kept as it is
+        let end = lines[i..]
+            .iter()
+            .position(|l| {
+                let l = l.trim();
+                is_kept_line(l) || l == "---" || l.starts_with("```") || l.starts_with("~~~")
+            })
+            .map_or(lines.len(), |n| i + n);
+        let last = lines[end - 1];
+        let paragraph = &content[offset(line)..offset(last) + last.len()];
+        let text = paragraph.trim_end_matches(['\n', '\r']);
+        // Paragraphs of markup only, like closing tags, have nothing to translate
+        if has_words(text) {
+            segments.push(Segment {
+                text,
+                slide: Some(slide.max(1)),
+            });
+            segments.push(Segment::kept(&paragraph[text.len()..]));
+        } else {
+            segments.push(Segment::kept(paragraph));
+        }
+        i = end;
+    }
+    segments
+}
+
+/// Blank lines, conditional markers and Slidev slot names like `::right::`
+fn is_kept_line(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with("#[modmod:")
+        || (line.len() > 4 && line.starts_with("::") && line.ends_with("::"))
+}
+
+/// Whether there is text outside the HTML tags of a paragraph
+fn has_words(paragraph: &str) -> bool {
+    let mut in_tag = false;
+    paragraph.chars().any(|c| {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag && c.is_alphabetic() => return true,
+            _ => {}
+        }
+        false
+    })
+}
+
+/// The number of lines of the frontmatter of a slide, if `lines` start with one,
+/// following the rule of [`crate::slides::split_slides`]
+fn frontmatter_end(lines: &[&str]) -> Option<usize> {
+    let close = lines.iter().position(|l| l.trim_end() == "---")?;
+    let frontmatter = &lines[..close];
+    let is_frontmatter = frontmatter.first().is_some_and(|l| !l.trim().is_empty())
+        && frontmatter.iter().all(|l| {
+            l.trim().is_empty()
+                || l.starts_with([' ', '-'])
+                || (!l.starts_with('#') && l.contains(':'))
+        });
+    is_frontmatter.then_some(close)
+}
+
+/// A 64-bit FNV-1a hash of the text with its whitespace normalized. Unlike the hasher of the
+/// standard library, it doesn't change between versions of Rust.
+fn text_id(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (i, word) in text.split_whitespace().enumerate() {
+        let separator = if i == 0 { &[][..] } else { &[b' '][..] };
+        for byte in separator.iter().chain(word.as_bytes()) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// `slides.md` becomes `slides.nl.md` for locale `nl`
+fn localized_path(path: &Path, locale: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}.{locale}.{}", extension.to_string_lossy()),
+        None => format!("{stem}.{locale}"),
+    };
+    path.with_file_name(name)
+}
+
+fn check_locale(locale: &str) -> Result<(), I18nError> {
+    if !locale.is_empty()
+        && locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Ok(());
+    }
+    Err(Report::new(I18nError::default()).attach_printable(format!(
+        "Locale '{locale}' is not valid, it should be a code like 'nl' or 'pt-BR'"
+    )))
+}
+
+/// Whether `name`, from the name of a file like `slides.nl.md`, is a locale code like `nl` or
+/// `pt-BR`, rather than part of the name like in `slides.backup.md`
+fn is_locale_code(name: &str) -> bool {
+    let (language, region) = name.split_once(['-', '_']).unwrap_or((name, "A"));
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && !region.is_empty()
+        && region.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn read_translation_file(path: &Path) -> Result<TranslationFile, I18nError> {
+    toml::from_str(&path.read_to_string::<I18nError>()?)
+        .into_report()
+        .change_context(I18nError::default())
+        .attach_printable_lazy(|| format!("Unable to parse translation file at {}", path.display()))
+}
+
+fn preview(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.chars().count() > PREVIEW_LENGTH {
+        true => format!(
+            "{}...",
+            text.chars().take(PREVIEW_LENGTH).collect::<String>()
+        ),
+        false => text,
+    }
+}
@@ -0,0 +1,198 @@
+//! The speaker notes of a track compiled into a document per module, for trainers to print
+//! and teach from, written by `generate --instructor-notes`, and the handouts of
+//! `generate --instructor-handout`, a document per deck with the objectives, summary, notes
+//! and exercises of its unit.
+//!
+//! The notes are read back from the rendered decks, and taken from each slide the way
+//! Slidev does when presenting, see [`crate::slides::slide_notes`], so that the document
+//! has the same notes as the presenter view. Each note is under the first heading of its
+//! slide, in the order of the decks, and slides without notes are left out.
+//!
+//! The documents go into [`INSTRUCTOR_DIR`] of the output folder, which isn't part of the
+//! deployed site, and is left out of archives of the output, see [`crate::archive`].
+
+use std::path::Path;
+
+use error_stack::Result;
+
+use crate::{
+    io::PathExt,
+    slides::{slide_heading, slide_notes, split_slides},
+    to_prefixed_tag, ContentFilter, LoadTrackError, OutputLayout, Topic, Track,
+};
+
+/// The folder of the output the instructor notes are written into
+pub const INSTRUCTOR_DIR: &str = "instructor";
+
+impl Track {
+    /// Writes `notes-<module>.md` into [`INSTRUCTOR_DIR`] for each module that has decks with
+    /// notes among the decks rendered into `out_dir`
+    pub(crate) fn write_instructor_notes(
+        &self,
+        out_dir: &Path,
+        layout: &OutputLayout,
+        filter: ContentFilter,
+    ) -> Result<(), LoadTrackError> {
+        let notes_dir = out_dir.join(INSTRUCTOR_DIR);
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let title = match label.display.is_empty() {
+                true => module.data.name.clone(),
+                false => format!("Module {} - {}", label.display, module.data.name),
+            };
+            let mut document = format!("# Instructor notes: {title}\n");
+            let mut has_notes = false;
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| u.data.render_slides && filter.includes_unit(&u.data));
+            for unit in units {
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let deck = out_dir
+                    .join(layout.slides())
+                    .join(to_prefixed_tag(&unit.data.name, &prefix))
+                    .with_extension("md");
+                // Decks without content are not written
+                if !deck.exists() {
+                    continue;
+                }
+                let unit_notes = deck_notes(&deck.read_to_string()?);
+                if unit_notes.is_empty() {
+                    continue;
+                }
+                let unit_title = format!("Unit {} - {}", label.qualify(unit.index), unit.data.name);
+                document.push_str(&format!("\n## {unit_title}\n{unit_notes}"));
+                has_notes = true;
+            }
+            if !has_notes {
+                continue;
+            }
+            notes_dir.create_dir_all()?;
+            let name = to_prefixed_tag(&module.data.name, &label.path);
+            notes_dir
+                .join(format!("notes-{name}.md"))
+                .write_text_file(document)?;
+        }
+        Ok(())
+    }
+    /// Writes `<deck>.md` into [`INSTRUCTOR_DIR`] for each deck rendered into `out_dir`, with
+    /// the objectives, summary and exercises of its unit, and the notes of the deck
+    pub(crate) fn write_instructor_handouts(
+        &self,
+        out_dir: &Path,
+        layout: &OutputLayout,
+        filter: ContentFilter,
+    ) -> Result<(), LoadTrackError> {
+        let handouts_dir = out_dir.join(INSTRUCTOR_DIR);
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| u.data.render_slides && filter.includes_unit(&u.data));
+            for unit in units {
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let slug = to_prefixed_tag(&unit.data.name, &prefix);
+                let deck = out_dir
+                    .join(layout.slides())
+                    .join(&slug)
+                    .with_extension("md");
+                // Decks without content are not written
+                if !deck.exists() {
+                    continue;
+                }
+                let notes = deck_notes(&deck.read_to_string()?);
+                let topics: Vec<&Topic> = unit
+                    .data
+                    .topics
+                    .iter()
+                    .map(|t| &t.data)
+                    .filter(|t| filter.includes(t.optional, &t.profiles))
+                    .collect();
+                let optional_marker = |optional| match optional {
+                    true => " *(optional)*",
+                    false => "",
+                };
+                let list = |items: &mut dyn Iterator<Item = (&String, bool)>| {
+                    let mut list: Vec<String> = vec![];
+                    for (item, optional) in items {
+                        let item = format!("- {}{}\n", item.trim(), optional_marker(optional));
+                        if !list.contains(&item) {
+                            list.push(item);
+                        }
+                    }
+                    list.concat()
+                };
+                let objectives = list(
+                    &mut topics
+                        .iter()
+                        .flat_map(|t| t.objectives.iter().map(|o| (o, t.optional))),
+                );
+                let summary = list(
+                    &mut topics
+                        .iter()
+                        .flat_map(|t| t.summary.iter().map(|s| (s, t.optional))),
+                );
+                let mut exercises = String::new();
+                let unit_exercises = topics
+                    .iter()
+                    .flat_map(|t| t.exercises.iter().map(|e| &e.data))
+                    .filter(|e| filter.includes(e.optional, &e.profiles));
+                for (exercise, number) in unit_exercises.zip(1..) {
+                    exercises.push_str(&format!(
+                        "- Exercise {}: {}{}\n",
+                        label.qualify(format!("{}.{number}", unit.index)),
+                        exercise.name,
+                        optional_marker(exercise.optional)
+                    ));
+                }
+
+                let mut handout = format!(
+                    "# Instructor handout: Unit {} - {}\n\nModule {}\n",
+                    label.qualify(unit.index),
+                    unit.data.name,
+                    match label.display.is_empty() {
+                        true => module.data.name.clone(),
+                        false => format!("{} - {}", label.display, module.data.name),
+                    }
+                );
+                for (title, part) in [
+                    ("Objectives", &objectives),
+                    ("Summary", &summary),
+                    ("Notes", &notes),
+                    ("Exercises", &exercises),
+                ] {
+                    if !part.is_empty() {
+                        let separator = if part.starts_with('\n') { "" } else { "\n" };
+                        handout.push_str(&format!("\n## {title}\n{separator}{part}"));
+                    }
+                }
+                handouts_dir.create_dir_all()?;
+                handouts_dir
+                    .join(slug)
+                    .with_extension("md")
+                    .write_text_file(handout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The notes of the slides of `deck`, each under the number and first heading of its slide
+fn deck_notes(deck: &str) -> String {
+    let mut deck_notes = String::new();
+    for (slide, number) in split_slides(deck).into_iter().zip(1..) {
+        let Some(notes) = slide_notes(slide) else {
+            continue;
+        };
+        match slide_heading(slide) {
+            Some(heading) => deck_notes.push_str(&format!("\n### Slide {number}: {heading}\n\n")),
+            None => deck_notes.push_str(&format!("\n### Slide {number}\n\n")),
+        }
+        deck_notes.push_str(notes);
+        deck_notes.push('\n');
+    }
+    deck_notes
+}
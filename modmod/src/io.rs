@@ -1,29 +1,1198 @@
 use std::{
+    collections::{hash_map::RandomState, BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsString,
     fmt,
     fs::{self, File},
+    hash::BuildHasher,
     io,
-    path::Path,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, Mutex},
 };
 
-use error_stack::{Context, IntoReport, Result, ResultExt};
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
 use fs_extra::dir::DirContent;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::{
+    catalog::ErrorKind,
+    context::{self, RunContext},
+    report::format_bytes,
+    sha256::hash_file,
+};
+
+/// Suffix of the temporary files that generated files are written to, next to the file
+/// they replace once they are complete
+pub const TEMP_SUFFIX: &str = ".modmod-tmp";
+
+/// Name of the journal of a render into a folder, see [`begin_journal`]
+pub const JOURNAL_FILE: &str = ".modmod-journal";
+
+/// A file created by [`PathExt::create_file`]. Nothing appears at its path until it is
+/// [committed](OutputFile::commit): until then it's written to a temporary file next to
+/// it, which is removed if the file is dropped, so that an interrupted or failed write
+/// never leaves a truncated file behind.
+pub enum OutputFile {
+    File(TempFile),
+    /// Created while writes are discarded, see [`RunContext::discarding`]
+    Discarded(io::Sink),
+    /// Created by a sink that keeps what is written, like [`RecordingFs`] and
+    /// [`MemoryFs`], which gets the content once the file is committed
+    Buffered {
+        path: PathBuf,
+        content: Vec<u8>,
+        mode: Option<u32>,
+    },
+}
+
+/// A file of generated text, created by [`PathExt::create_text_file`]. It's written with
+/// `\n` line endings, which it writes as `\r\n` when [`RunContext::line_ending`] asked for
+/// those.
+pub struct TextFile {
+    file: OutputFile,
+    crlf: bool,
+}
+
+impl TextFile {
+    /// Commits the file, see [`OutputFile::commit`]
+    pub fn commit<C: Context + Default>(self) -> Result<(), C> {
+        self.file.commit()
+    }
+}
+
+impl io::Write for TextFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.crlf {
+            return self.file.write(buf);
+        }
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            match line.strip_suffix(b"\n") {
+                Some(line) => {
+                    let line = line.strip_suffix(b"\r").unwrap_or(line);
+                    io::Write::write_all(&mut self.file, line)?;
+                    io::Write::write_all(&mut self.file, b"\r\n")?;
+                }
+                None => io::Write::write_all(&mut self.file, line)?,
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// How the lines of generated text files end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(Self::Lf),
+            "crlf" => Ok(Self::Crlf),
+            _ => Err(format!("unknown line ending '{s}', expected lf or crlf")),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Lf => "lf",
+            Self::Crlf => "crlf",
+        })
+    }
+}
+
+/// How the lines of generated text files end, see [`RunContext::line_ending`]
+pub(crate) fn line_ending() -> LineEnding {
+    context::current().line_ending
+}
+
+/// Text as it's read: without a byte order mark, and with `\n` line endings, so that
+/// files edited on Windows are assembled and compared like any other
+pub fn normalize_text(content: String) -> String {
+    let content = match content.strip_prefix('\u{feff}') {
+        Some(content) => content.to_string(),
+        None => content,
+    };
+    match content.contains('\r') {
+        true => content.replace("\r\n", "\n").replace('\r', "\n"),
+        false => content,
+    }
+}
+
+/// The temporary file an [`OutputFile`] is written to
+pub struct TempFile {
+    file: File,
+    temp: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl TempFile {
+    fn create(path: &Path) -> io::Result<Self> {
+        let temp = temp_path(path);
+        Ok(Self {
+            file: File::create(&temp)?,
+            temp,
+            path: path.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    /// Moves the file into place, unless the file there has the same content. Returns
+    /// whether it was moved.
+    fn commit(&mut self) -> io::Result<bool> {
+        io::Write::flush(&mut self.file)?;
+        if skips_unchanged() && same_content(&self.temp, &self.path)? {
+            // Dropping removes the temporary file
+            return Ok(false);
+        }
+        if syncs_writes() {
+            self.file.sync_all()?;
+        }
+        fs::rename(&self.temp, &self.path)?;
+        self.committed = true;
+        if syncs_writes() {
+            sync_parent(&self.path)?;
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+impl OutputFile {
+    /// Sets the Unix permission bits of the file, like `0o755` for a script. Other
+    /// platforms have no such bits, so there it does nothing.
+    pub fn set_mode<C: Context + Default>(&mut self, mode: u32) -> Result<(), C> {
+        match self {
+            Self::File(file) => set_mode(&file.temp, mode)
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Error setting the permissions of file at path {path}",
+                        path = file.path.to_string_lossy()
+                    )
+                })
+                .change_context(C::default()),
+            Self::Buffered { mode: buffered, .. } => {
+                *buffered = Some(mode);
+                Ok(())
+            }
+            Self::Discarded(_) => Ok(()),
+        }
+    }
+
+    /// Moves the file into place, after syncing it to disk when the context asks for that,
+    /// see [`RunContext::sync_writes`]. A file with the same content as the one at its path
+    /// is left out, so that the one there keeps its modification time, see
+    /// [`RunContext::rewrite_unchanged`].
+    pub fn commit<C: Context + Default>(self) -> Result<(), C> {
+        let (path, written) = match self {
+            Self::File(mut file) => {
+                let written = file
+                    .commit()
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Error writing file at path {path}",
+                            path = file.path.to_string_lossy()
+                        )
+                    })
+                    .change_context(C::default())
+                    .attach(ErrorKind::WriteFailed)?;
+                (file.path.clone(), written)
+            }
+            Self::Buffered {
+                path,
+                content,
+                mode,
+            } => {
+                let written = sink_for(&path)
+                    .write(&path, content, mode)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Error writing file at path {path}",
+                            path = path.to_string_lossy()
+                        )
+                    })
+                    .change_context(C::default())
+                    .attach(ErrorKind::WriteFailed)?;
+                (path, written)
+            }
+            // Without its content, there's no telling whether it changed
+            Self::Discarded(_) => return Ok(()),
+        };
+        completed::<C>(&path, written)
+    }
+}
+
+impl io::Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.file.write(buf),
+            Self::Discarded(sink) => sink.write(buf),
+            Self::Buffered { content, .. } => content.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.file.flush(),
+            Self::Discarded(sink) => sink.flush(),
+            Self::Buffered { .. } => Ok(()),
+        }
+    }
+}
+
+/// Whether the files at `a` and `b` have the same content and permissions, so that a
+/// script that lost its executable bit is copied again. False if `b` doesn't exist.
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let Ok(existing) = fs::metadata(b) else {
+        return Ok(false);
+    };
+    let new = fs::metadata(a)?;
+    if !existing.is_file()
+        || existing.len() != new.len()
+        || existing.permissions() != new.permissions()
+    {
+        return Ok(false);
+    }
+    same_bytes(File::open(a)?, File::open(b)?)
+}
+
+/// Size of the chunks files are read in when they are compared or hashed, so that large
+/// assets, like videos, aren't read into memory at once
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Whether `a` and `b` read the same bytes, reading both a chunk at a time
+pub(crate) fn same_bytes(a: impl io::Read, b: impl io::Read) -> io::Result<bool> {
+    let mut a = io::BufReader::with_capacity(CHUNK_SIZE, a);
+    let mut b = io::BufReader::with_capacity(CHUNK_SIZE, b);
+    loop {
+        let (chunk_a, chunk_b) = (
+            io::BufRead::fill_buf(&mut a)?,
+            io::BufRead::fill_buf(&mut b)?,
+        );
+        if chunk_a.is_empty() || chunk_b.is_empty() {
+            return Ok(chunk_a.is_empty() && chunk_b.is_empty());
+        }
+        let len = chunk_a.len().min(chunk_b.len());
+        if chunk_a[..len] != chunk_b[..len] {
+            return Ok(false);
+        }
+        io::BufRead::consume(&mut a, len);
+        io::BufRead::consume(&mut b, len);
+    }
+}
+
+/// Reads the file at `path` a chunk of at most [`CHUNK_SIZE`] bytes at a time. Files that
+/// were written in memory are there in one piece already, see [`MemoryFs`].
+pub(crate) fn read_chunks(path: &Path, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    let sink = sink_for(path);
+    if sink.holds(path) {
+        f(&sink.read(path)?);
+        return Ok(());
+    }
+    let mut file = File::open(path)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        match io::Read::read(&mut file, &mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Whether the file at `path` has the permission bits `mode`, which any file has on
+/// platforms without them
+#[cfg(unix)]
+fn has_mode(path: &Path, mode: u32) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o7777 == mode)
+}
+
+#[cfg(not(unix))]
+fn has_mode(_path: &Path, _mode: u32) -> bool {
+    true
+}
+
+/// The temporary file that `path` is written to, hidden in the same folder so that it can
+/// be renamed to it
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(TEMP_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Makes the rename of a file into its folder durable
+fn sync_parent(path: &Path) -> io::Result<()> {
+    // Folders can't be opened as files on Windows, where renames are durable once they return
+    if cfg!(unix) {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            File::open(parent)?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+/// Where the reads and writes of [`PathExt`] go. [`RealFs`] makes them, and the others
+/// leave the file system alone, see [`RunContext::discarding`], [`RunContext::recording`]
+/// and [`RunContext::in_memory`].
+pub trait FsSink: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+    /// Whether the sink keeps the file at `path` itself, rather than on disk
+    fn holds(&self, path: &Path) -> bool {
+        let _ = path;
+        false
+    }
+    /// The files in the folder `dir` and its subfolders that the sink keeps itself, in order
+    /// of their paths, if it keeps that folder. `None` is for folders on disk.
+    fn files_in(&self, dir: &Path) -> Option<Vec<PathBuf>> {
+        let _ = dir;
+        None
+    }
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile>;
+    /// Takes the content of an [`OutputFile::Buffered`] that was created by this sink
+    /// when it's committed. Returns whether it changed the file.
+    fn write(&self, path: &Path, content: Vec<u8>, mode: Option<u32>) -> io::Result<bool> {
+        let _ = (path, content, mode);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the file wasn't created by this sink",
+        ))
+    }
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Creates a symbolic link at `link` that points to `target`, replacing what is there
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Whether the writes are made
+    fn is_real(&self) -> bool {
+        false
+    }
+}
+
+pub struct RealFs;
+
+impl FsSink for RealFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile> {
+        TempFile::create(path).map(OutputFile::File)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // Copied by path rather than through a `TempFile`, which keeps the permissions
+        let temp = temp_path(to);
+        let copied = fs::copy(from, &temp).and_then(|_| {
+            if syncs_writes() {
+                File::open(&temp)?.sync_all()?;
+            }
+            fs::rename(&temp, to)
+        });
+        if copied.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        copied?;
+        if syncs_writes() {
+            sync_parent(to)?;
+        }
+        Ok(())
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            match fs::symlink_metadata(link) {
+                Ok(existing) if existing.is_dir() => fs::remove_dir_all(link)?,
+                Ok(_) => fs::remove_file(link)?,
+                Err(_) => {}
+            }
+            std::os::unix::fs::symlink(target, link)
+        }
+        // Creating links takes privileges on Windows, see `CopyTreeOptions::preserve_symlinks`
+        #[cfg(not(unix))]
+        {
+            let _ = (target, link);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "symbolic links are only created on Unix",
+            ))
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn is_real(&self) -> bool {
+        true
+    }
+}
+
+/// Drops all writes, only checking that copied files can be read, as copying them would
+pub struct DiscardingFs;
+
+impl FsSink for DiscardingFs {
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn create_file(&self, _path: &Path) -> io::Result<OutputFile> {
+        Ok(OutputFile::Discarded(io::sink()))
+    }
+
+    fn copy(&self, from: &Path, _to: &Path) -> io::Result<()> {
+        File::open(from).map(drop)
+    }
+
+    fn symlink(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_file(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_dir(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A write that was recorded instead of made, see [`RunContext::recording`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedWrite {
+    CreateDir(PathBuf),
+    /// A file that is written with `size` bytes, replacing the one there if `overwrite`
+    WriteFile {
+        path: PathBuf,
+        size: u64,
+        overwrite: bool,
+    },
+    /// A file of `size` bytes that is copied, replacing the one there if `overwrite`
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        size: u64,
+        overwrite: bool,
+    },
+    Symlink {
+        target: PathBuf,
+        link: PathBuf,
+    },
+    RemoveFile(PathBuf),
+    RemoveDir(PathBuf),
+    /// A command that is run in `dir`, like a build step, which writes files of its own
+    Run {
+        command: String,
+        dir: PathBuf,
+    },
+}
+
+impl fmt::Display for PlannedWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateDir(path) => write!(f, "create folder {}", path.display()),
+            Self::WriteFile {
+                path,
+                size,
+                overwrite,
+            } => {
+                let action = match overwrite {
+                    true => "overwrite",
+                    false => "create",
+                };
+                write!(f, "{action} {} ({})", path.display(), format_bytes(*size))
+            }
+            Self::Copy {
+                from,
+                to,
+                size,
+                overwrite,
+            } => {
+                let over = match overwrite {
+                    true => "over",
+                    false => "to",
+                };
+                write!(
+                    f,
+                    "copy {} {over} {} ({})",
+                    from.display(),
+                    to.display(),
+                    format_bytes(*size)
+                )
+            }
+            Self::Symlink { target, link } => {
+                write!(f, "link {} to {}", link.display(), target.display())
+            }
+            Self::RemoveFile(path) => write!(f, "remove {}", path.display()),
+            Self::RemoveDir(path) => write!(f, "remove folder {}", path.display()),
+            Self::Run { command, dir } => write!(f, "run `{command}` in {}", dir.display()),
+        }
+    }
+}
+
+/// Drops all writes like [`DiscardingFs`], and records them
+#[derive(Default)]
+pub struct RecordingFs {
+    writes: Mutex<Vec<PlannedWrite>>,
+    /// The folders that the recorded writes create
+    dirs: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl RecordingFs {
+    pub(crate) fn record(&self, write: PlannedWrite) {
+        self.writes.lock().unwrap().push(write);
+    }
+
+    /// The writes that were recorded so far, in order, leaving them out of later calls
+    pub fn take(&self) -> Vec<PlannedWrite> {
+        std::mem::take(&mut self.writes.lock().unwrap())
+    }
+}
+
+impl FsSink for RecordingFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        // Folders that are there already, or that are created by an earlier write, aren't
+        // worth mentioning
+        let mut dirs = self.dirs.lock().unwrap();
+        if !path.is_dir() && !dirs.contains(path) {
+            dirs.extend(path.ancestors().map(Path::to_path_buf));
+            self.record(PlannedWrite::CreateDir(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile> {
+        // Recorded once it's committed, if it changes the file
+        Ok(OutputFile::Buffered {
+            path: path.to_path_buf(),
+            content: vec![],
+            mode: None,
+        })
+    }
+
+    fn write(&self, path: &Path, content: Vec<u8>, mode: Option<u32>) -> io::Result<bool> {
+        let written = !skips_unchanged()
+            || fs::read(path).map_or(true, |existing| existing != content)
+            || mode.is_some_and(|mode| !has_mode(path, mode));
+        if written {
+            self.record(PlannedWrite::WriteFile {
+                path: path.to_path_buf(),
+                size: content.len() as u64,
+                overwrite: path.exists(),
+            });
+        }
+        Ok(written)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        DiscardingFs.copy(from, to)?;
+        self.record(PlannedWrite::Copy {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            size: fs::metadata(from)?.len(),
+            overwrite: to.exists(),
+        });
+        Ok(())
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        self.record(PlannedWrite::Symlink {
+            target: target.to_path_buf(),
+            link: link.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.record(PlannedWrite::RemoveFile(path.to_path_buf()));
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.record(PlannedWrite::RemoveDir(path.to_path_buf()));
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.remove_dir(path)
+    }
+}
+
+/// Keeps the files that are written in memory instead of on disk, so that renders can be
+/// run and their output inspected without an output folder. Reads look in memory first,
+/// and then on disk, so that content can come from either, see [`MemoryFs::insert`].
+/// Renders read back what they wrote, like the render manifest, so that rendering into the
+/// same folder again works like it does on disk. Symbolic links can't be kept in memory, so
+/// creating them fails.
+#[derive(Default)]
+pub struct MemoryFs {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+    dirs: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl MemoryFs {
+    /// Adds a file, as if it was written
+    pub fn insert(&self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.into(), content.into());
+    }
+
+    /// The content of the file at `path`, if it was written
+    pub fn file(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+
+    /// The files that were written, by path
+    pub fn files(&self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.lock().unwrap().clone()
+    }
+}
+
+impl FsSink for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.file(path) {
+            Some(content) => Ok(content),
+            None => fs::read(path),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+            || self.dirs.lock().unwrap().contains(path)
+            || path.exists()
+    }
+
+    fn holds(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+
+    fn files_in(&self, dir: &Path) -> Option<Vec<PathBuf>> {
+        let files = self.files.lock().unwrap();
+        let listed: Vec<PathBuf> = files
+            .keys()
+            .filter(|file| file.starts_with(dir) && *file != dir)
+            .cloned()
+            .collect();
+        let kept = !listed.is_empty() || self.dirs.lock().unwrap().contains(dir);
+        kept.then_some(listed)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut dirs = self.dirs.lock().unwrap();
+        dirs.extend(path.ancestors().map(Path::to_path_buf));
+        Ok(())
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile> {
+        Ok(OutputFile::Buffered {
+            path: path.to_path_buf(),
+            content: vec![],
+            mode: None,
+        })
+    }
+
+    fn write(&self, path: &Path, content: Vec<u8>, _mode: Option<u32>) -> io::Result<bool> {
+        let mut files = self.files.lock().unwrap();
+        let written = !skips_unchanged() || files.get(path) != Some(&content);
+        files.insert(path.to_path_buf(), content);
+        Ok(written)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let content = self.read(from)?;
+        self.insert(to, content);
+        Ok(())
+    }
+
+    fn symlink(&self, _target: &Path, link: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can't be linked in memory", link.display()),
+        ))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        match self.files.lock().unwrap().remove(path) {
+            Some(_) => Ok(()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.dirs.lock().unwrap().remove(path);
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|file, _| !file.starts_with(path));
+        self.dirs
+            .lock()
+            .unwrap()
+            .retain(|dir| !dir.starts_with(path));
+        Ok(())
+    }
+}
+
+/// Whether writes go anywhere but the file system, see [`RunContext::writes_discarded`]
+pub fn writes_discarded() -> bool {
+    context::current().writes_discarded()
+}
+
+/// The content of the file at `path`, which may have been written in memory, see
+/// [`MemoryFs`]
+pub(crate) fn read(path: &Path) -> io::Result<Vec<u8>> {
+    sink_for(path).read(path)
+}
+
+/// Records that `command` would be run in `dir`, for a dry run that skips it
+pub(crate) fn record_run(command: String, dir: &Path) {
+    context::current().record(PlannedWrite::Run {
+        command,
+        dir: dir.to_path_buf(),
+    });
+}
+
+/// Whether there is a file or folder at `path`, which may have been written in memory, see
+/// [`MemoryFs`]
+pub fn exists(path: &Path) -> bool {
+    sink_for(path).exists(path)
+}
+
+/// The files in the folder `dir` and its subfolders like [`walk_dir`] lists them, also when
+/// they were written in memory, see [`MemoryFs`]. `None` if there is no folder at `dir`.
+pub fn files_in<C: Context + Default>(
+    dir: &Path,
+    preserve_symlinks: bool,
+) -> Result<Option<Vec<DirEntry>>, C> {
+    if let Some(files) = sink_for(dir).files_in(dir) {
+        return Ok(Some(files.into_iter().map(DirEntry::File).collect()));
+    }
+    match dir.is_dir() {
+        true => walk_dir(dir, preserve_symlinks).map(Some),
+        false => Ok(None),
+    }
+}
+
+/// Whether the folder `dir` is kept in memory, so that what is written into it can be read
+/// back although writes are discarded, see [`MemoryFs`]
+pub(crate) fn kept_in_memory(dir: &Path) -> bool {
+    sink_for(dir).files_in(dir).is_some()
+}
+
+/// Whether the folder at `dir` holds nothing, also when it was written in memory, see
+/// [`MemoryFs`]
+pub(crate) fn is_empty_dir(dir: &Path) -> bool {
+    match sink_for(dir).files_in(dir) {
+        Some(files) => files.is_empty(),
+        None => fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none()),
+    }
+}
+
+/// `path` with forward slashes, for paths that end up in generated text like npm
+/// scripts, links in the book and patches, so that output is the same on every platform.
+/// Fails on paths that aren't UTF-8, which can't be written into the text as they are.
+pub fn slash_path<C: Context + Default>(path: &Path) -> Result<String, C> {
+    let mut slashed = String::new();
+    // Whether the last part was a folder or file name, rather than a prefix or the root
+    let mut after_name = false;
+    for component in path.components() {
+        let Some(part) = component.as_os_str().to_str() else {
+            return Err(Report::new(C::default()).attach_printable(format!(
+                "Path {} isn't valid UTF-8, so it can't be written into the output",
+                path.display()
+            )));
+        };
+        match component {
+            // Like `C:` or `\\server\share` on Windows
+            Component::Prefix(_) => slashed.push_str(&part.replace('\\', "/")),
+            Component::RootDir => slashed.push('/'),
+            _ if after_name => {
+                slashed.push('/');
+                slashed.push_str(part);
+            }
+            _ => slashed.push_str(part),
+        }
+        after_name = matches!(
+            component,
+            Component::Normal(_) | Component::CurDir | Component::ParentDir
+        );
+    }
+    Ok(slashed)
+}
+
+/// A folder of its own in the temporary directory, to render into before comparing or
+/// copying the output, see [`scratch_dir`]. It's removed with what is in it when it's
+/// dropped.
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    /// Those of the context it was made in, which it's taken out of again when dropped
+    scratch_dirs: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl ScratchDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl std::ops::Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+        self.scratch_dirs
+            .lock()
+            .unwrap()
+            .retain(|dir| *dir != self.path);
+    }
+}
+
+/// A new, empty folder in the temporary directory, to render into before comparing or
+/// copying the output. Its name starts with `name`, and ends with the process id and a
+/// random suffix, so that commands that run at the same time each get their own. It is
+/// written to even when writes are discarded, so that commands still run fully on dry runs.
+pub fn scratch_dir<C: Context + Default>(name: &str) -> Result<ScratchDir, C> {
+    let temp_dir = std::env::temp_dir();
+    let mut attempt = 0u32;
+    let path = loop {
+        // The hasher is seeded at random
+        let suffix = RandomState::new().hash_one(attempt) as u32;
+        let path = temp_dir.join(format!("{name}-{}-{suffix:08x}", std::process::id()));
+        match fs::create_dir(&path) {
+            Ok(()) => break path,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => {
+                return Err(e)
+                    .into_report()
+                    .attach_printable(format!(
+                        "Error creating directory at path {}",
+                        path.display()
+                    ))
+                    .change_context(C::default())
+            }
+        }
+    };
+    let scratch_dirs = context::current().scratch_dirs.clone();
+    scratch_dirs.lock().unwrap().push(path.clone());
+    Ok(ScratchDir { path, scratch_dirs })
+}
+
+/// Whether writes to `path` are discarded: when they are in general, and it isn't in a
+/// [`scratch_dir`]
+pub fn writes_discarded_at(path: &Path) -> bool {
+    context::current().writes_discarded_at(path)
+}
+
+pub(crate) fn skips_unchanged() -> bool {
+    !context::current().rewrite_unchanged
+}
+
+/// How many files were written, and how many were left alone as they had the same content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteCounts {
+    pub written: usize,
+    pub unchanged: usize,
+}
+
+impl RunContext {
+    /// The files that were written and left unchanged in this context so far
+    pub fn write_counts(&self) -> WriteCounts {
+        WriteCounts {
+            written: self.written.load(Ordering::Relaxed),
+            unchanged: self.unchanged.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Starts collecting the paths of the files that are written or left unchanged, which
+    /// [`RunContext::take_tracked_files`] returns. Used to tell which files of an earlier
+    /// render weren't rendered again.
+    pub fn track_files(&self) {
+        *self.tracked.lock().unwrap() = Some(HashSet::new());
+    }
+
+    /// The files that were written or left unchanged since [`RunContext::track_files`] was
+    /// called, which stops collecting them
+    pub fn take_tracked_files(&self) -> HashSet<PathBuf> {
+        self.tracked.lock().unwrap().take().unwrap_or_default()
+    }
+}
+
+/// Tracks a file that is kept as it is instead of being rendered, so that it isn't taken for a
+/// file of an earlier render that is no longer rendered
+pub(crate) fn keep_file(path: &Path) {
+    if let Some(files) = context::current().tracked.lock().unwrap().as_mut() {
+        files.insert(path.to_path_buf());
+    }
+}
+
+/// Counts and tracks a file that is complete, and adds it to the journal
+fn completed<C: Context + Default>(path: &Path, written: bool) -> Result<(), C> {
+    let context = context::current();
+    match written {
+        true => context.written.fetch_add(1, Ordering::Relaxed),
+        false => context.unchanged.fetch_add(1, Ordering::Relaxed),
+    };
+    if let Some(files) = context.tracked.lock().unwrap().as_mut() {
+        files.insert(path.to_path_buf());
+    }
+    record_completed(&context, path)
+}
+
+fn syncs_writes() -> bool {
+    context::current().sync_writes
+}
+
+pub(crate) struct Journal {
+    dir: PathBuf,
+    /// The canonical path of `dir`, which renders write to, once it exists
+    canonical: Option<PathBuf>,
+    /// Created with the first file that completes, so that a run that fails before it
+    /// wrote anything doesn't leave a journal
+    file: Option<File>,
+}
+
+/// The journal of a render into `dir`, see [`begin_journal`]. It's in the folder, so that
+/// it's gone along with the folder, and renders with `--clear` leave it in place.
+pub fn journal_path(dir: &Path) -> PathBuf {
+    dir.join(JOURNAL_FILE)
+}
+
+/// Starts recording the files that are written into `dir`. Each
+/// file is added to the journal at [`journal_path`] once it's complete, as a line with its
+/// path relative to `dir`. A journal that is still there after a run tells that the run
+/// didn't finish, and which files it wrote, see [`read_journal`]. Does nothing while
+/// writes to `dir` are discarded.
+pub fn begin_journal<C: Context + Default>(dir: &Path) -> Result<(), C> {
+    if writes_discarded_at(dir) {
+        return Ok(());
+    }
+    *context::current().journal.lock().unwrap() = Some(Journal {
+        dir: dir.to_path_buf(),
+        canonical: None,
+        file: None,
+    });
+    Ok(())
+}
+
+/// Stops recording the files that are written, and removes the journal, as the run it
+/// was kept for completed, or failed and reported why
+pub fn end_journal<C: Context + Default>() -> Result<(), C> {
+    let Some(journal) = context::current().journal.lock().unwrap().take() else {
+        return Ok(());
+    };
+    drop(journal.file);
+    // The journal of an earlier run is still there when this one completed no file
+    let path = journal_path(&journal.dir);
+    match path.is_file() {
+        true => path.remove_file(),
+        false => Ok(()),
+    }
+}
+
+/// The files that were recorded in the journal of an earlier render into `dir` that
+/// didn't finish, relative to `dir`, or `None` if there is no journal
+pub fn read_journal<C: Context + Default>(dir: &Path) -> Result<Option<Vec<String>>, C> {
+    let path = journal_path(dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let journal = path.read_to_string::<C>()?;
+    Ok(Some(
+        journal
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    ))
+}
+
+/// Adds a completed file to the journal, if one is kept for a folder it is in
+fn record_completed<C: Context + Default>(context: &RunContext, path: &Path) -> Result<(), C> {
+    let mut journal = context.journal.lock().unwrap();
+    let Some(journal) = journal.as_mut() else {
+        return Ok(());
+    };
+    if journal.canonical.is_none() {
+        journal.canonical = journal.dir.canonicalize().ok();
+    }
+    let Some(relative) = journal
+        .canonical
+        .as_ref()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+    else {
+        return Ok(());
+    };
+    let line = format!("{}\n", crate::manifest::relative_path(relative));
+    if journal.file.is_none() {
+        let path = journal_path(&journal.dir);
+        journal.file = Some(
+            File::create(&path)
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!("Error creating journal at path {}", path.display())
+                })
+                .change_context(C::default())?,
+        );
+    }
+    let file = journal.file.as_mut().unwrap();
+    io::Write::write_all(file, line.as_bytes())
+        .and_then(|_| match context.sync_writes {
+            true => file.sync_data(),
+            false => Ok(()),
+        })
+        .into_report()
+        .attach_printable("Error writing to the journal of the render")
+        .change_context(C::default())
+}
+
+/// The sink of the current context, except for the [scratch dirs](scratch_dir) of a context
+/// that discards writes, which are written to the file system
+fn sink_for(path: &Path) -> Arc<dyn FsSink> {
+    let context = context::current();
+    match context.writes_discarded() && !context.writes_discarded_at(path) {
+        true => Arc::new(RealFs),
+        false => context.sink.clone(),
+    }
+}
 
 pub trait PathExt {
     fn create_dir_all<C: Context + Default>(&self) -> Result<(), C>;
     fn read_to_string<C: Context + Default>(&self) -> Result<String, C>;
-    fn try_create_file<C: Context + Default>(&self, force: bool) -> Result<File, C>;
-    fn create_file<C: Context + Default>(&self) -> Result<File, C> {
+    fn try_create_file<C: Context + Default>(&self, force: bool) -> Result<OutputFile, C>;
+    fn create_file<C: Context + Default>(&self) -> Result<OutputFile, C> {
         self.try_create_file(true)
     }
+    /// Creates the file with `content` and [commits](OutputFile::commit) it
+    fn try_write_file<C: Context + Default>(
+        &self,
+        force: bool,
+        content: impl AsRef<[u8]>,
+    ) -> Result<(), C> {
+        let mut file = self.try_create_file::<C>(force)?;
+        file.write_all::<C>(content)?;
+        file.commit()
+    }
+    fn write_file<C: Context + Default>(&self, content: impl AsRef<[u8]>) -> Result<(), C> {
+        self.try_write_file(true, content)
+    }
+    /// Like [`PathExt::create_file`], for a file with the Unix permission bits `mode`, see
+    /// [`OutputFile::set_mode`]
+    fn create_file_with_mode<C: Context + Default>(&self, mode: u32) -> Result<OutputFile, C> {
+        let mut file = self.create_file::<C>()?;
+        file.set_mode::<C>(mode)?;
+        Ok(file)
+    }
+    /// Like [`PathExt::create_file`], for generated text, see [`TextFile`]
+    fn create_text_file<C: Context + Default>(&self) -> Result<TextFile, C> {
+        Ok(TextFile {
+            file: self.create_file()?,
+            crlf: line_ending() == LineEnding::Crlf,
+        })
+    }
+    /// Like [`PathExt::write_file`], for generated text, see [`TextFile`]
+    fn write_text_file<C: Context + Default>(&self, content: impl AsRef<[u8]>) -> Result<(), C> {
+        let mut file = self.create_text_file::<C>()?;
+        file.write_all::<C>(content.as_ref())?;
+        file.commit()
+    }
     fn open_file<C: Context + Default>(&self) -> Result<File, C>;
     fn get_dir_content<C: Context + Default>(&self) -> Result<DirContent, C>;
-    fn copy<C: Context + Default>(&self, to: impl AsRef<Path>) -> Result<(), C>;
+    fn copy<C: Context + Default>(&self, to: impl AsRef<Path>) -> Result<(), C> {
+        self.copy_with(to, OnConflict::Overwrite).map(|_| ())
+    }
+    /// Copies the file to `to`, doing what `on_conflict` says if another file was copied
+    /// there in the same [`RunContext`], like by the same render. Returns where the file was copied to, or where a file
+    /// with the same content already is.
+    fn copy_with<C: Context + Default>(
+        &self,
+        to: impl AsRef<Path>,
+        on_conflict: OnConflict,
+    ) -> Result<PathBuf, C>;
+    fn remove_file<C: Context + Default>(&self) -> Result<(), C>;
+    /// Removes an empty directory
+    fn remove_dir<C: Context + Default>(&self) -> Result<(), C>;
+    fn remove_dir_all<C: Context + Default>(&self) -> Result<(), C>;
 }
 
 impl<T: AsRef<Path>> PathExt for T {
     fn create_dir_all<C: Context + Default>(&self) -> Result<(), C> {
         let path = self.as_ref();
-        fs::create_dir_all(path)
+        sink_for(path)
+            .create_dir_all(path)
             .into_report()
             .attach_printable_lazy(|| {
                 format!(
@@ -32,12 +1201,19 @@ impl<T: AsRef<Path>> PathExt for T {
                 )
             })
             .change_context(C::default())
+            .attach(ErrorKind::WriteFailed)
     }
 
     fn read_to_string<C: Context + Default>(&self) -> Result<String, C> {
         let path = self.as_ref();
 
-        fs::read_to_string(path)
+        sink_for(path)
+            .read(path)
+            .and_then(|content| {
+                String::from_utf8(content)
+                    .map(normalize_text)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
             .into_report()
             .attach_printable_lazy(|| {
                 format!(
@@ -48,10 +1224,9 @@ impl<T: AsRef<Path>> PathExt for T {
             .change_context(C::default())
     }
 
-    fn try_create_file<C: Context + Default>(&self, force: bool) -> Result<File, C> {
+    fn try_create_file<C: Context + Default>(&self, force: bool) -> Result<OutputFile, C> {
         let path = self.as_ref();
-
-        if path.exists() && !force {
+        if sink_for(path).exists(path) && !force {
             return Err(io::Error::new(
                 io::ErrorKind::AlreadyExists,
                 format!(
@@ -63,7 +1238,8 @@ impl<T: AsRef<Path>> PathExt for T {
             .change_context(C::default());
         }
 
-        File::create(path)
+        sink_for(path)
+            .create_file(path)
             .into_report()
             .attach_printable_lazy(|| {
                 format!(
@@ -72,6 +1248,7 @@ impl<T: AsRef<Path>> PathExt for T {
                 )
             })
             .change_context(C::default())
+            .attach(ErrorKind::WriteFailed)
     }
 
     fn open_file<C: Context + Default>(&self) -> Result<File, C> {
@@ -101,10 +1278,29 @@ impl<T: AsRef<Path>> PathExt for T {
             .change_context(C::default())
     }
 
-    fn copy<C: Context + Default>(&self, to: impl AsRef<Path>) -> Result<(), C> {
+    fn copy_with<C: Context + Default>(
+        &self,
+        to: impl AsRef<Path>,
+        on_conflict: OnConflict,
+    ) -> Result<PathBuf, C> {
         let from = self.as_ref();
-        let to = to.as_ref();
-        fs::copy(from, to)
+        let Some(to) = copy_destination::<C>(from, to.as_ref(), on_conflict)? else {
+            return Ok(to.as_ref().to_path_buf());
+        };
+        let to = to.as_path();
+        let unchanged = skips_unchanged()
+            && same_content(from, to)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error reading file at path {}", from.display()))
+                .change_context(C::default())?;
+        if unchanged {
+            return completed(to, false).map(|_| to.to_path_buf());
+        }
+        if let Some(target) = dangling_target(from) {
+            return Err(dangling_link(from, &target));
+        }
+        sink_for(to)
+            .copy(from, to)
             .into_report()
             .attach_printable_lazy(|| {
                 format!(
@@ -113,9 +1309,412 @@ impl<T: AsRef<Path>> PathExt for T {
                     to = to.to_string_lossy()
                 )
             })
+            .change_context(C::default())
+            .attach(ErrorKind::WriteFailed)?;
+        completed(to, true).map(|_| to.to_path_buf())
+    }
+
+    fn remove_file<C: Context + Default>(&self) -> Result<(), C> {
+        let path = self.as_ref();
+        sink_for(path)
+            .remove_file(path)
+            .into_report()
+            .attach_printable_lazy(|| format!("Error removing file at path {}", path.display()))
+            .change_context(C::default())
+    }
+
+    fn remove_dir<C: Context + Default>(&self) -> Result<(), C> {
+        let path = self.as_ref();
+        sink_for(path)
+            .remove_dir(path)
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Error removing directory at path {}", path.display())
+            })
+            .change_context(C::default())
+    }
+
+    fn remove_dir_all<C: Context + Default>(&self) -> Result<(), C> {
+        let path = self.as_ref();
+        sink_for(path)
+            .remove_dir_all(path)
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Error removing directory at path {}", path.display())
+            })
+            .change_context(C::default())
+    }
+}
+
+/// A file found by [`walk_dir`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirEntry {
+    File(PathBuf),
+    /// A symbolic link that is listed as a link rather than followed, with the target as
+    /// it's written in the link
+    Symlink {
+        path: PathBuf,
+        target: PathBuf,
+    },
+}
+
+impl DirEntry {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Symlink { path, .. } => path,
+        }
+    }
+}
+
+/// The files in `dir` and its subfolders, in order of their paths. Symbolic links are
+/// followed: a link to a file is listed like the file, and the files in a linked folder
+/// under the link. With `preserve_symlinks`, links are listed as [`DirEntry::Symlink`]
+/// instead.
+///
+/// Fails on links that point to nothing, naming the link and its target, and on links to
+/// a folder they are in, which would make the walk endless.
+pub fn walk_dir<C: Context + Default>(
+    dir: &Path,
+    preserve_symlinks: bool,
+) -> Result<Vec<DirEntry>, C> {
+    let mut entries = vec![];
+    let mut ancestors = vec![canonicalize::<C>(dir)?];
+    walk(dir, preserve_symlinks, true, &mut ancestors, &mut entries)?;
+    Ok(entries)
+}
+
+fn walk<C: Context + Default>(
+    dir: &Path,
+    preserve_symlinks: bool,
+    recursive: bool,
+    ancestors: &mut Vec<PathBuf>,
+    entries: &mut Vec<DirEntry>,
+) -> Result<(), C> {
+    let error = |path: &Path| format!("Error reading folder {}", path.display());
+    let mut paths = fs::read_dir(dir)
+        .and_then(|read| {
+            read.map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .into_report()
+        .attach_printable_lazy(|| error(dir))
+        .change_context(C::default())?;
+    paths.sort();
+    for path in paths {
+        let metadata = fs::symlink_metadata(&path)
+            .into_report()
+            .attach_printable_lazy(|| error(dir))
+            .change_context(C::default())?;
+        let is_dir = match metadata.file_type().is_symlink() {
+            true => {
+                let target = fs::read_link(&path)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error reading symbolic link {}", path.display())
+                    })
+                    .change_context(C::default())?;
+                if preserve_symlinks {
+                    entries.push(DirEntry::Symlink { path, target });
+                    continue;
+                }
+                match fs::metadata(&path) {
+                    Ok(resolved) => resolved.is_dir(),
+                    Err(_) => return Err(dangling_link(&path, &target)),
+                }
+            }
+            false => metadata.is_dir(),
+        };
+        if !is_dir {
+            entries.push(DirEntry::File(path));
+            continue;
+        }
+        if !recursive {
+            continue;
+        }
+        let canonical = canonicalize::<C>(&path)?;
+        if let Some(ancestor) = ancestors.iter().find(|a| **a == canonical) {
+            return Err(Report::new(C::default()).attach_printable(format!(
+                "Symbolic link {} points to {}, which contains it, so its files would be copied endlessly",
+                path.display(),
+                ancestor.display()
+            )));
+        }
+        ancestors.push(canonical);
+        walk(&path, preserve_symlinks, recursive, ancestors, entries)?;
+        ancestors.pop();
+    }
+    Ok(())
+}
+
+fn canonicalize<C: Context + Default>(path: &Path) -> Result<PathBuf, C> {
+    path.canonicalize()
+        .into_report()
+        .attach_printable_lazy(|| format!("Error resolving path {}", path.display()))
+        .change_context(C::default())
+}
+
+/// The target of `path` if it's a symbolic link that points to nothing, or to a chain of
+/// links that doesn't end
+fn dangling_target(path: &Path) -> Option<PathBuf> {
+    let target = fs::read_link(path).ok()?;
+    fs::metadata(path).is_err().then_some(target)
+}
+
+fn dangling_link<C: Context + Default>(link: &Path, target: &Path) -> Report<C> {
+    // Relative targets are relative to the folder of the link
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    let problem = match fs::symlink_metadata(&resolved) {
+        Ok(_) => "a symbolic link that doesn't lead to a file or folder",
+        Err(_) => "which doesn't exist",
+    };
+    Report::new(C::default()).attach_printable(format!(
+        "Symbolic link {} points to {}, {problem}",
+        link.display(),
+        resolved.display()
+    ))
+}
+
+/// What [`PathExt::copy_with`] does when another file was already copied to its
+/// destination. Files with the same content never conflict, and are copied once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Replace the other file, so that the last copy wins
+    #[default]
+    Overwrite,
+    /// Fail, naming both files
+    Error,
+    /// Copy the file next to the other one, with a hash of its content added to its name,
+    /// like `logo-0badc0de.png`
+    Rename,
+}
+
+/// Where `from` is copied to when it goes to `to`, or `None` if a file with the same
+/// content was copied there already
+fn copy_destination<C: Context + Default>(
+    from: &Path,
+    to: &Path,
+    on_conflict: OnConflict,
+) -> Result<Option<PathBuf>, C> {
+    let context = context::current();
+    let mut copies = context.copies.lock().unwrap();
+    let first = match copies.get(to) {
+        Some(first) if on_conflict != OnConflict::Overwrite && first != from => first.clone(),
+        _ => {
+            copies.insert(to.to_path_buf(), from.to_path_buf());
+            return Ok(Some(to.to_path_buf()));
+        }
+    };
+    let same = File::open(from)
+        .and_then(|from| same_bytes(from, File::open(&first)?))
+        .into_report()
+        .attach_printable_lazy(|| {
+            format!(
+                "Error comparing files at paths {} and {}",
+                first.display(),
+                from.display()
+            )
+        })
+        .change_context(C::default())?;
+    if same {
+        return Ok(None);
+    }
+    if on_conflict == OnConflict::Error {
+        return Err(Report::new(C::default())
+            .attach_printable(format!(
+                "Files {} and {} would both be copied to {}, but have different content",
+                first.display(),
+                from.display(),
+                to.display()
+            ))
+            .attach(ErrorKind::CopyConflict));
+    }
+    let stem = to.file_stem().unwrap_or_default().to_string_lossy();
+    let hash = hash_file(from)
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading file at path {}", from.display()))
+        .change_context(C::default())?;
+    let hash = &hash[..8];
+    let renamed = to.with_file_name(match to.extension() {
+        Some(extension) => format!("{stem}-{hash}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{hash}"),
+    });
+    // The same content was renamed to the same name before
+    if copies.contains_key(&renamed) {
+        return Ok(None);
+    }
+    copies.insert(renamed.clone(), from.to_path_buf());
+    Ok(Some(renamed))
+}
+
+/// What [`copy_tree`] does with files that are already at their destination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Replace them, like [`PathExt::copy`] does
+    #[default]
+    Overwrite,
+    /// Keep them, leaving their source files uncopied
+    Skip,
+    /// Fail before anything is copied
+    Error,
+}
+
+/// Which files [`copy_tree`] copies, and where to
+#[derive(Debug, Default)]
+pub struct CopyTreeOptions<'a> {
+    /// Whether the files in subfolders are copied too, rather than only those in the source
+    /// folder itself
+    pub recursive: bool,
+    /// Globs, relative to the source folder, of the files to copy. All files are copied when
+    /// there are none. `*` doesn't match `/`, so that `**/*.png` is needed for images in
+    /// subfolders.
+    pub include: &'a [&'a str],
+    /// Globs, relative to the source folder, of files not to copy even if they are included
+    pub exclude: &'a [&'a str],
+    /// Whether files keep their path relative to [`Self::root`] under the destination
+    /// folder. Otherwise they all go into the destination folder itself, and two files with
+    /// the same name are an error.
+    pub preserve_structure: bool,
+    /// The folder paths are kept relative to, which must contain the source folder.
+    /// Defaults to the source folder.
+    pub root: Option<&'a Path>,
+    pub on_existing: OnExisting,
+    /// What is done with files that go where another file was copied to before, see
+    /// [`PathExt::copy_with`]
+    pub on_conflict: OnConflict,
+    /// Whether symbolic links are copied as links to the same target, rather than copying
+    /// what they point to. Only on Unix: elsewhere, creating links takes privileges, so
+    /// they are always followed.
+    pub preserve_symlinks: bool,
+}
+
+/// Copies the files in `src_dir` into `dst_dir`, creating the folders they go into. Returns
+/// the destinations of the files that were copied, in order of their source paths. Writes
+/// go through [`PathExt`], so they are discarded or recorded like its own.
+pub fn copy_tree<C: Context + Default>(
+    src_dir: &Path,
+    dst_dir: &Path,
+    options: &CopyTreeOptions,
+) -> Result<Vec<PathBuf>, C> {
+    let globs = |patterns: &[&str]| -> Result<GlobSet, C> {
+        let mut set = GlobSetBuilder::new();
+        for pattern in patterns {
+            set.add(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .into_report()
+                    .attach_printable_lazy(|| format!("Error parsing glob '{pattern}'"))
+                    .change_context(C::default())?,
+            );
+        }
+        set.build().into_report().change_context(C::default())
+    };
+    let include = globs(options.include)?;
+    let exclude = globs(options.exclude)?;
+    let root = options.root.unwrap_or(src_dir);
+    let Ok(under_root) = src_dir.strip_prefix(root) else {
+        return Err(Report::new(C::default()).attach_printable(format!(
+            "Folder {} isn't in {}, so its files can't keep their path relative to it",
+            src_dir.display(),
+            root.display()
+        )));
+    };
+
+    let mut entries = vec![];
+    let mut ancestors = vec![canonicalize::<C>(src_dir)?];
+    walk::<C>(
+        src_dir,
+        options.preserve_symlinks && cfg!(unix),
+        options.recursive,
+        &mut ancestors,
+        &mut entries,
+    )?;
+    let mut copies = vec![];
+    let mut flattened: HashMap<OsString, PathBuf> = HashMap::new();
+    for entry in entries {
+        let file = entry.path();
+        let relative = file.strip_prefix(src_dir).unwrap();
+        if (!options.include.is_empty() && !include.is_match(relative))
+            || exclude.is_match(relative)
+        {
+            continue;
+        }
+        let to = match options.preserve_structure {
+            true => dst_dir.join(under_root).join(relative),
+            false => {
+                let name = relative.file_name().unwrap_or_default().to_os_string();
+                if let Some(first) = flattened.get(&name) {
+                    return Err(Report::new(C::default())
+                        .attach_printable(format!(
+                            "Files {} and {} would both be copied to {}",
+                            first.display(),
+                            file.display(),
+                            dst_dir.join(&name).display()
+                        ))
+                        .attach(ErrorKind::CopyConflict));
+                }
+                flattened.insert(name.clone(), file.to_path_buf());
+                dst_dir.join(name)
+            }
+        };
+        copies.push((entry, to));
+    }
+
+    // Links that point to nothing are still there
+    let exists = |path: &Path| fs::symlink_metadata(path).is_ok();
+    match options.on_existing {
+        OnExisting::Overwrite => {}
+        OnExisting::Skip => copies.retain(|(_, to)| !exists(to)),
+        OnExisting::Error => {
+            if let Some((from, to)) = copies.iter().find(|(_, to)| exists(to)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "Path at {to} already exists, so {from} can't be copied to it",
+                        to = to.to_string_lossy(),
+                        from = from.path().to_string_lossy()
+                    ),
+                ))
+                .into_report()
+                .change_context(C::default());
+            }
+        }
+    }
+    let mut destinations = vec![];
+    for (from, to) in copies {
+        if let Some(parent) = to.parent() {
+            parent.create_dir_all::<C>()?;
+        }
+        match from {
+            DirEntry::File(from) => {
+                destinations.push(from.copy_with::<C>(to, options.on_conflict)?)
+            }
+            DirEntry::Symlink { target, .. } => {
+                symlink::<C>(&target, &to)?;
+                destinations.push(to);
+            }
+        }
+    }
+    Ok(destinations)
+}
+
+/// Creates a symbolic link at `link` that points to `target`, unless the link is there
+fn symlink<C: Context + Default>(target: &Path, link: &Path) -> Result<(), C> {
+    let unchanged = skips_unchanged() && fs::read_link(link).is_ok_and(|t| t == target);
+    if !unchanged {
+        sink_for(link)
+            .symlink(target, link)
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!(
+                    "Error creating symbolic link {} to {}",
+                    link.display(),
+                    target.display()
+                )
+            })
             .change_context(C::default())?;
-        Ok(())
     }
+    completed(link, !unchanged)
 }
 
 pub trait WriteExt {
@@ -0,0 +1,123 @@
+//! Reading JSON that is handed to modmod, like the package.json stub of the slides, strictly
+//! enough that mistakes in it are reported rather than silently merged.
+//!
+//! `serde_json` keeps the last of duplicate keys in an object, so a stub that defines a key
+//! twice would lose the first without a word. [`from_str`] refuses those, and errors say
+//! where the value that is wrong is with a [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901),
+//! like `/scripts/build`.
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+/// Parses `content` as a single JSON value, failing on duplicate keys in objects and on
+/// anything after the value but whitespace
+pub(crate) fn from_str(content: &str) -> serde_json::Result<Value> {
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    let value = Unique {
+        pointer: String::new(),
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// The JSON pointer of `key` in the value at `pointer`
+pub(crate) fn pointer(pointer: &str, key: &str) -> String {
+    format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// The root of a JSON pointer is the empty string, which is shown as `/`
+pub(crate) fn display_pointer(pointer: &str) -> &str {
+    match pointer {
+        "" => "/",
+        pointer => pointer,
+    }
+}
+
+/// What kind of value `value` is, like `an array`, for errors that say what was found
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Deserializes the value at `pointer`, failing on duplicate keys
+struct Unique {
+    pointer: String,
+}
+
+impl<'de> DeserializeSeed<'de> for Unique {
+    type Value = Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Unique {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element_seed(Unique {
+            pointer: pointer(&self.pointer, &values.len().to_string()),
+        })? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut object = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let pointer = pointer(&self.pointer, &key);
+            if object.contains_key(&key) {
+                return Err(de::Error::custom(format!(
+                    "duplicate key '{key}' at {pointer}"
+                )));
+            }
+            let value = map.next_value_seed(Unique { pointer })?;
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
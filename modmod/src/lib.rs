@@ -1,124 +1,1857 @@
+pub mod analytics;
+pub mod anki;
+pub mod archive;
 mod book;
+pub mod cache;
+mod cargo_workspace;
+pub mod cartridge;
+pub mod catalog;
+pub mod changes;
+pub mod check;
+pub mod classroom;
+mod conditional;
+pub mod container;
+pub mod context;
+pub mod course_catalog;
+mod course_manifest;
+mod credits;
+pub mod date;
+pub mod deploy;
+pub mod diagnostic;
+pub mod docx;
+mod estimates;
+pub mod exercise_test;
 mod exercises;
+pub mod external_links;
+pub mod failure;
+pub mod format;
+mod frontmatter;
+mod git;
+pub mod graph;
+pub mod handout;
+pub mod i18n;
+mod instructor;
 pub mod io;
+mod json;
+pub mod link_check;
+pub mod lint;
+pub mod list;
 pub mod load;
+pub mod lock;
+pub mod log;
+pub mod manifest;
+pub mod migrate;
+pub mod parallel;
 pub mod patch;
+mod pdf;
+pub mod progress;
+pub mod publish;
+pub mod qr;
+pub mod quiz;
+pub mod report;
+mod scaffold;
+pub mod schedule;
+pub mod scorm;
+pub mod search;
+mod sha256;
+mod sitemap;
 mod slides;
+pub mod slug;
+mod solutions;
+pub mod stats;
+pub mod suggest;
+mod summary;
+pub mod template;
+mod thumbnail;
+mod toolchain;
+pub mod update;
+pub mod url;
+pub mod version;
 
 use self::{
+    analytics::Analytics,
     book::{Book, BookBuilder, ChapterBuilder, SectionBuilder},
-    load::{Load, TrackDef},
+    load::{Loader, TrackDef},
+    report::{RenderReport, Reporter, Warning, WarningCode},
+    update::{FileHashes, LocalEdits},
 };
 use book::BookRenderOptions;
+use conditional::Conditions;
+use course_manifest::CourseManifestOptions;
+use date::Date;
 use error_stack::{IntoReport, Report, Result, ResultExt};
+use estimates::Estimate;
 use exercises::{
     ExerciseCollection, ExerciseCollectionBuilder, ModuleExercisesBuilder, UnitExercisesBuilder,
 };
 use io::PathExt;
 use load::Indexed;
-use slides::{SlideDeckBuilder, SlidesPackage, SlidesPackageBuilder};
+use manifest::{ArtifactKind, RenderManifest};
+use progress::{Phase, Timing};
+use quiz::{QuizMode, QuizQuestion};
+use search::{SearchIndex, SearchIndexOptions};
+use serde::{Deserialize, Serialize};
+use slides::{SlideDeckBuilder, SlidesPackage, SlidesPackageBuilder, SlidesRenderOptions};
 use std::{
-    fmt::{self, Display},
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::{self, Display, Write},
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
 };
+use url::BaseUrl;
+use version::CourseVersion;
 
-pub use slides::SlidesRenderOptions;
+/// The version of modmod, which is recorded in the generated output
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub struct TrackRenderOptions<'t, 'u, O: AsRef<Path>, P: AsRef<Path>> {
-    pub out_dir: O,
-    pub slide_opts: SlidesRenderOptions<'t, 'u, P>,
+/// How a track is rendered by [`Track::render`]. Options that are added later get a default
+/// that renders as before, so start from [`RenderOptions::new`] and set the ones to change.
+/// The options can be read from a configuration file, in which every one is optional.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RenderOptions {
+    /// The folder the track is rendered into
+    pub out_dir: PathBuf,
+    /// The Slidev theme of the decks: the name of a theme package, or the path of a theme
+    /// folder if it starts with `.` or is absolute, which is copied into the slides package
+    pub theme: String,
+    /// The `package.json` the one of the slides package is made from, instead of the
+    /// built-in one
+    pub package_json: Option<PathBuf>,
+    /// The URL the output folder is deployed at
+    pub base_url: BaseUrl,
+    /// Fail if the track doesn't match its lock file, see [`lock`]
+    pub locked: bool,
+    /// Replace the files of an earlier render in the output folder. Without it, the output
+    /// folder must be empty.
     pub clear_output_dir: bool,
+    /// With `clear_output_dir`, render into a folder that isn't empty and holds no earlier
+    /// output of modmod, see [`update::is_output_dir`]. Without it, such a folder is refused,
+    /// as the files in it that aren't rendered would be removed.
+    pub force: bool,
+    /// Leave out topics and exercises marked as optional
+    pub skip_optional: bool,
+    /// The profile to render the track for. Must be one of the profiles the track defines.
+    pub profile: Option<String>,
+    /// Don't warn about gaps in the numbering of modules and units
+    pub allow_index_gaps: bool,
+    /// Only render the units matching one of these selectors, or all of them if empty.
+    /// See [`Unit::matches`]. Unlike with [`Track::select_units`], the units keep their
+    /// numbers, so that they can be rendered into the output of the whole track, like
+    /// `--changed-since` does.
+    pub units: Vec<String>,
+    /// Write a search index of the rendered slides and book, with a page to search it
+    pub search_index: Option<SearchIndexOptions>,
+    /// Folders that override those of the output layout of the track
+    pub layout: OutputLayout,
+    /// List near-duplicate objectives of a unit once, like exact duplicates
+    pub merge_similar_objectives: bool,
+    /// The parts of the output that are rendered. Output that links to parts that are
+    /// not rendered leaves those links out.
+    pub targets: Vec<RenderTarget>,
+    /// The version of the course to render the track as, instead of the one of the track
+    pub course_version: Option<String>,
+    /// Accept any course version, not only loosely semver ones. See [`CourseVersion`].
+    pub free_form_version: bool,
+    /// Settings of the track that are overridden for this render only
+    pub overrides: RenderOverrides,
+    /// Warn about images and exercise files that are larger than this many bytes, see
+    /// [`report::DEFAULT_MAX_ASSET_SIZE`]
+    pub max_asset_size: Option<u64>,
+    /// Warn about topics and exercise descriptions that are larger than this many bytes,
+    /// see [`report::DEFAULT_MAX_SECTION_SIZE`]
+    pub max_section_size: Option<u64>,
+    /// Replace the `rust-toolchain.toml`, devcontainer and deploy workflow files in the output,
+    /// even if they weren't generated by modmod
+    pub force_scaffold: bool,
+    /// Write a workflow for this CI service into the output folder, which builds the slides
+    /// and the book and deploys them, see [`deploy`]
+    pub deploy_workflow: Option<deploy::CiProvider>,
+    /// Write a Dockerfile into the output folder that builds the slides and the book and
+    /// serves them, see [`container`]
+    pub container: bool,
+    /// Write a description of the course for websites and learning management systems into
+    /// the output folder, as `course.json`
+    pub course_json: bool,
+    /// Write the lecture and exercise time of each module and unit into the output folder,
+    /// as `schedule.md`
+    pub schedule_md: bool,
+    /// How the quizzes of the topics are rendered into the book, see [`quiz`]
+    pub quiz: QuizMode,
+    /// Write the speaker notes of the rendered decks into a document per module
+    pub instructor_notes: bool,
+    /// Write a document per rendered deck for trainers, with the objectives, summary, speaker
+    /// notes and exercises of its unit
+    pub instructor_handout: bool,
+    /// Export a preview of the title slide of each deck, for the landing page and the
+    /// catalog
+    pub thumbnails: bool,
+    /// Also write the decks into one deck, `slides/all.md`, with an `export-all` script that
+    /// exports it as one PDF
+    pub export_combined: bool,
+    /// What the QR codes on the slides of each deck link to, see [`qr`]
+    pub qr_codes: Vec<qr::QrTarget>,
+    /// Write the credits of the topics and exercises into the root of each output
+    pub credits: bool,
+    /// Write the slides of the topics into the book pages of their units, with their
+    /// objectives, summary and further reading, as notes to read after the lectures
+    pub book_notes: bool,
+    /// Also render the exercise packages with their solutions, into the solutions folder of
+    /// the layout
+    pub with_solutions: bool,
+    /// Render the track in this locale, from its translations, see [`Track::localized`]. The
+    /// slides, book, exercises and solutions folders get the locale as suffix, like
+    /// `slides-nl`, unless it is the locale of the track.
+    pub locale: Option<String>,
 }
 
-#[derive(Debug)]
+impl RenderOptions {
+    /// The default options, rendering into `out_dir`
+    pub fn new(out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            out_dir: out_dir.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            out_dir: PathBuf::new(),
+            theme: "teach-rs".to_string(),
+            package_json: None,
+            base_url: BaseUrl::root(),
+            locked: false,
+            clear_output_dir: false,
+            force: false,
+            skip_optional: false,
+            profile: None,
+            allow_index_gaps: false,
+            units: vec![],
+            search_index: None,
+            layout: OutputLayout::default(),
+            merge_similar_objectives: false,
+            targets: RenderTarget::ALL.to_vec(),
+            course_version: None,
+            free_form_version: false,
+            overrides: RenderOverrides::default(),
+            max_asset_size: Some(report::DEFAULT_MAX_ASSET_SIZE),
+            max_section_size: Some(report::DEFAULT_MAX_SECTION_SIZE),
+            force_scaffold: false,
+            deploy_workflow: None,
+            container: false,
+            course_json: false,
+            schedule_md: false,
+            quiz: QuizMode::default(),
+            instructor_notes: false,
+            instructor_handout: false,
+            thumbnails: false,
+            export_combined: false,
+            qr_codes: vec![],
+            credits: false,
+            book_notes: false,
+            with_solutions: false,
+            locale: None,
+        }
+    }
+}
+
+/// Settings of the track that are overridden for a single render, like for a one-off
+/// delivery in another style, without editing the track. They are recorded in the render
+/// manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderOverrides {
+    /// Slide template for the units that don't set their own, instead of the default slide
+    /// template of the track. Units that set their own keep it, with a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slide_template: Option<PathBuf>,
+    /// Folder with an mdbook theme, that is copied into the book as its `theme` folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book_theme: Option<PathBuf>,
+}
+
+impl RenderOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A part of the output of a track, that can be rendered without the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderTarget {
+    Slides,
+    Book,
+    Exercises,
+}
+
+impl RenderTarget {
+    pub const ALL: [Self; 3] = [Self::Slides, Self::Book, Self::Exercises];
+}
+
+impl fmt::Display for RenderTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Slides => "slides",
+            Self::Book => "book",
+            Self::Exercises => "exercises",
+        })
+    }
+}
+
+impl std::str::FromStr for RenderTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|target| target.to_string() == s)
+            .ok_or_else(|| format!("unknown target '{s}', expected slides, book or exercises"))
+    }
+}
+
+/// What to do when content is missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Fail loading the track
+    #[default]
+    Strict,
+    /// Report a warning and render placeholder content
+    Lenient,
+}
+
+/// Decides which units, topics and exercises end up in the rendered output. By default,
+/// that's everything but content restricted to profiles.
+#[derive(Debug, Clone, Copy, Default)]
+struct ContentFilter<'a> {
+    skip_optional: bool,
+    profile: Option<&'a str>,
+    units: &'a [String],
+}
+
+impl ContentFilter<'_> {
+    fn includes_unit(&self, unit: &Unit) -> bool {
+        self.includes(false, &unit.profiles)
+            && (self.units.is_empty() || self.units.iter().any(|s| unit.matches(s)))
+    }
+
+    /// Content restricted to certain profiles is only included when rendering one of them
+    fn includes(&self, optional: bool, profiles: &[String]) -> bool {
+        if self.skip_optional && optional {
+            return false;
+        }
+        profiles.is_empty()
+            || self
+                .profile
+                .is_some_and(|profile| profiles.iter().any(|p| p == profile))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Track {
     pub name: String,
+    /// What the track is about, as set in the track definition
+    pub description: Option<String>,
+    /// The version of the course, as set in the track definition
+    pub version: Option<String>,
     pub modules: Vec<Indexed<Module>>,
+    /// How the modules are numbered in titles and output paths
+    pub module_numbering: ModuleNumbering,
+    pub orphan_ignore: Vec<String>,
+    /// Globs of external links that aren't requested when checking the track
+    pub external_links_ignore: Vec<String>,
+    /// Names of the variants this track can be rendered in
+    pub profiles: Vec<String>,
+    /// Slide template used for units that don't define their own
+    pub default_slide_template: Option<PathBuf>,
+    /// Slide templates units can refer to by name
+    pub templates: BTreeMap<String, PathBuf>,
+    /// Values conditional content blocks can refer to
+    pub variables: BTreeMap<String, String>,
+    /// Information about the course this track is taught in
+    pub course: Course,
+    pub license: Option<License>,
+    /// The analytics service the deployed course reports its usage to, see [`analytics`]
+    pub analytics: Option<Analytics>,
+    /// The tools the exercises are worked on with
+    pub toolchain: Option<Toolchain>,
+    /// Where the output is written, relative to the output folder
+    pub output: OutputLayout,
+    /// The locale the content of the track is written in, like `en`
+    pub locale: String,
+    /// The locales the content of the track is translated to, see [`Track::localized`]
+    pub translations: BTreeSet<String>,
+    /// How summaries are derived for the topics that don't have one, if they are
+    pub derive_summaries: Option<DeriveSummaries>,
+    /// Problems that were tolerated while loading the track leniently
+    pub warnings: Vec<Warning>,
+    /// Path to the definition file of this track
+    pub definition: PathBuf,
+    /// The folder the content paths of the track definition are relative to
+    pub content_root: PathBuf,
+    /// Paths to the definitions this track extends, nearest base first
+    pub bases: Vec<PathBuf>,
+    /// Library crates exercises can depend on
+    pub shared_crates: Vec<SharedCrate>,
+    /// The tags units are allowed to have. Units can have any tag if empty.
+    pub unit_tags: Vec<String>,
+    /// Whether search engines may index the deployed site, as the `robots.txt` written into
+    /// the output tells them
+    pub indexable: bool,
+    /// How long loading the track took
+    pub load_duration: Duration,
 }
 
 impl Track {
+    /// Loads the track defined at `path`, with the modules, units and topics it refers to
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadTrackError> {
+        Self::load_toml_def(path)
+    }
+
     pub fn load_toml_def(path: impl AsRef<Path>) -> Result<Self, LoadTrackError> {
-        let def = TrackDef::load(path.as_ref(), None).change_context(LoadTrackError)?;
-        def.resolve().change_context(LoadTrackError)
+        Self::load_toml_def_with(path, &mut Loader::new())
+    }
+
+    /// Load several tracks, sharing the parsed modules and topics between them.
+    /// A failure to load one track does not affect the others.
+    pub fn load_toml_defs<P: AsRef<Path>>(
+        paths: &[P],
+        policy: FailurePolicy,
+    ) -> Vec<Result<Self, LoadTrackError>> {
+        Self::load_toml_defs_with(paths, Loader::with_policy(policy))
     }
 
-    pub fn render<O: AsRef<Path>, P: AsRef<Path>>(
+    /// Load several tracks with the given loader, sharing the parsed modules and topics between them
+    pub fn load_toml_defs_with<P: AsRef<Path>>(
+        paths: &[P],
+        mut loader: Loader,
+    ) -> Vec<Result<Self, LoadTrackError>> {
+        let _phase = progress::start(Phase::Load, None);
+        paths
+            .iter()
+            .map(|path| Self::load_toml_def_with(path, &mut loader))
+            .collect()
+    }
+
+    /// Iterate over all topics in the track, in order. Topics that are
+    /// included in more than one unit are yielded more than once.
+    pub fn topics(&self) -> impl Iterator<Item = &Topic> {
+        self.modules
+            .iter()
+            .flat_map(|m| m.data.units.iter())
+            .flat_map(|u| u.data.topics.iter())
+            .map(|t| &t.data)
+    }
+
+    /// Iterate over all units in the track, in order
+    pub fn units(&self) -> impl Iterator<Item = &Unit> {
+        self.modules
+            .iter()
+            .flat_map(|m| m.data.units.iter())
+            .map(|u| &u.data)
+    }
+
+    /// Iterate over all exercises in the track, in order. Exercises whose topic
+    /// is included in more than one unit are yielded more than once.
+    pub fn exercises(&self) -> impl Iterator<Item = &Exercise> {
+        self.topics()
+            .flat_map(|t| t.exercises.iter())
+            .map(|e| &e.data)
+    }
+
+    /// Keeps only the units picked by `selectors`, and numbers the modules and units that are
+    /// left again from one, so that the output has no gaps where the others were. A selector
+    /// picks all units of a module by its label or id, like `B`, a unit by the label of its
+    /// module and its number, like `B.2`, or units by their id or tag, see [`Unit::matches`].
+    /// Modules with a label of their own keep it.
+    pub fn select_units(&mut self, selectors: &[String]) -> Result<(), LoadTrackError> {
+        for selector in selectors {
+            let found = self
+                .modules
+                .iter()
+                .any(|m| m.data.units.iter().any(|u| m.data.selects(u, selector)));
+            if !found {
+                let tags: BTreeSet<String> = self
+                    .units()
+                    .flat_map(|u| u.tags.iter().map(|t| format!("tag:{t}")))
+                    .collect();
+                let candidates = self
+                    .modules
+                    .iter()
+                    .flat_map(|m| {
+                        let module = &m.data;
+                        let units = module.units.iter().flat_map(|u| {
+                            [format!("{}.{}", module.label.path, u.index), u.data.id()]
+                        });
+                        [module.label.path.clone(), module.id()]
+                            .into_iter()
+                            .chain(units)
+                    })
+                    .chain(tags);
+                return Err(Report::new(LoadTrackError).attach_printable(format!(
+                    "Unit selector '{selector}' matches no modules or units.{}",
+                    suggest::did_you_mean(selector, candidates)
+                )));
+            }
+        }
+        let numbering = self.module_numbering;
+        let modules = std::mem::take(&mut self.modules);
+        self.modules = modules
+            .into_iter()
+            .filter_map(|mut module| {
+                let units = std::mem::take(&mut module.data.units);
+                module.data.units = units
+                    .into_iter()
+                    .filter(|u| selectors.iter().any(|s| module.data.selects(u, s)))
+                    .collect();
+                (!module.data.units.is_empty()).then_some(module)
+            })
+            .zip(1..)
+            .map(|(mut module, index)| {
+                if module.data.label == ModuleLabel::new(numbering, module.index, None) {
+                    module.data.label = ModuleLabel::new(numbering, index, None);
+                }
+                module.index = index;
+                for (unit, index) in module.data.units.iter_mut().zip(1..) {
+                    unit.index = index;
+                }
+                module
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// The slide template used for the deck of a unit. `None` means the built-in template is used.
+    pub fn slide_template<'me>(&'me self, unit: &'me Unit) -> Option<&'me Path> {
+        unit.template
+            .as_deref()
+            .or(self.default_slide_template.as_deref())
+    }
+
+    fn load_toml_def_with(
+        path: impl AsRef<Path>,
+        loader: &mut Loader,
+    ) -> Result<Self, LoadTrackError> {
+        let start = Instant::now();
+        let _span = tracing::info_span!("track", label = %path.as_ref().display()).entered();
+        let def = TrackDef::load_source(path.as_ref()).change_context(LoadTrackError)?;
+        let mut track = def.resolve(loader).change_context(LoadTrackError)?;
+        track.load_duration = start.elapsed();
+        Ok(track)
+    }
+
+    /// Renders the track as set in `options`. The warnings of the render are passed to
+    /// `reporter` when it's done, and are in the returned report as well.
+    ///
+    /// ```
+    /// use modmod::{context::RunContext, report::Warning, RenderOptions, Track};
+    ///
+    /// let _context = RunContext::default().enter();
+    /// let track = Track::load("tests/fixtures/basic/basic.track.toml")?;
+    /// let warnings: Vec<Warning> = track.validate();
+    /// let out_dir = std::env::temp_dir().join(format!("preview-{}", std::process::id()));
+    /// let mut options = RenderOptions::new(&out_dir);
+    /// options.clear_output_dir = true;
+    /// let report = track.render(&options, &mut ())?;
+    /// println!("{report}");
+    /// # std::fs::remove_dir_all(&out_dir).unwrap();
+    /// # Ok::<(), error_stack::Report<modmod::LoadTrackError>>(())
+    /// ```
+    pub fn render(
         &self,
-        TrackRenderOptions {
+        options: &RenderOptions,
+        reporter: &mut dyn Reporter,
+    ) -> Result<RenderReport, LoadTrackError> {
+        if options.locked {
+            self.verify_lock().change_context(LoadTrackError)?;
+        }
+        if options.clear_output_dir
+            && !options.force
+            && !update::is_output_dir(&options.out_dir).change_context(LoadTrackError)?
+        {
+            return Err(Report::new(LoadTrackError).attach_printable(format!(
+                "{} isn't empty, and has no {} of an earlier render, so it may not be an output folder. Rendering into it with --clear would overwrite its files and remove the ones that aren't rendered. Pass --force to do so anyway, or use `modmod update`, which keeps files that aren't generated",
+                options.out_dir.display(),
+                update::HASHES_FILE
+            )));
+        }
+        let out_dir = options.out_dir.as_path();
+        let writes = !context::current().writes_discarded_at(out_dir);
+        // The files changed by hand since the last render are saved before they're
+        // overwritten, and put back after, also when the render fails
+        let edits = match (options.clear_output_dir && writes, options.force) {
+            (true, false) => {
+                let edits = LocalEdits::of_dir(out_dir).change_context(LoadTrackError)?;
+                edits.save(out_dir).change_context(LoadTrackError)?;
+                Some(edits)
+            }
+            (true, true) => Some(LocalEdits::default()),
+            (false, _) => None,
+        };
+        let result = {
+            // The files, timings and cache of this render are kept apart from those of others
+            let _context = context::current().for_render().enter();
+            match options.locale.as_deref().filter(|l| *l != self.locale) {
+                Some(locale) => self
+                    .localized(locale)
+                    .and_then(|track| track.render_with(options.clone())),
+                None => self.render_with(options.clone()),
+            }
+        };
+        let mut report = match result {
+            Ok(report) => report,
+            Err(error) => {
+                let Some(edits) = edits else {
+                    return Err(error);
+                };
+                return Err(match edits.put_back(out_dir) {
+                    Ok(()) => error,
+                    Err(e) => error.attach_printable(format!(
+                        "The files that were changed locally couldn't be put back, they are saved in {}: {e:?}",
+                        out_dir.join(update::EDITS_DIR).display()
+                    )),
+                });
+            }
+        };
+        // Lets the next render and `update` tell local changes apart from changes in the
+        // generated output
+        if writes {
+            let mut hashes = FileHashes::of_dir(out_dir).change_context(LoadTrackError)?;
+            if let Some(edits) = edits.filter(|edits| !edits.is_empty()) {
+                report.local_edits = Some(
+                    edits
+                        .restore(out_dir, &mut hashes)
+                        .change_context(LoadTrackError)?,
+                );
+            }
+            hashes.write(out_dir).change_context(LoadTrackError)?;
+        }
+        // Those of loading the track first, then those of each kind of output, each in the
+        // order of the track
+        let mut warnings: Vec<_> = report.warnings.iter().collect();
+        warnings.sort_by_key(|w| w.artifact);
+        warnings.into_iter().for_each(|w| reporter.warning(w));
+        reporter.rendered(&report);
+        Ok(report)
+    }
+
+    fn render_with(
+        &self,
+        RenderOptions {
             out_dir,
-            slide_opts,
+            theme,
+            package_json,
+            base_url,
+            locked: _,
             clear_output_dir,
-        }: TrackRenderOptions<'_, '_, O, P>,
-    ) -> Result<(), LoadTrackError> {
-        let out_dir = out_dir.as_ref();
+            force: _,
+            skip_optional,
+            profile,
+            allow_index_gaps,
+            units,
+            search_index,
+            layout: layout_overrides,
+            merge_similar_objectives,
+            mut targets,
+            course_version,
+            free_form_version,
+            overrides,
+            max_asset_size,
+            max_section_size,
+            force_scaffold,
+            deploy_workflow,
+            container,
+            course_json,
+            schedule_md,
+            quiz,
+            instructor_notes,
+            instructor_handout,
+            thumbnails,
+            export_combined,
+            mut qr_codes,
+            credits,
+            book_notes,
+            with_solutions,
+            locale,
+        }: RenderOptions,
+    ) -> Result<RenderReport, LoadTrackError> {
+        // A render in the locale of the track is its default one, without suffixes
+        let locale = locale.filter(|l| *l != self.locale);
+        let slide_opts = SlidesRenderOptions {
+            theme: &theme,
+            package_json: package_json.as_ref(),
+            base_url: &base_url,
+            export_combined,
+            locale: locale.as_deref(),
+        };
+        let mut default_layout = self.output.clone();
+        default_layout.merge(layout_overrides);
+        default_layout.validate()?;
+        let layout = match &locale {
+            Some(locale) => default_layout.localized(locale),
+            None => default_layout.clone(),
+        };
+        if let Some(profile) = &profile {
+            if !self.profiles.contains(profile) {
+                return Err(Report::new(LoadTrackError)
+                    .attach_printable(format!(
+                        "Unknown profile '{profile}'.{}",
+                        suggest::did_you_mean(profile, &self.profiles)
+                    ))
+                    .attach_printable(format!(
+                        "Track defines profiles [{}]",
+                        self.profiles.join(", ")
+                    )));
+            }
+        }
+        let course_version = course_version
+            .as_deref()
+            .or(self.version.as_deref())
+            .map(|version| {
+                CourseVersion::parse(version, free_form_version)
+                    .map_err(|e| Report::new(LoadTrackError).attach_printable(e.to_string()))
+            })
+            .transpose()?;
+        for selector in units.iter() {
+            if !self.units().any(|u| u.matches(selector)) {
+                let tags: BTreeSet<String> = self
+                    .units()
+                    .flat_map(|u| u.tags.iter().map(|t| format!("tag:{t}")))
+                    .collect();
+                let candidates = self.units().map(|u| u.id()).chain(tags);
+                return Err(Report::new(LoadTrackError).attach_printable(format!(
+                    "Unit selector '{selector}' matches no units.{}",
+                    suggest::did_you_mean(selector, candidates)
+                )));
+            }
+        }
+        let filter = ContentFilter {
+            skip_optional,
+            profile: profile.as_deref(),
+            units: &units,
+        };
+        let conditions = Conditions {
+            profile: profile.as_deref(),
+            known_profiles: &self.profiles,
+            variables: &self.variables,
+        };
+
+        let context = context::current();
+        let out_dir = out_dir.as_path();
         out_dir.create_dir_all()?;
-        let out_dir = &out_dir
-            .canonicalize()
-            .into_report()
-            .change_context(LoadTrackError)?;
+        // Without writes, the output folder may not be there to canonicalize
+        let discarding = context.writes_discarded_at(out_dir);
+        let out_dir = &if discarding {
+            out_dir.to_path_buf()
+        } else {
+            out_dir
+                .canonicalize()
+                .into_report()
+                .change_context(LoadTrackError)?
+        };
 
-        if out_dir.exists() {
+        // With `clear_output_dir`, the files of the earlier render are replaced as they are
+        // rendered again, so that those that stay the same keep their modification time.
+        // The files that aren't rendered again are removed afterwards.
+        let mut earlier_files = vec![];
+        if io::exists(out_dir) {
             if clear_output_dir {
-                // remove output dir and contents
-                fs::remove_dir_all(out_dir)
-                    .into_report()
-                    .change_context(LoadTrackError)?;
+                // Links are listed as links, so that files they point to aren't removed
+                earlier_files = io::files_in(out_dir, true)?.unwrap_or_default();
+                context.track_files();
             } else {
                 // Return error if output dir is not empty
-                let None = fs::read_dir(out_dir)
-                    .into_report()
-                    .change_context(LoadTrackError)?
-                    .next()
-                else {
+                if !io::is_empty_dir(out_dir) {
                     return Err(Report::new(LoadTrackError)
                         .attach_printable("Output directory is not empty"));
-                };
+                }
             }
         }
         // Ensure output dir exists
         out_dir.create_dir_all()?;
+        targets.sort();
+        targets.dedup();
+        let renders = |target| targets.contains(&target);
+        let mut report = RenderReport::new(&self.name, out_dir);
+        // The options that change what the decks, book pages and exercise packages are
+        // rendered to, which the render cache is keyed on as well
+        cache::open(
+            out_dir,
+            &(
+                VERSION,
+                slide_opts.theme,
+                slide_opts.base_url,
+                &layout,
+                conditions,
+                quiz,
+                context.line_ending,
+            ),
+        );
+        let _span = tracing::info_span!(
+            "render",
+            label = %self.name,
+            out_dir = %out_dir.display()
+        )
+        .entered();
+        tracing::info!(
+            track = %self.name,
+            "Rendering track '{}' into {}",
+            self.name,
+            out_dir.display()
+        );
+        report.warnings.extend(self.warnings.iter().cloned());
+        if !allow_index_gaps {
+            report.warnings.extend(self.index_gaps());
+        }
+        if let (Some(template), true) = (&overrides.slide_template, renders(RenderTarget::Slides)) {
+            for module in self.modules.iter() {
+                let units = module
+                    .data
+                    .units
+                    .iter()
+                    .map(|u| &u.data)
+                    .filter(|u| filter.includes_unit(u) && u.render_slides);
+                for unit in units {
+                    let Some(own) = &unit.template else {
+                        continue;
+                    };
+                    report.warnings.push(Warning::new(
+                        WarningCode::TemplateOverrideIgnored,
+                        format!(
+                            "Unit '{}' sets its own slide template {}, which is used instead of the overriding template {}",
+                            unit.name,
+                            own.display(),
+                            template.display()
+                        ),
+                        Some(&module.data.definition),
+                    ));
+                }
+            }
+        }
+        for unit in self.units().filter(|u| filter.includes_unit(u)) {
+            report.skipped_decks += usize::from(!unit.render_slides);
+            report.skipped_book_sections += usize::from(!unit.render_book);
+            if !unit.render_slides {
+                continue;
+            }
+            for topic in unit.topics.iter().map(|t| &t.data) {
+                if topic.summary_derived
+                    && filter.includes(topic.optional, &topic.profiles)
+                    && !report.derived_summaries.contains(&topic.name)
+                {
+                    report.derived_summaries.push(topic.name.clone());
+                }
+            }
+        }
 
         // Render the modules in the track
         let mut book_builder = Book::builder(&self.name);
+        book_builder.course(&self.course);
+        if let Some(license) = &self.license {
+            book_builder.license(license);
+        }
         let mut slides_builder = SlidesPackage::builder(&self.name);
+        slides_builder.course(&self.course);
+        if let Some(license) = &self.license {
+            slides_builder.license(license);
+        }
+        if let Some(analytics) = &self.analytics {
+            book_builder.analytics(analytics);
+            slides_builder.analytics(analytics);
+        }
+        if let Some(version) = &course_version {
+            book_builder.course_version(version);
+            slides_builder.course_version(version);
+        }
+        let default_template = overrides
+            .slide_template
+            .as_ref()
+            .or(self.default_slide_template.as_ref());
+        if let Some(template) = default_template {
+            slides_builder.default_template(template);
+        }
+        if merge_similar_objectives {
+            book_builder.merge_similar_objectives();
+            slides_builder.merge_similar_objectives();
+        }
         let mut exercises_builder = ExerciseCollection::builder();
+        exercises_builder.shared_crates(&self.shared_crates);
 
         self.modules.iter().try_for_each(|module| {
             module.render(
                 &mut book_builder,
                 &mut slides_builder,
                 &mut exercises_builder,
+                filter,
             )
         })?;
 
+        // The errors of the exercises, book and slides are reported together, once all three
+        // were rendered, unless the context fails fast, see `RunContext::fail_fast`
+        let mut errors = None;
         // Build and render exercise packages
         let exercises = exercises_builder.build();
-        let exercise_paths = exercises.render(out_dir).change_context(LoadTrackError)?;
-        // Build and render the exercise book
-        let book = book_builder.build();
-        let book_opts = BookRenderOptions {
-            exercise_paths: &exercise_paths,
-            slides_url_base: slide_opts.url_base,
+        // The book refers to the folders of the exercises, even when they aren't rendered
+        let rendered_exercises = match renders(RenderTarget::Exercises) {
+            true => exercises
+                .render(
+                    out_dir,
+                    layout.exercises(),
+                    with_solutions.then(|| layout.solutions()),
+                    renders(RenderTarget::Book).then(|| layout.book()),
+                )
+                .map(Some),
+            false => Ok(None),
         };
-        book.render(book_opts, out_dir)
-            .change_context(LoadTrackError)?;
+        let exercises_rendered = matches!(rendered_exercises, Ok(Some(_)));
+        let exercise_paths = match rendered_exercises {
+            Ok(Some(paths)) => {
+                report.exercises = paths.len();
+                paths
+            }
+            Ok(None) => exercises.output_paths(layout.exercises()),
+            Err(error) => {
+                parallel::push_error(&mut errors, error.change_context(LoadTrackError))?;
+                exercises.output_paths(layout.exercises())
+            }
+        };
+        if let (Some(toolchain), true) = (&self.toolchain, exercises_rendered) {
+            let exercises_dir = layout.exercises();
+            let mut folders = exercise_paths
+                .values()
+                .filter_map(|path| path.strip_prefix(exercises_dir).ok())
+                .map(io::slash_path::<LoadTrackError>)
+                .collect::<Result<Vec<_>, _>>()?;
+            folders.sort();
+            folders.dedup();
+            let written = toolchain::write(
+                toolchain,
+                &self.name,
+                &out_dir.join(exercises_dir),
+                &folders,
+                force_scaffold,
+                &mut report.warnings,
+            );
+            if let Err(error) = written {
+                parallel::push_error(&mut errors, error.change_context(LoadTrackError))?;
+            }
+        }
+        if exercises_rendered {
+            let exercises_dir = layout.exercises();
+            let extensions = self
+                .toolchain
+                .as_ref()
+                .map_or_else(Toolchain::default_extensions, |t| t.extensions.clone());
+            let written = self
+                .exercise_crates(&exercise_paths, exercises_dir)
+                .and_then(
+                    |crates| match crates.members.is_empty() && crates.excluded.is_empty() {
+                        true => Ok(()),
+                        false => cargo_workspace::write(
+                            &crates,
+                            &extensions,
+                            &out_dir.join(exercises_dir),
+                            force_scaffold,
+                            &mut report.warnings,
+                        ),
+                    },
+                );
+            if let Err(error) = written {
+                parallel::push_error(&mut errors, error.change_context(LoadTrackError))?;
+            }
+        }
+        for exercise in self.exercises() {
+            let Some(git) = &exercise.git else {
+                continue;
+            };
+            // Topics can be included in more than one unit
+            let rendered = exercise_paths.contains_key(&exercise.path);
+            if rendered
+                && renders(RenderTarget::Exercises)
+                && !report
+                    .git_exercises
+                    .iter()
+                    .any(|(n, _)| *n == exercise.name)
+            {
+                report
+                    .git_exercises
+                    .push((exercise.name.clone(), git.clone()));
+            }
+        }
+        // Build and render the exercise book
+        let mut book_quizzes = false;
+        if renders(RenderTarget::Book) {
+            let book = book_builder.build();
+            book_quizzes = quiz == QuizMode::MdbookQuiz && book.has_quiz();
+            let book_opts = BookRenderOptions {
+                exercise_paths: &exercise_paths,
+                base_url: slide_opts.base_url,
+                layout: &layout,
+                conditions,
+                with_slides: renders(RenderTarget::Slides),
+                theme: overrides.book_theme.as_deref(),
+                quiz,
+                notes: book_notes,
+                language: locale.as_deref().unwrap_or(&self.locale),
+            };
+            let start = report.warnings.len();
+            let rendered = book.render(book_opts, out_dir, &mut report.warnings);
+            report::set_artifact(&mut report.warnings[start..], ArtifactKind::Chapter);
+            match rendered {
+                Ok(sections) => report.book_sections = sections,
+                Err(error) => {
+                    parallel::push_error(&mut errors, error.change_context(LoadTrackError))?
+                }
+            }
+        }
 
+        let slide_theme = slide_opts.theme;
+        let base_url = slide_opts.base_url;
+        let mut written_decks = vec![];
         // Build and render the slides package
-        let slides_package = slides_builder.build();
-        slides_package
-            .render(out_dir, slide_opts)
+        if renders(RenderTarget::Slides) {
+            qr_codes.sort();
+            qr_codes.dedup();
+            // Without the book, there are no chapters for codes of the exercises to link to
+            if !renders(RenderTarget::Book) {
+                qr_codes.retain(|target| *target != qr::QrTarget::Exercises);
+            }
+            // Without the exercises, the decks have none to link to
+            if renders(RenderTarget::Exercises) {
+                slides_builder
+                    .exercise_paths(&exercise_paths, layout.slides())
+                    .change_context(LoadTrackError)?;
+            }
+            slides_builder.qr_codes(self.qr_codes(
+                out_dir,
+                &layout,
+                base_url,
+                &qr_codes,
+                filter,
+                &mut report.warnings,
+            ));
+            let slides_package = slides_builder.build();
+            let start = report.warnings.len();
+            let rendered = slides_package.render(
+                out_dir,
+                slide_opts,
+                &layout,
+                &conditions,
+                &mut report.warnings,
+            );
+            report::set_artifact(&mut report.warnings[start..], ArtifactKind::Deck);
+            match rendered {
+                Ok(decks) => {
+                    report.decks = decks.len();
+                    written_decks = decks;
+                }
+                Err(error) => {
+                    parallel::push_error(&mut errors, error.change_context(LoadTrackError))?
+                }
+            }
+        }
+        if let Some(errors) = errors {
+            return Err(errors);
+        }
+        let (asset_bytes, asset_warnings) = report::take_assets(max_asset_size);
+        report.asset_bytes = asset_bytes;
+        report.warnings.extend(asset_warnings);
+        if let Some(max_size) = max_section_size {
+            report
+                .warnings
+                .extend(self.large_sections(filter, max_size));
+        }
+        report.targets = targets.clone();
+        report.course_version = course_version.clone();
+        report.timings.push(Timing {
+            phase: Phase::Load,
+            item: None,
+            duration: self.load_duration,
+        });
+        report.timings.extend(progress::take_timings());
+
+        let search = search_index.is_some();
+        let mut thumbnail_prefixes = vec![];
+        let mut root_files = vec![];
+        // These read back the rendered output
+        if !discarding || io::kept_in_memory(out_dir) {
+            if let Some(options) = search_index {
+                self.write_search_index(out_dir, &layout, options, filter)?;
+            }
+            let mut manifest = RenderManifest::new(&self.name);
+            manifest.slide_theme = renders(RenderTarget::Slides).then(|| slide_theme.to_string());
+            manifest.overrides = overrides.clone();
+            self.write_render_manifest(
+                manifest,
+                out_dir,
+                (&layout, locale.as_deref()),
+                filter,
+                &exercise_paths,
+                &report,
+            )?;
+            if instructor_notes && renders(RenderTarget::Slides) {
+                self.write_instructor_notes(out_dir, &layout, filter)?;
+            }
+            if instructor_handout && renders(RenderTarget::Slides) {
+                self.write_instructor_handouts(out_dir, &layout, filter)?;
+            }
+            if deploy_workflow.is_some() || container {
+                root_files = self.write_sitemap(
+                    out_dir,
+                    &layout,
+                    base_url,
+                    &written_decks,
+                    &mut report.warnings,
+                )?;
+            }
+            if thumbnails && !written_decks.is_empty() {
+                thumbnail_prefixes =
+                    thumbnail::export(out_dir, &layout, &written_decks, &mut report.warnings)?;
+            }
+            if course_json {
+                self.write_course_manifest(
+                    out_dir,
+                    CourseManifestOptions {
+                        layout: &layout,
+                        base_url,
+                        filter,
+                        conditions: &conditions,
+                        course_version: course_version.as_ref(),
+                        exercise_paths: &exercise_paths,
+                    },
+                )?;
+            }
+        }
+
+        // Each of the outputs is published on its own, so each gets a copy of the license
+        let roots: Vec<&Path> = [
+            (RenderTarget::Book, layout.book()),
+            (RenderTarget::Slides, layout.slides()),
+            (RenderTarget::Exercises, layout.exercises()),
+        ]
+        .into_iter()
+        .filter(|(target, _)| renders(*target))
+        .map(|(_, root)| root)
+        .collect();
+        match self.license.as_ref().map(|l| l.text.as_ref()) {
+            Some(Some(text)) => {
+                for root in roots.iter() {
+                    text.copy(out_dir.join(root).join("LICENSE"))?;
+                }
+                // Exercise packages are handed out one by one, so they get a copy as well,
+                // unless they come with a license of their own
+                if exercises_rendered {
+                    let mut packages: Vec<_> = exercise_paths
+                        .iter()
+                        .filter(|(source, _)| !source.join("LICENSE").exists())
+                        .map(|(_, rendered)| out_dir.join(rendered))
+                        .collect();
+                    packages.sort();
+                    for package in packages {
+                        text.copy(package.join("LICENSE"))?;
+                    }
+                }
+            }
+            Some(None) => report.warnings.push(Warning::new(
+                WarningCode::NoLicense,
+                format!(
+                    "The license of track '{}' has no text, so no LICENSE is copied into its output",
+                    self.name
+                ),
+                Some(&self.definition),
+            )),
+            None => {}
+        }
+        if schedule_md {
+            self.write_schedule_md(out_dir, filter)?;
+        }
+        if credits {
+            self.write_credits(out_dir, &roots, filter, &mut report.warnings)?;
+        }
+        let site = deploy::Site {
+            track_name: &self.name,
+            layout: &layout,
+            decks: &written_decks,
+            thumbnails: &thumbnail_prefixes,
+            book: renders(RenderTarget::Book),
+            exercises: renders(RenderTarget::Exercises),
+            search: search && !discarding,
+            root_files,
+            indexable: self.indexable,
+            quiz: book_quizzes,
+            toolchain: self.toolchain.as_ref(),
+        };
+        if let Some(provider) = deploy_workflow {
+            deploy::write(
+                provider,
+                &site,
+                out_dir,
+                force_scaffold,
+                &mut report.warnings,
+            )
+            .change_context(LoadTrackError)?;
+        }
+        if container {
+            container::write(
+                &site,
+                base_url,
+                out_dir,
+                force_scaffold,
+                &mut report.warnings,
+            )
             .change_context(LoadTrackError)?;
+        }
+        report.cached_files = cache::close()?;
+        if clear_output_dir {
+            // The output of the other locales is rendered on its own, see `RenderOptions::locale`
+            let rendered_locale = locale.as_ref().unwrap_or(&self.locale);
+            let other_locales = std::iter::once(&self.locale)
+                .chain(self.translations.iter())
+                .filter(|l| *l != rendered_locale)
+                .map(|l| match *l == self.locale {
+                    true => default_layout.clone(),
+                    false => default_layout.localized(l),
+                });
+            let kept: Vec<PathBuf> = other_locales
+                .flat_map(|other| {
+                    other
+                        .folders()
+                        .map(|dir| out_dir.join(dir))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|dir| dir != out_dir)
+                .collect();
+            remove_stale_files(out_dir, earlier_files, context.take_tracked_files(), &kept)?;
+        }
+        let counts = context.write_counts();
+        report.written_files = counts.written;
+        report.unchanged_files = counts.unchanged;
+
+        Ok(report)
+    }
 
+    /// A warning for each topic and exercise description `filter` includes whose content is
+    /// larger than `max_size` bytes. Decks and book pages are written a section at a time,
+    /// so they render, but they are slow to load.
+    fn large_sections(&self, filter: ContentFilter, max_size: u64) -> Vec<Warning> {
+        let mut warnings = vec![];
+        let mut listed = vec![];
+        let mut check = |what: String, path: &Path, size: u64| {
+            if size > max_size && !listed.contains(&what) {
+                warnings.push(Warning::new(
+                    WarningCode::LargeSection,
+                    format!(
+                        "{what} is {}, which is more than the limit of {}",
+                        report::format_bytes(size),
+                        report::format_bytes(max_size)
+                    ),
+                    Some(path),
+                ));
+                listed.push(what);
+            }
+        };
+        let topics = self
+            .units()
+            .filter(|u| filter.includes_unit(u))
+            .flat_map(|u| u.topics.iter().map(|t| &t.data))
+            .filter(|t| filter.includes(t.optional, &t.profiles));
+        for topic in topics {
+            let (path, size) = match &topic.content {
+                TopicContent::File(path) => (path, fs::metadata(path).map_or(0, |m| m.len())),
+                TopicContent::Inline(content) => (&topic.definition, content.len() as u64),
+            };
+            check(format!("The content of topic '{}'", topic.name), path, size);
+            let descriptions = topic
+                .exercises
+                .iter()
+                .map(|e| &e.data)
+                .filter(|e| filter.includes(e.optional, &e.profiles))
+                .filter_map(|e| Some((&e.name, e.description.as_ref()?)));
+            for (name, path) in descriptions {
+                let size = fs::metadata(path).map_or(0, |m| m.len());
+                check(format!("The description of exercise '{name}'"), path, size);
+            }
+        }
+        warnings
+    }
+
+    /// Lists the rendered decks, book chapters and exercise packages in the render manifest,
+    /// after those of the other locales the output has
+    fn write_render_manifest(
+        &self,
+        mut manifest: RenderManifest,
+        out_dir: &Path,
+        (layout, locale): (&OutputLayout, Option<&str>),
+        filter: ContentFilter,
+        exercise_paths: &HashMap<PathBuf, PathBuf>,
+        report: &RenderReport,
+    ) -> Result<(), LoadTrackError> {
+        let source = |path: &Path| {
+            manifest::relative_path(exercises::relative_path(&self.content_root, path).as_ref())
+        };
+        manifest.course_version = report.course_version.as_ref().map(|v| v.to_string());
+        for module in self.modules.iter() {
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| filter.includes_unit(&u.data));
+            for unit in units {
+                let indices = (module.index, unit.index);
+                let name = &unit.data.name;
+                let topics: Vec<_> = unit
+                    .data
+                    .topics
+                    .iter()
+                    .map(|t| &t.data)
+                    .filter(|t| filter.includes(t.optional, &t.profiles))
+                    .collect();
+                let prefix = format!("{}_{}", module.data.label.path, unit.index);
+                let deck = layout
+                    .slides()
+                    .join(to_prefixed_tag(name, &prefix))
+                    .with_extension("md");
+                // Decks without content are not written
+                if unit.data.render_slides && io::exists(&out_dir.join(&deck)) {
+                    let sources = topics
+                        .iter()
+                        .map(|t| source(t.content.path().unwrap_or(&t.definition)))
+                        .collect();
+                    manifest
+                        .add(out_dir, ArtifactKind::Deck, indices, name, &deck, sources)
+                        .change_context(LoadTrackError)?;
+                }
+                let page = layout
+                    .book()
+                    .join("src")
+                    .join(to_tag(name))
+                    .with_extension("md");
+                if unit.data.render_book && io::exists(&out_dir.join(&page)) {
+                    let sources = topics.iter().map(|t| source(&t.definition)).collect();
+                    manifest
+                        .add(
+                            out_dir,
+                            ArtifactKind::Chapter,
+                            indices,
+                            name,
+                            &page,
+                            sources,
+                        )
+                        .change_context(LoadTrackError)?;
+                }
+                let exercises = topics
+                    .iter()
+                    .flat_map(|t| t.exercises.iter().map(|e| &e.data))
+                    .filter(|e| filter.includes(e.optional, &e.profiles));
+                for exercise in exercises {
+                    let Some(rendered) = exercise_paths.get(&exercise.path) else {
+                        continue;
+                    };
+                    if !io::exists(&out_dir.join(rendered)) {
+                        continue;
+                    }
+                    let path = rendered.strip_prefix(out_dir).unwrap_or(rendered);
+                    // Topics can be included in more than one unit
+                    let listed = manifest::relative_path(path);
+                    if manifest.artifacts.iter().any(|a| a.path == listed) {
+                        continue;
+                    }
+                    let sources = vec![source(&exercise.path)];
+                    manifest
+                        .add(
+                            out_dir,
+                            ArtifactKind::Exercise,
+                            indices,
+                            &exercise.name,
+                            path,
+                            sources,
+                        )
+                        .change_context(LoadTrackError)?
+                        .starter_fails_tests = exercise.starter_fails_tests;
+                }
+            }
+        }
+        for artifact in manifest.artifacts.iter_mut() {
+            artifact.locale = locale.map(str::to_string);
+        }
+        manifest.timings(&report.timings);
+        // An earlier manifest that can't be read is replaced, like the rest of the output
+        if let Ok(Some(earlier)) = RenderManifest::read(out_dir) {
+            manifest.keep_other_locales(&earlier, locale, out_dir);
+            manifest.keep_unchanged(&earlier);
+        }
+        manifest.write(out_dir).change_context(LoadTrackError)
+    }
+
+    /// Indexes the rendered decks and book pages, which are read back from the output
+    fn write_search_index(
+        &self,
+        out_dir: &Path,
+        layout: &OutputLayout,
+        options: SearchIndexOptions,
+        filter: ContentFilter,
+    ) -> Result<(), LoadTrackError> {
+        let mut index = SearchIndex::new(options);
+        for module in self.modules.iter() {
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| filter.includes_unit(&u.data));
+            for unit in units {
+                let prefix = format!("{}_{}", module.data.label.path, unit.index);
+                let name = &unit.data.name;
+                let deck_slug = to_prefixed_tag(name, &prefix);
+                let deck = out_dir
+                    .join(layout.slides())
+                    .join(&deck_slug)
+                    .with_extension("md");
+                // Decks without content are not written
+                if unit.data.render_slides && io::exists(&deck) {
+                    let url = OutputLayout::url_path(&layout.slides().join(&prefix)) + "/";
+                    index.add_deck(&deck_slug, name, &url, &deck.read_to_string()?);
+                }
+                if unit.data.render_book {
+                    let page_slug = to_tag(name);
+                    let page = out_dir
+                        .join(layout.book())
+                        .join("src")
+                        .join(&page_slug)
+                        .with_extension("md");
+                    // The book may not be rendered
+                    if io::exists(&page) {
+                        let url = OutputLayout::url_path(&layout.book().join(&page_slug)) + ".html";
+                        index.add_page(&page_slug, name, &url, &page.read_to_string()?);
+                    }
+                }
+            }
+        }
+        index.write(out_dir).change_context(LoadTrackError)
+    }
+}
+
+/// License of the material of a track, as set in the `[license]` table of the track definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct License {
+    /// SPDX identifier of the license, like `CC-BY-SA-4.0`
+    pub spdx: String,
+    /// Line crediting the authors, added to the book and the slides
+    pub attribution: String,
+    /// Path to the full text of the license, which is copied into the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<PathBuf>,
+}
+
+/// The tools the exercises are worked on with, as set in the `[toolchain]` table of the track
+/// definition. The `rust-toolchain.toml` of the exercises and their devcontainer both take
+/// their versions from here, so that they can't disagree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Toolchain {
+    /// The Rust toolchain, like `1.79` or `stable`
+    pub rust: String,
+    /// Components installed with the Rust toolchain, `rustfmt` and `clippy` by default
+    #[serde(default = "Toolchain::default_components")]
+    pub components: Vec<String>,
+    /// The major version of Node.js the slides are built with, `20` by default
+    #[serde(default = "Toolchain::default_node")]
+    pub node: String,
+    /// The version of mdbook the book is built with, the latest by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdbook: Option<String>,
+    /// Editor extensions the devcontainer recommends, rust-analyzer by default
+    #[serde(default = "Toolchain::default_extensions")]
+    pub extensions: Vec<String>,
+    /// Whether a devcontainer is generated alongside the exercises
+    #[serde(default)]
+    pub devcontainer: bool,
+}
+
+impl Toolchain {
+    fn default_components() -> Vec<String> {
+        vec!["rustfmt".to_string(), "clippy".to_string()]
+    }
+
+    fn default_node() -> String {
+        "20".to_string()
+    }
+
+    pub(crate) fn default_extensions() -> Vec<String> {
+        vec!["rust-lang.rust-analyzer".to_string()]
+    }
+}
+
+/// Where the output is written, relative to the output folder, as set in the `[output]` table
+/// of the track definition. Folders that are not set have their default location.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputLayout {
+    /// The slides package, `slides` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slides: Option<PathBuf>,
+    /// The book, `book` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book: Option<PathBuf>,
+    /// The exercise packages, `exercises` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exercises: Option<PathBuf>,
+    /// The exercise packages with their solutions, `solutions` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solutions: Option<PathBuf>,
+    /// The slide images, relative to the slides package. `images` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<PathBuf>,
+    /// Where the build scripts of the slides package put the built decks, relative to
+    /// the slides package. `dist` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dist: Option<PathBuf>,
+}
+
+impl OutputLayout {
+    pub fn slides(&self) -> &Path {
+        self.slides.as_deref().unwrap_or(Path::new("slides"))
+    }
+
+    pub fn book(&self) -> &Path {
+        self.book.as_deref().unwrap_or(Path::new("book"))
+    }
+
+    pub fn exercises(&self) -> &Path {
+        self.exercises.as_deref().unwrap_or(Path::new("exercises"))
+    }
+
+    pub fn solutions(&self) -> &Path {
+        self.solutions.as_deref().unwrap_or(Path::new("solutions"))
+    }
+
+    pub fn images(&self) -> &Path {
+        self.images.as_deref().unwrap_or(Path::new("images"))
+    }
+
+    pub fn dist(&self) -> &Path {
+        self.dist.as_deref().unwrap_or(Path::new("dist"))
+    }
+
+    /// The folders of the slides, book, exercises and solutions, which hold the output of a
+    /// single locale
+    fn folders(&self) -> impl Iterator<Item = &Path> {
+        [
+            self.slides(),
+            self.book(),
+            self.exercises(),
+            self.solutions(),
+        ]
+        .into_iter()
+    }
+
+    /// The layout of the output in `locale`, with the locale as suffix of the slides, book,
+    /// exercises and solutions folders, like `slides-nl`
+    pub fn localized(&self, locale: &str) -> OutputLayout {
+        let localize = |path: &Path| {
+            let localized = match path.file_name() {
+                Some(name) => path.with_file_name(format!("{}-{locale}", name.to_string_lossy())),
+                None => path.join(locale),
+            };
+            Some(localized)
+        };
+        OutputLayout {
+            slides: localize(self.slides()),
+            book: localize(self.book()),
+            exercises: localize(self.exercises()),
+            solutions: localize(self.solutions()),
+            images: self.images.clone(),
+            dist: self.dist.clone(),
+        }
+    }
+
+    /// Overrides the folders of this layout with the folders that are set in `other`
+    pub fn merge(&mut self, other: OutputLayout) {
+        let OutputLayout {
+            slides,
+            book,
+            exercises,
+            solutions,
+            images,
+            dist,
+        } = other;
+        self.slides = slides.or(self.slides.take());
+        self.book = book.or(self.book.take());
+        self.exercises = exercises.or(self.exercises.take());
+        self.solutions = solutions.or(self.solutions.take());
+        self.images = images.or(self.images.take());
+        self.dist = dist.or(self.dist.take());
+    }
+
+    /// The folders must stay within the folder they are relative to
+    fn validate(&self) -> Result<(), LoadTrackError> {
+        for (what, path) in [
+            ("slides", self.slides()),
+            ("book", self.book()),
+            ("exercises", self.exercises()),
+            ("solutions", self.solutions()),
+            ("images", self.images()),
+            ("dist", self.dist()),
+        ] {
+            let escapes = path
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            if escapes {
+                return Err(Report::new(LoadTrackError).attach_printable(format!(
+                    "Output folder {what} = '{}' must be a relative path without '..'",
+                    path.display()
+                )));
+            }
+        }
         Ok(())
     }
+
+    /// The URL path of a folder of the layout, with forward slashes and without
+    /// a trailing one. Empty if the folder is the output folder itself.
+    pub(crate) fn url_path(path: &Path) -> String {
+        path.components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The URL the slide deck with this prefix is deployed at, like `/<base url>/slides/2_1/`
+    pub(crate) fn deck_url(&self, base_url: &BaseUrl, deck_prefix: &str) -> String {
+        base_url.join(&Self::dir_url_path(&self.slides().join(deck_prefix)))
+    }
+
+    /// The URL path of a folder of the layout relative to the base URL, with a trailing
+    /// slash unless it's the output folder itself
+    pub(crate) fn dir_url_path(path: &Path) -> String {
+        match Self::url_path(path) {
+            path if path.is_empty() => path,
+            path => path + "/",
+        }
+    }
+}
+
+/// Opts in to deriving summaries for topics that don't have one, from the level-2
+/// headings of their slides, as set in the `[derive_summaries]` table of the track definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeriveSummaries {
+    /// The maximum number of summary items derived for a topic
+    #[serde(default = "load::serde_defaults::derived_summary_items")]
+    pub max_items: usize,
+    /// Case-insensitive globs of headings that are not used. Headings named "Exercise" never are.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// How modules are numbered in titles, headings and output paths, as set by
+/// `module_numbering` in the track definition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleNumbering {
+    /// 1, 2, 3, ...
+    #[default]
+    Numbers,
+    /// A, B, C, ..., Z, AA, AB, ...
+    Letters,
+    /// I, II, III, ...
+    Roman,
+    /// Modules are not numbered in titles and headings
+    None,
+}
+
+impl ModuleNumbering {
+    /// The label of the module with the given index, which is empty if modules are not numbered
+    pub fn label(self, index: usize) -> String {
+        match self {
+            Self::Numbers => index.to_string(),
+            Self::Letters => {
+                // Bijective base 26, so that Z is followed by AA
+                let mut label = vec![];
+                let mut n = index;
+                while n > 0 {
+                    n -= 1;
+                    label.push(char::from(b'A' + (n % 26) as u8));
+                    n /= 26;
+                }
+                label.iter().rev().collect()
+            }
+            Self::Roman => {
+                const NUMERALS: &[(usize, &str)] = &[
+                    (1000, "M"),
+                    (900, "CM"),
+                    (500, "D"),
+                    (400, "CD"),
+                    (100, "C"),
+                    (90, "XC"),
+                    (50, "L"),
+                    (40, "XL"),
+                    (10, "X"),
+                    (9, "IX"),
+                    (5, "V"),
+                    (4, "IV"),
+                    (1, "I"),
+                ];
+                let mut label = String::new();
+                let mut n = index;
+                for (value, numeral) in NUMERALS {
+                    while n >= *value {
+                        label.push_str(numeral);
+                        n -= value;
+                    }
+                }
+                label
+            }
+            Self::None => String::new(),
+        }
+    }
 }
 
-#[derive(Debug)]
+/// How a module is referred to in the output. Modules are still ordered by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleLabel {
+    /// Shown in titles, headings and `#[modmod:mod_index]`. Empty if modules are not numbered.
+    pub display: String,
+    /// Prefixes the slide deck and exercise folder names of the module, and is never empty
+    pub path: String,
+}
+
+impl ModuleLabel {
+    /// The label of a module, which is either set by the module itself or follows from
+    /// its index in the numbering of the track
+    pub fn new(numbering: ModuleNumbering, index: usize, custom: Option<String>) -> Self {
+        let display = custom.unwrap_or_else(|| numbering.label(index));
+        let path = match display.is_empty() {
+            true => index.to_string(),
+            false => to_tag(&display),
+        };
+        Self { display, path }
+    }
+
+    /// A number within the module, like that of a unit, qualified with the module label
+    pub fn qualify(&self, number: impl Display) -> String {
+        match self.display.is_empty() {
+            true => number.to_string(),
+            false => format!("{}.{number}", self.display),
+        }
+    }
+}
+
+/// Metadata of a course run, as set in the `[course]` table of the track definition.
+/// All of it is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Course {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub institution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cohort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact_email: Option<String>,
+}
+
+impl Course {
+    /// Names of the fields, as used in `#[modmod:course_<field>]` placeholders
+    pub const FIELDS: &'static [&'static str] = &[
+        "code",
+        "institution",
+        "start_date",
+        "end_date",
+        "cohort",
+        "contact_email",
+    ];
+
+    /// Overrides the fields of this course with the fields that are set in `other`
+    pub fn merge(&mut self, other: Course) {
+        let Course {
+            code,
+            institution,
+            start_date,
+            end_date,
+            cohort,
+            contact_email,
+        } = other;
+        self.code = code.or(self.code.take());
+        self.institution = institution.or(self.institution.take());
+        self.start_date = start_date.or(self.start_date);
+        self.end_date = end_date.or(self.end_date);
+        self.cohort = cohort.or(self.cohort.take());
+        self.contact_email = contact_email.or(self.contact_email.take());
+    }
+
+    /// The value of a field by name, if it is set
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "code" => self.code.clone(),
+            "institution" => self.institution.clone(),
+            "start_date" => self.start_date.map(|d| d.to_string()),
+            "end_date" => self.end_date.map(|d| d.to_string()),
+            "cohort" => self.cohort.clone(),
+            "contact_email" => self.contact_email.clone(),
+            _ => None,
+        }
+    }
+
+    /// Labels and values of the fields that are set, in a fixed order
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Course code", "code"),
+            ("Institution", "institution"),
+            ("Start date", "start_date"),
+            ("End date", "end_date"),
+            ("Cohort", "cohort"),
+            ("Contact", "contact_email"),
+        ]
+        .into_iter()
+        .filter_map(|(label, name)| Some((label, self.field(name)?)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Module {
     pub name: String,
     pub description: String,
+    pub label: ModuleLabel,
     pub units: Vec<Indexed<Unit>>,
+    /// Path to the definition file of this module
+    pub definition: PathBuf,
+}
+
+impl Module {
+    /// Identifies the module in the track: the name of the folder of its definition
+    pub fn id(&self) -> String {
+        self.definition
+            .parent()
+            .and_then(|p| p.file_name())
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Whether all units of the module are picked by a selector of [`Track::select_units`],
+    /// which is its label like `B` or its id
+    pub fn matches(&self, selector: &str) -> bool {
+        (!self.label.display.is_empty() && self.label.display == selector)
+            || self.label.path == selector
+            || self.id() == selector
+    }
+
+    /// Whether `unit` of this module is picked by a selector of [`Track::select_units`]: the
+    /// module itself, `<module>.<unit number>` like `B.2`, or the id or a tag of the unit
+    pub fn selects(&self, unit: &Indexed<Unit>, selector: &str) -> bool {
+        let numbered = selector
+            .rsplit_once('.')
+            .is_some_and(|(module, n)| n.parse() == Ok(unit.index) && self.matches(module));
+        numbered || self.matches(selector) || unit.data.matches(selector)
+    }
 }
 
 impl Indexed<Module> {
@@ -127,25 +1860,30 @@ impl Indexed<Module> {
         book_builder: &mut BookBuilder<'me>,
         slides: &mut SlidesPackageBuilder<'me>,
         exercises: &mut ExerciseCollectionBuilder<'me>,
+        filter: ContentFilter,
     ) -> Result<(), LoadTrackError> {
         let Indexed {
             data,
             index: module_index,
         } = self;
 
-        let mut chapter = book_builder.chapter(&data.name, *module_index);
-        let mut module_exercises = exercises.module(&data.name, *module_index);
+        let mut chapter = book_builder.chapter(&data.name, *module_index, &data.label);
+        let mut module_exercises = exercises.module(&data.name, &data.label.path);
 
         // Render all units in this module
-        data.units.iter().try_for_each(|unit| {
-            unit.render(
-                &data.name,
-                *module_index,
-                &mut chapter,
-                slides,
-                &mut module_exercises,
-            )
-        })?;
+        data.units
+            .iter()
+            .filter(|unit| filter.includes_unit(&unit.data))
+            .try_for_each(|unit| {
+                unit.render(
+                    &data.name,
+                    &data.label,
+                    &mut chapter,
+                    slides,
+                    &mut module_exercises,
+                    filter,
+                )
+            })?;
 
         chapter.add();
         module_exercises.add();
@@ -153,58 +1891,179 @@ impl Indexed<Module> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Unit {
     pub name: String,
     pub template: Option<PathBuf>,
+    /// The Slidev theme of the unit's deck, instead of the one of the render. Theme folders
+    /// are absolute paths, see [`RenderOptions::theme`].
+    pub theme: Option<String>,
     pub topics: Vec<Indexed<Topic>>,
+    /// The sessions this unit is taught in. Always contains at least one session.
+    pub sessions: Vec<Session>,
+    pub profiles: Vec<String>,
+    /// Delivery metadata, like the day the unit is taught on
+    pub tags: Vec<String>,
+    /// Whether a slide deck is generated for the unit
+    pub render_slides: bool,
+    /// Whether the unit gets a page in the exercise book
+    pub render_book: bool,
+    /// Whether the unit confirmed that it has no exercises on purpose
+    pub no_exercises: bool,
+}
+
+impl Unit {
+    /// The id units are selected by, derived from their name
+    pub fn id(&self) -> String {
+        to_tag(&self.name)
+    }
+
+    /// Whether the unit is selected by a selector of the form `tag:<tag>`, or by its id
+    pub fn matches(&self, selector: &str) -> bool {
+        match selector.strip_prefix("tag:") {
+            Some(tag) => self.tags.iter().any(|t| t == tag),
+            None => self.id() == selector,
+        }
+    }
+}
+
+/// A scheduled part of a unit, like a lecture or a lab
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub name: String,
+    pub duration_minutes: Option<u32>,
+    /// Names of the topics covered in this session
+    pub topics: Vec<String>,
+    /// Names of the exercises done in this session
+    pub exercises: Vec<String>,
+    /// Whether this session was derived because the unit didn't define any
+    pub implicit: bool,
 }
 
 impl Indexed<Unit> {
     fn render<'me>(
         &'me self,
         module_name: &'me str,
-        module_index: usize,
+        module_label: &'me ModuleLabel,
         chapter: &mut ChapterBuilder<'me, '_>,
         slides: &mut SlidesPackageBuilder<'me>,
         module_exercises: &mut ModuleExercisesBuilder<'me, '_>,
+        filter: ContentFilter,
     ) -> Result<(), LoadTrackError> {
         let Indexed {
             data,
             index: unit_index,
         } = self;
 
-        let mut section = chapter.section(module_index, *unit_index, &data.name);
+        let mut section = chapter.section(*unit_index, &data.name);
+        section.sessions(&data.sessions);
+        section.tags(&data.tags);
+        if !data.render_book {
+            section.skip_page();
+        }
+        if !data.render_slides {
+            section.skip_slides();
+        }
         let mut deck = slides.deck(
             &data.name,
             module_name,
-            module_index,
+            module_label,
             *unit_index,
             data.template.as_deref(),
+            data.theme.as_deref(),
         );
         let mut unit_exercises = module_exercises.unit(&data.name, *unit_index);
 
         data.topics
             .iter()
-            .try_for_each(|topic| topic.render(&mut section, &mut deck, &mut unit_exercises))?;
+            .filter(|topic| filter.includes(topic.data.optional, &topic.data.profiles))
+            .try_for_each(|topic| {
+                topic.render(&mut section, &mut deck, &mut unit_exercises, filter)
+            })?;
 
         section.add();
-        deck.add();
+        // Leaving the deck out of the package is all it takes to skip it
+        if data.render_slides {
+            deck.add();
+        }
         unit_exercises.add();
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Topic {
     pub name: String,
     pub exercises: Vec<Indexed<Exercise>>,
     pub summary: Vec<String>,
+    /// Whether the summary was derived from the headings of the slides, rather than written
+    pub summary_derived: bool,
     pub objectives: Vec<String>,
-    pub content: PathBuf,
+    /// The translations of the objectives and summary items that the definition gives in
+    /// each locale, see [`i18n::LocalizedText`]
+    pub translations: i18n::Translations,
+    pub content: TopicContent,
     pub further_reading: Vec<String>,
-    pub images: Vec<PathBuf>,
+    /// Terms the topic introduces, with their definitions
+    pub glossary: BTreeMap<String, String>,
+    /// Questions for the quiz at the end of the book chapter
+    pub quiz: Vec<QuizQuestion>,
+    pub images: Vec<TopicImage>,
+    pub optional: bool,
+    /// The minutes the topic takes to teach, see [`schedule`]
+    pub duration_minutes: Option<u32>,
+    pub authors: Vec<String>,
+    pub license: Option<String>,
+    pub last_reviewed: Option<Date>,
+    pub profiles: Vec<String>,
+    /// Identifies the topic in links, derived from the name of the topic's folder
+    pub id: String,
+    /// Ids the topic had before it was renamed
+    pub aliases: Vec<String>,
+    /// Path to the definition file of this topic
+    pub definition: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct TopicImage {
+    pub path: PathBuf,
+    /// Whether the image was found in the topic's `images` folder, rather than declared
+    pub discovered: bool,
+}
+
+/// The slide content of a topic
+#[derive(Debug, Clone)]
+pub enum TopicContent {
+    /// Content is read from the file at this path
+    File(PathBuf),
+    /// Content was defined inline in the unit definition
+    Inline(String),
+}
+
+impl TopicContent {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            TopicContent::File(path) => Some(path),
+            TopicContent::Inline(_) => None,
+        }
+    }
+}
+
+impl Topic {
+    /// A line attributing this topic to its authors, if any authorship or license info was given
+    pub fn attribution(&self) -> Option<String> {
+        let mut line = self.name.clone();
+        if !self.authors.is_empty() {
+            line += &format!(" by {}", self.authors.join(", "));
+        }
+        match &self.license {
+            Some(license) => line += &format!(", licensed under {license}"),
+            None if self.authors.is_empty() => return None,
+            None => {}
+        }
+        Some(line)
+    }
 }
 
 impl Indexed<Topic> {
@@ -213,18 +2072,38 @@ impl Indexed<Topic> {
         section: &mut SectionBuilder<'me, '_, '_>,
         deck: &mut SlideDeckBuilder<'me, '_>,
         unit_exercises: &mut UnitExercisesBuilder<'me, '_, '_>,
+        filter: ContentFilter,
     ) -> Result<(), LoadTrackError> {
         let Indexed { data, .. } = self;
 
-        let mut slides_section = deck.section(&data.content);
+        let mut slides_section = deck.section(&data.name, &data.content);
 
-        data.summary
-            .iter()
-            .for_each(|item| slides_section.summary(item));
+        if data.optional {
+            slides_section.mark_optional();
+        }
 
-        data.objectives
-            .iter()
-            .for_each(|obj| slides_section.objective(obj));
+        let (lecture, exercises) = Estimate::of_topic(data, filter);
+        slides_section.duration(lecture + exercises);
+
+        if let Some(attribution) = data.attribution() {
+            slides_section.attribution(attribution.clone());
+            section.attribution(attribution);
+        }
+
+        section.aliases(&data.aliases);
+        section.topic(data);
+
+        data.summary.iter().for_each(|item| {
+            slides_section.summary(item);
+            section.summary(item);
+        });
+
+        data.objectives.iter().for_each(|obj| {
+            slides_section.objective(obj);
+            section.objective(&data.name, obj);
+        });
+
+        section.quiz(&data.quiz);
 
         data.further_reading
             .iter()
@@ -232,11 +2111,15 @@ impl Indexed<Topic> {
 
         data.images
             .iter()
-            .for_each(|image| slides_section.image(image));
+            .for_each(|image| slides_section.image(&image.path));
 
         data.exercises
             .iter()
-            .try_for_each(|exercise| exercise.render(section, unit_exercises))?;
+            .filter(|exercise| filter.includes(exercise.data.optional, &exercise.data.profiles))
+            .try_for_each(|exercise| {
+                slides_section.exercise(&exercise.data);
+                exercise.render(section, unit_exercises, data.optional)
+            })?;
 
         slides_section.add();
 
@@ -244,12 +2127,47 @@ impl Indexed<Topic> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Exercise {
     pub name: String,
     pub path: PathBuf,
-    pub description: PathBuf,
+    /// Path to the description, or `None` if it is missing and the track was loaded leniently
+    pub description: Option<PathBuf>,
     pub includes: Vec<String>,
+    pub optional: bool,
+    /// Whether the tests fail on the exercise as handed out, until it is done
+    pub starter_fails_tests: bool,
+    /// The minutes the exercise takes to do, for the schedule and the estimates of the stats
+    pub duration_minutes: Option<u32>,
+    pub profiles: Vec<String>,
+    /// Identifies the exercise in links, derived from the name of the exercise folder
+    pub id: String,
+    /// Package name set in the exercise's `Cargo.toml`, if it has one
+    pub package: Option<String>,
+    /// The repository the exercise was checked out from, if it is hosted in one
+    pub git: Option<GitSource>,
+    /// Ids the exercise had before it was renamed
+    pub aliases: Vec<String>,
+}
+
+/// Where an exercise that is hosted in a git repository was checked out from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitSource {
+    pub url: String,
+    /// The commit the revision in the exercise definition resolved to
+    pub commit: String,
+}
+
+/// A library crate that is shared by several exercises. It is copied into the output once,
+/// and the path dependencies of exercises on it are pointed at the copy.
+#[derive(Debug, Clone)]
+pub struct SharedCrate {
+    /// Package name, as set in the crate's `Cargo.toml`
+    pub name: String,
+    /// Package version, as set in the crate's `Cargo.toml`
+    pub version: Option<String>,
+    /// Path to the folder of the crate
+    pub path: PathBuf,
 }
 
 impl Indexed<Exercise> {
@@ -257,12 +2175,28 @@ impl Indexed<Exercise> {
         &'me self,
         section: &mut SectionBuilder<'me, '_, '_>,
         unit_exercises: &mut UnitExercisesBuilder<'me, '_, '_>,
+        topic_optional: bool,
     ) -> Result<(), LoadTrackError> {
         let Indexed { data, .. } = self;
 
-        section.subsection(&data.name, &data.description, &data.path);
+        // Exercises of optional topics are optional themselves
+        let optional = data.optional || topic_optional;
+        let description = section.subsection(
+            &data.name,
+            data.description.as_deref(),
+            &data.path,
+            optional,
+            data.duration_minutes,
+            &data.aliases,
+        );
 
-        unit_exercises.package(&data.name, &data.path, &data.includes);
+        unit_exercises.package(
+            &data.name,
+            &data.path,
+            &data.includes,
+            data.git.as_ref(),
+            description,
+        );
 
         Ok(())
     }
@@ -279,31 +2213,120 @@ impl fmt::Display for LoadTrackError {
 
 impl error_stack::Context for LoadTrackError {}
 
+/// Removes the files of an earlier render into `out_dir` that weren't rendered again, and
+/// the folders they leave empty, except for those in the `kept` folders
+fn remove_stale_files(
+    out_dir: &Path,
+    earlier_files: Vec<io::DirEntry>,
+    rendered: HashSet<PathBuf>,
+    kept: &[PathBuf],
+) -> Result<(), LoadTrackError> {
+    let mut dirs = HashSet::new();
+    // The manifests are always written again: the render manifest after the render, and
+    // that of `update` by the command, but dry runs leave them out. The journal is removed
+    // by the command once the render finished.
+    let manifests = [
+        manifest::RENDER_MANIFEST_FILE,
+        update::HASHES_FILE,
+        io::JOURNAL_FILE,
+    ];
+    for file in earlier_files.iter().map(io::DirEntry::path) {
+        let manifest = file.parent() == Some(out_dir)
+            && file
+                .file_name()
+                .is_some_and(|name| manifests.iter().any(|m| name == *m));
+        // The local changes, which are put back after the render
+        let saved = file.starts_with(out_dir.join(update::EDITS_DIR));
+        // A folder that became a link since would lead out of the output folder
+        let linked = file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != out_dir)
+            .any(Path::is_symlink);
+        let kept = kept.iter().any(|dir| file.starts_with(dir));
+        if manifest || saved || linked || kept || rendered.contains(file) {
+            continue;
+        }
+        file.remove_file()?;
+        dirs.extend(
+            file.ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != out_dir)
+                .map(Path::to_path_buf),
+        );
+    }
+    // Deepest first, so that their parents can be emptied too
+    let mut dirs: Vec<_> = dirs.into_iter().collect();
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in dirs {
+        if io::is_empty_dir(&dir) {
+            dir.remove_dir()?;
+        }
+    }
+    Ok(())
+}
+
 fn to_prefixed_tag<S, P>(s: S, p: P) -> String
 where
     S: Display,
     P: Display,
 {
-    to_tag(format!("{p}-{s}"))
+    thread_local! {
+        // Tags are made for every deck, page and exercise, so the title they are made of
+        // is put together in the same buffer each time
+        static TITLE: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+    TITLE.with(|title| {
+        let mut title = title.borrow_mut();
+        title.clear();
+        // Writing to a string doesn't fail
+        let _ = write!(title, "{p}-{s}");
+        slug::slug(&title)
+    })
 }
 
 fn to_tag<S>(s: S) -> String
 where
-    S: ToString,
+    S: AsRef<str>,
 {
-    let mut s = s.to_string();
-    s.make_ascii_lowercase();
-    let mut tag = String::new();
-    let mut words = s.split_whitespace();
+    slug::slug(s.as_ref())
+}
 
-    match words.next() {
-        Some(w) => tag.push_str(w),
-        None => return s,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RunContext;
+
+    #[test]
+    fn tags_of_plain_titles() {
+        assert_eq!(to_tag("Basic Syntax"), "basic-syntax");
+        assert_eq!(to_prefixed_tag("Basic Syntax", "2_1"), "2_1-basic-syntax");
+        assert_eq!(
+            to_prefixed_tag("Foundations of Rust", 2),
+            "2-foundations-of-rust"
+        );
     }
 
-    for word in words {
-        tag.push('-');
-        tag.push_str(word);
+    #[test]
+    fn prefixed_tags_keep_the_prefix() {
+        let _context = RunContext::default().enter();
+        for title in [
+            "",
+            "🦀",
+            "CON",
+            "a/b: c",
+            ".hidden",
+            &"long title ".repeat(20),
+        ] {
+            for prefix in ["1", "2_3"] {
+                let tag = to_prefixed_tag(title, prefix);
+                assert!(
+                    tag.starts_with(&format!("{prefix}-")),
+                    "{title:?} became {tag}"
+                );
+                assert!(tag.len() <= slug::MAX_SLUG_LEN, "{title:?} became {tag}");
+                assert_eq!(tag, slug::slug(&tag));
+            }
+        }
     }
-    tag
 }
@@ -0,0 +1,309 @@
+//! Checking all links of a track for `modmod check-links`: those in the content, like
+//! [`Track::broken_links`] does for `modmod check`, and those in the output the content is
+//! rendered into.
+//!
+//! Topic content ends up in the deck of each unit that includes the topic, and exercise
+//! descriptions in the book page of their unit, so a relative link that works next to its
+//! source can lead nowhere once rendered. The links in the decks and book pages of a render of
+//! the track are therefore resolved from where those files are in the output, and checked
+//! against the files that the render writes. A broken link is reported at the line of the
+//! content it comes from, if it can be found there, and otherwise at the line of the output.
+//! Links in the book to headings are checked against the headings of the rendered pages,
+//! which mdbook links to.
+//!
+//! External links are only requested when asked, see [`Track::check_external_links`], so that
+//! the rest of the checks works offline.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use error_stack::Result;
+
+use crate::{
+    book::heading_anchor,
+    check::{headings, is_url, links, BrokenLink, CheckError},
+    external_links::{ExternalLinkOptions, ExternalLinkReport},
+    report::WarningCode,
+    to_prefixed_tag, to_tag, Track,
+};
+
+/// Options for [`Track::check_links`]
+#[derive(Debug, Default)]
+pub struct LinkCheckOptions<'o> {
+    /// The folder a render of the track went into, and the files it wrote there by path, see
+    /// [`RunContext::in_memory`](crate::context::RunContext::in_memory). Without them, only the links in the content are
+    /// checked.
+    pub output: Option<(&'o Path, &'o BTreeMap<PathBuf, Vec<u8>>)>,
+    /// Request the external links with these options as well
+    pub online: Option<ExternalLinkOptions>,
+}
+
+/// The outcome of [`Track::check_links`]
+#[derive(Debug, Default)]
+pub struct LinkReport {
+    /// The broken links, sorted by file and line
+    pub broken: Vec<BrokenLink>,
+    /// The outcome of requesting the external links, if they were. Its unreachable links are
+    /// in `broken` as well.
+    pub external: Option<ExternalLinkReport>,
+}
+
+impl LinkReport {
+    pub fn is_empty(&self) -> bool {
+        self.broken.is_empty()
+    }
+}
+
+impl fmt::Display for LinkReport {
+    /// Lists the broken links grouped by the file they are in
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files: BTreeSet<_> = self.broken.iter().map(|link| &link.file).collect();
+        match self.broken.is_empty() {
+            true => writeln!(f, "No broken links")?,
+            false => writeln!(
+                f,
+                "{} broken link(s) in {} file(s)",
+                self.broken.len(),
+                files.len()
+            )?,
+        }
+        let mut file = None;
+        for link in &self.broken {
+            if file != Some(&link.file) {
+                writeln!(f, "  {}", link.file.display())?;
+                file = Some(&link.file);
+            }
+            writeln!(
+                f,
+                "    line {}: {} {}",
+                link.line, link.target, link.problem
+            )?;
+        }
+        if let Some(external) = &self.external {
+            writeln!(
+                f,
+                "External links: {} URL(s) checked ({} from the cache), {} ignored, {} link(s) can't be reached",
+                external.checked,
+                external.cached,
+                external.ignored,
+                external.unreachable.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Track {
+    /// Reports the broken links in the content of the track and in the rendered output of
+    /// `opts`, and the external links that can't be reached if they are requested
+    pub fn check_links(&self, opts: &LinkCheckOptions) -> Result<LinkReport, CheckError> {
+        let mut broken = self.broken_content_links();
+        if let Some((out_dir, files)) = opts.output {
+            // Content that is rendered is checked where it ends up instead, except for URLs
+            let rendered: HashSet<&Path> = self
+                .output_sources()
+                .into_iter()
+                .filter(|(output, _)| files.contains_key(&out_dir.join(output)))
+                .flat_map(|(_, sources)| sources)
+                .collect();
+            broken.retain(|link| is_url(&link.target) || !rendered.contains(link.file.as_path()));
+            broken.extend(self.broken_output_links(out_dir, files));
+        }
+        let external = match &opts.online {
+            Some(link_opts) => {
+                let report = self.check_external_links(link_opts)?;
+                broken.extend(report.unreachable.iter().map(|link| BrokenLink {
+                    code: WarningCode::UnreachableLink,
+                    ..BrokenLink::new(
+                        &link.file,
+                        link.line,
+                        &link.url,
+                        format!("can't be reached: {}", link.error),
+                    )
+                }));
+                Some(report)
+            }
+            None => None,
+        };
+        broken.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        Ok(LinkReport { broken, external })
+    }
+
+    /// The content files that end up in each deck and book page, by the path of the deck or
+    /// page in the output folder
+    fn output_sources(&self) -> HashMap<PathBuf, Vec<&Path>> {
+        let mut sources: HashMap<PathBuf, Vec<&Path>> = HashMap::new();
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            for unit in module.data.units.iter() {
+                let name = &unit.data.name;
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let deck = self
+                    .output
+                    .slides()
+                    .join(to_prefixed_tag(name, &prefix))
+                    .with_extension("md");
+                let page = self
+                    .output
+                    .book()
+                    .join("src")
+                    .join(to_tag(name))
+                    .with_extension("md");
+                let topics = unit.data.topics.iter().map(|t| &t.data);
+                sources
+                    .entry(deck)
+                    .or_default()
+                    .extend(topics.clone().filter_map(|t| t.content.path()));
+                sources.entry(page).or_default().extend(
+                    topics
+                        .flat_map(|t| t.exercises.iter())
+                        .filter_map(|e| e.data.description.as_deref()),
+                );
+            }
+        }
+        sources
+    }
+
+    /// The relative links in the Markdown files of the output in `out_dir` that lead to no
+    /// file or heading of it
+    fn broken_output_links(
+        &self,
+        out_dir: &Path,
+        files: &BTreeMap<PathBuf, Vec<u8>>,
+    ) -> Vec<BrokenLink> {
+        let sources = self.output_sources();
+        let book = out_dir.join(self.output.book());
+        let dirs: BTreeSet<&Path> = files
+            .keys()
+            .flat_map(|file| file.ancestors().skip(1))
+            .collect();
+        let mut broken = vec![];
+        for (path, content) in files {
+            let Ok(relative) = path.strip_prefix(out_dir) else {
+                continue;
+            };
+            if path.extension().is_none_or(|e| e != "md") {
+                continue;
+            }
+            let Ok(content) = std::str::from_utf8(content) else {
+                continue;
+            };
+            let output = Output {
+                out_dir,
+                files,
+                dirs: &dirs,
+                in_book: path.starts_with(&book),
+            };
+            for (line, target) in links(content) {
+                let Some(problem) = output.broken_link(path, content, target) else {
+                    continue;
+                };
+                let source = sources.get(relative).and_then(|sources| {
+                    sources.iter().find_map(|source| {
+                        let content = fs::read_to_string(source).ok()?;
+                        let (line, _) = links(&content).into_iter().find(|(_, t)| *t == target)?;
+                        Some((source, line))
+                    })
+                });
+                broken.push(match source {
+                    Some((source, line)) => BrokenLink::new(
+                        source,
+                        line,
+                        target,
+                        format!("{problem}, once rendered into {}", relative.display()),
+                    ),
+                    None => BrokenLink::new(path, line, target, problem),
+                });
+            }
+        }
+        broken
+    }
+}
+
+/// The files of a render, to check the links in it against
+struct Output<'o> {
+    out_dir: &'o Path,
+    files: &'o BTreeMap<PathBuf, Vec<u8>>,
+    /// The folders the files are in
+    dirs: &'o BTreeSet<&'o Path>,
+    /// Whether the links are in a page of the book, where headings can be linked to
+    in_book: bool,
+}
+
+impl Output<'_> {
+    /// What is wrong with a link in the output file at `file`, if anything
+    fn broken_link(&self, file: &Path, content: &str, target: &str) -> Option<String> {
+        // URLs and other schemes, like `mailto:`, and links within the site
+        let scheme = target.split_once(':').map(|(scheme, _)| scheme);
+        if is_url(target)
+            || scheme.is_some_and(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric()))
+            || target.starts_with('/')
+        {
+            return None;
+        }
+        let target = target.split('?').next().unwrap_or_default();
+        let (path, anchor) = match target.split_once('#') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (target, None),
+        };
+        let linked = match path.is_empty() {
+            true => file.to_path_buf(),
+            false => {
+                let linked = normalize(&file.parent().unwrap_or(Path::new("")).join(path));
+                if !linked.starts_with(self.out_dir) {
+                    return Some("leads out of the output folder".to_string());
+                }
+                // mdbook turns the pages into HTML files, which the book links to
+                let page = match self.in_book && linked.extension().is_some_and(|e| e == "html") {
+                    true => linked.with_extension("md"),
+                    false => linked,
+                };
+                if !self.files.contains_key(&page) && !self.dirs.contains(page.as_path()) {
+                    let in_output = page.strip_prefix(self.out_dir).unwrap_or(&page);
+                    return Some(format!(
+                        "refers to {}, which isn't in the output",
+                        in_output.display()
+                    ));
+                }
+                page
+            }
+        };
+        let anchor = anchor.filter(|_| self.in_book)?;
+        let linked_content = match linked == file {
+            true => content,
+            false => std::str::from_utf8(self.files.get(&linked)?).ok()?,
+        };
+        let has_heading = headings(linked_content).any(|h| heading_anchor(h) == anchor);
+        (!has_heading).then(|| match linked == file {
+            true => format!("refers to heading '#{anchor}', which is not in the page"),
+            false => {
+                let in_output = linked.strip_prefix(self.out_dir).unwrap_or(&linked);
+                format!(
+                    "refers to heading '#{anchor}', which is not in {}",
+                    in_output.display()
+                )
+            }
+        })
+    }
+}
+
+/// The path without `.` and `..` components, where the components they lead back from are
+/// left out
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
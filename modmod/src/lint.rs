@@ -0,0 +1,197 @@
+//! `modmod lint`: checks of a loaded track that find authoring mistakes before the track is
+//! rendered, without writing anything.
+//!
+//! Each [`Check`] has a name, which `--allow` takes to leave it out, and reports what it finds
+//! as [`Finding`]s of a [`Severity`]. The track is meant to be loaded leniently, so that
+//! content that can't be found is a `missing-content` warning of the load, which the
+//! [`MissingContent`] check reports as an error, instead of the first of them failing the
+//! load. All checks run, so that all problems are reported at once.
+
+use std::{collections::BTreeSet, fmt, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    check::CheckOptions,
+    report::{Warning, WarningCode},
+    Track,
+};
+
+/// How bad a [`Finding`] is: errors fail `modmod lint`, warnings don't
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        })
+    }
+}
+
+/// A problem a [`Check`] found in a track
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// The name of the check that found it
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// The file the problem is in, if any
+    pub file: Option<PathBuf>,
+    /// The line in the file, if known
+    pub line: Option<usize>,
+    /// The code of the warning it was made from, if it was, which `--allow` takes as well
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<WarningCode>,
+}
+
+impl Finding {
+    /// The finding of `check` about what `warning` is about
+    pub fn of_warning(check: &'static str, severity: Severity, warning: Warning) -> Self {
+        Self {
+            check,
+            severity,
+            message: warning.message,
+            file: warning.source,
+            line: warning.line,
+            code: Some(warning.code),
+        }
+    }
+}
+
+/// Like `error[missing-content]: <message> (<file>:<line>)`
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.check, self.message)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " ({}:{line})", file.display()),
+            (Some(file), None) => write!(f, " ({})", file.display()),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+/// A check of a loaded track, see [`CHECKS`]
+pub trait Check: Sync {
+    /// The name of the check, which `--allow` takes
+    fn name(&self) -> &'static str;
+    /// What the check reports, as `--list-checks` lists it
+    fn description(&self) -> &'static str;
+    /// The problems the check finds in `track`
+    fn run(&self, track: &Track) -> Vec<Finding>;
+}
+
+/// The checks `modmod lint` runs, in the order it reports their findings
+pub const CHECKS: &[&dyn Check] = &[&MissingContent, &EmptyObjectives, &CheckWarnings];
+
+/// Content that the definitions refer to, but that isn't on disk: topics, slides, exercise
+/// descriptions, images and templates
+pub struct MissingContent;
+
+impl Check for MissingContent {
+    fn name(&self) -> &'static str {
+        "missing-content"
+    }
+
+    fn description(&self) -> &'static str {
+        "Topics, slides, exercise descriptions, images and templates that the definitions refer to, but that don't exist"
+    }
+
+    fn run(&self, track: &Track) -> Vec<Finding> {
+        track
+            .warnings
+            .iter()
+            .filter(|warning| warning.code == WarningCode::MissingContent)
+            .map(|warning| Finding::of_warning(self.name(), Severity::Error, warning.clone()))
+            .collect()
+    }
+}
+
+/// Units of which no topic has objectives, which leaves the objectives slide of their deck
+/// empty
+pub struct EmptyObjectives;
+
+impl Check for EmptyObjectives {
+    fn name(&self) -> &'static str {
+        "empty-objectives"
+    }
+
+    fn description(&self) -> &'static str {
+        "Units of which none of the topics has objectives, so that their deck lists none"
+    }
+
+    fn run(&self, track: &Track) -> Vec<Finding> {
+        let mut findings = vec![];
+        for module in track.modules.iter().map(|m| &m.data) {
+            for unit in module.units.iter().map(|u| &u.data) {
+                if unit.topics.iter().all(|t| t.data.objectives.is_empty()) {
+                    findings.push(Finding {
+                        check: self.name(),
+                        severity: Severity::Warning,
+                        message: format!(
+                            "None of the topics of unit '{}' has objectives",
+                            unit.name
+                        ),
+                        file: Some(module.definition.clone()),
+                        line: None,
+                        code: None,
+                    });
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// The other warnings of `modmod check`, see [`Track::check`]
+pub struct CheckWarnings;
+
+impl Check for CheckWarnings {
+    fn name(&self) -> &'static str {
+        "check"
+    }
+
+    fn description(&self) -> &'static str {
+        "The warnings of modmod check, like units without exercises or broken links. Their codes can be allowed one by one"
+    }
+
+    fn run(&self, track: &Track) -> Vec<Finding> {
+        track
+            .check(&CheckOptions::default())
+            .into_iter()
+            // Reported as errors instead
+            .filter(|warning| warning.code != WarningCode::MissingContent)
+            .map(|warning| Finding::of_warning(self.name(), Severity::Warning, warning))
+            .collect()
+    }
+}
+
+/// The names that `allow` can hold: those of the checks and of the warning codes
+pub fn allowable() -> impl Iterator<Item = &'static str> {
+    CHECKS
+        .iter()
+        .map(|check| check.name())
+        .chain(WarningCode::ALL.iter().map(|code| code.as_str()))
+}
+
+impl Track {
+    /// Runs the [`CHECKS`] that `allow` doesn't name, and returns what they find, leaving out
+    /// the findings of the warning codes that `allow` names
+    pub fn lint(&self, allow: &BTreeSet<String>) -> Vec<Finding> {
+        CHECKS
+            .iter()
+            .filter(|check| !allow.contains(check.name()))
+            .flat_map(|check| check.run(self))
+            .filter(|finding| {
+                !finding
+                    .code
+                    .is_some_and(|code| allow.contains(code.as_str()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,312 @@
+//! Listing the structure of a track: its modules, units, topics and exercises, with their
+//! ids, so that they can be looked up for `--units` and scripts.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use error_stack::{Context, Report, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    conditional::Conditions,
+    stats::{unit_counts, Counts},
+    suggest, Module, Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ListError {}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to list the track")
+    }
+}
+
+impl error_stack::Context for ListError {}
+
+/// How deep into the track a listing goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ListDepth {
+    Modules,
+    Units,
+    Topics,
+    Exercises,
+}
+
+impl FromStr for ListDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "modules" => Ok(Self::Modules),
+            "units" => Ok(Self::Units),
+            "topics" => Ok(Self::Topics),
+            "exercises" => Ok(Self::Exercises),
+            _ => Err(format!(
+                "unknown level '{s}', expected modules, units, topics or exercises"
+            )),
+        }
+    }
+}
+
+/// The structure of a track, down to some [`ListDepth`]. The levels below it are left
+/// out, as are paths unless they were asked for.
+#[derive(Debug, Serialize)]
+pub struct TrackListing {
+    pub track: String,
+    pub modules: Vec<ModuleListing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleListing {
+    pub index: usize,
+    /// The name of the folder of the module's definition
+    pub id: String,
+    /// The label of the module, in the numbering style of the track
+    pub label: String,
+    pub name: String,
+    pub total: Counts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<UnitListing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitListing {
+    pub index: usize,
+    /// The id `--units` selects the unit with
+    pub id: String,
+    pub name: String,
+    /// Nested rather than flattened like in [`crate::stats::UnitStats`], as the counts
+    /// would clash with the topics of the unit
+    pub counts: Counts,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Like `no-slides` for units that don't render slides
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<&'static str>,
+    /// The definition of the module the unit is defined in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<TopicListing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicListing {
+    pub index: usize,
+    pub id: String,
+    pub name: String,
+    pub optional: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exercises: Vec<ExerciseListing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseListing {
+    pub index: usize,
+    pub id: String,
+    pub name: String,
+    pub optional: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl TrackListing {
+    /// Lists the track down to `depth`, or only the module `module`: its id, the part of
+    /// its id before the first dash, like `B` for `B-crate-engineering`, or its label.
+    /// With `paths`, the source files of each item are included. Durations are those of
+    /// [`crate::stats`], with conditional blocks resolved as when the track is rendered
+    /// without a profile.
+    pub fn collect(
+        track: &Track,
+        depth: ListDepth,
+        module: Option<&str>,
+        paths: bool,
+    ) -> Result<Self, ListError> {
+        let matches = |m: &Module| module.is_none_or(|module| module_matches(m, module));
+        if let Some(module) = module {
+            check_module::<ListError>(track, module)?;
+        }
+
+        let conditions = Conditions {
+            profile: None,
+            known_profiles: &track.profiles,
+            variables: &track.variables,
+        };
+        let path = |path: &Path| paths.then(|| path.display().to_string());
+        let mut modules = vec![];
+        for module in track.modules.iter().filter(|m| matches(&m.data)) {
+            let mut total = Counts::default();
+            let mut units = vec![];
+            for unit in module.data.units.iter() {
+                let counts =
+                    unit_counts(&unit.data, &conditions).change_context(ListError::default())?;
+                total += counts;
+                if depth < ListDepth::Units {
+                    continue;
+                }
+                let flags = [
+                    (!unit.data.render_slides, "no-slides"),
+                    (!unit.data.render_book, "no-book"),
+                    (unit.data.no_exercises, "no-exercises"),
+                ];
+                let topics = match depth >= ListDepth::Topics {
+                    true => unit
+                        .data
+                        .topics
+                        .iter()
+                        .map(|topic| TopicListing {
+                            index: topic.index,
+                            id: topic.data.id.clone(),
+                            name: topic.data.name.clone(),
+                            optional: topic.data.optional,
+                            path: path(&topic.data.definition),
+                            exercises: match depth >= ListDepth::Exercises {
+                                true => topic
+                                    .data
+                                    .exercises
+                                    .iter()
+                                    .map(|exercise| ExerciseListing {
+                                        index: exercise.index,
+                                        id: exercise.data.id.clone(),
+                                        name: exercise.data.name.clone(),
+                                        optional: exercise.data.optional,
+                                        path: path(&exercise.data.path),
+                                    })
+                                    .collect(),
+                                false => vec![],
+                            },
+                        })
+                        .collect(),
+                    false => vec![],
+                };
+                units.push(UnitListing {
+                    index: unit.index,
+                    id: unit.data.id(),
+                    name: unit.data.name.clone(),
+                    counts,
+                    tags: unit.data.tags.clone(),
+                    flags: flags
+                        .into_iter()
+                        .filter_map(|(set, flag)| set.then_some(flag))
+                        .collect(),
+                    path: path(&module.data.definition),
+                    topics,
+                });
+            }
+            modules.push(ModuleListing {
+                index: module.index,
+                id: module.data.id(),
+                label: module.data.label.display.clone(),
+                name: module.data.name.clone(),
+                total,
+                path: path(&module.data.definition),
+                units,
+            });
+        }
+        Ok(Self {
+            track: track.name.clone(),
+            modules,
+        })
+    }
+}
+
+/// Whether `module` is selected by `selector`: its id, the part of its id before the first
+/// dash, or its label
+pub(crate) fn module_matches(module: &Module, selector: &str) -> bool {
+    let id = module.id();
+    id == selector || id.split('-').next() == Some(selector) || module.label.display == selector
+}
+
+/// Fails if no module of the track is selected by `selector`, suggesting the id it may mean
+pub(crate) fn check_module<C: Context + Default>(track: &Track, selector: &str) -> Result<(), C> {
+    if track
+        .modules
+        .iter()
+        .any(|m| module_matches(&m.data, selector))
+    {
+        return Ok(());
+    }
+    let module_ids: Vec<_> = track.modules.iter().map(|m| m.data.id()).collect();
+    let ids: Vec<_> = module_ids.iter().map(|id| format!("'{id}'")).collect();
+    Err(Report::new(C::default()).attach_printable(format!(
+        "The track has no module '{selector}'. It has modules {}.{}",
+        ids.join(", "),
+        suggest::did_you_mean(selector, &module_ids)
+    )))
+}
+
+/// `90` becomes `1h30m`
+pub(crate) fn duration(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h{minutes}m"),
+    }
+}
+
+impl fmt::Display for TrackListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter, indent: usize, text: String, path: &Option<String>| {
+            write!(f, "{:indent$}{text}", "", indent = indent * 2)?;
+            if let Some(path) = path {
+                write!(f, "  ({path})")?;
+            }
+            writeln!(f)
+        };
+        writeln!(f, "Track '{}'", self.track)?;
+        for module in self.modules.iter() {
+            let name = format!("{} {}", module.label, module.name);
+            let text = format!(
+                "{} [{}] {}",
+                name.trim_start(),
+                module.id,
+                duration(module.total.estimated_minutes)
+            );
+            line(f, 1, text, &module.path)?;
+            for unit in module.units.iter() {
+                let number = match module.label.is_empty() {
+                    true => unit.index.to_string(),
+                    false => format!("{}.{}", module.label, unit.index),
+                };
+                let mut text = format!(
+                    "{number} {} [{}] {}",
+                    unit.name,
+                    unit.id,
+                    duration(unit.counts.estimated_minutes)
+                );
+                for tag in unit.tags.iter() {
+                    text.push_str(&format!(" tag:{tag}"));
+                }
+                if !unit.flags.is_empty() {
+                    text.push_str(&format!(" ({})", unit.flags.join(", ")));
+                }
+                line(f, 2, text, &unit.path)?;
+                for topic in unit.topics.iter() {
+                    let optional = match topic.optional {
+                        true => " (optional)",
+                        false => "",
+                    };
+                    let text = format!("{} {} [{}]{optional}", topic.index, topic.name, topic.id);
+                    line(f, 3, text, &topic.path)?;
+                    for exercise in topic.exercises.iter() {
+                        let optional = match exercise.optional {
+                            true => " (optional)",
+                            false => "",
+                        };
+                        let text = format!(
+                            "{} {} [{}]{optional}",
+                            exercise.index, exercise.name, exercise.id
+                        );
+                        line(f, 4, text, &exercise.path)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,58 +1,1031 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use std::{
     any::type_name,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt, fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use error_stack::{IntoReport, Result, ResultExt};
+use error_stack::{IntoReport, Report, Result, ResultExt};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::io::PathExt;
+use crate::{
+    analytics::Analytics,
+    catalog::ErrorKind,
+    date::Date,
+    diagnostic::Location,
+    frontmatter, git,
+    i18n::{split_localized, LocalizedText, Translations},
+    io::{normalize_text, PathExt},
+    migrate, parallel,
+    quiz::{self, QuizQuestion},
+    report::{Warning, WarningCode},
+    slides::is_theme_folder,
+    suggest,
+    summary::SummaryDeriver,
+    FailurePolicy,
+};
 
-use super::{Exercise, Module, Topic, Track, Unit};
+use super::{
+    to_prefixed_tag, to_tag, Course, DeriveSummaries, Exercise, License, Module, ModuleLabel,
+    ModuleNumbering, OutputLayout, Session, SharedCrate, Toolchain, Topic, TopicContent,
+    TopicImage, Track, Unit,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrackDef {
     pub name: String,
+    /// What the track is about, in a sentence or two, as listed in the catalog of tracks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The locale the content is written in, like `en`. Defaults to `en`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// The version of the course, like the edition of a quarter. See [`crate::version::CourseVersion`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The folder the paths to content are relative to, like those of the modules, relative to
+    /// the track definition. Defaults to the folder of the track definition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_root: Option<PathBuf>,
     #[serde(default)]
     pub modules: Vec<PathBuf>,
+    /// Globs, relative to the content root, of content that should
+    /// not be reported as orphaned even though the track doesn't refer to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orphan_ignore: Vec<String>,
+    /// Globs of external links that `modmod check --check-external` doesn't request, like
+    /// sites that refuse requests from scripts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_links_ignore: Vec<String>,
+    /// Named variants of the track, that select the content for different audiences
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Slide template for units that don't set their own, relative to the track definition
+    /// or as `name:<name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_slide_template: Option<PathBuf>,
+    /// Slide templates that units can refer to as `name:<name>`, relative to the track definition
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, PathBuf>,
+    /// Values that conditional content blocks can refer to as `var.<name>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    pub course: Course,
+    /// Where the output is written, relative to the output folder
+    #[serde(default)]
+    pub output: OutputLayout,
+    /// License of the material, with the path to its text relative to the track definition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+    /// The analytics service the deployed course reports its usage to, which replaces that of
+    /// the base track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analytics: Option<Analytics>,
+    /// The tools the exercises are worked on with, which replace those of the base track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<Toolchain>,
+    /// Derive summaries for topics that don't have one from their headings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derive_summaries: Option<DeriveSummaries>,
+    /// How modules are numbered. Defaults to numbers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module_numbering: Option<ModuleNumbering>,
+    /// Semver requirement on the version of modmod the track can be rendered with
+    #[serde(
+        default,
+        alias = "modmod-version",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modmod_version: Option<String>,
+    /// Track definition this track is based on, relative to this definition.
+    /// The modules of this track are added to those of the base track.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<PathBuf>,
+    /// Ids of modules of the base track that are left out of this track
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_modules: Vec<String>,
+    /// Units that are left out of this track, as `<module id>/<unit id>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_units: Vec<String>,
+    /// Units that are added to modules of this track
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add_units: Vec<AddUnitDef>,
+    /// Ids of modules in the order they appear in. Modules that are not listed
+    /// follow the listed ones in their original order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub module_order: Vec<String>,
+    /// Folders of library crates that exercises can depend on, relative to the track definition
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_crates: Vec<PathBuf>,
+    /// The tags units are allowed to have. Units can have any tag if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unit_tags: Vec<String>,
+    /// Whether search engines may index the deployed site, as the `robots.txt` written into
+    /// the output tells them. Defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexable: Option<bool>,
+}
+
+/// A unit that a track adds to one of its modules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddUnitDef {
+    /// Id of the module the unit is added to
+    pub module: String,
+    #[serde(flatten)]
+    pub unit: UnitDef,
+}
+
+impl PathTo<TrackDef> {
+    pub fn resolve(self, loader: &mut Loader) -> Result<Track, HydrateTrackError> {
+        // A new track starts with fresh warnings and include chain, as the loader may be shared
+        loader.warnings.clear();
+        loader.include_chain.clear();
+
+        let Merged {
+            def: PathTo {
+                data,
+                path: track_path,
+            },
+            bases,
+            unit_changes,
+            content_root,
+        } = self.merge(loader, loader.content_dir.clone())?;
+        let TrackDef {
+            name,
+            description,
+            locale,
+            version,
+            modules: module_paths,
+            orphan_ignore,
+            external_links_ignore,
+            profiles,
+            default_slide_template,
+            templates,
+            variables,
+            course,
+            output,
+            mut license,
+            analytics,
+            toolchain,
+            derive_summaries,
+            module_numbering,
+            shared_crates,
+            unit_tags,
+            indexable,
+            ..
+        } = data;
+
+        if let (Some(start), Some(end)) = (course.start_date, course.end_date) {
+            if end < start {
+                return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                    "Course end date {end} is before its start date {start}"
+                )));
+            }
+        }
+
+        if let Some(problems) = analytics
+            .as_ref()
+            .map(Analytics::problems)
+            .filter(|p| !p.is_empty())
+        {
+            let mut report = Report::new(HydrateTrackError);
+            for problem in problems {
+                report = report.attach_printable(problem);
+            }
+            return Err(
+                report.attach_printable(format!("In track definition at {}", track_path.display()))
+            );
+        }
+
+        let variables = resolve_variables(variables)
+            .attach_printable_lazy(|| format!("In track definition at {}", track_path.display()))?;
+
+        let mut modules = Vec::with_capacity(module_paths.len());
+        let base_path = match is_synthetic_source(&track_path) {
+            true => content_root.as_path(),
+            false => folder_of(&track_path),
+        };
+        loader.summaries = derive_summaries
+            .as_ref()
+            .map(SummaryDeriver::new)
+            .transpose()
+            .attach_printable_lazy(|| format!("In track definition at {}", track_path.display()))?;
+        loader.module_numbering = module_numbering.unwrap_or_default();
+        loader.locale = locale.unwrap_or_else(|| "en".to_string());
+        loader.templates.clear();
+        for (name, path) in templates {
+            let what = format!("Slide template '{name}'");
+            let path = resolve_path(base_path, &path, &what);
+            if let Some(path) = loader.tolerate(path, Some(&track_path))? {
+                loader.templates.insert(name, path);
+            }
+        }
+        let default_slide_template = default_slide_template
+            .map(|t| loader.resolve_template(base_path, &t, "Default slide template"))
+            .transpose()
+            .attach_printable_lazy(|| format!("In track definition at {}", track_path.display()));
+        let default_slide_template = loader
+            .tolerate(default_slide_template, Some(&track_path))?
+            .flatten();
+        if let Some(license) = &mut license {
+            let text = license
+                .text
+                .take()
+                .map(|t| resolve_path(base_path, &t, "License text"))
+                .transpose();
+            license.text = loader.tolerate(text, Some(&track_path))?.flatten();
+        }
+        loader.enter(&track_path)?;
+        let mut included = Included::default();
+        let mut indices = Indices::default();
+        loader.prefetch(&module_paths, base_path)?;
+        let mut unit_changes: Vec<_> = unit_changes.into_iter().map(Some).collect();
+        for module_path in module_paths {
+            let _span = tracing::info_span!("module", label = %module_id(&module_path)).entered();
+            let mut module = loader
+                .load_module(&module_path, Some(base_path))
+                .change_context(HydrateTrackError)?;
+            let id = module_id(&module_path);
+            for change in unit_changes.iter_mut() {
+                if let Some(change) = change.take_if(|c| c.module() == id) {
+                    change.apply(&mut module)?;
+                }
+            }
+            included.insert("Module", &module.path, &module_path, loader)?;
+            let module_index = indices
+                .assign(module.data.index, &module.path.to_string_lossy())
+                .attach_printable_lazy(|| {
+                    format!("In track definition at {}", track_path.display())
+                })?;
+            loader.enter(&module.path)?;
+            let module = module.resolve(module_index, loader);
+            loader.leave();
+            modules.push(module?);
+        }
+        loader.leave();
+
+        let shared_crates = resolve_shared_crates(&shared_crates, base_path)
+            .attach_printable_lazy(|| format!("In track definition at {}", track_path.display()))?;
+
+        let mut track = Track {
+            name,
+            description,
+            locale: loader.locale.clone(),
+            translations: BTreeSet::new(),
+            version,
+            modules,
+            module_numbering: loader.module_numbering,
+            orphan_ignore,
+            external_links_ignore,
+            profiles,
+            default_slide_template,
+            course,
+            license,
+            analytics,
+            toolchain,
+            output,
+            derive_summaries,
+            warnings: std::mem::take(&mut loader.warnings),
+            definition: track_path,
+            content_root,
+            templates: std::mem::take(&mut loader.templates),
+            variables,
+            bases,
+            shared_crates,
+            unit_tags,
+            indexable: indexable.unwrap_or(true),
+            load_duration: Duration::ZERO,
+        };
+        validate_profiles(&track)?;
+        validate_unit_tags(&track)?;
+        validate_aliases(&track)?;
+        validate_unique_names(&track)?;
+        track.translations = track.find_translations();
+        Ok(track)
+    }
+}
+
+/// A track definition with the track definitions it extends merged into it
+struct Merged {
+    /// The merged definition, with all paths made absolute, and with nothing left to extend
+    def: PathTo<TrackDef>,
+    /// The definitions that were merged in, nearest base first
+    bases: Vec<PathBuf>,
+    /// Changes to the units of the modules, in the order they are to be applied in
+    unit_changes: Vec<UnitChange>,
+    /// The folder the content paths of the outermost definition are relative to
+    content_root: PathBuf,
+}
+
+/// A change to the units of a module, which can only be applied once the module is loaded
+enum UnitChange {
+    Remove {
+        module: String,
+        unit: String,
+        source: PathBuf,
+    },
+    Add {
+        module: String,
+        unit: UnitDef,
+        source: PathBuf,
+    },
+}
+
+impl UnitChange {
+    fn module(&self) -> &str {
+        match self {
+            Self::Remove { module, .. } | Self::Add { module, .. } => module,
+        }
+    }
+
+    fn apply(self, module: &mut PathTo<ModuleDef>) -> Result<(), HydrateTrackError> {
+        let units = &mut module.data.units;
+        match self {
+            Self::Remove {
+                module: module_id,
+                unit,
+                source,
+            } => {
+                let Some(position) = units.iter().position(|u| to_tag(&u.name) == unit) else {
+                    return Err(Report::new(HydrateTrackError)
+                        .attach_printable(format!(
+                            "Unable to remove unit '{unit}' from module '{module_id}', as the module has no such unit. It has units {}.{}",
+                            unit_ids(units),
+                            suggest::did_you_mean(&unit, units.iter().map(|u| to_tag(&u.name)))
+                        ))
+                        .attach_printable(format!("In track definition at {}", source.display())));
+                };
+                units.remove(position);
+            }
+            Self::Add {
+                module: module_id,
+                unit,
+                source,
+            } => {
+                let id = to_tag(&unit.name);
+                if units.iter().any(|u| to_tag(&u.name) == id) {
+                    return Err(Report::new(HydrateTrackError)
+                        .attach_printable(format!(
+                            "Unable to add unit '{id}' to module '{module_id}', as the module already has a unit with that id"
+                        ))
+                        .attach_printable(format!("In track definition at {}", source.display())));
+                }
+                units.push(unit);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unit_ids(units: &[UnitDef]) -> String {
+    units
+        .iter()
+        .map(|u| format!("'{}'", to_tag(&u.name)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The id of a module is the name of the folder its definition is in
+fn module_id(path: &Path) -> String {
+    path.parent()
+        .and_then(Path::file_name)
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The folder of the definition at `path`, which the paths in it are relative to. A
+/// definition without a folder is in the current one.
+fn folder_of(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+fn module_ids(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| format!("'{}'", module_id(p)))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl PathTo<TrackDef> {
-    pub fn resolve(self) -> Result<Track, HydrateTrackError> {
+    /// Merges the track definitions this definition extends into it. The content paths of
+    /// this definition are resolved against `content_dir` if given, instead of its
+    /// `content_root`, while those of the definitions it extends are resolved as they define.
+    fn merge(
+        self,
+        loader: &mut Loader,
+        content_dir: Option<PathBuf>,
+    ) -> Result<Merged, HydrateTrackError> {
         let PathTo {
             data,
             path: track_path,
         } = self;
         let TrackDef {
             name,
-            modules: module_paths,
+            description,
+            locale,
+            version,
+            content_root,
+            modules,
+            orphan_ignore,
+            external_links_ignore,
+            profiles,
+            default_slide_template,
+            templates,
+            variables,
+            course,
+            output,
+            license,
+            analytics,
+            toolchain,
+            derive_summaries,
+            module_numbering,
+            modmod_version,
+            extends,
+            remove_modules,
+            remove_units,
+            add_units,
+            module_order,
+            shared_crates,
+            unit_tags,
+            indexable,
         } = data;
+        let in_track = || format!("In track definition at {}", track_path.display());
+        let base_path = match is_synthetic_source(&track_path) {
+            true => match &content_dir {
+                Some(dir) => resolve_path(Path::new(""), dir, "Content directory")
+                    .attach_printable_lazy(in_track)?,
+                None => {
+                    return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                        "The track definition is read from {}, so it isn't in a folder that the paths in it are relative to. Give that folder with --content-dir",
+                        track_path.display()
+                    )))
+                }
+            },
+            false => folder_of(&track_path).to_path_buf(),
+        };
+        let base_path = base_path.as_path();
 
-        let mut modules = Vec::with_capacity(module_paths.len());
-        let base_path = track_path.parent().unwrap();
-        for (module_path, module_index) in module_paths.into_iter().zip(1..) {
-            modules.push(
-                ModuleDef::load(&module_path, Some(base_path))
-                    .change_context(HydrateTrackError)?
-                    .resolve(module_index)?,
+        if let Some(requirement) = modmod_version {
+            check_modmod_version(&requirement, &track_path, loader)?;
+        }
+        let content_base = match (content_dir, content_root) {
+            (Some(dir), _) => resolve_path(Path::new(""), &dir, "Content directory"),
+            (None, Some(root)) => resolve_path(base_path, &root, "Content root"),
+            (None, None) => Ok(base_path.to_path_buf()),
+        }
+        .attach_printable_lazy(in_track)?;
+
+        let mut merged = match extends {
+            Some(extends) => {
+                let base = TrackDef::load(&extends, Some(base_path))
+                    .change_context(HydrateTrackError)
+                    .attach_printable_lazy(in_track)?;
+                loader.enter(&track_path)?;
+                let mut merged = base.merge(loader, None)?;
+                loader.leave();
+                merged.bases.insert(0, merged.def.path.clone());
+                merged
+            }
+            None => Merged {
+                def: TrackDef {
+                    name: String::new(),
+                    description: None,
+                    locale: None,
+                    version: None,
+                    content_root: None,
+                    modules: vec![],
+                    orphan_ignore: vec![],
+                    external_links_ignore: vec![],
+                    profiles: vec![],
+                    default_slide_template: None,
+                    templates: BTreeMap::new(),
+                    variables: BTreeMap::new(),
+                    course: Course::default(),
+                    output: OutputLayout::default(),
+                    license: None,
+                    analytics: None,
+                    toolchain: None,
+                    derive_summaries: None,
+                    module_numbering: None,
+                    modmod_version: None,
+                    extends: None,
+                    remove_modules: vec![],
+                    remove_units: vec![],
+                    add_units: vec![],
+                    module_order: vec![],
+                    shared_crates: vec![],
+                    unit_tags: vec![],
+                    indexable: None,
+                }
+                .with_path(track_path.clone()),
+                bases: vec![],
+                unit_changes: vec![],
+                content_root: PathBuf::new(),
+            },
+        };
+        let def = &mut merged.def.data;
+
+        for id in remove_modules {
+            let Some(position) = def.modules.iter().position(|m| module_id(m) == id) else {
+                return Err(Report::new(HydrateTrackError)
+                    .attach_printable(format!(
+                        "Unable to remove module '{id}', as the base track has no such module. It has modules {}.{}",
+                        module_ids(&def.modules),
+                        suggest::did_you_mean(&id, def.modules.iter().map(|m| module_id(m)))
+                    ))
+                    .attach_printable(in_track()));
+            };
+            def.modules.remove(position);
+            merged.unit_changes.retain(|c| c.module() != id);
+        }
+        for module in modules {
+            let id = module_id(&module);
+            if def.modules.iter().any(|m| module_id(m) == id) {
+                return Err(Report::new(HydrateTrackError)
+                    .attach_printable(format!(
+                        "Unable to add module {}, as the track already has a module with id '{id}'",
+                        module.display()
+                    ))
+                    .attach_printable(in_track()));
+            }
+            def.modules.push(content_base.join(module));
+        }
+
+        let mut ordered: Vec<PathBuf> = Vec::with_capacity(def.modules.len());
+        for id in module_order {
+            let Some(position) = def.modules.iter().position(|m| module_id(m) == id) else {
+                let problem = if ordered.iter().any(|m| module_id(m) == id) {
+                    "is listed more than once"
+                } else {
+                    "is not a module of the track"
+                };
+                return Err(Report::new(HydrateTrackError)
+                    .attach_printable(format!("Module '{id}' in the module order {problem}"))
+                    .attach_printable(in_track()));
+            };
+            ordered.push(def.modules.remove(position));
+        }
+        ordered.append(&mut def.modules);
+        def.modules = ordered;
+
+        let has_module = |def: &TrackDef, id: &str, what: &str| {
+            if def.modules.iter().any(|m| module_id(m) == id) {
+                return Ok(());
+            }
+            Err(Report::new(HydrateTrackError)
+                .attach_printable(format!(
+                    "Unable to {what}, as the track has no module '{id}'. It has modules {}.{}",
+                    module_ids(&def.modules),
+                    suggest::did_you_mean(id, def.modules.iter().map(|m| module_id(m)))
+                ))
+                .attach_printable(in_track()))
+        };
+        for unit in remove_units {
+            let Some((module, unit)) = unit.split_once('/') else {
+                return Err(Report::new(HydrateTrackError)
+                    .attach_printable(format!(
+                        "Unit '{unit}' to remove should be of the form '<module id>/<unit id>'"
+                    ))
+                    .attach_printable(in_track()));
+            };
+            has_module(def, module, &format!("remove unit '{unit}'"))?;
+            merged.unit_changes.push(UnitChange::Remove {
+                module: module.to_string(),
+                unit: unit.to_string(),
+                source: track_path.clone(),
+            });
+        }
+        for AddUnitDef { module, mut unit } in add_units {
+            has_module(def, &module, &format!("add unit '{}'", unit.name))?;
+            unit.rebase(&content_base);
+            merged.unit_changes.push(UnitChange::Add {
+                module,
+                unit,
+                source: track_path.clone(),
+            });
+        }
+
+        def.name = name;
+        def.orphan_ignore.extend(
+            orphan_ignore
+                .into_iter()
+                .map(|p| content_base.join(p).to_string_lossy().into_owned()),
+        );
+        for pattern in external_links_ignore {
+            if !def.external_links_ignore.contains(&pattern) {
+                def.external_links_ignore.push(pattern);
+            }
+        }
+        for profile in profiles {
+            if !def.profiles.contains(&profile) {
+                def.profiles.push(profile);
+            }
+        }
+        for tag in unit_tags {
+            if !def.unit_tags.contains(&tag) {
+                def.unit_tags.push(tag);
+            }
+        }
+        if let Some(template) = default_slide_template {
+            def.default_slide_template = Some(rebase_template(base_path, template));
+        }
+        def.templates.extend(
+            templates
+                .into_iter()
+                .map(|(name, path)| (name, base_path.join(path))),
+        );
+        def.variables.extend(variables);
+        def.course.merge(course);
+        def.output.merge(output);
+        if let Some(mut license) = license {
+            license.text = license.text.map(|t| base_path.join(t));
+            def.license = Some(license);
+        }
+        if analytics.is_some() {
+            def.analytics = analytics;
+        }
+        if toolchain.is_some() {
+            def.toolchain = toolchain;
+        }
+        if derive_summaries.is_some() {
+            def.derive_summaries = derive_summaries;
+        }
+        if module_numbering.is_some() {
+            def.module_numbering = module_numbering;
+        }
+        if indexable.is_some() {
+            def.indexable = indexable;
+        }
+        if version.is_some() {
+            def.version = version;
+        }
+        if description.is_some() {
+            def.description = description;
+        }
+        if locale.is_some() {
+            def.locale = locale;
+        }
+        def.shared_crates
+            .extend(shared_crates.into_iter().map(|c| content_base.join(c)));
+        merged.def.path = track_path;
+        merged.content_root = content_base;
+        Ok(merged)
+    }
+}
+
+/// Reads the names and versions of the shared crates from their `Cargo.toml`
+fn resolve_shared_crates(
+    paths: &[PathBuf],
+    base_path: &Path,
+) -> Result<Vec<SharedCrate>, HydrateTrackError> {
+    let mut shared_crates: Vec<SharedCrate> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let path = resolve_path(base_path, path, "Shared crate")?;
+        let manifest_path = path.join("Cargo.toml");
+        let manifest: toml::Value =
+            toml::from_str(&manifest_path.read_to_string::<HydrateTrackError>()?)
+                .into_report()
+                .change_context(HydrateTrackError)
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Unable to parse shared crate manifest at {}",
+                        manifest_path.display()
+                    )
+                })?;
+        let package = manifest.get("package");
+        let field = |key: &str| package.and_then(|p| p.get(key)).and_then(|v| v.as_str());
+        let Some(name) = field("name") else {
+            return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                "Shared crate manifest at {} has no package name",
+                manifest_path.display()
+            )));
+        };
+        if let Some(other) = shared_crates.iter().find(|c| c.name == name) {
+            return Err(Report::new(HydrateTrackError)
+                .attach_printable(format!(
+                    "Shared crates at {} and {} are both named '{name}'",
+                    other.path.display(),
+                    path.display()
+                ))
+                .attach(ErrorKind::DuplicateName));
+        }
+        shared_crates.push(SharedCrate {
+            name: name.to_string(),
+            version: field("version").map(str::to_string),
+            path,
+        });
+    }
+    Ok(shared_crates)
+}
+
+/// Refuses tracks that require a different version of modmod than this one.
+/// A requirement that can't be parsed is only reported as a warning.
+fn check_modmod_version(
+    requirement: &str,
+    track_path: &Path,
+    loader: &mut Loader,
+) -> Result<(), HydrateTrackError> {
+    let requirement = match semver::VersionReq::parse(requirement) {
+        Ok(requirement) => requirement,
+        Err(e) => {
+            loader.warnings.push(Warning::new(
+                WarningCode::InvalidModmodVersion,
+                format!("Unable to parse modmod_version requirement '{requirement}': {e}"),
+                Some(track_path),
+            ));
+            return Ok(());
+        }
+    };
+    let version = semver::Version::parse(crate::VERSION)
+        .into_report()
+        .change_context(HydrateTrackError)?;
+    if !requirement.matches(&version) {
+        return Err(Report::new(HydrateTrackError)
+            .attach_printable(format!(
+                "Track definition at {} requires modmod {requirement}, but this is modmod {version}",
+                track_path.display()
+            ))
+            .attach_printable("Please upgrade modmod to render this track")
+            .attach(ErrorKind::UnsupportedModmodVersion));
+    }
+    Ok(())
+}
+
+/// Makes sure units only use the tags of the track's vocabulary, if it has one
+fn validate_unit_tags(track: &Track) -> Result<(), HydrateTrackError> {
+    if track.unit_tags.is_empty() {
+        return Ok(());
+    }
+    let mut report: Option<Report<HydrateTrackError>> = None;
+    for unit in track.units() {
+        for tag in unit.tags.iter().filter(|t| !track.unit_tags.contains(t)) {
+            let error = format!("Unit '{}' has unknown tag '{tag}'", unit.name);
+            report = Some(match report {
+                Some(report) => report.attach_printable(error),
+                None => Report::new(HydrateTrackError)
+                    .attach_printable(format!(
+                        "Track defines unit tags [{}]",
+                        track.unit_tags.join(", ")
+                    ))
+                    .attach_printable(error),
+            });
+        }
+    }
+    report.map_or(Ok(()), Err)
+}
+
+/// Converts the track variables to the strings conditions compare them with
+fn resolve_variables(
+    variables: BTreeMap<String, toml::Value>,
+) -> Result<BTreeMap<String, String>, HydrateTrackError> {
+    variables
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                value => {
+                    return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                    "Variable '{name}' is a {}, but variables must be strings, numbers or booleans",
+                    value.type_str()
+                )))
+                }
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Makes sure all content only refers to profiles the track defines
+fn validate_profiles(track: &Track) -> Result<(), HydrateTrackError> {
+    let mut errors = vec![];
+    let mut check = |kind: &str, name: &str, profiles: &[String]| {
+        for profile in profiles {
+            if !track.profiles.contains(profile) {
+                errors.push(format!(
+                    "{kind} '{name}' refers to unknown profile '{profile}'"
+                ));
+            }
+        }
+    };
+
+    for unit in track.units() {
+        check("Unit", &unit.name, &unit.profiles);
+    }
+    for topic in track.topics() {
+        check("Topic", &topic.name, &topic.profiles);
+    }
+    for exercise in track.exercises() {
+        check("Exercise", &exercise.name, &exercise.profiles);
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    errors.dedup();
+    let mut report = Report::new(HydrateTrackError).attach_printable(format!(
+        "Track defines profiles [{}]",
+        track.profiles.join(", ")
+    ));
+    for error in errors {
+        report = report.attach_printable(error);
+    }
+    Err(report)
+}
+
+/// Makes sure that aliases are unambiguous: no alias is the id of a topic or
+/// exercise in the track, and no alias is used by more than one of them
+fn validate_aliases(track: &Track) -> Result<(), HydrateTrackError> {
+    let mut ids = HashMap::new();
+    for topic in track.topics() {
+        ids.insert(topic.id.as_str(), format!("topic '{}'", topic.name));
+    }
+    for exercise in track.exercises() {
+        ids.insert(
+            exercise.id.as_str(),
+            format!("exercise '{}'", exercise.name),
+        );
+    }
+
+    let mut errors = vec![];
+    let mut aliases: HashMap<&str, String> = HashMap::new();
+    let items = track
+        .topics()
+        .map(|t| (&t.aliases, format!("topic '{}'", t.name)))
+        .chain(
+            track
+                .exercises()
+                .map(|e| (&e.aliases, format!("exercise '{}'", e.name))),
+        );
+    for (item_aliases, item) in items {
+        for alias in item_aliases {
+            if let Some(live) = ids.get(alias.as_str()) {
+                errors.push(format!("Alias '{alias}' of {item} is the id of {live}"));
+            }
+            match aliases.get(alias.as_str()) {
+                // Topics can be included more than once
+                Some(other) if *other == item => {}
+                Some(other) => errors.push(format!(
+                    "Alias '{alias}' is used by both {other} and {item}"
+                )),
+                None => {
+                    aliases.insert(alias, item.clone());
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    errors.sort();
+    errors.dedup();
+    let mut report = Report::new(HydrateTrackError).attach(ErrorKind::DuplicateAlias);
+    for error in errors {
+        report = report.attach_printable(error);
+    }
+    Err(report)
+}
+
+/// Makes sure the names that content ends up under in the output are unique:
+/// exercise ids and package names, and the file names of book sections and slide decks
+fn validate_unique_names(track: &Track) -> Result<(), HydrateTrackError> {
+    let mut errors = vec![];
+    let mut check = |seen: &mut HashMap<String, String>, what: &str, name: String, item: String| {
+        match seen.get(&name) {
+            // Topics can be included more than once
+            Some(other) if *other == item => {}
+            Some(other) => errors.push(format!(
+                "{what} '{name}' is used by both {other} and {item}"
+            )),
+            None => {
+                seen.insert(name, item);
+            }
+        }
+    };
+
+    let mut exercise_ids = HashMap::new();
+    let mut packages = HashMap::new();
+    for topic in track.topics() {
+        for exercise in topic.exercises.iter().map(|e| &e.data) {
+            let item = format!(
+                "exercise '{}' in {}",
+                exercise.name,
+                topic.definition.display()
+            );
+            check(
+                &mut exercise_ids,
+                "Exercise id",
+                exercise.id.clone(),
+                item.clone(),
+            );
+            if let Some(package) = &exercise.package {
+                check(&mut packages, "Package name", package.clone(), item);
+            }
+        }
+    }
+
+    let mut sections = HashMap::new();
+    let mut decks = HashMap::new();
+    let mut labels = HashMap::new();
+    for module in track.modules.iter() {
+        // Module labels prefix the names of slide decks and exercise folders
+        check(
+            &mut labels,
+            "Module label",
+            module.data.label.path.clone(),
+            format!("module {}", module.data.definition.display()),
+        );
+        for unit in module.data.units.iter() {
+            let item = format!(
+                "unit '{}' in {}",
+                unit.data.name,
+                module.data.definition.display()
+            );
+            check(
+                &mut sections,
+                "Book section file name",
+                to_tag(&unit.data.name),
+                item.clone(),
+            );
+            let deck_prefix = format!("{}_{}", module.data.label.path, unit.index);
+            check(
+                &mut decks,
+                "Slide deck file name",
+                to_prefixed_tag(&unit.data.name, deck_prefix),
+                item,
             );
         }
+    }
 
-        Ok(Track { name, modules })
+    if errors.is_empty() {
+        return Ok(());
     }
+    errors.sort();
+    errors.dedup();
+    let mut report = Report::new(HydrateTrackError).attach(ErrorKind::DuplicateName);
+    for error in errors {
+        report = report.attach_printable(error);
+    }
+    Err(report)
+}
+
+/// The package name in the manifest of a crate. Manifests that can't be read are
+/// reported when the exercise is rendered.
+fn package_name(crate_path: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(crate_path.join("Cargo.toml")).ok()?;
+    let manifest: toml::Value = toml::from_str(&manifest).ok()?;
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
 }
 
+/// A collection of tracks that are rendered together
 #[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceDef {
+    #[serde(default)]
+    pub tracks: Vec<PathBuf>,
+}
+
+impl PathTo<WorkspaceDef> {
+    /// Paths to the track definitions in this workspace, relative to the workspace file
+    pub fn track_paths(&self) -> Vec<PathBuf> {
+        let base_path = folder_of(&self.path);
+        self.data.tracks.iter().map(|t| base_path.join(t)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleDef {
     pub name: String,
     pub description: String,
+    /// The number of the module in the track. Defaults to the number of the previous module plus one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Label of the module in titles and output paths, instead of the one the
+    /// numbering of the track gives it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
     #[serde(default)]
     pub units: Vec<UnitDef>,
 }
 
 impl PathTo<ModuleDef> {
-    fn resolve(self, module_index: usize) -> Result<Indexed<Module>, HydrateTrackError> {
+    fn resolve(
+        self,
+        module_index: usize,
+        loader: &mut Loader,
+    ) -> Result<Indexed<Module>, HydrateTrackError> {
         let PathTo {
             data: def,
             path: module_path,
@@ -60,82 +1033,609 @@ impl PathTo<ModuleDef> {
         let ModuleDef {
             name,
             description,
+            index: _,
+            label,
             units: unit_defs,
         } = def;
 
         let mut units = Vec::with_capacity(unit_defs.len());
-        let base_path = module_path.parent().unwrap();
-        for (unit_def, unit_index) in unit_defs.into_iter().zip(1..) {
-            units.push(unit_def.resolve(unit_index, base_path)?);
+        let mut indices = Indices::default();
+        for unit_def in unit_defs {
+            let _span = tracing::info_span!("unit", label = %to_tag(&unit_def.name)).entered();
+            let in_module = || {
+                format!(
+                    "In module definition at {path}",
+                    path = module_path.to_string_lossy()
+                )
+            };
+            let unit_index = indices
+                .assign(unit_def.index, &format!("unit '{}'", unit_def.name))
+                .attach_printable_lazy(in_module)?;
+            units.push(
+                unit_def
+                    .resolve(unit_index, &module_path, loader)
+                    .attach_printable_lazy(in_module)?,
+            );
         }
 
         Ok(Module {
             name,
             description,
+            label: ModuleLabel::new(loader.module_numbering, module_index, label),
             units,
+            definition: module_path,
         }
         .with_index(module_index))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnitDef {
     pub name: String,
+    /// The number of the unit in the module. Defaults to the number of the previous unit plus one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
     pub template: Option<PathBuf>,
+    /// The Slidev theme of the unit's deck: the name of a theme package, or the path of a
+    /// theme folder relative to the definition if it starts with `.`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<TopicRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sessions: Vec<SessionDef>,
+    /// Profiles this item is rendered for. Rendered for all profiles if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Delivery metadata, like the day the unit is taught on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether a slide deck is generated for the unit
+    #[serde(
+        default = "crate::load::serde_defaults::r#true",
+        skip_serializing_if = "crate::load::serde_defaults::is_true"
+    )]
+    pub render_slides: bool,
+    /// Whether the unit gets a page in the exercise book
+    #[serde(
+        default = "crate::load::serde_defaults::r#true",
+        skip_serializing_if = "crate::load::serde_defaults::is_true"
+    )]
+    pub render_book: bool,
+    /// Confirms that the unit has no exercises on purpose
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub no_exercises: bool,
+}
+
+impl Default for UnitDef {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            index: None,
+            template: None,
+            theme: None,
+            topics: vec![],
+            sessions: vec![],
+            profiles: vec![],
+            tags: vec![],
+            render_slides: true,
+            render_book: true,
+            no_exercises: false,
+        }
+    }
+}
+
+impl UnitDef {
+    /// Makes the paths in the definition absolute, so that it can be moved to another definition file
+    fn rebase(&mut self, base_path: &Path) {
+        if let Some(template) = self.template.take() {
+            self.template = Some(rebase_template(base_path, template));
+        }
+        if let Some(theme) = self.theme.as_mut().filter(|t| is_theme_folder(t)) {
+            *theme = base_path.join(&*theme).to_string_lossy().into_owned();
+        }
+        for topic in self.topics.iter_mut() {
+            if let TopicRef::Path(path) = topic {
+                *path = base_path.join(&*path);
+            }
+        }
+        for session in self.sessions.iter_mut() {
+            for topic in session.topics.iter_mut() {
+                *topic = base_path.join(&*topic);
+            }
+        }
+    }
+}
+
+/// An entry in the topic list of a unit: either a path to a topic definition
+/// file, or a small topic that is defined inline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TopicRef {
+    Path(PathBuf),
+    Inline(Box<InlineTopicDef>),
+}
+
+impl<'de> Deserialize<'de> for TopicRef {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error;
+        // Deserializing via a Value rather than using an untagged enum
+        // keeps the error messages of malformed inline topics intact
+        match toml::Value::deserialize(deserializer)? {
+            toml::Value::String(path) => Ok(Self::Path(path.into())),
+            table @ toml::Value::Table(_) => table
+                .try_into()
+                .map(|inline| Self::Inline(Box::new(inline)))
+                .map_err(D::Error::custom),
+            other => Err(D::Error::custom(format!(
+                "expected a topic path or an inline topic table, found a {}",
+                other.type_str()
+            ))),
+        }
+    }
+}
+
+impl From<PathBuf> for TopicRef {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+/// A topic that is defined directly in the unit definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineTopicDef {
+    pub name: String,
+    /// The slide content of the topic
     #[serde(default)]
+    pub content: String,
+    /// Each in the locale of the track, or in several locales, see [`LocalizedText`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summary: Vec<LocalizedText>,
+    /// Each in the locale of the track, or in several locales, see [`LocalizedText`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objectives: Vec<LocalizedText>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub further_reading: Vec<String>,
+    /// Terms the topic introduces, with their definitions
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub glossary: BTreeMap<String, String>,
+    /// Questions for the quiz at the end of the book chapter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiz: Vec<QuizQuestion>,
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub optional: bool,
+    /// The minutes the topic takes to teach, instead of its share of its session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    /// Profiles this item is rendered for. Rendered for all profiles if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Not supported for inline topics, only accepted to give a clear error
+    #[serde(default, skip_serializing)]
+    exercises: Option<toml::Value>,
+    /// Not supported for inline topics, only accepted to give a clear error
+    #[serde(default, skip_serializing)]
+    images: Option<toml::Value>,
+}
+
+impl InlineTopicDef {
+    fn resolve(
+        self,
+        topic_index: usize,
+        unit_name: &str,
+        definition: &Path,
+        locale: &str,
+        summaries: Option<&SummaryDeriver>,
+    ) -> Result<Indexed<Topic>, HydrateTrackError> {
+        let InlineTopicDef {
+            name,
+            content,
+            summary,
+            objectives,
+            further_reading,
+            glossary,
+            quiz,
+            optional,
+            duration_minutes,
+            profiles,
+            exercises,
+            images,
+        } = self;
+
+        for (field, value) in [("exercises", exercises), ("images", images)] {
+            if value.is_some() {
+                return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                    "Inline topic '{name}' in unit '{unit_name}' defines {field}, which inline topics can't have. Move the topic to its own topic.toml instead."
+                )));
+            }
+        }
+        check_quiz(&quiz, &name, definition)?;
+        let in_topic = || format!("In inline topic '{name}' at {}", definition.display());
+        let mut translations = Translations::new();
+        let objectives = split_localized(objectives, locale, &mut translations)
+            .change_context(HydrateTrackError)
+            .attach_printable_lazy(in_topic)?;
+        let mut summary = split_localized(summary, locale, &mut translations)
+            .change_context(HydrateTrackError)
+            .attach_printable_lazy(in_topic)?;
+
+        let mut summary_derived = false;
+        if summary.is_empty() {
+            if let Some(deriver) = summaries {
+                summary = deriver.derive(&content);
+                summary_derived = !summary.is_empty();
+            }
+        }
+
+        let id = to_tag(&name);
+        Ok(Topic {
+            name,
+            exercises: vec![],
+            summary,
+            summary_derived,
+            objectives,
+            translations,
+            content: TopicContent::Inline(content),
+            further_reading,
+            glossary,
+            quiz,
+            images: vec![],
+            optional,
+            duration_minutes,
+            authors: vec![],
+            license: None,
+            last_reviewed: None,
+            profiles,
+            id,
+            aliases: vec![],
+            definition: definition.to_path_buf(),
+        }
+        .with_index(topic_index))
+    }
+}
+
+/// A scheduled part of a unit, like a lecture or a lab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    /// Topics covered in this session, as they appear in the unit's `topics` list
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub topics: Vec<PathBuf>,
+    /// Names of the exercises done in this session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exercises: Vec<String>,
 }
 
 impl UnitDef {
     fn resolve(
         self,
         unit_index: usize,
-        base_path: &Path,
+        module_path: &Path,
+        loader: &mut Loader,
     ) -> Result<Indexed<Unit>, HydrateTrackError> {
+        let base_path = folder_of(module_path);
         let UnitDef {
             name,
+            index: _,
             template,
+            theme,
             topics: topic_paths,
+            sessions: session_defs,
+            profiles,
+            tags,
+            render_slides,
+            render_book,
+            no_exercises,
         } = self;
 
+        let topic_paths = expand_topic_paths(&topic_paths, base_path)
+            .attach_printable_lazy(|| format!("In unit '{name}'"))?;
+
         let mut topics = Vec::with_capacity(topic_paths.len());
-        for (topic_path, topic_index) in topic_paths.into_iter().zip(1..) {
-            topics.push(
-                TopicDef::load(&topic_path, Some(base_path))
-                    .change_context(HydrateTrackError)?
-                    .resolve(topic_index)?,
-            );
+        let mut topic_keys = Vec::with_capacity(topic_paths.len());
+        let mut included = Included::default();
+        for (topic_ref, topic_index) in topic_paths.into_iter().zip(1..) {
+            match topic_ref {
+                TopicRef::Path(topic_path) => {
+                    let topic = loader
+                        .load_topic(&topic_path, Some(base_path))
+                        .change_context(HydrateTrackError)?;
+                    included
+                        .insert("Topic", &topic.path, &topic_path, loader)
+                        .attach_printable_lazy(|| format!("In unit '{name}'"))?;
+                    topics.push(topic.resolve(topic_index, loader)?);
+                    topic_keys.push(topic_path);
+                }
+                TopicRef::Inline(inline) => {
+                    // Sessions refer to inline topics by name
+                    topic_keys.push(PathBuf::from(&inline.name));
+                    topics.push(inline.resolve(
+                        topic_index,
+                        &name,
+                        module_path,
+                        &loader.locale,
+                        loader.summaries.as_ref(),
+                    )?);
+                }
+            }
         }
 
-        let template = match template {
-            Some(t) => Some(
-                base_path
-                    .join(t)
-                    .canonicalize()
-                    .into_report()
-                    .change_context(HydrateTrackError)?,
-            ),
-            None => None,
+        let sessions = resolve_sessions(&name, session_defs, &topic_keys, &topics)?;
+
+        let template = template
+            .map(|t| loader.resolve_template(base_path, &t, "Slide template"))
+            .transpose()
+            .attach_printable_lazy(|| format!("In unit '{name}'"));
+        let template = loader.tolerate(template, Some(module_path))?.flatten();
+        // Theme folders are copied from wherever the slides are rendered
+        let theme = match theme {
+            Some(theme) if is_theme_folder(&theme) => {
+                let folder = resolve_path(base_path, Path::new(&theme), "Slide theme folder")
+                    .map(|folder| folder.to_string_lossy().into_owned())
+                    .attach_printable_lazy(|| format!("In unit '{name}'"));
+                loader.tolerate(folder, Some(module_path))?
+            }
+            theme => theme,
         };
 
         Ok(Unit {
             name,
             template,
+            theme,
             topics,
+            sessions,
+            profiles,
+            tags,
+            render_slides,
+            render_book,
+            no_exercises,
         }
         .with_index(unit_index))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Expands the topic paths of a unit. Entries can be paths to topic definition files,
+/// directories containing a `topic.toml`, or glob patterns matching `topic.toml` files or
+/// directories containing one, which may start with `../`.
+/// Glob matches are ordered lexicographically, and topics that are matched more than
+/// once keep the position of their first occurrence, so that listing a topic before
+/// a glob that also matches it puts it first.
+fn expand_topic_paths(
+    entries: &[TopicRef],
+    base_path: &Path,
+) -> Result<Vec<TopicRef>, HydrateTrackError> {
+    let topic_file = |path: PathBuf| {
+        if base_path.join(&path).is_dir() {
+            path.join("topic.toml")
+        } else {
+            path
+        }
+    };
+
+    let mut contents: HashMap<String, Vec<String>> = HashMap::new();
+    let mut expanded = vec![];
+    for entry in entries {
+        let entry = match entry {
+            TopicRef::Path(entry) => entry,
+            inline @ TopicRef::Inline(_) => {
+                expanded.push(inline.clone());
+                continue;
+            }
+        };
+        let pattern = entry.to_string_lossy().replace('\\', "/");
+        if !is_glob(&pattern) {
+            expanded.push(topic_file(entry.clone()).into());
+            continue;
+        }
+
+        // The glob is matched in the folder its leading components without wildcards point
+        // to, so that `../shared/*` matches the topics next to the folder of the unit
+        let components: Vec<&str> = pattern.split('/').filter(|c| *c != ".").collect();
+        let literal = components.iter().take_while(|c| !is_glob(c)).count();
+        let prefix = components[..literal].join("/");
+        let rest = components[literal..].join("/");
+        let glob = globset::GlobBuilder::new(&rest)
+            .literal_separator(true)
+            .build()
+            .into_report()
+            .attach_printable_lazy(|| format!("Error parsing topic glob '{pattern}'"))
+            .change_context(HydrateTrackError)?
+            .compile_matcher();
+
+        let dir = base_path.join(&prefix);
+        if !dir.is_dir() {
+            return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                "Topic glob '{pattern}' does not match any topic, as folder {} doesn't exist",
+                dir.display()
+            )));
+        }
+        // Relative paths of everything below the folder, with forward slashes and sorted,
+        // so that the expansion doesn't depend on the platform
+        if !contents.contains_key(&prefix) {
+            let dir_content = dir.get_dir_content::<HydrateTrackError>()?;
+            let mut c: Vec<String> = dir_content
+                .directories
+                .iter()
+                .chain(dir_content.files.iter())
+                .filter_map(|p| {
+                    let relative = Path::new(p).strip_prefix(&dir).ok()?;
+                    let parts: Vec<_> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect();
+                    Some(parts.join("/"))
+                })
+                .filter(|p| !p.is_empty())
+                .collect();
+            c.sort();
+            contents.insert(prefix.clone(), c);
+        }
+
+        // Only topic definitions and the folders that have one are topics
+        let matches: Vec<_> = contents[&prefix]
+            .iter()
+            .filter(|p| glob.is_match(p))
+            .map(|p| Path::new(&prefix).join(p))
+            .filter(|p| {
+                let full = base_path.join(p);
+                match full.is_dir() {
+                    true => full.join("topic.toml").is_file(),
+                    false => p.file_name().is_some_and(|name| name == "topic.toml"),
+                }
+            })
+            .map(topic_file)
+            .map(TopicRef::from)
+            .collect();
+
+        if matches.is_empty() {
+            return Err(Report::new(HydrateTrackError)
+                .attach_printable(format!("Topic glob '{pattern}' does not match any topic")));
+        }
+        expanded.extend(matches);
+    }
+
+    // Remove topics matched more than once, keeping the first occurrence
+    let mut seen = std::collections::HashSet::new();
+    expanded.retain(|topic_ref| match topic_ref {
+        TopicRef::Path(p) => {
+            let key = base_path
+                .join(p)
+                .canonicalize()
+                .unwrap_or(base_path.join(p));
+            seen.insert(key)
+        }
+        TopicRef::Inline(_) => true,
+    });
+
+    Ok(expanded)
+}
+
+/// Resolves the sessions of a unit, making sure every topic and exercise of the unit is
+/// part of exactly one session. Units that don't define sessions get a single implicit one.
+fn resolve_sessions(
+    unit_name: &str,
+    session_defs: Vec<SessionDef>,
+    topic_paths: &[PathBuf],
+    topics: &[Indexed<Topic>],
+) -> Result<Vec<Session>, HydrateTrackError> {
+    let exercise_names = || {
+        topics
+            .iter()
+            .flat_map(|t| t.data.exercises.iter())
+            .map(|e| e.data.name.clone())
+    };
+
+    if session_defs.is_empty() {
+        return Ok(vec![Session {
+            name: unit_name.to_string(),
+            duration_minutes: None,
+            topics: topics.iter().map(|t| t.data.name.clone()).collect(),
+            exercises: exercise_names().collect(),
+            implicit: true,
+        }]);
+    }
+
+    let mut topic_sessions: HashMap<&Path, &str> = HashMap::new();
+    let mut exercise_sessions: HashMap<&str, &str> = HashMap::new();
+    let mut errors = vec![];
+
+    for session in session_defs.iter() {
+        for topic_path in session.topics.iter() {
+            if !topic_paths.contains(topic_path) {
+                errors.push(format!(
+                    "Session '{}' refers to topic {}, which is not part of the unit",
+                    session.name,
+                    topic_path.to_string_lossy()
+                ));
+            } else if let Some(other) = topic_sessions.insert(topic_path, &session.name) {
+                errors.push(format!(
+                    "Topic {} is assigned to both session '{other}' and session '{}'",
+                    topic_path.to_string_lossy(),
+                    session.name
+                ));
+            }
+        }
+        for exercise in session.exercises.iter() {
+            if !exercise_names().any(|e| &e == exercise) {
+                errors.push(format!(
+                    "Session '{}' refers to exercise '{exercise}', which is not part of the unit",
+                    session.name,
+                ));
+            } else if let Some(other) = exercise_sessions.insert(exercise, &session.name) {
+                errors.push(format!(
+                    "Exercise '{exercise}' is assigned to both session '{other}' and session '{}'",
+                    session.name
+                ));
+            }
+        }
+    }
+
+    for topic_path in topic_paths {
+        if !topic_sessions.contains_key(topic_path.as_path()) {
+            errors.push(format!(
+                "Topic {} is not assigned to any session",
+                topic_path.to_string_lossy()
+            ));
+        }
+    }
+    for exercise in exercise_names() {
+        if !exercise_sessions.contains_key(exercise.as_str()) {
+            errors.push(format!(
+                "Exercise '{exercise}' is not assigned to any session"
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        let mut report = Report::new(HydrateTrackError)
+            .attach_printable(format!("Invalid sessions in unit '{unit_name}'"));
+        for error in errors {
+            report = report.attach_printable(error);
+        }
+        return Err(report);
+    }
+
+    // Sessions only refer to topics of the unit, see above
+    let topic_name = |path: &PathBuf| {
+        topic_paths
+            .iter()
+            .position(|p| p == path)
+            .and_then(|i| topics.get(i))
+            .map(|topic| topic.data.name.clone())
+            .unwrap_or_default()
+    };
+
+    Ok(session_defs
+        .into_iter()
+        .map(|session| Session {
+            topics: session.topics.iter().map(topic_name).collect(),
+            name: session.name,
+            duration_minutes: session.duration_minutes,
+            exercises: session.exercises,
+            implicit: false,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopicDef {
     pub name: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub exercises: Vec<ExerciseDef>,
+    /// Each in the locale of the track, or in several locales, see [`LocalizedText`]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub summary: Vec<String>,
+    pub summary: Vec<LocalizedText>,
+    /// Each in the locale of the track, or in several locales, see [`LocalizedText`]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub objectives: Vec<String>,
+    pub objectives: Vec<LocalizedText>,
     #[serde(
         default = "crate::load::serde_defaults::topic_slides_md",
         skip_serializing_if = "crate::load::serde_defaults::is_topic_slides_md"
@@ -143,6 +1643,34 @@ pub struct TopicDef {
     pub content: PathBuf,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub further_reading: Vec<String>,
+    /// Terms the topic introduces, with their definitions
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub glossary: BTreeMap<String, String>,
+    /// Questions for the quiz at the end of the book chapter, see [`crate::quiz`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiz: Vec<QuizQuestion>,
+    /// Former ids of this topic, from before it was renamed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Images used by the slides, relative to the topic definition. Images in the
+    /// `images` folder next to the topic definition are added automatically.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub optional: bool,
+    /// The minutes the topic takes to teach, instead of its share of its session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// SPDX identifier of the license of this topic, if it differs from the track's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reviewed: Option<Date>,
+    /// Profiles this item is rendered for. Rendered for all profiles if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
 }
 
 impl Default for TopicDef {
@@ -154,12 +1682,26 @@ impl Default for TopicDef {
             objectives: Default::default(),
             content: serde_defaults::topic_slides_md(),
             further_reading: Default::default(),
+            glossary: Default::default(),
+            quiz: Default::default(),
+            aliases: Default::default(),
+            images: Default::default(),
+            optional: Default::default(),
+            duration_minutes: Default::default(),
+            authors: Default::default(),
+            license: Default::default(),
+            last_reviewed: Default::default(),
+            profiles: Default::default(),
         }
     }
 }
 
 impl PathTo<TopicDef> {
-    fn resolve(self, topic_index: usize) -> Result<Indexed<Topic>, HydrateTrackError> {
+    fn resolve(
+        self,
+        topic_index: usize,
+        loader: &mut Loader,
+    ) -> Result<Indexed<Topic>, HydrateTrackError> {
         let PathTo {
             data: def,
             path: topic_path,
@@ -171,51 +1713,170 @@ impl PathTo<TopicDef> {
             summary,
             objectives,
             content,
-            further_reading,
+            mut further_reading,
+            glossary,
+            quiz,
+            aliases,
+            images: declared_images,
+            optional,
+            duration_minutes,
+            authors,
+            license,
+            last_reviewed,
+            profiles,
         } = def;
 
+        check_quiz(&quiz, &name, &topic_path)?;
+        let in_topic = || format!("In topic '{name}' at {}", topic_path.display());
+        let mut translations = Translations::new();
+        let mut objectives = split_localized(objectives, &loader.locale, &mut translations)
+            .change_context(HydrateTrackError)
+            .attach_printable_lazy(in_topic)?;
+        let mut summary = split_localized(summary, &loader.locale, &mut translations)
+            .change_context(HydrateTrackError)
+            .attach_printable_lazy(in_topic)?;
+
         let mut exercises = Vec::new();
-        let base_path = topic_path.parent().unwrap();
+        let base_path = folder_of(&topic_path);
         for (exercise_def, exercise_index) in exercise_defs.into_iter().zip(1..) {
-            exercises.push(exercise_def.resolve(exercise_index, base_path)?)
+            let exercise = exercise_def.resolve(exercise_index, base_path, loader);
+            exercises.extend(loader.tolerate(exercise, Some(&topic_path))?);
         }
 
-        let content = base_path
-            .join(content)
-            .canonicalize()
-            .into_report()
-            .change_context(HydrateTrackError)?;
-
-        let images = base_path.join("images");
-        let images = images
-            .is_dir()
-            .then_some(
-                images
-                    .get_dir_content()
-                    .map(|c| c.files.into_iter().map(PathBuf::from)),
-            )
-            .transpose()?
-            .into_iter()
-            .flatten()
-            .collect();
+        let mut summary_derived = false;
+        let content_path = resolve_path(base_path, &content, "Topic content");
+        let placeholder =
+            || TopicContent::Inline(format!("# TODO: missing slides for topic {name}"));
+        let content = match loader.tolerate(content_path, Some(&topic_path))? {
+            Some(content) if !loader.read_content => TopicContent::File(content),
+            Some(content) => {
+                // Metadata can also be set in the frontmatter of the content file, but not in both places
+                let content_text = loader.content(&content)?;
+                let split = frontmatter::split(&content_text)
+                    .change_context(HydrateTrackError)
+                    .attach_printable_lazy(|| format!("In topic content at {}", content.display()))
+                    .attach_lazy(|| Location::file(&content))?;
+                let mut slides = content_text.as_str();
+                if let Some((frontmatter, rest)) = split {
+                    slides = rest;
+                    for (key, from_frontmatter, from_toml) in [
+                        ("objectives", frontmatter.objectives, &mut objectives),
+                        ("summary", frontmatter.summary, &mut summary),
+                        (
+                            "further_reading",
+                            frontmatter.further_reading,
+                            &mut further_reading,
+                        ),
+                    ] {
+                        let Some(from_frontmatter) = from_frontmatter else {
+                            continue;
+                        };
+                        if !from_toml.is_empty() {
+                            return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                                "Topic '{name}' defines {key} both in {} and in the frontmatter of {}",
+                                topic_path.display(),
+                                content.display()
+                            )));
+                        }
+                        *from_toml = from_frontmatter;
+                    }
+                }
+
+                if summary.is_empty() {
+                    if let Some(deriver) = &loader.summaries {
+                        summary = deriver.derive(slides);
+                        summary_derived = !summary.is_empty();
+                    }
+                }
+
+                if slides.trim().is_empty() && loader.policy == FailurePolicy::Lenient {
+                    loader.warnings.push(Warning::new(
+                        WarningCode::EmptyTopic,
+                        format!("Topic '{name}' has no slides"),
+                        Some(&content),
+                    ));
+                    placeholder()
+                } else {
+                    TopicContent::File(content)
+                }
+            }
+            None => placeholder(),
+        };
+
+        let mut images = Vec::with_capacity(declared_images.len());
+        for image in declared_images {
+            let path = resolve_path(base_path, &image, "Image");
+            if let Some(path) = loader.tolerate(path, Some(&topic_path))? {
+                images.push(TopicImage {
+                    path,
+                    discovered: false,
+                });
+            }
+        }
+        let images_dir = base_path.join("images");
+        if images_dir.is_dir() {
+            let mut discovered: Vec<_> = images_dir
+                .get_dir_content()?
+                .files
+                .into_iter()
+                .map(PathBuf::from)
+                .filter(|path| is_image(path))
+                .collect();
+            discovered.sort();
+            for path in discovered {
+                if !images.iter().any(|image| image.path == path) {
+                    images.push(TopicImage {
+                        path,
+                        discovered: true,
+                    });
+                }
+            }
+        }
+
+        let id = topic_path
+            .parent()
+            .and_then(Path::file_name)
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| to_tag(&name));
 
         Ok(Topic {
             name,
             exercises,
             summary,
+            summary_derived,
             objectives,
+            translations,
             content,
             further_reading,
+            glossary,
+            quiz,
             images,
+            optional,
+            duration_minutes,
+            authors,
+            license,
+            last_reviewed,
+            profiles,
+            id,
+            aliases,
+            definition: topic_path,
         }
         .with_index(topic_index))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExerciseDef {
     pub name: String,
+    /// Path to the exercise directory, or for exercises in a git repository,
+    /// the path to the exercise within the repository
     pub path: PathBuf,
+    /// URL of the git repository the exercise is hosted in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// The revision of the git repository to use, preferably a commit hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
     #[serde(
         default = "crate::load::serde_defaults::exercise_description_md",
         skip_serializing_if = "crate::load::serde_defaults::is_exercise_description_md"
@@ -226,6 +1887,21 @@ pub struct ExerciseDef {
         skip_serializing_if = "crate::load::serde_defaults::is_exercise_includes"
     )]
     pub includes: Vec<String>,
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub optional: bool,
+    /// Whether the tests of the exercise fail until it is done, so that `modmod exercise
+    /// test` expects them to fail on the exercise as handed out
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub starter_fails_tests: bool,
+    /// The minutes the exercise takes to do, for the schedule and the estimates of the stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+    /// Profiles this item is rendered for. Rendered for all profiles if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Former ids of this exercise, from before it was renamed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
 }
 
 impl Default for ExerciseDef {
@@ -233,8 +1909,15 @@ impl Default for ExerciseDef {
         Self {
             name: Default::default(),
             path: Default::default(),
+            git: None,
+            rev: None,
             description: serde_defaults::exercise_description_md(),
             includes: serde_defaults::exercise_includes(),
+            optional: Default::default(),
+            starter_fails_tests: Default::default(),
+            duration_minutes: Default::default(),
+            profiles: Default::default(),
+            aliases: Default::default(),
         }
     }
 }
@@ -244,28 +1927,56 @@ impl ExerciseDef {
         self,
         exercise_index: usize,
         base_path: &Path,
+        loader: &mut Loader,
     ) -> Result<Indexed<Exercise>, HydrateTrackError> {
         let ExerciseDef {
             name,
             path: exercise_path,
+            git,
+            rev,
             description,
             includes,
+            optional,
+            starter_fails_tests,
+            duration_minutes,
+            profiles,
+            aliases,
         } = self;
-        let path = base_path
-            .join(exercise_path)
-            .canonicalize()
-            .into_report()
-            .change_context(HydrateTrackError)?;
-        let description = path
-            .join(description)
-            .canonicalize()
-            .into_report()
-            .change_context(HydrateTrackError)?;
+        let (base_path, git) = match (git, rev) {
+            (Some(url), Some(rev)) => {
+                let (checkout, source) =
+                    git::checkout(&url, &rev, &loader.git_cache_dir, loader.offline)
+                        .change_context(HydrateTrackError)
+                        .attach_printable_lazy(|| format!("In exercise '{name}'"))?;
+                (checkout, Some(source))
+            }
+            (None, None) => (base_path.to_path_buf(), None),
+            _ => {
+                return Err(Report::new(HydrateTrackError).attach_printable(format!(
+                    "Exercise '{name}' has to set both `git` and `rev`, or neither"
+                )))
+            }
+        };
+        let path = resolve_path(&base_path, &exercise_path, "Exercise directory")?;
+        let description = resolve_path(&path, &description, "Exercise description");
+        let description = loader.tolerate(description, Some(&path))?;
+        let package = package_name(&path);
         Ok(Exercise {
             name,
+            id: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            package,
+            git,
             path,
             description,
             includes,
+            optional,
+            starter_fails_tests,
+            duration_minutes,
+            profiles,
+            aliases,
         }
         .with_index(exercise_index))
     }
@@ -274,6 +1985,13 @@ impl ExerciseDef {
 #[derive(Debug)]
 pub struct LoadError(&'static str, PathBuf);
 
+impl LoadError {
+    /// Path of the definition that couldn't be loaded
+    pub fn path(&self) -> &Path {
+        &self.1
+    }
+}
+
 impl fmt::Display for LoadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let LoadError(ty, path) = self;
@@ -288,19 +2006,19 @@ impl fmt::Display for LoadError {
 impl error_stack::Context for LoadError {}
 
 pub trait Load: DeserializeOwned + Sized + 'static {
+    /// The keys of the definition, to tell which one a misspelled key was meant to be
+    fn keys() -> Keys {
+        Keys::of::<Self>()
+    }
+
     fn load(path: &Path, base_path: Option<&Path>) -> Result<PathTo<Self>, LoadError> {
-        let path = base_path.map(|b| b.join(path)).unwrap_or(path.to_owned());
-        let path = path
-            .canonicalize()
-            .into_report()
-            .attach_printable_lazy(|| {
-                format!(
-                    "Unable to canonicalize path {path}. Make sure the path leads to an existing file.",
-                    path = path.to_string_lossy()
-                )
-            })
-            .change_context_lazy(|| LoadError(type_name::<Self>(), path.clone()))?;
+        let path = canonicalize_def_path::<Self>(path, base_path)?;
+        Self::load_canonical(path)
+    }
+
+    fn load_canonical(path: PathBuf) -> Result<PathTo<Self>, LoadError> {
         let content = fs::read_to_string(&path)
+            .map(normalize_text)
             .into_report()
             .attach_printable_lazy(|| {
                 format!(
@@ -309,19 +2027,743 @@ pub trait Load: DeserializeOwned + Sized + 'static {
                 )
             })
             .change_context_lazy(|| LoadError(type_name::<Self>(), path.clone()))?;
-        let data: Self = toml::from_str(&content)
-            .into_report()
-            .attach_printable_lazy(|| {
-                format!("Unable to parse TOML file with contents '{content}'")
-            })
-            .change_context_lazy(|| LoadError(type_name::<Self>(), path.clone()))?;
+        Self::parse(path, &content)
+    }
+
+    /// Parses the definition that was read from `path`
+    fn parse(path: PathBuf, content: &str) -> Result<PathTo<Self>, LoadError> {
+        if let Err(message) = crate::migrate::check_schema_version(content) {
+            return Err(Report::new(LoadError(type_name::<Self>(), path.clone()))
+                .attach_printable(message)
+                .attach(Location::file(&path))
+                .attach(ErrorKind::InvalidDefinition));
+        }
+        let data: Self = toml::from_str(content).map_err(|error| {
+            let (description, location) = parse_error_location(&path, content, &error);
+            Report::new(LoadError(type_name::<Self>(), path.clone()))
+                .attach_printable(description)
+                .attach(location)
+                .attach(ErrorKind::InvalidDefinition)
+        })?;
+        // Keys that are far from any key modmod knows are left alone, like those of newer
+        // versions of modmod, but ones that are close are most likely misspelled
+        if let Ok(table) = toml::from_str::<toml::Table>(content) {
+            let keys = Self::keys().and(&[migrate::SCHEMA_VERSION_KEY]);
+            if let Some((key, known)) = keys.misspelled(&table) {
+                let line = content
+                    .lines()
+                    .position(|line| {
+                        let line = line
+                            .trim_start()
+                            .trim_start_matches("[[")
+                            .trim_start_matches('[');
+                        line.strip_prefix(key.as_str())
+                            .is_some_and(|rest| rest.starts_with([' ', '=', ']', '.']))
+                    })
+                    .map(|i| i + 1);
+                let mut location = Location::file(&path);
+                location.line = line;
+                return Err(Report::new(LoadError(type_name::<Self>(), path.clone()))
+                    .attach_printable(format!("Unknown key '{key}'. Did you mean '{known}'?"))
+                    .attach(location)
+                    .attach(ErrorKind::UnknownKey));
+            }
+        }
+        crate::progress::advance(crate::progress::Phase::Load, &path.to_string_lossy());
         Ok(data.with_path(path))
     }
 }
 
-impl Load for TrackDef {}
-impl Load for ModuleDef {}
-impl Load for TopicDef {}
+/// The keys of a table in a definition file, and of the tables nested in it
+#[derive(Debug, Clone, Default)]
+pub struct Keys {
+    keys: Vec<&'static str>,
+    /// The keys of the tables, or arrays of tables, under some of the keys
+    nested: Vec<(&'static str, Keys)>,
+}
+
+impl Keys {
+    /// The fields of a struct that derives `Deserialize`, without the fields of structs it
+    /// flattens
+    pub fn of<T: DeserializeOwned>() -> Self {
+        Self {
+            keys: fields_of::<T>().to_vec(),
+            nested: vec![],
+        }
+    }
+
+    /// Also accepts `keys`, like aliases of fields
+    fn and(mut self, keys: &[&'static str]) -> Self {
+        self.keys.extend(keys);
+        self
+    }
+
+    /// The table, or tables, under `key` have the keys `nested`
+    fn nest(mut self, key: &'static str, nested: Keys) -> Self {
+        self.nested.push((key, nested));
+        self
+    }
+
+    /// The first key in `table` or its nested tables that is unknown, but close to a known
+    /// key, with that key
+    fn misspelled(&self, table: &toml::Table) -> Option<(String, &'static str)> {
+        for (key, value) in table {
+            if !self.keys.contains(&key.as_str()) {
+                if let Some(known) = suggest::closest(key, self.keys.iter().copied()) {
+                    return Some((key.clone(), known));
+                }
+                continue;
+            }
+            let Some((_, nested)) = self.nested.iter().find(|(k, _)| k == key) else {
+                continue;
+            };
+            let tables: Vec<&toml::Table> = match value {
+                toml::Value::Table(table) => vec![table],
+                toml::Value::Array(values) => values.iter().filter_map(|v| v.as_table()).collect(),
+                _ => vec![],
+            };
+            if let Some(misspelled) = tables.into_iter().find_map(|t| nested.misspelled(t)) {
+                return Some(misspelled);
+            }
+        }
+        None
+    }
+}
+
+/// The fields of a struct that derives `Deserialize`, which it passes to the deserializer
+fn fields_of<T: DeserializeOwned>() -> &'static [&'static str] {
+    use serde::de::{value::Error, Error as _, Visitor};
+
+    struct Fields<'f>(&'f mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for Fields<'_> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Error> {
+            Err(Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> std::result::Result<V::Value, Error> {
+            *self.0 = fields;
+            Err(Error::custom("only the fields are needed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+            identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
+/// Describes where in a definition file parsing failed, as `<path>:<line>:<column>: <message>`
+/// followed by the offending line, if the parser reported a location
+fn parse_error_location(path: &Path, content: &str, error: &toml::de::Error) -> (String, Location) {
+    let message = error.message().trim_end();
+    let mut location = Location::file(path);
+    location.message = Some(message.to_string());
+    let Some(span) = error.span() else {
+        return (format!("{}: {message}", path.display()), location);
+    };
+    let start = span.start.min(content.len());
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[start..]
+        .find('\n')
+        .map_or(content.len(), |i| start + i);
+    let line = content[..start].matches('\n').count() + 1;
+    let column = content[line_start..start].chars().count() + 1;
+    let snippet = content[line_start..line_end].trim_end();
+    let end = span.end.clamp(start, line_end.max(start));
+    let len = content[start..end].chars().count().max(1);
+    let gutter = " ".repeat(line.to_string().len());
+    location.line = Some(line);
+    location.column = Some(column);
+    location.len = len;
+    let description = format!(
+        "{}:{line}:{column}: {message}\n{gutter} |\n{line} | {snippet}\n{gutter} | {}{}",
+        path.display(),
+        " ".repeat(column - 1),
+        "^".repeat(len),
+    );
+    (description, location)
+}
+
+/// Extensions of the files that are considered images
+pub(crate) const IMAGE_EXTENSIONS: &[&str] =
+    &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "ico"];
+
+pub(crate) fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// The name of the template a template reference refers to, if it is of the form `name:<name>`
+fn template_name(template: &Path) -> Option<&str> {
+    template.to_str()?.strip_prefix("name:")
+}
+
+/// Makes a template path absolute, leaving references to named templates as they are
+fn rebase_template(base_path: &Path, template: PathBuf) -> PathBuf {
+    if template_name(&template).is_some() {
+        template
+    } else {
+        base_path.join(template)
+    }
+}
+
+/// Resolves a path from a definition file against the directory of that file
+fn resolve_path(base_path: &Path, path: &Path, what: &str) -> Result<PathBuf, HydrateTrackError> {
+    let full_path = base_path.join(path);
+    full_path
+        .canonicalize()
+        .into_report()
+        .change_context(HydrateTrackError)
+        .attach_printable_lazy(|| {
+            format!(
+                "{what} '{}' not found at {}",
+                path.display(),
+                full_path.display()
+            )
+        })
+        .attach(ErrorKind::MissingContentFile)
+}
+
+/// Fails on a quiz with questions that couldn't be answered, listing all their problems
+fn check_quiz(
+    quiz: &[QuizQuestion],
+    topic: &str,
+    definition: &Path,
+) -> Result<(), HydrateTrackError> {
+    let problems = quiz::problems(quiz);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let mut report = Report::new(HydrateTrackError).attach_printable(format!(
+        "The quiz of topic '{topic}' in {} has {} problem(s)",
+        definition.display(),
+        problems.len()
+    ));
+    for problem in problems {
+        report = report.attach_printable(problem);
+    }
+    Err(report
+        .attach(ErrorKind::InvalidContent)
+        .attach(Location::file(definition)))
+}
+
+fn canonicalize_def_path<T: 'static>(
+    path: &Path,
+    base_path: Option<&Path>,
+) -> Result<PathBuf, LoadError> {
+    let path = base_path.map(|b| b.join(path)).unwrap_or(path.to_owned());
+    path.canonicalize()
+        .into_report()
+        .attach_printable_lazy(|| {
+            format!(
+                "Unable to canonicalize path {path}. Make sure the path leads to an existing file.",
+                path = path.to_string_lossy()
+            )
+        })
+        .change_context_lazy(|| LoadError(type_name::<T>(), path.clone()))
+        .attach(ErrorKind::MissingContentFile)
+}
+
+impl Load for TrackDef {
+    fn keys() -> Keys {
+        Keys::of::<Self>()
+            .and(&["modmod-version"])
+            .nest("course", Keys::of::<Course>())
+            .nest("output", Keys::of::<OutputLayout>())
+            .nest("license", Keys::of::<License>())
+            .nest("analytics", Keys::of::<Analytics>())
+            .nest("toolchain", Keys::of::<Toolchain>())
+            .nest("derive_summaries", Keys::of::<DeriveSummaries>())
+            .nest("add_units", UnitDef::keys().and(&["module"]))
+    }
+}
+
+/// Stands in for the path of a track definition that is read from stdin
+pub const STDIN_SOURCE: &str = "<stdin>";
+
+/// How long fetching a track definition from a URL may take
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// How long connecting to the server of a URL may take, of [`FETCH_TIMEOUT_SECS`]
+const FETCH_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// The largest track definition that is fetched from a URL, in bytes
+const FETCH_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Whether the track definition at `path` isn't a file, but was read from stdin or
+/// fetched from a URL. Such a definition has no folder that the paths in it are relative
+/// to, so they are resolved against the content directory instead.
+pub fn is_synthetic_source(path: &Path) -> bool {
+    path == Path::new(STDIN_SOURCE)
+        || path
+            .to_str()
+            .is_some_and(|p| p.starts_with("https://") || p.starts_with("http://"))
+}
+
+impl TrackDef {
+    /// Loads the track definition at `source`: a file, `-` for stdin, or an https URL
+    pub fn load_source(source: &Path) -> Result<PathTo<Self>, LoadError> {
+        let (path, content) = match source.to_str() {
+            Some("-") => {
+                let path = PathBuf::from(STDIN_SOURCE);
+                let mut content = String::new();
+                std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)
+                    .into_report()
+                    .attach_printable("Unable to read the track definition from stdin")
+                    .change_context_lazy(|| LoadError(type_name::<Self>(), path.clone()))?;
+                (path, content)
+            }
+            Some(url) if is_synthetic_source(source) => {
+                let path = source.to_path_buf();
+                let content = fetch(url).map_err(|message| {
+                    Report::new(LoadError(type_name::<Self>(), path.clone()))
+                        .attach_printable(format!("Unable to fetch track definition: {message}"))
+                })?;
+                (path, content)
+            }
+            _ => return Self::load(source, None),
+        };
+        Self::parse(path, &content)
+    }
+}
+
+/// Fetches the text at an https URL using curl, which has to be on the `PATH`, like it
+/// has to for [`crate::external_links`]
+fn fetch(url: &str) -> std::result::Result<String, String> {
+    if !url.starts_with("https://") {
+        return Err(format!("only https URLs are fetched, not {url}"));
+    }
+    let output = std::process::Command::new("curl")
+        // Redirects may not lead to plain http either
+        .args(["--silent", "--show-error", "--location", "--fail"])
+        .args(["--proto", "=https", "--proto-redir", "=https"])
+        .args(["--connect-timeout", &FETCH_CONNECT_TIMEOUT_SECS.to_string()])
+        .args(["--max-time", &FETCH_TIMEOUT_SECS.to_string()])
+        .args(["--max-filesize", &FETCH_MAX_SIZE.to_string(), url])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "curl isn't installed, or isn't on the PATH".to_string()
+            }
+            _ => format!("unable to run curl: {e}"),
+        })?;
+    if !output.status.success() {
+        // Like `curl: (6) Could not resolve host: example.com`
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim().trim_start_matches("curl: ");
+        let message = match message.split_once(") ") {
+            Some((code, rest)) if code.starts_with('(') => rest,
+            _ => message,
+        };
+        return Err(message.to_string());
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{url} isn't text"))
+}
+
+impl Load for ModuleDef {
+    fn keys() -> Keys {
+        Keys::of::<Self>().nest("units", UnitDef::keys())
+    }
+}
+
+impl Load for TopicDef {
+    fn keys() -> Keys {
+        Keys::of::<Self>().nest("exercises", Keys::of::<ExerciseDef>())
+    }
+}
+
+impl Load for WorkspaceDef {}
+
+impl UnitDef {
+    fn keys() -> Keys {
+        Keys::of::<Self>()
+            .nest("topics", Keys::of::<InlineTopicDef>())
+            .nest("sessions", Keys::of::<SessionDef>())
+    }
+}
+
+/// Keeps state shared between the loading of several definition files, so that
+/// modules and topics that are referred to more than once are only read and parsed once,
+/// even across tracks.
+#[derive(Debug)]
+pub struct Loader {
+    modules: HashMap<PathBuf, ModuleDef>,
+    topics: HashMap<PathBuf, TopicDef>,
+    /// Canonical paths of the definitions that are currently being resolved,
+    /// outermost first
+    include_chain: Vec<PathBuf>,
+    /// Contents of the topic content files that were read ahead of resolving the topics
+    contents: HashMap<PathBuf, String>,
+    policy: FailurePolicy,
+    /// Whether to read topic content files while loading, to find their frontmatter
+    read_content: bool,
+    /// Problems that were tolerated while loading the current track
+    warnings: Vec<Warning>,
+    /// The named slide templates of the current track
+    templates: BTreeMap<String, PathBuf>,
+    /// Derives summaries for the topics of the current track, if it opted in
+    summaries: Option<SummaryDeriver>,
+    /// The locale of the current track, which topic definitions give their objectives and
+    /// summaries in
+    locale: String,
+    /// How the modules of the current track are numbered
+    module_numbering: ModuleNumbering,
+    /// Where exercises hosted in git repositories are checked out
+    git_cache_dir: PathBuf,
+    /// Whether to fail on exercises that aren't in the git cache, instead of fetching them
+    offline: bool,
+    /// The folder the content paths of the tracks are relative to, instead of their `content_root`
+    content_dir: Option<PathBuf>,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self {
+            modules: HashMap::new(),
+            topics: HashMap::new(),
+            include_chain: vec![],
+            contents: HashMap::new(),
+            policy: FailurePolicy::default(),
+            read_content: true,
+            warnings: vec![],
+            templates: BTreeMap::new(),
+            summaries: None,
+            locale: "en".to_string(),
+            module_numbering: ModuleNumbering::default(),
+            git_cache_dir: git::default_cache_dir(),
+            offline: false,
+            content_dir: None,
+        }
+    }
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_policy(policy: FailurePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Don't read topic content files while loading. Loading is faster, but metadata
+    /// that is set in the frontmatter of the content is not picked up.
+    pub fn without_content(mut self) -> Self {
+        self.read_content = false;
+        self
+    }
+
+    /// Don't fetch exercises that are hosted in git repositories, but fail if they
+    /// are not in the cache yet
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    /// Check out exercises that are hosted in git repositories into this directory,
+    /// instead of the default cache directory
+    pub fn with_git_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.git_cache_dir = dir;
+        self
+    }
+
+    /// Resolve the paths to the modules and other content of the tracks against this
+    /// directory, instead of against their `content_root`. The tracks they extend still
+    /// resolve their paths as they define.
+    pub fn with_content_dir(mut self, dir: PathBuf) -> Self {
+        self.content_dir = Some(dir);
+        self
+    }
+
+    /// Reads the module and topic definitions of the given modules, and the topic content,
+    /// on several threads, so that resolving the track doesn't have to wait for each file in turn.
+    /// Failures are ignored here: they are reported when the definitions are resolved, in order.
+    /// Fails with the errors of all definitions that could not be parsed, so that they can
+    /// be fixed in one go. Definitions that can't be found are left to be reported when
+    /// they are resolved, as the loading policy may tolerate them.
+    fn prefetch(
+        &mut self,
+        module_paths: &[PathBuf],
+        base_path: &Path,
+    ) -> Result<(), HydrateTrackError> {
+        let mut errors: Vec<Report<LoadError>> = vec![];
+        let module_paths: Vec<_> = module_paths
+            .iter()
+            .filter_map(|p| canonicalize_def_path::<ModuleDef>(p, Some(base_path)).ok())
+            .filter(|p| !self.modules.contains_key(p))
+            .collect();
+        let modules = parallel_map(&module_paths, |p| ModuleDef::load_canonical(p.clone()));
+        for module in modules {
+            match module {
+                Ok(module) => {
+                    self.modules.insert(module.path, module.data);
+                }
+                Err(report) => errors.push(report),
+            }
+        }
+
+        let mut topic_paths: Vec<_> = module_paths
+            .iter()
+            .filter_map(|p| Some((p.parent()?, self.modules.get(p)?)))
+            .flat_map(|(dir, module)| {
+                module
+                    .units
+                    .iter()
+                    .flat_map(|u| u.topics.iter())
+                    .filter_map(move |topic| match topic {
+                        TopicRef::Path(p) if !is_glob(&p.to_string_lossy()) => Some(dir.join(p)),
+                        _ => None,
+                    })
+            })
+            .map(|p| if p.is_dir() { p.join("topic.toml") } else { p })
+            .filter_map(|p| p.canonicalize().ok())
+            .filter(|p| !self.topics.contains_key(p))
+            .collect();
+        topic_paths.sort();
+        topic_paths.dedup();
+        let topics = parallel_map(&topic_paths, |p| TopicDef::load_canonical(p.clone()));
+        let topics: Vec<_> = topics
+            .into_iter()
+            .filter_map(|topic| topic.map_err(|report| errors.push(report)).ok())
+            .collect();
+
+        if self.read_content {
+            let content_paths: Vec<_> = topics
+                .iter()
+                .filter_map(|t| t.path.parent()?.join(&t.data.content).canonicalize().ok())
+                .filter(|p| !self.contents.contains_key(p))
+                .collect();
+            let contents = parallel_map(&content_paths, |p| {
+                fs::read_to_string(p).ok().map(normalize_text)
+            });
+            self.contents.extend(
+                content_paths
+                    .into_iter()
+                    .zip(contents)
+                    .filter_map(|(p, c)| Some((p, c?))),
+            );
+        }
+        self.topics
+            .extend(topics.into_iter().map(|t| (t.path, t.data)));
+
+        let mut errors = errors.into_iter();
+        let Some(mut report) = errors.next() else {
+            return Ok(());
+        };
+        let count = errors.len() + 1;
+        report.extend(errors);
+        Err(report
+            .change_context(HydrateTrackError)
+            .attach_printable(format!("{count} definition file(s) could not be loaded")))
+    }
+
+    /// Resolves a reference to a slide template, which is either a path relative to the
+    /// definition, or the name of one of the track's templates as `name:<name>`
+    fn resolve_template(
+        &self,
+        base_path: &Path,
+        template: &Path,
+        what: &str,
+    ) -> Result<PathBuf, HydrateTrackError> {
+        let Some(name) = template_name(template) else {
+            return resolve_path(base_path, template, what);
+        };
+        match self.templates.get(name) {
+            Some(path) => Ok(path.clone()),
+            None => {
+                let available = if self.templates.is_empty() {
+                    "The track doesn't define any templates".to_string()
+                } else {
+                    format!(
+                        "The track defines templates {}",
+                        self.templates
+                            .keys()
+                            .map(|n| format!("'{n}'"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                };
+                Err(Report::new(HydrateTrackError)
+                    .attach_printable(format!(
+                        "{what} '{name}' is not defined.{} {available}",
+                        suggest::did_you_mean(name, self.templates.keys())
+                    ))
+                    .attach(ErrorKind::UndefinedReference))
+            }
+        }
+    }
+
+    /// The text of a topic content file, read ahead of time if possible
+    fn content(&mut self, path: &Path) -> Result<String, HydrateTrackError> {
+        match self.contents.remove(path) {
+            Some(content) => Ok(content),
+            None => path.read_to_string(),
+        }
+    }
+
+    /// When loading leniently, turns a failure to find a file into a warning and returns `None`
+    fn tolerate<T>(
+        &mut self,
+        result: Result<T, HydrateTrackError>,
+        source: Option<&Path>,
+    ) -> Result<Option<T>, HydrateTrackError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(report) if self.policy == FailurePolicy::Lenient => {
+                let message = report
+                    .frames()
+                    .find_map(|frame| frame.downcast_ref::<String>())
+                    .cloned()
+                    .unwrap_or_else(|| report.to_string());
+                self.warnings
+                    .push(Warning::new(WarningCode::MissingContent, message, source));
+                Ok(None)
+            }
+            Err(report) => Err(report),
+        }
+    }
+
+    /// Start resolving the definition at the given canonical path,
+    /// failing if that would close a cycle of includes
+    fn enter(&mut self, path: &Path) -> Result<(), HydrateTrackError> {
+        if let Some(start) = self.include_chain.iter().position(|p| p == path) {
+            let cycle: Vec<_> = self.include_chain[start..]
+                .iter()
+                .chain([&path.to_path_buf()])
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(Report::new(HydrateTrackError)
+                .attach_printable(format!("Circular include: {}", cycle.join(" → ")))
+                .attach(ErrorKind::CircularInclude));
+        }
+        self.include_chain.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Done resolving the definition that was entered last
+    fn leave(&mut self) {
+        self.include_chain.pop();
+    }
+
+    /// The chain of definitions that are being resolved, for use in error messages
+    fn chain_display(&self) -> String {
+        self.include_chain
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+
+    fn load_module(
+        &mut self,
+        path: &Path,
+        base_path: Option<&Path>,
+    ) -> Result<PathTo<ModuleDef>, LoadError> {
+        load_cached(&mut self.modules, path, base_path)
+    }
+
+    fn load_topic(
+        &mut self,
+        path: &Path,
+        base_path: Option<&Path>,
+    ) -> Result<PathTo<TopicDef>, LoadError> {
+        load_cached(&mut self.topics, path, base_path)
+    }
+}
+
+/// Applies `f` to all items using a number of threads, keeping the results in the order of the items
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    parallel::map_with(items, parallel::jobs(), f)
+}
+
+fn load_cached<T: Load + Clone>(
+    cache: &mut HashMap<PathBuf, T>,
+    path: &Path,
+    base_path: Option<&Path>,
+) -> Result<PathTo<T>, LoadError> {
+    let path = canonicalize_def_path::<T>(path, base_path)?;
+    if let Some(data) = cache.get(&path) {
+        return Ok(data.clone().with_path(path));
+    }
+    let def = T::load_canonical(path)?;
+    cache.insert(def.path.clone(), def.data.clone());
+    Ok(def)
+}
+
+/// Assigns the indices of modules in a track or units in a module
+#[derive(Default)]
+struct Indices {
+    /// Maps assigned indices to a description of what they were assigned to
+    assigned: HashMap<usize, String>,
+    last: Option<usize>,
+}
+
+impl Indices {
+    /// Assign the index that was set explicitly, or else the one after the last assigned index
+    fn assign(&mut self, index: Option<usize>, what: &str) -> Result<usize, HydrateTrackError> {
+        let index = index.unwrap_or(self.last.map(|i| i + 1).unwrap_or(1));
+        if let Some(other) = self.assigned.get(&index) {
+            return Err(Report::new(HydrateTrackError)
+                .attach_printable(format!("Index {index} is used by both {other} and {what}"))
+                .attach(ErrorKind::DuplicateIndex));
+        }
+        self.assigned.insert(index, what.to_string());
+        self.last = Some(index);
+        Ok(index)
+    }
+}
+
+/// Definitions included by a single definition file, to detect the same
+/// file being included more than once, possibly through different paths
+#[derive(Default)]
+struct Included {
+    /// Maps canonical paths to the path they were first included as
+    paths: HashMap<PathBuf, PathBuf>,
+}
+
+impl Included {
+    fn insert(
+        &mut self,
+        kind: &str,
+        canonical: &Path,
+        as_written: &Path,
+        loader: &Loader,
+    ) -> Result<(), HydrateTrackError> {
+        match self.paths.get(canonical) {
+            Some(first) => Err(Report::new(HydrateTrackError)
+                .attach_printable(format!(
+                    "{kind} {} is included more than once, as '{}' and as '{}'",
+                    canonical.display(),
+                    first.display(),
+                    as_written.display()
+                ))
+                .attach_printable(format!("Included from {}", loader.chain_display()))
+                .attach(ErrorKind::DuplicateInclude)),
+            None => {
+                self.paths
+                    .insert(canonical.to_path_buf(), as_written.to_path_buf());
+                Ok(())
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct PathTo<T> {
@@ -337,7 +2779,7 @@ trait WithPath: Sized {
 
 impl<T> WithPath for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Indexed<T> {
     pub data: T,
     pub index: usize,
@@ -383,6 +2825,22 @@ pub mod serde_defaults {
         includes == &exercise_includes()
     }
 
+    pub fn is_false(b: &bool) -> bool {
+        !b
+    }
+
+    pub fn r#true() -> bool {
+        true
+    }
+
+    pub fn is_true(b: &bool) -> bool {
+        *b
+    }
+
+    pub fn derived_summary_items() -> usize {
+        5
+    }
+
     pub fn topic_slides_md() -> PathBuf {
         PathBuf::from("slides.md")
     }
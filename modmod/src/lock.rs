@@ -0,0 +1,317 @@
+//! Lockfiles, that record the content a track was built from, so that an old build can be
+//! reproduced exactly, and a build can be refused when its content changed.
+//!
+//! A lockfile holds the hash of every source file a track refers to, the commits its
+//! exercises from git repositories were checked out at, and the version of modmod. Files are
+//! hashed with SHA-256, so that no change of the content goes unnoticed, see
+//! [`crate::sha256`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    catalog::ErrorKind, exercises::relative_path, io::PathExt, load::is_synthetic_source, manifest,
+    sha256, Track,
+};
+
+/// Name of the lockfile, in the folder of the track definitions it locks
+pub const LOCK_FILE: &str = "modmod.lock";
+
+/// Version of the format of the lockfile. Version 1 had FNV-1a hashes, which aren't
+/// collision resistant; its locks have to be refreshed with `modmod update-lock`.
+pub const LOCK_VERSION: u32 = 2;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct LockError {}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to lock the content of the track")
+    }
+}
+
+impl error_stack::Context for LockError {}
+
+/// The locks of the tracks with their definition in one folder, keyed on the file name of
+/// their definition
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    pub tracks: BTreeMap<String, TrackLock>,
+}
+
+/// The content a track was locked at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackLock {
+    /// The version of modmod the track was locked with
+    pub modmod_version: String,
+    /// The SHA-256 hash of each source file, keyed on its path relative to the lockfile
+    pub files: BTreeMap<String, String>,
+    /// The commits the exercises from git repositories were checked out at
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub git: Vec<GitLock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GitLock {
+    pub exercise: String,
+    pub url: String,
+    pub commit: String,
+}
+
+/// How the content of a track differs from its lock. Paths are relative to the lockfile.
+#[derive(Debug, Default)]
+pub struct LockDiff {
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Exercises from git repositories that are checked out at another commit, or that were
+    /// added or removed
+    pub git: Vec<String>,
+    /// The versions of modmod the track was locked with and is built with, if they differ
+    pub modmod_version: Option<(String, String)>,
+}
+
+impl LockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.git.is_empty()
+            && self.modmod_version.is_none()
+    }
+
+    /// Describes each difference on a line of its own
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if let Some((locked, current)) = &self.modmod_version {
+            lines.push(format!(
+                "locked with modmod {locked}, but this is modmod {current}"
+            ));
+        }
+        lines.extend(self.changed.iter().map(|f| format!("changed: {f}")));
+        lines.extend(self.added.iter().map(|f| format!("added: {f}")));
+        lines.extend(self.removed.iter().map(|f| format!("removed: {f}")));
+        lines.extend(self.git.iter().map(|e| format!("git: {e}")));
+        lines
+    }
+}
+
+impl Lockfile {
+    /// The lockfile of a track, in the folder of its definition
+    pub fn path_of(track_definition: &Path) -> PathBuf {
+        track_definition.with_file_name(LOCK_FILE)
+    }
+
+    /// The key of a track in the lockfile
+    pub fn key_of(track_definition: &Path) -> String {
+        track_definition
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Loads the lockfile at `path`, if there is one
+    pub fn load(path: &Path) -> Result<Option<Self>, LockError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let lockfile: Self = toml::from_str(&path.read_to_string()?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Invalid lockfile at {}", path.display()))
+            .change_context(LockError::default())?;
+        if lockfile.version > LOCK_VERSION {
+            return Err(Report::new(LockError::default()).attach_printable(format!(
+                "The lockfile at {} has version {}, but this modmod only knows up to version {LOCK_VERSION}",
+                path.display(),
+                lockfile.version
+            )));
+        }
+        Ok(Some(lockfile))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), LockError> {
+        let toml = toml::to_string_pretty(self)
+            .into_report()
+            .change_context(LockError::default())?;
+        path.write_file(format!(
+            "# Generated by modmod {}. Refresh it with `modmod update-lock`, rather than by hand.\n{toml}",
+            crate::VERSION
+        ))
+    }
+}
+
+impl Track {
+    /// The source files the track refers to: its definitions, templates, slides, images and
+    /// exercise descriptions, and the files of its exercises and shared crates. Exercises
+    /// from git repositories are locked by their commit instead.
+    pub fn source_files(&self) -> Result<BTreeSet<PathBuf>, LockError> {
+        let mut files: BTreeSet<PathBuf> = self.definition_files().into_iter().collect();
+        files.extend(self.default_slide_template.iter().cloned());
+        files.extend(self.templates.values().cloned());
+        files.extend(self.units().filter_map(|u| u.template.clone()));
+        for topic in self.topics() {
+            files.extend(topic.content.path().map(Path::to_path_buf));
+            files.extend(topic.images.iter().map(|i| i.path.clone()));
+        }
+        let mut crate_dirs: Vec<&Path> = self
+            .shared_crates
+            .iter()
+            .map(|c| c.path.as_path())
+            .collect();
+        for exercise in self.exercises() {
+            files.extend(exercise.description.clone());
+            if exercise.git.is_none() {
+                crate_dirs.push(&exercise.path);
+            }
+        }
+        for dir in crate_dirs {
+            let content = dir.get_dir_content()?;
+            // Build output isn't content
+            files.extend(
+                content
+                    .files
+                    .iter()
+                    .map(PathBuf::from)
+                    .filter(|f| !f.strip_prefix(dir).is_ok_and(|f| f.starts_with("target"))),
+            );
+        }
+        Ok(files)
+    }
+
+    /// The lockfile of the track, which needs its definition to be a file
+    fn lockfile_path(&self) -> Result<PathBuf, LockError> {
+        if is_synthetic_source(&self.definition) {
+            return Err(Report::new(LockError::default()).attach_printable(format!(
+                "Track '{}' is read from {}, so there is no folder for its lockfile",
+                self.name,
+                self.definition.display()
+            )));
+        }
+        Ok(Lockfile::path_of(&self.definition))
+    }
+
+    /// Locks the current content of the track, with paths relative to its lockfile
+    pub fn lock(&self) -> Result<TrackLock, LockError> {
+        let lock_path = self.lockfile_path()?;
+        let lock_dir = lock_path.parent().unwrap_or(Path::new(""));
+        let mut files = BTreeMap::new();
+        for file in self.source_files()? {
+            let hash = sha256::hash_file(&file)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error reading file at path {}", file.display()))
+                .change_context(LockError::default())?;
+            files.insert(
+                manifest::relative_path(&relative_path(lock_dir, &file)),
+                hash,
+            );
+        }
+        let mut git: Vec<_> = self
+            .exercises()
+            .filter_map(|e| {
+                e.git.as_ref().map(|git| GitLock {
+                    exercise: e.name.clone(),
+                    url: git.url.clone(),
+                    commit: git.commit.clone(),
+                })
+            })
+            .collect();
+        git.sort();
+        git.dedup();
+        Ok(TrackLock {
+            modmod_version: crate::VERSION.to_string(),
+            files,
+            git,
+        })
+    }
+
+    /// The lock of the track in its lockfile, if it is locked
+    pub fn locked(&self) -> Result<Option<TrackLock>, LockError> {
+        let path = self.lockfile_path()?;
+        Ok(Lockfile::load(&path)?
+            .and_then(|mut lockfile| lockfile.tracks.remove(&Lockfile::key_of(&self.definition))))
+    }
+
+    /// Fails if the track isn't locked, or if its content differs from its lock, listing
+    /// the differences
+    pub fn verify_lock(&self) -> Result<(), LockError> {
+        let path = self.lockfile_path()?;
+        let lockfile = Lockfile::load(&path)?;
+        if let Some(lockfile) = lockfile.as_ref().filter(|l| l.version < LOCK_VERSION) {
+            return Err(Report::new(LockError::default())
+                .attach_printable(format!(
+                    "The lockfile at {} has version {}, whose hashes can't be verified anymore. Refresh the locks in it with `modmod update-lock`",
+                    path.display(),
+                    lockfile.version
+                ))
+                .attach(ErrorKind::LockMismatch));
+        }
+        let locked =
+            lockfile.and_then(|mut l| l.tracks.remove(&Lockfile::key_of(&self.definition)));
+        let Some(locked) = locked else {
+            return Err(Report::new(LockError::default())
+                .attach_printable(format!(
+                    "Track '{}' isn't locked in {}. Lock it with `modmod lock`",
+                    self.name,
+                    path.display()
+                ))
+                .attach(ErrorKind::LockMismatch));
+        };
+        let diff = locked.diff(&self.lock()?);
+        if diff.is_empty() {
+            return Ok(());
+        }
+        let mut report = Report::new(LockError::default())
+            .attach(ErrorKind::LockMismatch)
+            .attach_printable(format!(
+                "The content of track '{}' differs from its lock in {}",
+                self.name,
+                path.display()
+            ));
+        for line in diff.lines() {
+            report = report.attach_printable(line);
+        }
+        Err(report)
+    }
+}
+
+impl TrackLock {
+    /// How `current` differs from this lock
+    pub fn diff(&self, current: &TrackLock) -> LockDiff {
+        let mut diff = LockDiff::default();
+        for (file, hash) in current.files.iter() {
+            match self.files.get(file) {
+                Some(locked) if locked != hash => diff.changed.push(file.clone()),
+                Some(_) => {}
+                None => diff.added.push(file.clone()),
+            }
+        }
+        diff.removed = self
+            .files
+            .keys()
+            .filter(|file| !current.files.contains_key(*file))
+            .cloned()
+            .collect();
+        let describe =
+            |git: &GitLock| format!("{} at {} from {}", git.exercise, git.commit, git.url);
+        for git in current.git.iter().filter(|g| !self.git.contains(g)) {
+            diff.git.push(format!("now {}", describe(git)));
+        }
+        for git in self.git.iter().filter(|g| !current.git.contains(g)) {
+            diff.git.push(format!("was {}", describe(git)));
+        }
+        if self.modmod_version != current.modmod_version {
+            diff.modmod_version =
+                Some((self.modmod_version.clone(), current.modmod_version.clone()));
+        }
+        diff
+    }
+}
@@ -0,0 +1,356 @@
+//! Progress and warnings of modmod, logged as [`tracing`] events, in spans like `render`,
+//! `slides` and `deck` for where in a render they happened. Nothing is written until [`init`]
+//! installs a subscriber, so that tools using modmod as a library can install their own, or
+//! none.
+//!
+//! How the events are logged is set for the process, but the warnings and errors are counted
+//! and collected in the [`RunContext`](crate::context::RunContext) they are logged in.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, IsTerminal, Write},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use error_stack::Report;
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{
+        format::{debug_fn, Writer},
+        FmtContext, FormatEvent, FormatFields, FormattedFields,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter,
+};
+
+use crate::{
+    context,
+    diagnostic::Diagnostic,
+    failure::{ErrorCode, Failure, FailureSummary},
+    report::{Warning, WarningCode},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One line per event, like `warning: ...`
+    #[default]
+    Human,
+    /// One JSON object per line, with the level, the message and the fields of the event,
+    /// and the spans it is in
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format '{s}', expected human or json")),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Human => "human",
+            Self::Json => "json",
+        })
+    }
+}
+
+/// The format [`init`] was called with
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Logs the events of `level` and above to stderr from now on. Human readable events are
+/// colored, unless `NO_COLOR` is set or stderr isn't a terminal. Only the first call has
+/// effect, and none if another subscriber was installed already.
+///
+/// `MODMOD_LOG` adds the directives of an [`EnvFilter`] to the level, like
+/// `MODMOD_LOG=debug`, or `MODMOD_LOG=warn,[deck]=debug` to log the details of rendering the
+/// decks only.
+pub fn init(level: Level, format: LogFormat) {
+    if FORMAT.set(format).is_err() {
+        return;
+    }
+    let color = format == LogFormat::Human
+        && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && io::stderr().is_terminal();
+    let level = level.as_str().to_ascii_lowercase();
+    let directives = std::env::var("MODMOD_LOG").unwrap_or_default();
+    let (filter, invalid) = match EnvFilter::builder().parse(format!("{level},{directives}")) {
+        Ok(filter) => (filter, None),
+        Err(error) => (EnvFilter::new(level), Some(error)),
+    };
+    let human = (format == LogFormat::Human).then(|| {
+        tracing_subscriber::fmt::layer()
+            .event_format(HumanFormat { color })
+            .fmt_fields(debug_fn(label_field))
+            .with_writer(|| Stderr)
+    });
+    let json = (format == LogFormat::Json).then(|| {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .with_target(false)
+            .without_time()
+            .with_writer(|| Stderr)
+    });
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(human)
+        .with(json)
+        .try_init();
+    if let (Ok(()), Some(error)) = (installed, invalid) {
+        tracing::warn!("Ignoring MODMOD_LOG: {error}");
+    }
+}
+
+/// Formats an event as one line, like `warning: ...`. At the debug level, the line ends with
+/// the spans the event is in, like `[render Rust > slides > deck 2.1]`.
+struct HumanFormat {
+    color: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for HumanFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let level = *event.metadata().level();
+        let mut message = Message::default();
+        event.record(&mut message);
+        let mut message = message.0;
+        // Only worth the noise when looking for where something happened
+        if level >= Level::DEBUG {
+            let spans: Vec<_> = ctx
+                .event_scope()
+                .into_iter()
+                .flat_map(|scope| scope.from_root())
+                .map(|span| {
+                    let extensions = span.extensions();
+                    // Fields recorded later add a space to the label, even though they're
+                    // left out of it
+                    match extensions
+                        .get::<FormattedFields<N>>()
+                        .map(|label| label.trim_end())
+                    {
+                        Some(label) if !label.is_empty() => format!("{} {label}", span.name()),
+                        _ => span.name().to_string(),
+                    }
+                })
+                .collect();
+            if !spans.is_empty() {
+                message = format!("{message} [{}]", spans.join(" > "));
+            }
+        }
+        let line = match level {
+            // Error messages tell that they are errors themselves, like `Error rendering track`
+            Level::ERROR => strip_ansi_unless(&message, self.color),
+            _ if self.color => {
+                let color = match level {
+                    Level::WARN => "1;33",
+                    Level::INFO => "1;32",
+                    _ => "1;34",
+                };
+                format!("\x1b[{color}m{}\x1b[0m: {message}", human_name(level))
+            }
+            _ => format!("{}: {}", human_name(level), strip_ansi(&message)),
+        };
+        writeln!(writer, "{line}")
+    }
+}
+
+/// Formats the fields of spans for [`HumanFormat`] as their `label` only
+fn label_field(writer: &mut Writer<'_>, field: &Field, value: &dyn fmt::Debug) -> fmt::Result {
+    match field.name() {
+        "label" => write!(writer, "{value:?}"),
+        _ => Ok(()),
+    }
+}
+
+/// The message of an event
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+/// Writes events to stderr, clearing the progress bar for them, see
+/// [`crate::progress::suspend`]
+struct Stderr;
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = Ok(());
+        crate::progress::suspend(|| written = io::stderr().lock().write_all(buf));
+        written.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// The errors and warnings collected in the current context, if it collects them, see
+/// [`RunContext::collect_failures`](crate::context::RunContext::collect_failures). Collecting
+/// stops.
+pub fn take_failures() -> Option<FailureSummary> {
+    context::current().failures.lock().unwrap().take()
+}
+
+fn record(add: impl FnOnce(&mut FailureSummary)) {
+    if let Some(summary) = context::current().failures.lock().unwrap().as_mut() {
+        add(summary);
+    }
+}
+
+/// Whether events of `level` are logged, by any of the directives of the filter
+pub fn enabled(level: Level) -> bool {
+    level <= LevelFilter::current()
+}
+
+/// Logs an error report after `message`, with its [`ErrorCode`] as field, and returns the code
+pub fn report<C>(message: &str, report: &Report<C>) -> ErrorCode {
+    let code = ErrorCode::of(report);
+    if !Failure::summarizes_others(report) {
+        record(|summary| summary.errors.extend(Failure::all_from_report(report)));
+    }
+    let text =
+        diagnostics(message, report, code).unwrap_or_else(|| format!("{message}: {report:?}"));
+    let text = match FORMAT.get() {
+        Some(LogFormat::Json) => strip_ansi(&text),
+        _ => text,
+    };
+    tracing::error!(code = %code, "{text}");
+    code
+}
+
+/// Whether error reports are logged as they are, instead of as diagnostics: with `-v` or
+/// `MODMOD_BACKTRACE=1`
+fn full_reports() -> bool {
+    enabled(Level::INFO) || std::env::var_os("MODMOD_BACKTRACE").is_some_and(|v| v == "1")
+}
+
+/// The errors in the report as [`Diagnostic`]s, followed by how many there are, if the
+/// report is about invalid definitions or content in known files, and it is logged in the
+/// human format. Reports that summarize others only give their message, as the errors
+/// they summarize were shown already.
+fn diagnostics<C>(message: &str, report: &Report<C>, code: ErrorCode) -> Option<String> {
+    let human = FORMAT.get() == Some(&LogFormat::Human);
+    if !human || code != ErrorCode::Validation || full_reports() {
+        return None;
+    }
+    if Failure::summarizes_others(report) {
+        return Some(format!(
+            "{message}: {}",
+            Failure::from_report(report).message
+        ));
+    }
+    let diagnostics = Diagnostic::from_report(report);
+    if !diagnostics.iter().any(|d| d.location.path.is_some()) {
+        return None;
+    }
+    let mut text = String::new();
+    for diagnostic in &diagnostics {
+        text.push_str(&diagnostic.render());
+        text.push_str("\n\n");
+    }
+    text.push_str(&format!(
+        "{message}: {} error(s). Run with -v or MODMOD_BACKTRACE=1 to see the full report",
+        diagnostics.len()
+    ));
+    Some(text)
+}
+
+/// Logs a warning of the render report, with its code and the file and line it is about as
+/// fields
+pub fn warning(warning: &Warning) {
+    record_warning(warning);
+    tracing::warn!(
+        warning = %warning.code,
+        id = warning.code.id(),
+        file = warning.source.as_ref().map(|source| tracing::field::display(source.display())),
+        line = warning.line,
+        artifact = warning.artifact.map(|artifact| artifact.as_str()),
+        "{warning}"
+    );
+}
+
+/// Counts a warning towards [`warning_counts`] and the failures the context collects,
+/// without logging it. For warnings that are shown in another way, like by `modmod check`.
+pub fn record_warning(warning: &Warning) {
+    *context::current()
+        .warning_counts
+        .lock()
+        .unwrap()
+        .entry(warning.code)
+        .or_default() += 1;
+    record(|summary| summary.warnings.push(Failure::from_warning(warning)));
+}
+
+/// How many warnings of each code were logged or recorded so far in the current context
+/// and the renders in it, also the ones below the level that is logged
+pub fn warning_counts() -> BTreeMap<WarningCode, usize> {
+    context::current().warning_counts.lock().unwrap().clone()
+}
+
+fn human_name(level: Level) -> &'static str {
+    match level {
+        Level::ERROR => "error",
+        Level::WARN => "warning",
+        Level::INFO => "info",
+        Level::DEBUG => "debug",
+        Level::TRACE => "trace",
+    }
+}
+
+fn strip_ansi_unless(text: &str, color: bool) -> String {
+    if color {
+        text.to_string()
+    } else {
+        strip_ansi(text)
+    }
+}
+
+/// Removes the escape sequences error reports are styled with
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip up to and including the final byte of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
@@ -0,0 +1,362 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    date::Date,
+    io::{self, PathExt},
+    progress::Timing,
+    update::{fnv1a, FNV_OFFSET},
+    RenderOverrides,
+};
+
+/// Name of the render manifest, in the root of the output of a track. Every render writes
+/// it, and `publish`, `serve`, `export` and `handout` read it. The hashes that
+/// `update` and `clean` go by are in [`HASHES_FILE`](crate::update::HASHES_FILE) instead.
+pub const RENDER_MANIFEST_FILE: &str = "modmod-manifest.json";
+
+/// Version of the format of the render manifest. It's increased on changes that
+/// tools reading the manifest may trip over, like removing or renaming fields.
+pub const RENDER_MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RenderManifestError {}
+
+impl fmt::Display for RenderManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to read or write render manifest")
+    }
+}
+
+impl error_stack::Context for RenderManifestError {}
+
+/// Describes everything that was rendered for a track, for deployment scripts and other
+/// tools. It's written to [`RENDER_MANIFEST_FILE`] in the output folder of the track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderManifest {
+    /// See [`RENDER_MANIFEST_VERSION`]
+    pub version: u32,
+    pub track: String,
+    /// When the track was rendered, in RFC 3339 format in UTC. Empty, and left out, in the
+    /// manifests of archives, see [`RenderManifest::archived`].
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub generated_at: String,
+    pub modmod_version: String,
+    /// The version of the course the track was rendered as, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub course_version: Option<String>,
+    /// The Slidev theme of the decks, if the slides were rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slide_theme: Option<String>,
+    /// Settings of the track that were overridden for this render
+    #[serde(default, skip_serializing_if = "RenderOverrides::is_empty")]
+    pub overrides: RenderOverrides,
+    pub artifacts: Vec<Artifact>,
+    /// How long each phase of the render and each artifact took, in the order they
+    /// finished. Left out when `$SOURCE_DATE_EPOCH` is set, so that the output can be
+    /// reproduced.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timings: Vec<Timing>,
+}
+
+/// A slide deck, book chapter or exercise package that was rendered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    /// Index of the module the artifact belongs to
+    pub module: usize,
+    /// Index of the unit within its module
+    pub unit: usize,
+    pub title: String,
+    /// Path of the rendered file or, for exercises, folder, relative to the output folder
+    pub path: String,
+    /// Paths of the content the artifact was rendered from, relative to the track definition
+    pub sources: Vec<String>,
+    /// Hash of the rendered content. For exercises, it covers the paths and content of all
+    /// files in the package.
+    pub hash: String,
+    /// When the artifact was last rendered with other content, which is the `generated_at` of
+    /// that render. Left out in manifests of earlier versions of modmod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// For exercises, whether their tests fail until they are done
+    #[serde(default, skip_serializing_if = "crate::load::serde_defaults::is_false")]
+    pub starter_fails_tests: bool,
+    /// The locale the artifact was rendered in, if it's another one than that of the track,
+    /// see [`RenderOptions::locale`](crate::RenderOptions::locale)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Deck,
+    Chapter,
+    Exercise,
+}
+
+impl ArtifactKind {
+    /// How the kind is written in the manifest
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deck => "deck",
+            Self::Chapter => "chapter",
+            Self::Exercise => "exercise",
+        }
+    }
+}
+
+impl RenderManifest {
+    pub(crate) fn new(track: &str) -> Self {
+        Self {
+            version: RENDER_MANIFEST_VERSION,
+            track: track.to_string(),
+            generated_at: now(),
+            modmod_version: crate::VERSION.to_string(),
+            course_version: None,
+            slide_theme: None,
+            overrides: RenderOverrides::default(),
+            artifacts: vec![],
+            timings: vec![],
+        }
+    }
+
+    /// Adds the rendered file or folder at `path`, relative to `out_dir`, and returns it
+    /// so that it can be filled in further
+    pub(crate) fn add(
+        &mut self,
+        out_dir: &Path,
+        kind: ArtifactKind,
+        (module, unit): (usize, usize),
+        title: &str,
+        path: &Path,
+        sources: Vec<String>,
+    ) -> Result<&mut Artifact, RenderManifestError> {
+        let full_path = out_dir.join(path);
+        // Hashed as it's read, as decks and exercise packages can be large
+        let mut hash = FNV_OFFSET;
+        let files = io::files_in::<RenderManifestError>(&full_path, false)?;
+        if let Some(files) = files {
+            for file in files.iter().map(io::DirEntry::path) {
+                let relative = file
+                    .strip_prefix(&full_path)
+                    .into_report()
+                    .change_context(RenderManifestError::default())?;
+                hash = fnv1a(hash, relative_path(relative).as_bytes());
+                hash = fnv1a(hash, &[0]);
+                hash = hash_chunks(hash, file)?;
+            }
+        } else {
+            hash = hash_chunks(hash, &full_path)?;
+        }
+        let hash = format!("{hash:016x}");
+        let index = self.artifacts.len();
+        self.artifacts.push(Artifact {
+            kind,
+            module,
+            unit,
+            title: title.to_string(),
+            path: relative_path(path),
+            sources,
+            hash,
+            updated_at: Some(self.generated_at.clone()),
+            starter_fails_tests: false,
+            locale: None,
+        });
+        Ok(&mut self.artifacts[index])
+    }
+
+    /// Takes when the artifacts were last changed from the `earlier` manifest of the same
+    /// output, for the artifacts that were rendered with the same content again
+    pub(crate) fn keep_updated_at(&mut self, earlier: &RenderManifest) {
+        for artifact in self.artifacts.iter_mut() {
+            let unchanged = earlier
+                .artifacts
+                .iter()
+                .find(|a| a.path == artifact.path && a.hash == artifact.hash);
+            if let Some(updated_at) = unchanged.and_then(|a| a.updated_at.clone()) {
+                artifact.updated_at = Some(updated_at);
+            }
+        }
+    }
+
+    /// The artifacts of the render whose files are in the root of the output folder, like
+    /// its landing page: those in the locale of the first artifact. The artifacts of the
+    /// other locales the track was rendered in come after them.
+    pub fn main_artifacts(&self) -> impl Iterator<Item = &Artifact> {
+        let locale = self.artifacts.first().map(|a| &a.locale);
+        self.artifacts
+            .iter()
+            .filter(move |a| Some(&a.locale) == locale)
+    }
+
+    /// Keeps the artifacts of the `earlier` manifest of the same output that were rendered in
+    /// other locales than `locale`, the one of this render, as long as they are still in
+    /// `out_dir`: a render leaves the output of the other locales as it is
+    pub(crate) fn keep_other_locales(
+        &mut self,
+        earlier: &RenderManifest,
+        locale: Option<&str>,
+        out_dir: &Path,
+    ) {
+        let others: Vec<_> = earlier
+            .artifacts
+            .iter()
+            .filter(|a| a.locale.as_deref() != locale && io::exists(&out_dir.join(&a.path)))
+            .cloned()
+            .collect();
+        self.artifacts.extend(others);
+    }
+
+    /// Keeps the `earlier` manifest of the same output as it is, with when it was generated
+    /// and how long that took, if it lists the same artifacts with the same content, so
+    /// that rendering a track again without changes leaves its output folder as it is.
+    /// Otherwise only takes when the artifacts were last changed, see
+    /// [`RenderManifest::keep_updated_at`].
+    pub(crate) fn keep_unchanged(&mut self, earlier: &RenderManifest) {
+        self.keep_updated_at(earlier);
+        let comparable = |manifest: &RenderManifest| {
+            let mut value = serde_json::to_value(manifest).ok()?;
+            let object = value.as_object_mut()?;
+            object.remove("generated_at");
+            object.remove("timings");
+            Some(value)
+        };
+        let unchanged = comparable(self).is_some_and(|value| Some(value) == comparable(earlier));
+        if unchanged {
+            self.generated_at.clone_from(&earlier.generated_at);
+            self.timings.clone_from(&earlier.timings);
+        }
+    }
+
+    pub(crate) fn timings(&mut self, timings: &[Timing]) {
+        if !reproducible() {
+            self.timings = timings.to_vec();
+        }
+    }
+
+    /// Reads the manifest of the output folder of a track, if it has one
+    pub fn read(out_dir: &Path) -> Result<Option<Self>, RenderManifestError> {
+        let path = out_dir.join(RENDER_MANIFEST_FILE);
+        if !io::exists(&path) {
+            return Ok(None);
+        }
+        serde_json::from_slice(&read(&path)?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Invalid manifest at {}", path.display()))
+            .change_context(RenderManifestError::default())
+    }
+
+    /// Reads the manifests of the tracks rendered into `out_dir`, which is either the output
+    /// folder of one track, or has a subfolder for each track. Returns them with the folder
+    /// of their track, relative to `out_dir`, which is empty for the output of one track.
+    pub fn read_all(out_dir: &Path) -> Result<Vec<(PathBuf, Self)>, RenderManifestError> {
+        if let Some(manifest) = Self::read(out_dir)? {
+            return Ok(vec![(PathBuf::new(), manifest)]);
+        }
+        let mut manifests = vec![];
+        if !out_dir.is_dir() {
+            return Ok(manifests);
+        }
+        let entries = std::fs::read_dir(out_dir)
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to read folder {}", out_dir.display()))
+            .change_context(RenderManifestError::default())?;
+        for entry in entries {
+            let entry = entry
+                .into_report()
+                .change_context(RenderManifestError::default())?;
+            let name = PathBuf::from(entry.file_name());
+            if let Some(manifest) = Self::read(&out_dir.join(&name))? {
+                manifests.push((name, manifest));
+            }
+        }
+        manifests.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(manifests)
+    }
+
+    /// The manifest at `path` as it's put in archives: without when the track was rendered,
+    /// when each artifact was last changed and how long that took, so that archiving the
+    /// same content gives the same archive
+    pub(crate) fn archived(path: &Path) -> Result<Vec<u8>, RenderManifestError> {
+        let mut manifest: Self = serde_json::from_slice(&read(path)?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Invalid manifest at {}", path.display()))
+            .change_context(RenderManifestError::default())?;
+        manifest.generated_at.clear();
+        manifest.timings.clear();
+        for artifact in manifest.artifacts.iter_mut() {
+            artifact.updated_at = None;
+        }
+        serde_json::to_vec_pretty(&manifest)
+            .into_report()
+            .change_context(RenderManifestError::default())
+    }
+
+    pub(crate) fn write(&self, out_dir: &Path) -> Result<(), RenderManifestError> {
+        let json = serde_json::to_string_pretty(self)
+            .into_report()
+            .change_context(RenderManifestError::default())?;
+        out_dir.join(RENDER_MANIFEST_FILE).write_file(json)
+    }
+}
+
+/// A relative path with forward slashes, as written in the manifest
+pub(crate) fn relative_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, RenderManifestError> {
+    io::read(path)
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+        .change_context(RenderManifestError::default())
+}
+
+/// `hash` with the content of the file at `path` added, a chunk at a time
+fn hash_chunks(mut hash: u64, path: &Path) -> Result<u64, RenderManifestError> {
+    io::read_chunks(path, |chunk| hash = fnv1a(hash, chunk))
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+        .change_context(RenderManifestError::default())?;
+    Ok(hash)
+}
+
+/// Whether the output is to be reproducible, because `$SOURCE_DATE_EPOCH` is set
+fn reproducible() -> bool {
+    std::env::var_os("SOURCE_DATE_EPOCH").is_some()
+}
+
+/// The current time in RFC 3339 format, in UTC. `$SOURCE_DATE_EPOCH` overrides it, so
+/// that the output can be reproduced.
+fn now() -> String {
+    let from_env = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok());
+    let secs = from_env.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    });
+    let date = Date::from_days_since_epoch((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{date}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
@@ -0,0 +1,179 @@
+//! Upgrading definition files to newer versions of their format, so that tracks written for
+//! an older modmod keep up with renamed and restructured keys.
+//!
+//! Definition files say which version of the format they are written in with
+//! `schema_version`, and files without it are at version 1. Each change to the format adds a
+//! [`Migration`] to [`MIGRATIONS`] that upgrades files from the version before, and bumps
+//! [`CURRENT_SCHEMA_VERSION`].
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::Deserialize;
+use toml_edit::{value, Document, Key, Table};
+
+use crate::{format::DefinitionKind, Track};
+
+/// The version of the definition format this version of modmod writes
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// The key definition files set their schema version with
+pub(crate) const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct MigrateError {}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to migrate definition file")
+    }
+}
+
+impl error_stack::Context for MigrateError {}
+
+/// A change to the definition format, that upgrades files from schema version `from` to
+/// `from + 1`
+pub struct Migration {
+    pub from: u32,
+    /// What the migration changes
+    pub summary: &'static str,
+    /// Changes the root table of a definition of the given kind, and describes each change it
+    /// made. Files it doesn't apply to are left alone.
+    apply: fn(&mut Table, DefinitionKind) -> Vec<String>,
+}
+
+/// The migrations, one for every schema version before [`CURRENT_SCHEMA_VERSION`], in order
+pub static MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    summary: "`modmod-version` is named `modmod_version`, like the other keys",
+    apply: rename_modmod_version,
+}];
+
+fn rename_modmod_version(table: &mut Table, kind: DefinitionKind) -> Vec<String> {
+    if kind != DefinitionKind::Track {
+        return vec![];
+    }
+    rename_key(table, "modmod-version", "modmod_version")
+}
+
+/// Renames a key, keeping its place and the comments before it
+fn rename_key(table: &mut Table, from: &str, to: &str) -> Vec<String> {
+    let order: Vec<String> = table
+        .iter()
+        .map(|(key, _)| match key == from {
+            true => to.to_string(),
+            false => key.to_string(),
+        })
+        .collect();
+    let Some((key, item)) = table.remove_entry(from) else {
+        return vec![];
+    };
+    table.insert_formatted(&Key::new(to).with_decor(key.decor().clone()), item);
+    let position = |key: &Key| order.iter().position(|k| k == key.get());
+    table.sort_values_by(|a, _, b, _| position(a).cmp(&position(b)));
+    vec![format!("renamed `{from}` to `{to}`")]
+}
+
+#[derive(Deserialize)]
+struct Header {
+    schema_version: Option<u32>,
+}
+
+/// The schema version the definition is written in. TOML that can't be parsed is reported
+/// as error.
+pub fn schema_version(content: &str) -> Result<u32, MigrateError> {
+    let header: Header = toml::from_str(content)
+        .into_report()
+        .change_context(MigrateError::default())?;
+    Ok(header.schema_version.unwrap_or(1))
+}
+
+/// Refuses definitions written in a newer version of the format than this modmod knows.
+/// Problems parsing the definition are left to loading it.
+pub(crate) fn check_schema_version(content: &str) -> std::result::Result<(), String> {
+    match schema_version(content) {
+        Ok(version) if version > CURRENT_SCHEMA_VERSION => Err(format!(
+            "The definition has schema version {version}, but this modmod only knows up to version {CURRENT_SCHEMA_VERSION}. Please upgrade modmod to load it"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A definition file migrated by [`migrate_definition`]
+#[derive(Debug, Clone)]
+pub struct MigratedDefinition {
+    /// The schema version the file was in
+    pub from: u32,
+    /// The schema version the file is in now, which is `from` if it was at or past the
+    /// version it was migrated to
+    pub to: u32,
+    /// What was changed, in order
+    pub changes: Vec<String>,
+    pub content: String,
+}
+
+/// Applies the migrations from the schema version of the definition up to version `to`,
+/// and sets its `schema_version` to the version it ends up at
+pub fn migrate_definition(
+    content: &str,
+    kind: DefinitionKind,
+    to: u32,
+) -> Result<MigratedDefinition, MigrateError> {
+    let from = schema_version(content)?;
+    if to > CURRENT_SCHEMA_VERSION {
+        return Err(
+            Report::new(MigrateError::default()).attach_printable(format!(
+                "Schema version {to} doesn't exist, the latest is {CURRENT_SCHEMA_VERSION}"
+            )),
+        );
+    }
+    if from >= to {
+        return Ok(MigratedDefinition {
+            from,
+            to: from,
+            changes: vec![],
+            content: content.to_string(),
+        });
+    }
+
+    let mut doc: Document = content
+        .parse()
+        .into_report()
+        .change_context(MigrateError::default())?;
+    let table = doc.as_table_mut();
+    let mut changes = vec![];
+    for migration in MIGRATIONS.iter().filter(|m| (from..to).contains(&m.from)) {
+        changes.extend((migration.apply)(table, kind));
+    }
+    table.insert(SCHEMA_VERSION_KEY, value(i64::from(to)));
+    let position = |key: &Key| key.get() != SCHEMA_VERSION_KEY;
+    table.sort_values_by(|a, _, b, _| position(a).cmp(&position(b)));
+    changes.push(format!("set `{SCHEMA_VERSION_KEY} = {to}`"));
+    Ok(MigratedDefinition {
+        from,
+        to,
+        changes,
+        content: doc.to_string(),
+    })
+}
+
+impl Track {
+    /// The definition files the track is loaded from: its own, those of the tracks it
+    /// extends, and those of its modules and topics
+    pub fn definition_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<&Path> = vec![&self.definition];
+        files.extend(self.bases.iter().map(PathBuf::as_path));
+        files.extend(self.modules.iter().map(|m| m.data.definition.as_path()));
+        files.extend(self.topics().map(|t| t.definition.as_path()));
+        let mut seen = std::collections::HashSet::new();
+        files
+            .into_iter()
+            .filter(|file| seen.insert(*file))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+}
@@ -0,0 +1,168 @@
+//! Loading and rendering independent items, like the definitions, decks and exercise
+//! packages of a track, on more than one thread
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use error_stack::{Context, Report, Result};
+
+use crate::context;
+
+/// The number of threads to load and render on: that of the current context, see
+/// [`RunContext::jobs`](crate::context::RunContext::jobs), or else the number of CPUs
+pub fn jobs() -> usize {
+    context::current()
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// Whether rendering stops at the first item that fails, see
+/// [`RunContext::fail_fast`](crate::context::RunContext::fail_fast)
+pub fn fail_fast() -> bool {
+    context::current().fail_fast
+}
+
+/// Calls `f` on each of `items`, spread over [`jobs`] threads, and returns the results in
+/// the order of the items, whichever finishes first. If items fail, the errors of all of
+/// them are returned in one report, in the order of the items. With [`fail_fast`],
+/// the items that haven't started yet once an item fails are skipped, and the error of the
+/// first item that failed is returned, like a sequential loop would.
+pub(crate) fn map<'a, T, R, C>(
+    items: &'a [T],
+    f: impl Fn(&'a T) -> Result<R, C> + Sync,
+) -> Result<Vec<R>, C>
+where
+    T: Sync,
+    R: Send,
+    C: Context,
+{
+    let mut done = vec![];
+    let mut errors = None;
+    for result in map_each(items, f) {
+        match result {
+            Ok(value) => done.push(value),
+            Err(error) => push_error(&mut errors, error)?,
+        }
+    }
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(done),
+    }
+}
+
+/// Like [`map`], but returns the result of each item, so that what the items that succeeded
+/// share can be written as well. With [`fail_fast`], there are no results for the items
+/// after the first that failed.
+pub(crate) fn map_each<'a, T, R, C>(
+    items: &'a [T],
+    f: impl Fn(&'a T) -> Result<R, C> + Sync,
+) -> Vec<Result<R, C>>
+where
+    T: Sync,
+    R: Send,
+    C: Context,
+{
+    let fail_fast = fail_fast();
+    let jobs = jobs().min(items.len());
+    if jobs <= 1 {
+        let mut results = vec![];
+        for item in items {
+            let result = f(item);
+            let failed = result.is_err();
+            results.push(result);
+            if failed && fail_fast {
+                break;
+            }
+        }
+        return results;
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Vec<Mutex<Option<Result<R, C>>>> =
+        items.iter().map(|_| Mutex::new(None)).collect();
+    // The threads continue the caller's span, so the spans of the items nest in it
+    let span = tracing::Span::current();
+    let context = context::current();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                let _span = span.enter();
+                let _context = context::enter(context.clone());
+                while !(fail_fast && failed.load(Ordering::Relaxed)) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let result = f(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    *results[i].lock().unwrap() = Some(result);
+                }
+            });
+        }
+    });
+    // Items are started in order, so all items before the first failure have a result
+    let results = results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap());
+    match fail_fast {
+        true => {
+            let mut done = vec![];
+            for result in results.map_while(|result| result) {
+                let failed = result.is_err();
+                done.push(result);
+                if failed {
+                    break;
+                }
+            }
+            done
+        }
+        false => results.flatten().collect(),
+    }
+}
+
+/// Adds `error` to the errors in `errors`, so that they are reported together once the
+/// other items are done. With [`fail_fast`], all errors so far are returned right away.
+pub(crate) fn push_error<C>(errors: &mut Option<Report<C>>, error: Report<C>) -> Result<(), C> {
+    match errors {
+        Some(errors) => errors.extend_one(error),
+        None => *errors = Some(error),
+    }
+    match fail_fast() {
+        true => Err(errors.take().unwrap()),
+        false => Ok(()),
+    }
+}
+
+/// Applies `f` to all items using at most `threads` threads, keeping the results in the order
+/// of the items
+pub(crate) fn map_with<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    let context = context::current();
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(|| {
+                    let _context = context::enter(context.clone());
+                    chunk.iter().map(&f).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
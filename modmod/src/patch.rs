@@ -8,7 +8,7 @@ use std::{
     path::Path,
 };
 
-use crate::io::PathExt;
+use crate::io::{slash_path, PathExt};
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -39,20 +39,22 @@ impl Patch {
         }: GenPatchOptions<N, O, P>,
     ) -> Result<(), GenPatchError> {
         use std::io::Write;
-        let mut patch_file = std::fs::File::create(&patch_file).unwrap();
+        let mut patch_file = patch_file.as_ref().create_file::<GenPatchError>()?;
 
         'files: for new_file_path in new_dir.as_ref().get_dir_content()?.files {
             let relative_file_path = Path::new(&new_file_path)
                 .strip_prefix(new_dir.as_ref())
                 .unwrap();
 
+            let patch_path = slash_path::<GenPatchError>(relative_file_path)?;
+
             let mut new_file = new_file_path.open_file()?;
             let old_file_path = old_dir.as_ref().join(relative_file_path);
 
             let mut old_file = match fs::File::open(&old_file_path) {
                 Ok(f) => f,
                 Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    println!("File not found at {}", old_file_path.to_str().unwrap());
+                    println!("File not found at {}", old_file_path.display());
                     #[cfg(unix)]
                     {
                         fs::File::open("/dev/null").unwrap()
@@ -87,7 +89,7 @@ impl Patch {
                         println!(
                             "{} and {} are the same",
                             new_file_path.as_str(),
-                            old_file_path.to_str().unwrap()
+                            old_file_path.display()
                         );
                         continue 'files;
                     }
@@ -96,10 +98,8 @@ impl Patch {
                 write!(
                     patch_file,
                     "{}",
-                    diff.unified_diff().header(
-                        &format!("a/{}", relative_file_path.to_str().unwrap()),
-                        &format!("b/{}", relative_file_path.to_str().unwrap()),
-                    )
+                    diff.unified_diff()
+                        .header(&format!("a/{patch_path}"), &format!("b/{patch_path}"),)
                 )
                 .unwrap();
 
@@ -121,10 +121,7 @@ impl Patch {
                 let diff = TextDiff::from_lines(&old, &new);
 
                 diff.unified_diff()
-                    .header(
-                        &format!("a/{}", relative_file_path.to_str().unwrap()),
-                        &format!("b/{}", relative_file_path.to_str().unwrap()),
-                    )
+                    .header(&format!("a/{patch_path}"), &format!("b/{patch_path}"))
                     .to_writer(&mut patch_file)
                     .into_report()
                     .change_context(GenPatchError::default())?;
@@ -134,6 +131,6 @@ impl Patch {
 
             unreachable!("All types of files should have been handled");
         }
-        Ok(())
+        patch_file.commit()
     }
 }
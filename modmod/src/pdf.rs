@@ -0,0 +1,638 @@
+//! Putting handouts together: reading the pages of existing files, like exported slides,
+//! laying out pages of text, and writing both into a new file. Files are read and written
+//! with lopdf.
+//!
+//! Pages are copied with everything they refer to, and their content is left as it is, so
+//! their content streams never have to be decompressed. Encrypted files are not read.
+//!
+//! Text pages use the standard fonts every PDF reader has, so no fonts are embedded, and
+//! their text is limited to the characters of the Windows-1252 encoding. Written files are
+//! deterministic: they hold no timestamps or identifiers.
+
+// Input files come from elsewhere, so problems with them are reported rather than panicked on
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{collections::HashMap, fmt, io::Write};
+
+use error_stack::{Report, Result};
+use lopdf::{Dictionary, Object, ObjectId, Stream, StringFormat};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub(crate) struct PdfError {}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to read or write PDF")
+    }
+}
+
+impl error_stack::Context for PdfError {}
+
+fn error(message: impl fmt::Display + fmt::Debug + Send + Sync + 'static) -> Report<PdfError> {
+    Report::new(PdfError::default()).attach_printable(message)
+}
+
+/// Nesting of the page tree beyond this is taken to be a broken file
+const MAX_DEPTH: usize = 64;
+
+/// A PDF file that was read
+pub(crate) struct Document(lopdf::Document);
+
+/// Attributes of pages that they can take from the nodes of the page tree above them
+const INHERITED: &[&str] = &["Resources", "MediaBox", "CropBox", "Rotate"];
+
+impl Document {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, PdfError> {
+        let header = &bytes[..1024.min(bytes.len())];
+        if !header.windows(5).any(|window| window == b"%PDF-") {
+            return Err(error("The file isn't a PDF"));
+        }
+        let document = lopdf::Document::load_mem(bytes)
+            .map_err(|e| error(format!("The PDF can't be read: {e}")))?;
+        if document.was_encrypted() || document.trailer.has(b"Encrypt") {
+            return Err(error("The PDF is encrypted, so its pages can't be copied"));
+        }
+        Ok(Self(document))
+    }
+
+    /// The pages in order, with their object ids and with the attributes they inherit
+    /// added to them
+    fn pages(&self) -> Result<Vec<(ObjectId, Dictionary)>, PdfError> {
+        let document = &self.0;
+        let ids = document.get_pages();
+        if ids.is_empty() && document.catalog().is_err() {
+            return Err(error("The PDF has no catalog of its pages"));
+        }
+        let mut pages = vec![];
+        for id in ids.into_values() {
+            let mut page = document
+                .get_dictionary(id)
+                .map_err(|_| error("A page of the PDF isn't a dictionary"))?
+                .clone();
+            let mut node = page.get(b"Parent").and_then(Object::as_reference).ok();
+            for _ in 0..MAX_DEPTH {
+                let Some(parent) = node.and_then(|id| document.get_dictionary(id).ok()) else {
+                    break;
+                };
+                for key in INHERITED {
+                    if let (false, Ok(value)) =
+                        (page.has(key.as_bytes()), parent.get(key.as_bytes()))
+                    {
+                        page.set(*key, value.clone());
+                    }
+                }
+                node = parent.get(b"Parent").and_then(Object::as_reference).ok();
+            }
+            pages.push((id, page));
+        }
+        Ok(pages)
+    }
+}
+
+/// Page size of text pages, A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+
+/// Fonts of text pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Font {
+    Regular,
+    Bold,
+    Italic,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Self; 4] = [Self::Regular, Self::Bold, Self::Italic, Self::Mono];
+
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+            Self::Italic => "F3",
+            Self::Mono => "F4",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Self::Regular => "Helvetica",
+            Self::Bold => "Helvetica-Bold",
+            Self::Italic => "Helvetica-Oblique",
+            Self::Mono => "Courier",
+        }
+    }
+
+    /// Width of `text` in points, when set in `size`
+    fn width(self, text: &[u8], size: f32) -> f32 {
+        let units: u32 = match self {
+            Self::Mono => 600 * text.len() as u32,
+            _ => text.iter().map(|byte| helvetica_width(*byte)).sum(),
+        };
+        // Bold is a bit wider than the widths of the regular font
+        let scale = if self == Self::Bold { 1.08 } else { 1.0 };
+        units as f32 * scale * size / 1000.0
+    }
+}
+
+/// Width of a character of Helvetica, in thousandths of the font size
+fn helvetica_width(byte: u8) -> u32 {
+    const WIDTHS: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584,
+    ];
+    match byte {
+        32..=126 => u32::from(WIDTHS[usize::from(byte - 32)]),
+        _ => 556,
+    }
+}
+
+/// `text` in the Windows-1252 encoding of the standard fonts, with `?` for what it can't hold
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        // Selects how the character before is shown
+        .filter(|c| !matches!(c, '\u{fe00}'..='\u{fe0f}'))
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            // Content rates exercises with stars
+            '⭐' | '★' => b'*',
+            '\t' => b' ',
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Lays out text on pages
+pub(crate) struct TextLayout {
+    pages: Vec<Vec<u8>>,
+    page: Vec<u8>,
+    y: f32,
+}
+
+impl TextLayout {
+    pub(crate) fn new() -> Self {
+        Self {
+            pages: vec![],
+            page: vec![],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Continues on a new page, unless nothing is on the current one yet
+    pub(crate) fn page_break(&mut self) {
+        if !self.page.is_empty() {
+            self.pages.push(std::mem::take(&mut self.page));
+        }
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    pub(crate) fn space(&mut self, points: f32) {
+        if !self.page.is_empty() {
+            self.y -= points;
+        }
+    }
+
+    fn line(&mut self, text: &[u8], font: Font, size: f32, x: f32) {
+        let leading = size * 1.3;
+        if self.y - leading < MARGIN {
+            self.page_break();
+        }
+        self.y -= leading;
+        let mut escaped = Vec::with_capacity(text.len());
+        for byte in text {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                escaped.push(b'\\');
+            }
+            escaped.push(*byte);
+        }
+        let _ = write!(
+            self.page,
+            "BT /{} {size} Tf {:.2} {:.2} Td (",
+            font.resource(),
+            MARGIN + x,
+            self.y
+        );
+        self.page.extend(escaped);
+        self.page.extend(b") Tj ET\n");
+    }
+
+    /// Writes `text` wrapped to the width of the page, `indent` points from its margin
+    pub(crate) fn paragraph(&mut self, text: &str, font: Font, size: f32, indent: f32) {
+        self.item("", text, font, size, indent);
+    }
+
+    /// Writes `marker` at `indent`, and `text` wrapped next to it
+    pub(crate) fn item(&mut self, marker: &str, text: &str, font: Font, size: f32, indent: f32) {
+        let marker = win_ansi(marker);
+        let offset = match marker.is_empty() {
+            true => 0.0,
+            false => Font::Regular.width(&marker, size) + size * 0.5,
+        };
+        let width = PAGE_WIDTH - 2.0 * MARGIN - indent - offset;
+        let mut lines = vec![];
+        let mut line: Vec<u8> = vec![];
+        for word in text.split_whitespace().map(win_ansi) {
+            let mut candidate = line.clone();
+            if !candidate.is_empty() {
+                candidate.push(b' ');
+            }
+            candidate.extend(&word);
+            if line.is_empty() || font.width(&candidate, size) <= width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word));
+            }
+            // Words that don't fit on a line of their own are broken
+            while font.width(&line, size) > width && line.len() > 1 {
+                let mut split = line.len() - 1;
+                while split > 1 && font.width(&line[..split], size) > width {
+                    split -= 1;
+                }
+                let rest = line.split_off(split);
+                lines.push(std::mem::replace(&mut line, rest));
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            if i == 0 && !marker.is_empty() {
+                self.line(&marker, Font::Regular, size, indent);
+                self.y += size * 1.3;
+            }
+            self.line(&line, font, size, indent + offset);
+        }
+    }
+
+    /// Writes the lines of `text` as they are, breaking those that don't fit
+    pub(crate) fn preformatted(&mut self, text: &str, size: f32, indent: f32) {
+        let columns = ((PAGE_WIDTH - 2.0 * MARGIN - indent) / (0.6 * size)) as usize;
+        for line in text.lines() {
+            let line = win_ansi(line);
+            if line.is_empty() {
+                self.line(b"", Font::Mono, size, indent);
+            }
+            for chunk in line.chunks(columns.max(1)) {
+                self.line(chunk, Font::Mono, size, indent);
+            }
+        }
+    }
+
+    /// The content streams of the pages
+    pub(crate) fn finish(mut self) -> Vec<Vec<u8>> {
+        self.page_break();
+        self.pages
+    }
+}
+
+/// A PDF that is put together from the pages of other files and new pages of text
+pub(crate) struct PdfBuilder {
+    document: lopdf::Document,
+    /// The root of the page tree, which is written once the pages are known
+    page_tree: ObjectId,
+    pages: Vec<ObjectId>,
+    fonts: Option<Dictionary>,
+}
+
+impl PdfBuilder {
+    pub(crate) fn new() -> Self {
+        let mut document = lopdf::Document::with_version("1.7");
+        let page_tree = document.new_object_id();
+        Self {
+            document,
+            page_tree,
+            pages: vec![],
+            fonts: None,
+        }
+    }
+
+    pub(crate) fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Adds the pages of `document`, and returns how many there were
+    pub(crate) fn append(&mut self, document: &Document) -> Result<usize, PdfError> {
+        let pages = document.pages()?;
+        let mut ids = HashMap::new();
+        let mut queue = vec![];
+        // Links between pages keep pointing to the same page
+        let page_ids: Vec<_> = pages
+            .iter()
+            .map(|(old, _)| {
+                let new = self.document.new_object_id();
+                ids.insert(*old, new);
+                new
+            })
+            .collect();
+        for ((_, page), new) in pages.iter().zip(page_ids.iter()) {
+            let mut dict = Dictionary::new();
+            for (key, value) in page.iter() {
+                if key != b"Parent" && key != b"B" {
+                    let value = self.import(value, &document.0, &mut ids, &mut queue);
+                    dict.set(key.clone(), value);
+                }
+            }
+            dict.set("Parent", self.page_tree);
+            self.document.set_object(*new, dict);
+        }
+        while let Some(old) = queue.pop() {
+            let object = document.0.get_object(old).cloned().unwrap_or(Object::Null);
+            let object = self.import(&object, &document.0, &mut ids, &mut queue);
+            if let Some(new) = ids.get(&old) {
+                self.document.set_object(*new, object);
+            }
+        }
+        self.pages.extend(page_ids);
+        Ok(pages.len())
+    }
+
+    /// A copy of `object` of `document`, with the objects it refers to added
+    fn import(
+        &mut self,
+        object: &Object,
+        document: &lopdf::Document,
+        ids: &mut HashMap<ObjectId, ObjectId>,
+        queue: &mut Vec<ObjectId>,
+    ) -> Object {
+        match object {
+            Object::Reference(old) if !document.objects.contains_key(old) => Object::Null,
+            Object::Reference(old) => match ids.get(old) {
+                Some(new) => Object::Reference(*new),
+                None => {
+                    let new = self.document.new_object_id();
+                    ids.insert(*old, new);
+                    queue.push(*old);
+                    Object::Reference(new)
+                }
+            },
+            Object::Array(items) => Object::Array(
+                items
+                    .iter()
+                    .map(|item| self.import(item, document, ids, queue))
+                    .collect(),
+            ),
+            Object::Dictionary(dict) => {
+                Object::Dictionary(self.import_dict(dict, document, ids, queue))
+            }
+            Object::Stream(stream) => {
+                let mut stream = stream.clone();
+                stream.dict = self.import_dict(&stream.dict, document, ids, queue);
+                Object::Stream(stream)
+            }
+            object => object.clone(),
+        }
+    }
+
+    fn import_dict(
+        &mut self,
+        dict: &Dictionary,
+        document: &lopdf::Document,
+        ids: &mut HashMap<ObjectId, ObjectId>,
+        queue: &mut Vec<ObjectId>,
+    ) -> Dictionary {
+        dict.iter()
+            .map(|(key, value)| (key.clone(), self.import(value, document, ids, queue)))
+            .collect()
+    }
+
+    /// Adds A4 pages with the content streams of a [`TextLayout`]
+    pub(crate) fn add_text_pages(&mut self, pages: Vec<Vec<u8>>) {
+        if self.fonts.is_none() && !pages.is_empty() {
+            let mut fonts = Dictionary::new();
+            for font in Font::ALL {
+                let mut dict = Dictionary::new();
+                dict.set("Type", Object::Name(b"Font".to_vec()));
+                dict.set("Subtype", Object::Name(b"Type1".to_vec()));
+                dict.set("BaseFont", Object::Name(font.base_font().into()));
+                dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+                fonts.set(font.resource(), self.document.add_object(dict));
+            }
+            self.fonts = Some(fonts);
+        }
+        let fonts = self.fonts.clone().unwrap_or_default();
+        for content in pages {
+            let content = self
+                .document
+                .add_object(Stream::new(Dictionary::new(), content));
+            let mut resources = Dictionary::new();
+            resources.set("Font", fonts.clone());
+            let mut page = Dictionary::new();
+            page.set("Type", Object::Name(b"Page".to_vec()));
+            page.set("Parent", self.page_tree);
+            page.set(
+                "MediaBox",
+                [0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT]
+                    .map(|v| Object::Integer(v as i64))
+                    .to_vec(),
+            );
+            page.set("Resources", resources);
+            page.set("Contents", content);
+            let page = self.document.add_object(page);
+            self.pages.push(page);
+        }
+    }
+
+    /// The file, with `title` as the title of the document
+    pub(crate) fn finish(mut self, title: &str) -> Result<Vec<u8>, PdfError> {
+        let kids: Vec<Object> = self.pages.iter().map(|page| (*page).into()).collect();
+        let mut tree = Dictionary::new();
+        tree.set("Type", Object::Name(b"Pages".to_vec()));
+        tree.set("Kids", kids);
+        tree.set("Count", self.pages.len() as i64);
+        self.document.set_object(self.page_tree, tree);
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", self.page_tree);
+        let catalog = self.document.add_object(catalog);
+        let mut info = Dictionary::new();
+        info.set(
+            "Title",
+            Object::String(utf16(title), StringFormat::Hexadecimal),
+        );
+        info.set(
+            "Producer",
+            Object::string_literal(format!("modmod {}", crate::VERSION)),
+        );
+        let info = self.document.add_object(info);
+        self.document.trailer.set("Root", catalog);
+        self.document.trailer.set("Info", info);
+
+        let mut file = vec![];
+        self.document
+            .save_to(&mut file)
+            .map_err(|e| error(format!("The PDF can't be written: {e}")))?;
+        Ok(file)
+    }
+}
+
+/// A text string in UTF-16, which any title can be written in
+fn utf16(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xfe, 0xff];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    bytes
+}
+
+#[cfg(test)]
+// Tests fail by panicking
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn text_pages(paragraphs: &[&str]) -> Vec<Vec<u8>> {
+        let mut layout = TextLayout::new();
+        for (i, paragraph) in paragraphs.iter().enumerate() {
+            if i > 0 {
+                layout.page_break();
+            }
+            layout.paragraph(paragraph, Font::Regular, 11.0, 0.0);
+        }
+        layout.finish()
+    }
+
+    fn text_pdf(paragraphs: &[&str], title: &str) -> Vec<u8> {
+        let mut builder = PdfBuilder::new();
+        builder.add_text_pages(text_pages(paragraphs));
+        builder.finish(title).unwrap()
+    }
+
+    /// A PDF like those exporters write, with its catalog and page tree in a compressed
+    /// object stream, and a media box that its pages inherit from the page tree
+    fn object_stream_pdf(contents: &[&str]) -> Vec<u8> {
+        let mut document = lopdf::Document::with_version("1.5");
+        let tree = document.new_object_id();
+        let kids: Vec<Object> = contents
+            .iter()
+            .map(|content| {
+                let content = document
+                    .add_object(Stream::new(Dictionary::new(), content.as_bytes().to_vec()));
+                let mut page = Dictionary::new();
+                page.set("Type", Object::Name(b"Page".to_vec()));
+                page.set("Parent", tree);
+                page.set("Contents", content);
+                document.add_object(page).into()
+            })
+            .collect();
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name(b"Pages".to_vec()));
+        pages.set("Count", kids.len() as i64);
+        pages.set("Kids", kids);
+        pages.set("MediaBox", vec![0.into(), 0.into(), 200.into(), 100.into()]);
+        document.set_object(tree, pages);
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", tree);
+        let catalog = document.add_object(catalog);
+        document.trailer.set("Root", catalog);
+        document.compress();
+        let mut file = vec![];
+        document.save_modern(&mut file).unwrap();
+        file
+    }
+
+    fn ascii(bytes: &[u8]) -> &str {
+        std::str::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn written_files_read_back_with_lopdf() {
+        let file = text_pdf(&["First page", "Second page"], "Modüle 1");
+        let document = lopdf::Document::load_mem(&file).unwrap();
+        let pages = document.get_pages();
+        assert_eq!(pages.len(), 2);
+        let content = document.get_page_content(pages[&1]);
+        assert!(
+            ascii(&content).contains("(First page) Tj"),
+            "{}",
+            ascii(&content)
+        );
+
+        let info = document
+            .trailer
+            .get(b"Info")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let title = document
+            .get_dictionary(info)
+            .unwrap()
+            .get(b"Title")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert_eq!(title, utf16("Modüle 1"));
+
+        // The file is the same every time
+        assert_eq!(file, text_pdf(&["First page", "Second page"], "Modüle 1"));
+    }
+
+    #[test]
+    fn merged_files_keep_the_pages_and_their_content() {
+        let mut builder = PdfBuilder::new();
+        builder.add_text_pages(text_pages(&["Cover"]));
+        let slides = Document::parse(&text_pdf(&["Slide 1", "Slide 2"], "Slides")).unwrap();
+        assert_eq!(builder.append(&slides).unwrap(), 2);
+        let slides = Document::parse(&object_stream_pdf(&["BT (Deck) Tj ET"])).unwrap();
+        assert_eq!(builder.append(&slides).unwrap(), 1);
+        assert_eq!(builder.page_count(), 4);
+        let file = builder.finish("Handout").unwrap();
+
+        let document = lopdf::Document::load_mem(&file).unwrap();
+        let pages = document.get_pages();
+        assert_eq!(pages.len(), 4);
+        for (page, text) in [
+            (1, "(Cover)"),
+            (2, "(Slide 1)"),
+            (3, "(Slide 2)"),
+            (4, "(Deck)"),
+        ] {
+            let content = document.get_page_content(pages[&page]);
+            assert!(ascii(&content).contains(text), "{text} on page {page}");
+        }
+        // The media box of the page tree of the last file is inherited by its page
+        let page = document.get_dictionary(pages[&4]).unwrap();
+        assert!(page.get(b"MediaBox").is_ok());
+    }
+
+    #[test]
+    fn broken_and_encrypted_files_are_refused() {
+        let error = Document::parse(b"<html></html>").err().unwrap();
+        assert!(format!("{error:?}").contains("The file isn't a PDF"));
+
+        let mut file = text_pdf(&["Broken"], "Broken");
+        file.truncate(file.len() / 2);
+        let error = Document::parse(&file).err().unwrap();
+        assert!(
+            format!("{error:?}").contains("The PDF can't be read"),
+            "{error:?}"
+        );
+
+        let file = text_pdf(&["Secret"], "Secret");
+        let mut document = lopdf::Document::load_mem(&file).unwrap();
+        let mut encrypt = Dictionary::new();
+        encrypt.set("Filter", Object::Name(b"Standard".to_vec()));
+        let encrypt = document.add_object(encrypt);
+        document.trailer.set("Encrypt", encrypt);
+        let mut encrypted = vec![];
+        document.save_to(&mut encrypted).unwrap();
+        let error = Document::parse(&encrypted).err().unwrap();
+        assert!(
+            format!("{error:?}").contains("The PDF is encrypted"),
+            "{error:?}"
+        );
+    }
+}
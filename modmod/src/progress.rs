@@ -0,0 +1,337 @@
+//! Progress of loading and rendering a track, for showing that a long render is getting
+//! somewhere. Nothing is reported unless the [`RunContext`](crate::context::RunContext) of
+//! the run has a [`ProgressReporter`], see
+//! [`RunContext::progress`](crate::context::RunContext::progress).
+
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{field::Empty, info_span, span::EnteredSpan, Span};
+
+use crate::context;
+
+/// The phases of a run, which each report their progress on their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Loading the definitions of the track. How many there are isn't known up front.
+    Load,
+    Slides,
+    /// Copying the images of the slides
+    Images,
+    Book,
+    Exercises,
+    /// Writing the output into an archive
+    Archive,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Load => "Loading",
+            Self::Slides => "Rendering slides",
+            Self::Images => "Copying images",
+            Self::Book => "Rendering book",
+            Self::Exercises => "Copying exercises",
+            Self::Archive => "Writing archive",
+        })
+    }
+}
+
+impl Phase {
+    /// The name of the phase, as in the render report and the spans of the log
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Load => "load",
+            Self::Slides => "slides",
+            Self::Images => "images",
+            Self::Book => "book",
+            Self::Exercises => "exercises",
+            Self::Archive => "archive",
+        }
+    }
+
+    /// The span of the log the phase is, named after it. Span names have to be known at
+    /// compile time, which is why they are spelled out here.
+    fn span(self) -> Span {
+        match self {
+            Self::Load => info_span!("load"),
+            Self::Slides => info_span!("slides"),
+            Self::Images => info_span!("images"),
+            Self::Book => info_span!("book"),
+            Self::Exercises => info_span!("exercises"),
+            Self::Archive => info_span!("archive"),
+        }
+    }
+
+    /// The span of the log of an item of the phase, named after its [kind](Self::item_kind),
+    /// with the fields that are recorded in it later
+    fn item_span(self, item: &str) -> Span {
+        match self {
+            Self::Load => info_span!("definition", label = %item),
+            Self::Slides => info_span!("deck", label = %item, path = Empty, bytes = Empty),
+            Self::Images => info_span!("image", label = %item),
+            Self::Book => info_span!("page", label = %item, path = Empty, bytes = Empty),
+            Self::Exercises => info_span!("exercise", label = %item),
+            Self::Archive => info_span!("archive", label = %item),
+        }
+    }
+
+    /// What the items of the phase are, like `deck` for [`Phase::Slides`]
+    pub fn item_kind(self) -> &'static str {
+        match self {
+            Self::Load => "definition",
+            Self::Slides => "deck",
+            Self::Images => "image",
+            Self::Book => "page",
+            Self::Exercises => "exercise",
+            Self::Archive => "archive",
+        }
+    }
+}
+
+/// Gets told about the progress of a run. All methods do nothing by default.
+pub trait ProgressReporter: Send + Sync {
+    /// A phase starts, with `total` items to go through if that's known
+    fn start(&self, _phase: Phase, _total: Option<usize>) {}
+
+    /// The phase got to its next item, like a deck or definition file
+    fn advance(&self, _phase: Phase, _item: &str) {}
+
+    fn finish(&self, _phase: Phase) {}
+
+    /// A phase, or one of its items, finished in `timing.duration`
+    fn timing(&self, _timing: &Timing) {}
+
+    /// Runs `write`, which writes a log message. Reporters that draw on stderr can clear
+    /// their output first and redraw it afterwards, so that the two don't garble each other.
+    fn suspend(&self, write: &mut dyn FnMut()) {
+        write()
+    }
+}
+
+/// How long a phase, or one of its items like a deck or exercise package, took
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub phase: Phase,
+    /// The item, or `None` for the phase as a whole
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    /// Wall-clock time, written as fractional milliseconds
+    #[serde(
+        rename = "ms",
+        serialize_with = "to_millis",
+        deserialize_with = "from_millis"
+    )]
+    pub duration: Duration,
+}
+
+fn to_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+fn from_millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let millis = f64::deserialize(deserializer)?;
+    Ok(Duration::from_secs_f64(millis.max(0.0) / 1000.0))
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.duration.as_secs_f64() * 1000.0;
+        match &self.item {
+            Some(item) => write!(f, "{millis:>8.1} ms  {} {item}", self.phase.item_kind()),
+            None => write!(f, "{millis:>8.1} ms  {}", self.phase),
+        }
+    }
+}
+
+/// Starts a phase, which finishes when the returned guard is dropped, also on errors. The
+/// phase is a span of the log as well.
+pub(crate) fn start(phase: Phase, total: Option<usize>) -> PhaseGuard {
+    if let Some(reporter) = &context::current().progress {
+        reporter.start(phase, total);
+    }
+    PhaseGuard(phase, Instant::now(), phase.span().entered())
+}
+
+pub(crate) struct PhaseGuard(Phase, Instant, #[allow(dead_code)] EnteredSpan);
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        if let Some(reporter) = &context::current().progress {
+            reporter.finish(self.0);
+        }
+        record(Timing {
+            phase: self.0,
+            item: None,
+            duration: self.1.elapsed(),
+        });
+    }
+}
+
+/// Times an item of `phase`, like a deck or exercise package, until the returned guard
+/// is dropped. The item is a span of the log as well, named after the kind of item.
+pub(crate) fn time(phase: Phase, item: &str) -> ItemGuard {
+    let span = phase.item_span(item).entered();
+    ItemGuard(phase, item.to_string(), Instant::now(), span)
+}
+
+pub(crate) struct ItemGuard(Phase, String, Instant, EnteredSpan);
+
+impl ItemGuard {
+    /// Records a field of the span of the item, like the path it was written to. The span
+    /// has to have the field, see [`Phase::item_span`].
+    pub(crate) fn record(&self, key: &'static str, value: impl fmt::Display) {
+        self.3.record(key, tracing::field::display(value));
+    }
+}
+
+impl Drop for ItemGuard {
+    fn drop(&mut self) {
+        record(Timing {
+            phase: self.0,
+            item: Some(std::mem::take(&mut self.1)),
+            duration: self.2.elapsed(),
+        });
+    }
+}
+
+/// Reports `timing`, and keeps it for the render report of the current context. It is
+/// logged at the debug level, in the span of its phase or item if that is still entered.
+pub(crate) fn record(timing: Timing) {
+    let millis = timing.duration.as_secs_f64() * 1000.0;
+    match &timing.item {
+        Some(item) => tracing::debug!(
+            duration_ms = format_args!("{millis:.1}"),
+            "Finished {} {item} in {millis:.1} ms",
+            timing.phase.item_kind()
+        ),
+        None => tracing::debug!(
+            duration_ms = format_args!("{millis:.1}"),
+            "Finished {} in {millis:.1} ms",
+            timing.phase.name()
+        ),
+    }
+    let context = context::current();
+    if let Some(reporter) = &context.progress {
+        reporter.timing(&timing);
+    }
+    context.timings.lock().unwrap().push(timing);
+}
+
+/// The timings that were reported in the current context so far, in the order they
+/// finished in
+pub(crate) fn take_timings() -> Vec<Timing> {
+    std::mem::take(&mut context::current().timings.lock().unwrap())
+}
+
+pub(crate) fn advance(phase: Phase, item: &str) {
+    if let Some(reporter) = &context::current().progress {
+        reporter.advance(phase, item);
+    }
+}
+
+pub(crate) fn suspend(mut write: impl FnMut()) {
+    match &context::current().progress {
+        Some(reporter) => reporter.suspend(&mut write),
+        None => write(),
+    }
+}
+
+/// Draws a line on stderr with the progress of the current phase, like
+/// `Rendering slides [=====>    ] 12/38 Ownership`, or a count if the total isn't known
+#[derive(Default)]
+pub struct ProgressBar {
+    current: Mutex<Option<Progress>>,
+}
+
+struct Progress {
+    phase: Phase,
+    done: usize,
+    total: Option<usize>,
+    item: String,
+}
+
+const BAR_WIDTH: usize = 24;
+/// Long items are cut off, so that the line fits in most terminals
+const MAX_ITEM_WIDTH: usize = 32;
+
+impl ProgressBar {
+    fn draw(progress: &Progress) {
+        let Progress {
+            phase,
+            done,
+            total,
+            item,
+        } = progress;
+        // Keep the end, which is what tells paths apart
+        let skipped = item.chars().count().saturating_sub(MAX_ITEM_WIDTH);
+        let item = match skipped {
+            0 => item.to_string(),
+            _ => format!("…{}", item.chars().skip(skipped + 1).collect::<String>()),
+        };
+        let line = match total {
+            Some(total) => {
+                let filled = (done * BAR_WIDTH).checked_div(*total).unwrap_or(BAR_WIDTH);
+                let bar = format!("{:=<filled$}>", "");
+                format!(
+                    "{phase} [{bar:<width$}] {done}/{total} {item}",
+                    width = BAR_WIDTH + 1
+                )
+            }
+            None => format!("{phase}... {done} {item}"),
+        };
+        let _ = write!(io::stderr().lock(), "\r\x1b[2K{line}");
+    }
+
+    fn clear() {
+        let _ = write!(io::stderr().lock(), "\r\x1b[2K");
+    }
+}
+
+impl ProgressReporter for ProgressBar {
+    fn start(&self, phase: Phase, total: Option<usize>) {
+        let progress = Progress {
+            phase,
+            done: 0,
+            total,
+            item: String::new(),
+        };
+        Self::draw(&progress);
+        *self.current.lock().unwrap() = Some(progress);
+    }
+
+    fn advance(&self, phase: Phase, item: &str) {
+        let mut current = self.current.lock().unwrap();
+        let Some(progress) = current.as_mut().filter(|p| p.phase == phase) else {
+            return;
+        };
+        progress.done += 1;
+        progress.item = item.to_string();
+        Self::draw(progress);
+    }
+
+    fn finish(&self, phase: Phase) {
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_some_and(|p| p.phase == phase) {
+            *current = None;
+            Self::clear();
+        }
+    }
+
+    fn suspend(&self, write: &mut dyn FnMut()) {
+        let current = self.current.lock().unwrap();
+        if current.is_some() {
+            Self::clear();
+        }
+        write();
+        if let Some(progress) = current.as_ref() {
+            Self::draw(progress);
+        }
+    }
+}
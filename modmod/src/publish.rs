@@ -0,0 +1,351 @@
+//! Publishing the built slides, book and exercises of a track to a git branch, like
+//! `gh-pages`, from which they are deployed.
+
+use std::{
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use git2::{Index, IndexEntry, IndexTime, Oid, PushOptions, Repository, StatusOptions};
+
+use crate::{
+    archive::is_executable,
+    git::{self, failed},
+    io::PathExt,
+    manifest::{ArtifactKind, RenderManifest, RENDER_MANIFEST_FILE},
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct PublishError {}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to publish output")
+    }
+}
+
+impl error_stack::Context for PublishError {}
+
+pub struct PublishOptions<'a> {
+    /// The output folder of a single track
+    pub out_dir: &'a Path,
+    /// The repository the branch is committed to, and pushed from
+    pub repo_dir: &'a Path,
+    pub branch: &'a str,
+    pub remote: &'a str,
+    /// Run the build scripts of the slides and `mdbook build` first
+    pub build: bool,
+    /// Publish even if the repository has uncommitted changes
+    pub force: bool,
+}
+
+/// What [`publish`] committed, or would commit on a dry run
+#[derive(Debug)]
+pub struct PublishReport {
+    pub branch: String,
+    pub remote: String,
+    /// The commit that was pushed, unless it was a dry run
+    pub commit: Option<String>,
+    /// The published files, relative to the root of the branch
+    pub files: Vec<String>,
+}
+
+impl fmt::Display for PublishReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            branch,
+            remote,
+            commit,
+            files,
+        } = self;
+        match commit {
+            Some(commit) => write!(
+                f,
+                "Pushed {} file(s) to branch {branch} of {remote} in commit {commit}",
+                files.len()
+            ),
+            None => {
+                write!(
+                    f,
+                    "Would commit {} file(s) to branch {branch} of {remote}:",
+                    files.len()
+                )?;
+                for file in files {
+                    write!(f, "\n  {file}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Commits the built output in `out_dir` to a branch, on top of what it has on the remote,
+/// and pushes it. The branch gets the site layout the slides are built for:
+///
+/// - `slides/<prefix>/`: the build of each deck, like `slides/2_1/`
+/// - `book/`: the built book
+/// - `exercises/`: the exercise packages as they were rendered
+///
+/// The working tree and index of the repository are left alone.
+pub fn publish(options: PublishOptions) -> Result<PublishReport, PublishError> {
+    let PublishOptions {
+        out_dir,
+        repo_dir,
+        branch,
+        remote,
+        build,
+        force,
+    } = options;
+    // On dry runs, only what would be committed is collected
+    let dry_run = crate::io::writes_discarded();
+    let repo = git::open::<PublishError>(repo_dir)?;
+    let uncommitted_changes = repo
+        .statuses(Some(
+            StatusOptions::new()
+                .include_untracked(false)
+                .include_ignored(false),
+        ))
+        .map_err(failed::<PublishError>(
+            "reading the status of the repository",
+        ))?
+        .iter()
+        .next()
+        .is_some();
+    if uncommitted_changes && !force {
+        return Err(Report::new(PublishError::default()).attach_printable(format!(
+            "The repository at {} has uncommitted changes, so the published output may not match any commit. Commit them, or use --force",
+            repo_dir.display()
+        )));
+    }
+
+    let manifest_path = out_dir.join(RENDER_MANIFEST_FILE);
+    if !manifest_path.exists() {
+        return Err(
+            Report::new(PublishError::default()).attach_printable(format!(
+                "{} has no {RENDER_MANIFEST_FILE}, so it isn't the output folder of a track",
+                out_dir.display()
+            )),
+        );
+    }
+    let manifest: RenderManifest = serde_json::from_str(&manifest_path.read_to_string()?)
+        .into_report()
+        .attach_printable_lazy(|| format!("Invalid manifest at {}", manifest_path.display()))
+        .change_context(PublishError::default())?;
+    // Decks are written to the slides package, and chapters to the `src` folder of the book
+    let decks: Vec<_> = manifest
+        .main_artifacts()
+        .filter(|a| a.kind == ArtifactKind::Deck)
+        .map(|a| out_dir.join(&a.path))
+        .collect();
+    let slides_dir = decks.first().and_then(|deck| deck.parent());
+    let book_dir = manifest
+        .main_artifacts()
+        .find(|a| a.kind == ArtifactKind::Chapter)
+        .and_then(|a| Some(out_dir.join(Path::new(&a.path).parent()?.parent()?)));
+    let exercises_dir = manifest
+        .main_artifacts()
+        .find(|a| a.kind == ArtifactKind::Exercise)
+        .and_then(|a| Some(out_dir.join(a.path.split('/').next()?)));
+
+    if build {
+        if let Some(slides_dir) = slides_dir {
+            run::<PublishError>(slides_dir, "npm", &["install"], dry_run)?;
+            for deck in decks.iter() {
+                let script = format!("build-{}", prefix(deck)?);
+                run::<PublishError>(slides_dir, "npm", &["run", &script], dry_run)?;
+            }
+        }
+        if let Some(book_dir) = &book_dir {
+            run::<PublishError>(book_dir, "mdbook", &["build"], dry_run)?;
+        }
+    }
+    // What isn't built yet on a dry run would be built by the build steps
+    let check_built = |path: &Path| match path.exists() || (dry_run && build) {
+        true => Ok(path.exists()),
+        false => Err(not_built(path)),
+    };
+
+    // The files to publish, with their path on the branch
+    let mut files: Vec<(PathBuf, String)> = vec![];
+    let mut add_dir = |dir: &Path, to: &str| -> Result<(), PublishError> {
+        for path in dir.get_dir_content::<PublishError>()?.files {
+            let path = PathBuf::from(path);
+            let relative = path
+                .strip_prefix(dir)
+                .into_report()
+                .change_context(PublishError::default())?;
+            let relative = crate::manifest::relative_path(relative);
+            files.push((path, format!("{to}/{relative}")));
+        }
+        Ok(())
+    };
+    if let Some(slides_dir) = slides_dir {
+        let dist_dir = slides_dir.join("dist");
+        for deck in decks.iter() {
+            let built = dist_dir.join(file_stem(deck)?);
+            if check_built(&built)? {
+                add_dir(&built, &format!("slides/{}", prefix(deck)?))?;
+            }
+        }
+    }
+    if let Some(book_dir) = &book_dir {
+        let built = book_dir.join("book");
+        if check_built(&built)? {
+            add_dir(&built, "book")?;
+        }
+    }
+    if let Some(exercises_dir) = &exercises_dir {
+        add_dir(exercises_dir, "exercises")?;
+    }
+    files.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    let mut report = PublishReport {
+        branch: branch.to_string(),
+        remote: remote.to_string(),
+        commit: None,
+        files: files.iter().map(|(_, to)| to.clone()).collect(),
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    let commit = commit(&repo, &files, branch, remote, uncommitted_changes)?;
+    report.commit = Some(commit.to_string());
+    Ok(report)
+}
+
+/// Commits the files, with their path on the branch, to `branch`, on top of the branch on
+/// the remote if it's there, and pushes it. Returns the commit.
+///
+/// The files are put together through an index of their own, so that neither the working
+/// tree nor the index of the repository is touched.
+fn commit(
+    repo: &Repository,
+    files: &[(PathBuf, String)],
+    branch: &str,
+    remote: &str,
+    uncommitted_changes: bool,
+) -> Result<Oid, PublishError> {
+    let mut index = Index::new().map_err(failed::<PublishError>("creating an index"))?;
+    for (from, to) in files {
+        let entry = IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: match is_executable(from) {
+                true => 0o100755,
+                false => 0o100644,
+            },
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: repo
+                .blob_path(from)
+                .map_err(failed::<PublishError>("adding the files"))
+                .attach_printable_lazy(|| format!("Adding {}", from.display()))?,
+            flags: 0,
+            flags_extended: 0,
+            path: to.as_bytes().to_vec(),
+        };
+        index
+            .add(&entry)
+            .map_err(failed::<PublishError>("adding the files"))?;
+    }
+    let tree = index
+        .write_tree_to(repo)
+        .and_then(|tree| repo.find_tree(tree))
+        .map_err(failed::<PublishError>("writing the tree"))?;
+
+    // A remote can be given by its name or its URL
+    let mut remote = repo
+        .find_remote(remote)
+        .or_else(|_| repo.remote_anonymous(remote))
+        .map_err(failed::<PublishError>("finding the remote"))?;
+    // A branch that doesn't exist yet on the remote starts out without history
+    let branch_ref = format!("refs/heads/{branch}");
+    let parent = git::fetch::<PublishError>(repo, &mut remote, &branch_ref, false)?
+        .map(|tip| repo.find_commit(tip))
+        .transpose()
+        .map_err(failed::<PublishError>("finding the branch"))?;
+    let source = git::head::<PublishError>(repo)?
+        .as_object()
+        .short_id()
+        .map_err(failed::<PublishError>("abbreviating the commit of HEAD"))?;
+    let mut message = format!("Publish output of {}", source.as_str().unwrap_or_default());
+    if uncommitted_changes {
+        message.push_str(" with uncommitted changes");
+    }
+    let parents: Vec<_> = parent.iter().collect();
+    let commit = repo
+        .signature()
+        .and_then(|author| repo.commit(None, &author, &author, &message, &tree, &parents))
+        .map_err(failed::<PublishError>("committing"))?;
+
+    // Pushed as is, so that a local branch with the same name is left alone
+    let mut options = PushOptions::new();
+    options.remote_callbacks(git::callbacks());
+    remote
+        .push(&[format!("{commit}:{branch_ref}")], Some(&mut options))
+        .map_err(failed::<PublishError>("pushing"))?;
+    Ok(commit)
+}
+
+/// The prefix of a deck, like `2_1` for `2_1-basic-syntax.md`, by which its build
+/// script is named
+fn prefix(deck: &Path) -> Result<String, PublishError> {
+    let stem = file_stem(deck)?.to_string_lossy();
+    Ok(stem
+        .split_once('-')
+        .map_or(&*stem, |(prefix, _)| prefix)
+        .to_string())
+}
+
+fn file_stem(deck: &Path) -> Result<&OsStr, PublishError> {
+    deck.file_stem().ok_or_else(|| {
+        Report::new(PublishError::default()).attach_printable(format!(
+            "The manifest lists a deck without a name: {}",
+            deck.display()
+        ))
+    })
+}
+
+fn not_built(path: &Path) -> Report<PublishError> {
+    Report::new(PublishError::default()).attach_printable(format!(
+        "{} hasn't been built, use --build to build it first",
+        path.display()
+    ))
+}
+
+/// Runs a build step, or only records it on a dry run, see
+/// [`PlannedWrite::Run`](crate::io::PlannedWrite::Run)
+pub(crate) fn run<C: Context + Default>(
+    dir: &Path,
+    program: &str,
+    args: &[&str],
+    dry_run: bool,
+) -> Result<(), C> {
+    if dry_run {
+        crate::io::record_run(format!("{program} {}", args.join(" ")), dir);
+        return Ok(());
+    }
+    let status = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .into_report()
+        .attach_printable_lazy(|| format!("Unable to run {program}"))
+        .change_context(C::default())?;
+    if !status.success() {
+        return Err(Report::new(C::default()).attach_printable(format!(
+            "`{program} {}` in {} failed: {status}",
+            args.join(" "),
+            dir.display()
+        )));
+    }
+    Ok(())
+}
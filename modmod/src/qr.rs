@@ -0,0 +1,293 @@
+//! QR codes on the slides that link to what goes with a deck, written by
+//! `generate --qr-code <target>` into [`QR_DIR`] of the images of the slides, and shown on a
+//! slide of the deck with the `#[modmod:qr_codes]` placeholder.
+//!
+//! A code of the `exercises` target links to the chapter of the unit in the book, and one of
+//! the `feedback` target to the URL in the [`FEEDBACK_VARIABLE`] variable of the track, in
+//! which `{unit}` is replaced by the id of the unit. The URLs are made from the base URL of
+//! the render like the other URLs of the output, and need it to have an origin to be of use
+//! on a phone. A deck only gets a code for what is rendered with it: without the book, or
+//! for a unit whose chapter is left out, there is no code of its exercises.
+//!
+//! Codes are encoded by the `qrcode` crate at error correction level M, in the smallest
+//! version that fits, and written as SVG with a quiet zone of four modules.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use qrcode::{Color, EcLevel};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cartridge::escape,
+    report::{Warning, WarningCode},
+    scaffold::GENERATED,
+    to_prefixed_tag, to_tag,
+    url::BaseUrl,
+    ContentFilter, OutputLayout, Track,
+};
+
+/// The folder of the images of the slides the codes are written into, as
+/// `<deck>-<target>.svg`
+pub const QR_DIR: &str = "qr";
+
+/// The variable of the track with the URL of the feedback form of a unit
+pub const FEEDBACK_VARIABLE: &str = "feedback_url";
+
+/// What a QR code on the slides of a unit links to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrTarget {
+    /// The chapter of the unit in the book, which describes its exercises
+    Exercises,
+    /// The feedback form of the unit, set in [`FEEDBACK_VARIABLE`]
+    Feedback,
+}
+
+impl QrTarget {
+    pub const ALL: [Self; 2] = [Self::Exercises, Self::Feedback];
+
+    /// The caption of the code on the slide
+    pub(crate) fn caption(&self) -> &'static str {
+        match self {
+            Self::Exercises => "Exercises",
+            Self::Feedback => "Feedback",
+        }
+    }
+}
+
+impl fmt::Display for QrTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Exercises => "exercises",
+            Self::Feedback => "feedback",
+        })
+    }
+}
+
+impl std::str::FromStr for QrTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|target| target.to_string() == s)
+            .ok_or_else(|| format!("unknown QR code target '{s}', expected exercises or feedback"))
+    }
+}
+
+/// A QR code of a deck
+#[derive(Debug, Clone)]
+pub(crate) struct QrCode {
+    pub(crate) target: QrTarget,
+    pub(crate) url: String,
+    pub(crate) svg: String,
+}
+
+impl QrCode {
+    /// The name of the image of the code of the deck with `prefix`, in [`QR_DIR`]
+    pub(crate) fn file_name(&self, prefix: &str) -> String {
+        format!("{prefix}-{}.svg", self.target)
+    }
+}
+
+impl Track {
+    /// The QR codes of the `targets` for each deck, by the prefix of the deck. The chapters
+    /// of the book are looked for in `out_dir`, so the book has to be rendered first, and
+    /// the `exercises` target left out of `targets` when it isn't rendered with the decks.
+    pub(crate) fn qr_codes(
+        &self,
+        out_dir: &Path,
+        layout: &OutputLayout,
+        base_url: &BaseUrl,
+        targets: &[QrTarget],
+        filter: ContentFilter,
+        warnings: &mut Vec<Warning>,
+    ) -> HashMap<String, Vec<QrCode>> {
+        let mut codes = HashMap::new();
+        if targets.is_empty() {
+            return codes;
+        }
+        let feedback = match self.variables.get(FEEDBACK_VARIABLE) {
+            None if targets.contains(&QrTarget::Feedback) => {
+                warnings.push(Warning::new(
+                    WarningCode::QrCodeSkipped,
+                    format!(
+                        "The decks have no QR code of their feedback form, as the track doesn't set the variable {FEEDBACK_VARIABLE}"
+                    ),
+                    None,
+                ));
+                None
+            }
+            url => url.filter(|_| targets.contains(&QrTarget::Feedback)),
+        };
+        let mut without_origin = vec![];
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let units = module
+                .data
+                .units
+                .iter()
+                .filter(|u| u.data.render_slides && filter.includes_unit(&u.data));
+            for unit in units {
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let mut deck_codes = vec![];
+                let page = to_tag(&unit.data.name);
+                let chapter = layout.book().join("src").join(&page).with_extension("md");
+                let has_exercises = unit
+                    .data
+                    .topics
+                    .iter()
+                    .map(|t| &t.data)
+                    .filter(|t| filter.includes(t.optional, &t.profiles))
+                    .flat_map(|t| t.exercises.iter().map(|e| &e.data))
+                    .any(|e| filter.includes(e.optional, &e.profiles));
+                // A chapter that isn't rendered with the deck would be a dead link
+                if targets.contains(&QrTarget::Exercises)
+                    && unit.data.render_book
+                    && has_exercises
+                    && out_dir.join(&chapter).exists()
+                {
+                    let path = OutputLayout::url_path(&layout.book().join(&page)) + ".html";
+                    deck_codes.push((QrTarget::Exercises, path));
+                }
+                if let Some(url) = feedback {
+                    deck_codes.push((QrTarget::Feedback, url.replace("{unit}", &unit.data.id())));
+                }
+                for (target, url) in deck_codes {
+                    let url = match url.starts_with("https://") || url.starts_with("http://") {
+                        true => url,
+                        false if base_url.origin().is_some() => base_url.join(&url),
+                        false => {
+                            if !without_origin.contains(&target) {
+                                without_origin.push(target);
+                            }
+                            continue;
+                        }
+                    };
+                    match svg(url.as_bytes()) {
+                        Some(svg) => codes
+                            .entry(prefix.clone())
+                            .or_insert_with(Vec::new)
+                            .push(QrCode { target, url, svg }),
+                        None => warnings.push(Warning::new(
+                            WarningCode::QrCodeSkipped,
+                            format!(
+                                "Deck {} has no QR code of its {target}, as its URL is too long for a QR code: {url}",
+                                to_prefixed_tag(&unit.data.name, &prefix)
+                            ),
+                            None,
+                        )),
+                    }
+                }
+            }
+        }
+        for target in without_origin {
+            warnings.push(Warning::new(
+                WarningCode::QrCodeSkipped,
+                format!(
+                    "The decks have no QR code of their {target}, as the base URL {} has no origin, like https://example.com, that a phone can open the URL with",
+                    base_url.path()
+                ),
+                None,
+            ));
+        }
+        codes
+    }
+}
+
+/// The QR code of `data` as an SVG image, or `None` if it's too long to fit in one
+pub(crate) fn svg(data: &[u8]) -> Option<String> {
+    let code = qrcode::QrCode::with_error_correction_level(data, EcLevel::M).ok()?;
+    let width = code.width();
+    let size = width + 2 * QUIET_ZONE;
+    let mut path = String::new();
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (x, y) = (i % width + QUIET_ZONE, i / width + QUIET_ZONE);
+            path.push_str(&format!("M{x},{y}h1v1h-1z"));
+        }
+    }
+    Some(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">
+<!-- {GENERATED} -->
+<title>{}</title>
+<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>
+<path fill=\"#000\" d=\"{path}\"/>
+</svg>
+",
+        escape(&String::from_utf8_lossy(data))
+    ))
+}
+
+/// The light modules around a code, which readers need to find it
+const QUIET_ZONE: usize = 4;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a reader makes of the code in `svg`, with each module drawn as 4 by 4 pixels
+    fn decode(svg: &str) -> String {
+        const SCALE: usize = 4;
+        let size: usize = svg
+            .split_once("viewBox=\"0 0 ")
+            .and_then(|(_, rest)| rest.split_once(' '))
+            .map(|(size, _)| size.parse().unwrap())
+            .unwrap();
+        let (_, path) = svg.split_once(" d=\"").unwrap();
+        let (path, _) = path.split_once('"').unwrap();
+        let mut dark = vec![false; size * size];
+        for module in path.split('M').skip(1) {
+            let (x, rest) = module.split_once(',').unwrap();
+            let (y, _) = rest.split_once('h').unwrap();
+            let (x, y): (usize, usize) = (x.parse().unwrap(), y.parse().unwrap());
+            dark[y * size + x] = true;
+        }
+        let pixels = size * SCALE;
+        let mut image = rqrr::PreparedImage::prepare_from_greyscale(pixels, pixels, |x, y| {
+            match dark[y / SCALE * size + x / SCALE] {
+                true => 0,
+                false => 255,
+            }
+        });
+        let grids = image.detect_grids();
+        assert_eq!(grids.len(), 1);
+        let (_, content) = grids[0].decode().unwrap();
+        content
+    }
+
+    #[test]
+    fn codes_read_back_as_their_url() {
+        for url in [
+            "https://example.com/book/first-steps.html",
+            "HTTPS://EXAMPLE.COM/1234",
+            "https://example.com/feedback?unit=1_1&lang=nl#form",
+            &format!("https://example.com/{}", "long-path/".repeat(40)),
+        ] {
+            let svg = svg(url.as_bytes()).unwrap();
+            assert_eq!(decode(&svg), url);
+        }
+    }
+
+    #[test]
+    fn codes_have_a_quiet_zone_and_their_url_as_title() {
+        // Too long for the 14 bytes of version 1, so the 25 modules of version 2, and 4 on
+        // each side
+        let svg = svg(b"https://a.nl/?a&b").unwrap();
+        assert!(svg.contains("viewBox=\"0 0 33 33\""), "{svg}");
+        assert!(
+            svg.contains("<title>https://a.nl/?a&amp;b</title>"),
+            "{svg}"
+        );
+        // The finder pattern in the top left corner starts right after the quiet zone
+        assert!(svg.contains(" d=\"M4,4h1v1h-1zM5,4h1v1h-1z"), "{svg}");
+        assert!(!svg.contains("M3,"), "{svg}");
+    }
+
+    #[test]
+    fn data_that_doesnt_fit_has_no_code() {
+        // Version 40 at level M holds 2331 bytes
+        assert!(svg(&[b'a'; 2331]).is_some());
+        assert!(svg(&[0xff; 2332]).is_none());
+    }
+}
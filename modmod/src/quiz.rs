@@ -0,0 +1,239 @@
+//! Quizzes at the end of book chapters, for students to check what they took away from a
+//! unit.
+//!
+//! Topics define their questions as `[[quiz]]` tables, with a prompt, the correct answers,
+//! and for multiple choice questions the wrong answers to pick from as `distractors`. A
+//! question without distractors is answered by typing one of its answers. The questions are
+//! checked when the track is loaded, so a broken quiz fails the render rather than the book
+//! in the browser of a student.
+//!
+//! The quiz of a chapter has the questions of all its topics. With [`QuizMode::Static`],
+//! it's written into the chapter as a list of questions with their answers folded away.
+//! With [`QuizMode::MdbookQuiz`], the questions are written in the format of
+//! [mdbook-quiz](https://github.com/cognitive-engineering-lab/mdbook-quiz) into
+//! [`QUIZZES_DIR`] next to the chapters, which are answered in the browser with immediate
+//! feedback, and the book is set up to be built with its preprocessor.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The folder the quizzes are written into for mdbook-quiz, relative to the book sources
+pub const QUIZZES_DIR: &str = "quizzes";
+
+/// A question of the quiz of a topic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuizQuestion {
+    /// The question, in Markdown
+    pub prompt: String,
+    /// The correct answers. Multiple choice questions with more than one have the student
+    /// select all of them, others accept any of them.
+    pub answers: Vec<String>,
+    /// The wrong answers of a multiple choice question
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub distractors: Vec<String>,
+    /// Why the answers are correct, shown after answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+impl QuizQuestion {
+    pub fn is_multiple_choice(&self) -> bool {
+        !self.distractors.is_empty()
+    }
+}
+
+/// How quizzes are rendered into the book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuizMode {
+    /// As part of the chapter, with the answers folded away
+    #[default]
+    Static,
+    /// Answered in the browser, with the mdbook-quiz preprocessor
+    MdbookQuiz,
+}
+
+impl QuizMode {
+    pub const ALL: [Self; 2] = [Self::Static, Self::MdbookQuiz];
+}
+
+impl fmt::Display for QuizMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Static => "static",
+            Self::MdbookQuiz => "mdbook-quiz",
+        })
+    }
+}
+
+impl std::str::FromStr for QuizMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s)
+            .ok_or_else(|| format!("unknown quiz mode '{s}', expected static or mdbook-quiz"))
+    }
+}
+
+/// The problems with the questions of a quiz, each naming the question by its number
+pub(crate) fn problems(questions: &[QuizQuestion]) -> Vec<String> {
+    let mut problems = vec![];
+    for (question, number) in questions.iter().zip(1..) {
+        let mut problem = |text: String| problems.push(format!("Question {number}: {text}"));
+        if question.prompt.trim().is_empty() {
+            problem("the prompt is empty".to_string());
+        }
+        if question.answers.is_empty() {
+            problem("there are no answers".to_string());
+        }
+        let choices = question.answers.iter().chain(question.distractors.iter());
+        let mut seen: Vec<&str> = vec![];
+        for choice in choices {
+            let choice = choice.trim();
+            if choice.is_empty() {
+                problem("an answer or distractor is empty".to_string());
+            } else if seen.contains(&choice) {
+                match question.answers.iter().any(|a| a.trim() == choice)
+                    && question.distractors.iter().any(|d| d.trim() == choice)
+                {
+                    true => problem(format!("'{choice}' is both an answer and a distractor")),
+                    false => problem(format!("'{choice}' is given more than once")),
+                }
+            }
+            seen.push(choice);
+        }
+        if question
+            .explanation
+            .as_ref()
+            .is_some_and(|e| e.trim().is_empty())
+        {
+            problem("the explanation is empty".to_string());
+        }
+    }
+    problems
+}
+
+/// The quiz as a section of a chapter, with the answers folded away
+pub(crate) fn static_markdown(questions: &[&QuizQuestion]) -> String {
+    let mut markdown = String::from("## Quiz\n\n");
+    for (question, number) in questions.iter().zip(1..) {
+        markdown.push_str(&format!("**{number}.** {}\n\n", question.prompt.trim()));
+        if question.is_multiple_choice() {
+            // In a fixed order that doesn't give away which are correct
+            let mut choices: Vec<_> = question
+                .answers
+                .iter()
+                .chain(question.distractors.iter())
+                .map(|choice| choice.trim())
+                .collect();
+            choices.sort_unstable();
+            for choice in choices {
+                markdown.push_str(&format!("- {choice}\n"));
+            }
+            markdown.push('\n');
+        }
+        let answers: Vec<_> = question.answers.iter().map(|a| a.trim()).collect();
+        markdown.push_str("<details>\n<summary>Answer</summary>\n\n");
+        match (question.is_multiple_choice(), answers.split_first()) {
+            (false, Some((answer, others))) if !others.is_empty() => {
+                markdown.push_str(&format!("{answer} (or {})\n", others.join(", ")));
+            }
+            _ => markdown.push_str(&format!("{}\n", answers.join(", "))),
+        }
+        if let Some(explanation) = &question.explanation {
+            markdown.push_str(&format!("\n{}\n", explanation.trim()));
+        }
+        markdown.push_str("\n</details>\n\n");
+    }
+    markdown
+}
+
+/// The quiz as a section of a chapter that mdbook-quiz renders from the quiz at `path`,
+/// relative to the chapter
+pub(crate) fn mdbook_quiz_markdown(path: &str) -> String {
+    format!("## Quiz\n\n{{{{#quiz {path}}}}}\n\n")
+}
+
+#[derive(Serialize)]
+struct QuizFile<'a> {
+    questions: Vec<ToolQuestion<'a>>,
+}
+
+#[derive(Serialize)]
+struct ToolQuestion<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    prompt: ToolPrompt<'a>,
+    answer: ToolAnswer<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ToolPrompt<'a> {
+    prompt: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    distractors: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ToolAnswer<'a> {
+    answer: AnswerValue<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum AnswerValue<'a> {
+    One(&'a str),
+    All(Vec<&'a str>),
+}
+
+/// The quiz in the TOML format of mdbook-quiz
+pub(crate) fn mdbook_quiz_toml(questions: &[&QuizQuestion]) -> Result<String, toml::ser::Error> {
+    let file = QuizFile {
+        questions: questions
+            .iter()
+            .map(|question| {
+                let answers: Vec<_> = question.answers.iter().map(|a| a.trim()).collect();
+                let (kind, answer) = match (question.is_multiple_choice(), answers.as_slice()) {
+                    (true, [answer]) => (
+                        "MultipleChoice",
+                        ToolAnswer {
+                            answer: AnswerValue::One(answer),
+                            alternatives: vec![],
+                        },
+                    ),
+                    (true, _) => (
+                        "MultipleChoice",
+                        ToolAnswer {
+                            answer: AnswerValue::All(answers),
+                            alternatives: vec![],
+                        },
+                    ),
+                    (false, answers) => (
+                        "ShortAnswer",
+                        ToolAnswer {
+                            answer: AnswerValue::One(answers.first().copied().unwrap_or_default()),
+                            alternatives: answers.iter().skip(1).copied().collect(),
+                        },
+                    ),
+                };
+                ToolQuestion {
+                    kind,
+                    prompt: ToolPrompt {
+                        prompt: question.prompt.trim(),
+                        distractors: question.distractors.iter().map(|d| d.trim()).collect(),
+                    },
+                    answer,
+                    context: question.explanation.as_deref().map(str::trim),
+                }
+            })
+            .collect(),
+    };
+    toml::to_string(&file)
+}
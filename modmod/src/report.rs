@@ -0,0 +1,630 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    manifest::ArtifactKind, progress::Timing, update::UpdateReport, version::CourseVersion,
+    GitSource, RenderTarget,
+};
+
+/// Summary of what was generated when rendering a track
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RenderReport {
+    /// Name of the rendered track
+    pub track: String,
+    /// Directory the track was rendered into
+    pub out_dir: PathBuf,
+    /// The parts of the output that were rendered
+    pub targets: Vec<RenderTarget>,
+    /// The version of the course the track was rendered as
+    pub course_version: Option<CourseVersion>,
+    /// Number of slide decks that were written
+    pub decks: usize,
+    /// Number of units whose slide deck was skipped by setting `render_slides = false`
+    pub skipped_decks: usize,
+    /// Number of unit pages in the exercise book
+    pub book_sections: usize,
+    /// Number of units whose book page was skipped by setting `render_book = false`
+    pub skipped_book_sections: usize,
+    /// Number of exercise packages that were copied
+    pub exercises: usize,
+    /// Names of the exercises that were checked out from git repositories, with their source
+    pub git_exercises: Vec<(String, GitSource)>,
+    /// Names of the rendered topics whose summary was derived from their headings
+    pub derived_summaries: Vec<String>,
+    /// Number of files that were written or copied
+    pub written_files: usize,
+    /// Number of files that were left alone, as the file there had the same content
+    pub unchanged_files: usize,
+    /// Number of files that were kept from the render cache without rendering them, as what
+    /// they are rendered from didn't change
+    pub cached_files: usize,
+    /// Bytes of the assets that were copied for each slide deck and exercise package, by
+    /// its path in the output folder: the images of a deck, and the files of a package
+    pub asset_bytes: Vec<(String, u64)>,
+    /// Problems that were encountered while rendering, but didn't stop it
+    pub warnings: Vec<Warning>,
+    /// How long loading the track, each render phase and each artifact took
+    pub timings: Vec<Timing>,
+    /// The files of the output folder that were changed by hand since the last render, and
+    /// were kept, if the render cleared a folder that had any
+    pub local_edits: Option<UpdateReport>,
+}
+
+impl RenderReport {
+    pub fn new(track: &str, out_dir: &Path) -> Self {
+        Self {
+            track: track.to_string(),
+            out_dir: out_dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// The `n` artifacts, like decks and exercise packages, that took longest to render
+    pub fn slowest(&self, n: usize) -> Vec<&Timing> {
+        let mut artifacts: Vec<_> = self.timings.iter().filter(|t| t.item.is_some()).collect();
+        artifacts.sort_by_key(|t| std::cmp::Reverse(t.duration));
+        artifacts.truncate(n);
+        artifacts
+    }
+}
+
+/// Gets told about the outcome of [`Track::render`](crate::Track::render). All methods do
+/// nothing by default, and `()` is a reporter that does nothing. The progress of a render is
+/// reported to the [`ProgressReporter`](crate::progress::ProgressReporter) of the
+/// [`RunContext`](crate::context::RunContext) it runs in.
+pub trait Reporter {
+    /// A problem the render encountered, in the order of [`Warning::artifact`]
+    fn warning(&mut self, _warning: &Warning) {}
+
+    /// The render finished
+    fn rendered(&mut self, _report: &RenderReport) {}
+}
+
+impl Reporter for () {}
+
+impl fmt::Display for RenderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            track,
+            out_dir,
+            targets,
+            course_version,
+            decks,
+            skipped_decks,
+            book_sections,
+            skipped_book_sections,
+            exercises,
+            git_exercises,
+            derived_summaries,
+            written_files,
+            unchanged_files,
+            cached_files,
+            asset_bytes,
+            warnings,
+            timings: _,
+            local_edits: _,
+        } = self;
+        writeln!(
+            f,
+            "Track '{track}' rendered into {} by modmod {}",
+            out_dir.display(),
+            crate::VERSION
+        )?;
+        let names: Vec<_> = targets.iter().map(|t| t.to_string()).collect();
+        write!(f, "  targets: {}", names.join(", "))?;
+        if let Some(version) = course_version {
+            write!(f, "\n  course version: {version}")?;
+        }
+        if targets.contains(&RenderTarget::Slides) {
+            write!(f, "\n  {decks} slide deck(s)")?;
+            if *skipped_decks > 0 {
+                write!(f, ", {skipped_decks} skipped by configuration")?;
+            }
+        }
+        if targets.contains(&RenderTarget::Book) {
+            write!(f, "\n  {book_sections} book unit page(s)")?;
+            if *skipped_book_sections > 0 {
+                write!(f, ", {skipped_book_sections} skipped by configuration")?;
+            }
+        }
+        if targets.contains(&RenderTarget::Exercises) {
+            write!(f, "\n  {exercises} exercise package(s)")?;
+            for (name, GitSource { url, commit }) in git_exercises {
+                write!(f, "\n    '{name}' from {url} at {commit}")?;
+            }
+        }
+        if !derived_summaries.is_empty() {
+            write!(
+                f,
+                "\n  {} topic summary(ies) derived from headings",
+                derived_summaries.len()
+            )?;
+        }
+        write!(f, "\n  {written_files} file(s) written")?;
+        if *unchanged_files > 0 {
+            write!(f, ", {unchanged_files} unchanged")?;
+        }
+        if *cached_files > 0 {
+            write!(f, ", {cached_files} kept from the render cache")?;
+        }
+        let largest = asset_bytes.iter().max_by_key(|(_, bytes)| *bytes);
+        if let Some((item, bytes)) = largest.filter(|(_, bytes)| *bytes > 0) {
+            let total = asset_bytes.iter().map(|(_, bytes)| bytes).sum();
+            write!(
+                f,
+                "\n  {} of assets copied, most for {item} ({})",
+                format_bytes(total),
+                format_bytes(*bytes)
+            )?;
+        }
+        // The warnings themselves are logged while rendering
+        if !warnings.is_empty() {
+            write!(f, "\n  {} warning(s)", warnings.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// The size in bytes above which images and exercise files are reported with a
+/// [`WarningCode::LargeAsset`] warning, unless another limit is given
+pub const DEFAULT_MAX_ASSET_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The size in bytes above which the content of a topic or an exercise description is
+/// reported with a [`WarningCode::LargeSection`] warning, unless another limit is given
+pub const DEFAULT_MAX_SECTION_SIZE: u64 = 1024 * 1024;
+
+/// `1536` becomes `1.5 KiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// The files copied for a deck or exercise package, with their size in bytes
+pub(crate) type Assets = Vec<(PathBuf, u64)>;
+
+/// Records the files that were copied into the output for `item`, like the images of a
+/// deck, for [`RenderReport::asset_bytes`] and the warnings about large assets
+pub(crate) fn record_assets<'a>(item: &str, files: impl IntoIterator<Item = &'a Path>) {
+    let files = files
+        .into_iter()
+        .map(|file| {
+            let size = std::fs::metadata(file).map_or(0, |m| m.len());
+            (file.to_path_buf(), size)
+        })
+        .collect();
+    crate::context::current()
+        .assets
+        .lock()
+        .unwrap()
+        .push((item.to_string(), files));
+}
+
+/// The assets recorded in the current context so far, sorted by item, with a warning for each file
+/// that is larger than `max_size` bytes
+pub(crate) fn take_assets(max_size: Option<u64>) -> (Vec<(String, u64)>, Vec<Warning>) {
+    let mut assets = std::mem::take(&mut *crate::context::current().assets.lock().unwrap());
+    // Decks and exercise packages are rendered in parallel
+    assets.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut warnings = vec![];
+    let mut bytes = vec![];
+    for (item, files) in assets {
+        for (file, size) in files.iter() {
+            if max_size.is_some_and(|max| *size > max) {
+                warnings.push(Warning::new(
+                    WarningCode::LargeAsset,
+                    format!(
+                        "{} of {item} is {}, which is more than the limit of {}. Large files like videos are better hosted elsewhere and linked to",
+                        file.file_name().unwrap_or_default().to_string_lossy(),
+                        format_bytes(*size),
+                        format_bytes(max_size.unwrap_or_default())
+                    ),
+                    Some(file),
+                ));
+            }
+        }
+        bytes.push((item, files.iter().map(|(_, size)| size).sum()));
+    }
+    (bytes, warnings)
+}
+
+/// What kind of problem a [`Warning`] is about. The codes are stable, so that runs can deny
+/// specific warnings with `--deny warnings=<codes>`: renaming one is a breaking change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WarningCode {
+    /// Content that can't be found, when loading leniently
+    MissingContent,
+    /// A topic without slide content
+    EmptyTopic,
+    /// A `modmod_version` requirement that can't be parsed
+    InvalidModmodVersion,
+    /// A course version that isn't loosely semver
+    InvalidCourseVersion,
+    /// Output that is updated to an older course version than it was rendered with
+    CourseVersionDowngrade,
+    /// A track without a license
+    NoLicense,
+    /// A unit without exercises that doesn't set `no_exercises = true`
+    NoExercises,
+    /// A unit that sets `no_exercises = true`, but has exercises
+    UnexpectedExercises,
+    /// A topic that was reviewed too long ago
+    ReviewOverdue,
+    /// A topic that was never reviewed
+    NeverReviewed,
+    /// An image of a topic that its content doesn't refer to
+    UnreferencedImage,
+    /// A missing number in the indices of the modules or units
+    IndexGap,
+    /// A link that uses the former id of a renamed topic or exercise
+    AliasLink,
+    /// Slide templates with the same content
+    DuplicateTemplate,
+    /// A unit that keeps its own slide template, though the render overrides the default one
+    TemplateOverrideIgnored,
+    /// A link to a file or heading that doesn't exist, or an invalid URL
+    BrokenLink,
+    /// An external link that can't be reached
+    UnreachableLink,
+    /// A problem reading or writing the external link cache
+    LinkCache,
+    /// A placeholder the slide template uses, but that is empty for the deck
+    EmptyPlaceholder,
+    /// A course field the slide template uses, but that the track doesn't set
+    MissingCourseField,
+    /// A `#[modmod:...]` marker that is left in the output
+    UnresolvedPlaceholder,
+    /// An objective that repeats an earlier one of the unit
+    RepeatedObjective,
+    /// A slide template that doesn't use `#[modmod:content]`
+    NoContentPlaceholder,
+    /// An inline topic, which can't be translated
+    UntranslatableTopic,
+    /// A translation file for a topic that isn't part of the track
+    UnknownTranslation,
+    /// A translation whose source changed
+    OutdatedTranslation,
+    /// Strings of a topic that aren't translated, or topics and exercise descriptions without
+    /// a translation to the locale the track is rendered in
+    Untranslated,
+    /// A key in the configuration file that modmod doesn't know
+    UnknownConfigKey,
+    /// An image or exercise file that is larger than the limit for assets
+    LargeAsset,
+    /// A toolchain, devcontainer or deploy workflow file that is kept, as modmod didn't generate it
+    KeptScaffold,
+    /// Previews of decks that can't be exported, as Slidev can't take screenshots
+    ThumbnailsSkipped,
+    /// A sitemap that isn't written, as the base URL has no origin
+    SitemapSkipped,
+    /// A QR code that is left off the slides, as its URL can't be made or is too long
+    QrCodeSkipped,
+    /// A topic or exercise description that is larger than the limit for sections
+    LargeSection,
+    /// A topic without authors or a license, which the credits can't attribute
+    AttributionUnknown,
+}
+
+impl WarningCode {
+    pub const ALL: &'static [WarningCode] = &[
+        Self::MissingContent,
+        Self::EmptyTopic,
+        Self::InvalidModmodVersion,
+        Self::InvalidCourseVersion,
+        Self::CourseVersionDowngrade,
+        Self::NoLicense,
+        Self::NoExercises,
+        Self::UnexpectedExercises,
+        Self::ReviewOverdue,
+        Self::NeverReviewed,
+        Self::UnreferencedImage,
+        Self::IndexGap,
+        Self::AliasLink,
+        Self::DuplicateTemplate,
+        Self::TemplateOverrideIgnored,
+        Self::BrokenLink,
+        Self::UnreachableLink,
+        Self::LinkCache,
+        Self::EmptyPlaceholder,
+        Self::MissingCourseField,
+        Self::UnresolvedPlaceholder,
+        Self::RepeatedObjective,
+        Self::NoContentPlaceholder,
+        Self::UntranslatableTopic,
+        Self::UnknownTranslation,
+        Self::OutdatedTranslation,
+        Self::Untranslated,
+        Self::UnknownConfigKey,
+        Self::LargeAsset,
+        Self::KeptScaffold,
+        Self::ThumbnailsSkipped,
+        Self::SitemapSkipped,
+        Self::QrCodeSkipped,
+        Self::LargeSection,
+        Self::AttributionUnknown,
+    ];
+
+    /// The stable code of the warning in the [`crate::catalog`], like `W0102`
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::MissingContent => "W0101",
+            Self::EmptyTopic => "W0102",
+            Self::InvalidModmodVersion => "W0103",
+            Self::InvalidCourseVersion => "W0104",
+            Self::CourseVersionDowngrade => "W0105",
+            Self::NoLicense => "W0106",
+            Self::NoExercises => "W0107",
+            Self::UnexpectedExercises => "W0108",
+            Self::ReviewOverdue => "W0109",
+            Self::NeverReviewed => "W0110",
+            Self::UnreferencedImage => "W0111",
+            Self::IndexGap => "W0112",
+            Self::UnknownConfigKey => "W0113",
+            Self::DuplicateTemplate => "W0201",
+            Self::TemplateOverrideIgnored => "W0202",
+            Self::EmptyPlaceholder => "W0203",
+            Self::MissingCourseField => "W0204",
+            Self::UnresolvedPlaceholder => "W0205",
+            Self::RepeatedObjective => "W0206",
+            Self::NoContentPlaceholder => "W0207",
+            Self::AliasLink => "W0301",
+            Self::BrokenLink => "W0302",
+            Self::UnreachableLink => "W0303",
+            Self::LinkCache => "W0304",
+            Self::UntranslatableTopic => "W0401",
+            Self::UnknownTranslation => "W0402",
+            Self::OutdatedTranslation => "W0403",
+            Self::Untranslated => "W0404",
+            Self::LargeAsset => "W0501",
+            Self::KeptScaffold => "W0502",
+            Self::ThumbnailsSkipped => "W0503",
+            Self::SitemapSkipped => "W0504",
+            Self::QrCodeSkipped => "W0505",
+            Self::LargeSection => "W0506",
+            Self::AttributionUnknown => "W0114",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MissingContent => "missing-content",
+            Self::EmptyTopic => "empty-topic",
+            Self::InvalidModmodVersion => "invalid-modmod-version",
+            Self::InvalidCourseVersion => "invalid-course-version",
+            Self::CourseVersionDowngrade => "course-version-downgrade",
+            Self::NoLicense => "no-license",
+            Self::NoExercises => "no-exercises",
+            Self::UnexpectedExercises => "unexpected-exercises",
+            Self::ReviewOverdue => "review-overdue",
+            Self::NeverReviewed => "never-reviewed",
+            Self::UnreferencedImage => "unreferenced-image",
+            Self::IndexGap => "index-gap",
+            Self::AliasLink => "alias-link",
+            Self::DuplicateTemplate => "duplicate-template",
+            Self::TemplateOverrideIgnored => "template-override-ignored",
+            Self::BrokenLink => "broken-link",
+            Self::UnreachableLink => "unreachable-link",
+            Self::LinkCache => "link-cache",
+            Self::EmptyPlaceholder => "empty-placeholder",
+            Self::MissingCourseField => "missing-course-field",
+            Self::UnresolvedPlaceholder => "unresolved-placeholder",
+            Self::RepeatedObjective => "repeated-objective",
+            Self::NoContentPlaceholder => "no-content-placeholder",
+            Self::UntranslatableTopic => "untranslatable-topic",
+            Self::UnknownTranslation => "unknown-translation",
+            Self::OutdatedTranslation => "outdated-translation",
+            Self::Untranslated => "untranslated",
+            Self::UnknownConfigKey => "unknown-config-key",
+            Self::LargeAsset => "large-asset",
+            Self::KeptScaffold => "kept-scaffold",
+            Self::ThumbnailsSkipped => "thumbnails-skipped",
+            Self::SitemapSkipped => "sitemap-skipped",
+            Self::QrCodeSkipped => "qr-code-skipped",
+            Self::LargeSection => "large-section",
+            Self::AttributionUnknown => "attribution-unknown",
+        }
+    }
+}
+
+impl Serialize for WarningCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for WarningCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WarningCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| {
+                let codes: Vec<_> = Self::ALL.iter().map(|c| c.as_str()).collect();
+                format!(
+                    "Unknown warning code '{s}'. The codes are {}",
+                    codes.join(", ")
+                )
+            })
+    }
+}
+
+/// A problem that does not prevent rendering, but that should be reported to the user.
+/// The warnings of a render are in [`RenderReport::warnings`], in the order of the track.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// The file the warning is about, if any
+    pub source: Option<PathBuf>,
+    /// The line in the source file the warning is about, if known
+    pub line: Option<usize>,
+    /// The kind of output the warning came up rendering, if it came up rendering rather
+    /// than loading the track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<ArtifactKind>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>, source: Option<&Path>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            source: source.map(Path::to_path_buf),
+            line: None,
+            artifact: None,
+        }
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        match (&self.source, self.line) {
+            (Some(source), Some(line)) => write!(f, " ({}:{line})", source.display())?,
+            (Some(source), None) => write!(f, " ({})", source.display())?,
+            (None, _) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Sets the artifact of the `warnings` that don't have one yet
+pub(crate) fn set_artifact(warnings: &mut [Warning], artifact: ArtifactKind) {
+    for warning in warnings.iter_mut().filter(|w| w.artifact.is_none()) {
+        warning.artifact = Some(artifact);
+    }
+}
+
+/// Reports the `#[modmod:...]` markers that are left in rendered output. These are either
+/// misspelled placeholders, or conditional markers that were not resolved.
+pub(crate) fn leftover_placeholders(content: &str, output: &Path, warnings: &mut Vec<Warning>) {
+    leftover_placeholders_from_line(content, 1, output, warnings)
+}
+
+/// Like [`leftover_placeholders`], for content that starts at line `first_line` of the
+/// output
+pub(crate) fn leftover_placeholders_from_line(
+    content: &str,
+    first_line: usize,
+    output: &Path,
+    warnings: &mut Vec<Warning>,
+) {
+    for (text, line) in content.lines().zip(first_line..) {
+        for (start, _) in text.match_indices("#[modmod:") {
+            let marker = &text[start..];
+            let marker = &marker[..marker.find(']').map_or(marker.len(), |end| end + 1)];
+            warnings.push(
+                Warning::new(
+                    WarningCode::UnresolvedPlaceholder,
+                    format!("Unresolved placeholder `{marker}`"),
+                    Some(output),
+                )
+                .at_line(line),
+            );
+        }
+    }
+}
+
+/// How similar, from 0 to 1, two objectives must be to be merged as near-duplicates
+const SIMILAR_OBJECTIVES: f64 = 0.85;
+
+/// An objective of a unit, with the topic it comes from and whether that topic is optional
+pub(crate) struct UnitObjective<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) topic: &'a str,
+    pub(crate) optional: bool,
+}
+
+/// Leaves out the objectives of a unit that repeat an earlier one, ignoring case and whitespace.
+/// With `merge_similar`, near-duplicates are left out as well. The order of the remaining
+/// objectives is kept, and each objective that is left out is reported with its topic.
+pub(crate) fn dedupe_objectives<'a>(
+    objectives: Vec<UnitObjective<'a>>,
+    unit: &str,
+    merge_similar: bool,
+    warnings: &mut Vec<Warning>,
+) -> Vec<UnitObjective<'a>> {
+    let normalize = |text: &str| {
+        text.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches('.')
+            .to_lowercase()
+    };
+    let mut kept: Vec<(String, UnitObjective)> = vec![];
+    for objective in objectives {
+        let normalized = normalize(objective.text);
+        let earlier = kept.iter().find(|(other, _)| {
+            *other == normalized
+                || (merge_similar && similarity(other, &normalized) >= SIMILAR_OBJECTIVES)
+        });
+        match earlier {
+            Some((_, earlier)) => warnings.push(Warning::new(
+WarningCode::RepeatedObjective,
+                format!(
+                    "Objective \"{}\" of topic '{}' repeats \"{}\" of topic '{}' in unit '{unit}', and is left out",
+                    objective.text.trim(),
+                    objective.topic,
+                    earlier.text.trim(),
+                    earlier.topic
+                ),
+                None,
+            )),
+            None => kept.push((normalized, objective)),
+        }
+    }
+    kept.into_iter().map(|(_, objective)| objective).collect()
+}
+
+/// One minus the Levenshtein distance of the texts, relative to the length of the longest
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
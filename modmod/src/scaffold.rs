@@ -0,0 +1,89 @@
+//! Files modmod writes into the output for the tools around a course, like the devcontainer
+//! of the exercises or the workflow that deploys the course. Those are files the people who
+//! run a course may well set up or change by hand.
+//!
+//! So a file that is already there is only replaced if modmod generated it, which it tells by
+//! the [`GENERATED`] mark in its first lines, or if it is forced to. Other files are kept with
+//! a [`WarningCode::KeptScaffold`] warning. A generated file can end with a line that has the
+//! [`KEPT_BELOW`] mark, after which additions by hand are kept when it is generated again.
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::path::Path;
+
+use error_stack::{Context, Result};
+
+use crate::{
+    io::{self, PathExt, WriteExt},
+    report::{Warning, WarningCode},
+};
+
+/// Starts the comment at the top of each generated file
+pub(crate) const GENERATED: &str = "Generated by modmod";
+
+/// Is in the comment after which the content of a generated file is kept
+pub(crate) const KEPT_BELOW: &str =
+    "Everything below this line is kept when modmod generates this file again";
+
+pub(crate) struct Scaffold<'w> {
+    /// Replace files that modmod didn't generate too
+    pub(crate) force: bool,
+    pub(crate) warnings: &'w mut Vec<Warning>,
+}
+
+impl Scaffold<'_> {
+    /// Writes `content` to `path`, or keeps the file that is there if modmod didn't generate
+    /// it. If both have a [`KEPT_BELOW`] line, what follows it in the file that is there is
+    /// kept. Files with a `mode` are written as they are, as a script with Windows line
+    /// endings doesn't run.
+    pub(crate) fn write<C: Context + Default>(
+        &mut self,
+        path: &Path,
+        content: &str,
+        mode: Option<u32>,
+    ) -> Result<(), C> {
+        let mut content = content.to_string();
+        if path.exists() {
+            let existing = path.read_to_string::<C>()?;
+            if is_generated(&existing) {
+                if let (Some((generated, _)), Some((_, kept))) =
+                    (split_kept(&content), split_kept(&existing))
+                {
+                    content = format!("{generated}{kept}");
+                }
+            } else if !self.force {
+                self.warnings.push(Warning::new(
+                    WarningCode::KeptScaffold,
+                    format!(
+                        "Kept {}, as it wasn't generated by modmod. Pass --force-scaffold to replace it",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                    Some(path),
+                ));
+                io::keep_file(path);
+                return Ok(());
+            }
+        }
+        match mode {
+            Some(mode) => {
+                let mut file = path.create_file_with_mode::<C>(mode)?;
+                file.write_all::<C>(content)?;
+                file.commit()
+            }
+            None => path.write_text_file(content),
+        }
+    }
+}
+
+fn is_generated(content: &str) -> bool {
+    content.lines().take(3).any(|line| line.contains(GENERATED))
+}
+
+/// Splits `content` after the line with the [`KEPT_BELOW`] mark, if it has one
+fn split_kept(content: &str) -> Option<(&str, &str)> {
+    let mark = content.find(KEPT_BELOW)?;
+    let end = content[mark..]
+        .find('\n')
+        .map_or(content.len(), |end| mark + end + 1);
+    Some(content.split_at(end))
+}
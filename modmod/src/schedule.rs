@@ -0,0 +1,1033 @@
+//! A schedule of a track: its sessions laid out over the days of a course, for planners to
+//! import into a calendar or share as an agenda. Sessions follow each other from the start of
+//! each day, in the order of the track, until the time budget of the day is used up. A
+//! session that doesn't fit in what's left of a day starts on the next one, and one that is
+//! longer than a whole day is split into parts that fill the days it takes. Weekends and
+//! the days that are skipped, like holidays, have no sessions.
+//!
+//! Sessions without a duration get an even share of the estimate of their unit, as in
+//! [`crate::stats`]. The schedule can be written as iCalendar, with one event per session
+//! part, as a Markdown agenda, or as a run-sheet for presenters. Times are local times,
+//! without a time zone.
+//!
+//! For the run-sheet, the minutes of a session are budgeted over its topics and exercises,
+//! see [`budget`]. Topics that set a duration get it. When the session has exercises, the
+//! other topics get their estimate from their slides and the exercises share what is left,
+//! and otherwise the topics share the minutes of the session in proportion to their slides.
+//! Sessions whose content doesn't fit in them get a warning.
+
+use std::{fmt, fmt::Write, str::FromStr};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    conditional::Conditions,
+    date::Date,
+    stats::{topic_slide_count, unit_counts, MINUTES_PER_SLIDE},
+    to_tag,
+    url::BaseUrl,
+    OutputLayout, Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ScheduleError {}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to schedule the track")
+    }
+}
+
+impl error_stack::Context for ScheduleError {}
+
+/// The hours sessions are scheduled in each day, like `09:00-17:00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingHours {
+    /// Minutes since midnight
+    pub start: u32,
+    /// Minutes since midnight, after `start`
+    pub end: u32,
+}
+
+impl WorkingHours {
+    pub fn minutes(&self) -> u32 {
+        self.end - self.start
+    }
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            start: 9 * 60,
+            end: 17 * 60,
+        }
+    }
+}
+
+impl FromStr for WorkingHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let time = |time: &str| {
+            let (hours, minutes) = time.trim().split_once(':')?;
+            if minutes.len() != 2 {
+                return None;
+            }
+            let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+            (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+        };
+        let invalid = || format!("'{s}' is not of the form HH:MM-HH:MM, like 09:00-17:00");
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let (start, end) = (
+            time(start).ok_or_else(invalid)?,
+            time(end).ok_or_else(invalid)?,
+        );
+        if end <= start {
+            return Err(format!("the working hours '{s}' end before they start"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for WorkingHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", clock(self.start), clock(self.end))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleOptions {
+    /// The first day of the course. If it's a day that is skipped, the course starts on the
+    /// first day after it that isn't.
+    pub start_date: Date,
+    pub hours: WorkingHours,
+    /// The minutes of sessions per day, if less than the working hours
+    pub minutes_per_day: Option<u32>,
+    /// Schedule sessions on Saturdays and Sundays too
+    pub weekends: bool,
+    /// Days without sessions, like holidays
+    pub skip_dates: Vec<Date>,
+    /// The URL the output of the track is deployed at, for the links to the slides and book
+    pub base_url: BaseUrl,
+}
+
+/// A track with its sessions laid out over days, see the [module docs](self)
+#[derive(Debug)]
+pub struct Schedule {
+    pub track: String,
+    pub events: Vec<Event>,
+}
+
+/// A session, or a part of one, on a day of the course
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub date: Date,
+    /// Minutes since midnight
+    pub start: u32,
+    pub minutes: u32,
+    /// Like `Unit 1.2 - Ownership`
+    pub unit: String,
+    /// The name of the session, or `None` for a unit that doesn't define sessions
+    pub session: Option<String>,
+    /// The number of the part and the number of parts, for a session that is split over days
+    pub part: Option<(usize, usize)>,
+    pub slides_url: Option<String>,
+    pub book_url: Option<String>,
+    /// Identifies the event across schedules of the same track, so calendars update it when
+    /// it's imported again
+    pub uid: String,
+    /// The topics and exercises of the session in this part, with the times budgeted for them
+    pub items: Vec<AgendaItem>,
+    /// What doesn't fit in the session. Only the first part of a session has them.
+    pub warnings: Vec<String>,
+}
+
+impl Event {
+    pub fn end(&self) -> u32 {
+        self.start + self.minutes
+    }
+
+    /// Like `Unit 1.2 - Ownership: Lab (part 1/2)`
+    pub fn title(&self) -> String {
+        let mut title = self.unit.clone();
+        if let Some(session) = &self.session {
+            let _ = write!(title, ": {session}");
+        }
+        if let Some((part, parts)) = self.part {
+            let _ = write!(title, " (part {part}/{parts})");
+        }
+        title
+    }
+}
+
+/// A topic or exercise of a session, or the time left in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Topic,
+    Exercise,
+    /// Minutes of a session that no topic or exercise takes
+    Buffer,
+}
+
+/// A topic or exercise on the run-sheet of a session
+#[derive(Debug, Clone)]
+pub struct AgendaItem {
+    pub kind: ItemKind,
+    pub name: String,
+    /// The slides of a topic in the deck of its unit
+    pub slides: usize,
+    /// Minutes since midnight
+    pub start: u32,
+    pub minutes: u32,
+    /// Whether the topic set its own duration, rather than getting a share of its session
+    pub explicit: bool,
+    /// Whether it goes on from the previous part of a session that is split over days
+    pub continued: bool,
+}
+
+/// What the minutes of a session are budgeted over
+#[derive(Debug, Clone)]
+pub struct BudgetItem {
+    pub kind: ItemKind,
+    pub name: String,
+    pub slides: usize,
+    /// The duration the topic sets
+    pub explicit: Option<u32>,
+}
+
+/// Gives each of `items` its minutes out of the `minutes` of a session, see the
+/// [module docs](self), returning them with what doesn't fit. Leftover minutes that no item
+/// takes are added as a buffer. `has_duration` tells whether the session sets its
+/// duration, as content can only not fit in one that does.
+pub fn budget(
+    minutes: u32,
+    items: &[BudgetItem],
+    has_duration: bool,
+) -> (Vec<(BudgetItem, u32)>, Vec<String>) {
+    let mut warnings = vec![];
+    let fixed: u32 = items.iter().filter_map(|item| item.explicit).sum();
+    if fixed > minutes && has_duration {
+        warnings.push(format!(
+            "The topics with a duration take {fixed} minutes, {} more than the {minutes} of the session",
+            fixed - minutes
+        ));
+    }
+    let left = minutes.saturating_sub(fixed);
+    let flexible = |kind| {
+        items
+            .iter()
+            .enumerate()
+            .filter(move |(_, item)| item.kind == kind && item.explicit.is_none())
+            .map(|(i, _)| i)
+    };
+    let topics: Vec<_> = flexible(ItemKind::Topic).collect();
+    let exercises: Vec<_> = flexible(ItemKind::Exercise).collect();
+    let estimate: u32 = topics
+        .iter()
+        .map(|i| items[*i].slides as u32 * MINUTES_PER_SLIDE)
+        .sum();
+
+    let mut given: Vec<u32> = items
+        .iter()
+        .map(|item| item.explicit.unwrap_or(0))
+        .collect();
+    let topic_minutes = match exercises.is_empty() {
+        true => left,
+        false => estimate.min(left),
+    };
+    let weights: Vec<_> = topics.iter().map(|i| items[*i].slides as u32).collect();
+    for (i, share) in topics.iter().zip(apportion(topic_minutes, &weights)) {
+        given[*i] = share;
+    }
+    let exercise_minutes = left - topic_minutes;
+    for (i, share) in exercises
+        .iter()
+        .zip(apportion(exercise_minutes, &vec![1; exercises.len()]))
+    {
+        given[*i] = share;
+    }
+    if has_duration && topic_minutes < estimate {
+        warnings.push(format!(
+            "The slides of the topics without a duration are estimated at {estimate} minutes, but {topic_minutes} are left for them"
+        ));
+    }
+    if has_duration && !exercises.is_empty() && exercise_minutes == 0 {
+        warnings.push("No time is left for the exercises".to_string());
+    }
+
+    let mut budgeted: Vec<_> = items.iter().cloned().zip(given).collect();
+    let used: u32 = budgeted.iter().map(|(_, minutes)| minutes).sum();
+    if used < minutes {
+        budgeted.push((
+            BudgetItem {
+                kind: ItemKind::Buffer,
+                name: "Buffer".to_string(),
+                slides: 0,
+                explicit: None,
+            },
+            minutes - used,
+        ));
+    }
+    (budgeted, warnings)
+}
+
+/// Splits `total` in whole minutes in proportion to `weights`, giving the minutes lost to
+/// rounding to the largest remainders, and to the first among equal ones. The parts add up to
+/// `total`, and are split evenly if all weights are 0.
+pub fn apportion(total: u32, weights: &[u32]) -> Vec<u32> {
+    if weights.is_empty() {
+        return vec![];
+    }
+    let weights: Vec<u64> = match weights.iter().all(|w| *w == 0) {
+        true => vec![1; weights.len()],
+        false => weights.iter().map(|w| u64::from(*w)).collect(),
+    };
+    let sum: u64 = weights.iter().sum();
+    let exact: Vec<u64> = weights.iter().map(|w| u64::from(total) * w).collect();
+    let mut parts: Vec<u32> = exact.iter().map(|e| (e / sum) as u32).collect();
+    let mut by_remainder: Vec<usize> = (0..weights.len()).collect();
+    by_remainder.sort_by_key(|i| std::cmp::Reverse(exact[*i] % sum));
+    let left = total - parts.iter().sum::<u32>();
+    for i in by_remainder.into_iter().take(left as usize) {
+        parts[i] += 1;
+    }
+    parts
+}
+
+/// A session to lay out, before it's given a day
+struct Planned {
+    minutes: u32,
+    unit: String,
+    session: Option<String>,
+    slides_url: Option<String>,
+    book_url: Option<String>,
+    uid: String,
+    items: Vec<(BudgetItem, u32)>,
+    warnings: Vec<String>,
+}
+
+impl Schedule {
+    /// Lays out the sessions of `track` from `options.start_date`. Conditional blocks in the
+    /// slides are resolved for the estimates as when the track is rendered without a profile.
+    pub fn lay_out(track: &Track, options: &ScheduleOptions) -> Result<Self, ScheduleError> {
+        let conditions = Conditions {
+            profile: None,
+            known_profiles: &track.profiles,
+            variables: &track.variables,
+        };
+        let layout = &track.output;
+        let track_tag = to_tag(&track.name);
+        let mut planned = vec![];
+        for module in track.modules.iter() {
+            let label = &module.data.label;
+            for unit in module.data.units.iter() {
+                let counts =
+                    unit_counts(&unit.data, &conditions).change_context(ScheduleError::default())?;
+                let prefix = format!("{}_{}", label.path, unit.index);
+                let sessions = &unit.data.sessions;
+                let share =
+                    (counts.slides as u32 * MINUTES_PER_SLIDE / sessions.len() as u32).max(1);
+                for (i, session) in sessions.iter().enumerate() {
+                    let minutes = session.duration_minutes.unwrap_or(share);
+                    let mut items = vec![];
+                    for name in session.topics.iter() {
+                        let Some(topic) = unit.data.topics.iter().find(|t| t.data.name == *name)
+                        else {
+                            continue;
+                        };
+                        let slides = match unit.data.render_slides {
+                            true => topic_slide_count(&topic.data, &conditions)
+                                .change_context(ScheduleError::default())?,
+                            false => 0,
+                        };
+                        items.push(BudgetItem {
+                            kind: ItemKind::Topic,
+                            name: name.clone(),
+                            slides,
+                            explicit: topic.data.duration_minutes,
+                        });
+                    }
+                    items.extend(session.exercises.iter().map(|name| BudgetItem {
+                        kind: ItemKind::Exercise,
+                        name: name.clone(),
+                        slides: 0,
+                        explicit: None,
+                    }));
+                    let (items, warnings) =
+                        budget(minutes, &items, session.duration_minutes.is_some());
+                    planned.push(Planned {
+                        minutes,
+                        unit: format!("Unit {} - {}", label.qualify(unit.index), unit.data.name),
+                        session: (!session.implicit).then(|| session.name.clone()),
+                        slides_url: unit
+                            .data
+                            .render_slides
+                            .then(|| layout.deck_url(&options.base_url, &prefix)),
+                        book_url: unit
+                            .data
+                            .render_book
+                            .then(|| book_url(layout, &options.base_url, &unit.data.name)),
+                        uid: format!("{track_tag}-{prefix}-{}@modmod", i + 1),
+                        items,
+                        warnings,
+                    });
+                }
+            }
+        }
+        Ok(Self {
+            track: track.name.clone(),
+            events: lay_out(planned, options),
+        })
+    }
+
+    /// The schedule as an iCalendar file, with one event per session part. `stamp` is the
+    /// date the events are said to be created on.
+    pub fn to_ics(&self, stamp: Date) -> String {
+        let mut ics = String::new();
+        let mut line = |line: String| {
+            ics.push_str(&fold(&line));
+            ics.push_str("\r\n");
+        };
+        line("BEGIN:VCALENDAR".into());
+        line("VERSION:2.0".into());
+        line("PRODID:-//modmod//schedule//EN".into());
+        line("CALSCALE:GREGORIAN".into());
+        line(format!("X-WR-CALNAME:{}", escape_text(&self.track)));
+        for event in self.events.iter() {
+            let time = |minutes: u32| {
+                format!(
+                    "{}T{:02}{:02}00",
+                    ics_date(event.date),
+                    minutes / 60,
+                    minutes % 60
+                )
+            };
+            line("BEGIN:VEVENT".into());
+            line(format!("UID:{}", event.uid));
+            line(format!("DTSTAMP:{}T000000Z", ics_date(stamp)));
+            line(format!("DTSTART:{}", time(event.start)));
+            line(format!("DTEND:{}", time(event.end())));
+            line(format!("SUMMARY:{}", escape_text(&event.title())));
+            let links: Vec<_> = [("Slides", &event.slides_url), ("Book", &event.book_url)]
+                .into_iter()
+                .filter_map(|(name, url)| url.as_ref().map(|url| format!("{name}: {url}")))
+                .collect();
+            if !links.is_empty() {
+                line(format!("DESCRIPTION:{}", escape_text(&links.join("\n"))));
+            }
+            // The URL property has to be absolute, which the links are with an origin
+            let url = event.book_url.as_ref().or(event.slides_url.as_ref());
+            if let Some(url) = url.filter(|url| url.contains("://")) {
+                line(format!("URL:{url}"));
+            }
+            line("END:VEVENT".into());
+        }
+        line("END:VCALENDAR".into());
+        ics
+    }
+
+    /// The schedule as a Markdown agenda, with a section per day
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# Schedule of {}\n", self.track);
+        let mut day = None;
+        for event in self.events.iter() {
+            if day != Some(event.date) {
+                day = Some(event.date);
+                let _ = write!(md, "\n## {} {}\n\n", weekday_name(event.date), event.date);
+            }
+            let _ = write!(
+                md,
+                "- {}-{} {}",
+                clock(event.start),
+                clock(event.end()),
+                event.title()
+            );
+            for (name, url) in [("Slides", &event.slides_url), ("Book", &event.book_url)] {
+                if let Some(url) = url {
+                    let _ = write!(md, " · [{name}]({url})");
+                }
+            }
+            md.push('\n');
+        }
+        md
+    }
+
+    /// The schedule as a run-sheet for presenters: a section per day, with the times of the
+    /// topics and exercises of each session, a placeholder for a break between sessions, and
+    /// the warnings of sessions whose content doesn't fit in them
+    pub fn to_agenda(&self) -> String {
+        let mut md = format!("# Agenda of {}\n", self.track);
+        let mut day = None;
+        for event in self.events.iter() {
+            if day != Some(event.date) {
+                day = Some(event.date);
+                let _ = write!(md, "\n## {} {}\n", weekday_name(event.date), event.date);
+            } else {
+                let _ = write!(md, "\n*{} Break*\n", clock(event.start));
+            }
+            let _ = write!(
+                md,
+                "\n### {}-{} {}\n\n",
+                clock(event.start),
+                clock(event.end()),
+                event.title()
+            );
+            for warning in event.warnings.iter() {
+                let _ = writeln!(md, "> **Warning:** {warning}\n");
+            }
+            md.push_str("| Time | Content | Minutes |\n| --- | --- | ---: |\n");
+            for item in event.items.iter() {
+                let mut content = match item.kind {
+                    ItemKind::Topic => item.name.clone(),
+                    ItemKind::Exercise => format!("Exercise: {}", item.name),
+                    ItemKind::Buffer => "*Buffer*".to_string(),
+                };
+                if item.slides > 0 {
+                    let _ = write!(content, " ({} slides)", item.slides);
+                }
+                if item.continued {
+                    content.push_str(", continued");
+                }
+                let over = match item.start + item.minutes > event.end() {
+                    true => " (over)",
+                    false => "",
+                };
+                let set = match item.explicit {
+                    true => " (set)",
+                    false => "",
+                };
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {}{set}{over} |",
+                    clock(item.start),
+                    content.replace('|', "\\|"),
+                    item.minutes
+                );
+            }
+            let links: Vec<_> = [("Slides", &event.slides_url), ("Book", &event.book_url)]
+                .into_iter()
+                .filter_map(|(name, url)| url.as_ref().map(|url| format!("[{name}]({url})")))
+                .collect();
+            if !links.is_empty() {
+                let _ = writeln!(md, "\n{}", links.join(" · "));
+            }
+        }
+        md
+    }
+
+    /// The warnings of the sessions whose content doesn't fit in them, with the title of
+    /// the session
+    pub fn warnings(&self) -> impl Iterator<Item = (String, &str)> {
+        self.events.iter().flat_map(|event| {
+            let title = event.title();
+            event
+                .warnings
+                .iter()
+                .map(move |warning| (title.clone(), warning.as_str()))
+        })
+    }
+
+    /// The number of days the course takes
+    pub fn days(&self) -> usize {
+        let mut days: Vec<_> = self.events.iter().map(|e| e.date).collect();
+        days.dedup();
+        days.len()
+    }
+}
+
+/// Gives the sessions their days and times, see the [module docs](self)
+fn lay_out(planned: Vec<Planned>, options: &ScheduleOptions) -> Vec<Event> {
+    let budget = options
+        .minutes_per_day
+        .map_or(options.hours.minutes(), |m| m.min(options.hours.minutes()))
+        .max(1);
+    let skipped = |date: Date| {
+        (!options.weekends && date.weekday() >= 5) || options.skip_dates.contains(&date)
+    };
+    let working_day = |mut date: Date| {
+        while skipped(date) {
+            date = date.add_days(1);
+        }
+        date
+    };
+    let mut date = working_day(options.start_date);
+    let mut used = 0;
+    let mut events = vec![];
+    for session in planned {
+        // Sessions that fit in a day aren't split, so they start on the next day if needed
+        if used > 0 && used + session.minutes > budget && session.minutes <= budget {
+            date = working_day(date.add_days(1));
+            used = 0;
+        }
+        let first = events.len();
+        let mut remaining = session.minutes;
+        loop {
+            if used == budget {
+                date = working_day(date.add_days(1));
+                used = 0;
+            }
+            let minutes = remaining.min(budget - used);
+            events.push(Event {
+                date,
+                start: options.hours.start + used,
+                minutes,
+                unit: session.unit.clone(),
+                session: session.session.clone(),
+                part: None,
+                slides_url: session.slides_url.clone(),
+                book_url: session.book_url.clone(),
+                uid: session.uid.clone(),
+                items: vec![],
+                warnings: vec![],
+            });
+            used += minutes;
+            remaining -= minutes;
+            if remaining == 0 {
+                break;
+            }
+        }
+        events[first].warnings = session.warnings;
+        place_items(&mut events[first..], session.items);
+        let parts = events.len() - first;
+        if parts > 1 {
+            for (i, event) in events[first..].iter_mut().enumerate() {
+                event.part = Some((i + 1, parts));
+                event.uid = event.uid.replacen('@', &format!("-{}@", i + 1), 1);
+            }
+        }
+    }
+    events
+}
+
+/// Gives the budgeted items of a session their times in its `parts`, one after the other.
+/// An item that doesn't fit in what's left of a part goes on in the next one, and what
+/// doesn't fit in the session goes on after the end of its last part.
+fn place_items(parts: &mut [Event], items: Vec<(BudgetItem, u32)>) {
+    let last = parts.len() - 1;
+    let mut items = items.into_iter();
+    let mut pending: Option<(BudgetItem, u32, bool)> = None;
+    for (p, part) in parts.iter_mut().enumerate() {
+        let mut time = part.start;
+        while let Some((item, remaining, continued)) = pending
+            .take()
+            .or_else(|| items.next().map(|(item, minutes)| (item, minutes, false)))
+        {
+            let minutes = match p == last {
+                true => remaining,
+                false => remaining.min(part.end() - time),
+            };
+            if minutes == 0 && remaining > 0 {
+                pending = Some((item, remaining, continued));
+                break;
+            }
+            part.items.push(AgendaItem {
+                kind: item.kind,
+                name: item.name.clone(),
+                slides: item.slides,
+                start: time,
+                minutes,
+                explicit: item.explicit.is_some(),
+                continued,
+            });
+            time += minutes;
+            if remaining > minutes {
+                pending = Some((item, remaining - minutes, true));
+                break;
+            }
+        }
+    }
+}
+
+/// The URL of the book page of a unit
+fn book_url(layout: &OutputLayout, base_url: &BaseUrl, unit_name: &str) -> String {
+    base_url.join(&(OutputLayout::url_path(&layout.book().join(to_tag(unit_name))) + ".html"))
+}
+
+/// `570` becomes `09:30`
+fn clock(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn weekday_name(date: Date) -> &'static str {
+    [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ][usize::from(date.weekday())]
+}
+
+fn ics_date(date: Date) -> String {
+    format!("{:04}{:02}{:02}", date.year, date.month, date.day)
+}
+
+/// Escapes a value of a text property of iCalendar
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line of iCalendar into lines of at most 75 bytes, where each line after
+/// the first starts with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    fn options(start_date: &str) -> ScheduleOptions {
+        ScheduleOptions {
+            start_date: date(start_date),
+            hours: WorkingHours::default(),
+            minutes_per_day: None,
+            weekends: false,
+            skip_dates: vec![],
+            base_url: "/".parse().unwrap(),
+        }
+    }
+
+    /// Sessions of `minutes` each, of units 1, 2, ...
+    fn schedule(sessions: &[u32], options: &ScheduleOptions) -> Schedule {
+        let planned = sessions
+            .iter()
+            .zip(1..)
+            .map(|(minutes, i)| Planned {
+                minutes: *minutes,
+                unit: format!("Unit 1.{i} - Unit {i}"),
+                session: None,
+                slides_url: None,
+                book_url: None,
+                uid: format!("track-1_{i}-1@modmod"),
+                items: vec![],
+                warnings: vec![],
+            })
+            .collect();
+        Schedule {
+            track: "Track".to_string(),
+            events: lay_out(planned, options),
+        }
+    }
+
+    /// The date, start and end of each event
+    fn times(schedule: &Schedule) -> Vec<(String, String, String)> {
+        schedule
+            .events
+            .iter()
+            .map(|e| (e.date.to_string(), clock(e.start), clock(e.end())))
+            .collect()
+    }
+
+    fn times_of(expected: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+        expected
+            .iter()
+            .map(|(d, s, e)| (d.to_string(), s.to_string(), e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn sessions_that_dont_fit_move_to_the_next_day() {
+        let schedule = schedule(&[300, 240, 120], &options("2025-03-03"));
+        assert_eq!(
+            times(&schedule),
+            times_of(&[
+                ("2025-03-03", "09:00", "14:00"),
+                ("2025-03-04", "09:00", "13:00"),
+                ("2025-03-04", "13:00", "15:00"),
+            ])
+        );
+        assert_eq!(schedule.days(), 2);
+    }
+
+    #[test]
+    fn sessions_longer_than_a_day_are_split() {
+        let mut options = options("2025-03-03");
+        options.minutes_per_day = Some(360);
+        let schedule = schedule(&[60, 600], &options);
+        assert_eq!(
+            times(&schedule),
+            times_of(&[
+                ("2025-03-03", "09:00", "10:00"),
+                ("2025-03-03", "10:00", "15:00"),
+                ("2025-03-04", "09:00", "14:00"),
+            ])
+        );
+        let parts: Vec<_> = schedule.events.iter().map(|e| e.part).collect();
+        assert_eq!(parts, [None, Some((1, 2)), Some((2, 2))]);
+        assert_eq!(schedule.events[1].uid, "track-1_2-1-1@modmod");
+        assert_eq!(schedule.events[2].uid, "track-1_2-1-2@modmod");
+        assert_eq!(schedule.events[2].title(), "Unit 1.2 - Unit 2 (part 2/2)");
+    }
+
+    #[test]
+    fn weekends_and_skipped_days_have_no_sessions() {
+        // Friday, and the Monday after is a holiday
+        let mut holidays = options("2025-04-18");
+        holidays.skip_dates = vec![date("2025-04-18"), date("2025-04-21")];
+        assert_eq!(
+            times(&schedule(&[480, 480], &holidays)),
+            times_of(&[
+                ("2025-04-22", "09:00", "17:00"),
+                ("2025-04-23", "09:00", "17:00"),
+            ])
+        );
+
+        // Starting on a Saturday
+        let saturday = schedule(&[480, 480, 480], &options("2025-03-01"));
+        let dates: Vec<_> = saturday.events.iter().map(|e| e.date).collect();
+        assert_eq!(
+            dates,
+            [date("2025-03-03"), date("2025-03-04"), date("2025-03-05")]
+        );
+
+        let mut weekends = options("2025-03-01");
+        weekends.weekends = true;
+        let with_weekends = schedule(&[480, 480], &weekends);
+        let dates: Vec<_> = with_weekends.events.iter().map(|e| e.date).collect();
+        assert_eq!(dates, [date("2025-03-01"), date("2025-03-02")]);
+    }
+
+    #[test]
+    fn ics_dates_cross_months_and_years() {
+        // Tuesday 2024-12-31, then New Year's Day is skipped
+        let mut new_year = options("2024-12-31");
+        new_year.skip_dates = vec![date("2025-01-01")];
+        let ics = schedule(&[480, 90], &new_year).to_ics(date("2024-12-01"));
+        let lines: Vec<_> = ics
+            .split("\r\n")
+            .filter(|line| line.starts_with("DTSTART") || line.starts_with("DTEND"))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "DTSTART:20241231T090000",
+                "DTEND:20241231T170000",
+                "DTSTART:20250102T090000",
+                "DTEND:20250102T103000",
+            ]
+        );
+
+        // Friday 2025-01-31 goes on to Monday 2025-02-03
+        let ics = schedule(&[480, 480], &options("2025-01-31")).to_ics(date("2025-01-01"));
+        assert!(ics.contains("DTSTART:20250131T090000\r\n"), "{ics}");
+        assert!(ics.contains("DTSTART:20250203T090000\r\n"), "{ics}");
+        assert!(ics.contains("DTSTAMP:20250101T000000Z\r\n"), "{ics}");
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape_text("Traits, generics; and \\ paths\nnext line"),
+            r"Traits\, generics\; and \\ paths\nnext line"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        for line in [
+            format!("SUMMARY:{}", "a".repeat(200)),
+            // Characters of more than one byte aren't split
+            format!("SUMMARY:{}", "é".repeat(100)),
+            format!("SUMMARY:{}", "🦀".repeat(40)),
+        ] {
+            let folded = fold(&line);
+            let lines: Vec<_> = folded.split("\r\n").collect();
+            assert!(lines.len() > 1, "{folded}");
+            for (i, part) in lines.iter().enumerate() {
+                assert!(part.len() <= 75, "{part} is {} octets", part.len());
+                assert_eq!(part.starts_with(' '), i > 0, "{part}");
+            }
+            assert_eq!(folded.replace("\r\n ", ""), line);
+        }
+        let short = "SUMMARY:".to_string() + &"a".repeat(67);
+        assert_eq!(fold(&short), short);
+    }
+
+    #[test]
+    fn calendar_of_a_session_with_links() {
+        let mut schedule = schedule(&[60], &options("2025-03-03"));
+        let event = &mut schedule.events[0];
+        event.session = Some("Lab; part one".to_string());
+        event.slides_url = Some("https://example.com/slides/1_1/".to_string());
+        event.book_url = Some("https://example.com/book/unit-1.html".to_string());
+        let ics = schedule.to_ics(date("2025-03-01"));
+        assert!(
+            ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"),
+            "{ics}"
+        );
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"), "{ics}");
+        assert!(
+            ics.contains("SUMMARY:Unit 1.1 - Unit 1: Lab\\; part one\r\n"),
+            "{ics}"
+        );
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(
+            unfolded.contains(
+                "DESCRIPTION:Slides: https://example.com/slides/1_1/\\nBook: https://example.com/book/unit-1.html\r\n"
+            ),
+            "{unfolded}"
+        );
+        assert!(
+            unfolded.contains("URL:https://example.com/book/unit-1.html\r\n"),
+            "{unfolded}"
+        );
+    }
+
+    fn item(kind: ItemKind, slides: usize, explicit: Option<u32>) -> BudgetItem {
+        BudgetItem {
+            kind,
+            name: format!("{kind:?} with {slides} slides"),
+            slides,
+            explicit,
+        }
+    }
+
+    fn minutes(budgeted: &[(BudgetItem, u32)]) -> Vec<(ItemKind, u32)> {
+        budgeted
+            .iter()
+            .map(|(item, minutes)| (item.kind, *minutes))
+            .collect()
+    }
+
+    #[test]
+    fn apportioned_minutes_are_rounded_to_the_largest_remainders() {
+        assert_eq!(apportion(10, &[1, 1, 1]), [4, 3, 3]);
+        assert_eq!(apportion(10, &[1, 2]), [3, 7]);
+        assert_eq!(
+            apportion(100, &[1, 1, 1, 1, 1, 1]),
+            [17, 17, 17, 17, 16, 16]
+        );
+        // 7 * 2/9 = 1.56 and 7 * 4/9 = 3.11, so the first gets the minute left
+        assert_eq!(apportion(7, &[2, 3, 4]), [2, 2, 3]);
+        assert_eq!(apportion(0, &[3, 5]), [0, 0]);
+        assert_eq!(apportion(5, &[]), Vec::<u32>::new());
+        // Without any weight, the minutes are split evenly
+        assert_eq!(apportion(7, &[0, 0]), [4, 3]);
+        assert_eq!(apportion(6, &[0, 4]), [0, 6]);
+
+        for total in 0..200 {
+            for weights in [&[1, 2, 3][..], &[7], &[0, 1, 0], &[13, 13, 1, 40, 2]] {
+                let parts = apportion(total, weights);
+                assert_eq!(parts.len(), weights.len());
+                assert_eq!(parts.iter().sum::<u32>(), total, "{total} over {weights:?}");
+                // No part is off by more than a minute from its exact share
+                let sum: u32 = weights.iter().sum();
+                for (part, weight) in parts.iter().zip(weights) {
+                    let exact = f64::from(total) * f64::from(*weight) / f64::from(sum);
+                    assert!((f64::from(*part) - exact).abs() < 1.0, "{parts:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn budgets_add_up_to_the_session() {
+        let items = [
+            item(ItemKind::Topic, 5, None),
+            item(ItemKind::Topic, 3, Some(20)),
+            item(ItemKind::Topic, 0, None),
+            item(ItemKind::Exercise, 0, None),
+            item(ItemKind::Exercise, 0, None),
+        ];
+        for session in [0, 1, 19, 20, 21, 35, 36, 37, 90, 241] {
+            let (budgeted, _) = budget(session, &items, true);
+            let total: u32 = budgeted.iter().map(|(_, minutes)| minutes).sum();
+            // What the topics set is given even if it doesn't fit
+            assert_eq!(total, session.max(20), "{budgeted:?}");
+        }
+
+        // 5 slides take 10 minutes, and the exercises share the other 60 - 20 - 10
+        let (budgeted, warnings) = budget(60, &items, true);
+        assert_eq!(
+            minutes(&budgeted),
+            [
+                (ItemKind::Topic, 10),
+                (ItemKind::Topic, 20),
+                (ItemKind::Topic, 0),
+                (ItemKind::Exercise, 15),
+                (ItemKind::Exercise, 15),
+            ]
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn sessions_without_exercises_are_shared_by_slides_with_a_buffer_for_the_rest() {
+        let items = [
+            item(ItemKind::Topic, 1, None),
+            item(ItemKind::Topic, 2, None),
+        ];
+        let (budgeted, warnings) = budget(31, &items, true);
+        assert_eq!(
+            minutes(&budgeted),
+            [(ItemKind::Topic, 10), (ItemKind::Topic, 21)]
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        // Topics with a duration of 0 take no time, and what no one takes is a buffer
+        let items = [
+            item(ItemKind::Topic, 4, Some(0)),
+            item(ItemKind::Topic, 2, Some(15)),
+        ];
+        let (budgeted, _) = budget(45, &items, true);
+        assert_eq!(
+            minutes(&budgeted),
+            [
+                (ItemKind::Topic, 0),
+                (ItemKind::Topic, 15),
+                (ItemKind::Buffer, 30),
+            ]
+        );
+        let (budgeted, _) = budget(45, &[], true);
+        assert_eq!(minutes(&budgeted), [(ItemKind::Buffer, 45)]);
+    }
+
+    #[test]
+    fn budgets_that_dont_fit_warn_only_for_sessions_with_a_duration() {
+        let items = [
+            item(ItemKind::Topic, 10, None),
+            item(ItemKind::Topic, 1, Some(40)),
+            item(ItemKind::Exercise, 0, None),
+        ];
+        let (budgeted, warnings) = budget(30, &items, true);
+        assert_eq!(
+            minutes(&budgeted),
+            [
+                (ItemKind::Topic, 0),
+                (ItemKind::Topic, 40),
+                (ItemKind::Exercise, 0),
+            ]
+        );
+        assert_eq!(
+            warnings,
+            [
+                "The topics with a duration take 40 minutes, 10 more than the 30 of the session",
+                "The slides of the topics without a duration are estimated at 20 minutes, but 0 are left for them",
+                "No time is left for the exercises",
+            ]
+        );
+        let (_, warnings) = budget(30, &items, false);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+}
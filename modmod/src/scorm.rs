@@ -0,0 +1,407 @@
+//! Exporting the built slides and book of a track as a SCORM package, for learning
+//! management systems that only import SCORM.
+//!
+//! The package follows the structure of the track: the organization of its manifest has an
+//! item for each module, with an item for each of its units. Each unit is a SCO with a launch
+//! page that shows its book chapter and links to its slide deck, and reports to the LMS that
+//! the unit was viewed. The built book is packaged as `book/`, and each deck as
+//! `slides/<prefix>/`, as they are published by [`crate::publish`].
+
+use std::{fmt, fmt::Write, path::Path, str::FromStr};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    archive::{entries_of_dir, Entry, EntryContent},
+    cartridge::{escape, identifier, unescape},
+    publish::run,
+    to_prefixed_tag, to_tag, OutputLayout, Track,
+};
+
+/// Name of the manifest of a package, in its root
+pub const SCORM_MANIFEST_FILE: &str = "imsmanifest.xml";
+
+/// The runtime adapter that the launch pages load
+const RUNTIME: &str = include_str!("../include/scorm.js");
+const RUNTIME_FILE: &str = "scorm.js";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ScormError {}
+
+impl fmt::Display for ScormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to export the track as SCORM package")
+    }
+}
+
+impl error_stack::Context for ScormError {}
+
+/// The version of SCORM a package is made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScormVersion {
+    Scorm12,
+    Scorm2004,
+}
+
+impl ScormVersion {
+    pub const ALL: [Self; 2] = [Self::Scorm12, Self::Scorm2004];
+}
+
+impl fmt::Display for ScormVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Scorm12 => "1.2",
+            Self::Scorm2004 => "2004",
+        })
+    }
+}
+
+impl FromStr for ScormVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.to_string() == s)
+            .ok_or_else(|| format!("unknown SCORM version '{s}', expected 1.2 or 2004"))
+    }
+}
+
+pub struct ScormOptions<'a> {
+    /// The layout the track was rendered with
+    pub layout: &'a OutputLayout,
+    pub version: ScormVersion,
+    /// Run the build scripts of the slides and `mdbook build` first
+    pub build: bool,
+}
+
+/// The files of a package, and what it holds
+#[derive(Debug)]
+pub struct ScormPackage {
+    pub entries: Vec<Entry>,
+    pub units: usize,
+    pub chapters: usize,
+    pub decks: usize,
+}
+
+/// A unit in the package, with the entry points of its chapter and deck in the package
+struct Sco {
+    identifier: String,
+    title: String,
+    chapter: Option<String>,
+    deck: Option<String>,
+}
+
+impl Track {
+    /// Collects the SCORM package of the track from its slides and book, as rendered into
+    /// `out_dir` with the layout of `options` and built. Units with neither a chapter nor a
+    /// deck are left out.
+    pub fn scorm(&self, out_dir: &Path, options: ScormOptions) -> Result<ScormPackage, ScormError> {
+        let ScormOptions {
+            layout,
+            version,
+            build,
+        } = options;
+        let slides_dir = out_dir.join(layout.slides());
+        let book_dir = out_dir.join(layout.book());
+        // Decks without content are not written, and units can leave out either
+        let deck = |module: &crate::Module, unit: &crate::Indexed<crate::Unit>| {
+            let prefix = format!("{}_{}", module.label.path, unit.index);
+            let slug = to_prefixed_tag(&unit.data.name, &prefix);
+            (unit.data.render_slides && slides_dir.join(&slug).with_extension("md").exists())
+                .then_some((prefix, slug))
+        };
+        let chapter = |unit: &crate::Unit| {
+            let page = to_tag(&unit.name);
+            (unit.render_book
+                && book_dir
+                    .join("src")
+                    .join(&page)
+                    .with_extension("md")
+                    .exists())
+            .then_some(page)
+        };
+
+        if build {
+            let dry_run = crate::io::writes_discarded();
+            let prefixes: Vec<_> = self
+                .modules
+                .iter()
+                .flat_map(|m| m.data.units.iter().filter_map(|u| deck(&m.data, u)))
+                .collect();
+            if !prefixes.is_empty() {
+                run::<ScormError>(&slides_dir, "npm", &["install"], dry_run)?;
+            }
+            for (prefix, _) in prefixes {
+                let script = format!("build-{prefix}");
+                run::<ScormError>(&slides_dir, "npm", &["run", &script], dry_run)?;
+            }
+            if self.units().any(|u| chapter(u).is_some()) {
+                run::<ScormError>(&book_dir, "mdbook", &["build"], dry_run)?;
+            }
+        }
+
+        let mut package = ScormPackage {
+            entries: vec![],
+            units: 0,
+            chapters: 0,
+            decks: 0,
+        };
+        let mut content = vec![];
+        let mut add_dir = |dir: &Path, to: &str| -> Result<(), ScormError> {
+            if !dir.exists() {
+                return Err(Report::new(ScormError::default()).attach_printable(format!(
+                    "{} hasn't been built, use --build to build it first",
+                    dir.display()
+                )));
+            }
+            for entry in entries_of_dir(dir).change_context(ScormError::default())? {
+                content.push(Entry {
+                    path: format!("{to}/{}", entry.path),
+                    ..entry
+                });
+            }
+            Ok(())
+        };
+        let mut organization = String::new();
+        let mut scos = vec![];
+        for module in self.modules.iter() {
+            let label = &module.data.label;
+            let mut items = String::new();
+            for unit in module.data.units.iter() {
+                let chapter = chapter(&unit.data).map(|page| format!("book/{page}.html"));
+                let deck = match deck(&module.data, unit) {
+                    Some((prefix, slug)) => {
+                        add_dir(
+                            &slides_dir.join(layout.dist()).join(slug),
+                            &format!("slides/{prefix}"),
+                        )?;
+                        Some(format!("slides/{prefix}/index.html"))
+                    }
+                    None => None,
+                };
+                if chapter.is_none() && deck.is_none() {
+                    continue;
+                }
+                let sco = Sco {
+                    identifier: format!(
+                        "sco_{}",
+                        identifier(&format!("{}_{}", label.path, unit.index))
+                    ),
+                    title: format!("Unit {} - {}", label.qualify(unit.index), unit.data.name),
+                    chapter,
+                    deck,
+                };
+                let _ = write!(
+                    items,
+                    "<item identifier=\"item_{0}\" identifierref=\"{0}\">\n<title>{1}</title>\n</item>\n",
+                    sco.identifier,
+                    escape(&sco.title)
+                );
+                scos.push(sco);
+            }
+            if items.is_empty() {
+                continue;
+            }
+            let title = match label.display.is_empty() {
+                true => module.data.name.clone(),
+                false => format!("Module {} - {}", label.display, module.data.name),
+            };
+            let _ = write!(
+                organization,
+                "<item identifier=\"module_{}\">\n<title>{}</title>\n{items}</item>\n",
+                identifier(&label.path),
+                escape(&title)
+            );
+        }
+        if scos.iter().any(|sco| sco.chapter.is_some()) {
+            add_dir(&book_dir.join("book"), "book")?;
+        }
+        for sco in scos.iter() {
+            package
+                .entries
+                .push(entry(&launch_file(sco), launch_page(sco)));
+            package.units += 1;
+            package.chapters += usize::from(sco.chapter.is_some());
+            package.decks += usize::from(sco.deck.is_some());
+        }
+        package
+            .entries
+            .push(entry(RUNTIME_FILE, RUNTIME.to_string()));
+        package.entries.insert(
+            0,
+            entry(
+                SCORM_MANIFEST_FILE,
+                manifest_xml(&self.name, version, &organization, &scos, &content),
+            ),
+        );
+        package.entries.extend(content);
+        package.entries.sort_by(|a, b| a.path.cmp(&b.path));
+        check_manifest(&package.entries)?;
+        Ok(package)
+    }
+}
+
+/// Parses the manifest of the package back, and checks that every file and launch page it
+/// refers to is in the package, and every item refers to a resource of the manifest
+pub fn check_manifest(entries: &[Entry]) -> Result<(), ScormError> {
+    let Some(manifest) = entries.iter().find(|e| e.path == SCORM_MANIFEST_FILE) else {
+        return Err(Report::new(ScormError::default())
+            .attach_printable(format!("The package has no {SCORM_MANIFEST_FILE}")));
+    };
+    let manifest = manifest
+        .content
+        .bytes()
+        .change_context(ScormError::default())?;
+    let manifest = String::from_utf8_lossy(&manifest);
+    let values = |attribute: &str| -> Vec<String> {
+        manifest
+            .split(&format!(" {attribute}=\""))
+            .skip(1)
+            .filter_map(|rest| rest.split_once('"'))
+            .map(|(value, _)| unescape(value))
+            .collect()
+    };
+    let resources: Vec<_> = manifest
+        .split("<resource ")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("identifier=\""))
+        .filter_map(|(_, rest)| rest.split_once('"'))
+        .map(|(identifier, _)| unescape(identifier))
+        .collect();
+    let missing_files: Vec<_> = values("href")
+        .into_iter()
+        .filter(|href| !entries.iter().any(|e| &e.path == href))
+        .collect();
+    if !missing_files.is_empty() {
+        return Err(Report::new(ScormError::default()).attach_printable(format!(
+            "The manifest of the package refers to files it doesn't have: {}",
+            missing_files.join(", ")
+        )));
+    }
+    let missing_resources: Vec<_> = values("identifierref")
+        .into_iter()
+        .filter(|reference| !resources.contains(reference))
+        .collect();
+    if !missing_resources.is_empty() {
+        return Err(Report::new(ScormError::default()).attach_printable(format!(
+            "The manifest of the package refers to resources it doesn't have: {}",
+            missing_resources.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn entry(path: &str, content: String) -> Entry {
+    Entry {
+        path: path.to_string(),
+        executable: false,
+        content: EntryContent::Bytes(content.into_bytes()),
+    }
+}
+
+fn launch_file(sco: &Sco) -> String {
+    format!("units/{}.html", sco.identifier.trim_start_matches("sco_"))
+}
+
+/// The page the LMS opens for a unit: its chapter in a frame, if it has one, and a link to
+/// its deck, which opens in a window of its own as the slides are navigated with the keyboard
+fn launch_page(sco: &Sco) -> String {
+    let title = escape(&sco.title);
+    let mut page = format!(
+        r#"<!DOCTYPE html>
+<!-- Generated by modmod -->
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<script src="../{RUNTIME_FILE}"></script>
+<style>
+html, body {{ height: 100%; margin: 0; display: flex; flex-direction: column; font-family: sans-serif; }}
+header {{ padding: 0.5em 1em; }}
+iframe {{ flex: 1; border: none; }}
+</style>
+</head>
+<body>
+<header>
+<h1>{title}</h1>
+"#
+    );
+    if let Some(deck) = &sco.deck {
+        let _ = writeln!(
+            page,
+            "<p><a href=\"../{}\" target=\"_blank\">Open the slides</a></p>",
+            escape(deck)
+        );
+    }
+    page.push_str("</header>\n");
+    if let Some(chapter) = &sco.chapter {
+        let _ = writeln!(
+            page,
+            "<iframe src=\"../{}\" title=\"{title}\"></iframe>",
+            escape(chapter)
+        );
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+fn manifest_xml(
+    track: &str,
+    version: ScormVersion,
+    organization: &str,
+    scos: &[Sco],
+    content: &[Entry],
+) -> String {
+    let identifier = format!("modmod_{}", identifier(&to_tag(track)));
+    let (namespaces, schema_version, scorm_type) = match version {
+        ScormVersion::Scorm12 => (
+            r#"version="1.0" xmlns="http://www.imsproject.org/xsd/imscp_rootv1p1p2" xmlns:adlcp="http://www.adlnet.org/xsd/adlcp_rootv1p2" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsproject.org/xsd/imscp_rootv1p1p2 imscp_rootv1p1p2.xsd http://www.adlnet.org/xsd/adlcp_rootv1p2 adlcp_rootv1p2.xsd""#,
+            "1.2",
+            "adlcp:scormtype",
+        ),
+        ScormVersion::Scorm2004 => (
+            r#"version="1" xmlns="http://www.imsglobal.org/xsd/imscp_v1p1" xmlns:adlcp="http://www.adlnet.org/xsd/adlcp_v1p3" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.imsglobal.org/xsd/imscp_v1p1 imscp_v1p1.xsd http://www.adlnet.org/xsd/adlcp_v1p3 adlcp_v1p3.xsd""#,
+            "2004 4th Edition",
+            "adlcp:scormType",
+        ),
+    };
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="{identifier}" {namespaces}>
+<metadata>
+<schema>ADL SCORM</schema>
+<schemaversion>{schema_version}</schemaversion>
+</metadata>
+<organizations default="organization">
+<organization identifier="organization">
+<title>{}</title>
+{organization}</organization>
+</organizations>
+<resources>
+"#,
+        escape(track)
+    );
+    for sco in scos {
+        let launch = escape(&launch_file(sco));
+        let _ = write!(
+            xml,
+            "<resource identifier=\"{}\" type=\"webcontent\" {scorm_type}=\"sco\" href=\"{launch}\">\n<file href=\"{launch}\"/>\n<dependency identifierref=\"runtime\"/>\n<dependency identifierref=\"content\"/>\n</resource>\n",
+            sco.identifier,
+        );
+    }
+    let _ = write!(
+        xml,
+        "<resource identifier=\"runtime\" type=\"webcontent\" {scorm_type}=\"asset\">\n<file href=\"{RUNTIME_FILE}\"/>\n</resource>\n"
+    );
+    let _ = writeln!(
+        xml,
+        "<resource identifier=\"content\" type=\"webcontent\" {scorm_type}=\"asset\">"
+    );
+    for entry in content {
+        let _ = writeln!(xml, "<file href=\"{}\"/>", escape(&entry.path));
+    }
+    xml.push_str("</resource>\n</resources>\n</manifest>\n");
+    xml
+}
@@ -0,0 +1,238 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{book::heading_anchor, io::PathExt, slides::split_slides};
+
+const SEARCH_PAGE: &str = include_str!("../include/search.html");
+
+/// Length of the snippets shown with search results, in characters
+const SNIPPET_LENGTH: usize = 160;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct SearchIndexError {}
+
+impl fmt::Display for SearchIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write search index")
+    }
+}
+
+impl error_stack::Context for SearchIndexError {}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchIndexOptions {
+    /// Leave the content of code blocks out of the index
+    pub skip_code: bool,
+}
+
+/// The search index that is written to `search-index.json`. Sources are keyed on the
+/// slug of their deck or book page, so that an index can be updated one source at a time.
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchIndex {
+    #[serde(skip)]
+    options: SearchIndexOptions,
+    sources: BTreeMap<String, Source>,
+    /// Maps each term to the sources and the index of the section it occurs in
+    terms: BTreeMap<String, Vec<(String, usize)>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Source {
+    title: String,
+    /// Location of the deck or page, relative to the root the output is published at
+    url: String,
+    sections: Vec<Section>,
+}
+
+/// A slide of a deck, or a part of a book page under one heading
+#[derive(Debug, Serialize)]
+struct Section {
+    /// Number of the slide, or anchor of the heading
+    anchor: String,
+    heading: String,
+    snippet: String,
+}
+
+impl SearchIndex {
+    pub(crate) fn new(options: SearchIndexOptions) -> Self {
+        Self {
+            options,
+            sources: BTreeMap::new(),
+            terms: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a rendered slide deck, whose slides are found at `url/<slide number>`
+    pub(crate) fn add_deck(&mut self, slug: &str, title: &str, url: &str, deck: &str) {
+        let slides = split_slides(deck)
+            .into_iter()
+            .zip(1..)
+            .map(|(slide, number)| (number.to_string(), slide))
+            .collect();
+        self.add(format!("slides/{slug}"), title, url, slides);
+    }
+
+    /// Adds a rendered book page, split at its headings
+    pub(crate) fn add_page(&mut self, slug: &str, title: &str, url: &str, page: &str) {
+        let mut parts: Vec<(String, &str)> = vec![];
+        let mut start = 0;
+        let mut anchor = String::new();
+        let mut fence = false;
+        for (offset, line) in line_offsets(page) {
+            if line.trim_start().starts_with("```") {
+                fence = !fence;
+            }
+            if fence || !line.starts_with('#') {
+                continue;
+            }
+            parts.push((std::mem::take(&mut anchor), &page[start..offset]));
+            anchor = heading_anchor(line.trim_start_matches('#').trim());
+            start = offset;
+        }
+        parts.push((anchor, &page[start..]));
+        parts.retain(|(_, part)| !part.trim().is_empty());
+        self.add(format!("book/{slug}"), title, url, parts);
+    }
+
+    fn add(&mut self, key: String, title: &str, url: &str, parts: Vec<(String, &str)>) {
+        let mut sections = vec![];
+        for (anchor, part) in parts {
+            let text = plain_text(part, self.options.skip_code);
+            if text.body.is_empty() && text.heading.is_empty() {
+                continue;
+            }
+            let index = sections.len();
+            let mut words: Vec<_> = terms(&text.heading).chain(terms(&text.body)).collect();
+            words.sort();
+            words.dedup();
+            for word in words {
+                self.terms
+                    .entry(word)
+                    .or_default()
+                    .push((key.clone(), index));
+            }
+            sections.push(Section {
+                anchor,
+                snippet: text.body.chars().take(SNIPPET_LENGTH).collect(),
+                heading: text.heading,
+            });
+        }
+        self.sources.insert(
+            key,
+            Source {
+                title: title.to_string(),
+                url: url.to_string(),
+                sections,
+            },
+        );
+    }
+
+    /// Writes the index and the search page into the output root
+    pub(crate) fn write(&self, out_dir: &Path) -> Result<(), SearchIndexError> {
+        let json = serde_json::to_string(self)
+            .into_report()
+            .change_context(SearchIndexError::default())?;
+        out_dir.join("search-index.json").write_text_file(json)?;
+        out_dir.join("search.html").write_text_file(SEARCH_PAGE)?;
+        Ok(())
+    }
+}
+
+struct PlainText {
+    heading: String,
+    body: String,
+}
+
+/// The text of a slide or page section without markdown and HTML markup. The first heading
+/// is returned separately. HTML comments, which hold the speaker notes of slides, are left out.
+fn plain_text(markdown: &str, skip_code: bool) -> PlainText {
+    let mut heading = String::new();
+    let mut body = String::new();
+    let mut fence = false;
+    let mut comment = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = !fence;
+            continue;
+        }
+        if fence {
+            if !skip_code {
+                body.push_str(trimmed);
+                body.push(' ');
+            }
+            continue;
+        }
+        if comment || trimmed.starts_with("<!--") {
+            comment = !trimmed.ends_with("-->");
+            continue;
+        }
+        let is_heading = trimmed.starts_with('#');
+        let text = strip_markup(trimmed.trim_start_matches(['#', '>', '-']));
+        if is_heading && heading.is_empty() {
+            heading = text;
+        } else if !text.is_empty() {
+            body.push_str(&text);
+            body.push(' ');
+        }
+    }
+    PlainText {
+        heading,
+        body: body.trim_end().to_string(),
+    }
+}
+
+/// Removes HTML tags, link targets and markdown punctuation from a line
+fn strip_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' if chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '/') =>
+            {
+                for c in chars.by_ref() {
+                    if c == '>' {
+                        break;
+                    }
+                }
+            }
+            // The target of a link or image
+            ']' if chars.peek() == Some(&'(') => {
+                for c in chars.by_ref() {
+                    if c == ')' {
+                        break;
+                    }
+                }
+            }
+            '!' if chars.peek() == Some(&'[') => {}
+            '*' | '`' | '[' | ']' | '|' => {}
+            c => text.push(c),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The lowercase words of two or more characters in a text
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(|word| word.trim_matches('_'))
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase)
+}
+
+/// The lines of a text, with their byte offset
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.trim_end_matches('\n')))
+    })
+}
@@ -0,0 +1,45 @@
+//! SHA-256 hashes, with the [`sha2`] crate, for what has to tell any change of content: the
+//! hashes of lockfiles, of the files modmod wrote into an output folder, and of the inputs
+//! and outputs of the render cache. Hashes that only have to be stable, like those in slugs and ids, use
+//! the faster FNV-1a of [`crate::update`].
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::io;
+
+/// The SHA-256 hash of `content`, as lowercase hex
+pub(crate) fn hash(content: &[u8]) -> String {
+    hex(&Sha256::digest(content))
+}
+
+/// Like [`hash`], for the content of a file, which is read in chunks rather than at once
+pub(crate) fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut hash = Sha256::new();
+    io::read_chunks(path, |chunk| hash.update(chunk))?;
+    Ok(hex(&hash.finalize()))
+}
+
+/// `digest` as lowercase hex
+pub(crate) fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors() {
+        // From the examples of FIPS 180-4
+        assert_eq!(
+            hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! The `sitemap.xml` and `robots.txt` of the deployed site, written into the root of the
+//! output with the deploy workflow and the container, which copy them into the site.
+//!
+//! The sitemap lists the landing page, the decks and the pages of the book at the URLs they
+//! are deployed at, which are made from the base URL of the render. Sitemaps need absolute
+//! URLs, so without an origin in the base URL only `robots.txt` is written. The date an
+//! entry was last modified is when it was last rendered with other content, which the render
+//! manifest keeps track of, see [`crate::manifest::Artifact::updated_at`].
+//!
+//! Tracks of courses that must not show up in search engines set `indexable = false`: their
+//! `robots.txt` disallows the whole site, there is no sitemap, and the landing page asks not
+//! to be indexed. Crawlers only read `robots.txt` at the root of a host, so for a site that
+//! is deployed under a subpath, it has to be copied there.
+
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    cartridge::escape,
+    io::PathExt,
+    manifest::{self, ArtifactKind, RenderManifest},
+    report::{Warning, WarningCode},
+    scaffold::GENERATED,
+    slides::WrittenDeck,
+    url::BaseUrl,
+    LoadTrackError, OutputLayout, Track,
+};
+
+/// Name of the sitemap in the root of the output
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+
+/// Name of the file that tells crawlers what they may index, in the root of the output
+pub const ROBOTS_FILE: &str = "robots.txt";
+
+impl Track {
+    /// Writes [`ROBOTS_FILE`] into `out_dir`, and [`SITEMAP_FILE`] if the site may be indexed
+    /// and `base_url` has an origin. Returns the names of the files that were written.
+    pub(crate) fn write_sitemap(
+        &self,
+        out_dir: &Path,
+        layout: &OutputLayout,
+        base_url: &BaseUrl,
+        decks: &[WrittenDeck],
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vec<&'static str>, LoadTrackError> {
+        let sitemap = match (self.indexable, base_url.origin()) {
+            (false, _) => None,
+            (true, None) => {
+                warnings.push(Warning::new(
+                    WarningCode::SitemapSkipped,
+                    format!(
+                        "No {SITEMAP_FILE} is written, as the base URL {} has no origin, like https://example.com, that the URLs in it need",
+                        base_url.path()
+                    ),
+                    None,
+                ));
+                None
+            }
+            (true, Some(_)) => {
+                let manifest = RenderManifest::read(out_dir)
+                    .change_context(LoadTrackError)?
+                    .unwrap_or_else(|| RenderManifest::new(&self.name));
+                Some(sitemap(&manifest, layout, base_url, decks))
+            }
+        };
+
+        let mut robots = format!("# {GENERATED}\nUser-agent: *\n");
+        match self.indexable {
+            true => robots.push_str("Allow: /\n"),
+            false => robots.push_str(&format!("Disallow: {}\n", base_url.path())),
+        }
+        if sitemap.is_some() {
+            robots.push_str(&format!("\nSitemap: {}\n", base_url.join(SITEMAP_FILE)));
+        }
+        out_dir.join(ROBOTS_FILE).write_text_file(robots)?;
+        let mut written = vec![ROBOTS_FILE];
+        if let Some(sitemap) = sitemap {
+            out_dir.join(SITEMAP_FILE).write_text_file(sitemap)?;
+            written.push(SITEMAP_FILE);
+        }
+        Ok(written)
+    }
+}
+
+/// The sitemap of the decks and book chapters in `manifest`
+fn sitemap(
+    manifest: &RenderManifest,
+    layout: &OutputLayout,
+    base_url: &BaseUrl,
+    decks: &[WrittenDeck],
+) -> String {
+    // The URL of each entry, with when it was last modified, if known
+    let mut entries: Vec<(String, Option<&str>)> = vec![];
+    let updated_at = |kind: ArtifactKind, path: &Path| {
+        let path = manifest::relative_path(path);
+        manifest
+            .artifacts
+            .iter()
+            .find(|a| a.kind == kind && a.path == path)
+            .and_then(|a| a.updated_at.as_deref())
+    };
+    for deck in decks {
+        let path = layout.slides().join(&deck.slug).with_extension("md");
+        entries.push((
+            layout.deck_url(base_url, &deck.prefix),
+            updated_at(ArtifactKind::Deck, &path),
+        ));
+    }
+    let chapters: Vec<_> = manifest
+        .main_artifacts()
+        .filter(|a| a.kind == ArtifactKind::Chapter)
+        .collect();
+    if !chapters.is_empty() {
+        entries.push((
+            base_url.join(&OutputLayout::dir_url_path(layout.book())),
+            chapters
+                .iter()
+                .filter_map(|c| c.updated_at.as_deref())
+                .max(),
+        ));
+    }
+    for chapter in chapters {
+        let Some(page) = Path::new(&chapter.path).file_stem() else {
+            continue;
+        };
+        entries.push((
+            base_url.join(&(OutputLayout::url_path(&layout.book().join(page)) + ".html")),
+            chapter.updated_at.as_deref(),
+        ));
+    }
+    // The landing page changes with what it links to
+    let landing = (
+        base_url.join(""),
+        entries.iter().filter_map(|(_, updated)| *updated).max(),
+    );
+    entries.insert(0, landing);
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- {GENERATED} -->
+<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">
+"
+    );
+    for (url, updated) in entries {
+        xml.push_str(&format!("  <url>\n    <loc>{}</loc>\n", escape(&url)));
+        if let Some(updated) = updated {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", escape(updated)));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
@@ -1,20 +1,56 @@
 #![allow(dead_code)]
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fmt::{self, Write};
-use std::path::Path;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
 
-use error_stack::{IntoReport, Result, ResultExt};
+use error_stack::{IntoReport, Report, Result, ResultExt};
 use serde_json::Value as JsonValue;
 
 type JsonObject = serde_json::Map<String, JsonValue>;
 
 use crate::{
-    io::{PathExt, WriteExt},
+    analytics::{Analytics, SLIDES_INDEX_FILE},
+    cache::{self, Inputs},
+    check::{self, MarkdownRef, RefKind},
+    conditional::{self, Conditions},
+    diagnostic::Location,
+    estimates::Estimate,
+    frontmatter,
+    io::{copy_tree, same_bytes, slash_path, CopyTreeOptions, OnConflict, PathExt},
+    json, parallel,
+    progress::{self, Phase},
+    qr::{QrCode, QR_DIR},
+    report::{self, UnitObjective, Warning, WarningCode},
+    suggest,
+    template::{PlaceholderUse, RenderedDeckSource, TemplateInspection},
     to_prefixed_tag, to_tag,
+    update::{fnv1a, FNV_OFFSET},
+    url::BaseUrl,
+    version::CourseVersion,
+    Course, Exercise, License, ModuleLabel, OutputLayout, TopicContent,
 };
 
 const PACKAGE_JSON_CONTENT_STUB: &str = include_str!("../include/slides/package.json");
+// The built-in stub is parsed when the slides are rendered, this catches the worst of
+// breaking it at compile time
+const _: () = assert!(
+    !PACKAGE_JSON_CONTENT_STUB.is_empty() && PACKAGE_JSON_CONTENT_STUB.as_bytes()[0] == b'{',
+    "The built-in package.json stub must be a JSON object"
+);
+/// Folder of the slides package that theme folders are copied into
+pub const THEMES_DIR: &str = "themes";
 const SLIDES_TEMPLATE_DEFAULT: &str = include_str!("../include/slides/default.md");
 
+/// Name of the deck that combines all decks of the package, in the slides folder
+pub(crate) const COMBINED_DECK_FILE: &str = "all.md";
+
 #[derive(Debug, Default)]
 #[non_exhaustive]
 pub struct RenderSlidesError {}
@@ -28,16 +64,39 @@ impl fmt::Display for RenderSlidesError {
 impl error_stack::Context for RenderSlidesError {}
 
 pub struct SlidesRenderOptions<'t, 'u, P: AsRef<Path>> {
+    /// The Slidev theme of the decks that don't set their own: the name of a theme package,
+    /// or the path of a theme folder if it starts with `.` or is absolute. Theme folders are
+    /// copied into [`THEMES_DIR`] of the slides package, which depends on them.
     pub theme: &'t str,
     pub package_json: Option<P>,
-    pub url_base: &'u str,
+    /// The URL the output folder is deployed at
+    pub base_url: &'u BaseUrl,
+    /// Also write the decks into one, [`COMBINED_DECK_FILE`], with an `export-all` script
+    pub export_combined: bool,
+    /// The locale the decks are rendered in, if it isn't the one of the track. The package
+    /// is named after it as well, so that it doesn't clash with the package of another locale.
+    pub locale: Option<&'t str>,
 }
 
 #[derive(Debug)]
 pub struct SlidesPackage<'track> {
     /// Name of the package, corresponds to the name of the track
     name: &'track str,
+    /// Template for decks that don't have their own
+    default_template: Option<&'track Path>,
+    course: Option<&'track Course>,
+    license: Option<&'track License>,
+    course_version: Option<&'track CourseVersion>,
+    analytics: Option<&'track Analytics>,
+    /// The QR codes of the decks, by their prefix
+    qr_codes: HashMap<String, Vec<QrCode>>,
+    /// The folders of the exercises in the output, and their URLs, by the path of the exercise
+    exercise_folders: HashMap<PathBuf, (String, String)>,
+    /// Whether near-duplicate objectives of a unit are listed once, like exact duplicates
+    merge_similar_objectives: bool,
     decks: Vec<SlideDeck<'track>>,
+    /// The content of the topic files the decks read
+    topic_files: TopicFiles,
 }
 
 impl<'track> SlidesPackage<'track> {
@@ -45,7 +104,16 @@ impl<'track> SlidesPackage<'track> {
         SlidesPackageBuilder {
             package: SlidesPackage {
                 name,
+                default_template: None,
+                course: None,
+                license: None,
+                course_version: None,
+                analytics: None,
+                qr_codes: HashMap::new(),
+                exercise_folders: HashMap::new(),
+                merge_similar_objectives: false,
                 decks: vec![],
+                topic_files: TopicFiles::default(),
             },
         }
     }
@@ -56,69 +124,231 @@ impl<'track> SlidesPackage<'track> {
         SlidesRenderOptions {
             theme,
             package_json,
-            url_base,
+            base_url,
+            export_combined,
+            locale,
         }: SlidesRenderOptions<'_, '_, P>,
-    ) -> Result<(), RenderSlidesError> {
+        layout: &OutputLayout,
+        conditions: &Conditions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vec<WrittenDeck>, RenderSlidesError> {
         let mut package_json: JsonObject = match package_json {
-            Some(p) => serde_json::from_str(&p.read_to_string()?)
+            Some(p) => read_package_json_stub(p.as_ref())?,
+            None => serde_json::from_str(PACKAGE_JSON_CONTENT_STUB)
                 .into_report()
-                .change_context(RenderSlidesError::default())?,
-            None => serde_json::from_str(PACKAGE_JSON_CONTENT_STUB).unwrap(),
+                .change_context(RenderSlidesError::default())
+                .attach_printable("The built-in package.json stub of modmod is invalid")?,
         };
 
-        package_json.insert("name".into(), to_tag(self.name).into());
-        let mut package_scripts = JsonObject::new();
+        let name = match locale {
+            Some(locale) => to_tag(format!("{} {locale}", self.name)),
+            None => to_tag(self.name),
+        };
+        package_json.insert("name".into(), name.into());
+        if let Some(license) = self.license {
+            package_json.insert("license".into(), license.spdx.as_str().into());
+        }
+        // Record what generated the package, to help diagnose version mismatches
+        package_json.insert(
+            "modmod".into(),
+            serde_json::json!({ "version": crate::VERSION }),
+        );
+        // The scripts of the stub come first, and were checked to be strings
+        let mut package_scripts = match package_json.remove("scripts") {
+            Some(JsonValue::Object(scripts)) => scripts,
+            _ => JsonObject::new(),
+        };
 
         let output_dir = out_dir.as_ref();
-        let slides_output_dir = output_dir.join("slides");
+        let slides_output_dir = output_dir.join(layout.slides());
         slides_output_dir.create_dir_all()?;
 
-        let slide_images_dir = slides_output_dir.join("images");
+        // Theme folders are copied into the package, which depends on those that are a package
+        let mut themes = vec![];
+        for (folder, name) in self.theme_folders(theme)? {
+            let to = slides_output_dir.join(THEMES_DIR).join(&name);
+            copy_tree::<RenderSlidesError>(
+                &folder,
+                &to,
+                &CopyTreeOptions {
+                    recursive: true,
+                    exclude: &["node_modules", "node_modules/**"],
+                    preserve_structure: true,
+                    ..Default::default()
+                },
+            )
+            .attach_printable_lazy(|| format!("Copying slide theme {}", folder.display()))?;
+            if let Some(package) = theme_package_name(&folder) {
+                themes.push((package, format!("file:./{THEMES_DIR}/{name}")));
+            }
+        }
+        if !themes.is_empty() {
+            let dependencies = package_json
+                .entry("dependencies")
+                .or_insert_with(|| JsonObject::new().into());
+            if let JsonValue::Object(dependencies) = dependencies {
+                for (package, path) in themes {
+                    dependencies.insert(package, path.into());
+                }
+            }
+        }
+
+        let slide_images_dir = slides_output_dir.join(layout.images());
         slide_images_dir.create_dir_all()?;
-        let url_base = url_base.trim_matches('/');
-        let url_base_separator = if url_base.is_empty() { "" } else { "/" };
+        let images_url = images_url(layout);
+        let image_names = self.image_names();
+        let deck_slugs = Mutex::new(HashSet::new());
 
-        for deck in self.decks.iter() {
-            let deck_prefix = format!("{}_{}", deck.module_index, deck.unit_index);
-            let deck_slug = to_prefixed_tag(deck.name, &deck_prefix);
+        let _phase = progress::start(Phase::Slides, Some(self.decks.len()));
+        // The decks are written on their own, and what they share is put together
+        // afterwards in the order of the decks, so that it doesn't depend on which deck
+        // finished first
+        // Decks that fail are left out of what they share, and their errors are reported
+        // once that is written
+        let mut errors = None;
+        let mut rendered = vec![];
+        for result in parallel::map_each(&self.decks, |deck| {
+            progress::advance(Phase::Slides, deck.name);
+            let timing = progress::time(Phase::Slides, deck.name);
+            let deck_prefix = deck.prefix();
+            let deck_slug = deck.slug();
+            // Deck names are made unique when the track is loaded
+            debug_assert!(
+                deck_slugs
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(deck_slug.clone()),
+                "Duplicate slide deck name {deck_slug}"
+            );
             let deck_output = slides_output_dir.join(&deck_slug).with_extension("md");
-            let mut unit_content = String::new();
-            let mut unit_objectives = String::new();
-            let mut unit_summary = String::new();
-
-            for section in deck.sections.iter() {
-                let topic_content = section.content.read_to_string()?;
-                let topic_content = topic_content.trim();
+            let mut warnings = vec![];
+            let mut template_warnings = vec![];
+            let values = self
+                .deck_values(deck, theme, conditions, &mut warnings)
+                .attach_printable_lazy(|| deck.describe())?;
+            if values.is_empty() {
+                return Ok(None);
+            }
+            // The warnings about the values come up whether the deck is written or not
+            let values_warnings = warnings.len();
 
-                if !topic_content.is_empty() {
-                    if !topic_content.starts_with("---") {
-                        unit_content.write_str("---\n\n").unwrap();
-                    }
-                    unit_content.write_str(topic_content).unwrap();
-                    unit_content.write_str("\n").unwrap();
-                }
+            // The deck is skipped if it's filled in with the same values and template as
+            // when it was last rendered
+            let template = deck.template.or(self.default_template);
+            let mut inputs = Inputs::new(&deck_output).value(&values).value(&(
+                &images_url,
+                &image_names,
+                self.course,
+            ));
+            if let Some(template) = template {
+                inputs = inputs
+                    .file(template)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error reading slide template {}", template.display())
+                    })
+                    .change_context(RenderSlidesError::default())
+                    .attach_printable_lazy(|| deck.describe())?;
+            }
+            let rendered_deck = |warnings, template_warnings| RenderedDeck {
+                deck,
+                prefix: deck_prefix.clone(),
+                slug: deck_slug.clone(),
+                output: deck_output.clone(),
+                warnings,
+                template_warnings,
+            };
+            if let Some(mut cached) = cache::cached(&inputs) {
+                let template_warnings = cached.pop().unwrap_or_default();
+                warnings.extend(cached.into_iter().flatten());
+                return Ok(Some(rendered_deck(warnings, template_warnings)));
+            }
 
-                for objective in section.objectives.iter() {
-                    unit_objectives += &format!("- {}\n", objective.trim());
-                }
+            tracing::info!(
+                kind = "deck",
+                unit = %deck.name,
+                path = %deck_output.display(),
+                "Rendering deck {}",
+                deck_output.display()
+            );
+            let writing = || format!("While writing the deck to {}", deck_output.display());
+            let mut file = deck_output
+                .create_text_file()
+                .attach_printable_lazy(writing)
+                .attach_printable_lazy(|| deck.describe())?;
+            let bytes = self
+                .write_deck(
+                    &values,
+                    (&images_url, &image_names),
+                    &deck_output,
+                    io::BufWriter::new(&mut file),
+                    (&mut warnings, &mut template_warnings),
+                )
+                .attach_printable_lazy(|| deck.describe())?;
+            timing.record("path", deck_output.display());
+            timing.record("bytes", bytes);
+            file.commit()
+                .attach_printable_lazy(writing)
+                .attach_printable_lazy(|| deck.describe())?;
+            cache::record(
+                inputs,
+                std::slice::from_ref(&deck_output),
+                &[&warnings[values_warnings..], &template_warnings],
+            );
+            Ok(Some(rendered_deck(warnings, template_warnings)))
+        }) {
+            match result {
+                Ok(deck) => rendered.extend(deck),
+                Err(error) => parallel::push_error(&mut errors, error)?,
+            }
+        }
 
-                for item in section.summary.iter() {
-                    unit_summary += &format!("- {}\n", item.trim());
+        // Maps the file names of images in the output to the image they were copied from
+        let mut copied_images: HashMap<&OsStr, &Path> = HashMap::new();
+        let mut image_copies = vec![];
+        // Other images with a name that is taken, which are copied after the first so that
+        // conflicts are reported the same on every run
+        let mut same_name_copies: Vec<(&Path, &OsStr, PathBuf)> = vec![];
+        for RenderedDeck {
+            deck,
+            prefix: deck_prefix,
+            slug: deck_slug,
+            output: deck_output,
+            warnings: deck_warnings,
+            template_warnings,
+        } in rendered.iter()
+        {
+            warnings.extend(deck_warnings.iter().cloned());
+            if let Some(codes) = self.qr_codes.get(deck_prefix) {
+                let qr_dir = slide_images_dir.join(QR_DIR);
+                qr_dir.create_dir_all()?;
+                for code in codes {
+                    qr_dir
+                        .join(code.file_name(deck_prefix))
+                        .write_text_file(&code.svg)?;
                 }
             }
-
-            if unit_content.is_empty() && unit_objectives.is_empty() && unit_summary.is_empty() {
-                continue;
+            // Templates are shared between decks, so their warnings are reported once
+            for warning in template_warnings {
+                if !warnings
+                    .iter()
+                    .any(|w| w.message == warning.message && w.source == warning.source)
+                {
+                    warnings.push(warning.clone());
+                }
             }
 
-            let mut deck_file = deck_output.create_file()?;
-
             {
                 let deck_output_str = deck_output
                     .strip_prefix(&slides_output_dir)
-                    .unwrap()
-                    .to_str()
-                    .unwrap();
+                    .map_err(|_| {
+                        Report::new(RenderSlidesError::default()).attach_printable(format!(
+                            "Deck {} isn't in the slides folder {}",
+                            deck_output.display(),
+                            slides_output_dir.display()
+                        ))
+                    })
+                    .and_then(slash_path)?;
 
                 package_scripts.insert(
                     format!("dev-{deck_prefix}"),
@@ -127,8 +357,12 @@ impl<'track> SlidesPackage<'track> {
 
                 package_scripts.insert(
                     format!("build-{deck_prefix}"),
-                    format!("slidev build --download --out dist/{deck_slug} --base /{url_base}{url_base_separator}slides/{}_{}/ {deck_output_str}", deck.module_index, deck.unit_index)
-                        .into(),
+                    format!(
+                        "slidev build --download --out {} --base {} {deck_output_str}",
+                        OutputLayout::url_path(&layout.dist().join(deck_slug)),
+                        layout.deck_url(base_url, deck_prefix),
+                    )
+                    .into(),
                 );
                 package_scripts.insert(
                     format!("export-{deck_prefix}"),
@@ -136,41 +370,1649 @@ impl<'track> SlidesPackage<'track> {
                 );
             }
 
+            report::record_assets(
+                &crate::manifest::relative_path(
+                    deck_output.strip_prefix(output_dir).unwrap_or(deck_output),
+                ),
+                deck.sections.iter().flat_map(|s| s.images.iter().copied()),
+            );
             for section in deck.sections.iter() {
-                section
+                for (path, name) in section
                     .images
                     .iter()
-                    .filter_map(|path| path.file_name().map(|name| (path, name)))
-                    .try_for_each(|(path, name)| path.copy(slide_images_dir.join(name)))?;
+                    .filter_map(|path| path.file_name().map(|name| (*path, name)))
+                {
+                    // All images end up in the same folder, so they can only share a name
+                    // if they have the same content
+                    match copied_images.get(name) {
+                        Some(&first) if first == path => continue,
+                        Some(_) => same_name_copies.push((path, name, slide_images_dir.join(name))),
+                        None => {
+                            image_copies.push((path, slide_images_dir.join(name)));
+                            copied_images.insert(name, path);
+                        }
+                    }
+                }
             }
+        }
+        // Images with a name that is taken are usually copies of the same image, like a
+        // diagram that several topics have. They are compared with the first image of their
+        // name in parallel, and only those with other content are copied, where they
+        // conflict with it.
+        if !same_name_copies.is_empty() {
+            let same = parallel::map(&same_name_copies, |(path, name, _)| {
+                let Some(first) = copied_images.get(name) else {
+                    return Ok(false);
+                };
+                same_file(first, path)
+                    .into_report()
+                    .change_context(RenderSlidesError::default())
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Error comparing images at paths {} and {}",
+                            first.display(),
+                            path.display()
+                        )
+                    })
+            })?;
+            let mut same = same.into_iter();
+            same_name_copies.retain(|_| !same.next().unwrap_or_default());
+        }
+        let _images_phase = progress::start(
+            Phase::Images,
+            Some(image_copies.len() + same_name_copies.len()),
+        );
+        let copying_images = || {
+            format!(
+                "While copying the images of the slides to {}",
+                slide_images_dir.display()
+            )
+        };
+        let copy_image = |from: &Path, to: &Path| {
+            let name = to.file_name().unwrap_or_default().to_string_lossy();
+            progress::advance(Phase::Images, &name);
+            let _span =
+                tracing::info_span!("image", label = %name, from = %from.display()).entered();
+            from.copy_with(to, OnConflict::Error).map(|_| ())
+        };
+        if let Err(error) = parallel::map(&image_copies, |(from, to)| copy_image(from, to)) {
+            parallel::push_error(&mut errors, error.attach_printable(copying_images()))?;
+        }
+        for (from, _, to) in same_name_copies {
+            if let Err(error) = copy_image(from, &to) {
+                parallel::push_error(&mut errors, error.attach_printable(copying_images()))?;
+            }
+        }
 
-            let template_content = deck
-                .template
-                .map(|t| t.read_to_string())
-                .unwrap_or(Ok(SLIDES_TEMPLATE_DEFAULT.to_string()))?;
-            let slides_content = template_content
-                .replace("#[modmod:mod_title]", deck.module_name)
-                .replace("#[modmod:mod_index]", &deck.module_index.to_string())
-                .replace("#[modmod:unit_index]", &deck.unit_index.to_string())
-                .replace("#[modmod:unit_title]", deck.name)
-                .replace("#[modmod:content]", &unit_content)
-                .replace("#[modmod:objectives]", &unit_objectives)
-                .replace("#[modmod:summary]", &unit_summary)
-                .replace("#[modmod:theme]", theme);
-
-            deck_file.write_all(slides_content)?;
+        // Read back from the decks that rendered, as those that are cached aren't rendered
+        // again. Without writes, there are no decks to read.
+        if export_combined && !rendered.is_empty() && !crate::io::writes_discarded_at(output_dir) {
+            let decks = rendered
+                .iter()
+                .map(|deck| deck.output.read_to_string())
+                .collect::<Result<Vec<String>, RenderSlidesError>>()?;
+            slides_output_dir
+                .join(COMBINED_DECK_FILE)
+                .write_text_file(combined_deck(self.name, &decks))?;
+            package_scripts.insert(
+                "export-all".into(),
+                format!("slidev export {COMBINED_DECK_FILE}").into(),
+            );
         }
 
-        // Add underscore key, so that preceding lines can have a trailing comma
-        package_scripts.insert("_".into(), "".into());
         package_json.insert("scripts".into(), package_scripts.into());
-        let package_json = serde_json::to_string_pretty(&package_json).unwrap();
-        let package_json_file = slides_output_dir.join("package.json");
-        let mut package_json_file = package_json_file.create_file()?;
-        package_json_file.write_all(package_json)?;
+        let package_json = serde_json::to_string_pretty(&package_json)
+            .into_report()
+            .change_context(RenderSlidesError::default())
+            .attach_printable("Unable to serialize the package.json of the slides")?;
+        let package_json_path = slides_output_dir.join("package.json");
+        package_json_path
+            .write_text_file(package_json)
+            .attach_printable_lazy(|| format!("While writing {}", package_json_path.display()))?;
+        if let Some(analytics) = self.analytics {
+            slides_output_dir
+                .join(SLIDES_INDEX_FILE)
+                .write_text_file(analytics.slides_index())?;
+        }
 
-        Ok(())
+        match errors {
+            Some(errors) => Err(errors.attach_printable(format!(
+                "The package.json and images of the slides were written for the {} deck(s) that rendered",
+                rendered.len()
+            ))),
+            None => Ok(rendered
+                .iter()
+                .map(|deck| WrittenDeck {
+                    name: deck.deck.name.to_string(),
+                    prefix: deck.prefix.clone(),
+                    slug: deck.slug.clone(),
+                })
+                .collect()),
+        }
+    }
+}
+
+impl<'track> SlidesPackage<'track> {
+    /// Writes the markdown of the deck to `out`: its template with the placeholders filled
+    /// in, and the images referred to under `images_url`. Fails if it refers to images that
+    /// aren't one of the `image_names` of the package, before the deck is committed. The template is split at its
+    /// placeholders once, and their values are written as they are, so that the deck
+    /// isn't copied for each placeholder. Warnings about the deck go to the first list,
+    /// and those about its template, which other decks may share, to the second. Returns
+    /// the number of bytes that were written.
+    fn write_deck(
+        &self,
+        values: &DeckValues,
+        (images_url, image_names): (&str, &BTreeSet<String>),
+        deck_output: &Path,
+        out: impl io::Write,
+        (warnings, template_warnings): (&mut Vec<Warning>, &mut Vec<Warning>),
+    ) -> Result<usize, RenderSlidesError> {
+        let template = values.deck.template.or(self.default_template);
+        let template_content = template
+            .map(|t| t.read_to_string())
+            .unwrap_or(Ok(SLIDES_TEMPLATE_DEFAULT.to_string()))?;
+        let segments = self.template_segments(&template_content, template, template_warnings)?;
+
+        // The placeholders that do something when they are empty are filled in first, to
+        // know whether they are
+        let filled: Vec<Option<String>> = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Deck {
+                    placeholder,
+                    when_empty,
+                    ..
+                } if *when_empty != WhenEmpty::Nothing => Some(placeholder.value(values)),
+                _ => None,
+            })
+            .collect();
+        let mut emptied_slides = vec![];
+        for (i, (segment, filled)) in segments.iter().zip(filled.iter()).enumerate() {
+            let (value, marker, when_empty) = match (segment, filled) {
+                (
+                    Segment::Deck {
+                        marker, when_empty, ..
+                    },
+                    Some(value),
+                ) => (value, marker, when_empty),
+                (
+                    Segment::Course {
+                        value,
+                        marker,
+                        when_empty,
+                    },
+                    _,
+                ) => (value, marker, when_empty),
+                _ => continue,
+            };
+            if !value.trim().is_empty() {
+                continue;
+            }
+            match when_empty {
+                WhenEmpty::Nothing => {}
+                WhenEmpty::Fail => {
+                    let template = match template {
+                        Some(template) => template.display().to_string(),
+                        None => "built-in".to_string(),
+                    };
+                    return Err(Report::new(RenderSlidesError::default()).attach_printable(
+                        format!(
+                            "The {template} slide template requires {marker} to be filled in, but it's empty for this deck"
+                        ),
+                    ));
+                }
+                WhenEmpty::RemoveSlide => emptied_slides.push(i),
+            }
+        }
+        let write_segment = |i: usize, segment: &Segment, out: &mut dyn Write| match segment {
+            Segment::Text(text) => out.write_str(text),
+            Segment::Course { value, .. } => out.write_str(value),
+            Segment::Deck { placeholder, .. } => match &filled[i] {
+                Some(value) => out.write_str(value),
+                None => (placeholder.write)(values, out),
+            },
+        };
+
+        let mut writer = DeckWriter {
+            out,
+            images_url,
+            image_names,
+            output: deck_output,
+            line: 1,
+            bytes: 0,
+            warnings,
+            error: None,
+            missing: vec![],
+            labels: vec![],
+            definitions: HashMap::new(),
+        };
+        let written = if emptied_slides.is_empty() {
+            segments
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, segment)| write_segment(i, segment, &mut writer))
+        } else {
+            // The deck is put together first, to find the slides the empty placeholders
+            // are on
+            let mut deck = String::new();
+            let mut offsets = vec![];
+            segments
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, segment)| {
+                    if emptied_slides.contains(&i) {
+                        offsets.push(deck.len());
+                    }
+                    write_segment(i, segment, &mut deck)
+                })
+                .and_then(|()| writer.write_str(&remove_slides(&deck, &offsets)))
+        };
+        values.take_error()?;
+        writer.missing_images()?;
+        let flushed = match (written, writer.error.take()) {
+            (_, Some(e)) => Err(e),
+            (Err(_), None) => Err(io::Error::other("formatting failed")),
+            (Ok(()), None) => writer.out.flush(),
+        };
+        flushed
+            .map(|()| writer.bytes)
+            .into_report()
+            .attach_printable_lazy(|| format!("Error writing deck {}", deck_output.display()))
+            .change_context(RenderSlidesError::default())
+    }
+
+    /// The deck selected by `selector`: its number like `3.2`, its prefix like `3_2`, or the
+    /// id of its unit
+    fn find_deck(&self, selector: &str) -> Result<&SlideDeck<'track>, RenderSlidesError> {
+        let number = |deck: &SlideDeck| deck.module_label.qualify(deck.unit_index);
+        self.decks
+            .iter()
+            .find(|deck| {
+                let prefix = format!("{}_{}", deck.module_label.path, deck.unit_index);
+                [number(deck), prefix, to_tag(deck.name)].contains(&selector.to_string())
+            })
+            .ok_or_else(|| {
+                let decks: Vec<_> = self.decks.iter().map(number).collect();
+                Report::new(RenderSlidesError::default())
+                    .attach_printable(format!(
+                        "No deck '{selector}'.{}",
+                        suggest::did_you_mean(
+                            selector,
+                            self.decks.iter().map(|deck| to_tag(deck.name))
+                        )
+                    ))
+                    .attach_printable(format!("The decks are {}", decks.join(", ")))
+            })
+    }
+
+    /// Renders only the deck selected by `selector`, like [`SlidesPackage::render`] would,
+    /// but returns its markdown instead of writing it, and lists its images instead of
+    /// copying them. See [`Track::render_deck`](crate::Track::render_deck).
+    pub(crate) fn render_deck(
+        &self,
+        selector: &str,
+        theme: &str,
+        layout: &OutputLayout,
+        conditions: &Conditions,
+    ) -> Result<RenderedDeckSource, RenderSlidesError> {
+        let deck = self.find_deck(selector)?;
+        let path = layout.slides().join(deck.slug()).with_extension("md");
+        let mut warnings = vec![];
+        let mut template_warnings = vec![];
+        let values = self
+            .deck_values(deck, theme, conditions, &mut warnings)
+            .attach_printable_lazy(|| deck.describe())?;
+        if values.is_empty() {
+            return Err(
+                Report::new(RenderSlidesError::default()).attach_printable(format!(
+                    "Deck '{selector}' has no content, so rendering leaves it out"
+                )),
+            );
+        }
+        let mut markdown = vec![];
+        self.write_deck(
+            &values,
+            (&images_url(layout), &self.image_names()),
+            &path,
+            &mut markdown,
+            (&mut warnings, &mut template_warnings),
+        )
+        .attach_printable_lazy(|| deck.describe())?;
+        // Only strings were written
+        let markdown = String::from_utf8(markdown)
+            .into_report()
+            .change_context(RenderSlidesError::default())
+            .attach_printable_lazy(|| deck.describe())?;
+        warnings.extend(template_warnings);
+        let mut images: Vec<PathBuf> = vec![];
+        for image in deck.sections.iter().flat_map(|s| s.images.iter()) {
+            if !images.iter().any(|i| i == image) {
+                images.push(image.to_path_buf());
+            }
+        }
+        Ok(RenderedDeckSource {
+            deck: format!(
+                "{} {}",
+                deck.module_label.qualify(deck.unit_index),
+                deck.name
+            ),
+            path,
+            markdown,
+            images,
+            warnings,
+        })
+    }
+
+    /// The names of the images that are copied into the images folder, which the decks
+    /// refer to as `/images/<name>`, including the QR codes in their subfolder
+    fn image_names(&self) -> BTreeSet<String> {
+        let images = self
+            .decks
+            .iter()
+            .flat_map(|d| d.sections.iter())
+            .flat_map(|s| s.images.iter())
+            .filter_map(|image| image.file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        let qr_codes = self.qr_codes.iter().flat_map(|(prefix, codes)| {
+            codes
+                .iter()
+                .map(move |code| format!("{QR_DIR}/{}", code.file_name(prefix)))
+        });
+        images.chain(qr_codes).collect()
+    }
+
+    /// The theme folders of `theme` and the themes of the decks, with the name of the folder
+    /// of [`THEMES_DIR`] each is copied to. Fails on folders that don't exist, and on
+    /// folders with the same name.
+    fn theme_folders(&self, theme: &str) -> Result<Vec<(PathBuf, String)>, RenderSlidesError> {
+        let mut folders: Vec<(PathBuf, String)> = vec![];
+        let themes = std::iter::once(theme).chain(self.decks.iter().filter_map(|d| d.theme));
+        for theme in themes.filter(|t| is_theme_folder(t)) {
+            let folder = Path::new(theme);
+            if !folder.is_dir() {
+                return Err(
+                    Report::new(RenderSlidesError::default()).attach_printable(format!(
+                        "Slide theme folder {} doesn't exist",
+                        folder.display()
+                    )),
+                );
+            }
+            let folder = folder
+                .canonicalize()
+                .into_report()
+                .change_context(RenderSlidesError::default())
+                .attach_printable_lazy(|| {
+                    format!("Unable to find slide theme folder {}", folder.display())
+                })?;
+            let name = theme_folder_name(theme).unwrap_or_default();
+            match folders.iter().find(|(_, n)| *n == name) {
+                Some((other, _)) if *other == folder => {}
+                Some((other, _)) => {
+                    return Err(Report::new(RenderSlidesError::default()).attach_printable(
+                        format!(
+                        "Slide theme folders {} and {} would both be copied to {THEMES_DIR}/{name}",
+                        other.display(),
+                        folder.display()
+                    ),
+                    ));
+                }
+                None => folders.push((folder, name)),
+            }
+        }
+        Ok(folders)
+    }
+
+    /// The values the [`DECK_PLACEHOLDERS`] of `deck` are replaced by
+    fn deck_values<'d>(
+        &'d self,
+        deck: &'d SlideDeck<'track>,
+        theme: &'d str,
+        conditions: &Conditions<'d>,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<DeckValues<'d, 'track>, RenderSlidesError> {
+        let mut unit_content = vec![];
+        let mut unit_summary = String::new();
+        let mut unit_attribution = String::new();
+        for section in deck.sections.iter() {
+            let slides = read_topic_slides(
+                section.content,
+                section.name,
+                conditions,
+                Some(&self.topic_files),
+            )
+            .attach_printable_lazy(|| section.describe())?;
+            unit_content.push(SectionSlides {
+                len: slides.len(),
+                hash: fnv1a(FNV_OFFSET, slides.as_bytes()),
+            });
+            let optional_marker = if section.optional {
+                " *(optional)*"
+            } else {
+                ""
+            };
+
+            for item in section.summary.iter() {
+                unit_summary += &format!("- {}{optional_marker}\n", item.trim());
+            }
+
+            if let Some(attribution) = &section.attribution {
+                unit_attribution += &format!("- {attribution}\n");
+            }
+        }
+
+        let objectives = deck
+            .sections
+            .iter()
+            .flat_map(|section| {
+                section.objectives.iter().map(|text| UnitObjective {
+                    text,
+                    topic: section.name,
+                    optional: section.optional,
+                })
+            })
+            .collect();
+        let mut unit_objectives = String::new();
+        for objective in report::dedupe_objectives(
+            objectives,
+            deck.name,
+            self.merge_similar_objectives,
+            warnings,
+        ) {
+            let optional_marker = if objective.optional {
+                " *(optional)*"
+            } else {
+                ""
+            };
+            unit_objectives += &format!("- {}{optional_marker}\n", objective.text.trim());
+        }
+
+        // Topics of a unit often point to the same material, which is listed once
+        let mut further_reading: Vec<&str> = vec![];
+        for item in deck.sections.iter().flat_map(|s| s.further_reading.iter()) {
+            let item = item.trim();
+            if !item.is_empty() && !further_reading.contains(&item) {
+                further_reading.push(item);
+            }
+        }
+        let further_reading = further_reading
+            .iter()
+            .map(|item| format!("- {item}\n"))
+            .collect();
+
+        Ok(DeckValues {
+            exercises: self.exercises_list(deck, conditions)?,
+            deck,
+            theme: theme_reference(deck.theme.unwrap_or(theme)),
+            conditions: *conditions,
+            topic_files: &self.topic_files,
+            content: unit_content,
+            objectives: unit_objectives,
+            summary: unit_summary,
+            further_reading,
+            attribution: attribution_slide(&unit_attribution, self.license, self.course_version),
+            qr_codes: self
+                .qr_codes
+                .get(&deck.prefix())
+                .map(|codes| qr_slide(codes, &deck.prefix()))
+                .unwrap_or_default(),
+            error: RefCell::new(None),
+        })
+    }
+
+    /// The list that `#[modmod:exercises]` is replaced by in `deck`: the exercises of its unit
+    /// that are in the exercises output, numbered like in the book, each linking to its
+    /// folder there and followed by the first paragraph of its description
+    fn exercises_list(
+        &self,
+        deck: &SlideDeck,
+        conditions: &Conditions,
+    ) -> Result<String, RenderSlidesError> {
+        let mut list = String::new();
+        let exercises = deck.sections.iter().flat_map(|section| {
+            section
+                .exercises
+                .iter()
+                .map(move |exercise| (exercise, section.optional || exercise.optional))
+        });
+        for ((exercise, optional), number) in exercises.zip(1..) {
+            let number = deck
+                .module_label
+                .qualify(format!("{}.{number}", deck.unit_index));
+            let optional_marker = if optional { " *(optional)*" } else { "" };
+            let title = format!("Exercise {number}: {}", exercise.name);
+            // Exercises that aren't in the output have no folder to go to
+            let Some((dir, url)) = self.exercise_folders.get(&exercise.path) else {
+                continue;
+            };
+            list += &format!("- [{title}]({url}){optional_marker}\n");
+            let Some(description) = &exercise.description else {
+                continue;
+            };
+            let in_description = || format!("In exercise description at {}", description.display());
+            let content: String = description
+                .read_to_string()
+                .attach_printable_lazy(in_description)?;
+            let resolved = conditional::resolve(&content, conditions)
+                .change_context(RenderSlidesError::default())
+                .attach_printable_lazy(in_description)
+                .attach_lazy(|| Location::file(description))?;
+            if let Some(paragraph) = first_paragraph(&resolved) {
+                let paragraph = paragraph
+                    .replace("#[modmod:exercise_dir]", dir)
+                    .replace("#[modmod:exercise_ref]", &number);
+                list += &format!("\n  {}\n\n", paragraph.replace('\n', "\n  "));
+            }
+        }
+        if list.ends_with("\n\n") {
+            list.pop();
+        }
+        Ok(list)
+    }
+
+    /// The placeholders of the template of the deck selected by `selector`, see
+    /// [`Track::inspect_template`](crate::Track::inspect_template)
+    pub(crate) fn inspect(
+        &self,
+        selector: &str,
+        theme: &str,
+        conditions: &Conditions,
+    ) -> Result<TemplateInspection, RenderSlidesError> {
+        let number = |deck: &SlideDeck| deck.module_label.qualify(deck.unit_index);
+        let deck = self.find_deck(selector)?;
+
+        let mut warnings = vec![];
+        let values = self
+            .deck_values(deck, theme, conditions, &mut warnings)
+            .attach_printable_lazy(|| deck.describe())?;
+        let template = deck.template.or(self.default_template);
+        let template_content = template
+            .map(|t| t.read_to_string())
+            .unwrap_or(Ok(SLIDES_TEMPLATE_DEFAULT.to_string()))?;
+
+        let default_course = Course::default();
+        let course = self.course.unwrap_or(&default_course);
+        let course_placeholders = Course::FIELDS.iter().map(|field| {
+            let description = format!("The course.{field} of the track");
+            let value = course.field(field).unwrap_or_default();
+            (format!("#[modmod:course_{field}]"), description, value)
+        });
+        let deck_placeholders: Vec<_> = DECK_PLACEHOLDERS
+            .iter()
+            .map(|placeholder| {
+                let value = placeholder.value(&values);
+                (
+                    placeholder.marker(),
+                    placeholder.description.to_string(),
+                    value,
+                )
+            })
+            .collect();
+        values
+            .take_error()
+            .attach_printable_lazy(|| deck.describe())?;
+        let mut placeholders = vec![];
+        for (marker, description, value) in course_placeholders.chain(deck_placeholders) {
+            let forms = WhenEmpty::forms(&marker);
+            let used = forms.iter().any(|form| template_content.contains(form));
+            if used && value.trim().is_empty() {
+                warnings.push(Warning::new(
+                    WarningCode::EmptyPlaceholder,
+                    format!("The template uses {marker}, but it's empty for this deck"),
+                    template,
+                ));
+            }
+            placeholders.push(PlaceholderUse {
+                marker,
+                description,
+                used,
+                value,
+            });
+        }
+        // Placeholders that are left after filling in the known ones don't exist
+        let mut filled = template_content;
+        for placeholder in placeholders.iter() {
+            for form in WhenEmpty::forms(&placeholder.marker) {
+                filled = filled.replace(&form, "");
+            }
+        }
+        if let Some(template) = template {
+            report::leftover_placeholders(&filled, template, &mut warnings);
+        }
+
+        Ok(TemplateInspection {
+            deck: format!("{} {}", number(deck), deck.name),
+            template: template.map(Path::to_path_buf),
+            placeholders,
+            warnings,
+        })
+    }
+
+    /// Splits `template` at the placeholders it uses. The `#[modmod:course_<field>]`
+    /// placeholders of fields that are not set are filled in with an empty string, and
+    /// reported once per template, like a template that doesn't use `#[modmod:content]`.
+    /// Markers in code blocks are left as they are, and fails on markers of placeholders
+    /// that don't exist.
+    fn template_segments<'t>(
+        &self,
+        template: &'t str,
+        path: Option<&Path>,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vec<Segment<'t>>, RenderSlidesError> {
+        const START: &str = "#[modmod:";
+        let default_course = Course::default();
+        let course = self.course.unwrap_or(&default_course);
+        let code = code_blocks(template);
+        let mut segments = vec![];
+        // Where the text before the next placeholder starts, and where to look for it
+        let (mut text_start, mut from) = (0, 0);
+        while let Some(start) = template[from..].find(START).map(|i| from + i) {
+            let after = start + START.len();
+            let Some(end) = template[after..].find(']').map(|i| after + i) else {
+                break;
+            };
+            if code.iter().any(|block| block.contains(&start)) {
+                from = after;
+                continue;
+            }
+            let (name, when_empty) = WhenEmpty::split(&template[after..end]);
+            let marker = &template[start..=end];
+            let segment =
+                if let Some(placeholder) = DECK_PLACEHOLDERS.iter().find(|p| p.name == name) {
+                    Segment::Deck {
+                        placeholder,
+                        marker,
+                        when_empty,
+                    }
+                } else if let Some(field) = name
+                    .strip_prefix("course_")
+                    .filter(|field| Course::FIELDS.contains(field))
+                {
+                    let value = course.field(field).unwrap_or_else(|| {
+                        let warning = Warning::new(
+                            WarningCode::MissingCourseField,
+                            format!(
+                            "Slide template uses #[modmod:{name}], but course.{field} is not set"
+                        ),
+                            path,
+                        );
+                        if !warnings
+                            .iter()
+                            .any(|w| w.message == warning.message && w.source == warning.source)
+                        {
+                            warnings.push(warning);
+                        }
+                        String::new()
+                    });
+                    Segment::Course {
+                        value,
+                        marker,
+                        when_empty,
+                    }
+                } else {
+                    let template_name = match path {
+                        Some(path) => path.display().to_string(),
+                        None => "built-in".to_string(),
+                    };
+                    let names = DECK_PLACEHOLDERS
+                        .iter()
+                        .map(|p| p.name.to_string())
+                        .chain(Course::FIELDS.iter().map(|field| format!("course_{field}")));
+                    return Err(Report::new(RenderSlidesError::default())
+                        .attach_printable(format!(
+                            "Unknown placeholder {marker} in the {template_name} slide template.{}",
+                            suggest::did_you_mean(name, names)
+                        ))
+                        .attach(Location {
+                            path: path.map(Path::to_path_buf),
+                            line: Some(template[..start].matches('\n').count() + 1),
+                            ..Default::default()
+                        }));
+                };
+            segments.push(Segment::Text(&template[text_start..start]));
+            segments.push(segment);
+            (text_start, from) = (end + 1, end + 1);
+        }
+        segments.push(Segment::Text(&template[text_start..]));
+        let has_content = segments.iter().any(|segment| {
+            matches!(segment, Segment::Deck { placeholder, .. } if placeholder.name == "content")
+        });
+        if !has_content {
+            let warning = Warning::new(
+                WarningCode::NoContentPlaceholder,
+                "Slide template doesn't use #[modmod:content], so its decks have none of the slides of their topics",
+                path,
+            );
+            if !warnings
+                .iter()
+                .any(|w| w.source == warning.source && w.code == warning.code)
+            {
+                warnings.push(warning);
+            }
+        }
+        Ok(segments)
+    }
+}
+
+/// Whether `theme` is the path of a theme folder, rather than the name of a theme package
+pub(crate) fn is_theme_folder(theme: &str) -> bool {
+    theme.starts_with('.') || Path::new(theme).is_absolute()
+}
+
+/// The name of the folder of [`THEMES_DIR`] the theme folder `theme` is copied to, or `None`
+/// if `theme` is the name of a theme package
+fn theme_folder_name(theme: &str) -> Option<String> {
+    if !is_theme_folder(theme) {
+        return None;
+    }
+    // The name of `.` is that of the folder it is
+    let path = Path::new(theme);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let name = path.file_name().map(|name| to_tag(name.to_string_lossy()));
+    Some(
+        name.filter(|name| !name.is_empty())
+            .unwrap_or("theme".to_string()),
+    )
+}
+
+/// What the frontmatter of a deck refers to `theme` as: the name of a theme package, or
+/// where a theme folder is copied to, relative to the decks
+fn theme_reference(theme: &str) -> String {
+    match theme_folder_name(theme) {
+        Some(name) => format!("./{THEMES_DIR}/{name}"),
+        None => theme.to_string(),
+    }
+}
+
+/// The package name in the `package.json` of the theme folder at `folder`, if it has one
+fn theme_package_name(folder: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(folder.join("package.json")).ok()?;
+    let package: JsonValue = serde_json::from_str(&content).ok()?;
+    Some(package.get("name")?.as_str()?.to_string())
+}
+
+/// Reads the package.json stub at `path` that the package of the slides is merged into. It
+/// has to be a JSON object without duplicate keys, and its `scripts`, if any, an object of
+/// strings, which the scripts of the decks are added to.
+fn read_package_json_stub(path: &Path) -> Result<JsonObject, RenderSlidesError> {
+    let reading = || format!("While reading the package.json stub at {}", path.display());
+    let content = path.read_to_string().attach_printable_lazy(reading)?;
+    let invalid = |message: String, line: Option<usize>, column: Option<usize>| {
+        Report::new(RenderSlidesError::default())
+            .attach_printable(format!(
+                "Invalid package.json stub at {}: {message}",
+                path.display()
+            ))
+            .attach(Location {
+                path: Some(path.to_path_buf()),
+                line,
+                column,
+                ..Default::default()
+            })
+    };
+    let value = json::from_str(&content)
+        .map_err(|e| invalid(e.to_string(), Some(e.line()), Some(e.column())))?;
+    let expected = |expected: &str, pointer: &str, found: &JsonValue| {
+        invalid(
+            format!(
+                "expected {expected} at {}, but found {}",
+                json::display_pointer(pointer),
+                json::type_name(found)
+            ),
+            None,
+            None,
+        )
+    };
+    let JsonValue::Object(stub) = value else {
+        return Err(expected("an object", "", &value));
+    };
+    match stub.get("scripts") {
+        None => {}
+        Some(JsonValue::Object(scripts)) => {
+            if let Some((name, script)) = scripts.iter().find(|(_, script)| !script.is_string()) {
+                let pointer = json::pointer("/scripts", name);
+                return Err(expected("a string", &pointer, script));
+            }
+        }
+        Some(scripts) => return Err(expected("an object", "/scripts", scripts)),
+    }
+    Ok(stub)
+}
+
+/// Whether the files at `a` and `b` have the same content, which files of another size
+/// don't need to be read for
+fn same_file(a: &Path, b: &Path) -> io::Result<bool> {
+    if std::fs::metadata(a)?.len() != std::fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    same_bytes(std::fs::File::open(a)?, std::fs::File::open(b)?)
+}
+
+/// The URL slides refer to the images folder of the layout by
+fn images_url(layout: &OutputLayout) -> String {
+    match OutputLayout::url_path(layout.images()) {
+        url if url.is_empty() => String::from("/"),
+        url => format!("/{url}/"),
+    }
+}
+
+/// The slides of a topic as they end up in a deck: without the topic frontmatter, with its
+/// conditional blocks resolved, and starting with a slide separator. Empty if the topic has no slides.
+pub(crate) fn topic_slides(
+    content: &TopicContent,
+    name: &str,
+    conditions: &Conditions,
+) -> Result<String, RenderSlidesError> {
+    read_topic_slides(content, name, conditions, None)
+}
+
+/// Like [`topic_slides`], reading the topic file through `files` if given
+fn read_topic_slides(
+    content: &TopicContent,
+    name: &str,
+    conditions: &Conditions,
+    files: Option<&TopicFiles>,
+) -> Result<String, RenderSlidesError> {
+    let topic_content = match (content, files) {
+        (TopicContent::File(path), Some(files)) => files.read(path)?,
+        (TopicContent::File(path), None) => path.read_to_string()?,
+        (TopicContent::Inline(content), _) => content.clone(),
+    };
+    let in_topic = || match content.path() {
+        Some(path) => format!("In topic content at {}", path.display()),
+        None => format!("In inline content of topic '{name}'"),
+    };
+    // Topic metadata in the frontmatter was picked up while loading.
+    // Stripping it goes first, so that a Slidev frontmatter block right
+    // after it is recognized below.
+    let location = || Location {
+        path: content.path().map(Path::to_path_buf),
+        ..Default::default()
+    };
+    let stripped = frontmatter::strip(&topic_content)
+        .change_context(RenderSlidesError::default())
+        .attach_printable_lazy(in_topic)
+        .attach_lazy(location)?;
+    let start = topic_content.len() - stripped.len();
+    let first_line = topic_content[..start].matches('\n').count() + 1;
+    let resolved = conditional::resolve_from_line(stripped, first_line, conditions)
+        .change_context(RenderSlidesError::default())
+        .attach_printable_lazy(in_topic)
+        .attach_lazy(location)?;
+    // Slides without conditional blocks are trimmed in the buffer they were read into,
+    // rather than copied, as topics can be large
+    Ok(match resolved {
+        Cow::Borrowed(_) => separated_slides(topic_content, start),
+        Cow::Owned(resolved) => separated_slides(resolved, 0),
+    })
+}
+
+/// The content of the topic files that the decks of a package are made of, which is read
+/// from disk once for all decks that share a topic. Each deck reads the slides of its topics
+/// twice as well, once to hash them and once to write them. Files are kept up to
+/// [`TopicFiles::BUDGET`] bytes in all, and the ones after that are read each time, so that
+/// the topics of a large track aren't all held in memory.
+///
+/// Decks are rendered in parallel, so the lock is only held to look a file up or keep it:
+/// files are read and copied for a deck outside of it.
+#[derive(Default)]
+struct TopicFiles {
+    /// The files that are kept, and their size in all
+    files: Mutex<(HashMap<PathBuf, Arc<str>>, usize)>,
+}
+
+/// Leaves out the files, as what is kept depends on the order the decks were rendered in,
+/// and [`DeckValues`] are hashed into the inputs of the render cache
+impl fmt::Debug for TopicFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopicFiles").finish_non_exhaustive()
+    }
+}
+
+impl TopicFiles {
+    const BUDGET: usize = 64 * 1024 * 1024;
+
+    /// The content of the topic file at `path`, like [`PathExt::read_to_string`] reads it
+    fn read(&self, path: &Path) -> Result<String, RenderSlidesError> {
+        let files = || self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let kept = files().0.get(path).cloned();
+        if let Some(content) = kept {
+            return Ok(content.to_string());
+        }
+        // Read without holding the lock, so that decks can read different topics at once
+        let content: String = path.read_to_string()?;
+        if content.len() <= Self::BUDGET {
+            let shared = Arc::from(content.as_str());
+            let (kept, size) = &mut *files();
+            if *size + content.len() <= Self::BUDGET && !kept.contains_key(path) {
+                *size += content.len();
+                kept.insert(path.to_path_buf(), shared);
+            }
+        }
+        Ok(content)
+    }
+}
+
+/// The first paragraph of an exercise description, leaving out the headings, HTML comments
+/// and code blocks before it, or `None` if it has none
+fn first_paragraph(description: &str) -> Option<String> {
+    let mut lines = description.lines().map(str::trim_end).peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let closing = match trimmed {
+            _ if trimmed.starts_with("<!--") => Some("-->"),
+            _ if trimmed.starts_with("```") => Some("```"),
+            _ if trimmed.starts_with("~~~") => Some("~~~"),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            // A comment can end on the line it starts on, a code block can't
+            let rest = &trimmed[3..];
+            if !(closing == "-->" && rest.contains(closing)) {
+                for line in lines.by_ref() {
+                    if line.contains(closing) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        let mut paragraph = trimmed.to_string();
+        while let Some(line) = lines.next_if(|l| !l.trim().is_empty()) {
+            paragraph.push('\n');
+            paragraph.push_str(line);
+        }
+        return Some(paragraph);
+    }
+    None
+}
+
+/// `content` from `start` on, trimmed and starting with a slide separator unless it's empty
+fn separated_slides(mut content: String, start: usize) -> String {
+    let end = content.trim_end().len().max(start);
+    content.truncate(end);
+    let leading = content[start..].len() - content[start..].trim_start().len();
+    content.drain(..start + leading);
+    if !content.is_empty() && !content.starts_with("---") {
+        content.insert_str(0, "---\n\n");
+    }
+    content
+}
+
+/// Counts the slides in content that starts with a slide separator, like [`topic_slides`] returns
+pub(crate) fn count_slides(slides: &str) -> usize {
+    split_slides(slides).len()
+}
+
+/// Splits content that starts with a slide separator into the content of its slides.
+/// A separator followed by `key: value` lines and another separator opens a slide with frontmatter,
+/// rather than two slides. The frontmatter is not part of the slide content.
+pub(crate) fn split_slides(slides: &str) -> Vec<&str> {
+    slide_ranges(slides)
+        .into_iter()
+        .map(|(_, body)| &slides[body])
+        .collect()
+}
+
+/// The speaker notes of a slide, as [`split_slides`] returns it: like Slidev takes them, the
+/// HTML comment that the slide ends with, without its markers. `None` if it doesn't end with
+/// one or the comment is empty.
+pub(crate) fn slide_notes(slide: &str) -> Option<&str> {
+    let body = slide.trim_end().strip_suffix("-->")?;
+    let start = body.rfind("<!--")?;
+    // The comment has to open on a line of its own
+    let before = body[..start].trim_end_matches([' ', '\t']);
+    if !before.is_empty() && !before.ends_with('\n') {
+        return None;
+    }
+    let notes = body[start + "<!--".len()..].trim();
+    (!notes.is_empty()).then_some(notes)
+}
+
+/// The text of the first heading of a slide, outside of code blocks
+pub(crate) fn slide_heading(slide: &str) -> Option<&str> {
+    let mut fence = false;
+    for line in slide.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = !fence;
+        } else if !fence && line.starts_with('#') {
+            let text = line.trim_start_matches('#');
+            if text.starts_with(' ') && !text.trim().is_empty() {
+                return Some(text.trim());
+            }
+        }
+    }
+    None
+}
+
+/// Removes the slides of `deck` that the `offsets` are in, like [`split_slides`] finds them.
+/// What comes before the first slide is kept.
+fn remove_slides(deck: &str, offsets: &[usize]) -> String {
+    let slides = slide_ranges(deck);
+    let first = slides.first().map_or(deck.len(), |(start, _)| *start);
+    let mut kept = deck[..first].to_string();
+    for (i, (start, _)) in slides.iter().enumerate() {
+        // An offset at the very end is on the last slide
+        let end = slides.get(i + 1).map(|(next, _)| *next);
+        let on_slide = |offset: &usize| *offset >= *start && end.is_none_or(|end| *offset < end);
+        if !offsets.iter().any(on_slide) {
+            kept.push_str(&deck[*start..end.unwrap_or(deck.len())]);
+        }
+    }
+    kept
+}
+
+/// The ranges of the fenced code blocks of `text`, from their opening fence up to the end of
+/// their closing one, or of the text if they aren't closed
+fn code_blocks(text: &str) -> Vec<Range<usize>> {
+    let mut blocks = vec![];
+    let mut fence: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some((f, start)) if trimmed.starts_with(f) => {
+                blocks.push(start..offset + line.len());
+                fence = None;
+            }
+            Some(_) => {}
+            None if trimmed.starts_with("```") => fence = Some(("```", offset)),
+            None if trimmed.starts_with("~~~") => fence = Some(("~~~", offset)),
+            None => {}
+        }
+        offset += line.len();
+    }
+    if let Some((_, start)) = fence {
+        blocks.push(start..text.len());
+    }
+    blocks
+}
+
+/// Where each slide of `slides` starts, at its separator, and the range of its content
+/// without its frontmatter
+fn slide_ranges(slides: &str) -> Vec<(usize, Range<usize>)> {
+    let offset = |line: &str| line.as_ptr() as usize - slides.as_ptr() as usize;
+    let mut ranges = vec![];
+    // Where the current slide and its content start, if a separator was seen
+    let mut start: Option<(usize, usize)> = None;
+    let mut lines = slides.split_inclusive('\n');
+    let mut fence: Option<&str> = None;
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(f) if trimmed.starts_with(f) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None if line.trim_end() == "---" => {
+                if let Some((slide, body)) = start {
+                    ranges.push((slide, body..offset(line)));
+                }
+                let slide = offset(line);
+                let mut body = offset(line) + line.len();
+                let mut ahead = lines.clone();
+                let mut frontmatter = vec![];
+                let mut closed = false;
+                for line in ahead.by_ref() {
+                    if line.trim_end() == "---" {
+                        closed = true;
+                        body = offset(line) + line.len();
+                        break;
+                    }
+                    frontmatter.push(line);
+                }
+                let is_frontmatter = closed
+                    && frontmatter.first().is_some_and(|l| !l.trim().is_empty())
+                    && frontmatter.iter().all(|l| {
+                        l.trim().is_empty()
+                            || l.starts_with([' ', '-'])
+                            || (!l.starts_with('#') && l.contains(':'))
+                    });
+                if is_frontmatter {
+                    lines = ahead;
+                } else {
+                    body = offset(line) + line.len();
+                }
+                start = Some((slide, body));
+            }
+            None => {}
+        }
     }
+    if let Some((slide, body)) = start {
+        ranges.push((slide, body..slides.len()));
+    }
+    ranges
+}
+
+/// The keys of the headmatter of a deck that configure its first slide rather than the
+/// deck, which the combined deck keeps on the title slide of each deck
+const SLIDE_KEYS: &[&str] = &["layout", "class", "background", "image"];
+
+/// The keys of the headmatter of a deck that are about its unit, which the combined deck
+/// replaces by its own
+const UNIT_KEYS: &[&str] = &["title", "info"];
+
+/// The `decks` of the package with `title` in one, to export all slides of the track as
+/// one document. Slidev only takes the headmatter at the top of a deck, so the combined
+/// deck has that of the first deck but with its own title, and the other headmatter of each
+/// deck is left out but for the [`SLIDE_KEYS`] of its title slide.
+fn combined_deck(title: &str, decks: &[String]) -> String {
+    let decks: Vec<_> = decks.iter().map(|deck| split_headmatter(deck)).collect();
+    let mut combined = String::from("---\n");
+    if let Some((headmatter, _)) = decks.first() {
+        for (key, text) in headmatter.iter() {
+            if !SLIDE_KEYS.contains(key) && !UNIT_KEYS.contains(key) {
+                combined.push_str(text);
+            }
+        }
+    }
+    // JSON strings are YAML strings
+    let yaml_title = serde_json::to_string(title).unwrap_or_default();
+    combined.push_str(&format!(
+        "title: {yaml_title}\nlayout: cover\n---\n\n# {title}\n"
+    ));
+    for (headmatter, slides) in decks {
+        combined.push_str("\n---\n");
+        let mut slide_keys = headmatter
+            .iter()
+            .filter(|(key, _)| SLIDE_KEYS.contains(key))
+            .peekable();
+        if slide_keys.peek().is_some() {
+            slide_keys.for_each(|(_, text)| combined.push_str(text));
+            combined.push_str("---\n");
+        }
+        combined.push_str(slides);
+        if !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+    }
+    combined
+}
+
+/// Splits the headmatter off a deck, as its top-level keys with the lines they take up,
+/// and the slides that come after it. Decks without headmatter have no keys.
+fn split_headmatter(deck: &str) -> (Vec<(&str, &str)>, &str) {
+    let mut lines = deck.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return (vec![], deck);
+    }
+    let offset = |line: &str| line.as_ptr() as usize - deck.as_ptr() as usize;
+    // Each key with where its lines start and end
+    let mut keys: Vec<(&str, usize, usize)> = vec![];
+    for line in lines {
+        if line.trim_end() == "---" {
+            let keys = keys
+                .into_iter()
+                .map(|(key, start, end)| (key, &deck[start..end]))
+                .collect();
+            return (keys, &deck[offset(line) + line.len()..]);
+        }
+        let end = offset(line) + line.len();
+        match line.split_once(':') {
+            // Top-level keys start at the beginning of the line
+            Some((key, _)) if !line.starts_with([' ', '\t', '-', '#']) => {
+                keys.push((key.trim(), offset(line), end))
+            }
+            _ => {
+                if let Some(last) = keys.last_mut() {
+                    last.2 = end;
+                }
+            }
+        }
+    }
+    // Without the end of the headmatter, it's the first slide
+    (vec![], deck)
+}
+
+/// Renders the attribution slide, or nothing if there's nothing to attribute
+fn attribution_slide(
+    unit_attribution: &str,
+    license: Option<&License>,
+    course_version: Option<&CourseVersion>,
+) -> String {
+    let mut track_attribution = license
+        .map(|l| format!("{} ({})\n", l.attribution, l.spdx))
+        .unwrap_or_default();
+    if let Some(version) = course_version {
+        if !track_attribution.is_empty() {
+            track_attribution.push('\n');
+        }
+        track_attribution += &format!("Course version {version}\n");
+    }
+    let separator = match (unit_attribution.is_empty(), track_attribution.is_empty()) {
+        (true, true) => return String::new(),
+        (false, false) => "\n",
+        _ => "",
+    };
+    format!("\n---\n\n# Attribution\n\n{unit_attribution}{separator}{track_attribution}")
+}
+
+/// Renders the slide with the QR codes of a deck, which link to what goes with it
+fn qr_slide(codes: &[QrCode], prefix: &str) -> String {
+    let title = match codes {
+        [code] => code.target.caption().to_string(),
+        _ => format!(
+            "{} and {}",
+            codes[..codes.len() - 1]
+                .iter()
+                .map(|c| c.target.caption())
+                .collect::<Vec<_>>()
+                .join(", "),
+            codes[codes.len() - 1].target.caption().to_lowercase()
+        ),
+    };
+    let mut slide = format!(
+        "\n---\nlayout: center\n---\n\n# {title}\n\n<div class=\"flex justify-center gap-16 text-center\">\n"
+    );
+    for code in codes {
+        let caption = code.target.caption();
+        slide += &format!(
+            "<div>\n\n<img src=\"/images/{QR_DIR}/{}\" alt=\"QR code of the {}\" class=\"w-48 h-48 mx-auto\" />\n\n[{caption}]({})\n\n</div>\n",
+            code.file_name(prefix),
+            caption.to_lowercase(),
+            code.url
+        );
+    }
+    slide.push_str("</div>\n");
+    slide
+}
+
+/// A placeholder in slide templates, like `#[modmod:unit_title]`, which is replaced by a
+/// value of the deck
+pub struct Placeholder {
+    pub name: &'static str,
+    pub description: &'static str,
+    write: fn(&DeckValues, &mut dyn Write) -> fmt::Result,
+}
+
+impl Placeholder {
+    /// How the placeholder is written in a template
+    pub fn marker(&self) -> String {
+        format!("#[modmod:{}]", self.name)
+    }
+
+    /// The value of the placeholder. Reading the slides of a section again can fail, which
+    /// leaves the error in the values, see [`DeckValues::take_error`].
+    fn value(&self, values: &DeckValues) -> String {
+        let mut value = String::new();
+        // Writing to a string doesn't fail otherwise
+        let _ = (self.write)(values, &mut value);
+        value
+    }
+}
+
+/// The placeholders that are filled in for each deck. The `#[modmod:course_<field>]`
+/// placeholders for the fields in [`Course::FIELDS`] are filled in as well.
+pub const DECK_PLACEHOLDERS: &[Placeholder] = &[
+    Placeholder {
+        name: "mod_title",
+        description: "Name of the module",
+        write: |v, w| w.write_str(v.deck.module_name),
+    },
+    Placeholder {
+        name: "mod_index",
+        description: "Label of the module, like 2 or A",
+        write: |v, w| w.write_str(&v.deck.module_label.display),
+    },
+    Placeholder {
+        name: "unit_index",
+        description: "Number of the unit within its module",
+        write: |v, w| write!(w, "{}", v.deck.unit_index),
+    },
+    Placeholder {
+        name: "unit_title",
+        description: "Name of the unit",
+        write: |v, w| w.write_str(v.deck.name),
+    },
+    Placeholder {
+        name: "content",
+        description: "Slides of the topics of the unit",
+        write: |v, w| {
+            for (section, slides) in v.deck.sections.iter().zip(v.content.iter()) {
+                if slides.len == 0 {
+                    continue;
+                }
+                if section.optional {
+                    // Announce optional material, so that presenters know they can skip it
+                    write!(
+                        w,
+                        "---\nlayout: center\n---\n\n# {}\n\n*Optional material*\n\n",
+                        section.name
+                    )?;
+                }
+                // Read again, so that only one section of the deck is held in memory
+                let slides = match read_topic_slides(
+                    section.content,
+                    section.name,
+                    &v.conditions,
+                    Some(v.topic_files),
+                ) {
+                    Ok(slides) => slides,
+                    Err(error) => {
+                        *v.error.borrow_mut() = Some(error.attach_printable(section.describe()));
+                        return Err(fmt::Error);
+                    }
+                };
+                w.write_str(&slides)?;
+                w.write_str("\n")?;
+            }
+            Ok(())
+        },
+    },
+    Placeholder {
+        name: "duration",
+        description: "Estimated time of the topics and exercises of the unit, with ? if unknown",
+        write: |v, w| {
+            write!(
+                w,
+                "{}",
+                v.deck.sections.iter().map(|s| s.duration).sum::<Estimate>()
+            )
+        },
+    },
+    Placeholder {
+        name: "objectives",
+        description: "List of the learning objectives of the topics",
+        write: |v, w| w.write_str(&v.objectives),
+    },
+    Placeholder {
+        name: "summary",
+        description: "List of the summary items of the topics",
+        write: |v, w| w.write_str(&v.summary),
+    },
+    Placeholder {
+        name: "further_reading",
+        description: "List of the further reading of the topics, each entry once",
+        write: |v, w| w.write_str(&v.further_reading),
+    },
+    Placeholder {
+        name: "exercises",
+        description: "List of the exercises of the unit, linking to their folders, with the first paragraph of their descriptions",
+        write: |v, w| w.write_str(&v.exercises),
+    },
+    Placeholder {
+        name: "attribution",
+        description: "Slide attributing the material and the license, if there is any",
+        write: |v, w| w.write_str(&v.attribution),
+    },
+    Placeholder {
+        name: "qr_codes",
+        description: "Slide with the QR codes of generate --qr-code, if the deck has any",
+        write: |v, w| w.write_str(&v.qr_codes),
+    },
+    Placeholder {
+        name: "theme",
+        description: "Name of the Slidev theme",
+        write: |v, w| w.write_str(&v.theme),
+    },
+];
+
+/// What the [`DECK_PLACEHOLDERS`] of a deck are filled in from
+#[derive(Debug)]
+struct DeckValues<'d, 'track> {
+    deck: &'d SlideDeck<'track>,
+    /// The theme as the frontmatter refers to it, see [`theme_reference`]
+    theme: String,
+    conditions: Conditions<'d>,
+    /// The files the slides of the sections are read from
+    topic_files: &'d TopicFiles,
+    /// The slides of each section of the deck. They are read again as the deck is written.
+    content: Vec<SectionSlides>,
+    objectives: String,
+    summary: String,
+    further_reading: String,
+    exercises: String,
+    attribution: String,
+    qr_codes: String,
+    /// The error of reading the slides of a section again, which placeholders can't pass on
+    error: RefCell<Option<Report<RenderSlidesError>>>,
+}
+
+/// The slides of a section as they end up in a deck, by their size and hash
+#[derive(Debug)]
+struct SectionSlides {
+    /// Zero for sections without slides
+    len: usize,
+    hash: u64,
+}
+
+impl DeckValues<'_, '_> {
+    /// Whether the deck has no content at all, which leaves it out of the package
+    fn is_empty(&self) -> bool {
+        self.content.iter().all(|slides| slides.len == 0)
+            && self.objectives.is_empty()
+            && self.summary.is_empty()
+            && self.further_reading.is_empty()
+    }
+
+    /// The error that filling in the placeholders ran into, if any
+    fn take_error(&self) -> Result<(), RenderSlidesError> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A piece of a slide template, see [`SlidesPackage::template_segments`]
+enum Segment<'t> {
+    Text(&'t str),
+    /// A placeholder, written in the template as `marker`
+    Deck {
+        placeholder: &'static Placeholder,
+        marker: &'t str,
+        when_empty: WhenEmpty,
+    },
+    /// A `#[modmod:course_<field>]` placeholder, with the value of the field
+    Course {
+        value: String,
+        marker: &'t str,
+        when_empty: WhenEmpty,
+    },
+}
+
+/// What a placeholder does when its value is empty for a deck, which the template marks
+/// after its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WhenEmpty {
+    /// `#[modmod:objectives]` is left out
+    Nothing,
+    /// `#[modmod:objectives!]` fails rendering the deck
+    Fail,
+    /// `#[modmod:objectives?]` removes the slide it is on
+    RemoveSlide,
+}
+
+impl WhenEmpty {
+    /// Splits the mark off the name of a placeholder
+    fn split(name: &str) -> (&str, Self) {
+        if let Some(name) = name.strip_suffix('!') {
+            (name, Self::Fail)
+        } else if let Some(name) = name.strip_suffix('?') {
+            (name, Self::RemoveSlide)
+        } else {
+            (name, Self::Nothing)
+        }
+    }
+
+    /// The forms `marker`, like `#[modmod:objectives]`, can be written in a template
+    fn forms(marker: &str) -> [String; 3] {
+        let name = marker.trim_end_matches(']');
+        [marker.to_string(), format!("{name}!]"), format!("{name}?]")]
+    }
+}
+
+/// Writes a deck to `out`, pointing the images it refers to as `/images/<name>` or
+/// `images/<name>` to `images_url`, reporting the placeholders that are left in it, and
+/// keeping the images that aren't in `image_names` for [`DeckWriter::missing_images`].
+/// Errors of `out` are kept in `error`, as [`fmt::Write`] can't pass them on.
+struct DeckWriter<'a, W> {
+    out: W,
+    images_url: &'a str,
+    image_names: &'a BTreeSet<String>,
+    output: &'a Path,
+    /// The line of the deck that is being written
+    line: usize,
+    /// The number of bytes that were written
+    bytes: usize,
+    warnings: &'a mut Vec<Warning>,
+    error: Option<io::Error>,
+    /// The images that aren't in `image_names`, with the line they are on
+    missing: Vec<(usize, String)>,
+    /// The labels of the reference-style images, with the line they are on, which are
+    /// looked up in `definitions` once the deck is written
+    labels: Vec<(usize, String)>,
+    /// The targets of the reference definitions, keyed on their label, see
+    /// [`check::reference_label`]
+    definitions: HashMap<String, String>,
+}
+
+impl<W: io::Write> Write for DeckWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        report::leftover_placeholders_from_line(s, self.line, self.output, self.warnings);
+        let refs = check::markdown_refs(s);
+        self.collect_images(&refs);
+        self.line += s.matches('\n').count();
+        let written = match self.images_url {
+            url if url != "/images/" => self.write_images(s, &refs, url),
+            _ => self.write_all(s),
+        };
+        written.map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+impl<W: io::Write> DeckWriter<'_, W> {
+    fn write_all(&mut self, s: &str) -> io::Result<()> {
+        self.bytes += s.len();
+        self.out.write_all(s.as_bytes())
+    }
+
+    /// Keeps the images of `refs` that aren't one of the `image_names`, and the
+    /// reference-style images and definitions to check once the deck is written
+    fn collect_images(&mut self, refs: &[MarkdownRef]) {
+        for r in refs {
+            let line = self.line + r.line - 1;
+            match r.kind {
+                RefKind::Image | RefKind::HtmlImage => {
+                    if !is_copied_image(r.target, self.image_names) {
+                        self.missing.push((line, r.target.to_string()));
+                    }
+                }
+                RefKind::ImageLabel => self.labels.push((line, check::reference_label(r.target))),
+                RefKind::Definition(label) => {
+                    self.definitions
+                        .entry(check::reference_label(label))
+                        .or_insert_with(|| r.target.to_string());
+                }
+                RefKind::Link => {}
+            }
+        }
+    }
+
+    /// Fails if the deck that was written refers to images that aren't copied into the
+    /// images folder, with the line of each
+    fn missing_images(&mut self) -> Result<(), RenderSlidesError> {
+        let labelled = self.labels.iter().filter_map(|(line, label)| {
+            let target = self.definitions.get(label)?;
+            (!is_copied_image(target, self.image_names)).then(|| (*line, target.clone()))
+        });
+        let mut missing: Vec<_> = self
+            .missing
+            .drain(..)
+            .chain(labelled.collect::<Vec<_>>())
+            .collect();
+        missing.sort();
+        let mut errors: Option<Report<RenderSlidesError>> = None;
+        for (line, target) in missing {
+            let error = Report::new(RenderSlidesError::default())
+                .attach_printable(format!(
+                    "Image '{target}' is not an image of any of the topics, so it's missing from the slides"
+                ))
+                .attach(Location {
+                    path: Some(self.output.to_path_buf()),
+                    line: Some(line),
+                    ..Default::default()
+                });
+            match &mut errors {
+                Some(errors) => errors.extend_one(error),
+                None => errors = Some(error),
+            }
+        }
+        errors.map_or(Ok(()), Err)
+    }
+
+    /// Writes `s` with the targets of `refs` that are in the images folder pointing to
+    /// `url` instead, in one pass
+    fn write_images(&mut self, s: &str, refs: &[MarkdownRef], url: &str) -> io::Result<()> {
+        let mut targets: Vec<_> = refs
+            .iter()
+            .filter(|r| r.kind != RefKind::ImageLabel)
+            .collect();
+        targets.sort_by_key(|r| r.span.start);
+        let mut from = 0;
+        for r in targets {
+            let Some(name) = image_name(r.target).filter(|_| r.span.start >= from) else {
+                continue;
+            };
+            self.write_all(&s[from..r.span.start])?;
+            self.write_all(url)?;
+            self.write_all(name)?;
+            from = r.span.end;
+        }
+        self.write_all(&s[from..])
+    }
+}
+
+/// The path in the images folder that `target` refers to, like `a.png` for
+/// `/images/a.png` or `images/a.png`, with its query and fragment
+fn image_name(target: &str) -> Option<&str> {
+    let target = target.strip_prefix("./").unwrap_or(target);
+    target
+        .strip_prefix("/images/")
+        .or_else(|| target.strip_prefix("images/"))
+}
+
+/// Whether `target`, the source of an image in a deck, is one of the `image_names` that are
+/// copied into the images folder, or somewhere else than the output, like on the web
+fn is_copied_image(target: &str, image_names: &BTreeSet<String>) -> bool {
+    if target.is_empty() || target.starts_with(['#', '{']) || target.starts_with("//") {
+        return true;
+    }
+    let scheme = target.split_once(':').map(|(scheme, _)| scheme);
+    if scheme.is_some_and(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return true;
+    }
+    image_name(target)
+        .map(|name| &name[..name.find(['?', '#']).unwrap_or(name.len())])
+        .is_some_and(|name| image_names.contains(name))
+}
+
+/// A deck that was written, as far as deploying it is concerned
+#[derive(Debug, Clone)]
+pub(crate) struct WrittenDeck {
+    pub(crate) name: String,
+    /// Like `2_1`, which its build script and the folder it's served from are named after
+    pub(crate) prefix: String,
+    /// The name of the deck file, and of the folder its build script writes into
+    pub(crate) slug: String,
+}
+
+/// A deck that was written, with what it adds to the package
+struct RenderedDeck<'d, 'track> {
+    deck: &'d SlideDeck<'track>,
+    prefix: String,
+    slug: String,
+    output: PathBuf,
+    warnings: Vec<Warning>,
+    /// Warnings about the template, which other decks may have as well
+    template_warnings: Vec<Warning>,
 }
 
 #[derive(Debug)]
@@ -178,19 +2020,65 @@ pub struct SlideDeck<'track> {
     /// Name of the slide deck, corresponds to the name of the unit in the module
     name: &'track str,
     module_name: &'track str,
-    module_index: usize,
+    module_label: &'track ModuleLabel,
     unit_index: usize,
     template: Option<&'track Path>,
+    /// The theme of the deck, instead of the one of the package, see [`SlidesRenderOptions`]
+    theme: Option<&'track str>,
     sections: Vec<Section<'track>>,
 }
 
+impl SlideDeck<'_> {
+    /// Prefix of the deck in the scripts of the package and the file names of the output,
+    /// like `2_1`, from the path of its module label and the index of its unit
+    fn prefix(&self) -> String {
+        format!("{}_{}", self.module_label.path, self.unit_index)
+    }
+
+    /// File stem of the rendered deck, like `2_1-basic-syntax`
+    fn slug(&self) -> String {
+        to_prefixed_tag(self.name, self.prefix())
+    }
+
+    /// Names the deck in errors, like `In slide deck 2.1 Basic Syntax (2_1-basic-syntax),
+    /// unit 1 of module 'Basic Rust'`
+    fn describe(&self) -> String {
+        format!(
+            "In slide deck {} {} ({}), unit {} of module '{}'",
+            self.module_label.qualify(self.unit_index),
+            self.name,
+            self.slug(),
+            self.unit_index,
+            self.module_name
+        )
+    }
+}
+
+impl Section<'_> {
+    /// Names the section in errors, with the file its content is read from
+    fn describe(&self) -> String {
+        match self.content.path() {
+            Some(path) => format!("In section '{}', read from {}", self.name, path.display()),
+            None => format!("In section '{}', with inline content", self.name),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Section<'track> {
-    content: &'track Path,
+    /// Name of the section, corresponds to the name of the topic
+    name: &'track str,
+    content: &'track TopicContent,
+    optional: bool,
+    /// Estimated minutes of the topic and its exercises
+    duration: Estimate,
+    attribution: Option<String>,
     objectives: Vec<&'track str>,
     summary: Vec<&'track str>,
     further_reading: Vec<&'track str>,
     images: Vec<&'track Path>,
+    /// The exercises of the topic that are rendered
+    exercises: Vec<&'track Exercise>,
 }
 
 pub struct SlidesPackageBuilder<'track> {
@@ -202,23 +2090,75 @@ impl<'track> SlidesPackageBuilder<'track> {
         &mut self,
         name: &'track str,
         module_name: &'track str,
-        module_index: usize,
+        module_label: &'track ModuleLabel,
         unit_index: usize,
         template: Option<&'track Path>,
+        theme: Option<&'track str>,
     ) -> SlideDeckBuilder<'track, '_> {
         SlideDeckBuilder {
             package_builder: self,
             slide_deck: SlideDeck {
                 name,
                 module_name,
-                module_index,
+                module_label,
                 unit_index,
                 template,
+                theme,
                 sections: vec![],
             },
         }
     }
 
+    pub fn course(&mut self, course: &'track Course) {
+        self.package.course = Some(course);
+    }
+
+    pub fn license(&mut self, license: &'track License) {
+        self.package.license = Some(license);
+    }
+
+    pub fn course_version(&mut self, version: &'track CourseVersion) {
+        self.package.course_version = Some(version);
+    }
+
+    pub fn analytics(&mut self, analytics: &'track Analytics) {
+        self.package.analytics = Some(analytics);
+    }
+
+    /// Sets the QR codes of the decks, by their prefix, see [`crate::qr`]
+    pub(crate) fn qr_codes(&mut self, codes: HashMap<String, Vec<QrCode>>) {
+        self.package.qr_codes = codes;
+    }
+
+    /// Sets where the exercises end up, by their path, as
+    /// [`ExerciseCollection::output_paths`](crate::exercises::ExerciseCollection::output_paths)
+    /// returns it, for the decks to link to them. The links are relative to where the decks
+    /// are deployed, a folder of their own in `slides_dir` like `slides/1_1/`, so that they
+    /// work under any base URL.
+    pub(crate) fn exercise_paths(
+        &mut self,
+        paths: &HashMap<PathBuf, PathBuf>,
+        slides_dir: &Path,
+    ) -> Result<(), RenderSlidesError> {
+        let deck_depth = OutputLayout::dir_url_path(slides_dir).matches('/').count() + 1;
+        for (exercise, output) in paths {
+            let url = "../".repeat(deck_depth) + &OutputLayout::dir_url_path(output);
+            let folder = (slash_path(output)?, url);
+            self.package
+                .exercise_folders
+                .insert(exercise.clone(), folder);
+        }
+        Ok(())
+    }
+
+    pub fn merge_similar_objectives(&mut self) {
+        self.package.merge_similar_objectives = true;
+    }
+
+    pub fn default_template(&mut self, template: &'track Path) {
+        self.package.default_template = Some(template);
+    }
+
     pub fn build(self) -> SlidesPackage<'track> {
         self.package
     }
@@ -230,15 +2170,24 @@ pub struct SlideDeckBuilder<'track, 'p> {
 }
 
 impl<'track, 'p> SlideDeckBuilder<'track, 'p> {
-    pub fn section(&mut self, content: &'track Path) -> SlidesSectionBuilder<'track, 'p, '_> {
+    pub fn section(
+        &mut self,
+        name: &'track str,
+        content: &'track TopicContent,
+    ) -> SlidesSectionBuilder<'track, 'p, '_> {
         SlidesSectionBuilder {
             deck_builder: self,
             section: Section {
+                name,
                 content,
+                optional: false,
+                duration: Estimate::default(),
+                attribution: None,
                 objectives: vec![],
                 summary: vec![],
                 further_reading: vec![],
                 images: vec![],
+                exercises: vec![],
             },
         }
     }
@@ -267,12 +2216,296 @@ impl<'track, 'p, 'd> SlidesSectionBuilder<'track, 'p, 'd> {
         self.section.further_reading.push(further_reading);
     }
 
+    pub fn mark_optional(&mut self) {
+        self.section.optional = true;
+    }
+
+    pub fn duration(&mut self, duration: Estimate) {
+        self.section.duration = duration;
+    }
+
+    pub fn attribution(&mut self, attribution: String) {
+        self.section.attribution = Some(attribution);
+    }
+
     pub fn image(&mut self, image: &'track Path) {
         self.section.images.push(image);
     }
 
+    pub fn exercise(&mut self, exercise: &'track Exercise) {
+        self.section.exercises.push(exercise);
+    }
+
     pub fn add(self) -> &'d mut SlideDeckBuilder<'track, 'p> {
         self.deck_builder.slide_deck.sections.push(self.section);
         self.deck_builder
     }
 }
+
+#[cfg(test)]
+// Tests fail by panicking
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::diagnostic::Diagnostic;
+
+    /// The segments of `template`, with the warnings about it
+    fn segments(template: &str) -> (Result<Vec<Segment<'_>>, RenderSlidesError>, Vec<Warning>) {
+        let package = SlidesPackage::builder("Test").package;
+        let mut warnings = vec![];
+        let segments =
+            package.template_segments(template, Some(Path::new("template.md")), &mut warnings);
+        (segments, warnings)
+    }
+
+    /// The markers of the placeholders in `template`, with how they handle empty values
+    fn markers(template: &str) -> Vec<(&str, WhenEmpty)> {
+        segments(template)
+            .0
+            .unwrap()
+            .into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(_) => None,
+                Segment::Deck {
+                    marker, when_empty, ..
+                }
+                | Segment::Course {
+                    marker, when_empty, ..
+                } => Some((marker, when_empty)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn when_empty_marks() {
+        assert_eq!(WhenEmpty::split("summary"), ("summary", WhenEmpty::Nothing));
+        assert_eq!(WhenEmpty::split("summary!"), ("summary", WhenEmpty::Fail));
+        assert_eq!(
+            WhenEmpty::split("summary?"),
+            ("summary", WhenEmpty::RemoveSlide)
+        );
+        assert_eq!(
+            WhenEmpty::forms("#[modmod:summary]"),
+            [
+                "#[modmod:summary]",
+                "#[modmod:summary!]",
+                "#[modmod:summary?]"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_marker() {
+        let template = "#[modmod:content]\n\n# Summary\n\n#[modmod:sumary]\n";
+        let report = match segments(template).0 {
+            Ok(_) => panic!("unknown placeholder wasn't reported"),
+            Err(report) => report,
+        };
+        let message = format!("{report:?}");
+        assert!(
+            message.contains(
+                "Unknown placeholder #[modmod:sumary] in the template.md slide template."
+            ),
+            "{message}"
+        );
+        assert!(message.contains("summary"), "{message}");
+        let location = report.downcast_ref::<Location>().unwrap();
+        assert_eq!(location.line, Some(5));
+        assert_eq!(location.path.as_deref(), Some(Path::new("template.md")));
+    }
+
+    #[test]
+    fn unknown_marker_with_mark() {
+        let (segments, _) = segments("#[modmod:content]\n#[modmod:nothing?]\n");
+        assert!(segments.is_err());
+    }
+
+    #[test]
+    fn repeated_markers() {
+        let template = indoc! {"
+            # #[modmod:unit_title]
+
+            #[modmod:content]
+
+            ---
+
+            # #[modmod:unit_title], again
+
+            #[modmod:objectives?]
+            #[modmod:objectives!]
+        "};
+        assert_eq!(
+            markers(template),
+            [
+                ("#[modmod:unit_title]", WhenEmpty::Nothing),
+                ("#[modmod:content]", WhenEmpty::Nothing),
+                ("#[modmod:unit_title]", WhenEmpty::Nothing),
+                ("#[modmod:objectives?]", WhenEmpty::RemoveSlide),
+                ("#[modmod:objectives!]", WhenEmpty::Fail),
+            ]
+        );
+    }
+
+    #[test]
+    fn markers_in_code_blocks_are_left_alone() {
+        let template = indoc! {"
+            #[modmod:content]
+
+            ```md
+            #[modmod:unit_title] and #[modmod:made_up]
+            ```
+
+            ~~~
+            #[modmod:summary]
+            ~~~
+
+            #[modmod:summary]
+        "};
+        assert_eq!(
+            markers(template),
+            [
+                ("#[modmod:content]", WhenEmpty::Nothing),
+                ("#[modmod:summary]", WhenEmpty::Nothing),
+            ]
+        );
+        // The code blocks are kept in the text around the markers
+        let text: String = segments(template)
+            .0
+            .unwrap()
+            .into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert!(text.contains("#[modmod:unit_title] and #[modmod:made_up]"));
+    }
+
+    #[test]
+    fn unclosed_marker_is_text() {
+        assert_eq!(
+            markers("#[modmod:content]\n#[modmod:summary\n"),
+            [("#[modmod:content]", WhenEmpty::Nothing)]
+        );
+    }
+
+    #[test]
+    fn template_without_content() {
+        let (segments, warnings) = segments("# #[modmod:unit_title]\n");
+        assert!(segments.is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::NoContentPlaceholder);
+
+        let (_, warnings) = self::segments("#[modmod:content]\n");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn default_template_is_valid() {
+        let (segments, warnings) = segments(SLIDES_TEMPLATE_DEFAULT);
+        assert!(segments.is_ok());
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    /// Writes `chunks` like they are written for a deck, with the images `a.png` and
+    /// `b.svg` in the images folder at `images_url`
+    fn write_deck(chunks: &[&str], images_url: &str) -> (String, Result<(), RenderSlidesError>) {
+        let image_names = ["a.png".to_string(), "b.svg".to_string()].into();
+        let mut warnings = vec![];
+        let mut writer = DeckWriter {
+            out: vec![],
+            images_url,
+            image_names: &image_names,
+            output: Path::new("slides/1_1-deck.md"),
+            line: 1,
+            bytes: 0,
+            warnings: &mut warnings,
+            error: None,
+            missing: vec![],
+            labels: vec![],
+            definitions: HashMap::new(),
+        };
+        for chunk in chunks {
+            writer.write_str(chunk).unwrap();
+        }
+        let missing = writer.missing_images();
+        (String::from_utf8(writer.out).unwrap(), missing)
+    }
+
+    #[test]
+    fn copied_images_are_found() {
+        let deck = indoc! {r#"
+            ![A](/images/a.png) ![B](images/b.svg "Title")
+            <img class="w-40" src='./images/a.png' /> <IMG SRC="/images/b.svg?raw">
+            ![Reference][b] ![a]
+
+            [b]: /images/b.svg
+            [A]: <images/a.png>
+            ![Web](https://example.com/c.png) <img src="data:image/png;base64,AAAA">
+            ```md
+            ![In code](/images/c.png)
+            ```
+            `![Inline code](/images/c.png)` [Link](/images/c.pdf)
+        "#};
+        let (_, missing) = write_deck(&[deck], "/images/");
+        missing.unwrap();
+    }
+
+    #[test]
+    fn missing_images_fail_with_their_line() {
+        let template = "# Title\n\n![Gone](/images/gone.png)\n";
+        let content = indoc! {"
+            <img src='images/also-gone.svg' />
+            ![Diagram][diagram] ![Nowhere](nowhere.png)
+
+            [diagram]: /images/missing.svg
+        "};
+        let (_, missing) = write_deck(&[template, content], "/images/");
+        let report = missing.unwrap_err();
+        let errors: Vec<_> = Diagnostic::from_report(&report)
+            .into_iter()
+            .map(|error| (error.location.line, error.message))
+            .collect();
+        let error = |line, image: &str| {
+            (
+                Some(line),
+                format!("Image '{image}' is not an image of any of the topics, so it's missing from the slides"),
+            )
+        };
+        assert_eq!(
+            errors,
+            [
+                error(3, "/images/gone.png"),
+                error(4, "images/also-gone.svg"),
+                error(5, "/images/missing.svg"),
+                error(5, "nowhere.png"),
+            ]
+        );
+        let location = report.downcast_ref::<Location>().unwrap();
+        assert_eq!(
+            location.path.as_deref(),
+            Some(Path::new("slides/1_1-deck.md"))
+        );
+    }
+
+    #[test]
+    fn images_point_to_the_images_url() {
+        let deck = indoc! {"
+            ![A](/images/a.png) <img src='images/b.svg'> `![A](/images/a.png)`
+
+            [a]: ./images/a.png#top
+        "};
+        let (written, missing) = write_deck(&[deck], "/assets/");
+        missing.unwrap();
+        assert_eq!(
+            written,
+            indoc! {"
+                ![A](/assets/a.png) <img src='/assets/b.svg'> `![A](/images/a.png)`
+
+                [a]: /assets/a.png#top
+            "}
+        );
+    }
+}
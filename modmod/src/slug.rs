@@ -0,0 +1,294 @@
+//! Slugs: the names of generated files and folders, and the ids of units, derived from
+//! titles.
+//!
+//! Words are lowercased and joined with dashes. Only ASCII letters, digits, `-`, `_` and
+//! `.` are kept, so that slugs are valid file names on all platforms and can be used in
+//! URLs and markdown links as they are. Path separators become dashes, common accented
+//! letters are transliterated, like `é` to `e`, and anything else is left out, unless
+//! the [`RunContext`](crate::context::RunContext) keeps them, see
+//! [`RunContext::unicode_slugs`](crate::context::RunContext::unicode_slugs). A title of plain words and numbers, like `Basic Syntax`,
+//! simply becomes `basic-syntax`.
+
+use std::borrow::Cow;
+
+use crate::{context, update::stable_hash as hash};
+
+/// Slugs longer than this are cut short, keeping a hash of the rest, so that the paths of
+/// exercises nested in modules and units stay within the path length limit of Windows
+pub const MAX_SLUG_LEN: usize = 64;
+
+/// Windows doesn't allow these as file names, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// The slug of `title`, which is never empty
+pub fn slug(title: &str) -> String {
+    slug_of(title).into_owned()
+}
+
+/// Like [`slug`], borrowing `title` if it's a slug already, which most names of files and
+/// folders are
+pub fn slug_of(title: &str) -> Cow<'_, str> {
+    if is_slug(title) {
+        return Cow::Borrowed(title);
+    }
+    // Only letters that aren't ASCII depend on the context
+    let keep_unicode = !title.is_ascii() && context::current().unicode_slugs;
+    let mut slug = String::with_capacity(title.len());
+    for (i, word) in title.split_whitespace().enumerate() {
+        if i > 0 {
+            slug.push('-');
+        }
+        for c in word.chars() {
+            push_char(&mut slug, c, keep_unicode);
+        }
+    }
+    // Windows drops dots at the end of names, and names that start with one are hidden
+    let start = slug.len() - slug.trim_start_matches('.').len();
+    slug.drain(..start);
+    slug.truncate(slug.trim_end_matches('.').len());
+    if slug.is_empty() {
+        return Cow::Owned(match title.trim().is_empty() {
+            true => "untitled".to_string(),
+            // Titles of only emoji or punctuation get a slug of their own
+            false => format!("untitled-{}", &hash(title.as_bytes())[..8]),
+        });
+    }
+    let stem_len = slug.find('.').unwrap_or(slug.len());
+    if RESERVED_NAMES.contains(&&slug[..stem_len]) {
+        slug.insert(stem_len, '_');
+    }
+    if slug.len() > MAX_SLUG_LEN {
+        let mut end = MAX_SLUG_LEN - 9;
+        while !slug.is_char_boundary(end) {
+            end -= 1;
+        }
+        let hash = hash(slug.as_bytes());
+        slug.truncate(end);
+        slug.truncate(slug.trim_end_matches(['-', '.']).len());
+        slug.push('-');
+        slug.push_str(&hash[..8]);
+    }
+    Cow::Owned(slug)
+}
+
+/// Whether `title` is its own slug: a word of the characters slugs keep, lowercased, that
+/// doesn't need any of the changes for Windows
+fn is_slug(title: &str) -> bool {
+    let stem = title.split('.').next().unwrap_or(title);
+    !title.is_empty()
+        && title.len() <= MAX_SLUG_LEN
+        && title
+            .bytes()
+            .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.'))
+        && !title.starts_with('.')
+        && !title.ends_with('.')
+        && !RESERVED_NAMES.contains(&stem)
+}
+
+fn push_char(slug: &mut String, c: char, keep_unicode: bool) {
+    match c {
+        'a'..='z' | '0'..='9' | '-' | '_' | '.' => slug.push(c),
+        'A'..='Z' => slug.push(c.to_ascii_lowercase()),
+        '/' | '\\' | ':' | '|' => slug.push('-'),
+        c if c.is_ascii() => {}
+        c if keep_unicode && c.is_alphanumeric() => slug.extend(c.to_lowercase()),
+        c => {
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            slug.push_str(transliterate(lower));
+        }
+    }
+}
+
+/// The ASCII spelling of common accented letters, or nothing for other characters
+fn transliterate(c: char) -> &'static str {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RunContext;
+
+    /// Characters titles are made of in the tests: plain words, and everything that gets
+    /// special treatment
+    const ALPHABET: &[&str] = &[
+        "a",
+        "Z",
+        "q",
+        "7",
+        " ",
+        " ",
+        "\t",
+        "-",
+        "_",
+        ".",
+        "..",
+        "/",
+        "\\",
+        ":",
+        "|",
+        "?",
+        "*",
+        "\"",
+        "<",
+        ">",
+        "é",
+        "ß",
+        "Ø",
+        "ñ",
+        "æ",
+        "日本",
+        "Ж",
+        "🦀",
+        "👩‍🔬",
+        "\u{200b}",
+        "\n",
+        "con",
+        "NUL",
+        "lpt1",
+        "Com9",
+    ];
+
+    /// A small xorshift generator, so that the tests see the same titles on each run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize
+        }
+
+        fn title(&mut self) -> String {
+            // Up to twice the length limit, to cut some of them short
+            let len = self.next() % (2 * MAX_SLUG_LEN);
+            (0..len)
+                .map(|_| ALPHABET[self.next() % ALPHABET.len()])
+                .collect()
+        }
+    }
+
+    fn titles() -> impl Iterator<Item = String> {
+        let mut rng = Rng(0x5eed_1234_abcd_ef01);
+        (0..5000).map(move |_| rng.title())
+    }
+
+    /// What holds for the slug of any title
+    fn assert_valid(title: &str, slug: &str) {
+        assert!(!slug.is_empty(), "{title:?}");
+        assert!(slug.len() <= MAX_SLUG_LEN, "{title:?} became {slug:?}");
+        assert!(
+            slug.bytes()
+                .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.')),
+            "{title:?} became {slug:?}"
+        );
+        assert!(
+            !slug.starts_with('.') && !slug.ends_with('.'),
+            "{title:?} became {slug:?}"
+        );
+        let stem = slug.split('.').next().unwrap();
+        assert!(!RESERVED_NAMES.contains(&stem), "{title:?} became {slug:?}");
+    }
+
+    #[test]
+    fn slugs_of_any_title_are_valid_file_names() {
+        let _context = RunContext::default().enter();
+        for title in titles() {
+            assert_valid(&title, &slug(&title));
+        }
+    }
+
+    #[test]
+    fn slugs_are_their_own_slug() {
+        let _context = RunContext::default().enter();
+        for title in titles() {
+            let slug = slug(&title);
+            assert!(is_slug(&slug), "{title:?} became {slug:?}");
+            assert!(matches!(slug_of(&slug), Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn long_titles_keep_apart() {
+        let prefix = "A very long title of a unit, that goes on and on and on and on:";
+        let slugs: std::collections::HashSet<_> = (0..1000)
+            .map(|i| slug(&format!("{prefix} part {i}")))
+            .collect();
+        assert_eq!(slugs.len(), 1000);
+        assert!(slugs.iter().all(|slug| slug.len() <= MAX_SLUG_LEN));
+    }
+
+    #[test]
+    fn cut_short_stably() {
+        let title = "x".repeat(200);
+        let slug = slug(&title);
+        assert_eq!(slug.len(), MAX_SLUG_LEN);
+        assert_eq!(slug, super::slug(&title));
+        assert_ne!(slug, super::slug(&"x".repeat(201)));
+    }
+
+    #[test]
+    fn windows_names() {
+        assert_eq!(slug("CON"), "con_");
+        assert_eq!(slug("nul.txt"), "nul_.txt");
+        assert_eq!(slug("console"), "console");
+        assert_eq!(slug("a/b\\c:d|e"), "a-b-c-d-e");
+        assert_eq!(slug("...dots..."), "dots");
+    }
+
+    #[test]
+    fn titles_without_slug_characters() {
+        let _context = RunContext::default().enter();
+        assert_eq!(slug(""), "untitled");
+        assert_eq!(slug("   "), "untitled");
+        let crab = slug("🦀🦀");
+        assert!(crab.starts_with("untitled-") && crab.len() == 17, "{crab}");
+        assert_ne!(crab, slug("🦀"));
+    }
+
+    #[test]
+    fn plain_titles() {
+        let _context = RunContext::default().enter();
+        // The names of the files in the output of the teach-rs track
+        for (title, expected) in [
+            ("Introduction", "introduction"),
+            ("Basic Syntax", "basic-syntax"),
+            ("Ownership and References", "ownership-and-references"),
+            ("Traits and generics", "traits-and-generics"),
+            (
+                "Closures and Dynamic dispatch",
+                "closures-and-dynamic-dispatch",
+            ),
+            ("Rust for web", "rust-for-web"),
+            ("Foreign Function Interface", "foreign-function-interface"),
+            ("Café élève", "cafe-eleve"),
+        ] {
+            assert_eq!(slug(title), expected);
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Solution markers in the Rust files of exercise packages, which let an exercise hold its
+//! starter code and its solution in one place.
+//!
+//! The lines between a `// MODMOD-SOLUTION-START` and a `// MODMOD-SOLUTION-END` comment are
+//! the solution. The exercises output gets the starter code, in which each of these regions is
+//! replaced by `todo!()`, keeping the comments in it, like hints. With `generate
+//! --with-solutions`, the solutions output gets the code with only the markers left out.
+//!
+//! The markers have to be on a line of their own, and are only markers in code: in a string
+//! literal or a block comment, they are left alone. The `todo!()` gets a `;` unless the region
+//! ends the block or the list it is in, so that a region either is the tail of a block, like a
+//! function body, or holds statements.
+
+use std::{fmt, path::Path};
+
+use error_stack::{Report, Result};
+
+/// Starts a solution region
+pub const START: &str = "MODMOD-SOLUTION-START";
+/// Ends a solution region
+pub const END: &str = "MODMOD-SOLUTION-END";
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct SplitSolutionError;
+
+impl fmt::Display for SplitSolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to split the solution from the starter code")
+    }
+}
+
+impl error_stack::Context for SplitSolutionError {}
+
+/// The two versions of a file with solution markers
+#[derive(Debug)]
+pub(crate) struct Split {
+    pub(crate) starter: String,
+    pub(crate) solution: String,
+}
+
+/// Where a line starts, as far as telling code from literals and comments goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    Str,
+    /// A raw string, closed by a `"` and this many `#`
+    RawStr(usize),
+    /// A block comment, nested this deep
+    BlockComment(usize),
+}
+
+/// Splits `content`, the content of the Rust file at `path`, into its starter code and
+/// solution, or returns `None` if it has no markers. Markers that aren't balanced are an error.
+pub(crate) fn split(path: &Path, content: &str) -> Result<Option<Split>, SplitSolutionError> {
+    if !content.contains(START) && !content.contains(END) {
+        return Ok(None);
+    }
+    let unbalanced = |line: usize, problem: String| {
+        Report::new(SplitSolutionError)
+            .attach_printable(format!("{}:{line}: {problem}", path.display()))
+    };
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut states = Vec::with_capacity(lines.len());
+    let mut state = State::Code;
+    for line in lines.iter() {
+        states.push(state);
+        state = scan(line, state);
+    }
+
+    let marker = |i: usize| match states[i] {
+        State::Code => marker(lines[i]),
+        _ => None,
+    };
+    let mut starter = String::with_capacity(content.len());
+    let mut solution = String::with_capacity(content.len());
+    // The line and indentation of the start of the region the lines are in, if any
+    let mut region: Option<(usize, &str)> = None;
+    let mut found = false;
+    for (i, line) in lines.iter().enumerate() {
+        match (marker(i), region) {
+            (Some(START), None) => {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                region = Some((i, indent));
+                found = true;
+            }
+            (Some(START), Some((start, _))) => {
+                return Err(unbalanced(
+                    i + 1,
+                    format!(
+                        "{START}, but the region that starts at line {} isn't ended",
+                        start + 1
+                    ),
+                ));
+            }
+            (Some(_), None) => {
+                return Err(unbalanced(
+                    i + 1,
+                    format!("{END} without a {START} before it"),
+                ));
+            }
+            (Some(_), Some((_, indent))) => {
+                region = None;
+                // Whatever follows the region decides whether the `todo!()` is its value
+                let next = lines[i + 1..]
+                    .iter()
+                    .map(|l| l.trim_start())
+                    .find(|l| !l.is_empty() && !l.starts_with("//"));
+                let tail = next.is_none_or(|l| l.starts_with(['}', ')', ']']));
+                let semicolon = if tail { "" } else { ";" };
+                let newline = if line.ends_with("\r\n") { "\r\n" } else { "\n" };
+                starter.push_str(&format!("{indent}todo!(){semicolon}{newline}"));
+            }
+            (None, Some(_)) => {
+                solution.push_str(line);
+                if states[i] == State::Code && line.trim_start().starts_with("//") {
+                    starter.push_str(line);
+                }
+            }
+            (None, None) => {
+                solution.push_str(line);
+                starter.push_str(line);
+            }
+        }
+    }
+    if let Some((start, _)) = region {
+        return Err(unbalanced(
+            start + 1,
+            format!("{START} without a {END} after it"),
+        ));
+    }
+    Ok(found.then_some(Split { starter, solution }))
+}
+
+/// The marker that `line` consists of, if any
+fn marker(line: &str) -> Option<&'static str> {
+    let comment = line.trim().strip_prefix("//")?;
+    // Doc comments are part of the code
+    if comment.starts_with(['/', '!']) {
+        return None;
+    }
+    [START, END]
+        .into_iter()
+        .find(|marker| comment.trim() == *marker)
+}
+
+/// The state after `line`, which starts in `state`
+fn scan(line: &str, mut state: State) -> State {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match state {
+            State::Code => match (c, next) {
+                ('/', Some('/')) => return State::Code,
+                ('/', Some('*')) => {
+                    state = State::BlockComment(1);
+                    i += 1;
+                }
+                ('"', _) => state = State::Str,
+                ('r', Some('"' | '#')) if starts_token(&chars, i) => {
+                    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        state = State::RawStr(hashes);
+                        i += 1 + hashes;
+                    }
+                }
+                ('\'', _) => i += char_literal_len(&chars[i..]),
+                _ => {}
+            },
+            State::Str => match c {
+                '\\' => i += 1,
+                '"' => state = State::Code,
+                _ => {}
+            },
+            State::RawStr(hashes) => {
+                let closes =
+                    c == '"' && chars[i + 1..].iter().take_while(|c| **c == '#').count() >= hashes;
+                if closes {
+                    state = State::Code;
+                    i += hashes;
+                }
+            }
+            State::BlockComment(depth) => match (c, next) {
+                ('*', Some('/')) => {
+                    state = match depth {
+                        1 => State::Code,
+                        depth => State::BlockComment(depth - 1),
+                    };
+                    i += 1;
+                }
+                ('/', Some('*')) => {
+                    state = State::BlockComment(depth + 1);
+                    i += 1;
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    state
+}
+
+/// Whether the character at `i` starts a token, like the `r` of a raw string, or a `br`
+fn starts_token(chars: &[char], i: usize) -> bool {
+    let before = |j: usize| j.checked_sub(1).and_then(|j| chars.get(j)).copied();
+    let is_ident = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    match before(i) {
+        Some('b') => !is_ident(before(i - 1)),
+        c => !is_ident(c),
+    }
+}
+
+/// The characters after the `'` at the start of `chars` that are part of a character
+/// literal, or none if it is a lifetime or label
+fn char_literal_len(chars: &[char]) -> usize {
+    match (chars.get(1), chars.get(2)) {
+        // The escaped character may be a `'` itself
+        (Some('\\'), _) => chars
+            .get(3..)
+            .and_then(|rest| rest.iter().position(|c| *c == '\''))
+            .map_or(0, |end| end + 3),
+        (Some(_), Some('\'')) => 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn split_ok(content: &str) -> Option<Split> {
+        split(Path::new("src/lib.rs"), content).unwrap()
+    }
+
+    fn split_err(content: &str) -> String {
+        let error = split(Path::new("src/lib.rs"), content).unwrap_err();
+        format!("{error:?}")
+    }
+
+    #[test]
+    fn markers_in_literals_and_comments_are_left_alone() {
+        let content = indoc! {r##"
+            fn main() {
+                let s = "
+            // MODMOD-SOLUTION-START
+            ";
+                let t = r#"a " b
+            // MODMOD-SOLUTION-END
+            "#;
+                /* outer /* inner */
+            // MODMOD-SOLUTION-START
+                */
+                /// MODMOD-SOLUTION-END
+                let u = br"
+            // MODMOD-SOLUTION-END
+            ";
+            }
+        "##};
+        assert!(split_ok(content).is_none());
+    }
+
+    #[test]
+    fn regions_after_literals_are_split() {
+        let content = indoc! {r##"
+            fn main() {
+                let c = '"';
+                let s = r#"" // "#;
+                // MODMOD-SOLUTION-START
+                println!("{c}{s}");
+                // MODMOD-SOLUTION-END
+            }
+        "##};
+        let split = split_ok(content).unwrap();
+        assert_eq!(
+            split.starter,
+            indoc! {r##"
+                fn main() {
+                    let c = '"';
+                    let s = r#"" // "#;
+                    todo!()
+                }
+            "##}
+        );
+        assert_eq!(
+            split.solution,
+            indoc! {r##"
+                fn main() {
+                    let c = '"';
+                    let s = r#"" // "#;
+                    println!("{c}{s}");
+                }
+            "##}
+        );
+    }
+
+    #[test]
+    fn scan_tells_code_from_literals() {
+        assert_eq!(scan("let s = r##\"a\"# b\n", State::Code), State::RawStr(2));
+        assert_eq!(scan("\"#, c\"## + d\n", State::RawStr(2)), State::Code);
+        assert_eq!(scan("let s = \"a\\\"\n", State::Code), State::Str);
+        assert_eq!(
+            scan("fn f<'a>(s: &'a str) { '\\'' }\n", State::Code),
+            State::Code
+        );
+        assert_eq!(scan("let r#type = \"\n", State::Code), State::Str);
+        assert_eq!(
+            scan("/* /* */ // \"\n", State::Code),
+            State::BlockComment(1)
+        );
+        assert_eq!(scan("let s = \"/*\"; // \"\n", State::Code), State::Code);
+    }
+
+    #[test]
+    fn unbalanced_markers_are_reported_with_their_line() {
+        let error = split_err(indoc! {"
+            fn main() {
+                // MODMOD-SOLUTION-END
+            }
+        "});
+        assert!(
+            error.contains("src/lib.rs:2: MODMOD-SOLUTION-END without a MODMOD-SOLUTION-START"),
+            "{error}"
+        );
+
+        let error = split_err(indoc! {"
+            fn main() {
+                // MODMOD-SOLUTION-START
+                let a = 1;
+            }
+        "});
+        assert!(
+            error.contains("src/lib.rs:2: MODMOD-SOLUTION-START without a MODMOD-SOLUTION-END"),
+            "{error}"
+        );
+
+        let error = split_err(indoc! {"
+            fn main() {
+                // MODMOD-SOLUTION-START
+                let a = 1;
+                // MODMOD-SOLUTION-START
+            }
+        "});
+        assert!(
+            error.contains(
+                "src/lib.rs:4: MODMOD-SOLUTION-START, but the region that starts at line 2 \
+                 isn't ended"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn todo_is_the_tail_or_a_statement() {
+        let content = indoc! {"
+            fn sum(v: &[i32]) -> i32 {
+                // MODMOD-SOLUTION-START
+                // Add them up
+                v.iter().sum()
+                // MODMOD-SOLUTION-END
+            }
+
+            fn main() {
+                let mut v = vec![];
+                // MODMOD-SOLUTION-START
+                v.push(1);
+                // MODMOD-SOLUTION-END
+
+                // The sum
+                println!(\"{}\", sum(&v));
+                let w = (
+                    1,
+                    // MODMOD-SOLUTION-START
+                    2,
+                    // MODMOD-SOLUTION-END
+                );
+            }
+        "};
+        let split = split_ok(content).unwrap();
+        assert_eq!(
+            split.starter,
+            indoc! {"
+                fn sum(v: &[i32]) -> i32 {
+                    // Add them up
+                    todo!()
+                }
+
+                fn main() {
+                    let mut v = vec![];
+                    todo!();
+
+                    // The sum
+                    println!(\"{}\", sum(&v));
+                    let w = (
+                        1,
+                        todo!()
+                    );
+                }
+            "}
+        );
+        assert!(!split.solution.contains("MODMOD"));
+        assert!(split.solution.contains("    v.push(1);\n"));
+    }
+
+    #[test]
+    fn todo_keeps_crlf_line_endings() {
+        let content = "fn f() {\r\n    // MODMOD-SOLUTION-START\r\n    1\r\n    // MODMOD-SOLUTION-END\r\n}\r\n";
+        let split = split_ok(content).unwrap();
+        assert_eq!(split.starter, "fn f() {\r\n    todo!()\r\n}\r\n");
+        assert_eq!(split.solution, "fn f() {\r\n    1\r\n}\r\n");
+    }
+}
@@ -0,0 +1,230 @@
+use std::{fmt, ops::AddAssign};
+
+use error_stack::{Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    conditional::Conditions,
+    estimates::TopicEstimate,
+    slides::{count_slides, topic_slides},
+    ContentFilter, Topic, Track, Unit,
+};
+
+/// Minutes of teaching assumed per slide, for units without session durations
+pub(crate) const MINUTES_PER_SLIDE: u32 = 2;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct StatsError {}
+
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to collect track statistics")
+    }
+}
+
+impl error_stack::Context for StatsError {}
+
+/// The size of a track, with a breakdown per module and unit
+#[derive(Debug, Serialize)]
+pub struct TrackStats {
+    pub track: String,
+    pub modules: Vec<ModuleStats>,
+    pub total: Counts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleStats {
+    pub index: usize,
+    /// The label of the module, in the numbering style of the track
+    pub label: String,
+    pub name: String,
+    pub units: Vec<UnitStats>,
+    pub total: Counts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitStats {
+    pub index: usize,
+    pub name: String,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Counts {
+    pub units: usize,
+    pub topics: usize,
+    /// Approximate number of slides of topic content, counted from the slide separators
+    pub slides: usize,
+    pub exercises: usize,
+    pub images: usize,
+    /// The durations of the sessions, or the estimates of the topics and exercises for
+    /// units whose sessions have no duration, see [`unit_counts`]
+    pub estimated_minutes: u32,
+    /// The part of [`Counts::estimated_minutes`] that isn't optional material
+    pub core_minutes: u32,
+    /// The part of [`Counts::estimated_minutes`] spent on optional topics and exercises
+    pub optional_minutes: u32,
+}
+
+impl Counts {
+    pub fn hours(&self) -> f64 {
+        f64::from(self.estimated_minutes) / 60.0
+    }
+
+    pub fn core_hours(&self) -> f64 {
+        f64::from(self.core_minutes) / 60.0
+    }
+
+    pub fn optional_hours(&self) -> f64 {
+        f64::from(self.optional_minutes) / 60.0
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.units += other.units;
+        self.topics += other.topics;
+        self.slides += other.slides;
+        self.exercises += other.exercises;
+        self.images += other.images;
+        self.estimated_minutes += other.estimated_minutes;
+        self.core_minutes += other.core_minutes;
+        self.optional_minutes += other.optional_minutes;
+    }
+}
+
+impl TrackStats {
+    /// Counts all content of the track. Conditional blocks in the slides are resolved
+    /// as when the track is rendered without a profile.
+    pub fn collect(track: &Track) -> Result<Self, StatsError> {
+        let conditions = Conditions {
+            profile: None,
+            known_profiles: &track.profiles,
+            variables: &track.variables,
+        };
+        let mut total = Counts::default();
+        let mut modules = Vec::with_capacity(track.modules.len());
+        for module in track.modules.iter() {
+            let mut module_total = Counts::default();
+            let mut units = Vec::with_capacity(module.data.units.len());
+            for unit in module.data.units.iter() {
+                let counts = unit_counts(&unit.data, &conditions)?;
+                module_total += counts;
+                units.push(UnitStats {
+                    index: unit.index,
+                    name: unit.data.name.clone(),
+                    counts,
+                });
+            }
+            total += module_total;
+            modules.push(ModuleStats {
+                index: module.index,
+                label: module.data.label.display.clone(),
+                name: module.data.name.clone(),
+                units,
+                total: module_total,
+            });
+        }
+        Ok(Self {
+            track: track.name.clone(),
+            modules,
+            total,
+        })
+    }
+}
+
+/// The counts of `unit`. Its topics and exercises take the minutes of their
+/// [`TopicEstimate`]. When the sessions of the unit set their durations, those make up the
+/// estimate instead, of which the optional topics and exercises take their minutes.
+pub(crate) fn unit_counts(unit: &Unit, conditions: &Conditions) -> Result<Counts, StatsError> {
+    let mut counts = Counts {
+        units: 1,
+        topics: unit.topics.len(),
+        ..Default::default()
+    };
+    for topic in unit.topics.iter().map(|t| &t.data) {
+        counts.exercises += topic.exercises.len();
+        counts.images += topic.images.len();
+        let slides = match unit.render_slides {
+            true => topic_slide_count(topic, conditions)?,
+            false => 0,
+        };
+        counts.slides += slides;
+        let (core, optional) =
+            TopicEstimate::of(topic, ContentFilter::default()).minutes(topic.optional, slides);
+        counts.core_minutes += core;
+        counts.optional_minutes += optional;
+    }
+    let scheduled: Vec<_> = unit
+        .sessions
+        .iter()
+        .filter_map(|s| s.duration_minutes)
+        .collect();
+    if !scheduled.is_empty() {
+        let minutes = scheduled.iter().sum();
+        counts.optional_minutes = counts.optional_minutes.min(minutes);
+        counts.core_minutes = minutes - counts.optional_minutes;
+    }
+    counts.estimated_minutes = counts.core_minutes + counts.optional_minutes;
+    Ok(counts)
+}
+
+/// The number of slides a topic adds to the deck of its unit
+pub(crate) fn topic_slide_count(
+    topic: &Topic,
+    conditions: &Conditions,
+) -> Result<usize, StatsError> {
+    let slides = topic_slides(&topic.content, &topic.name, conditions)
+        .change_context(StatsError::default())?;
+    Ok(match slides.is_empty() {
+        true => 0,
+        // Optional topics are announced with a slide of their own
+        false => count_slides(&slides) + usize::from(topic.optional),
+    })
+}
+
+impl fmt::Display for TrackStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter, name: &str, c: &Counts| {
+            writeln!(
+                f,
+                "{name:<50} {:>5} {:>6} {:>6} {:>9} {:>6} {:>6.1} {:>10.1}",
+                c.units,
+                c.topics,
+                c.slides,
+                c.exercises,
+                c.images,
+                c.core_hours(),
+                c.optional_hours()
+            )
+        };
+        writeln!(f, "Statistics of track '{}'", self.track)?;
+        writeln!(
+            f,
+            "{:<50} {:>5} {:>6} {:>6} {:>9} {:>6} {:>6} {:>10}",
+            "", "Units", "Topics", "Slides", "Exercises", "Images", "Core h", "Optional h"
+        )?;
+        for module in self.modules.iter() {
+            row(
+                f,
+                format!("{} {}", module.label, module.name).trim_start(),
+                &module.total,
+            )?;
+            for unit in module.units.iter() {
+                let number = match module.label.is_empty() {
+                    true => unit.index.to_string(),
+                    false => format!("{}.{}", module.label, unit.index),
+                };
+                let name = format!("  {number} {}", unit.name);
+                row(f, &name, &unit.counts)?;
+            }
+        }
+        row(
+            f,
+            &format!("Total ({} module(s))", self.modules.len()),
+            &self.total,
+        )
+    }
+}
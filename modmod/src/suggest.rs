@@ -0,0 +1,108 @@
+//! Suggestions for misspelled keys and ids, like `objectives` for `objektives`, so that errors
+//! about names that don't exist can say which name was probably meant.
+//!
+//! Names are compared by their edit distance, ignoring case. A name is only suggested when
+//! it is a few edits away, so that a name that is simply not there doesn't get a suggestion
+//! that has nothing to do with it.
+
+/// The name among `candidates` that `name` is most likely a misspelling of, if any is close
+/// enough: at most a third of the characters of `name` differ, and at least one doesn't.
+/// Of names that are equally close, the first is taken.
+pub fn closest<'c>(name: &str, candidates: impl IntoIterator<Item = &'c str>) -> Option<&'c str> {
+    let len = name.chars().count();
+    let max_distance = (len / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance && *distance < len)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// ` Did you mean '<closest>'?` if there is a [`closest`] name, to end an error message with
+pub fn did_you_mean(name: &str, candidates: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    let candidates: Vec<_> = candidates.into_iter().collect();
+    match closest(name, candidates.iter().map(AsRef::as_ref)) {
+        Some(candidate) => format!(" Did you mean '{candidate}'?"),
+        None => String::new(),
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, ignoring case: the number of characters that
+/// have to be inserted, removed or replaced to turn one into the other
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != b);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(distance("objectives", "objectives"), 0);
+        assert_eq!(distance("objektives", "objectives"), 1);
+        assert_eq!(distance("objectivs", "objectives"), 1);
+        assert_eq!(distance("Objectives", "objectives"), 0);
+        assert_eq!(distance("tpoics", "topics"), 2);
+        assert_eq!(distance("", "name"), 4);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn names_up_to_five_characters_may_be_one_edit_off() {
+        assert_eq!(closest("nmae", ["name"]), None);
+        assert_eq!(closest("nme", ["name"]), Some("name"));
+        assert_eq!(closest("abcdx", ["abcde"]), Some("abcde"));
+        assert_eq!(closest("abcxy", ["abcde"]), None);
+        // A name of one character has nothing in common with another one
+        assert_eq!(closest("x", ["y"]), None);
+        assert_eq!(closest("ab", ["ac"]), Some("ac"));
+    }
+
+    #[test]
+    fn longer_names_may_be_a_third_off() {
+        // Six characters, two edits
+        assert_eq!(closest("abcdxy", ["abcdef"]), Some("abcdef"));
+        assert_eq!(closest("abcxyz", ["abcdef"]), None);
+        // Nine characters, three edits
+        assert_eq!(closest("abcdefxyz", ["abcdefghi"]), Some("abcdefghi"));
+        assert_eq!(closest("abcdewxyz", ["abcdefghi"]), None);
+        assert_eq!(closest("objektives", ["objectives"]), Some("objectives"));
+    }
+
+    #[test]
+    fn absent_names_get_no_suggestion() {
+        let keys = ["name", "objectives", "summary", "further_reading"];
+        for name in ["", "x", "license", "sessions", "deadline", "future_key"] {
+            assert_eq!(closest(name, keys), None, "{name}");
+        }
+        assert_eq!(did_you_mean("deadline", keys), "");
+    }
+
+    #[test]
+    fn the_closest_name_is_suggested() {
+        let keys = ["summary", "objectives", "objective"];
+        assert_eq!(closest("objectves", keys), Some("objectives"));
+        assert_eq!(closest("objectiv", keys), Some("objective"));
+        // Of names that are as close, the first
+        assert_eq!(closest("bc", ["ac", "bd"]), Some("ac"));
+        // A name isn't its own misspelling, but another case of it is
+        assert_eq!(closest("name", ["name"]), None);
+        assert_eq!(closest("Name", ["name"]), Some("name"));
+        assert_eq!(did_you_mean("summray", keys), " Did you mean 'summary'?");
+    }
+}
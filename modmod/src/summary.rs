@@ -0,0 +1,140 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use error_stack::{IntoReport, Result, ResultExt};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::{load::HydrateTrackError, DeriveSummaries};
+
+/// Headings that never make a good summary item
+const SKIPPED_HEADINGS: &[&str] = &["exercise", "exercises"];
+
+/// Derives summary items for topics that don't have any, from the level-2 headings of their slides
+#[derive(Debug)]
+pub(crate) struct SummaryDeriver {
+    max_items: usize,
+    exclude: GlobSet,
+}
+
+impl SummaryDeriver {
+    pub(crate) fn new(options: &DeriveSummaries) -> Result<Self, HydrateTrackError> {
+        let mut exclude = GlobSetBuilder::new();
+        for pattern in options.exclude.iter() {
+            exclude.add(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error parsing excluded heading glob '{pattern}'")
+                    })
+                    .change_context(HydrateTrackError)?,
+            );
+        }
+        Ok(Self {
+            max_items: options.max_items,
+            exclude: exclude
+                .build()
+                .into_report()
+                .change_context(HydrateTrackError)?,
+        })
+    }
+
+    /// The plain text of the level-2 headings in the content, in order and without repetitions.
+    /// Headings in code blocks are skipped, as are excluded ones.
+    pub(crate) fn derive(&self, content: &str) -> Vec<String> {
+        let mut items: Vec<String> = vec![];
+        let mut fence: Option<&str> = None;
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            match fence {
+                Some(f) if trimmed.starts_with(f) => fence = None,
+                Some(_) => {}
+                None if trimmed.starts_with("```") => fence = Some("```"),
+                None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+                None => {}
+            }
+            if fence.is_some() {
+                continue;
+            }
+            let Some(heading) = line.strip_prefix("## ") else {
+                continue;
+            };
+            let heading = plain_text(heading.trim().trim_end_matches('#'));
+            if heading.is_empty()
+                || SKIPPED_HEADINGS.contains(&heading.to_lowercase().as_str())
+                || self.exclude.is_match(&heading)
+                || items.contains(&heading)
+            {
+                continue;
+            }
+            items.push(heading);
+            if items.len() == self.max_items {
+                break;
+            }
+        }
+        items
+    }
+}
+
+/// Strips emphasis, links, images, inline code markers and HTML tags from a line of markdown
+fn plain_text(markdown: &str) -> String {
+    let chars: Vec<char> = markdown.chars().collect();
+    let mut text = String::with_capacity(markdown.len());
+    let mut i = 0;
+    let find = |from: usize, c: char| chars[from..].iter().position(|&d| d == c).map(|p| from + p);
+    let is_word = |i: Option<&char>| i.is_some_and(|c| c.is_alphanumeric());
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                text.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                if let Some(end) = find(i + 1, '`') {
+                    text.extend(&chars[i + 1..end]);
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => {
+                i += 1;
+                continue;
+            }
+            '[' => {
+                // A link is replaced by its text, which is stripped in turn
+                let link = find(i + 1, ']')
+                    .filter(|&end| chars.get(end + 1) == Some(&'('))
+                    .and_then(|end| Some((end, find(end + 2, ')')?)));
+                if let Some((end, url_end)) = link {
+                    text += &plain_text(&chars[i + 1..end].iter().collect::<String>());
+                    i = url_end + 1;
+                    continue;
+                }
+            }
+            '<' if chars
+                .get(i + 1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '/') =>
+            {
+                if let Some(end) = find(i + 1, '>') {
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '*' | '~' => {
+                i += 1;
+                continue;
+            }
+            // Underscores within words, like in snake_case names, are not emphasis
+            '_' if !(i > 0 && is_word(chars.get(i - 1)) && is_word(chars.get(i + 1))) => {
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,161 @@
+//! Inspecting the slide template of a deck: which placeholders it uses, and what they are
+//! replaced by, and rendering a single deck to see what it ends up as
+
+use std::{fmt, path::PathBuf};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::{
+    book::Book,
+    conditional::Conditions,
+    exercises::ExerciseCollection,
+    report::Warning,
+    slides::{RenderSlidesError, SlidesPackage},
+    suggest,
+    version::CourseVersion,
+    ContentFilter, Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct TemplateError {}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to inspect slide template")
+    }
+}
+
+impl error_stack::Context for TemplateError {}
+
+/// The placeholders of the template of a deck, with the values they get for that deck
+#[derive(Debug)]
+pub struct TemplateInspection {
+    /// The deck, as `<module>.<unit>` and the name of its unit
+    pub deck: String,
+    /// The template of the deck, or `None` for the built-in one
+    pub template: Option<PathBuf>,
+    /// All placeholders modmod fills in, in the order they are replaced in
+    pub placeholders: Vec<PlaceholderUse>,
+    /// Placeholders the template uses, but that are replaced by nothing or don't exist
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug)]
+pub struct PlaceholderUse {
+    /// The placeholder as it's written in templates, like `#[modmod:unit_title]`
+    pub marker: String,
+    pub description: String,
+    /// Whether the template of the deck contains the placeholder
+    pub used: bool,
+    /// What the placeholder is replaced by in this deck
+    pub value: String,
+}
+
+/// A single deck as [`Track::render`] writes it, see [`Track::render_deck`]
+#[derive(Debug)]
+pub struct RenderedDeckSource {
+    /// The deck, as `<module>.<unit>` and the name of its unit
+    pub deck: String,
+    /// Where rendering writes the deck, relative to the output folder
+    pub path: PathBuf,
+    pub markdown: String,
+    /// The images of the deck, which rendering copies into the images folder of the slides
+    pub images: Vec<PathBuf>,
+    /// Problems in the deck and its template, like placeholders that don't exist
+    pub warnings: Vec<Warning>,
+}
+
+impl Track {
+    /// Renders only the deck selected by `deck`, which is either its number like `3.2`, its
+    /// prefix like `3_2`, or the id of its unit, the same way [`Track::render`] does, and
+    /// returns its markdown instead of writing it. Conditional content is resolved for
+    /// `profile`.
+    pub fn render_deck(
+        &self,
+        deck: &str,
+        theme: &str,
+        profile: Option<&str>,
+    ) -> Result<RenderedDeckSource, TemplateError> {
+        self.with_slides_package(profile, |package, conditions| {
+            package.render_deck(deck, theme, &self.output, conditions)
+        })
+    }
+
+    /// Inspects the template of the deck selected by `deck`, which is either its number
+    /// like `3.2`, its prefix like `3_2`, or the id of its unit. Conditional content is
+    /// resolved for `profile`.
+    pub fn inspect_template(
+        &self,
+        deck: &str,
+        theme: &str,
+        profile: Option<&str>,
+    ) -> Result<TemplateInspection, TemplateError> {
+        self.with_slides_package(profile, |package, conditions| {
+            package.inspect(deck, theme, conditions)
+        })
+    }
+
+    /// Builds the slides package the track renders, for `profile`, and passes it to `f`
+    /// with the conditions to resolve the content with
+    fn with_slides_package<R>(
+        &self,
+        profile: Option<&str>,
+        f: impl FnOnce(&SlidesPackage, &Conditions) -> Result<R, RenderSlidesError>,
+    ) -> Result<R, TemplateError> {
+        if let Some(profile) = profile.filter(|p| !self.profiles.iter().any(|known| known == p)) {
+            return Err(
+                Report::new(TemplateError::default()).attach_printable(format!(
+                    "Unknown profile '{profile}'.{}",
+                    suggest::did_you_mean(profile, &self.profiles)
+                )),
+            );
+        }
+        let filter = ContentFilter {
+            skip_optional: false,
+            profile,
+            units: &[],
+        };
+        let conditions = Conditions {
+            profile,
+            known_profiles: &self.profiles,
+            variables: &self.variables,
+        };
+        // Validating the version is left to rendering
+        let course_version = self
+            .version
+            .as_deref()
+            .and_then(|version| CourseVersion::parse(version, true).ok());
+        let mut book_builder = Book::builder(&self.name);
+        let mut slides_builder = SlidesPackage::builder(&self.name);
+        slides_builder.course(&self.course);
+        if let Some(license) = &self.license {
+            slides_builder.license(license);
+        }
+        if let Some(version) = &course_version {
+            slides_builder.course_version(version);
+        }
+        if let Some(template) = &self.default_slide_template {
+            slides_builder.default_template(template);
+        }
+        let mut exercises_builder = ExerciseCollection::builder();
+        for module in self.modules.iter() {
+            module
+                .render(
+                    &mut book_builder,
+                    &mut slides_builder,
+                    &mut exercises_builder,
+                    filter,
+                )
+                .change_context(TemplateError::default())?;
+        }
+        // Like in a render, the decks link to where the exercises go
+        let exercise_paths = exercises_builder
+            .build()
+            .output_paths(self.output.exercises());
+        slides_builder
+            .exercise_paths(&exercise_paths, self.output.slides())
+            .change_context(TemplateError::default())?;
+        f(&slides_builder.build(), &conditions).change_context(TemplateError::default())
+    }
+}
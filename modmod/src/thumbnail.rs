@@ -0,0 +1,147 @@
+//! Previews of the title slides of the decks, for the landing page of the site and the
+//! catalog of tracks, exported by `generate --thumbnails` into [`THUMBS_DIR`] of the slides.
+//!
+//! Each preview is exported with the `export-<deck>` script of the slides package as a PNG
+//! of the first slide, which needs the `playwright-chromium` package of the slides to be
+//! installed. Without it, or without npm, the previews are skipped with a warning, and the
+//! render goes on. Decks that are rendered with the same content are left alone, so that
+//! their modification time doesn't change, see [`crate::io::rewrite_unchanged`]: a preview
+//! that is newer than its deck is kept instead of being exported again.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+use error_stack::Result;
+
+use crate::{
+    io::{self, PathExt},
+    report::{Warning, WarningCode},
+    slides::WrittenDeck,
+    LoadTrackError, OutputLayout,
+};
+
+/// The folder of the slides the previews are written into, as `<deck>.png`
+pub const THUMBS_DIR: &str = "thumbs";
+
+/// The path of the preview of the deck with `prefix`, relative to the output folder
+pub(crate) fn thumbnail_path(layout: &OutputLayout, prefix: &str) -> PathBuf {
+    layout
+        .slides()
+        .join(THUMBS_DIR)
+        .join(format!("{prefix}.png"))
+}
+
+/// Exports the previews of the `decks` rendered into `out_dir` that are missing or older
+/// than their deck, and returns the prefixes of the decks that have one. Decks whose preview
+/// can't be exported are left without one.
+pub(crate) fn export(
+    out_dir: &Path,
+    layout: &OutputLayout,
+    decks: &[WrittenDeck],
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<String>, LoadTrackError> {
+    let slides_dir = out_dir.join(layout.slides());
+    let thumbs_dir = slides_dir.join(THUMBS_DIR);
+    let mut thumbnails = vec![];
+    let mut stale = vec![];
+    for deck in decks {
+        let thumb = out_dir.join(thumbnail_path(layout, &deck.prefix));
+        let source = slides_dir.join(&deck.slug).with_extension("md");
+        match (modified(&thumb), modified(&source)) {
+            (Some(thumb_time), Some(deck_time)) if thumb_time >= deck_time => {
+                io::keep_file(&thumb);
+                thumbnails.push(deck.prefix.clone());
+            }
+            _ => stale.push(deck),
+        }
+    }
+    if stale.is_empty() {
+        return Ok(thumbnails);
+    }
+    if !slides_dir.join("node_modules/playwright-chromium").is_dir() {
+        warnings.push(Warning::new(
+            WarningCode::ThumbnailsSkipped,
+            format!(
+                "The previews of {} deck(s) are not exported, as playwright-chromium isn't installed. Run `npm install` in {} and render again",
+                stale.len(),
+                slides_dir.display()
+            ),
+            None,
+        ));
+        return Ok(thumbnails);
+    }
+
+    thumbs_dir.create_dir_all()?;
+    for deck in stale {
+        // Slidev writes the slides into a folder, as one file per slide
+        let export_dir = thumbs_dir.join(format!(".export-{}", deck.prefix));
+        if export_dir.exists() {
+            export_dir.remove_dir_all()?;
+        }
+        let script = format!("export-{}", deck.prefix);
+        let output = Command::new("npm")
+            .args(["run", &script, "--", "--format", "png", "--range", "1"])
+            .arg("--output")
+            .arg(&export_dir)
+            .current_dir(&slides_dir)
+            .output();
+        let failure = match &output {
+            Err(e) => Some(format!("npm can't be run: {e}")),
+            Ok(output) if !output.status.success() => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Some(match stderr.lines().rfind(|l| !l.trim().is_empty()) {
+                    Some(line) => format!("`npm run {script}` failed: {}", line.trim()),
+                    None => format!("`npm run {script}` failed: {}", output.status),
+                })
+            }
+            Ok(_) => None,
+        };
+        let png = first_png(&export_dir);
+        match (failure, png) {
+            (None, Some(png)) => {
+                let thumb = out_dir.join(thumbnail_path(layout, &deck.prefix));
+                // A preview with the same content would keep the time it was exported before
+                if thumb.exists() {
+                    thumb.remove_file()?;
+                }
+                png.copy(thumb)?;
+                thumbnails.push(deck.prefix.clone());
+            }
+            (failure, _) => warnings.push(Warning::new(
+                WarningCode::ThumbnailsSkipped,
+                format!(
+                    "The preview of deck '{}' is not exported: {}",
+                    deck.name,
+                    failure.unwrap_or_else(|| "Slidev wrote no image".to_string())
+                ),
+                None,
+            )),
+        }
+        if export_dir.exists() {
+            export_dir.remove_dir_all()?;
+        }
+    }
+    // In the order of the decks
+    thumbnails.sort_by_key(|prefix| decks.iter().position(|d| d.prefix == *prefix));
+    Ok(thumbnails)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The image of the first slide Slidev exported into `dir`, which it names after the number
+/// of the slide
+fn first_png(dir: &Path) -> Option<PathBuf> {
+    let mut pngs: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    pngs.sort();
+    pngs.into_iter().next()
+}
@@ -0,0 +1,179 @@
+//! The files the exercises are worked on with: a `rust-toolchain.toml` that pins the Rust
+//! toolchain, and, if the track asks for one, a devcontainer with that toolchain, Node.js for
+//! the slides and mdbook for the book, which builds the exercises once when it is created.
+//! All of them are written into the root of the exercises output from the same [`Toolchain`],
+//! so the versions they install can't disagree. Files that modmod didn't generate are kept,
+//! see [`crate::scaffold`].
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use std::{fmt, path::Path};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde_json::json;
+
+use crate::{
+    io::PathExt,
+    report::Warning,
+    scaffold::{Scaffold, GENERATED},
+    Toolchain,
+};
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct WriteToolchainError;
+
+impl fmt::Display for WriteToolchainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to write the toolchain files of the exercises")
+    }
+}
+
+impl error_stack::Context for WriteToolchainError {}
+
+/// Says where the generated files come from, at their top
+const HEADER: &str = "from the [toolchain] table of the track definition";
+
+/// The image the devcontainer is based on, which has rustup and the tools to build crates
+const BASE_IMAGE: &str = "mcr.microsoft.com/devcontainers/rust:1";
+
+/// The script that builds the exercises when the devcontainer is created, relative to the
+/// exercises output
+const POST_CREATE: &str = ".devcontainer/post-create.sh";
+
+/// Writes the toolchain files of `toolchain` into `exercises_dir`, the root of the exercises
+/// output. `exercises` are the folders of the exercise packages, relative to it, which the
+/// devcontainer builds when it is created. Files that modmod didn't generate are only replaced
+/// with `force`.
+pub(crate) fn write(
+    toolchain: &Toolchain,
+    track_name: &str,
+    exercises_dir: &Path,
+    exercises: &[String],
+    force: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), WriteToolchainError> {
+    validate(toolchain)?;
+    let mut scaffold = Scaffold { force, warnings };
+    scaffold.write::<WriteToolchainError>(
+        &exercises_dir.join("rust-toolchain.toml"),
+        &rust_toolchain(toolchain)?,
+        None,
+    )?;
+    if !toolchain.devcontainer {
+        return Ok(());
+    }
+    let devcontainer = exercises_dir.join(".devcontainer");
+    devcontainer.create_dir_all()?;
+    scaffold.write::<WriteToolchainError>(
+        &devcontainer.join("devcontainer.json"),
+        &devcontainer_json(toolchain, track_name)?,
+        None,
+    )?;
+    scaffold.write::<WriteToolchainError>(
+        &devcontainer.join("Dockerfile"),
+        &dockerfile(toolchain),
+        None,
+    )?;
+    scaffold.write::<WriteToolchainError>(
+        &exercises_dir.join(POST_CREATE),
+        &post_create(exercises),
+        Some(0o755),
+    )
+}
+
+/// The versions end up in a Dockerfile and in commands, so they're limited to the characters
+/// versions and names of components and extensions have
+pub(crate) fn validate(toolchain: &Toolchain) -> Result<(), WriteToolchainError> {
+    let values = [("rust", &toolchain.rust), ("node", &toolchain.node)]
+        .into_iter()
+        .chain(toolchain.mdbook.iter().map(|v| ("mdbook", v)))
+        .chain(toolchain.components.iter().map(|c| ("components", c)))
+        .chain(toolchain.extensions.iter().map(|e| ("extensions", e)));
+    for (key, value) in values {
+        let valid = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !valid {
+            return Err(Report::new(WriteToolchainError).attach_printable(format!(
+                "Invalid value '{value}' for `{key}` in the [toolchain] table. It can only have letters, digits, '.', '-' and '_'"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn rust_toolchain(toolchain: &Toolchain) -> Result<String, WriteToolchainError> {
+    let file = json!({
+        "toolchain": {
+            "channel": toolchain.rust,
+            "components": toolchain.components,
+        }
+    });
+    let content = toml::to_string(&file)
+        .into_report()
+        .change_context(WriteToolchainError)?;
+    Ok(format!("# {GENERATED} {HEADER}\n{content}"))
+}
+
+fn devcontainer_json(
+    toolchain: &Toolchain,
+    track_name: &str,
+) -> Result<String, WriteToolchainError> {
+    let config = json!({
+        "name": track_name,
+        "build": { "dockerfile": "Dockerfile" },
+        "features": {
+            "ghcr.io/devcontainers/features/node:1": { "version": toolchain.node },
+        },
+        "customizations": {
+            "vscode": { "extensions": toolchain.extensions },
+        },
+        "postCreateCommand": format!("sh {POST_CREATE}"),
+    });
+    let content = serde_json::to_string_pretty(&config)
+        .into_report()
+        .change_context(WriteToolchainError)?;
+    // devcontainer.json is JSON with comments
+    Ok(format!("// {GENERATED} {HEADER}\n{content}\n"))
+}
+
+fn dockerfile(toolchain: &Toolchain) -> String {
+    let components: String = toolchain
+        .components
+        .iter()
+        .map(|c| format!(" --component {c}"))
+        .collect();
+    let mdbook = match &toolchain.mdbook {
+        Some(version) => format!(" --version {version}"),
+        None => String::new(),
+    };
+    format!(
+        "# {GENERATED} {HEADER}
+FROM {BASE_IMAGE}
+# The toolchain of rust-toolchain.toml, so that it isn't downloaded on the first build
+RUN rustup toolchain install {rust} --profile minimal{components}
+RUN cargo install mdbook --locked{mdbook}
+",
+        rust = toolchain.rust,
+    )
+}
+
+fn post_create(exercises: &[String]) -> String {
+    let mut script = format!(
+        "#!/bin/sh
+# {GENERATED} {HEADER}
+# Builds the exercises once, so that their dependencies are there before the course starts.
+# Exercises that are meant to be fixed may not build yet, which is fine.
+cd \"$(dirname \"$0\")/..\" || exit 1
+build() {{
+    find \"$1\" -name Cargo.toml -not -path '*/target/*' -execdir cargo build --quiet \\;
+}}
+"
+    );
+    for exercise in exercises {
+        script.push_str(&format!("build '{}'\n", exercise.replace('\'', "'\\''")));
+    }
+    script
+}
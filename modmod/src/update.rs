@@ -0,0 +1,1161 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::{
+    cache::CACHE_FILE,
+    io::{self, PathExt, TEMP_SUFFIX},
+    manifest::{self, RenderManifest, RENDER_MANIFEST_FILE},
+    report::{Warning, WarningCode},
+    sha256::{self, hash},
+    version::CourseVersion,
+};
+
+/// Name of the file with the hashes of the generated files, in the root of the output
+/// folder. Every render writes it, see [`crate::Track::render`], and `update`, `diff`,
+/// `clean` and `verify-output` read it. Not to be confused with the render manifest of each track,
+/// [`RENDER_MANIFEST_FILE`], which the render writes for other tools.
+pub const HASHES_FILE: &str = ".modmod-hashes.json";
+
+/// The name [`HASHES_FILE`] had before, which is still read in output folders of
+/// earlier versions, and replaced by the new name when they're written again
+const OLD_HASHES_FILE: &str = ".modmod-manifest.json";
+
+/// Suffix of the files the generated version of a file is written to when it conflicts
+/// with local changes
+pub const CONFLICT_SUFFIX: &str = ".new";
+
+/// Folder in the root of the output folder that the files with local changes are saved
+/// into while the folder is rendered into again, see [`LocalEdits::save`]
+pub const EDITS_DIR: &str = ".modmod-edits";
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct UpdateError;
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to update output folder")
+    }
+}
+
+impl error_stack::Context for UpdateError {}
+
+/// The hashes of the files modmod wrote into an output folder, keyed on their path
+/// relative to that folder. Used to tell local changes apart from changes in the
+/// generated output when updating the folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FileHashes {
+    files: BTreeMap<String, String>,
+}
+
+impl FileHashes {
+    /// Hashes all files in `dir`, except for the manifest itself and conflicting versions
+    /// written by an earlier update. The files of the tracks rendered into its subfolders
+    /// get the hashes those tracks recorded, so that their local changes are still known as
+    /// such.
+    pub fn of_dir(dir: &Path) -> Result<Self, UpdateError> {
+        let mut tracks = vec![];
+        for path in dir_paths(dir)? {
+            let key = path.strip_prefix(dir).map(manifest::relative_path);
+            if let (Ok(key), Some(track_dir)) = (key, path.parent()) {
+                if is_track_hashes(&key) {
+                    if let Some(hashes) = Self::load(track_dir)? {
+                        let prefix = key.rsplit_once('/').unwrap_or_default().0;
+                        tracks.push((format!("{prefix}/"), hashes));
+                    }
+                }
+            }
+        }
+        let mut files = BTreeMap::new();
+        for (key, path) in dir_files(dir)? {
+            let recorded = tracks
+                .iter()
+                .find_map(|(prefix, hashes)| hashes.files.get(key.strip_prefix(prefix.as_str())?));
+            let hash = match recorded {
+                Some(hash) => hash.clone(),
+                None => hash(&read(&path)?),
+            };
+            files.insert(key, hash);
+        }
+        Ok(Self { files })
+    }
+
+    /// Loads the hashes of an output folder, if it has them
+    pub fn load(dir: &Path) -> Result<Option<Self>, UpdateError> {
+        let Some(path) = [HASHES_FILE, OLD_HASHES_FILE]
+            .into_iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+        else {
+            return Ok(None);
+        };
+        let mut manifest: Self = serde_json::from_str(&path.read_to_string()?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Invalid file hashes at {}", path.display()))
+            .change_context(UpdateError)?;
+        // Older versions of modmod wrote FNV-1a hashes. Files that are as modmod wrote them
+        // get their SHA-256 hash instead, and edited files keep the old hash, which matches
+        // no content, so that they are still taken to be edited.
+        for (key, hash) in manifest.files.iter_mut() {
+            if hash.len() != 16 {
+                continue;
+            }
+            if let Ok(content) = io::read(&dir.join(key)) {
+                if stable_hash(&content) == *hash {
+                    *hash = sha256::hash(&content);
+                }
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<(), UpdateError> {
+        let json = serde_json::to_string_pretty(self)
+            .into_report()
+            .change_context(UpdateError)?;
+        dir.join(HASHES_FILE).write_file(json)?;
+        let old = dir.join(OLD_HASHES_FILE);
+        match old.is_file() {
+            true => old.remove_file(),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Whether `dir` can be rendered into without `--force`: it doesn't exist, it's empty, or
+/// it holds the output of an earlier render, which left a manifest or a journal
+pub fn is_output_dir(dir: &Path) -> Result<bool, UpdateError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => {
+            return Err(Report::new(e)
+                .attach_printable(format!("Unable to read folder {}", dir.display()))
+                .change_context(UpdateError))
+        }
+    };
+    if entries.count() == 0
+        || dir.join(HASHES_FILE).is_file()
+        || dir.join(OLD_HASHES_FILE).is_file()
+        || io::journal_path(dir).is_file()
+    {
+        return Ok(true);
+    }
+    // Output that was copied without its hidden files still has the render manifests of
+    // its tracks
+    Ok(!RenderManifest::read_all(dir)
+        .change_context(UpdateError)?
+        .is_empty())
+}
+
+/// The outcome of updating an output folder. Paths are relative to the folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateReport {
+    pub out_dir: PathBuf,
+    /// Files that weren't in the folder yet
+    pub added: Vec<String>,
+    /// Files that weren't changed locally, and were replaced by their new version
+    pub updated: Vec<String>,
+    /// Files that are the same as their new version, and were left untouched
+    pub unchanged: usize,
+    /// Files that were changed locally but not in the generated output, and were kept
+    pub kept: Vec<String>,
+    /// Files that were changed both locally and in the generated output. Their new version
+    /// is written next to them, with the [`CONFLICT_SUFFIX`].
+    pub conflicts: Vec<String>,
+    /// Files that were generated by an earlier run, but no longer are. They are left in place.
+    pub stale: Vec<String>,
+    /// Whether the folder had a manifest. Without one, all files that differ from their
+    /// new version are treated as conflicts.
+    pub had_manifest: bool,
+}
+
+impl fmt::Display for UpdateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            out_dir,
+            added,
+            updated,
+            unchanged,
+            kept,
+            conflicts,
+            stale,
+            had_manifest,
+        } = self;
+        writeln!(
+            f,
+            "Updated {} by modmod {}",
+            out_dir.display(),
+            crate::VERSION
+        )?;
+        write!(
+            f,
+            "  {} file(s) added, {} updated, {unchanged} unchanged",
+            added.len(),
+            updated.len()
+        )?;
+        if !kept.is_empty() {
+            write!(
+                f,
+                "\n  {} file(s) with local changes kept, as their generated version didn't change",
+                kept.len()
+            )?;
+        }
+        if !had_manifest {
+            write!(
+                f,
+                "\n  warning: no {HASHES_FILE} found, so all files that differ from their generated version are treated as changed locally"
+            )?;
+        }
+        for path in stale {
+            write!(
+                f,
+                "\n  warning: {path} is no longer generated, and was left in place"
+            )?;
+        }
+        if !conflicts.is_empty() {
+            write!(
+                f,
+                "\n{} conflict(s), changed both locally and in the generated output:",
+                conflicts.len()
+            )?;
+            for path in conflicts {
+                write!(
+                    f,
+                    "\n  {path}: new version written to {path}{CONFLICT_SUFFIX}"
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Updates `out_dir` to the output that was freshly generated into `new_dir`, keeping local
+/// changes. Files that weren't changed locally since the previous run, according to the
+/// manifest in `out_dir`, are replaced. Files that are the same aren't written at all, so
+/// that their modification time is kept. The manifest is updated to the new output.
+pub fn update(new_dir: &Path, out_dir: &Path) -> Result<UpdateReport, UpdateError> {
+    let previous = FileHashes::load(out_dir)?;
+    let mut report = UpdateReport {
+        out_dir: out_dir.to_path_buf(),
+        had_manifest: previous.is_some(),
+        ..Default::default()
+    };
+    let previous = previous.unwrap_or_default();
+    out_dir.create_dir_all()?;
+    let mut manifest = FileHashes::default();
+
+    for (key, new_path) in dir_files(new_dir)? {
+        let new = read(&new_path)?;
+        let new_hash = hash(&new);
+        let out_path = out_dir.join(&key);
+        if !out_path.exists() {
+            if let Some(parent) = out_path.parent() {
+                parent.create_dir_all()?;
+            }
+            new_path.copy(&out_path)?;
+            report.added.push(key.clone());
+        } else {
+            let old = read(&out_path)?;
+            if old == new {
+                report.unchanged += 1;
+            } else {
+                match previous.files.get(&key) {
+                    Some(previous_hash) if *previous_hash == hash(&old) => {
+                        new_path.copy(&out_path)?;
+                        report.updated.push(key.clone());
+                    }
+                    Some(previous_hash) if *previous_hash == new_hash => {
+                        report.kept.push(key.clone());
+                    }
+                    _ => {
+                        new_path.copy(conflict_path(&out_path))?;
+                        report.conflicts.push(key.clone());
+                    }
+                }
+            }
+        }
+        manifest.files.insert(key, new_hash);
+    }
+
+    for path in new_dir.get_dir_content::<UpdateError>()?.files {
+        let path = PathBuf::from(path);
+        let Ok(key) = path.strip_prefix(new_dir) else {
+            continue;
+        };
+        let key_str = manifest::relative_path(key);
+        if is_render_manifest(&key_str) || is_track_hashes(&key_str) {
+            path.copy(out_dir.join(key))?;
+        }
+    }
+
+    report.stale = previous
+        .files
+        .into_keys()
+        .filter(|key| !manifest.files.contains_key(key) && out_dir.join(key).exists())
+        .collect();
+    manifest.write(out_dir)?;
+    Ok(report)
+}
+
+/// The files of an output folder that were changed locally since modmod last wrote them,
+/// according to its hashes, with their content. A render that clears the folder takes them
+/// and [saves](LocalEdits::save) them before rendering into it, and puts them back with
+/// [`LocalEdits::restore`] after, or with [`LocalEdits::put_back`] if it fails, so that
+/// changes made to the output by hand aren't lost.
+#[derive(Debug, Default)]
+pub struct LocalEdits {
+    /// The hash modmod recorded for each file, and its content with the local changes
+    files: Vec<(String, String, Vec<u8>)>,
+}
+
+impl LocalEdits {
+    /// The files of `out_dir` that differ from what modmod wrote, and those that a render
+    /// which didn't finish saved. A folder without hashes has none of its own, as there's
+    /// nothing to tell changed files by.
+    pub fn of_dir(out_dir: &Path) -> Result<Self, UpdateError> {
+        // The files of a render that didn't finish may have overwritten the saved ones
+        let Self { mut files } = Self::saved(out_dir)?;
+        let Some(previous) = FileHashes::load(out_dir)? else {
+            return Ok(Self { files });
+        };
+        for (key, previous_hash) in previous.files {
+            let path = out_dir.join(&key);
+            if !path.is_file() || files.iter().any(|(saved, ..)| *saved == key) {
+                continue;
+            }
+            let content = read(&path)?;
+            if hash(&content) != previous_hash {
+                files.push((key, previous_hash, content));
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// The edits an earlier render [saved](Self::save) into `out_dir`, which are still
+    /// there if it didn't finish
+    fn saved(out_dir: &Path) -> Result<Self, UpdateError> {
+        let dir = out_dir.join(EDITS_DIR);
+        let Some(saved) = FileHashes::load(&dir)? else {
+            return Ok(Self::default());
+        };
+        let files = saved
+            .files
+            .into_iter()
+            .map(|(key, previous_hash)| {
+                let content = read(&dir.join(&key))?;
+                Ok((key, previous_hash, content))
+            })
+            .collect::<Result<_, UpdateError>>()?;
+        Ok(Self { files })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Saves the files into the [`EDITS_DIR`] of `out_dir`, before it's rendered into, so
+    /// that the local changes are still there if the render is interrupted. The hashes are
+    /// written last, so that a folder that wasn't saved completely isn't read.
+    pub fn save(&self, out_dir: &Path) -> Result<(), UpdateError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let dir = out_dir.join(EDITS_DIR);
+        let mut saved = FileHashes::default();
+        for (key, previous_hash, content) in self.files.iter() {
+            let path = dir.join(key);
+            if let Some(parent) = path.parent() {
+                parent.create_dir_all()?;
+            }
+            path.write_file(content)?;
+            saved.files.insert(key.clone(), previous_hash.clone());
+        }
+        saved.write(&dir)
+    }
+
+    /// Puts the local changes back into `out_dir` as they were, after a render into it
+    /// failed, and removes the saved files. The other files that modmod wrote before get
+    /// the hashes of what the render replaced them with, so that they aren't taken for
+    /// local changes.
+    pub fn put_back(self, out_dir: &Path) -> Result<(), UpdateError> {
+        if let Some(mut hashes) = FileHashes::load(out_dir)? {
+            for (key, recorded) in hashes.files.iter_mut() {
+                let path = out_dir.join(key);
+                if path.is_file() && !self.files.iter().any(|(edited, ..)| edited == key) {
+                    *recorded = hash(&read(&path)?);
+                }
+            }
+            hashes.write(out_dir)?;
+        }
+        for (key, _, content) in self.files {
+            let path = out_dir.join(&key);
+            if let Some(parent) = path.parent() {
+                parent.create_dir_all()?;
+            }
+            path.write_file(content)?;
+        }
+        remove_saved_edits(out_dir)
+    }
+
+    /// Puts the local changes back into `out_dir` after a render into it, whose files
+    /// `hashes` are. Files that were rendered the same as before are kept as they were
+    /// changed, and files whose generated version changed too get it written next to them,
+    /// with the [`CONFLICT_SUFFIX`], like with [`update`]. Files that are no longer
+    /// generated are put back as well, and keep their earlier hash, so that they're still
+    /// known to be changed.
+    pub fn restore(
+        self,
+        out_dir: &Path,
+        hashes: &mut FileHashes,
+    ) -> Result<UpdateReport, UpdateError> {
+        let mut report = UpdateReport {
+            out_dir: out_dir.to_path_buf(),
+            had_manifest: true,
+            ..Default::default()
+        };
+        for (key, previous_hash, content) in self.files {
+            let path = out_dir.join(&key);
+            match hashes.files.get(&key) {
+                // Either rendered as it was changed, or not rendered at all, like the
+                // output of another locale. It's still known as changed.
+                Some(new_hash) if *new_hash == hash(&content) => {
+                    hashes.files.insert(key, previous_hash);
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(new_hash) if *new_hash == previous_hash => report.kept.push(key.clone()),
+                Some(_) => {
+                    path.copy(conflict_path(&path))?;
+                    report.conflicts.push(key.clone());
+                }
+                None => {
+                    if let Some(parent) = path.parent() {
+                        parent.create_dir_all()?;
+                    }
+                    hashes.files.insert(key.clone(), previous_hash);
+                    report.stale.push(key.clone());
+                }
+            }
+            path.write_file(content)?;
+        }
+        remove_saved_edits(out_dir)?;
+        Ok(report)
+    }
+}
+
+fn remove_saved_edits(out_dir: &Path) -> Result<(), UpdateError> {
+    let dir = out_dir.join(EDITS_DIR);
+    match dir.exists() {
+        true => dir.remove_dir_all(),
+        false => Ok(()),
+    }
+}
+
+/// Warns about the tracks in `out_dir` that were rendered as a newer course version than
+/// the one freshly generated into `new_dir`, which usually means that the wrong branch is
+/// checked out. Free-form versions can't be compared, so they are never warned about.
+pub fn check_course_versions(new_dir: &Path, out_dir: &Path) -> Result<Vec<Warning>, UpdateError> {
+    let mut warnings = vec![];
+    for (track_dir, new) in RenderManifest::read_all(new_dir).change_context(UpdateError)? {
+        let out_track_dir = match track_dir.as_os_str().is_empty() {
+            true => out_dir.to_path_buf(),
+            false => out_dir.join(&track_dir),
+        };
+        let Some(old) = RenderManifest::read(&out_track_dir).change_context(UpdateError)? else {
+            continue;
+        };
+        let (Some(old_version), Some(new_version)) = (old.course_version, new.course_version)
+        else {
+            continue;
+        };
+        let (Ok(old_version), Ok(new_version)) = (
+            CourseVersion::parse(&old_version, true),
+            CourseVersion::parse(&new_version, true),
+        ) else {
+            continue;
+        };
+        if old_version.compare(&new_version) == Some(std::cmp::Ordering::Greater) {
+            warnings.push(Warning::new(
+WarningCode::CourseVersionDowngrade,
+                format!(
+                    "The output of track '{}' was rendered as course version {old_version}, which is newer than version {new_version} that it's updated to. Is the right branch checked out?",
+                    new.track
+                ),
+                Some(&out_track_dir),
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// The outcome of cleaning an output folder. Paths are relative to the folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CleanReport {
+    pub out_dir: PathBuf,
+    /// Whether nothing was removed, only listed
+    pub dry_run: bool,
+    /// Generated files that were removed
+    pub removed: Vec<String>,
+    /// Generated files that were changed locally, and were kept
+    pub modified: Vec<String>,
+    /// Folders that were emptied by removing the files, and were removed too
+    pub pruned: Vec<String>,
+    /// The journal of a render that didn't finish, which was removed
+    pub journal: Option<PathBuf>,
+}
+
+impl fmt::Display for CleanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            out_dir,
+            dry_run,
+            removed,
+            modified,
+            pruned,
+            journal,
+        } = self;
+        let verb = match dry_run {
+            true => "Would remove",
+            false => "Removed",
+        };
+        if let Some(journal) = journal {
+            writeln!(
+                f,
+                "{verb} the journal {} of a render that didn't finish",
+                journal.display()
+            )?;
+        }
+        for path in removed {
+            writeln!(f, "{verb} {path}")?;
+        }
+        for path in pruned {
+            writeln!(f, "{verb} {path}/")?;
+        }
+        write!(
+            f,
+            "{verb} {} generated file(s) and {} empty folder(s) from {}",
+            removed.len(),
+            pruned.len(),
+            out_dir.display()
+        )?;
+        if !modified.is_empty() {
+            write!(
+                f,
+                "\n{} file(s) changed since they were generated were kept, use --force to remove them too:",
+                modified.len()
+            )?;
+            for path in modified {
+                write!(f, "\n  {path}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes the files modmod generated into `out_dir`, according to its manifest, leaving
+/// the files that were added to the folder in place. Generated files that were changed
+/// since are kept too, unless `force` is set. Folders that end up empty are removed.
+/// While writes are discarded, the report lists what would be removed.
+///
+/// The files that a render which didn't finish wrote are removed too, as listed in its
+/// journal, see [`io::begin_journal`], along with the journal and the temporary files the
+/// render left.
+///
+/// Fails if there is neither a manifest nor a journal, as without them there is no
+/// telling which files were generated.
+pub fn clean(out_dir: &Path, force: bool) -> Result<CleanReport, UpdateError> {
+    let journal = io::read_journal::<UpdateError>(out_dir)?;
+    let manifest = match (FileHashes::load(out_dir)?, &journal) {
+        (Some(manifest), _) => manifest,
+        (None, Some(_)) => FileHashes::default(),
+        (None, None) => {
+            return Err(Report::new(UpdateError).attach_printable(format!(
+                "No {HASHES_FILE} in {}, so it's unknown which files were generated",
+                out_dir.display()
+            )))
+        }
+    };
+    let mut report = CleanReport {
+        out_dir: out_dir.to_path_buf(),
+        dry_run: crate::io::writes_discarded_at(out_dir),
+        ..Default::default()
+    };
+    for (key, generated_hash) in manifest.files {
+        let path = out_dir.join(&key);
+        if !path.is_file() {
+            continue;
+        }
+        if force || hash(&read(&path)?) == generated_hash {
+            report.removed.push(key.clone());
+        } else {
+            report.modified.push(key.clone());
+        }
+        // The generated versions of conflicting files are left over from updates
+        let conflict = format!("{key}{CONFLICT_SUFFIX}");
+        if out_dir.join(&conflict).is_file() {
+            report.removed.push(conflict);
+        }
+    }
+    let content = out_dir.get_dir_content::<UpdateError>()?;
+    let relative = |path: &String| {
+        let path = Path::new(path).strip_prefix(out_dir).ok()?;
+        Some(path.to_path_buf()).filter(|path| !path.as_os_str().is_empty())
+    };
+    for key in content.files.iter().filter_map(relative) {
+        let key = manifest::relative_path(&key);
+        let hashes = is_track_hashes(&key) && report.modified.is_empty();
+        if is_render_manifest(&key) || hashes || key == CACHE_FILE || key.ends_with(TEMP_SUFFIX) {
+            report.removed.push(key);
+        }
+    }
+    // The saved local changes are put back by the next render
+    for key in journal.iter().flatten() {
+        let saved = Path::new(key).starts_with(EDITS_DIR);
+        if !saved && out_dir.join(key).is_file() && !report.removed.contains(key) {
+            report.removed.push(key.clone());
+        }
+    }
+    // Without the manifest, a later clean couldn't remove the files that were kept
+    for name in [HASHES_FILE, OLD_HASHES_FILE] {
+        if report.modified.is_empty() && out_dir.join(name).is_file() {
+            report.removed.push(name.to_string());
+        }
+    }
+    report.removed.sort();
+
+    // Folders that would be empty, deepest first so that their parents can be emptied too.
+    // That includes empty folders that were generated, like those of exercises without files.
+    let mut dirs: Vec<_> = content.directories.iter().filter_map(relative).collect();
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    let removed: BTreeSet<_> = report.removed.iter().map(PathBuf::from).collect();
+    let mut pruned = BTreeSet::new();
+    for dir in dirs {
+        let entries = fs::read_dir(out_dir.join(&dir))
+            .into_report()
+            .attach_printable_lazy(|| format!("Error reading folder {}", dir.display()))
+            .change_context(UpdateError)?;
+        let mut entries = entries.filter_map(|entry| entry.ok());
+        let emptied = entries.all(|entry| {
+            let entry = dir.join(entry.file_name());
+            removed.contains(&entry) || pruned.contains(&entry)
+        });
+        if emptied {
+            pruned.insert(dir);
+        }
+    }
+
+    for key in report.removed.iter() {
+        out_dir.join(key).remove_file()?;
+    }
+    // Deepest first again
+    for dir in pruned.iter().rev() {
+        out_dir.join(dir).remove_dir()?;
+    }
+    if journal.is_some() {
+        let path = io::journal_path(out_dir);
+        path.remove_file()?;
+        report.journal = Some(path);
+    }
+    report.pruned = pruned
+        .iter()
+        .map(|dir| manifest::relative_path(dir))
+        .collect();
+    Ok(report)
+}
+
+/// How freshly generated output differs from an existing output folder. Paths are
+/// relative to the folder.
+#[derive(Debug, Default)]
+pub struct OutputDiff {
+    /// Files that aren't in the folder yet
+    pub added: Vec<String>,
+    /// Files in the folder that are no longer generated. When the folder has a manifest,
+    /// only the files in it are considered.
+    pub removed: Vec<String>,
+    pub modified: Vec<ModifiedFile>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug)]
+pub struct ModifiedFile {
+    pub path: String,
+    /// Whether the file isn't text, in which case it's only compared by hash
+    pub binary: bool,
+    /// Unified diff of the text in the folder to the new text, if asked for
+    pub diff: Option<String>,
+}
+
+impl OutputDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl fmt::Display for OutputDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            added,
+            removed,
+            modified,
+            unchanged,
+        } = self;
+        write!(
+            f,
+            "{} file(s) added, {} removed, {} modified, {unchanged} unchanged",
+            added.len(),
+            removed.len(),
+            modified.len()
+        )?;
+        for path in added {
+            write!(f, "\n  added: {path}")?;
+        }
+        for path in removed {
+            write!(f, "\n  removed: {path}")?;
+        }
+        for file in modified {
+            write!(f, "\n  modified: {}", file.path)?;
+            if file.binary {
+                write!(f, " (binary)")?;
+            }
+        }
+        for diff in modified.iter().filter_map(|file| file.diff.as_ref()) {
+            write!(f, "\n\n{}", diff.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the output that was freshly generated into `new_dir` with `out_dir`. With
+/// `unified`, the changes to modified text files are included as unified diffs.
+pub fn diff(new_dir: &Path, out_dir: &Path, unified: bool) -> Result<OutputDiff, UpdateError> {
+    let mut diff = OutputDiff::default();
+    let new_files = dir_files(new_dir)?;
+    // With a manifest, files that modmod didn't write, like the installed node modules
+    // of the slides package, aren't reported as removed
+    let old_files: Vec<String> = match FileHashes::load(out_dir)? {
+        Some(manifest) => manifest
+            .files
+            .into_keys()
+            .filter(|key| out_dir.join(key).exists())
+            .collect(),
+        None if out_dir.exists() => dir_files(out_dir)?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect(),
+        None => vec![],
+    };
+    let new_keys: BTreeSet<_> = new_files.iter().map(|(key, _)| key.as_str()).collect();
+    diff.removed = old_files
+        .into_iter()
+        .filter(|key| !new_keys.contains(key.as_str()))
+        .collect();
+
+    for (key, new_path) in new_files {
+        let out_path = out_dir.join(&key);
+        if !out_path.exists() {
+            diff.added.push(key);
+            continue;
+        }
+        let new = read(&new_path)?;
+        let old = read(&out_path)?;
+        if hash(&new) == hash(&old) {
+            diff.unchanged += 1;
+            continue;
+        }
+        let (binary, unified_diff) = match (std::str::from_utf8(&old), std::str::from_utf8(&new)) {
+            (Ok(old), Ok(new)) => {
+                let unified_diff = unified.then(|| {
+                    TextDiff::from_lines(old, new)
+                        .unified_diff()
+                        .header(&format!("a/{key}"), &format!("b/{key}"))
+                        .to_string()
+                });
+                (false, unified_diff)
+            }
+            _ => (true, None),
+        };
+        diff.modified.push(ModifiedFile {
+            path: key,
+            binary,
+            diff: unified_diff,
+        });
+    }
+    Ok(diff)
+}
+
+/// How a published output folder drifted from its sources, see [`verify`]. Paths are
+/// relative to the folder.
+#[derive(Debug, Default)]
+pub struct OutputDrift {
+    pub out_dir: PathBuf,
+    /// Files that are generated differently, because their sources changed since the
+    /// folder was published
+    pub source_changed: Vec<String>,
+    /// Files that were edited in the folder since it was published. Publishing again
+    /// reverts them.
+    pub edited: Vec<String>,
+    /// Files that were edited in the folder, and whose sources changed as well
+    pub edited_and_source_changed: Vec<String>,
+    /// Files that were published and are still generated, but were removed from the folder
+    pub missing: Vec<String>,
+    /// Files that are generated, but weren't published yet
+    pub unpublished: Vec<String>,
+    /// Files that were published, but are no longer generated
+    pub stale: Vec<String>,
+    /// Files that modmod didn't write, in folders that it did write files into
+    pub extra: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl OutputDrift {
+    pub fn is_empty(&self) -> bool {
+        self.source_changed.is_empty()
+            && self.edited.is_empty()
+            && self.edited_and_source_changed.is_empty()
+            && self.missing.is_empty()
+            && self.unpublished.is_empty()
+            && self.stale.is_empty()
+            && self.extra.is_empty()
+    }
+
+    /// Whether the folder was changed by hand, rather than only being behind its sources
+    pub fn edited_by_hand(&self) -> bool {
+        !self.edited.is_empty()
+            || !self.edited_and_source_changed.is_empty()
+            || !self.missing.is_empty()
+            || !self.extra.is_empty()
+    }
+}
+
+impl fmt::Display for OutputDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            out_dir,
+            source_changed,
+            edited,
+            edited_and_source_changed,
+            missing,
+            unpublished,
+            stale,
+            extra,
+            unchanged,
+        } = self;
+        if self.is_empty() {
+            return write!(
+                f,
+                "The output in {} matches its sources, {unchanged} file(s) checked",
+                out_dir.display()
+            );
+        }
+        let sections = [
+            ("Source changed since publish", source_changed),
+            ("Output edited by hand", edited),
+            (
+                "Output edited by hand, and source changed since publish",
+                edited_and_source_changed,
+            ),
+            ("Missing from the output", missing),
+            ("Not published yet", unpublished),
+            ("No longer generated", stale),
+            ("Not generated by modmod", extra),
+        ];
+        write!(
+            f,
+            "The output in {} drifted from its sources, {unchanged} file(s) unchanged",
+            out_dir.display()
+        )?;
+        for (title, paths) in sections.into_iter().filter(|(_, paths)| !paths.is_empty()) {
+            write!(f, "\n{title}:")?;
+            for path in paths {
+                write!(f, "\n  {path}")?;
+            }
+        }
+        if self.edited_by_hand() {
+            write!(
+                f,
+                "\nChanges made by hand are lost when the output is generated again, make them in the sources instead"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies that the published `out_dir` still matches the output freshly generated into
+/// `new_dir`. The hashes in the manifest of `out_dir` tell the files that were edited in
+/// the folder apart from the ones whose sources changed since it was published.
+///
+/// Fails if there is no manifest, as without one there is no telling which it was.
+pub fn verify(new_dir: &Path, out_dir: &Path) -> Result<OutputDrift, UpdateError> {
+    let Some(published) = FileHashes::load(out_dir)? else {
+        return Err(Report::new(UpdateError).attach_printable(format!(
+            "No {HASHES_FILE} in {}, so it's unknown what was published",
+            out_dir.display()
+        )));
+    };
+    let mut drift = OutputDrift {
+        out_dir: out_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let new_files = dir_files(new_dir)?;
+    for (key, new_path) in new_files.iter() {
+        let out_path = out_dir.join(key);
+        let published_hash = published.files.get(key);
+        if !out_path.exists() {
+            match published_hash {
+                Some(_) => drift.missing.push(key.clone()),
+                None => drift.unpublished.push(key.clone()),
+            }
+            continue;
+        }
+        let new_hash = hash(&read(new_path)?);
+        let old_hash = hash(&read(&out_path)?);
+        if new_hash == old_hash {
+            drift.unchanged += 1;
+            continue;
+        }
+        match published_hash {
+            Some(published_hash) if *published_hash == old_hash => {
+                drift.source_changed.push(key.clone())
+            }
+            Some(published_hash) if *published_hash != new_hash => {
+                drift.edited_and_source_changed.push(key.clone())
+            }
+            // Without a hash, the file was added by hand where modmod now generates one
+            _ => drift.edited.push(key.clone()),
+        }
+    }
+
+    let new_keys: BTreeSet<_> = new_files.iter().map(|(key, _)| key.as_str()).collect();
+    drift.stale = published
+        .files
+        .keys()
+        .filter(|key| !new_keys.contains(key.as_str()) && out_dir.join(key).exists())
+        .cloned()
+        .collect();
+    // Other files, like the installed node modules of the slides package or a CI
+    // configuration next to the output, are left alone
+    let dir_of = |key: &str| key.rsplit_once('/').map(|(dir, _)| dir.to_string());
+    let generated_dirs: BTreeSet<_> = new_keys.iter().filter_map(|key| dir_of(key)).collect();
+    drift.extra = dir_files(out_dir)?
+        .into_iter()
+        .map(|(key, _)| key)
+        .filter(|key| {
+            !new_keys.contains(key.as_str())
+                && !published.files.contains_key(key)
+                && dir_of(key).is_some_and(|dir| generated_dirs.contains(&dir))
+        })
+        .collect();
+    Ok(drift)
+}
+
+/// Name of the file in a patch folder that lists the patches and copies in the order they
+/// are applied, see [`emit_patches`]
+pub const SERIES_FILE: &str = "series";
+
+/// The patches written by [`emit_patches`]. Paths are relative to the patch folder.
+#[derive(Debug, Default)]
+pub struct PatchSeries {
+    pub patch_dir: PathBuf,
+    /// Patches of text files, in the order they are applied
+    pub patches: Vec<String>,
+    /// Files that aren't text, which are copied into the output folder as a whole
+    pub copies: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl fmt::Display for PatchSeries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            patch_dir,
+            patches,
+            copies,
+            unchanged,
+        } = self;
+        write!(
+            f,
+            "Wrote {} patch(es) and {} file(s) to copy into {}, {unchanged} file(s) unchanged\n  apply them in the order listed in {}",
+            patches.len(),
+            copies.len(),
+            patch_dir.display(),
+            patch_dir.join(SERIES_FILE).display()
+        )
+    }
+}
+
+/// Writes the changes that would bring `out_dir` up to the output that was freshly
+/// generated into `new_dir` as patches into `patch_dir`, instead of making them. Each
+/// added or changed text file gets a unified diff, which applies with `git apply` or
+/// `patch -p1` from the root of `out_dir`. Files that aren't text can't be diffed, and
+/// are copied into `patch_dir` as a whole. The [`SERIES_FILE`] lists both in order, with a
+/// `copy <from> <to>` line for each copy.
+///
+/// Like [`update`], files that are no longer generated are left alone. Unlike it, local
+/// changes aren't kept: the patches are relative to what is in `out_dir` now. The manifest
+/// is patched too, so that a later update sees the patched files as generated.
+pub fn emit_patches(
+    new_dir: &Path,
+    out_dir: &Path,
+    patch_dir: &Path,
+) -> Result<PatchSeries, UpdateError> {
+    // Patches of an earlier run would end up in the series
+    let is_empty =
+        |dir: &Path| fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_none());
+    if patch_dir.exists() && !is_empty(patch_dir) {
+        return Err(Report::new(UpdateError).attach_printable(format!(
+            "The patch folder {} is not empty",
+            patch_dir.display()
+        )));
+    }
+    patch_dir.create_dir_all()?;
+
+    let mut manifest = FileHashes::default();
+    let mut changes = vec![];
+    let mut series = PatchSeries {
+        patch_dir: patch_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let read_old = |key: &str| {
+        let path = out_dir.join(key);
+        path.exists().then(|| read(&path)).transpose()
+    };
+    for (key, new_path) in dir_files(new_dir)? {
+        let new = read(&new_path)?;
+        manifest.files.insert(key.clone(), hash(&new));
+        let old = read_old(&key)?;
+        match old.as_ref() == Some(&new) {
+            true => series.unchanged += 1,
+            false => changes.push((key, old, new)),
+        }
+    }
+    let new_manifest = serde_json::to_string_pretty(&manifest)
+        .into_report()
+        .change_context(UpdateError)?
+        .into_bytes();
+    let old_manifest = read_old(HASHES_FILE)?;
+    if old_manifest.as_ref() != Some(&new_manifest) {
+        changes.push((HASHES_FILE.to_string(), old_manifest, new_manifest));
+    }
+
+    let mut lines = vec![
+        "# Apply in this order from the root of the output folder. Files that aren't text are copied as a whole.".to_string(),
+    ];
+    for (i, (key, old, new)) in changes.into_iter().enumerate() {
+        let old_text = match old.as_deref().map(std::str::from_utf8) {
+            Some(Ok(text)) => Some(text),
+            Some(Err(_)) => None,
+            None => Some(""),
+        };
+        match (old_text, std::str::from_utf8(&new)) {
+            (Some(old_text), Ok(new_text)) => {
+                let name = format!("{:04}-{}.patch", i + 1, key.replace('/', "-"));
+                let old_header = match old {
+                    Some(_) => format!("a/{key}"),
+                    None => "/dev/null".to_string(),
+                };
+                let patch = TextDiff::from_lines(old_text, new_text)
+                    .unified_diff()
+                    .header(&old_header, &format!("b/{key}"))
+                    .to_string();
+                patch_dir.join(&name).write_file(patch)?;
+                lines.push(name.clone());
+                series.patches.push(name);
+            }
+            _ => {
+                let name = format!("files/{key}");
+                let path = patch_dir.join(&name);
+                path.parent().unwrap().create_dir_all()?;
+                path.write_file(new)?;
+                lines.push(format!("copy {name} {key}"));
+                series.copies.push(name);
+            }
+        }
+    }
+    lines.push(String::new());
+    patch_dir.join(SERIES_FILE).write_file(lines.join("\n"))?;
+    Ok(series)
+}
+
+/// The files in `dir` that are part of the generated output, keyed on their path relative
+/// to `dir`, with forward slashes
+fn dir_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, UpdateError> {
+    let mut files: Vec<_> = dir_paths(dir)?
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(dir).ok()?;
+            let key = manifest::relative_path(relative);
+            // The render cache only holds for the folder it was rendered into
+            (key != HASHES_FILE
+                && key != OLD_HASHES_FILE
+                && key != CACHE_FILE
+                && key != io::JOURNAL_FILE
+                && !relative.starts_with(EDITS_DIR)
+                && !is_track_hashes(&key)
+                && !key.ends_with(CONFLICT_SUFFIX)
+                && !key.ends_with(TEMP_SUFFIX)
+                && !is_render_manifest(&key))
+            .then_some((key, path))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// All files in `dir` and its subfolders, which may have names that aren't UTF-8
+fn dir_paths(dir: &Path) -> Result<Vec<PathBuf>, UpdateError> {
+    Ok(io::walk_dir::<UpdateError>(dir, false)?
+        .into_iter()
+        .map(|entry| entry.path().to_path_buf())
+        .collect())
+}
+
+/// Render manifests hold the time they were written, so they differ on each run. They
+/// aren't compared, and are always replaced on updates.
+fn is_render_manifest(key: &str) -> bool {
+    key.rsplit('/').next() == Some(RENDER_MANIFEST_FILE)
+}
+
+/// The hashes that a track rendered into a subfolder of the output folder wrote, which are
+/// replaced on updates like the render manifests
+fn is_track_hashes(key: &str) -> bool {
+    key.contains('/')
+        && [HASHES_FILE, OLD_HASHES_FILE].contains(&key.rsplit('/').next().unwrap_or(key))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, UpdateError> {
+    fs::read(path)
+        .into_report()
+        .attach_printable_lazy(|| format!("Error reading file at path {}", path.display()))
+        .change_context(UpdateError)
+}
+
+/// `slides.md` becomes `slides.md.new`
+fn conflict_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(CONFLICT_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// The FNV-1a hash of `content`, as hex. Unlike the hashers of the standard library, it's
+/// stable between Rust versions, for names that have to stay the same, like slugs. Content
+/// is told apart with [`sha256::hash`].
+pub(crate) fn stable_hash(content: &[u8]) -> String {
+    format!("{:016x}", fnv1a(FNV_OFFSET, content))
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+pub(crate) fn fnv1a(mut hash: u64, content: &[u8]) -> u64 {
+    for byte in content {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,233 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// The URL the output of a track is deployed under, like `/` for the root of a site,
+/// `/training/rust-2025/` for a subpath, or `https://example.com/training/` with an origin.
+///
+/// It always ends in a single `/`, and its path always starts with one, so `training`,
+/// `/training` and `/training/` are the same base. URLs of the output are made with
+/// [`BaseUrl::join`], so that they follow these rules everywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseUrl {
+    /// Scheme and host, like `https://example.com`, or empty for a base that is only a path
+    origin: String,
+    /// The path, starting and ending with a `/`
+    path: String,
+}
+
+impl BaseUrl {
+    /// The root of the site, `/`
+    pub fn root() -> Self {
+        Self {
+            origin: String::new(),
+            path: "/".to_string(),
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.origin.is_empty() && self.path == "/"
+    }
+
+    /// The origin, like `https://example.com`, if the base has one
+    pub fn origin(&self) -> Option<&str> {
+        Some(self.origin.as_str()).filter(|origin| !origin.is_empty())
+    }
+
+    /// The path of the base, like `/training/rust-2025/`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The URL of `path`, relative to the base. Leading slashes of `path` are ignored, and a
+    /// trailing one is kept, so `slides/2_1/` and `/slides/2_1/` both give
+    /// `/training/slides/2_1/` for the base `/training/`.
+    pub fn join(&self, path: &str) -> String {
+        format!("{}{}", self.origin, self.join_path(path))
+    }
+
+    /// Like [`BaseUrl::join`], but without the origin, for settings that only take a path
+    pub fn join_path(&self, path: &str) -> String {
+        format!("{}{}", self.path, path.trim_start_matches('/'))
+    }
+}
+
+impl Default for BaseUrl {
+    fn default() -> Self {
+        Self::root()
+    }
+}
+
+impl fmt::Display for BaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.origin, self.path)
+    }
+}
+
+impl Serialize for BaseUrl {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BaseUrl {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        url.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidBaseUrl(String);
+
+impl fmt::Display for InvalidBaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid base URL: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidBaseUrl {}
+
+impl FromStr for BaseUrl {
+    type Err = InvalidBaseUrl;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(c) = s.chars().find(|c| c.is_whitespace() || "?#".contains(*c)) {
+            return Err(InvalidBaseUrl(format!(
+                "'{s}' contains '{c}', but a base URL can only have a scheme, a host and a path"
+            )));
+        }
+        if s.starts_with("//") {
+            return Err(InvalidBaseUrl(format!(
+                "'{s}' has no scheme, use e.g. https:{s} instead"
+            )));
+        }
+
+        let (origin, path) = match s.split_once("://") {
+            Some((scheme, rest)) => {
+                let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+                let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+                if !valid_scheme || host.is_empty() {
+                    return Err(InvalidBaseUrl(format!(
+                        "'{s}' is neither a path nor of the form <scheme>://<host>/<path>"
+                    )));
+                }
+                (format!("{scheme}://{host}"), path)
+            }
+            None => (String::new(), s),
+        };
+
+        let segments: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.iter().any(|s| *s == "." || *s == "..") {
+            return Err(InvalidBaseUrl(format!(
+                "'{s}' can't contain '.' or '..' segments"
+            )));
+        }
+        let path = segments
+            .iter()
+            .fold(String::from("/"), |path, segment| path + segment + "/");
+        Ok(Self { origin, path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base(s: &str) -> BaseUrl {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn paths_get_one_leading_and_trailing_slash() {
+        for s in [
+            "training",
+            "/training",
+            "/training/",
+            "training/",
+            "/training//",
+        ] {
+            assert_eq!(base(s).path(), "/training/", "{s}");
+        }
+        assert_eq!(base("a//b///c").path(), "/a/b/c/");
+        assert_eq!(
+            base("/training/rust-2025").to_string(),
+            "/training/rust-2025/"
+        );
+        assert_eq!(base("  /training  ").path(), "/training/");
+    }
+
+    #[test]
+    fn empty_bases_are_the_root() {
+        for s in ["", "/", "   "] {
+            let url = base(s);
+            assert!(url.is_root(), "{s}");
+            assert_eq!(url, BaseUrl::root());
+            assert_eq!(url.to_string(), "/");
+        }
+        assert_eq!(BaseUrl::default(), BaseUrl::root());
+        assert_eq!(BaseUrl::root().join("slides/1_1/"), "/slides/1_1/");
+    }
+
+    #[test]
+    fn absolute_bases_keep_their_origin() {
+        let url = base("https://example.com/training");
+        assert_eq!(url.origin(), Some("https://example.com"));
+        assert_eq!(url.path(), "/training/");
+        assert_eq!(url.to_string(), "https://example.com/training/");
+        assert!(!url.is_root());
+
+        let url = base("http://localhost:3000");
+        assert_eq!(url.origin(), Some("http://localhost:3000"));
+        assert_eq!(url.path(), "/");
+        assert!(!url.is_root());
+        assert_eq!(base("/training/").origin(), None);
+    }
+
+    #[test]
+    fn joins_relative_and_absolute_paths() {
+        let url = base("/training/");
+        for path in ["slides/2_1/", "/slides/2_1/", "//slides/2_1/"] {
+            assert_eq!(url.join(path), "/training/slides/2_1/", "{path}");
+        }
+        assert_eq!(url.join("book/index.html"), "/training/book/index.html");
+        assert_eq!(url.join(""), "/training/");
+
+        let url = base("https://example.com/training/");
+        assert_eq!(
+            url.join("/slides/2_1/"),
+            "https://example.com/training/slides/2_1/"
+        );
+        assert_eq!(url.join_path("/slides/2_1/"), "/training/slides/2_1/");
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_base() {
+        for (s, message) in [
+            ("/training?x=1", "contains '?'"),
+            ("/training#top", "contains '#'"),
+            ("/rust training", "contains ' '"),
+            ("//example.com/training", "has no scheme"),
+            ("https:///training", "neither a path nor"),
+            ("1http://example.com", "neither a path nor"),
+            ("/training/../other", "'..' segments"),
+            ("./training", "'..' segments"),
+        ] {
+            let error = s.parse::<BaseUrl>().unwrap_err().to_string();
+            assert!(error.contains(message), "{s}: {error}");
+            assert!(error.starts_with("invalid base URL: "), "{error}");
+        }
+    }
+
+    #[test]
+    fn serializes_as_its_normalized_string() {
+        let url: BaseUrl = serde_json::from_str("\"training\"").unwrap();
+        assert_eq!(serde_json::to_string(&url).unwrap(), "\"/training/\"");
+        let error = serde_json::from_str::<BaseUrl>("\"/a?b\"").unwrap_err();
+        assert!(error.to_string().contains("contains '?'"), "{error}");
+    }
+}
@@ -0,0 +1,103 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// The version of a course a track is rendered as, like the edition of a quarter, set by
+/// `version` in the track definition or `--course-version`.
+///
+/// Versions are loosely semver: one to three numbers, optionally prefixed with `v` and
+/// followed by a pre-release or build suffix, like `2025.1`, `v3` or `1.2.0-rc.1`. Any
+/// other line of text is only accepted as a free-form version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CourseVersion(String);
+
+impl CourseVersion {
+    /// Parses a version, which can be any line of text if `free_form` is set
+    pub fn parse(version: &str, free_form: bool) -> Result<Self, InvalidCourseVersion> {
+        let version = version.trim();
+        if version.is_empty() || version.contains(char::is_control) {
+            return Err(InvalidCourseVersion(format!(
+                "'{}' must be a single line of text",
+                version.escape_debug()
+            )));
+        }
+        if !free_form && loose_semver(version).is_none() {
+            return Err(InvalidCourseVersion(format!(
+                "'{version}' is not a version like 2025.1 or 1.2.0-rc.1. Use --free-form-version to allow any version"
+            )));
+        }
+        Ok(Self(version.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Compares the versions, if both are loosely semver. Free-form versions can't be
+    /// compared.
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        Some(loose_semver(&self.0)?.cmp_precedence(&loose_semver(&other.0)?))
+    }
+
+    /// The version with the characters that don't belong in file names replaced by `-`
+    pub fn file_name_part(&self) -> String {
+        self.0
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() || "._+-".contains(c) {
+                true => c,
+                false => '-',
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for CourseVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for CourseVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Versions are read back as free-form ones, as a render may have been made with any version
+impl<'de> Deserialize<'de> for CourseVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        Self::parse(&version, true).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidCourseVersion(String);
+
+impl fmt::Display for InvalidCourseVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid course version: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCourseVersion {}
+
+/// The version as semver, with a missing minor or patch number taken as 0
+fn loose_semver(version: &str) -> Option<semver::Version> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let end = version.find(['-', '+']).unwrap_or(version.len());
+    let (numbers, suffix) = version.split_at(end);
+    let parts: Vec<_> = numbers.split('.').collect();
+    let numeric = |part: &&str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if parts.len() > 3 || !parts.iter().all(numeric) {
+        return None;
+    }
+    let padded = parts
+        .iter()
+        .copied()
+        .chain(["0", "0"])
+        .take(3)
+        .collect::<Vec<_>>()
+        .join(".");
+    semver::Version::parse(&format!("{padded}{suffix}")).ok()
+}
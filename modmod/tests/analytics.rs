@@ -0,0 +1,179 @@
+//! The analytics script of the fixture track: left out without an `[analytics]` table, and
+//! loaded once by the slides and once by the book with it, with the settings escaped
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use common::{copy_basic_track, render_in_memory, TempDir};
+use modmod::{context::RunContext, RenderOptions, Track};
+
+/// The files of the fixture track rendered in memory, with `analytics` added to its track
+/// definition
+fn rendered_with(analytics: &str) -> Vec<(PathBuf, String)> {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("track"));
+    let definition = fs::read_to_string(&track_path).unwrap();
+    fs::write(&track_path, format!("{definition}\n{analytics}")).unwrap();
+    let track = Track::load(&track_path).unwrap();
+    let (_, files) = render_in_memory(&track, &RenderOptions::new(dir.join("out")));
+    files
+        .into_iter()
+        .map(|(path, content)| (path, String::from_utf8_lossy(&content).into_owned()))
+        .collect()
+}
+
+fn file<'a>(files: &'a [(PathBuf, String)], path: &str) -> Option<&'a str> {
+    files
+        .iter()
+        .find(|(p, _)| p.as_path() == Path::new(path))
+        .map(|(_, content)| content.as_str())
+}
+
+/// The files that mention `text`
+fn files_with<'a>(files: &'a [(PathBuf, String)], text: &str) -> Vec<&'a PathBuf> {
+    files
+        .iter()
+        .filter(|(_, content)| content.contains(text))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+#[test]
+fn nothing_is_added_without_analytics() {
+    let _context = RunContext::default().enter();
+    let files = rendered_with("");
+    assert_eq!(file(&files, "slides/index.html"), None);
+    assert_eq!(file(&files, "book/analytics.js"), None);
+    let book_toml = file(&files, "book/book.toml").unwrap();
+    assert!(!book_toml.contains("additional-js"), "{book_toml}");
+    for text in ["analytics", "plausible", "matomo"] {
+        assert!(files_with(&files, text).is_empty(), "{text}");
+    }
+}
+
+#[test]
+fn slides_and_book_load_the_script_once() {
+    let _context = RunContext::default().enter();
+    let files = rendered_with(
+        r#"[analytics]
+provider = "plausible"
+site_id = "rust.example.com"
+"#,
+    );
+    let index = file(&files, "slides/index.html").unwrap();
+    assert_eq!(index.matches("<script>").count(), 1, "{index}");
+    assert_eq!(index.matches("</script>").count(), 1, "{index}");
+    assert!(
+        index.contains("script.dataset.domain = \"rust.example.com\";"),
+        "{index}"
+    );
+    assert!(
+        index.contains("script.src = \"https://plausible.io/js/script.js\";"),
+        "{index}"
+    );
+
+    let script = file(&files, "book/analytics.js").unwrap();
+    assert!(script.contains("\"rust.example.com\""), "{script}");
+    let book_toml = file(&files, "book/book.toml").unwrap();
+    assert_eq!(
+        book_toml
+            .matches("additional-js = [\"analytics.js\"]")
+            .count(),
+        1,
+        "{book_toml}"
+    );
+
+    // Slidev merges the index into every deck, and mdbook adds the script to every page,
+    // so neither the decks nor the chapters load it again
+    let mut loading = files_with(&files, "rust.example.com");
+    loading.sort();
+    assert_eq!(
+        loading,
+        [
+            &PathBuf::from("book/analytics.js"),
+            &PathBuf::from("slides/index.html")
+        ]
+    );
+}
+
+#[test]
+fn matomo_gets_the_url_of_its_instance() {
+    let _context = RunContext::default().enter();
+    let files = rendered_with(
+        r#"[analytics]
+provider = "matomo"
+site_id = "7"
+script_url = "https://matomo.example.com"
+"#,
+    );
+    let script = file(&files, "book/analytics.js").unwrap();
+    assert!(
+        script.contains("var url = \"https://matomo.example.com/\";"),
+        "{script}"
+    );
+    assert!(
+        script.contains("_paq.push(['setSiteId', \"7\"]);"),
+        "{script}"
+    );
+    assert_eq!(script.matches("document.head.appendChild").count(), 1);
+}
+
+#[test]
+fn settings_are_escaped() {
+    let _context = RunContext::default().enter();
+    let files = rendered_with(
+        r#"[analytics]
+provider = "plausible"
+site_id = "x\"; alert('site'); \"</script><script>alert(1)</script>"
+script_url = "https://example.com/script.js?a=1&b='2'"
+"#,
+    );
+    let index = file(&files, "slides/index.html").unwrap();
+    // The settings can't end the string or the script
+    assert_eq!(index.matches("</script>").count(), 1, "{index}");
+    assert_eq!(index.matches("<script>").count(), 2, "{index}");
+    assert!(
+        index.contains(
+            r#"script.dataset.domain = "x\"; alert('site'); \"<\/script><script>alert(1)<\/script>";"#
+        ),
+        "{index}"
+    );
+    assert!(
+        index.contains(r#"script.src = "https://example.com/script.js?a=1&b='2'";"#),
+        "{index}"
+    );
+}
+
+#[test]
+fn invalid_settings_fail_loading() {
+    let _context = RunContext::default().enter();
+    for (analytics, problem) in [
+        (
+            "provider = \"matomo\"\nsite_id = \"7\"",
+            "[analytics] with provider matomo needs the URL of the Matomo instance as script_url",
+        ),
+        (
+            "provider = \"plausible\"\nsite_id = \" \"",
+            "The site_id of [analytics] is empty",
+        ),
+        (
+            "provider = \"plausible\"\nsite_id = \"a\"\nscript_url = \"javascript:alert(1)\"",
+            "The script_url of [analytics] is 'javascript:alert(1)', which isn't an http or https URL",
+        ),
+    ] {
+        let dir = TempDir::new();
+        let track_path = copy_basic_track(dir.path());
+        let definition = fs::read_to_string(&track_path).unwrap();
+        fs::write(
+            &track_path,
+            format!("{definition}\n[analytics]\n{analytics}\n"),
+        )
+        .unwrap();
+        let error = Track::load(&track_path).unwrap_err();
+        assert!(format!("{error:?}").contains(problem), "{error:?}");
+    }
+}
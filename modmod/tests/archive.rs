@@ -0,0 +1,124 @@
+//! An archive of a rendered track, unpacked again, must hold the files of the output folder
+//! that are for students, and nothing that depends on when or where it was rendered
+
+mod common;
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use common::{read_tree, render, TempDir};
+use modmod::{
+    archive::{self, ArchiveFormat, Entry, EntryContent},
+    cache::CACHE_FILE,
+    context::RunContext,
+    manifest::RENDER_MANIFEST_FILE,
+    update::HASHES_FILE,
+};
+use serde_json::Value;
+
+/// The render manifest without the fields that aren't archived
+fn without_timestamps(manifest: &[u8]) -> Value {
+    let mut manifest: Value = serde_json::from_slice(manifest).unwrap();
+    let object = manifest.as_object_mut().unwrap();
+    object.remove("generated_at");
+    object.remove("timings");
+    for artifact in object["artifacts"].as_array_mut().unwrap() {
+        artifact.as_object_mut().unwrap().remove("updated_at");
+    }
+    manifest
+}
+
+#[test]
+fn unpacked_archive_matches_the_output() {
+    let _context = RunContext::default().enter();
+    let track = common::basic_track();
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    // As left by `update` in an output folder that was updated
+    fs::write(out_dir.join(HASHES_FILE), "{}").unwrap();
+    let mut expected: BTreeMap<PathBuf, Vec<u8>> = read_tree(&out_dir).into_iter().collect();
+    assert!(expected.remove(&PathBuf::from(CACHE_FILE)).is_some());
+    assert!(expected.remove(&PathBuf::from(HASHES_FILE)).is_some());
+    let manifest = expected
+        .remove(&PathBuf::from(RENDER_MANIFEST_FILE))
+        .unwrap();
+
+    for (format, name) in [
+        (ArchiveFormat::Zip, "course.zip"),
+        (ArchiveFormat::TarGz, "course.tar.gz"),
+    ] {
+        let entries = archive::entries_of_dir(&out_dir).unwrap();
+        archive::write_archive(&entries, &dir.join(name), format).unwrap();
+
+        let unpacked = dir.join(format!("unpacked-{name}"));
+        for entry in archive::read_archive(&dir.join(name), format).unwrap() {
+            let EntryContent::Bytes(content) = entry.content else {
+                panic!("{} isn't read into memory", entry.path);
+            };
+            let path = unpacked.join(&entry.path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut files: BTreeMap<PathBuf, Vec<u8>> = read_tree(&unpacked).into_iter().collect();
+        let archived = files.remove(&PathBuf::from(RENDER_MANIFEST_FILE)).unwrap();
+        assert_eq!(files, expected, "{name}");
+        let archived_manifest: Value = serde_json::from_slice(&archived).unwrap();
+        assert!(archived_manifest.get("generated_at").is_none());
+        assert_eq!(archived_manifest, without_timestamps(&manifest), "{name}");
+    }
+}
+
+#[test]
+fn archives_of_the_same_content_are_the_same() {
+    let _context = RunContext::default().enter();
+    let track = common::basic_track();
+    let dir = TempDir::new();
+    let mut archives = vec![];
+    for name in ["first", "second"] {
+        let out_dir = dir.join(name);
+        render(&track, &out_dir);
+        // Renders at different times, which the archives mustn't tell
+        let manifest = out_dir.join(RENDER_MANIFEST_FILE);
+        let content = fs::read_to_string(&manifest).unwrap();
+        let mut value: Value = serde_json::from_str(&content).unwrap();
+        value["generated_at"] = Value::from(format!("2025-01-01T00:00:0{}Z", archives.len()));
+        fs::write(&manifest, value.to_string()).unwrap();
+
+        let entries = archive::entries_of_dir(&out_dir).unwrap();
+        let archive = dir.join(format!("{name}.zip"));
+        archive::write_archive(&entries, &archive, ArchiveFormat::Zip).unwrap();
+        archives.push(fs::read(archive).unwrap());
+    }
+    assert!(archives[0] == archives[1]);
+}
+
+#[test]
+fn long_paths_and_modes_survive_both_formats() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let long = format!("exercises/{}src/main.rs", "nested-folder/".repeat(12));
+    let entries = vec![
+        Entry {
+            path: long,
+            executable: false,
+            content: EntryContent::Bytes(b"fn main() {}\n".repeat(1000)),
+        },
+        Entry {
+            path: "setup.sh".to_string(),
+            executable: true,
+            content: EntryContent::Bytes(b"#!/bin/sh\n".to_vec()),
+        },
+    ];
+    for (format, name) in [
+        (ArchiveFormat::Zip, "course.zip"),
+        (ArchiveFormat::TarGz, "course.tar.gz"),
+    ] {
+        let path = dir.join(name);
+        archive::write_archive(&entries, &path, format).unwrap();
+        // The content is compressed
+        assert!(fs::metadata(&path).unwrap().len() < 2000, "{name}");
+        let read = archive::read_archive(&path, format).unwrap();
+        assert!(archive::same_entries(&read, &entries).unwrap(), "{name}");
+    }
+}
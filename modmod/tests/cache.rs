@@ -0,0 +1,194 @@
+//! The render cache of the fixture track: what is kept from it when rendering again, what
+//! renders an item again, and a cache file that can't be read
+
+mod common;
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use common::{change_upstream, copy_basic_track, read_tree, render, TempDir, DECK};
+use modmod::{cache::CACHE_FILE, context::RunContext, RenderOptions, Track};
+
+/// The entries of the render cache in `out_dir`, by key, with the files written for them
+fn entries(out_dir: &Path) -> BTreeMap<String, Vec<String>> {
+    let cache: serde_json::Value =
+        serde_json::from_slice(&fs::read(out_dir.join(CACHE_FILE)).unwrap()).unwrap();
+    assert_eq!(cache["version"], 2);
+    cache["entries"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, entry)| {
+            let outputs = entry["outputs"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|output| output["path"].as_str().unwrap().to_string())
+                .collect();
+            (key.clone(), outputs)
+        })
+        .collect()
+}
+
+/// The first file of each entry of `after` that isn't in `before`, which are the items that
+/// were rendered again
+fn rendered_again(
+    before: &BTreeMap<String, Vec<String>>,
+    after: &BTreeMap<String, Vec<String>>,
+) -> Vec<String> {
+    after
+        .iter()
+        .filter(|(key, _)| !before.contains_key(*key))
+        .map(|(_, outputs)| outputs[0].clone())
+        .collect()
+}
+
+/// A copy of the fixture track rendered into `out`, with the path of its track definition
+/// and the entries of the cache
+fn rendered(dir: &TempDir) -> (std::path::PathBuf, BTreeMap<String, Vec<String>>) {
+    let track_path = copy_basic_track(&dir.join("content"));
+    render(&Track::load(&track_path).unwrap(), &dir.join("out"));
+    (track_path, entries(&dir.join("out")))
+}
+
+#[test]
+fn unchanged_items_are_kept_from_the_cache() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    // A deck, a book page and an exercise package
+    assert_eq!(before.len(), 3, "{before:?}");
+    for key in before.keys() {
+        assert_eq!(key.len(), 64, "{key}");
+        assert!(key.bytes().all(|b| b.is_ascii_hexdigit()), "{key}");
+    }
+
+    let report = render(&Track::load(&track_path).unwrap(), &dir.join("out"));
+    assert_eq!(report.written_files, 0);
+    let outputs: usize = before.values().map(Vec::len).sum();
+    assert_eq!(report.cached_files, outputs);
+    assert_eq!(entries(&dir.join("out")), before);
+}
+
+#[test]
+fn changed_content_renders_its_item_again() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    let report = render(&change_upstream(&track_path), &dir.join("out"));
+    let after = entries(&dir.join("out"));
+    assert_eq!(rendered_again(&before, &after), [DECK]);
+    assert!(report.written_files > 0);
+    let deck = fs::read_to_string(dir.join("out").join(DECK)).unwrap();
+    assert!(deck.contains("Hello, modmod!"), "{deck}");
+}
+
+#[test]
+fn changed_templates_render_the_decks_again() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    let template = track_path.with_file_name("mods/A-basics/intro.md");
+    let content = fs::read_to_string(&template).unwrap();
+    fs::write(
+        &template,
+        format!("{content}\nA new line of the template\n"),
+    )
+    .unwrap();
+
+    render(&Track::load(&track_path).unwrap(), &dir.join("out"));
+    let after = entries(&dir.join("out"));
+    assert_eq!(rendered_again(&before, &after), [DECK]);
+    let deck = fs::read_to_string(dir.join("out").join(DECK)).unwrap();
+    assert!(deck.contains("A new line of the template"), "{deck}");
+}
+
+#[test]
+fn changed_dependencies_render_their_item_again() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello");
+
+    // A file of an exercise renders its package again
+    fs::write(
+        topic.join("exercises/greet/src/main.rs"),
+        "fn main() {\n    println!(\"Hello again\");\n}\n",
+    )
+    .unwrap();
+    render(&Track::load(&track_path).unwrap(), &dir.join("out"));
+    let after = entries(&dir.join("out"));
+    let again = rendered_again(&before, &after);
+    assert_eq!(again.len(), 1, "{again:?}");
+    assert!(again[0].starts_with("exercises/"), "{again:?}");
+
+    // The deck only refers to its images by name, so an image that changes is copied
+    // again, and the deck is kept
+    let image = topic.join("images/ferris.svg");
+    let content = fs::read_to_string(&image).unwrap();
+    let changed = content.replace("</svg>", "<!-- changed --></svg>");
+    fs::write(&image, &changed).unwrap();
+    let report = render(&Track::load(&track_path).unwrap(), &dir.join("out"));
+    assert!(rendered_again(&after, &entries(&dir.join("out"))).is_empty());
+    assert_eq!(report.written_files, 1);
+    let copies: Vec<_> = read_tree(&dir.join("out"))
+        .into_iter()
+        .filter(|(path, _)| path.ends_with("ferris.svg"))
+        .collect();
+    assert!(!copies.is_empty());
+    for (path, content) in copies {
+        assert_eq!(content, changed.as_bytes(), "{}", path.display());
+    }
+}
+
+#[test]
+fn edited_outputs_are_rendered_again() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    let deck = dir.join("out").join(DECK);
+    let content = fs::read(&deck).unwrap();
+    fs::write(&deck, "edited").unwrap();
+
+    // Without force, the edit would be kept as a local change
+    let mut options = RenderOptions::new(dir.join("out"));
+    options.clear_output_dir = true;
+    options.force = true;
+    let report = Track::load(&track_path)
+        .unwrap()
+        .render(&options, &mut ())
+        .unwrap();
+    assert_eq!(fs::read(&deck).unwrap(), content);
+    assert!(report.written_files > 0);
+    // The item is the same, so it's rendered into the same entry
+    assert_eq!(entries(&dir.join("out")), before);
+}
+
+#[test]
+fn caches_that_cant_be_read_render_everything() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, before) = rendered(&dir);
+    let track = Track::load(&track_path).unwrap();
+    let out_dir = dir.join("out");
+    let outputs: usize = before.values().map(Vec::len).sum();
+
+    for corrupt in [&b"{\"version\": 2, \"entries\": "[..], b"\xff\xfe", b"[]"] {
+        fs::write(out_dir.join(CACHE_FILE), corrupt).unwrap();
+        let report = render(&track, &out_dir);
+        assert_eq!(report.cached_files, 0);
+        // The rendered files are the same, so only the cache is written
+        assert_eq!(report.written_files, 1);
+        assert_eq!(entries(&out_dir), before);
+    }
+    // The cache written again is used
+    assert_eq!(render(&track, &out_dir).cached_files, outputs);
+
+    // Caches of another version are ignored
+    let cache = fs::read_to_string(out_dir.join(CACHE_FILE)).unwrap();
+    fs::write(
+        out_dir.join(CACHE_FILE),
+        cache.replace("\"version\":2", "\"version\":1"),
+    )
+    .unwrap();
+    assert_eq!(render(&track, &out_dir).cached_files, 0);
+}
@@ -0,0 +1,125 @@
+//! The codes of errors and warnings are stable: tools and users that react to them must
+//! keep working, so a code can't be renumbered, reused or dropped without a test failing
+
+use std::collections::HashSet;
+
+use modmod::{
+    catalog::{self, ErrorKind},
+    report::WarningCode,
+};
+
+/// Every code with its name, in the order of the catalog. Codes are only ever added.
+const CODES: &[(&str, &str)] = &[
+    ("E0001", "render-failed"),
+    ("E0002", "invalid-content"),
+    ("E0003", "invalid-arguments"),
+    ("E0101", "missing-content-file"),
+    ("E0102", "invalid-definition"),
+    ("E0103", "invalid-frontmatter"),
+    ("E0104", "invalid-condition"),
+    ("E0105", "undefined-reference"),
+    ("E0106", "circular-include"),
+    ("E0107", "unsupported-modmod-version"),
+    ("E0108", "unknown-key"),
+    ("E0201", "duplicate-name"),
+    ("E0202", "duplicate-index"),
+    ("E0203", "duplicate-alias"),
+    ("E0204", "duplicate-include"),
+    ("E0301", "copy-conflict"),
+    ("E0302", "write-failed"),
+    ("E0401", "lock-mismatch"),
+    ("W0101", "missing-content"),
+    ("W0102", "empty-topic"),
+    ("W0103", "invalid-modmod-version"),
+    ("W0104", "invalid-course-version"),
+    ("W0105", "course-version-downgrade"),
+    ("W0106", "no-license"),
+    ("W0107", "no-exercises"),
+    ("W0108", "unexpected-exercises"),
+    ("W0109", "review-overdue"),
+    ("W0110", "never-reviewed"),
+    ("W0111", "unreferenced-image"),
+    ("W0112", "index-gap"),
+    ("W0113", "unknown-config-key"),
+    ("W0114", "attribution-unknown"),
+    ("W0201", "duplicate-template"),
+    ("W0202", "template-override-ignored"),
+    ("W0203", "empty-placeholder"),
+    ("W0204", "missing-course-field"),
+    ("W0205", "unresolved-placeholder"),
+    ("W0206", "repeated-objective"),
+    ("W0207", "no-content-placeholder"),
+    ("W0301", "alias-link"),
+    ("W0302", "broken-link"),
+    ("W0303", "unreachable-link"),
+    ("W0304", "link-cache"),
+    ("W0401", "untranslatable-topic"),
+    ("W0402", "unknown-translation"),
+    ("W0403", "outdated-translation"),
+    ("W0404", "untranslated"),
+    ("W0501", "large-asset"),
+    ("W0502", "kept-scaffold"),
+    ("W0503", "thumbnails-skipped"),
+    ("W0504", "sitemap-skipped"),
+    ("W0505", "qr-code-skipped"),
+    ("W0506", "large-section"),
+];
+
+#[test]
+fn codes_are_never_renumbered() {
+    let catalog: Vec<_> = catalog::catalog()
+        .into_iter()
+        .map(|entry| (entry.code, entry.name))
+        .collect();
+    assert_eq!(catalog, CODES);
+}
+
+#[test]
+fn codes_and_names_are_unique() {
+    let codes: HashSet<_> = CODES.iter().map(|(code, _)| code).collect();
+    let names: HashSet<_> = CODES.iter().map(|(_, name)| name).collect();
+    assert_eq!(codes.len(), CODES.len());
+    assert_eq!(names.len(), CODES.len());
+    for (code, _) in CODES {
+        let (kind, digits) = code.split_at(1);
+        assert!(kind == "E" || kind == "W", "{code}");
+        assert!(
+            digits.len() == 4 && digits.chars().all(|c| c.is_ascii_digit()),
+            "{code}"
+        );
+    }
+}
+
+#[test]
+fn every_code_is_explained() {
+    for &(code, name) in CODES {
+        for query in [code, &code.to_lowercase(), name] {
+            let entry = catalog::explain(query).unwrap();
+            assert_eq!((entry.code, entry.name), (code, name), "{query}");
+            assert!(!entry.title.is_empty(), "{code}");
+            assert!(!entry.explanation.is_empty(), "{code}");
+        }
+    }
+    assert!(catalog::explain("E9999").is_none());
+}
+
+#[test]
+fn kinds_and_warning_codes_have_the_code_of_their_entry() {
+    for &kind in ErrorKind::ALL {
+        assert!(CODES.contains(&(kind.id(), kind.as_str())), "{kind:?}");
+        assert_eq!(serde_json::to_value(kind).unwrap(), kind.id(), "{kind:?}");
+    }
+    for &code in WarningCode::ALL {
+        assert!(CODES.contains(&(code.id(), code.as_str())), "{code:?}");
+        assert_eq!(code.as_str().parse::<WarningCode>(), Ok(code));
+    }
+}
+
+#[test]
+fn readme_lists_the_code_of_every_warning() {
+    let readme = include_str!("../README.md");
+    for &code in WarningCode::ALL {
+        let row = format!("| `{}` | {} |", code.as_str(), code.id());
+        assert!(readme.contains(&row), "{row}");
+    }
+}
@@ -0,0 +1,119 @@
+//! `Track::changed_since` on a copy of the fixture track in a git repository, with files
+//! that are renamed, deleted or not tracked by git
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{context::RunContext, Track};
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// A repository with the fixture track committed in it, with the path of the track
+fn repository(dir: &Path) -> std::path::PathBuf {
+    let track_path = copy_basic_track(dir);
+    git(dir, &["init", "--quiet"]);
+    git(dir, &["config", "user.name", "modmod"]);
+    git(dir, &["config", "user.email", "modmod@example.com"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "--quiet", "-m", "Start"]);
+    track_path
+}
+
+#[test]
+fn nothing_changed() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(repository(dir.path())).unwrap();
+
+    let changes = track.changed_since("HEAD").unwrap();
+    assert!(changes.files.is_empty(), "{:?}", changes.files);
+    assert!(changes.units.is_empty());
+    assert_eq!(changes.unchanged_units, ["first-steps"]);
+}
+
+#[test]
+fn renamed_files_change_their_old_and_new_path() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(repository(dir.path())).unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let images = Path::new("mods/A-basics/topics/hello/images");
+    git(
+        dir.path(),
+        &[
+            "mv",
+            &images.join("ferris.svg").to_string_lossy(),
+            &images.join("crab.svg").to_string_lossy(),
+        ],
+    );
+
+    let changes = track.changed_since("HEAD").unwrap();
+    assert_eq!(
+        changes.files,
+        [
+            root.join(images.join("crab.svg")),
+            root.join(images.join("ferris.svg"))
+        ]
+    );
+    assert_eq!(changes.units, ["first-steps"]);
+}
+
+#[test]
+fn deleted_and_untracked_files_change() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(repository(dir.path())).unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let slides = Path::new("mods/A-basics/topics/ownership/slides.md");
+    fs::remove_file(dir.join(slides)).unwrap();
+    fs::write(dir.join("notes.md"), "To do").unwrap();
+
+    let changes = track.changed_since("HEAD").unwrap();
+    assert_eq!(changes.files, [root.join(slides), root.join("notes.md")]);
+    assert_eq!(changes.units, ["first-steps"]);
+    assert!(changes.track_files.is_empty());
+}
+
+#[test]
+fn files_the_track_doesnt_use_affect_no_units() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(repository(dir.path())).unwrap();
+    fs::write(dir.join("notes.md"), "To do").unwrap();
+    git(dir.path(), &["add", "notes.md"]);
+    git(dir.path(), &["commit", "--quiet", "-m", "Notes"]);
+    git(dir.path(), &["mv", "notes.md", "todo.md"]);
+
+    let changes = track.changed_since("HEAD").unwrap();
+    assert_eq!(changes.files.len(), 2, "{:?}", changes.files);
+    assert!(changes.units.is_empty());
+    assert_eq!(changes.unchanged_units, ["first-steps"]);
+}
+
+#[test]
+fn unknown_revisions_are_errors() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(repository(dir.path())).unwrap();
+
+    let error = track.changed_since("no-such-branch").unwrap_err();
+    assert!(
+        format!("{error:?}").contains("Unknown git revision 'no-such-branch'"),
+        "{error:?}"
+    );
+    assert!(track.changed_since("--all").is_err());
+}
@@ -0,0 +1,72 @@
+//! `modmod check` of a track in a read-only folder, which it must leave as it is, and of the
+//! track `modmod init` creates, which it must pass
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, read_tree, TempDir};
+
+/// Makes the files and folders under `dir` read-only, or writable again
+fn set_readonly(dir: &Path, readonly: bool) {
+    let mut paths = vec![dir.to_path_buf()];
+    while let Some(path) = paths.pop() {
+        if path.is_dir() {
+            paths.extend(fs::read_dir(&path).unwrap().map(|e| e.unwrap().path()));
+        }
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(readonly);
+        fs::set_permissions(&path, permissions).unwrap();
+    }
+}
+
+#[test]
+fn check_writes_nothing() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    // Anything modmod would put in a cache or the temporary directory ends up in the
+    // folder too, so that it shows up below
+    let home = dir.join("home");
+    fs::create_dir(&home).unwrap();
+    let before = read_tree(dir.path());
+    set_readonly(dir.path(), true);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .arg("check")
+        .arg(&track_path)
+        .current_dir(dir.path())
+        .env("HOME", &home)
+        .env("TMPDIR", &home)
+        .env("XDG_CACHE_HOME", &home)
+        .output()
+        .unwrap();
+    set_readonly(dir.path(), false);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    // The runtime is reported, to keep an eye on hooks that run it
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Checked 1 track(s) in"), "{stdout}");
+    assert_eq!(read_tree(dir.path()), before);
+}
+
+#[test]
+fn init_track_passes_check() {
+    let dir = TempDir::new();
+    let track_dir = dir.join("course");
+    let modmod = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert!(output.status.success(), "{args:?}: {stderr}");
+        stderr
+    };
+
+    modmod(&["init", "course"]);
+    assert!(track_dir.join("LICENSE").is_file());
+    let stderr = modmod(&["check", "--deny-warnings", "course/course.track.toml"]);
+    assert!(!stderr.contains("warning"), "{stderr}");
+}
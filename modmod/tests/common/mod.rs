@@ -0,0 +1,147 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code)]
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use modmod::{context::RunContext, io::MemoryFs, report::RenderReport, RenderOptions, Track};
+
+/// The track in `tests/fixtures/basic`: one module with a unit of two topics, one of which
+/// has an image and an exercise
+pub const BASIC_TRACK: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/basic/basic.track.toml"
+);
+
+pub fn basic_track() -> Track {
+    Track::load(BASIC_TRACK).unwrap()
+}
+
+/// A copy of the fixture track in `dir`, to change its content, with the path of its track
+/// definition
+pub fn copy_basic_track(dir: &Path) -> PathBuf {
+    let fixture = Path::new(BASIC_TRACK).parent().unwrap();
+    for (path, content) in read_tree(fixture) {
+        let to = dir.join(path);
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::write(to, content).unwrap();
+    }
+    dir.join("basic.track.toml")
+}
+
+/// The deck of the only unit of the fixture track, relative to the output folder
+pub const DECK: &str = "slides/1_1-first-steps.md";
+
+/// Changes the slides of the first topic of a track copied by [`copy_basic_track`], which
+/// changes [`DECK`], and loads the changed track
+pub fn change_upstream(track_path: &Path) -> Track {
+    let slides = track_path.with_file_name("mods/A-basics/topics/hello/slides.md");
+    let content = fs::read_to_string(&slides).unwrap();
+    fs::write(&slides, content.replace("Hello, world!", "Hello, modmod!")).unwrap();
+    Track::load(track_path).unwrap()
+}
+
+/// Renders `track` into `out_dir`, clearing what an earlier render left there
+pub fn render(track: &Track, out_dir: &Path) -> RenderReport {
+    let mut options = RenderOptions::new(out_dir);
+    options.clear_output_dir = true;
+    track.render(&options, &mut ()).unwrap()
+}
+
+/// Renders `track` like [`render`], in a context of its own that keeps the output in memory,
+/// and returns the files with their path relative to `out_dir`, see [`memory_tree`]
+pub fn render_in_memory(
+    track: &Track,
+    options: &RenderOptions,
+) -> (RenderReport, Vec<(PathBuf, Vec<u8>)>) {
+    let (context, memory) = RunContext::in_memory();
+    let report = {
+        let _context = context.enter();
+        track.render(options, &mut ()).unwrap()
+    };
+    assert!(!options.out_dir.exists());
+    let files = memory_tree(&memory, &options.out_dir);
+    (report, files)
+}
+
+/// The files in `memory` under `dir`, by path relative to `dir`, like [`read_tree`]
+pub fn memory_tree(memory: &MemoryFs, dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    memory
+        .files()
+        .into_iter()
+        .filter_map(|(path, content)| Some((path.strip_prefix(dir).ok()?.to_path_buf(), content)))
+        .collect()
+}
+
+/// The context of errors from the helpers in `modmod::io`, which take the context of
+/// whatever uses them
+#[derive(Debug, Default)]
+pub struct TestError;
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("test failed")
+    }
+}
+
+impl error_stack::Context for TestError {}
+
+/// A folder of its own in the temporary directory, which is removed when it's dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let name = format!(
+            "modmod-test-{}-{nanos:x}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The files under `dir` with their content, by path relative to `dir`
+pub fn read_tree(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(next) = dirs.pop() {
+        for entry in fs::read_dir(&next).unwrap() {
+            let path = entry.unwrap().path();
+            match path.is_dir() {
+                true => dirs.push(path),
+                false => {
+                    let content = fs::read(&path).unwrap();
+                    files.push((path.strip_prefix(dir).unwrap().to_path_buf(), content));
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
@@ -0,0 +1,140 @@
+//! Completion, as the shell scripts of `modmod completions` ask for it, against a copy of
+//! the fixture track
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+
+/// The candidates modmod offers for the last of `words`, asked for like the fish script
+/// does, which gives one candidate per line followed by its help
+fn complete(dir: &Path, words: &[&str]) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .env("COMPLETE", "fish")
+        .arg("--")
+        .arg("modmod")
+        .args(words)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{words:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split('\t').next().unwrap().to_string())
+        .collect()
+}
+
+/// A copy of the fixture track with a second, tagged unit and profiles
+fn tagged_track(dir: &Path) {
+    let track_path = copy_basic_track(dir);
+    let mut track = fs::read_to_string(&track_path).unwrap();
+    track.push_str("profiles = [\"std\", \"embedded\"]\n");
+    fs::write(&track_path, track).unwrap();
+
+    let mod_toml = track_path.with_file_name("mods/A-basics/mod.toml");
+    let mut module = fs::read_to_string(&mod_toml).unwrap();
+    module.push_str(
+        "
+[[units]]
+name = \"Borrowing\"
+tags = [\"day-1\", \"remote\"]
+topics = [\"topics/ownership/topic.toml\"]
+",
+    );
+    fs::write(&mod_toml, module).unwrap();
+}
+
+#[test]
+fn completes_subcommands_and_options() {
+    let dir = TempDir::new();
+    assert_eq!(complete(dir.path(), &["gen"]), ["generate"]);
+    assert_eq!(complete(dir.path(), &["generate", "--cl"]), ["--clear"]);
+    // Global options are offered after a subcommand too
+    let options = complete(dir.path(), &["generate", "--"]);
+    assert!(options.contains(&"--clear".to_string()), "{options:?}");
+    assert!(options.contains(&"--help".to_string()), "{options:?}");
+    // The hidden subcommands aren't
+    assert!(!complete(dir.path(), &[""]).contains(&"anki".to_string()));
+    assert_eq!(
+        complete(dir.path(), &["check", "--error-format", ""]),
+        ["human", "json"]
+    );
+}
+
+#[test]
+fn completes_unit_selectors_from_the_track() {
+    let dir = TempDir::new();
+    tagged_track(dir.path());
+
+    assert_eq!(
+        complete(dir.path(), &["generate", "basic.track.toml", "--units", ""]),
+        [
+            "1",
+            "1.1",
+            "1.2",
+            "borrowing",
+            "first-steps",
+            "tag:day-1",
+            "tag:remote"
+        ]
+    );
+    assert_eq!(
+        complete(
+            dir.path(),
+            &["generate", "basic.track.toml", "--units", "tag:r"]
+        ),
+        ["tag:remote"]
+    );
+    assert_eq!(
+        complete(dir.path(), &["generate", "basic.track.toml", "--units=f"]),
+        ["--units=first-steps"]
+    );
+    assert_eq!(
+        complete(
+            dir.path(),
+            &["generate", "basic.track.toml", "--profile", ""]
+        ),
+        ["embedded", "std"]
+    );
+    // Without a track on the command line, there is nothing to offer
+    assert!(complete(dir.path(), &["generate", "--units", ""]).is_empty());
+}
+
+#[test]
+fn completes_render_targets() {
+    let dir = TempDir::new();
+    assert_eq!(
+        complete(dir.path(), &["generate", "--only", ""]),
+        ["slides", "book", "exercises"]
+    );
+    assert_eq!(complete(dir.path(), &["generate", "--skip", "b"]), ["book"]);
+}
+
+#[test]
+fn writes_registration_scripts() {
+    for shell in ["bash", "zsh", "fish"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .args(["completions", shell])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{shell}");
+        let script = String::from_utf8(output.stdout).unwrap();
+        // They call modmod back to complete the command line
+        let call = format!("COMPLETE={shell}");
+        assert!(
+            script.contains(&call) || script.contains(&format!("COMPLETE=\"{shell}\"")),
+            "{script}"
+        );
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["completions", "tcsh"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}
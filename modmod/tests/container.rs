@@ -0,0 +1,120 @@
+//! The Docker build context of the fixture track, and dry runs of the commands that build
+//! the context and publish the output
+
+mod common;
+
+use std::{path::Path, process::Command};
+
+use common::{basic_track, render_in_memory, TempDir};
+use modmod::{
+    container, context::RunContext, manifest::RENDER_MANIFEST_FILE, update::HASHES_FILE,
+    RenderOptions,
+};
+
+fn file<'a>(files: &'a [(std::path::PathBuf, Vec<u8>)], path: &str) -> &'a str {
+    let (_, content) = files
+        .iter()
+        .find(|(p, _)| p == Path::new(path))
+        .unwrap_or_else(|| panic!("{path} is missing"));
+    std::str::from_utf8(content).unwrap()
+}
+
+#[test]
+fn context_builds_and_serves_the_track() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let mut options = RenderOptions::new(dir.join("out"));
+    options.container = true;
+    let (_, files) = render_in_memory(&basic_track(), &options);
+
+    // The context is the rendered track, with the files that build and serve it
+    for path in ["slides/package.json", "book/book.toml", "index.html"] {
+        file(&files, path);
+    }
+    let dockerfile = file(&files, "Dockerfile");
+    assert!(dockerfile.contains("Basics"), "{dockerfile}");
+    assert!(dockerfile.starts_with("# "), "{dockerfile}");
+    let stages: Vec<_> = dockerfile
+        .lines()
+        .filter(|line| line.starts_with("FROM "))
+        .collect();
+    assert_eq!(stages, ["FROM rust:1 AS build", "FROM busybox:1.36"]);
+    assert!(
+        dockerfile.contains("apt-get install -y nodejs"),
+        "{dockerfile}"
+    );
+    assert!(
+        dockerfile.contains("WORKDIR /course\nCOPY . .\n"),
+        "{dockerfile}"
+    );
+    assert!(dockerfile.contains("mdbook build"), "{dockerfile}");
+    assert!(dockerfile.contains("cp index.html _site/"), "{dockerfile}");
+    assert!(
+        dockerfile.contains("COPY --from=build /course/_site /site\nEXPOSE 8080\n"),
+        "{dockerfile}"
+    );
+    assert!(dockerfile
+        .ends_with("CMD [\"httpd\", \"-f\", \"-v\", \"-p\", \"8080\", \"-h\", \"/site\"]\n"));
+
+    // What differs between renders of the same content stays out of the image
+    let ignored: Vec<_> = file(&files, ".dockerignore")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    for path in [
+        RENDER_MANIFEST_FILE,
+        HASHES_FILE,
+        "_site",
+        "slides/node_modules",
+    ] {
+        assert!(ignored.contains(&path), "{path} in {ignored:?}");
+    }
+}
+
+#[test]
+fn image_tags() {
+    assert_eq!(
+        container::image_tag("Rust intro", None),
+        "rust-intro:latest"
+    );
+    assert_eq!(
+        container::image_tag("Rust intro", Some("1.0.0+build.1")),
+        "rust-intro:1.0.0-build.1"
+    );
+}
+
+#[test]
+fn dry_runs_list_the_builds() {
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    let render = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["generate", "-o"])
+        .arg(&out_dir)
+        .arg(common::BASIC_TRACK)
+        .output()
+        .unwrap();
+    assert!(
+        render.status.success(),
+        "{}",
+        String::from_utf8_lossy(&render.stderr)
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["--dry-run", "container", "--build", "-o"])
+        .arg(&out_dir)
+        .arg(common::BASIC_TRACK)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let planned = format!(
+        "run `docker build --tag basics:latest .` in {}",
+        out_dir.display()
+    );
+    assert!(stdout.contains(&planned), "{stdout}");
+    assert!(!out_dir.join("Dockerfile").exists());
+}
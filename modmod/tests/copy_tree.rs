@@ -0,0 +1,348 @@
+//! `io::copy_tree` with each of its options, on a small folder tree
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{read_tree, TempDir, TestError};
+use modmod::context::RunContext;
+use modmod::io::{copy_tree, CopyTreeOptions, OnExisting};
+
+/// Folder `src` in `dir` with images at several depths, a page and a build folder
+fn source_tree(dir: &TempDir) -> PathBuf {
+    let src = dir.join("src");
+    for (path, content) in [
+        ("page.md", "# Page"),
+        ("logo.png", "logo"),
+        ("img/ferris.png", "ferris"),
+        ("img/old/ferris-1.png", "old ferris"),
+        ("target/debug/build.png", "build output"),
+    ] {
+        let path = src.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    src
+}
+
+fn copy(dir: &TempDir, options: &CopyTreeOptions) -> error_stack::Result<Vec<String>, TestError> {
+    let dst = dir.join("dst");
+    let copied = copy_tree::<TestError>(&dir.join("src"), &dst, options)?;
+    Ok(copied
+        .iter()
+        .map(|path| {
+            let path = path.strip_prefix(&dst).unwrap();
+            path.to_string_lossy().replace('\\', "/")
+        })
+        .collect())
+}
+
+/// The files in the destination folder, with their content
+fn copied(dir: &TempDir) -> Vec<(String, String)> {
+    read_tree(&dir.join("dst"))
+        .into_iter()
+        .map(|(path, content)| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            (path, String::from_utf8(content).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn copies_the_whole_tree_in_order() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    source_tree(&dir);
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        copy(&dir, &options).unwrap(),
+        [
+            "img/ferris.png",
+            "img/old/ferris-1.png",
+            "logo.png",
+            "page.md",
+            "target/debug/build.png",
+        ]
+    );
+    assert_eq!(copied(&dir)[0], ("img/ferris.png".into(), "ferris".into()));
+}
+
+#[test]
+fn only_copies_the_folder_itself_unless_recursive() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    source_tree(&dir);
+    let options = CopyTreeOptions {
+        preserve_structure: true,
+        ..Default::default()
+    };
+    assert_eq!(copy(&dir, &options).unwrap(), ["logo.png", "page.md"]);
+}
+
+#[test]
+fn includes_and_excludes_by_glob() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    source_tree(&dir);
+    let mut options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        include: &["**/*.png"],
+        exclude: &["target/**", "**/old/*"],
+        ..Default::default()
+    };
+    assert_eq!(
+        copy(&dir, &options).unwrap(),
+        ["img/ferris.png", "logo.png"]
+    );
+
+    // `*` doesn't match `/`
+    let _ = fs::remove_dir_all(dir.join("dst"));
+    options.include = &["*.png"];
+    options.exclude = &[];
+    assert_eq!(copy(&dir, &options).unwrap(), ["logo.png"]);
+
+    // Excludes win over includes
+    let _ = fs::remove_dir_all(dir.join("dst"));
+    options.include = &["logo.png", "page.md"];
+    options.exclude = &["*.md"];
+    assert_eq!(copy(&dir, &options).unwrap(), ["logo.png"]);
+
+    options.exclude = &["[unclosed"];
+    let report = copy(&dir, &options).unwrap_err();
+    assert!(
+        format!("{report:?}").contains("Error parsing glob '[unclosed'"),
+        "{report:?}"
+    );
+}
+
+#[test]
+fn keeps_paths_relative_to_the_root() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let src = source_tree(&dir);
+    let dst = dir.join("dst");
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        root: Some(src.parent().unwrap()),
+        ..Default::default()
+    };
+    let copied = copy_tree::<TestError>(&src.join("img"), &dst, &options).unwrap();
+    assert_eq!(
+        copied,
+        [
+            dst.join("src/img/ferris.png"),
+            dst.join("src/img/old/ferris-1.png")
+        ]
+    );
+
+    let options = CopyTreeOptions {
+        root: Some(&src.join("img")),
+        ..options
+    };
+    let report = copy_tree::<TestError>(&src, &dst, &options).unwrap_err();
+    assert!(
+        format!("{report:?}").contains("so its files can't keep their path relative to it"),
+        "{report:?}"
+    );
+}
+
+#[test]
+fn flattens_without_preserving_the_structure() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    source_tree(&dir);
+    let mut options = CopyTreeOptions {
+        recursive: true,
+        include: &["**/*.png"],
+        exclude: &["target/**"],
+        ..Default::default()
+    };
+    assert_eq!(
+        copy(&dir, &options).unwrap(),
+        ["ferris.png", "ferris-1.png", "logo.png"]
+    );
+
+    // Files with the same name would overwrite each other
+    fs::write(dir.join("src/img/old/logo.png"), "old logo").unwrap();
+    let _ = fs::remove_dir_all(dir.join("dst"));
+    options.include = &["**/logo.png"];
+    let report = copy(&dir, &options).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(message.contains("would both be copied to"), "{message}");
+    assert!(!dir.join("dst").exists());
+}
+
+#[test]
+fn files_that_are_there_are_overwritten_skipped_or_refused() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    source_tree(&dir);
+    let existing = dir.join("dst/logo.png");
+    let options = |on_existing| CopyTreeOptions {
+        on_existing,
+        ..Default::default()
+    };
+    let reset = || {
+        let _ = fs::remove_dir_all(dir.join("dst"));
+        fs::create_dir_all(dir.join("dst")).unwrap();
+        fs::write(&existing, "ours").unwrap();
+    };
+
+    reset();
+    assert_eq!(
+        copy(&dir, &options(OnExisting::Overwrite)).unwrap(),
+        ["logo.png", "page.md"]
+    );
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "logo");
+
+    reset();
+    assert_eq!(copy(&dir, &options(OnExisting::Skip)).unwrap(), ["page.md"]);
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "ours");
+
+    // Nothing is copied, not even the files that aren't there yet
+    reset();
+    let report = copy(&dir, &options(OnExisting::Error)).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(message.contains("already exists, so"), "{message}");
+    assert_eq!(copied(&dir), [("logo.png".into(), "ours".into())]);
+    assert_eq!(OnExisting::default(), OnExisting::Overwrite);
+}
+
+/// Adds links to `src`: one to a file in it, one to a folder next to it, and one to nothing
+#[cfg(unix)]
+fn link_tree(dir: &TempDir) -> PathBuf {
+    use std::os::unix::fs::symlink;
+
+    let src = source_tree(dir);
+    fs::create_dir_all(dir.join("shared")).unwrap();
+    fs::write(dir.join("shared/icon.png"), "icon").unwrap();
+    symlink("logo.png", src.join("alias.png")).unwrap();
+    symlink("../shared", src.join("shared")).unwrap();
+    src
+}
+
+#[cfg(unix)]
+#[test]
+fn follows_links_in_and_out_of_the_tree() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    link_tree(&dir);
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        include: &["**/*.png"],
+        exclude: &["target/**", "img/**"],
+        ..Default::default()
+    };
+    assert_eq!(
+        copy(&dir, &options).unwrap(),
+        ["alias.png", "logo.png", "shared/icon.png"]
+    );
+    assert_eq!(
+        copied(&dir),
+        [
+            ("alias.png".into(), "logo".into()),
+            ("logo.png".into(), "logo".into()),
+            ("shared/icon.png".into(), "icon".into()),
+        ]
+    );
+    let dst = dir.join("dst");
+    assert!(!dst.join("alias.png").is_symlink());
+    assert!(!dst.join("shared").is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn preserves_links_as_they_are_written() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let src = link_tree(&dir);
+    std::os::unix::fs::symlink("missing.png", src.join("gone.png")).unwrap();
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        exclude: &["target/**", "img/**"],
+        preserve_symlinks: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        copy(&dir, &options).unwrap(),
+        ["alias.png", "gone.png", "logo.png", "page.md", "shared"]
+    );
+    let dst = dir.join("dst");
+    for (link, target) in [
+        ("alias.png", "logo.png"),
+        ("gone.png", "missing.png"),
+        ("shared", "../shared"),
+    ] {
+        assert_eq!(
+            fs::read_link(dst.join(link)).unwrap(),
+            PathBuf::from(target)
+        );
+    }
+
+    // Links count as being there, even the ones that point to nothing
+    let options = CopyTreeOptions {
+        on_existing: OnExisting::Skip,
+        ..options
+    };
+    assert_eq!(copy(&dir, &options).unwrap(), Vec::<String>::new());
+}
+
+#[cfg(unix)]
+#[test]
+fn fails_on_links_to_nothing_and_to_their_own_folder() {
+    use std::os::unix::fs::symlink;
+
+    let dir = TempDir::new();
+    let src = link_tree(&dir);
+    let options = CopyTreeOptions {
+        recursive: true,
+        preserve_structure: true,
+        ..Default::default()
+    };
+
+    symlink("missing.png", src.join("gone.png")).unwrap();
+    let report = copy(&dir, &options).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains(&format!(
+            "Symbolic link {} points to {}, which doesn't exist",
+            src.join("gone.png").display(),
+            src.join("missing.png").display()
+        )),
+        "{message}"
+    );
+
+    // Links to each other never lead to a file
+    fs::remove_file(src.join("gone.png")).unwrap();
+    symlink("there.png", src.join("here.png")).unwrap();
+    symlink("here.png", src.join("there.png")).unwrap();
+    let report = copy(&dir, &options).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains("a symbolic link that doesn't lead to a file or folder"),
+        "{message}"
+    );
+
+    fs::remove_file(src.join("here.png")).unwrap();
+    fs::remove_file(src.join("there.png")).unwrap();
+    symlink("..", src.join("img/up")).unwrap();
+    let report = copy(&dir, &options).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains(&format!(
+            "Symbolic link {} points to {}, which contains it",
+            src.join("img/up").display(),
+            src.canonicalize().unwrap().display()
+        )),
+        "{message}"
+    );
+    assert!(!dir.join("dst").exists());
+}
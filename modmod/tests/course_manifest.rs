@@ -0,0 +1,35 @@
+//! A snapshot of the `course.json` of the fixture track, which websites and learning
+//! management systems read. Run with `MODMOD_UPDATE_SNAPSHOTS=1` to write the snapshot again
+//! after a change to the file that's intended, and review its diff.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{basic_track, TempDir};
+use modmod::RenderOptions;
+
+const SNAPSHOT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/snapshots/course/course.json"
+);
+
+#[test]
+fn course_json_of_the_fixture_track() {
+    let dir = TempDir::new();
+    let mut options = RenderOptions::new(dir.join("out"));
+    options.course_json = true;
+    basic_track().render(&options, &mut ()).unwrap();
+    let course_json = fs::read_to_string(dir.join("out/course.json")).unwrap();
+
+    if std::env::var_os("MODMOD_UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(Path::new(SNAPSHOT).parent().unwrap()).unwrap();
+        fs::write(SNAPSHOT, &course_json).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(SNAPSHOT).unwrap();
+    assert!(
+        course_json == expected,
+        "course.json differs from its snapshot:\n{course_json}"
+    );
+}
@@ -0,0 +1,198 @@
+//! Snapshots of the decks of the fixture tracks, which are to stay byte for byte the same
+//! as the decks are assembled in other ways. The tracks are rendered in memory. Run with `MODMOD_UPDATE_SNAPSHOTS=1` to write
+//! the snapshots again after a change to the output that's intended, and review their diff.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{read_tree, render_in_memory, TempDir, BASIC_TRACK};
+use modmod::{context::RunContext, RenderOptions, RenderTarget, Track};
+
+const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+const DECKS_TRACK: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/decks/decks.track.toml"
+);
+
+/// Renders the track at `track_path` and compares its decks with the snapshots in
+/// `tests/snapshots/<name>`
+fn assert_decks_match(track_path: &str, name: &str) {
+    let dir = TempDir::new();
+    let options = RenderOptions::new(dir.join("out"));
+    let (_, files) = render_in_memory(&Track::load(track_path).unwrap(), &options);
+    let decks: Vec<_> = files
+        .into_iter()
+        .filter_map(|(path, content)| Some((path.strip_prefix("slides").ok()?.to_owned(), content)))
+        .filter(|(path, _)| {
+            path.parent() == Some(Path::new("")) && path.extension() == Some("md".as_ref())
+        })
+        .collect();
+    assert!(!decks.is_empty());
+
+    let snapshots = Path::new(SNAPSHOTS).join(name);
+    if std::env::var_os("MODMOD_UPDATE_SNAPSHOTS").is_some() {
+        let _ = fs::remove_dir_all(&snapshots);
+        fs::create_dir_all(&snapshots).unwrap();
+        for (path, content) in &decks {
+            fs::write(snapshots.join(path), content).unwrap();
+        }
+        return;
+    }
+    let expected = read_tree(&snapshots);
+    let names = |files: &[(std::path::PathBuf, Vec<u8>)]| {
+        files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&decks),
+        names(&expected),
+        "other decks than in {name}"
+    );
+    for ((path, content), (_, expected)) in decks.iter().zip(&expected) {
+        assert!(
+            content == expected,
+            "{} differs from its snapshot:\n{}",
+            path.display(),
+            String::from_utf8_lossy(content)
+        );
+    }
+}
+
+#[test]
+fn basic_decks() {
+    let _context = RunContext::default().enter();
+    assert_decks_match(BASIC_TRACK, "basic");
+}
+
+#[test]
+fn decks_with_every_placeholder() {
+    let _context = RunContext::default().enter();
+    assert_decks_match(DECKS_TRACK, "decks");
+}
+
+#[test]
+fn decks_without_the_exercises_leave_out_their_slide() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    // With the default template, which has the slide
+    let track_path = common::copy_basic_track(&dir.join("content"));
+    let module = track_path.with_file_name("mods/A-basics/mod.toml");
+    let definition = fs::read_to_string(&module).unwrap();
+    fs::write(&module, definition.replace("template = \"intro.md\"\n", "")).unwrap();
+    let track = Track::load(&track_path).unwrap();
+
+    let deck = |options: &RenderOptions| {
+        let (_, files) = render_in_memory(&track, options);
+        let (_, deck) = files
+            .into_iter()
+            .find(|(path, _)| path == Path::new(common::DECK))
+            .unwrap();
+        String::from_utf8(deck).unwrap()
+    };
+    let mut options = RenderOptions::new(dir.join("out"));
+    let all = deck(&options);
+    assert!(all.contains("# Exercises"), "{all}");
+    assert!(
+        all.contains("[Exercise 1.1.1: Greet](../../exercises/1-basics/1-first-steps/1-greet/)"),
+        "{all}"
+    );
+
+    // The links are relative to the deck, so they don't change with the base URL, but do
+    // with the depth of the slides folder
+    options.base_url = "/training/rust/".parse().unwrap();
+    options.layout.slides = Some("site/slides".into());
+    let (_, files) = render_in_memory(&track, &options);
+    let (_, nested) = files
+        .into_iter()
+        .find(|(path, _)| path == Path::new("site/slides/1_1-first-steps.md"))
+        .unwrap();
+    let nested = String::from_utf8(nested).unwrap();
+    assert!(
+        nested.contains("(../../../exercises/1-basics/1-first-steps/1-greet/)"),
+        "{nested}"
+    );
+    options.base_url = Default::default();
+    options.layout = Default::default();
+
+    options.targets = vec![RenderTarget::Slides];
+    let slides = deck(&options);
+    assert!(!slides.contains("# Exercises"), "{slides}");
+    assert!(!slides.contains("Greet"), "{slides}");
+}
+
+/// The fixture track with `template` as the template of its unit, which has no objectives
+fn track_with_template(dir: &TempDir, template: &str) -> std::path::PathBuf {
+    let track_path = common::copy_basic_track(&dir.join("content"));
+    fs::write(
+        track_path.with_file_name("mods/A-basics/intro.md"),
+        template,
+    )
+    .unwrap();
+    track_path
+}
+
+const OBJECTIVES_TEMPLATE: &str = "---
+title: \"#[modmod:unit_title]\"
+---
+
+# Objectives
+
+#[modmod:objectives]
+
+---
+
+#[modmod:content]
+";
+
+#[test]
+fn empty_placeholders_are_left_out_or_remove_their_slide() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let options = RenderOptions::new(dir.join("out"));
+    let deck = |template: &str| {
+        let track = Track::load(track_with_template(&dir, template)).unwrap();
+        let (_, files) = render_in_memory(&track, &options);
+        let (_, deck) = files
+            .into_iter()
+            .find(|(path, _)| path == Path::new(common::DECK))
+            .unwrap();
+        String::from_utf8(deck).unwrap()
+    };
+
+    let left_out = deck(OBJECTIVES_TEMPLATE);
+    assert!(left_out.contains("# Objectives\n"), "{left_out}");
+    assert!(!left_out.contains("#[modmod:"), "{left_out}");
+    assert!(left_out.contains("Hello, world!"), "{left_out}");
+
+    let removed = deck(&OBJECTIVES_TEMPLATE.replace("objectives]", "objectives?]"));
+    assert!(!removed.contains("Objectives"), "{removed}");
+    assert!(!removed.contains("#[modmod:"), "{removed}");
+    assert!(removed.contains("Hello, world!"), "{removed}");
+}
+
+#[test]
+fn required_placeholders_that_are_empty_fail() {
+    let dir = TempDir::new();
+    let track_path = track_with_template(
+        &dir,
+        &OBJECTIVES_TEMPLATE.replace("objectives]", "objectives!]"),
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["generate", "-o"])
+        .arg(dir.join("out"))
+        .arg(&track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("slide template requires #[modmod:objectives!] to be filled in"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").join(common::DECK).exists());
+}
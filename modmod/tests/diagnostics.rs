@@ -0,0 +1,115 @@
+//! Snapshots of the diagnostics `modmod check` shows for copies of the fixture track that are
+//! broken in some way, in `tests/snapshots/diagnostics`. The folder of the copy is shown as
+//! `<content>`. Run with `MODMOD_UPDATE_SNAPSHOTS=1` to write the snapshots again after a
+//! change to the diagnostics that's intended, and review their diff.
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+
+const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/diagnostics");
+
+const HELLO: &str = "mods/A-basics/topics/hello";
+
+/// Checks a copy of the fixture track with `files` written into it, and compares what it
+/// printed to stderr with the snapshot `<name>.txt`
+fn assert_diagnostics_match(name: &str, files: &[(&str, &str)]) {
+    let dir = TempDir::new();
+    let content = dir.join("content");
+    let track_path = copy_basic_track(&content);
+    for (path, text) in files {
+        fs::write(content.join(path), text).unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .arg("check")
+        .arg(&track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr)
+        .replace(&content.display().to_string(), "<content>");
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+
+    let snapshot = Path::new(SNAPSHOTS).join(format!("{name}.txt"));
+    if std::env::var_os("MODMOD_UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(SNAPSHOTS).unwrap();
+        fs::write(&snapshot, &stderr).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&snapshot).unwrap();
+    assert!(
+        stderr == expected,
+        "The diagnostics differ from {}:\n{stderr}",
+        snapshot.display()
+    );
+}
+
+#[test]
+fn invalid_topic_definitions() {
+    assert_diagnostics_match(
+        "invalid-definitions",
+        &[
+            (
+                &format!("{HELLO}/topic.toml"),
+                "name = \"Hello, world\"\nobjectives = [\"a\", 3 4]\n",
+            ),
+            ("mods/A-basics/topics/ownership/topic.toml", "name = 3\n"),
+        ],
+    );
+}
+
+#[test]
+fn definition_that_ends_too_soon() {
+    assert_diagnostics_match(
+        "unexpected-end",
+        &[(
+            &format!("{HELLO}/topic.toml"),
+            "name = \"Hello, world\"\nobjectives = [\n",
+        )],
+    );
+}
+
+#[test]
+fn invalid_frontmatter() {
+    assert_diagnostics_match(
+        "invalid-frontmatter",
+        &[(
+            &format!("{HELLO}/slides.md"),
+            "---\nobjectives:\n  - Say hello\n   summary\n---\n# Hello, world\n",
+        )],
+    );
+}
+
+#[test]
+fn frontmatter_with_other_keys() {
+    assert_diagnostics_match(
+        "frontmatter-keys",
+        &[(
+            &format!("{HELLO}/slides.md"),
+            "---\nobjectives:\n  - Say hello\nlayout: default\n---\n# Hello, world\n",
+        )],
+    );
+}
+
+#[test]
+fn unclosed_conditional() {
+    assert_diagnostics_match(
+        "unclosed-conditional",
+        &[(
+            &format!("{HELLO}/slides.md"),
+            "# Hello, world\n\n#[modmod:if profile:std\nOnly with std\n",
+        )],
+    );
+}
+
+#[test]
+fn endif_without_if() {
+    assert_diagnostics_match(
+        "unmatched-endif",
+        &[(
+            &format!("{HELLO}/slides.md"),
+            "# Hello, world\n\nText\n#[modmod:endif]\n",
+        )],
+    );
+}
@@ -0,0 +1,124 @@
+//! Renders again into an output folder, and dry runs of the commands that write, which must
+//! leave the file system as it is
+
+mod common;
+
+use std::{fs, path::Path, process::Command, time::SystemTime};
+
+use common::{change_upstream, copy_basic_track, read_tree, render, TempDir, DECK};
+use modmod::{context::RunContext, io::PlannedWrite, update, Track};
+
+/// The files under `dir` with their content and modification time
+fn snapshot(dir: &Path) -> Vec<(std::path::PathBuf, Vec<u8>, SystemTime)> {
+    read_tree(dir)
+        .into_iter()
+        .map(|(path, content)| {
+            let modified = fs::metadata(dir.join(&path)).unwrap().modified().unwrap();
+            (path, content, modified)
+        })
+        .collect()
+}
+
+/// Whether `writes` has a write of `path`, relative to `dir`
+fn writes_to(writes: &[PlannedWrite], dir: &Path, path: &str) -> bool {
+    let path = dir.join(path);
+    writes
+        .iter()
+        .any(|write| format!("{write:?}").contains(&*path.to_string_lossy()))
+}
+
+#[test]
+fn rendering_again_writes_nothing() {
+    let _context = RunContext::default().enter();
+    let track = common::basic_track();
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    let first = render(&track, &out_dir);
+    let before = snapshot(&out_dir);
+
+    let second = render(&track, &out_dir);
+    assert_eq!(second.written_files, 0);
+    assert_eq!(
+        second.unchanged_files + second.cached_files,
+        first.written_files
+    );
+    assert_eq!(snapshot(&out_dir), before);
+}
+
+#[test]
+fn dry_run_render_leaves_the_output_alone() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let out_dir = dir.join("out");
+    render(&Track::load(&track_path).unwrap(), &out_dir);
+    let before = snapshot(&out_dir);
+
+    let track = change_upstream(&track_path);
+    let entered = RunContext::recording().enter();
+    render(&track, &out_dir);
+    let writes = entered.context().take_recorded_writes().unwrap();
+    drop(entered);
+
+    assert_eq!(snapshot(&out_dir), before);
+    assert!(writes_to(&writes, &out_dir, DECK), "{writes:?}");
+}
+
+#[test]
+fn dry_run_update_and_clean_leave_the_output_alone() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let out_dir = dir.join("out");
+    render(&Track::load(&track_path).unwrap(), &out_dir);
+    update::FileHashes::of_dir(&out_dir)
+        .and_then(|manifest| manifest.write(&out_dir))
+        .unwrap();
+    let new_dir = dir.join("new");
+    render(&change_upstream(&track_path), &new_dir);
+    let before = snapshot(&out_dir);
+
+    let entered = RunContext::recording().enter();
+    let updated = update::update(&new_dir, &out_dir).unwrap();
+    let cleaned = update::clean(&out_dir, true).unwrap();
+    let writes = entered.context().take_recorded_writes().unwrap();
+    drop(entered);
+
+    assert!(!updated.updated.is_empty(), "{updated}");
+    assert!(!cleaned.removed.is_empty());
+    assert_eq!(snapshot(&out_dir), before);
+    assert!(writes_to(&writes, &out_dir, DECK), "{writes:?}");
+}
+
+#[test]
+fn dry_run_init_lists_every_file_of_the_track() {
+    let dir = TempDir::new();
+    let track_dir = dir.join("course");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["--dry-run", "init"])
+        .arg(&track_dir)
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!track_dir.exists());
+    for file in [
+        "course.track.toml",
+        "LICENSE",
+        "mods/A-introduction/mod.toml",
+        "mods/A-introduction/topics/hello/topic.toml",
+        "mods/A-introduction/topics/hello/slides.md",
+        "mods/A-introduction/topics/hello/exercises/hello/Cargo.toml",
+        "mods/A-introduction/topics/hello/exercises/hello/description.md",
+        "mods/A-introduction/topics/hello/exercises/hello/src/main.rs",
+    ] {
+        let planned = format!("create {}", track_dir.join(file).display());
+        assert!(
+            stdout.contains(&planned),
+            "{file} is missing from:\n{stdout}"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Renders of the exercises of the fixture track
+
+mod common;
+
+use std::fs;
+
+use common::{render, TempDir};
+use modmod::{context::RunContext, Track};
+
+const README: &str = "exercises/1-basics/1-first-steps/README.md";
+
+#[test]
+fn unit_readme_links_to_the_exercise_folders() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    render(&common::basic_track(), dir.path());
+    let readme = fs::read_to_string(dir.join(README)).unwrap();
+    assert!(readme.contains("in [`1-greet`](1-greet/)"), "{readme}");
+}
+
+#[test]
+fn unit_readme_leaves_out_folders_without_files() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = common::copy_basic_track(&dir.join("content"));
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello/topic.toml");
+    let definition = fs::read_to_string(&topic).unwrap();
+    fs::write(&topic, format!("{definition}includes = [\"none/**\"]\n")).unwrap();
+    let track = Track::load(&track_path).unwrap();
+
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let readme = fs::read_to_string(out_dir.join(README)).unwrap();
+    assert!(readme.contains("Greet"), "{readme}");
+    assert!(!readme.contains("1-greet/"), "{readme}");
+}
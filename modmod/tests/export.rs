@@ -0,0 +1,180 @@
+//! The exports of `modmod export`, which must be readable by the tools they are for
+
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{
+    archive::{self, ArchiveFormat, EntryContent},
+    cartridge::{self, CARTRIDGE_MANIFEST_FILE},
+    context::RunContext,
+};
+
+#[test]
+fn anki_package_holds_a_collection() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello/topic.toml");
+    let content = fs::read_to_string(&topic).unwrap();
+    fs::write(
+        &topic,
+        format!("summary = [\"Rust prints with **println!**\"]\n{content}\n[glossary]\nmacro = \"Code that writes code\"\n"),
+    )
+    .unwrap();
+
+    // As `export anki`, and as the command it was before the group
+    for command in [&["export", "anki"][..], &["anki"]] {
+        let package = dir.join("deck.apkg");
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .args(command)
+            .args(["--summaries", "cloze", "-o"])
+            .arg(&package)
+            .arg(&track_path)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains("Wrote 1 card(s) and 1 cloze card(s)"),
+            "{stdout}"
+        );
+
+        let entries = archive::read_archive(&package, ArchiveFormat::Zip).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["collection.anki2", "media"]);
+        let EntryContent::Bytes(collection) = &entries[0].content else {
+            panic!("The collection isn't read into memory");
+        };
+        assert!(collection.starts_with(b"SQLite format 3\0"));
+        // Whole pages of 4096 bytes, as the header says
+        assert_eq!(collection[16..18], [0x10, 0x00]);
+        let pages = u32::from_be_bytes(collection[28..32].try_into().unwrap());
+        assert_eq!(collection.len(), pages as usize * 4096);
+        fs::remove_file(package).unwrap();
+    }
+}
+
+/// The content of an entry that is read into memory, as text
+fn text(entry: &archive::Entry) -> String {
+    let EntryContent::Bytes(content) = &entry.content else {
+        panic!("{} isn't read into memory", entry.path);
+    };
+    String::from_utf8(content.clone()).unwrap()
+}
+
+/// The values of `attribute` in the XML, in order
+fn attributes<'x>(xml: &'x str, attribute: &str) -> Vec<&'x str> {
+    xml.split(&format!(" {attribute}=\""))
+        .skip(1)
+        .map(|rest| rest.split_once('"').unwrap().0)
+        .collect()
+}
+
+#[test]
+fn cartridge_round_trips() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let package = dir.join("course.imscc");
+
+    for command in [&["export", "cartridge"][..], &["cartridge"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .args(command)
+            .args(["--base-url", "https://example.com/training", "-o"])
+            .arg(&package)
+            .arg(&track_path)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains("with 1 chapter(s), 1 slide link(s) and 1 assignment(s)"),
+            "{stdout}"
+        );
+    }
+
+    let entries = archive::read_archive(&package, ArchiveFormat::Zip).unwrap();
+    cartridge::check_references(&entries).unwrap();
+    let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            CARTRIDGE_MANIFEST_FILE,
+            "chapters/first-steps.html",
+            "slides/1_1.xml",
+            "assignments/1_1_1/assignment.xml",
+        ]
+    );
+    let manifest = text(&entries[0]);
+    assert!(manifest.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest"));
+    // Each item is of a resource, and each file of the cartridge is in a resource
+    let resources = attributes(&manifest, "identifier");
+    for reference in attributes(&manifest, "identifierref") {
+        assert!(resources.contains(&reference), "{reference}: {manifest}");
+    }
+    assert_eq!(
+        attributes(&manifest, "href")
+            .into_iter()
+            .filter(|href| !href.ends_with(".xsd"))
+            .collect::<Vec<_>>(),
+        [
+            "chapters/first-steps.html",
+            "chapters/first-steps.html",
+            "slides/1_1.xml",
+            "assignments/1_1_1/assignment.xml",
+        ]
+    );
+    assert!(manifest.contains("<title>Unit 1.1 - First steps</title>"));
+
+    let chapter = text(&entries[1]);
+    assert!(
+        chapter.contains("<title>Unit 1.1 - First steps</title>"),
+        "{chapter}"
+    );
+    let slides = text(&entries[2]);
+    assert!(
+        slides
+            .contains("<url href=\"https://example.com/training/slides/1_1/\" target=\"_blank\"/>"),
+        "{slides}"
+    );
+    let assignment = text(&entries[3]);
+    assert!(
+        assignment.contains("<title>Exercise 1.1.1: Greet</title>"),
+        "{assignment}"
+    );
+    assert!(
+        assignment.contains("&lt;p&gt;Make the program greet whoever runs it."),
+        "{assignment}"
+    );
+
+    // A cartridge without a file its manifest refers to is caught when it's read back
+    let incomplete = dir.join("incomplete.imscc");
+    archive::write_archive(&entries[..3], &incomplete, ArchiveFormat::Zip).unwrap();
+    let read_back = archive::read_archive(&incomplete, ArchiveFormat::Zip).unwrap();
+    let report = cartridge::check_references(&read_back).unwrap_err();
+    assert!(
+        format!("{report:?}").contains(
+            "The manifest of the cartridge refers to files it doesn't have: assignments/1_1_1/assignment.xml"
+        ),
+        "{report:?}"
+    );
+}
+
+#[test]
+fn cartridge_needs_an_absolute_base_url() {
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["export", "cartridge", "--base-url", "/training/", "-o"])
+        .arg(TempDir::new().join("course.imscc"))
+        .arg(common::BASIC_TRACK)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("--base-url must be an absolute URL like https://example.com/training/"),
+        "{stderr}"
+    );
+}
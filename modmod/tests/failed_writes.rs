@@ -0,0 +1,190 @@
+//! A render that fails partway through, because a file can't be written, must not leave
+//! files behind that were only written in part
+
+mod common;
+
+use std::{
+    fs, io,
+    io::Write,
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use common::{read_tree, render, TempDir};
+use modmod::{
+    context::RunContext,
+    failure::ErrorCode,
+    io::{self as modmod_io, FsSink, OutputFile, RealFs, JOURNAL_FILE, TEMP_SUFFIX},
+    update, RenderOptions,
+};
+
+/// Writes to the file system like [`RealFs`], until the file that is created after
+/// `succeeding` others: half of it is written to its temporary file, and then writing
+/// fails like it does on a full disk
+struct FailingFs {
+    succeeding: usize,
+    created: AtomicUsize,
+}
+
+impl FailingFs {
+    fn new(succeeding: usize) -> Self {
+        Self {
+            succeeding,
+            created: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl FsSink for FailingFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        RealFs.create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile> {
+        match self.created.fetch_add(1, Ordering::SeqCst) == self.succeeding {
+            // The content comes to `write` when the file is committed
+            true => Ok(OutputFile::Buffered {
+                path: path.to_path_buf(),
+                content: vec![],
+                mode: None,
+            }),
+            false => RealFs.create_file(path),
+        }
+    }
+
+    fn write(&self, path: &Path, content: Vec<u8>, _mode: Option<u32>) -> io::Result<bool> {
+        let mut file = RealFs.create_file(path)?;
+        file.write_all(&content[..content.len() / 2])?;
+        // Dropping the file without committing it is what an error while writing does
+        drop(file);
+        Err(io::Error::new(
+            io::ErrorKind::StorageFull,
+            "No space left on device",
+        ))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        RealFs.copy(from, to)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        RealFs.symlink(target, link)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_dir_all(path)
+    }
+
+    fn is_real(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn failed_write_leaves_only_complete_files() {
+    let _context = RunContext::default().enter();
+    let track = common::basic_track();
+    let dir = TempDir::new();
+    render(&track, &dir.join("complete"));
+    let complete = read_tree(&dir.join("complete"));
+
+    for succeeding in [0, 3, 6] {
+        let out_dir = dir.join(format!("failed-{succeeding}"));
+        let result = {
+            let _context = RunContext::with_sink(FailingFs::new(succeeding)).enter();
+            modmod_io::begin_journal::<update::UpdateError>(&out_dir).unwrap();
+            track.render(&RenderOptions::new(&out_dir), &mut ())
+        };
+        let report = result.unwrap_err();
+        let message = format!("{report:?}");
+        assert!(message.contains("No space left on device"), "{message}");
+        assert_eq!(ErrorCode::of(&report), ErrorCode::Render);
+
+        let mut written = read_tree(&out_dir);
+        written.retain(|(path, _)| path != Path::new(JOURNAL_FILE));
+        assert!(written.len() < complete.len());
+        for (path, content) in &written {
+            assert!(
+                !path.to_string_lossy().ends_with(TEMP_SUFFIX),
+                "{} was left behind",
+                path.display()
+            );
+            let expected = complete.iter().find(|(p, _)| p == path).map(|(_, c)| c);
+            assert_eq!(Some(content), expected, "{} is incomplete", path.display());
+        }
+
+        // The journal lists what the failed render wrote, so that it can be cleaned up
+        let journal = modmod_io::read_journal::<update::UpdateError>(&out_dir)
+            .unwrap()
+            .unwrap_or_default();
+        assert!(written
+            .iter()
+            .all(|(path, _)| journal.contains(&path.to_string_lossy().replace('\\', "/"))));
+        update::clean(&out_dir, false).unwrap();
+        assert!(read_tree(&out_dir).is_empty());
+        fs::remove_dir_all(&out_dir).ok();
+    }
+}
+
+#[test]
+fn failed_write_exits_as_a_render_error() {
+    let dir = TempDir::new();
+    let track_path = common::copy_basic_track(&dir.join("content"));
+    // The output folder can't be created under a file
+    fs::write(dir.join("file"), "").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["--error-format", "json", "generate", "-o"])
+        .arg(dir.join("file/out"))
+        .arg(&track_path)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    let summary = stderr.lines().last().unwrap();
+    assert!(summary.contains(r#""code":"render""#), "{summary}");
+    assert!(!summary.contains(r#""code":"validation""#), "{summary}");
+}
+
+#[test]
+fn failed_generate_leaves_no_journal() {
+    let dir = TempDir::new();
+    let track_path = common::copy_basic_track(&dir.join("content"));
+    let out_dir = dir.join("out");
+    let generate = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .arg("generate")
+            .args(args)
+            .arg("-o")
+            .arg(&out_dir)
+            .arg(&track_path)
+            .output()
+            .unwrap()
+    };
+    // The exercises and the book are written before the slides, whose folder can't be
+    // created over a file
+    fs::create_dir_all(&out_dir).unwrap();
+    fs::write(out_dir.join("slides"), "").unwrap();
+    let output = generate(&["--clear", "--force"]);
+    assert!(!output.status.success());
+    assert!(out_dir.join("book/src/SUMMARY.md").exists());
+    assert!(!modmod_io::journal_path(&out_dir).exists());
+
+    // Nor does an interrupted one outlive its folder
+    fs::write(modmod_io::journal_path(&out_dir), "book/src/SUMMARY.md\n").unwrap();
+    fs::remove_dir_all(&out_dir).unwrap();
+    let output = generate(&[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(!modmod_io::journal_path(&out_dir).exists());
+}
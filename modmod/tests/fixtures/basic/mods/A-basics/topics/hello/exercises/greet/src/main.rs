@@ -0,0 +1,3 @@
+fn main() {
+    // Print a greeting here
+}
@@ -0,0 +1,93 @@
+//! Exercises hosted in a git repository, here one next to a copy of the fixture track, which
+//! are fetched into the cache once
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{context::RunContext, load::Loader, Track};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// A copy of the fixture track whose exercise is in a repository of its own at `rev`, with
+/// the path of the track and the commit of the exercise
+fn track_with_git_exercise(dir: &Path, rev: Option<&str>) -> (std::path::PathBuf, String) {
+    let track_path = copy_basic_track(&dir.join("content"));
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello");
+    let repo = dir.join("greet");
+    fs::rename(topic.join("exercises/greet"), &repo).unwrap();
+    git(&repo, &["init", "--quiet", "--initial-branch=main"]);
+    git(&repo, &["config", "user.name", "modmod"]);
+    git(&repo, &["config", "user.email", "modmod@example.com"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "Greet"]);
+    let commit = git(&repo, &["rev-parse", "HEAD"]);
+    fs::write(
+        topic.join("topic.toml"),
+        format!(
+            "name = \"Hello, world\"\n\n[[exercises]]\nname = \"Greet\"\ngit = \"{}\"\nrev = \"{}\"\npath = \".\"\n",
+            repo.display(),
+            rev.unwrap_or(&commit)
+        ),
+    )
+    .unwrap();
+    (track_path, commit)
+}
+
+fn load(track_path: &Path, cache: &Path, offline: bool) -> Result<Track, String> {
+    let mut loader = Loader::new().with_git_cache_dir(cache.to_path_buf());
+    if offline {
+        loader = loader.offline();
+    }
+    let mut tracks = Track::load_toml_defs_with(&[track_path], loader);
+    tracks.pop().unwrap().map_err(|e| format!("{e:?}"))
+}
+
+fn greet_source(track: &Track) -> &modmod::GitSource {
+    let topic = &track.modules[0].data.units[0].data.topics[0].data;
+    topic.exercises[0].data.git.as_ref().unwrap()
+}
+
+#[test]
+fn branches_and_commits_are_checked_out_into_the_cache() {
+    let _context = RunContext::default().enter();
+    for rev in [Some("main"), None] {
+        let dir = TempDir::new();
+        let (track_path, commit) = track_with_git_exercise(dir.path(), rev);
+        let cache = dir.join("cache");
+
+        let track = load(&track_path, &cache, false).unwrap();
+        assert_eq!(greet_source(&track).commit, commit);
+        let checkouts: Vec<_> = fs::read_dir(cache.join("git")).unwrap().collect();
+        assert_eq!(checkouts.len(), 1);
+
+        // Once it's cached, the repository isn't needed anymore
+        fs::remove_dir_all(dir.join("greet")).unwrap();
+        let track = load(&track_path, &cache, true).unwrap();
+        assert_eq!(greet_source(&track).commit, commit);
+    }
+}
+
+#[test]
+fn exercises_that_are_not_cached_fail_offline() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track_path, _) = track_with_git_exercise(dir.path(), Some("main"));
+
+    let error = load(&track_path, &dir.join("cache"), true).unwrap_err();
+    assert!(error.contains("not allowed offline"), "{error}");
+}
@@ -0,0 +1,105 @@
+//! Renders of the fixture track into other folders of the output than the default ones,
+//! which everything that refers to them must follow
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{context::RunContext, manifest::RenderManifest};
+
+const LAYOUT: &str = r#"
+[output]
+slides = "deck"
+book = "docs"
+exercises = "work"
+images = "img"
+dist = "built"
+"#;
+
+/// Renders the track with `generate --clear`, and its exit code and stderr
+fn generate(track_path: &Path, out_dir: &Path, args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["generate", "--clear", "--base-url", "/training/", "-o"])
+        .arg(out_dir)
+        .args(args)
+        .arg(track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    (output.status.code(), stderr)
+}
+
+fn set_layout(track_path: &Path, layout: &str) {
+    let content = fs::read_to_string(track_path).unwrap();
+    fs::write(track_path, format!("{content}{layout}")).unwrap();
+}
+
+#[test]
+fn renders_into_the_folders_of_the_layout() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    set_layout(&track_path, LAYOUT);
+    let out_dir = dir.join("out");
+    let (code, stderr) = generate(&track_path, &out_dir, &[]);
+    assert_eq!(code, Some(0), "{stderr}");
+
+    for (path, exists) in [
+        ("deck/1_1-first-steps.md", true),
+        ("deck/img/ferris.svg", true),
+        ("docs/book.toml", true),
+        ("docs/src/first-steps.md", true),
+        ("work/Cargo.toml", true),
+        ("work/1-basics/1-first-steps/1-greet/src/main.rs", true),
+        ("slides", false),
+        ("book", false),
+        ("exercises", false),
+    ] {
+        assert_eq!(out_dir.join(path).exists(), exists, "{path}");
+    }
+    let read = |path: &str| fs::read_to_string(out_dir.join(path)).unwrap();
+    // Images, the build scripts of the slides and the links of the book to them
+    assert!(read("deck/1_1-first-steps.md").contains("![Ferris](/img/ferris.svg)"));
+    let package = read("deck/package.json");
+    assert!(
+        package.contains("--out built/1_1-first-steps --base /training/deck/1_1/"),
+        "{package}"
+    );
+    let chapter = read("docs/src/first-steps.md");
+    assert!(
+        chapter.contains("href=\"/training/deck/1_1/\""),
+        "{chapter}"
+    );
+    let workspace = read("work/Cargo.toml");
+    assert!(
+        workspace.contains("1-basics/1-first-steps/1-greet"),
+        "{workspace}"
+    );
+
+    let manifest = RenderManifest::read(&out_dir).unwrap().unwrap();
+    let paths: Vec<_> = manifest.artifacts.iter().map(|a| a.path.as_str()).collect();
+    assert!(paths.contains(&"deck/1_1-first-steps.md"), "{paths:?}");
+    assert!(paths.contains(&"docs/src/first-steps.md"), "{paths:?}");
+    assert!(
+        paths.contains(&"work/1-basics/1-first-steps/1-greet"),
+        "{paths:?}"
+    );
+
+    // The flags override the layout of the track
+    let (code, stderr) = generate(&track_path, &out_dir, &["--book-dir", "handbook"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(out_dir.join("handbook/src/first-steps.md").exists());
+    assert!(out_dir.join("deck/1_1-first-steps.md").exists());
+}
+
+#[test]
+fn folders_outside_the_output_are_rejected() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    set_layout(&track_path, "\n[output]\nslides = \"../outside\"\n");
+    let (code, stderr) = generate(&track_path, &dir.join("out"), &[]);
+    assert_ne!(code, Some(0), "{stderr}");
+    assert!(stderr.contains("../outside"), "{stderr}");
+    assert!(!dir.join("outside").exists());
+}
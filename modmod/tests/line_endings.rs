@@ -0,0 +1,152 @@
+//! Content edited on Windows, with a byte order mark or `\r\n` line endings, must render
+//! to the same output as the fixture track, and generated text is written with the line
+//! endings that were asked for
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use common::{copy_basic_track, memory_tree, read_tree, TempDir};
+use modmod::{
+    context::RunContext, io::LineEnding, manifest::RENDER_MANIFEST_FILE, RenderOptions, Track,
+};
+
+/// Changes the text of a file, like an editor on Windows would
+type Edit = fn(&str) -> String;
+
+/// A copy of the fixture track in `dir` with the text of its definitions and markdown
+/// changed by `edit`. The files of the exercise are copied as they are, and stay the same.
+fn edited_track(dir: &Path, edit: Edit) -> Track {
+    let track_path = copy_basic_track(dir);
+    for (path, content) in read_tree(dir) {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let definition = ["track.toml", "mod.toml", "topic.toml"]
+            .iter()
+            .any(|suffix| name.ends_with(suffix));
+        if definition || name.ends_with(".md") {
+            let content = String::from_utf8(content).unwrap();
+            fs::write(dir.join(path), edit(&content)).unwrap();
+        }
+    }
+    Track::load(&track_path).unwrap()
+}
+
+/// The output of `track` in memory, rendered with `line_ending`. The render manifest has the
+/// hashes of the sources, and is left out.
+fn render(track: &Track, dir: &TempDir, line_ending: LineEnding) -> Vec<(PathBuf, Vec<u8>)> {
+    let (context, memory) = RunContext::in_memory();
+    let _context = context.line_ending(line_ending).enter();
+    let out_dir = dir.join("out");
+    track
+        .render(&RenderOptions::new(&out_dir), &mut ())
+        .unwrap();
+    let mut files = memory_tree(&memory, &out_dir);
+    files.retain(|(path, _)| path != Path::new(RENDER_MANIFEST_FILE));
+    files
+}
+
+fn assert_same_output(name: &str, output: &[(PathBuf, Vec<u8>)], expected: &[(PathBuf, Vec<u8>)]) {
+    let paths =
+        |files: &[(PathBuf, Vec<u8>)]| files.iter().map(|f| f.0.clone()).collect::<Vec<_>>();
+    assert_eq!(paths(output), paths(expected), "{name}");
+    for ((path, content), (_, expected)) in output.iter().zip(expected) {
+        assert!(
+            content == expected,
+            "{name}: {} differs:\n{}",
+            path.display(),
+            String::from_utf8_lossy(content)
+        );
+    }
+}
+
+#[test]
+fn windows_text_renders_like_the_fixture() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let expected = render(&common::basic_track(), &dir, LineEnding::Lf);
+    let edits: [(&str, Edit); 4] = [
+        ("byte order mark", |text| format!("\u{feff}{text}")),
+        ("crlf", |text| text.replace('\n', "\r\n")),
+        ("byte order mark and crlf", |text| {
+            format!("\u{feff}{}", text.replace('\n', "\r\n"))
+        }),
+        ("cr", |text| text.replace('\n', "\r")),
+    ];
+    for (name, edit) in edits {
+        let dir = TempDir::new();
+        let track = edited_track(&dir.join("content"), edit);
+        assert_same_output(name, &render(&track, &dir, LineEnding::Lf), &expected);
+    }
+}
+
+#[test]
+fn frontmatter_after_a_byte_order_mark_is_found() {
+    let _context = RunContext::default().enter();
+    fn with_objectives(text: &str) -> String {
+        text.replace(
+            "---\nlayout: default\n---\n",
+            "---\nobjectives:\n  - Say hello\n---\n",
+        )
+    }
+    let dir = TempDir::new();
+    let expected = render(
+        &edited_track(&dir.join("lf"), with_objectives),
+        &dir,
+        LineEnding::Lf,
+    );
+    let track = edited_track(&dir.join("windows"), |text| {
+        format!("\u{feff}{}", with_objectives(text).replace('\n', "\r\n"))
+    });
+    let output = render(&track, &dir, LineEnding::Lf);
+    assert_same_output("frontmatter", &output, &expected);
+
+    let (_, deck) = output
+        .iter()
+        .find(|(path, _)| path == Path::new(common::DECK))
+        .unwrap();
+    let deck = String::from_utf8_lossy(deck);
+    assert!(!deck.contains("objectives:"), "{deck}");
+    let objectives: Vec<_> = track
+        .modules
+        .iter()
+        .flat_map(|m| &m.data.units)
+        .flat_map(|u| &u.data.topics)
+        .flat_map(|t| &t.data.objectives)
+        .collect();
+    // Both topics of the fixture have frontmatter
+    assert_eq!(objectives, ["Say hello"; 2]);
+}
+
+#[test]
+fn generated_text_gets_the_line_endings_asked_for() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = common::basic_track();
+    let lf = render(&track, &dir, LineEnding::Lf);
+    let crlf = render(&track, &dir, LineEnding::Crlf);
+    assert_eq!(lf.len(), crlf.len());
+
+    let mut converted = vec![];
+    for ((path, lf), (_, crlf)) in lf.iter().zip(&crlf) {
+        let lf = String::from_utf8_lossy(lf);
+        let crlf = String::from_utf8_lossy(crlf);
+        assert!(!crlf.contains("\r\r"), "{}", path.display());
+        if crlf != lf {
+            assert_eq!(crlf, lf.replace('\n', "\r\n"), "{}", path.display());
+            converted.push(path.to_str().unwrap().replace('\\', "/"));
+        }
+    }
+    for generated in [common::DECK, "slides/package.json", "book/src/SUMMARY.md"] {
+        assert!(converted.iter().any(|p| p == generated), "{converted:?}");
+    }
+    // Copied files are left as they are
+    for copied in [
+        "slides/images/ferris.svg",
+        "exercises/1-basics/1-first-steps/1-greet/src/main.rs",
+    ] {
+        assert!(!converted.iter().any(|p| p == copied), "{converted:?}");
+    }
+}
@@ -0,0 +1,357 @@
+//! Loading tracks whose definitions refer to their content in other ways than the fixture
+//! track does, and from other folders
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{context::RunContext, load::TrackDef, Track};
+
+/// Replaces the topic list of the unit of a track copied by [`copy_basic_track`]
+fn set_topics(track_path: &Path, topics: &str) {
+    let mod_toml = track_path.with_file_name("mods/A-basics/mod.toml");
+    let content = fs::read_to_string(&mod_toml).unwrap();
+    let start = content.find("topics = [").unwrap();
+    fs::write(
+        &mod_toml,
+        format!("{}topics = {topics}\n", &content[..start]),
+    )
+    .unwrap();
+}
+
+fn topic_names(track: &Track) -> Vec<&str> {
+    track.topics().map(|t| t.name.as_str()).collect()
+}
+
+#[test]
+fn topic_globs_only_match_topics() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topics = track_path.with_file_name("mods/A-basics/topics");
+    // Neither a plain file nor a folder without a topic.toml is a topic
+    fs::write(topics.join("notes.md"), "# Notes").unwrap();
+    fs::write(topics.join("draft.toml"), "name = \"Draft\"").unwrap();
+    fs::create_dir(topics.join("empty")).unwrap();
+
+    set_topics(&track_path, r#"["topics/*"]"#);
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(topic_names(&track), ["Hello, world", "Ownership"]);
+
+    set_topics(&track_path, r#"["topics/**/*.toml"]"#);
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(topic_names(&track), ["Hello, world", "Ownership"]);
+
+    set_topics(&track_path, r#"["topics/*.md"]"#);
+    let report = Track::load(&track_path).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains("Topic glob 'topics/*.md' does not match any topic"),
+        "{message}"
+    );
+}
+
+#[test]
+fn topic_globs_match_outside_the_module() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let mods = track_path.with_file_name("mods");
+    fs::rename(mods.join("A-basics/topics"), mods.join("shared")).unwrap();
+
+    set_topics(&track_path, r#"["../shared/*"]"#);
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(topic_names(&track), ["Hello, world", "Ownership"]);
+
+    set_topics(&track_path, r#"["./../shared/own*/topic.toml"]"#);
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(topic_names(&track), ["Ownership"]);
+
+    set_topics(&track_path, r#"["../missing/*"]"#);
+    let report = Track::load(&track_path).unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains("Topic glob '../missing/*' does not match any topic"),
+        "{message}"
+    );
+}
+
+/// Runs modmod in `dir`, and fails with its output if it fails
+fn modmod_in(dir: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{args:?}: {stderr}");
+}
+
+#[test]
+fn paths_are_relative_to_the_definition_that_has_them() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    // The track definition refers to its module through ../
+    let tracks = dir.join("content/tracks");
+    fs::create_dir(&tracks).unwrap();
+    let content = fs::read_to_string(&track_path).unwrap();
+    fs::write(
+        tracks.join("basic.track.toml"),
+        content.replace("\"mods/", "\"../mods/"),
+    )
+    .unwrap();
+    fs::remove_file(&track_path).unwrap();
+    fs::create_dir(dir.join("elsewhere")).unwrap();
+
+    for path in [
+        tracks.join("basic.track.toml"),
+        tracks.join("../tracks/basic.track.toml"),
+    ] {
+        let track = Track::load(&path).unwrap();
+        assert_eq!(topic_names(&track), ["Hello, world", "Ownership"]);
+    }
+    // From another working directory, with a relative path to the track
+    modmod_in(
+        &dir.join("elsewhere"),
+        &["check", "../content/tracks/basic.track.toml"],
+    );
+}
+
+#[test]
+fn create_works_from_inside_the_content() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let module_dir = track_path.with_file_name("mods/A-basics");
+
+    modmod_in(
+        &module_dir,
+        &["create", "topic", "mod.toml", "greeting", "Greeting"],
+    );
+    let mod_toml = fs::read_to_string(module_dir.join("mod.toml")).unwrap();
+    assert!(
+        mod_toml.contains("\"topics/greeting/topic.toml\""),
+        "{mod_toml}"
+    );
+    // From the folder of the topic, whose path then has no folder
+    let topic_dir = module_dir.join("topics/greeting");
+    modmod_in(&topic_dir, &["create", "exercise", "topic.toml", "shout"]);
+
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(
+        topic_names(&track),
+        ["Hello, world", "Ownership", "Greeting"]
+    );
+    let greeting = track.topics().find(|t| t.name == "Greeting").unwrap();
+    assert_eq!(greeting.exercises.len(), 1);
+    modmod_in(dir.path(), &["check", "basic.track.toml"]);
+}
+
+/// The messages attached to a report, outermost first
+fn messages<C>(report: &error_stack::Report<C>) -> Vec<String> {
+    report
+        .frames()
+        .filter_map(|frame| {
+            frame
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| frame.downcast_ref::<&str>().map(|s| s.to_string()))
+        })
+        .collect()
+}
+
+#[test]
+fn errors_of_parallel_loading_come_in_order() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topics_dir = track_path.with_file_name("mods/A-basics/topics");
+    let mut topics = vec![];
+    for i in 0..8 {
+        let topic = topics_dir.join(format!("broken-{i}"));
+        fs::create_dir(&topic).unwrap();
+        fs::write(topic.join("slides.md"), "# Broken\n").unwrap();
+        // Each broken in its own way, some on later lines than others
+        let definition = match i % 3 {
+            0 => format!("name = \"Broken {i}\"\nobjectives = [\n"),
+            1 => format!("name = {i}\n"),
+            _ => format!(
+                "{}name = \"Broken {i}\"\nname = \"Again\"\n",
+                "\n".repeat(i)
+            ),
+        };
+        fs::write(topic.join("topic.toml"), definition).unwrap();
+        topics.push(format!("\"topics/broken-{i}/topic.toml\""));
+    }
+    set_topics(&track_path, &format!("[{}]", topics.join(", ")));
+
+    let mut runs = vec![];
+    for jobs in [1, 8, 3, 8, 8] {
+        let _context = RunContext::default().jobs(jobs).enter();
+        let report = Track::load(&track_path).unwrap_err();
+        runs.push(messages(&report));
+    }
+    let first = &runs[0];
+    for (i, file) in (0..8).map(|i| (i, format!("broken-{i}/topic.toml"))) {
+        assert!(
+            first.iter().any(|m| m.contains(&file)),
+            "broken-{i} isn't reported in {first:#?}"
+        );
+    }
+    // In the order of the topics
+    let positions: Vec<_> = (0..8)
+        .map(|i| {
+            let file = format!("broken-{i}/topic.toml");
+            first.iter().position(|m| m.contains(&file)).unwrap()
+        })
+        .collect();
+    assert!(positions.windows(2).all(|p| p[0] < p[1]), "{first:#?}");
+    for run in &runs[1..] {
+        assert_eq!(run, first);
+    }
+}
+
+#[test]
+fn parse_errors_point_at_their_location() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello/topic.toml");
+    fs::write(
+        &topic,
+        "name = \"Hello, world\"\nobjectives = [\"a\", 3 4]\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .arg("check")
+        .arg(&track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Invalid definitions exit with 2
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    let expected = format!(
+        "error[E0102]: invalid array
+expected `]`
+ --> {}:2:22
+  |
+2 | objectives = [\"a\", 3 4]
+  |                      ^
+",
+        topic.display()
+    );
+    assert!(stderr.starts_with(&expected), "{stderr}");
+}
+
+#[test]
+fn keys_close_to_a_known_one_are_misspelled() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello/topic.toml");
+    let definition = fs::read_to_string(&topic).unwrap();
+    for (misspelled, expected) in [
+        (
+            "objektives = [\"a\"]\n",
+            "Unknown key 'objektives'. Did you mean 'objectives'?",
+        ),
+        (
+            "sumary = \"a\"\n",
+            "Unknown key 'sumary'. Did you mean 'summary'?",
+        ),
+    ] {
+        fs::write(&topic, format!("{misspelled}{definition}")).unwrap();
+        let report = Track::load(&track_path).unwrap_err();
+        let messages = messages(&report);
+        assert!(messages.iter().any(|m| m == expected), "{messages:#?}");
+        assert_eq!(
+            modmod::catalog::ErrorKind::of(&report),
+            modmod::catalog::ErrorKind::UnknownKey
+        );
+    }
+
+    // Also in the tables of a definition
+    fs::write(&topic, format!("{definition}optinal = true\n")).unwrap();
+    let messages = messages(&Track::load(&track_path).unwrap_err());
+    assert!(
+        messages
+            .iter()
+            .any(|m| m == "Unknown key 'optinal'. Did you mean 'optional'?"),
+        "{messages:#?}"
+    );
+}
+
+#[test]
+fn keys_far_from_any_known_one_are_ignored() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello/topic.toml");
+    let definition = fs::read_to_string(&topic).unwrap();
+    fs::write(
+        &topic,
+        format!("difficulty = \"hard\"\nx = 1\n{definition}"),
+    )
+    .unwrap();
+    let track = Track::load(&track_path).unwrap();
+    assert_eq!(topic_names(&track), ["Hello, world", "Ownership"]);
+}
+
+#[test]
+fn unknown_ids_suggest_the_closest_one() {
+    let _context = RunContext::default().enter();
+    let track = common::basic_track();
+    let render_units = |units: &[&str]| {
+        let dir = TempDir::new();
+        let mut options = modmod::RenderOptions::new(dir.join("out"));
+        options.units = units.iter().map(|u| u.to_string()).collect();
+        let report = track.render(&options, &mut ()).unwrap_err();
+        messages(&report)
+    };
+    let messages = render_units(&["frist-steps"]);
+    assert!(
+        messages
+            .iter()
+            .any(|m| m
+                == "Unit selector 'frist-steps' matches no units. Did you mean 'first-steps'?"),
+        "{messages:#?}"
+    );
+    // Nothing like any unit the track has
+    let messages = render_units(&["generics"]);
+    assert!(
+        messages
+            .iter()
+            .any(|m| m == "Unit selector 'generics' matches no units."),
+        "{messages:#?}"
+    );
+}
+
+#[test]
+fn only_https_urls_are_fetched() {
+    let error =
+        TrackDef::load_source(Path::new("http://example.com/basic.track.toml")).unwrap_err();
+    let messages = messages(&error);
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.contains("only https URLs are fetched")),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn fetching_without_curl_says_so() {
+    let dir = TempDir::new();
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(["check", "https://example.com/basic.track.toml"])
+        .env("PATH", dir.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("curl isn't installed, or isn't on the PATH"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,57 @@
+//! Locks of the fixture track, and verifying its content against them
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{change_upstream, copy_basic_track, TempDir};
+use modmod::{
+    context::RunContext,
+    lock::{Lockfile, LOCK_VERSION},
+    Track,
+};
+
+/// Locks `track` in the lockfile next to its definition, like `modmod lock`
+fn lock(track: &Track, version: u32) {
+    let lockfile = Lockfile {
+        version,
+        tracks: BTreeMap::from([(Lockfile::key_of(&track.definition), track.lock().unwrap())]),
+    };
+    lockfile
+        .write(&Lockfile::path_of(&track.definition))
+        .unwrap();
+}
+
+#[test]
+fn files_are_locked_with_sha256() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(copy_basic_track(dir.path())).unwrap();
+    let locked = track.lock().unwrap();
+    // As `sha256sum` has it
+    assert_eq!(
+        locked.files["mods/A-basics/topics/hello/slides.md"],
+        "bfd0f9eaba70c2632b892f637f080f7b43bc09d74a6467938b994f514087c084"
+    );
+
+    lock(&track, LOCK_VERSION);
+    track.verify_lock().unwrap();
+    let changed = change_upstream(&track.definition);
+    let report = changed.verify_lock().unwrap_err();
+    let message = format!("{report:?}");
+    assert!(
+        message.contains("mods/A-basics/topics/hello/slides.md"),
+        "{message}"
+    );
+}
+
+#[test]
+fn locks_with_the_old_hashes_have_to_be_refreshed() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = Track::load(copy_basic_track(dir.path())).unwrap();
+    lock(&track, 1);
+    let report = track.verify_lock().unwrap_err();
+    let message = format!("{report:?}");
+    assert!(message.contains("modmod update-lock"), "{message}");
+}
@@ -0,0 +1,144 @@
+//! Rendering a track with a topic and an exercise description of hundreds of megabytes,
+//! generated on the fly, within a ceiling on the memory the render allocates, which the
+//! allocator of this test counts
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use common::{copy_basic_track, TempDir, DECK};
+use modmod::{context::RunContext, report::WarningCode, RenderOptions, RenderTarget, Track};
+
+/// The system allocator, counting the bytes that are allocated and the most that were
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn allocated(size: usize) {
+    let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const MIB: usize = 1 << 20;
+
+/// Size of the generated sections
+const SECTION_SIZE: usize = 200 * MIB;
+
+/// Writes about [`SECTION_SIZE`] bytes of slides, with an image embedded as base64 on
+/// each slide, a line at a time
+fn write_section(path: &Path, header: &str) {
+    let mut file = BufWriter::new(fs::File::create(path).unwrap());
+    file.write_all(header.as_bytes()).unwrap();
+    let image = format!(
+        "![](data:image/png;base64,{})\n",
+        "iVBORw0KGgo".repeat(3000)
+    );
+    let mut written = header.len();
+    let mut slide = 0;
+    while written < SECTION_SIZE {
+        let text = format!(
+            "\n---\n\n# Slide {slide}\n\n{image}\nSome text of slide {slide} that goes with the image.\n"
+        );
+        file.write_all(text.as_bytes()).unwrap();
+        written += text.len();
+        slide += 1;
+    }
+    file.flush().unwrap();
+}
+
+/// The most the render of `track_path` into `out_dir` allocated at once, over what was
+/// allocated before, with the warnings of the render
+fn peak_of_render(
+    track_path: &Path,
+    out_dir: &Path,
+    target: RenderTarget,
+) -> (usize, Vec<WarningCode>) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let warnings = {
+        let track = Track::load(track_path).unwrap();
+        let mut options = RenderOptions::new(out_dir);
+        options.targets = vec![target];
+        options.clear_output_dir = true;
+        let report = track.render(&options, &mut ()).unwrap();
+        report.warnings.iter().map(|w| w.code).collect()
+    };
+    (PEAK.load(Ordering::Relaxed) - before, warnings)
+}
+
+// A single test, as the allocator counts what every thread of the test binary allocates
+#[test]
+fn large_sections_render_within_a_memory_ceiling() {
+    let _context = RunContext::default().enter();
+    // A section is held in memory at most once, on top of what the rest of the render takes
+    let ceiling = SECTION_SIZE + SECTION_SIZE / 4 + 32 * MIB;
+
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let topic = track_path.with_file_name("mods/A-basics/topics/hello");
+    write_section(
+        &topic.join("slides.md"),
+        "---\nlayout: default\n---\n\n# Hello, world\n",
+    );
+    let out_dir = dir.join("out");
+    let (peak, warnings) = peak_of_render(&track_path, &out_dir, RenderTarget::Slides);
+    let deck = fs::metadata(out_dir.join(DECK)).unwrap().len() as usize;
+    assert!(deck > SECTION_SIZE, "the deck is {deck} bytes");
+    assert!(peak < ceiling, "rendering the deck took {} MiB", peak / MIB);
+    assert!(
+        warnings.contains(&WarningCode::LargeSection),
+        "{warnings:?}"
+    );
+
+    fs::write(topic.join("slides.md"), "# Hello, world\n").unwrap();
+    write_section(
+        &topic.join("exercises/greet/description.md"),
+        "Make the program greet whoever runs it.\n",
+    );
+    let (peak, warnings) = peak_of_render(&track_path, &out_dir, RenderTarget::Book);
+    let pages: usize = fs::read_dir(out_dir.join("book/src"))
+        .unwrap()
+        .map(|entry| entry.unwrap().metadata().unwrap().len() as usize)
+        .max()
+        .unwrap();
+    assert!(pages > SECTION_SIZE, "the largest page is {pages} bytes");
+    assert!(peak < ceiling, "rendering the book took {} MiB", peak / MIB);
+    assert!(
+        warnings.contains(&WarningCode::LargeSection),
+        "{warnings:?}"
+    );
+}
@@ -0,0 +1,107 @@
+//! `modmod migrate` of the tracks in `tests/fixtures/migrate/<case>/before`, which must give
+//! the files in `tests/fixtures/migrate/<case>/after`
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use common::{read_tree, TempDir};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/migrate");
+
+/// A copy of the `before` folder of a case, with the path of its track definition
+fn copy_before(case: &str, dir: &Path) -> PathBuf {
+    for (path, content) in read_tree(&Path::new(FIXTURES).join(case).join("before")) {
+        let to = dir.join(path);
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::write(to, content).unwrap();
+    }
+    dir.join("basic.track.toml")
+}
+
+fn modmod(args: &[&str], track_path: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(args)
+        .arg(track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{args:?}: {stderr}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn assert_tree_is(dir: &Path, expected: &Path) {
+    let files = read_tree(dir);
+    let expected = read_tree(expected);
+    let paths =
+        |files: &[(PathBuf, Vec<u8>)]| files.iter().map(|f| f.0.clone()).collect::<Vec<_>>();
+    assert_eq!(paths(&files), paths(&expected));
+    for ((path, content), (_, expected)) in files.iter().zip(&expected) {
+        assert_eq!(
+            String::from_utf8_lossy(content),
+            String::from_utf8_lossy(expected),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+/// Migrates the case, checks the result, and checks that migrating it again changes nothing
+fn assert_migrates(case: &str) -> String {
+    let dir = TempDir::new();
+    let track_path = copy_before(case, dir.path());
+    let after = Path::new(FIXTURES).join(case).join("after");
+
+    let printed = modmod(&["migrate"], &track_path);
+    assert_tree_is(dir.path(), &after);
+    let again = modmod(&["migrate"], &track_path);
+    assert!(again.starts_with("0 file(s) migrated"), "{again}");
+    assert_tree_is(dir.path(), &after);
+    printed
+}
+
+#[test]
+fn migrates_version_1() {
+    let printed = assert_migrates("v1");
+    assert!(
+        printed.contains("  - renamed `modmod-version` to `modmod_version`\n"),
+        "{printed}"
+    );
+    assert!(
+        printed.ends_with("3 file(s) migrated to schema version 2, 0 file(s) already at it\n"),
+        "{printed}"
+    );
+}
+
+#[test]
+fn migrates_only_the_files_that_are_behind() {
+    let printed = assert_migrates("mixed");
+    assert!(!printed.contains("basic.track.toml"), "{printed}");
+    assert!(
+        printed.ends_with("2 file(s) migrated to schema version 2, 1 file(s) already at it\n"),
+        "{printed}"
+    );
+}
+
+#[test]
+fn dry_run_and_older_versions_change_nothing() {
+    let dir = TempDir::new();
+    let track_path = copy_before("v1", dir.path());
+    let before = Path::new(FIXTURES).join("v1/before");
+
+    let printed = modmod(&["--dry-run", "migrate"], &track_path);
+    assert_tree_is(dir.path(), &before);
+    assert!(
+        printed.contains("-modmod-version = \">=0.1\"\n+modmod_version = \">=0.1\"\n"),
+        "{printed}"
+    );
+    assert!(printed.contains("+schema_version = 2\n"), "{printed}");
+
+    let printed = modmod(&["migrate", "--to", "1"], &track_path);
+    assert_tree_is(dir.path(), &before);
+    assert!(printed.starts_with("0 file(s) migrated"), "{printed}");
+}
@@ -0,0 +1,51 @@
+//! A file of the track that's gone by the time it's rendered fails the render with an error
+//! that names both the file and the deck or book page it was for
+
+mod common;
+
+use std::fs;
+
+use common::{copy_basic_track, TempDir};
+use modmod::{context::RunContext, RenderOptions, Track};
+
+/// The error of rendering a copy of the fixture track of which `path` was removed after
+/// loading it
+fn render_without(path: &str) -> (String, String) {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let track = Track::load(&track_path).unwrap();
+    let removed = track_path.with_file_name(path);
+    fs::remove_file(&removed).unwrap();
+
+    let report = track
+        .render(&RenderOptions::new(dir.join("out")), &mut ())
+        .unwrap_err();
+    (format!("{report:?}"), removed.display().to_string())
+}
+
+#[test]
+fn names_the_deck_of_a_missing_slides_file() {
+    let _context = RunContext::default().enter();
+    let (error, path) = render_without("mods/A-basics/topics/ownership/slides.md");
+    assert!(error.contains(&path), "{error}");
+    assert!(error.contains("In slide deck 1.1 First steps"), "{error}");
+}
+
+#[test]
+fn names_the_deck_of_a_missing_template() {
+    let _context = RunContext::default().enter();
+    let (error, path) = render_without("mods/A-basics/intro.md");
+    assert!(error.contains(&path), "{error}");
+    assert!(error.contains("In slide deck 1.1 First steps"), "{error}");
+}
+
+#[test]
+fn names_the_page_of_a_missing_exercise_description() {
+    let _context = RunContext::default().enter();
+    let (error, path) = render_without("mods/A-basics/topics/hello/exercises/greet/description.md");
+    assert!(error.contains(&path), "{error}");
+    assert!(
+        error.contains("In book page 1.1 First steps of chapter 'Basics'"),
+        "{error}"
+    );
+}
@@ -0,0 +1,99 @@
+//! The Unix permission bits of files must survive rendering, rendering again and archiving
+
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use common::{copy_basic_track, render, TempDir, TestError};
+use modmod::{
+    archive::{self, ArchiveFormat},
+    context::RunContext,
+    io::PathExt,
+    Track,
+};
+
+const SCRIPT: &str = "exercises/1-basics/1-first-steps/1-greet/src/setup.sh";
+const MAIN: &str = "exercises/1-basics/1-first-steps/1-greet/src/main.rs";
+
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+fn set_mode(path: &Path, mode: u32) {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+/// A copy of the fixture track whose exercise has an executable script
+fn track_with_script(dir: &TempDir) -> Track {
+    let track_path = copy_basic_track(&dir.join("content"));
+    let exercise = track_path.with_file_name("mods/A-basics/topics/hello/exercises/greet");
+    fs::write(exercise.join("src/setup.sh"), "#!/bin/sh\necho ready\n").unwrap();
+    set_mode(&exercise.join("src/setup.sh"), 0o755);
+    set_mode(&exercise.join("src/main.rs"), 0o644);
+    Track::load(&track_path).unwrap()
+}
+
+#[test]
+fn copies_keep_their_mode() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = track_with_script(&dir);
+    let out = dir.join("out");
+    render(&track, &out);
+    assert_eq!(mode(&out.join(SCRIPT)), 0o755);
+    assert_eq!(mode(&out.join(MAIN)), 0o644);
+
+    // A script with the same content but without its executable bit is copied again
+    set_mode(&out.join(SCRIPT), 0o644);
+    render(&track, &out);
+    assert_eq!(mode(&out.join(SCRIPT)), 0o755);
+    assert_eq!(mode(&out.join(MAIN)), 0o644);
+}
+
+#[test]
+fn archives_keep_the_executable_bit() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let out = dir.join("out");
+    render(&track_with_script(&dir), &out);
+
+    for (format, name) in [
+        (ArchiveFormat::Zip, "course.zip"),
+        (ArchiveFormat::TarGz, "course.tar.gz"),
+    ] {
+        let entries = archive::entries_of_dir(&out).unwrap();
+        archive::write_archive(&entries, &dir.join(name), format).unwrap();
+        let executable: Vec<String> = archive::read_archive(&dir.join(name), format)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.executable)
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(executable, [SCRIPT], "{name}");
+    }
+}
+
+#[test]
+fn generated_files_get_their_mode() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let path: PathBuf = dir.join("run.sh");
+    let write = |mode| {
+        let mut file = path.create_file_with_mode::<TestError>(mode).unwrap();
+        file.write_all(b"#!/bin/sh\n").unwrap();
+        file.commit::<TestError>().unwrap();
+    };
+    write(0o755);
+    assert_eq!(mode(&path), 0o755);
+    // Only the mode changes, which is a change all the same
+    write(0o700);
+    assert_eq!(mode(&path), 0o700);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "#!/bin/sh\n");
+}
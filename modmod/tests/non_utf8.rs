@@ -0,0 +1,72 @@
+//! Paths that aren't valid UTF-8 are fine where they don't end up in the text of the output,
+//! like the folder the output is rendered into, which nothing in it refers to. Where they
+//! would end up in it, they are an error rather than a panic.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    ffi::OsStr,
+    fs,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use common::{copy_basic_track, read_tree, render, TempDir, TestError};
+use modmod::{
+    cache::CACHE_FILE, context::RunContext, io::slash_path, manifest::RENDER_MANIFEST_FILE, Track,
+};
+
+const EXERCISE: &str = "exercises/1-basics/1-first-steps/1-greet";
+
+/// The output of `track` rendered into `out_dir`, without the render manifest and the cache,
+/// which tell the output folder apart
+fn output(track: &Track, out_dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    render(track, out_dir);
+    let mut files = read_tree(out_dir);
+    files.retain(|(path, _)| {
+        ![RENDER_MANIFEST_FILE, CACHE_FILE]
+            .map(Path::new)
+            .contains(&path.as_path())
+    });
+    files
+}
+
+#[test]
+fn renders_into_a_folder_that_isnt_utf_8() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track = common::basic_track();
+    let expected = output(&track, &dir.join("out"));
+    let out_dir = dir.join(OsStr::from_bytes(b"out-\xff"));
+    assert_eq!(output(&track, &out_dir), expected);
+    // And again, into output that is there already
+    assert_eq!(output(&track, &out_dir), expected);
+}
+
+#[test]
+fn copies_exercise_files_whose_names_arent_utf_8() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let name = OsStr::from_bytes(b"caf\xe9.rs");
+    let src = track_path.with_file_name("mods/A-basics/topics/hello/exercises/greet/src");
+    fs::write(src.join(name), "// Latin-1\n").unwrap();
+
+    let out_dir = dir.join("out");
+    render(&Track::load(&track_path).unwrap(), &out_dir);
+    let copied = out_dir.join(EXERCISE).join("src").join(name);
+    assert_eq!(fs::read_to_string(copied).unwrap(), "// Latin-1\n");
+}
+
+#[test]
+fn paths_that_go_into_the_output_must_be_utf_8() {
+    let path = Path::new("exercises").join(OsStr::from_bytes(b"caf\xe9"));
+    let report = slash_path::<TestError>(&path).unwrap_err();
+    let error = format!("{report:?}");
+    assert!(
+        error.contains("Path exercises/caf\u{fffd} isn't valid UTF-8"),
+        "{error}"
+    );
+}
@@ -0,0 +1,116 @@
+//! The package.json stub the package of the slides is merged into is checked before it's
+//! merged, and mistakes in it are reported with where they are
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{render_in_memory, TempDir};
+use modmod::{context::RunContext, diagnostic::Location, RenderOptions};
+
+/// The error of rendering the fixture track with the stub `content`, and the path of the stub
+fn render_with_stub(content: &str) -> (String, Location, PathBuf) {
+    let dir = TempDir::new();
+    let stub = dir.join("package.json");
+    std::fs::write(&stub, content).unwrap();
+    let mut options = RenderOptions::new(dir.join("out"));
+    options.package_json = Some(stub.clone());
+    let report = common::basic_track().render(&options, &mut ()).unwrap_err();
+    let location = report
+        .frames()
+        .find_map(|frame| frame.downcast_ref::<Location>().cloned())
+        .unwrap();
+    (format!("{report:?}"), location, stub)
+}
+
+fn assert_invalid(content: &str, message: &str, line: Option<usize>) {
+    let (error, location, stub) = render_with_stub(content);
+    let expected = format!("Invalid package.json stub at {}: {message}", stub.display());
+    assert!(error.contains(&expected), "{error}");
+    assert_eq!(location.path, Some(stub), "{content}");
+    assert_eq!(location.line, line, "{content}");
+}
+
+#[test]
+fn scripts_must_be_an_object() {
+    let _context = RunContext::default().enter();
+    assert_invalid(
+        r#"{ "name": "slides", "scripts": "slidev build" }"#,
+        "expected an object at /scripts, but found a string",
+        None,
+    );
+    assert_invalid(
+        r#"{ "scripts": ["slidev"] }"#,
+        "expected an object at /scripts, but found an array",
+        None,
+    );
+}
+
+#[test]
+fn scripts_must_be_strings() {
+    let _context = RunContext::default().enter();
+    assert_invalid(
+        r#"{ "scripts": { "dev": "slidev", "build/all": { "run": "slidev build" } } }"#,
+        "expected a string at /scripts/build~1all, but found an object",
+        None,
+    );
+}
+
+#[test]
+fn the_stub_must_be_an_object() {
+    let _context = RunContext::default().enter();
+    assert_invalid(
+        r#"[{ "name": "slides" }]"#,
+        "expected an object at /, but found an array",
+        None,
+    );
+    assert_invalid("null", "expected an object at /, but found null", None);
+}
+
+#[test]
+fn keys_must_be_unique() {
+    let _context = RunContext::default().enter();
+    assert_invalid(
+        "{\n  \"name\": \"slides\",\n  \"scripts\": {\n    \"dev\": \"slidev\",\n    \"dev\": \"slidev --open\"\n  }\n}\n",
+        "duplicate key 'dev' at /scripts/dev",
+        Some(5),
+    );
+}
+
+#[test]
+fn nothing_may_follow_the_object() {
+    let _context = RunContext::default().enter();
+    assert_invalid(
+        "{ \"name\": \"slides\" }\n}\n",
+        "trailing characters at line 2 column 1",
+        Some(2),
+    );
+    assert_invalid(
+        "{ \"name\": \"slides\" }\n{ \"name\": \"again\" }\n",
+        "trailing characters at line 2 column 1",
+        Some(2),
+    );
+}
+
+#[test]
+fn scripts_of_the_stub_are_kept() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let stub = dir.join("package.json");
+    std::fs::write(
+        &stub,
+        r#"{ "name": "slides", "private": true, "scripts": { "lint": "eslint ." } }"#,
+    )
+    .unwrap();
+    let mut options = RenderOptions::new(dir.join("out"));
+    options.package_json = Some(stub);
+    let (_, files) = render_in_memory(&common::basic_track(), &options);
+    let (_, package) = files
+        .iter()
+        .find(|(path, _)| path.ends_with("slides/package.json"))
+        .unwrap();
+    let package: serde_json::Value = serde_json::from_slice(package).unwrap();
+    assert_eq!(package["private"], true);
+    assert_eq!(package["scripts"]["lint"], "eslint .");
+    assert_eq!(package["scripts"]["dev-1_1"], "slidev 1_1-first-steps.md");
+}
@@ -0,0 +1,100 @@
+//! Paths that end up in generated text are written with forward slashes, so that the output
+//! is the same on every platform. The paths here are put together with the separator of the
+//! platform the tests run on.
+
+mod common;
+
+use std::{
+    fs,
+    path::{PathBuf, MAIN_SEPARATOR_STR},
+};
+
+use common::{copy_basic_track, render, TempDir, TestError};
+use modmod::{context::RunContext, io::slash_path, Track};
+
+/// `parts` joined with the separator of the platform
+fn native(parts: &[&str]) -> PathBuf {
+    PathBuf::from(parts.join(MAIN_SEPARATOR_STR))
+}
+
+#[test]
+fn slash_path_joins_with_forward_slashes() {
+    let slash = |parts: &[&str]| slash_path::<TestError>(&native(parts)).unwrap();
+    assert_eq!(
+        slash(&["slides", "1_1-first-steps.md"]),
+        "slides/1_1-first-steps.md"
+    );
+    assert_eq!(slash(&["book", "src", ""]), "book/src");
+    assert_eq!(
+        slash(&[".", "exercises", "1-basics"]),
+        "./exercises/1-basics"
+    );
+    assert_eq!(slash(&["intro.md"]), "intro.md");
+    assert_eq!(slash(&[""]), "");
+    #[cfg(unix)]
+    {
+        assert_eq!(slash(&["", "course", "out"]), "/course/out");
+        assert_eq!(slash(&[""; 2]), "/");
+    }
+    #[cfg(windows)]
+    {
+        assert_eq!(slash(&["C:", "course", "out"]), "C:/course/out");
+        assert_eq!(
+            slash(&["", "", "server", "share", "out"]),
+            "//server/share/out"
+        );
+        // Relative to the working folder of the drive
+        assert_eq!(slash(&["D:notes", "intro.md"]), "D:notes/intro.md");
+    }
+}
+
+#[test]
+fn generated_text_is_the_same_on_every_platform() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let description = track_path.with_file_name(native(&[
+        "mods",
+        "A-basics",
+        "topics",
+        "hello",
+        "exercises",
+        "greet",
+        "description.md",
+    ]));
+    let mut text = fs::read_to_string(&description).unwrap();
+    text.push_str("Open `#[modmod:exercise_dir]`.\n");
+    fs::write(&description, text).unwrap();
+
+    let out = dir.join(native(&["output", "course"]));
+    render(&Track::load(&track_path).unwrap(), &out);
+
+    let read = |parts: &[&str]| fs::read_to_string(out.join(native(parts))).unwrap();
+    let summary = read(&["book", "src", "SUMMARY.md"]);
+    assert!(
+        summary.contains("\t- [First steps](first-steps.md)\n"),
+        "{summary}"
+    );
+    let page = read(&["book", "src", "first-steps.md"]);
+    assert!(
+        page.contains("Open `exercises/1-basics/1-first-steps/1-greet`."),
+        "{page}"
+    );
+    let package = read(&["slides", "package.json"]);
+    assert!(
+        package.contains("\"dev-1_1\": \"slidev 1_1-first-steps.md\""),
+        "{package}"
+    );
+    assert!(
+        package.contains("--out dist/1_1-first-steps --base /slides/1_1/ 1_1-first-steps.md\""),
+        "{package}"
+    );
+    let workspace = read(&["exercises", "Cargo.toml"]);
+    assert!(
+        workspace.contains("members = [\"1-basics/1-first-steps/1-greet\"]"),
+        "{workspace}"
+    );
+    for text in [summary, page, package, workspace] {
+        assert!(!text.contains('\\'), "{text}");
+    }
+}
@@ -0,0 +1,147 @@
+//! `modmod publish` of the rendered fixture track to a bare repository, with fake builds of
+//! the slides and the book, on a dry run, and without git on the `PATH`
+
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use common::{basic_track, render, TempDir};
+use modmod::context::RunContext;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn modmod(dir: &Path, args: &[&str], path: Option<&Path>) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_modmod"));
+    command.args(args).current_dir(dir);
+    if let Some(path) = path {
+        command.env("PATH", path);
+    }
+    command.output().unwrap()
+}
+
+/// A repository with a commit, whose `origin` is a bare repository, and the rendered
+/// track in its `out` folder, with the builds `--build` would make
+fn repository(dir: &TempDir) -> std::path::PathBuf {
+    let remote = dir.join("remote.git");
+    let repo = dir.join("repo");
+    fs::create_dir_all(&repo).unwrap();
+    git(dir.path(), &["init", "--quiet", "--bare", "remote.git"]);
+    git(&repo, &["init", "--quiet"]);
+    git(&repo, &["config", "user.name", "modmod"]);
+    git(&repo, &["config", "user.email", "modmod@example.com"]);
+    git(
+        &repo,
+        &["remote", "add", "origin", &remote.to_string_lossy()],
+    );
+    fs::write(repo.join(".gitignore"), "out/\n").unwrap();
+    git(&repo, &["add", ".gitignore"]);
+    git(&repo, &["commit", "--quiet", "-m", "Start"]);
+
+    let out = repo.join("out");
+    render(&basic_track(), &out);
+    let deck = out.join("slides/dist/1_1-first-steps");
+    fs::create_dir_all(&deck).unwrap();
+    fs::write(deck.join("index.html"), "deck").unwrap();
+    fs::create_dir_all(out.join("book/book")).unwrap();
+    fs::write(out.join("book/book/index.html"), "book").unwrap();
+    repo
+}
+
+#[test]
+fn pushes_the_built_output_to_the_branch() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let repo = repository(&dir);
+
+    let output = modmod(&repo, &["publish", "-o", "out"], None);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let files = git(
+        &dir.join("remote.git"),
+        &["ls-tree", "-r", "--name-only", "gh-pages"],
+    );
+    let files: Vec<_> = files.lines().collect();
+    assert!(files.contains(&"slides/1_1/index.html"), "{files:?}");
+    assert!(files.contains(&"book/index.html"), "{files:?}");
+    assert!(
+        files.iter().any(|f| f.starts_with("exercises/")),
+        "{files:?}"
+    );
+    // The repository itself is left alone
+    assert_eq!(git(&repo, &["status", "--porcelain"]), "");
+    assert!(git(&repo, &["branch", "--list", "gh-pages"]).is_empty());
+
+    // A second publish goes on top of the first
+    let output = modmod(&repo, &["publish", "-o", "out"], None);
+    assert!(output.status.success());
+    let log = git(&dir.join("remote.git"), &["log", "--oneline", "gh-pages"]);
+    assert_eq!(log.lines().count(), 2, "{log}");
+}
+
+#[test]
+fn publishes_without_git_on_the_path() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let repo = repository(&dir);
+    let empty = dir.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+
+    let output = modmod(&repo, &["publish", "-o", "out"], Some(&empty));
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let log = git(&dir.join("remote.git"), &["log", "--format=%s", "gh-pages"]);
+    let head = git(&repo, &["rev-parse", "--short", "HEAD"]);
+    assert_eq!(log.trim(), format!("Publish output of {}", head.trim()));
+}
+
+#[test]
+fn dry_runs_list_the_builds_and_files() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let repo = repository(&dir);
+
+    let output = modmod(
+        &repo,
+        &["--dry-run", "publish", "-o", "out", "--build"],
+        None,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for planned in [
+        "run `npm install` in out/slides",
+        "run `npm run build-1_1` in out/slides",
+        "run `mdbook build` in out/book",
+        "Would commit",
+    ] {
+        assert!(stdout.contains(planned), "{planned} in:\n{stdout}");
+    }
+    let branches = git(&dir.join("remote.git"), &["branch", "--list"]);
+    assert!(branches.is_empty(), "{branches}");
+}
@@ -0,0 +1,333 @@
+//! Renders of the fixture track, several in one process, which must not see each other's
+//! files, counts or copies. They are rendered in memory, but for the tests of the other
+//! ways of writing.
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use common::{basic_track, copy_basic_track, memory_tree, render_in_memory, TempDir};
+use modmod::{
+    context::RunContext,
+    io, log,
+    manifest::RenderManifest,
+    parallel,
+    report::{Warning, WarningCode},
+    RenderOptions, Track,
+};
+
+fn options(out_dir: &Path) -> RenderOptions {
+    let mut options = RenderOptions::new(out_dir);
+    options.clear_output_dir = true;
+    options
+}
+
+fn paths(files: &[(PathBuf, Vec<u8>)]) -> Vec<&Path> {
+    files.iter().map(|(path, _)| path.as_path()).collect()
+}
+
+#[test]
+fn renders_twice_in_one_process() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let (first, first_files) = render_in_memory(&track, &options(&dir.join("a")));
+    let (second, second_files) = render_in_memory(&track, &options(&dir.join("b")));
+    assert!(first.written_files > 0);
+    assert_eq!(second.written_files, first.written_files);
+    assert_eq!(second.unchanged_files, 0);
+    assert_eq!(second.asset_bytes, first.asset_bytes);
+    // Each report only has the timings of its own render
+    assert_eq!(second.timings.len(), first.timings.len());
+    assert_eq!(paths(&first_files), paths(&second_files));
+}
+
+#[test]
+fn renders_on_threads_at_the_same_time() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let (alone, alone_files) = render_in_memory(&track, &options(&dir.join("alone")));
+    let renders: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let (track, out_dir) = (&track, dir.join(i.to_string()));
+                scope.spawn(move || render_in_memory(track, &options(&out_dir)))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    for (report, files) in renders {
+        assert_eq!(report.written_files, alone.written_files);
+        assert_eq!(report.unchanged_files, 0);
+        assert_eq!(paths(&files), paths(&alone_files));
+    }
+}
+
+#[test]
+fn discarding_context_writes_nothing() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    {
+        let _context = RunContext::discarding().enter();
+        track.render(&options(&out_dir), &mut ()).unwrap();
+    }
+    assert!(!out_dir.exists());
+    // Once the context is left, renders write to disk again
+    let report = track.render(&options(&out_dir), &mut ()).unwrap();
+    assert!(report.written_files > 0);
+    assert!(out_dir.join("slides").is_dir());
+}
+
+#[test]
+fn in_memory_context_keeps_the_output() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    let (context, memory) = RunContext::in_memory();
+    let report = {
+        let _context = context.enter();
+        track.render(&options(&out_dir), &mut ()).unwrap()
+    };
+    assert!(!out_dir.exists());
+    let files = memory.files();
+    assert!(files
+        .keys()
+        .any(|path| path.ends_with("slides/package.json")));
+    assert_eq!(report.written_files, files.len());
+}
+
+#[test]
+fn recording_context_lists_the_writes() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let context = RunContext::recording();
+    let entered = context.enter();
+    track.render(&options(&dir.join("out")), &mut ()).unwrap();
+    let writes = entered.context().take_recorded_writes().unwrap();
+    assert!(!writes.is_empty());
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn renders_at_the_same_time_keep_their_own_settings_and_warnings() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let mod_toml = track_path.with_file_name("mods/A-basics/mod.toml");
+    let content = fs::read_to_string(&mod_toml).unwrap();
+    fs::write(&mod_toml, content.replace("First steps", "Première étape")).unwrap();
+
+    let runs: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = [(true, 1), (false, 3)]
+            .into_iter()
+            .map(|(unicode_slugs, jobs)| {
+                let (track_path, out_dir) = (&track_path, dir.join(format!("{unicode_slugs}")));
+                scope.spawn(move || {
+                    let (context, memory) = RunContext::in_memory();
+                    let context = context
+                        .unicode_slugs(unicode_slugs)
+                        .jobs(jobs)
+                        .fail_fast(unicode_slugs)
+                        .collect_failures();
+                    let _context = context.enter();
+                    let track = Track::load(track_path).unwrap();
+                    track.render(&options(&out_dir), &mut ()).unwrap();
+                    let code = match unicode_slugs {
+                        true => WarningCode::EmptyTopic,
+                        false => WarningCode::NoLicense,
+                    };
+                    log::record_warning(&Warning::new(code, "warning", None));
+                    let settings = (parallel::jobs(), parallel::fail_fast());
+                    let failures = log::take_failures().unwrap();
+                    let counts = log::warning_counts();
+                    let decks = memory_tree(&memory, &out_dir.join("slides"));
+                    (decks, settings, failures, counts)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let decks = |files: &[(PathBuf, Vec<u8>)]| {
+        files
+            .iter()
+            .filter_map(|(path, _)| path.to_str()?.strip_suffix(".md").map(String::from))
+            .collect::<Vec<_>>()
+    };
+    let [(unicode_decks, unicode_settings, unicode_failures, unicode_counts), (ascii_decks, ascii_settings, ascii_failures, ascii_counts)] =
+        <[_; 2]>::try_from(runs).unwrap();
+    assert_eq!(decks(&unicode_decks), ["1_1-première-étape"]);
+    assert_eq!(decks(&ascii_decks), ["1_1-premiere-etape"]);
+    assert_eq!(unicode_settings, (1, true));
+    assert_eq!(ascii_settings, (3, false));
+    // Each run only collects and counts its own warnings
+    assert_eq!(unicode_failures.warnings.len(), 1);
+    assert_eq!(ascii_failures.warnings.len(), 1);
+    assert_eq!(
+        unicode_counts.into_iter().collect::<Vec<_>>(),
+        [(WarningCode::EmptyTopic, 1)]
+    );
+    assert_eq!(
+        ascii_counts.into_iter().collect::<Vec<_>>(),
+        [(WarningCode::NoLicense, 1)]
+    );
+    // Outside of both, nothing was collected or counted
+    assert!(log::take_failures().is_none());
+    assert!(log::warning_counts().is_empty());
+}
+
+#[test]
+fn manifest_lists_the_artifacts_of_every_locale() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let slides = track_path.with_file_name("mods/A-basics/topics/hello/slides.md");
+    let content = fs::read_to_string(&slides).unwrap();
+    fs::write(
+        slides.with_file_name("slides.nl.md"),
+        content.replace("Hello, world!", "Hallo, wereld!"),
+    )
+    .unwrap();
+    let track = Track::load(&track_path).unwrap();
+    assert!(track.translations.contains("nl"));
+
+    // Like `generate --locale all`, which renders the locale of the track last
+    let out_dir = dir.join("out");
+    let (context, _memory) = RunContext::in_memory();
+    let _context = context.enter();
+    let mut options = options(&out_dir);
+    options.locale = Some("nl".to_string());
+    track.render(&options, &mut ()).unwrap();
+    options.locale = None;
+    track.render(&options, &mut ()).unwrap();
+
+    let manifest = RenderManifest::read(&out_dir).unwrap().unwrap();
+    let paths = |locale: Option<&str>| -> Vec<&str> {
+        manifest
+            .artifacts
+            .iter()
+            .filter(|a| a.locale.as_deref() == locale)
+            .map(|a| a.path.as_str())
+            .collect()
+    };
+    assert!(paths(None).contains(&common::DECK), "{:?}", paths(None));
+    assert!(
+        paths(Some("nl")).contains(&"slides-nl/1_1-first-steps.md"),
+        "{:?}",
+        paths(Some("nl"))
+    );
+    assert_eq!(
+        paths(None).len() + paths(Some("nl")).len(),
+        manifest.artifacts.len()
+    );
+    for artifact in &manifest.artifacts {
+        assert!(
+            io::exists(&out_dir.join(&artifact.path)),
+            "{}",
+            artifact.path
+        );
+    }
+    // The files in the root of the output are those of the track's own locale
+    assert!(manifest.main_artifacts().all(|a| a.locale.is_none()));
+
+    // Rendering the locale of the track again keeps those of the other locales
+    track.render(&options, &mut ()).unwrap();
+    let again = RenderManifest::read(&out_dir).unwrap().unwrap();
+    assert_eq!(again.artifacts.len(), manifest.artifacts.len());
+    assert!(!out_dir.exists());
+}
+
+#[test]
+fn clearing_removes_what_the_own_locale_no_longer_renders() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let slides = track_path.with_file_name("mods/A-basics/topics/hello/slides.md");
+    fs::copy(&slides, slides.with_file_name("slides.nl.md")).unwrap();
+    let out_dir = dir.join("out");
+    let (context, memory) = RunContext::in_memory();
+    let _context = context.enter();
+    let mut options = options(&out_dir);
+    for locale in [Some("nl"), None] {
+        options.locale = locale.map(str::to_string);
+        Track::load(&track_path)
+            .unwrap()
+            .render(&options, &mut ())
+            .unwrap();
+    }
+
+    let mod_toml = track_path.with_file_name("mods/A-basics/mod.toml");
+    let content = fs::read_to_string(&mod_toml).unwrap();
+    fs::write(&mod_toml, content.replace("First steps", "Getting started")).unwrap();
+    Track::load(&track_path)
+        .unwrap()
+        .render(&options, &mut ())
+        .unwrap();
+
+    for (path, rendered) in [
+        ("slides/1_1-getting-started.md", true),
+        ("book/src/getting-started.md", true),
+        (common::DECK, false),
+        ("book/src/first-steps.md", false),
+        ("exercises/1-basics/1-first-steps", false),
+        // The output of the other locale is left as it was
+        ("slides-nl/1_1-first-steps.md", true),
+        ("slides-nl/1_1-getting-started.md", false),
+    ] {
+        assert_eq!(io::exists(&out_dir.join(path)), rendered, "{path}");
+    }
+    // Nothing else is left of the earlier renders
+    let files = memory_tree(&memory, &out_dir);
+    let mut decks = paths(&files);
+    decks.retain(|path| path.extension() == Some("md".as_ref()) && path.starts_with("slides"));
+    assert_eq!(decks, [Path::new("slides/1_1-getting-started.md")]);
+    assert!(!out_dir.exists());
+}
+
+#[test]
+fn clearing_a_folder_that_isnt_output_needs_force() {
+    let _context = RunContext::default().enter();
+    let track = basic_track();
+    let dir = TempDir::new();
+    let checkout = dir.join("checkout");
+    fs::create_dir_all(&checkout).unwrap();
+    fs::write(checkout.join("notes.md"), "Not rendered by modmod").unwrap();
+
+    let mut options = options(&checkout);
+    let error = track.render(&options, &mut ()).unwrap_err();
+    assert!(
+        format!("{error:?}").contains("so it may not be an output folder"),
+        "{error:?}"
+    );
+    assert!(checkout.join("notes.md").exists());
+    assert_eq!(fs::read_dir(&checkout).unwrap().count(), 1);
+
+    options.force = true;
+    track.render(&options, &mut ()).unwrap();
+    assert!(!checkout.join("notes.md").exists());
+    // The folder holds output now, so it's rendered into again without force
+    options.force = false;
+    track.render(&options, &mut ()).unwrap();
+}
+
+#[test]
+fn threads_without_a_context_render_to_disk() {
+    let dir = TempDir::new();
+    let out_dir = dir.join("out");
+    // Like a thread that an embedding tool spawns, which doesn't enter a context
+    thread::spawn(move || {
+        let track = basic_track();
+        track.render(&options(&out_dir), &mut ()).unwrap();
+        assert!(out_dir.join("book/src/SUMMARY.md").exists());
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,92 @@
+//! Renders of unchanged tracks, which must give the same output byte for byte, and units
+//! whose output would end up at the same path
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{copy_basic_track, read_tree, render, TempDir, BASIC_TRACK};
+use modmod::{cache::CACHE_FILE, context::RunContext, Track};
+
+const DECKS_TRACK: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/decks/decks.track.toml"
+);
+
+#[test]
+fn renders_give_the_same_bytes() {
+    let _context = RunContext::default().enter();
+    // Without it, the render manifest says when the track was rendered and how long that
+    // took. This is the only test in this file that renders, so it doesn't affect others.
+    std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+
+    for track_path in [BASIC_TRACK, DECKS_TRACK] {
+        let track = Track::load(track_path).unwrap();
+        let dir = TempDir::new();
+        let out_dir = dir.join("out");
+        render(&track, &out_dir);
+        let first = read_tree(&out_dir);
+
+        // Into the same folder from scratch, so that nothing is kept from the first render
+        fs::remove_dir_all(&out_dir).unwrap();
+        render(&track, &out_dir);
+        assert_same(&first, &read_tree(&out_dir), track_path);
+
+        // Into another folder, where only the keys of the render cache differ, as they
+        // include the path of the output
+        let other = read_tree(&{
+            let other = dir.join("other");
+            render(&track, &other);
+            other
+        });
+        let without_cache = |files: &[(std::path::PathBuf, Vec<u8>)]| {
+            files
+                .iter()
+                .filter(|(path, _)| path != Path::new(CACHE_FILE))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_same(&without_cache(&first), &without_cache(&other), track_path);
+    }
+}
+
+fn assert_same(
+    first: &[(std::path::PathBuf, Vec<u8>)],
+    second: &[(std::path::PathBuf, Vec<u8>)],
+    track_path: &str,
+) {
+    let names = |files: &[(std::path::PathBuf, Vec<u8>)]| {
+        files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(first), names(second), "{track_path}");
+    for ((path, a), (_, b)) in first.iter().zip(second) {
+        assert!(
+            a == b,
+            "{} differs between renders of {track_path}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn units_with_the_same_slug_are_an_error() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(dir.path());
+    let module = track_path.with_file_name("mods/A-basics/mod.toml");
+    let mut definition = fs::read_to_string(&module).unwrap();
+    definition.push_str(
+        "\n[[units]]\nname = \"First-steps\"\ntopics = [\n    \"topics/ownership/topic.toml\",\n]\n",
+    );
+    fs::write(&module, definition).unwrap();
+
+    let message = format!("{:?}", Track::load(&track_path).unwrap_err());
+    assert!(
+        message.contains("'first-steps' is used by both unit 'First steps'")
+            && message.contains("and unit 'First-steps'"),
+        "{message}"
+    );
+}
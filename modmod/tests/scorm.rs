@@ -0,0 +1,198 @@
+//! `modmod scorm` of the rendered fixture track, with fake builds of the slides and the
+//! book, read back with the `zip` crate as a learning management system would
+
+mod common;
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use common::{basic_track, render, TempDir, BASIC_TRACK};
+use modmod::{
+    archive::{Entry, EntryContent},
+    context::RunContext,
+    scorm::{check_manifest, ScormOptions, ScormVersion, SCORM_MANIFEST_FILE},
+};
+
+fn modmod(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+/// The fixture track rendered into `out`, with the builds `--build` would make
+fn rendered(dir: &TempDir) -> PathBuf {
+    let out = dir.join("out");
+    render(&basic_track(), &out);
+    let deck = out.join("slides/dist/1_1-first-steps");
+    fs::create_dir_all(deck.join("assets")).unwrap();
+    fs::write(deck.join("index.html"), "deck").unwrap();
+    fs::write(deck.join("assets/index.js"), "slides()").unwrap();
+    fs::create_dir_all(out.join("book/book")).unwrap();
+    fs::write(out.join("book/book/index.html"), "book").unwrap();
+    fs::write(out.join("book/book/first-steps.html"), "chapter").unwrap();
+    out
+}
+
+/// The names and contents of the entries of the zip file at `path`, in their order
+fn read_zip(path: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut zip = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+    (0..zip.len())
+        .map(|i| {
+            let mut file = zip.by_index(i).unwrap();
+            let mut content = vec![];
+            file.read_to_end(&mut content).unwrap();
+            (file.name().unwrap().to_string(), content)
+        })
+        .collect()
+}
+
+fn package(version: &str) -> (TempDir, Vec<(String, Vec<u8>)>) {
+    let dir = TempDir::new();
+    let out = rendered(&dir);
+    let output = modmod(
+        dir.path(),
+        &[
+            "scorm",
+            BASIC_TRACK,
+            "--from",
+            &out.to_string_lossy(),
+            "-o",
+            "course.zip",
+            "--scorm-version",
+            version,
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let entries = read_zip(&dir.join("course.zip"));
+    (dir, entries)
+}
+
+fn manifest(entries: &[(String, Vec<u8>)]) -> String {
+    let (_, manifest) = entries
+        .iter()
+        .find(|(name, _)| name == SCORM_MANIFEST_FILE)
+        .expect("a manifest at the root of the package");
+    String::from_utf8(manifest.clone()).unwrap()
+}
+
+#[test]
+fn packages_read_back_with_the_manifest_at_the_root() {
+    let _context = RunContext::default().enter();
+    let (_dir, entries) = package("2004");
+    let names: Vec<_> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    for name in [
+        SCORM_MANIFEST_FILE,
+        "scorm.js",
+        "book/index.html",
+        "book/first-steps.html",
+        "slides/1_1/index.html",
+        "slides/1_1/assets/index.js",
+    ] {
+        assert!(names.contains(&name), "{name} in {names:?}");
+    }
+    assert!(names.iter().any(|name| name.starts_with("units/")));
+    for (name, content) in entries.iter() {
+        assert!(!name.starts_with('/') && !name.contains(".."), "{name}");
+        match name.as_str() {
+            "slides/1_1/assets/index.js" => assert_eq!(content, b"slides()"),
+            "book/first-steps.html" => assert_eq!(content, b"chapter"),
+            _ => {}
+        }
+    }
+
+    let manifest = manifest(&entries);
+    assert!(manifest.starts_with("<?xml"), "{manifest}");
+    assert!(
+        manifest.contains("<schemaversion>2004 4th Edition</schemaversion>"),
+        "{manifest}"
+    );
+    assert!(manifest.contains("adlcp:scormType=\"sco\""), "{manifest}");
+    // Every file the manifest refers to is in the package
+    for href in manifest.split(" href=\"").skip(1) {
+        let (href, _) = href.split_once('"').unwrap();
+        assert!(names.contains(&href), "{href} in {names:?}");
+    }
+}
+
+#[test]
+fn scorm_12_packages_use_its_schema() {
+    let _context = RunContext::default().enter();
+    let (_dir, entries) = package("1.2");
+    let manifest = manifest(&entries);
+    assert!(
+        manifest.contains("<schemaversion>1.2</schemaversion>"),
+        "{manifest}"
+    );
+    assert!(manifest.contains("adlcp:scormtype=\"sco\""), "{manifest}");
+    assert!(manifest.contains("imscp_rootv1p1p2"), "{manifest}");
+}
+
+#[test]
+fn unbuilt_output_is_refused() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let out = dir.join("out");
+    let track = basic_track();
+    render(&track, &out);
+
+    let options = ScormOptions {
+        layout: &track.output,
+        version: ScormVersion::Scorm2004,
+        build: false,
+    };
+    let error = track.scorm(&out, options).unwrap_err();
+    assert!(
+        format!("{error:?}").contains("hasn't been built, use --build to build it first"),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn manifests_that_refer_to_missing_files_fail_the_check() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let out = rendered(&dir);
+    let track = basic_track();
+    let options = ScormOptions {
+        layout: &track.output,
+        version: ScormVersion::Scorm2004,
+        build: false,
+    };
+    let package = track.scorm(&out, options).unwrap();
+    check_manifest(&package.entries).unwrap();
+    assert_eq!(package.units, 1);
+    assert_eq!(package.chapters, 1);
+    assert_eq!(package.decks, 1);
+
+    let without_deck: Vec<Entry> = package
+        .entries
+        .into_iter()
+        .filter(|e| e.path != "slides/1_1/index.html")
+        .collect();
+    let error = check_manifest(&without_deck).unwrap_err();
+    assert!(
+        format!("{error:?}").contains("slides/1_1/index.html"),
+        "{error:?}"
+    );
+
+    let none = [Entry {
+        path: "index.html".to_string(),
+        executable: false,
+        content: EntryContent::Bytes(vec![]),
+    }];
+    let error = check_manifest(&none).unwrap_err();
+    assert!(
+        format!("{error:?}").contains("The package has no imsmanifest.xml"),
+        "{error:?}"
+    );
+}
@@ -0,0 +1,170 @@
+//! Selecting part of a track with `Track::select_units` leaves the other units out of the
+//! output, and numbers what is left again without gaps
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{copy_basic_track, read_tree, TempDir};
+use modmod::{context::RunContext, Track};
+
+/// A copy of the fixture track with a second module of three units and a third of one tagged
+/// unit, each unit with an exercise of its own
+fn three_module_track(dir: &Path) -> Track {
+    let track_path = copy_basic_track(dir);
+    let modules = [
+        ("B-more", "More", &["Traits", "Generics", "Lifetimes"][..]),
+        ("C-last", "Last", &["Wrap up"][..]),
+    ];
+    for (id, name, units) in modules {
+        let module = dir.join("mods").join(id);
+        let mut definition = format!("name = \"{name}\"\ndescription = \"After the basics\"\n");
+        for unit in units {
+            let tag = unit.to_lowercase().replace(' ', "-");
+            definition.push_str(&format!(
+                "\n[[units]]\nname = \"{unit}\"\ntags = [\"{id}\"]\ntopics = [\"topics/{tag}/topic.toml\"]\n"
+            ));
+            let topic = module.join("topics").join(&tag);
+            let exercise = topic.join("exercises").join(&tag);
+            fs::create_dir_all(exercise.join("src")).unwrap();
+            fs::write(
+                topic.join("topic.toml"),
+                format!(
+                    "name = \"{unit}\"\n\n[[exercises]]\nname = \"{unit}\"\npath = \"exercises/{tag}\"\n"
+                ),
+            )
+            .unwrap();
+            fs::write(topic.join("slides.md"), format!("# {unit}\n")).unwrap();
+            fs::write(
+                exercise.join("Cargo.toml"),
+                format!("[package]\nname = \"{tag}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+            )
+            .unwrap();
+            fs::write(
+                exercise.join("description.md"),
+                format!("Practice {tag}.\n"),
+            )
+            .unwrap();
+            fs::write(exercise.join("src/main.rs"), "fn main() {}\n").unwrap();
+        }
+        fs::write(module.join("mod.toml"), definition).unwrap();
+    }
+    let track = fs::read_to_string(&track_path).unwrap().replace(
+        "modules = [\"mods/A-basics/mod.toml\"]",
+        "modules = [\"mods/A-basics/mod.toml\", \"mods/B-more/mod.toml\", \"mods/C-last/mod.toml\"]",
+    );
+    fs::write(&track_path, track).unwrap();
+    Track::load(&track_path).unwrap()
+}
+
+/// Selects `selectors` of the three module track, renders it and returns the decks, the
+/// exercise folders and the package.json scripts of the output
+fn render_selection(selectors: &[&str]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let dir = TempDir::new();
+    let mut track = three_module_track(&dir.join("track"));
+    let selectors: Vec<String> = selectors.iter().map(|s| s.to_string()).collect();
+    track.select_units(&selectors).unwrap();
+    let out_dir = dir.join("out");
+    common::render(&track, &out_dir);
+
+    let files = read_tree(&out_dir);
+    let paths = files.iter().map(|(path, _)| path.to_string_lossy());
+    let decks = paths
+        .clone()
+        .filter_map(|path| {
+            path.strip_prefix("slides/")?
+                .strip_suffix(".md")
+                .map(String::from)
+        })
+        .filter(|deck| !deck.contains('/'))
+        .collect();
+    let exercises = paths
+        .filter_map(|path| Some(path.strip_suffix("/Cargo.toml")?.to_string()))
+        .filter(|path| path.starts_with("exercises/") && path.matches('/').count() == 3)
+        .collect();
+    let package: serde_json::Value =
+        serde_json::from_slice(&fs::read(out_dir.join("slides/package.json")).unwrap()).unwrap();
+    let scripts = package["scripts"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    (decks, exercises, scripts)
+}
+
+#[test]
+fn selected_modules_and_units_are_numbered_without_gaps() {
+    let _context = RunContext::default().enter();
+    let (decks, exercises, scripts) = render_selection(&["A-basics", "2.3", "tag:C-last"]);
+
+    assert_eq!(
+        decks,
+        ["1_1-first-steps", "2_1-lifetimes", "3_1-wrap-up"],
+        "the deck prefixes are contiguous"
+    );
+    assert_eq!(
+        exercises,
+        [
+            "exercises/1-basics/1-first-steps/1-greet",
+            "exercises/2-more/1-lifetimes/1-lifetimes",
+            "exercises/3-last/1-wrap-up/1-wrap-up",
+        ]
+    );
+    assert_eq!(
+        scripts,
+        [
+            "dev-1_1",
+            "build-1_1",
+            "export-1_1",
+            "dev-2_1",
+            "build-2_1",
+            "export-2_1",
+            "dev-3_1",
+            "build-3_1",
+            "export-3_1",
+        ],
+        "there are scripts for the rendered decks only"
+    );
+}
+
+#[test]
+fn units_of_a_module_are_numbered_again_from_one() {
+    let _context = RunContext::default().enter();
+    let (decks, exercises, scripts) = render_selection(&["2.2", "2.3"]);
+
+    assert_eq!(decks, ["1_1-generics", "1_2-lifetimes"]);
+    assert_eq!(
+        exercises,
+        [
+            "exercises/1-more/1-generics/1-generics",
+            "exercises/1-more/2-lifetimes/1-lifetimes",
+        ]
+    );
+    assert_eq!(
+        scripts,
+        [
+            "dev-1_1",
+            "build-1_1",
+            "export-1_1",
+            "dev-1_2",
+            "build-1_2",
+            "export-1_2"
+        ]
+    );
+}
+
+#[test]
+fn selectors_that_match_nothing_are_refused() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let mut track = three_module_track(dir.path());
+
+    let error = track.select_units(&["2.4".to_string()]).unwrap_err();
+    let error = format!("{error:?}");
+    assert!(
+        error.contains("Unit selector '2.4' matches no modules or units. Did you mean '2.1'?"),
+        "{error}"
+    );
+    assert_eq!(track.units().count(), 5, "the track is left as it was");
+}
@@ -0,0 +1,239 @@
+//! `modmod serve` of a copy of the fixture track, with shell scripts as the dev servers, that
+//! say how they were started and keep running until they are stopped
+
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{self, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{change_upstream, copy_basic_track, TempDir};
+use modmod::context::RunContext;
+
+/// Prints its arguments, and the folder it runs in, then writes its pid to the file of
+/// its last argument and waits to be stopped
+const FAKE_SERVER: &str = r#"echo "$1 $2 $3 in $(pwd)"
+echo $$ >> "$4"
+exec sleep 60
+"#;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A port nothing listens on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// A running `modmod serve` with the lines it printed
+struct Serve {
+    process: process::Child,
+    lines: Receiver<String>,
+    seen: Vec<String>,
+    /// The lines that weren't waited for yet, as the servers start in either order
+    unmatched: Vec<String>,
+}
+
+impl Serve {
+    fn start(dir: &TempDir, track_path: &Path, ports: [u16; 3]) -> Self {
+        let script = dir.join("fake-server.sh");
+        fs::write(&script, FAKE_SERVER).unwrap();
+        let pids = dir.join("pids");
+        let command = |name: &str| {
+            format!(
+                "sh {} {name} {{deck}} {{port}} {}",
+                script.display(),
+                pids.display()
+            )
+        };
+        let [port, slides_port, book_port] = ports.map(|p| p.to_string());
+        let mut process = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .arg("serve")
+            .arg("-o")
+            .arg(dir.join("out"))
+            .args(["--port", &port, "--slides-port", &slides_port])
+            .args(["--book-port", &book_port, "--debounce", "50"])
+            .arg("--slides-command")
+            .arg(command("slides"))
+            .arg("--book-command")
+            .arg(command("book"))
+            .arg(track_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (sender, lines) = mpsc::channel();
+        for output in [
+            Box::new(process.stdout.take().unwrap()) as Box<dyn Read + Send>,
+            Box::new(process.stderr.take().unwrap()),
+        ] {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(output).lines().map_while(|l| l.ok()) {
+                    let _ = sender.send(line);
+                }
+            });
+        }
+        Self {
+            process,
+            lines,
+            seen: vec![],
+            unmatched: vec![],
+        }
+    }
+
+    /// Waits for a line that contains `text`, and returns it
+    fn wait_for(&mut self, text: &str) -> String {
+        if let Some(i) = self.unmatched.iter().position(|l| l.contains(text)) {
+            return self.unmatched.remove(i);
+        }
+        let started = Instant::now();
+        while let Some(left) = TIMEOUT.checked_sub(started.elapsed()) {
+            let Ok(line) = self.lines.recv_timeout(left) else {
+                break;
+            };
+            self.seen.push(line.clone());
+            if line.contains(text) {
+                return line;
+            }
+            self.unmatched.push(line);
+        }
+        panic!("No line with '{text}' in {:#?}", self.seen);
+    }
+
+    /// Stops it like Ctrl-C would, and returns what it printed
+    fn stop(mut self) -> Vec<String> {
+        let status = Command::new("kill")
+            .args(["-INT", &self.process.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = self.process.wait().unwrap();
+        assert!(status.success(), "{status}: {:#?}", self.seen);
+        self.seen.extend(self.lines.try_iter());
+        std::mem::take(&mut self.seen)
+    }
+}
+
+impl Drop for Serve {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+    }
+}
+
+fn is_alive(pid: &str) -> bool {
+    Command::new("kill")
+        .args(["-0", pid])
+        .stderr(Stdio::null())
+        .status()
+        .unwrap()
+        .success()
+}
+
+fn get(port: u16) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn serves_the_deck_and_the_book() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let ports @ [port, slides_port, book_port] = [free_port(), free_port(), free_port()];
+    let mut serve = Serve::start(&dir, &track_path, ports);
+
+    let slides = serve.wait_for("[slides] slides");
+    assert_eq!(
+        slides,
+        format!(
+            "[slides] slides 1_1-first-steps.md {slides_port} in {}",
+            dir.join("out/slides").display()
+        )
+    );
+    let book = serve.wait_for("[book] book");
+    assert!(
+        book.ends_with(&format!(
+            " {book_port} in {}",
+            dir.join("out/book").display()
+        )),
+        "{book}"
+    );
+
+    let index = get(port);
+    assert!(index.starts_with("HTTP/1.1 200 OK\r\n"), "{index}");
+    assert!(
+        index.contains(&format!(
+            "<li><a href=\"http://localhost:{slides_port}/\">1_1-first-steps</a> (served)</li>"
+        )),
+        "{index}"
+    );
+    assert!(
+        index.contains(&format!(
+            "<a href=\"http://localhost:{book_port}/\">Book</a>"
+        )),
+        "{index}"
+    );
+
+    // The servers pick up changes to the deck themselves
+    change_upstream(&track_path);
+    serve.wait_for("  regenerated slides/1_1-first-steps.md");
+    // But not to the slides package or the book configuration, which the track name is in
+    let track = fs::read_to_string(&track_path).unwrap();
+    fs::write(&track_path, track.replace("\"Basics\"", "\"Rust basics\"")).unwrap();
+    serve.wait_for("Restarting the slides server");
+    serve.wait_for("Restarting the book server");
+    serve.wait_for("[slides] slides");
+    serve.wait_for("[book] book");
+
+    let printed = serve.stop();
+    assert!(printed.iter().any(|l| l == "Stopping the dev servers"));
+    let restarts = printed
+        .iter()
+        .filter(|l| l.starts_with("Restarting"))
+        .count();
+    assert_eq!(restarts, 2, "{printed:#?}");
+    // Each of the four servers that were started is gone
+    let pids = fs::read_to_string(dir.join("pids")).unwrap();
+    assert_eq!(pids.lines().count(), 4, "{pids}");
+    for pid in pids.lines() {
+        assert!(!is_alive(pid), "{pid} still runs");
+    }
+}
+
+#[test]
+fn a_command_that_cannot_run_stops_serving() {
+    let dir = TempDir::new();
+    let track_path = copy_basic_track(&dir.join("content"));
+    let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+        .arg("serve")
+        .arg("-o")
+        .arg(dir.join("out"))
+        .args(["--port", &free_port().to_string()])
+        .args(["--slides-command", "modmod-missing-server {port}"])
+        .args(["--book-command", "true"])
+        .arg(&track_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("Unable to run `modmod-missing-server 3030`"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,162 @@
+//! A failure while rendering one slide deck of a track with several modules tells which
+//! deck, which section and which file it was about
+
+mod common;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{
+    context::RunContext,
+    io::{FsSink, OutputFile, RealFs},
+    RenderOptions, Track,
+};
+
+/// Reads and writes the file system like [`RealFs`], but reading or creating `failing`
+/// fails
+struct FailingFs {
+    failing: PathBuf,
+}
+
+impl FailingFs {
+    fn check(&self, path: &Path) -> io::Result<()> {
+        match path == self.failing {
+            true => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Permission denied",
+            )),
+            false => Ok(()),
+        }
+    }
+}
+
+impl FsSink for FailingFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.check(path)?;
+        RealFs.read(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        RealFs.create_dir_all(path)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<OutputFile> {
+        self.check(path)?;
+        RealFs.create_file(path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        RealFs.copy(from, to)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        RealFs.symlink(target, link)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        RealFs.remove_dir_all(path)
+    }
+
+    fn is_real(&self) -> bool {
+        true
+    }
+}
+
+const UNITS: [&str; 3] = ["Traits", "Generics", "Lifetimes"];
+
+/// A copy of the fixture track with a second module of three units, so that the last of
+/// them has the deck `2_3-lifetimes`
+fn two_module_track(dir: &Path) -> PathBuf {
+    let track_path = copy_basic_track(dir);
+    let module = dir.join("mods/B-more");
+    let mut definition = String::from("name = \"More\"\ndescription = \"After the basics\"\n");
+    for unit in UNITS {
+        let tag = unit.to_lowercase();
+        definition.push_str(&format!(
+            "\n[[units]]\nname = \"{unit}\"\ntopics = [\"topics/{tag}/topic.toml\"]\n"
+        ));
+        let topic = module.join("topics").join(&tag);
+        fs::create_dir_all(&topic).unwrap();
+        fs::write(topic.join("topic.toml"), format!("name = \"{unit}\"\n")).unwrap();
+        fs::write(
+            topic.join("slides.md"),
+            format!("# {unit}\n\nAbout {tag}\n"),
+        )
+        .unwrap();
+    }
+    fs::write(module.join("mod.toml"), definition).unwrap();
+    let track = fs::read_to_string(&track_path).unwrap();
+    let track = track.replace(
+        "modules = [\"mods/A-basics/mod.toml\"]",
+        "modules = [\"mods/A-basics/mod.toml\", \"mods/B-more/mod.toml\"]",
+    );
+    fs::write(&track_path, track).unwrap();
+    track_path
+}
+
+/// The error of rendering the track at `track_path` into `out_dir` while `failing` can't
+/// be read or written
+fn render_failing(track_path: &Path, out_dir: &Path, failing: PathBuf) -> String {
+    let track = Track::load(track_path).unwrap();
+    let _context = RunContext::with_sink(FailingFs { failing }).enter();
+    let report = track
+        .render(&RenderOptions::new(out_dir), &mut ())
+        .unwrap_err();
+    format!("{report:?}")
+}
+
+const DECK: &str = "In slide deck 2.3 Lifetimes (2_3-lifetimes), unit 3 of module 'More'";
+
+#[test]
+fn failure_reading_a_section_names_the_deck_and_the_file() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = two_module_track(&dir.join("content"));
+    let slides = track_path.with_file_name("mods/B-more/topics/lifetimes/slides.md");
+    let error = render_failing(&track_path, &dir.join("out"), slides.clone());
+
+    assert!(error.contains("Permission denied"), "{error}");
+    assert!(error.contains(DECK), "{error}");
+    assert!(
+        error.contains(&format!(
+            "In section 'Lifetimes', read from {}",
+            slides.display()
+        )),
+        "{error}"
+    );
+    // Only the deck that failed is named
+    assert!(!error.contains("2_2-generics"), "{error}");
+    assert!(!error.contains("1_1-first-steps"), "{error}");
+}
+
+#[test]
+fn failure_writing_a_deck_names_the_deck_and_where_it_went() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let track_path = two_module_track(&dir.join("content"));
+    let out_dir = dir.join("out");
+    fs::create_dir_all(&out_dir).unwrap();
+    let deck = out_dir
+        .canonicalize()
+        .unwrap()
+        .join("slides/2_3-lifetimes.md");
+    let error = render_failing(&track_path, &out_dir, deck.clone());
+
+    assert!(error.contains("Permission denied"), "{error}");
+    assert!(error.contains(DECK), "{error}");
+    assert!(
+        error.contains(&format!("While writing the deck to {}", deck.display())),
+        "{error}"
+    );
+}
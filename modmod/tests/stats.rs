@@ -0,0 +1,49 @@
+//! The estimates of `stats`, which are made of the same durations as `schedule.md`
+
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use modmod::{context::RunContext, stats::TrackStats, RenderOptions, Track};
+
+const DECKS_TRACK: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/decks/decks.track.toml"
+);
+
+#[test]
+fn core_and_optional_time() {
+    let _context = RunContext::default().enter();
+    let track = Track::load(DECKS_TRACK).unwrap();
+    let stats = TrackStats::collect(&track).unwrap();
+    let units = &stats.modules[0].units;
+
+    // The topics set 20 minutes, and 10 for the optional one, and the exercise has no
+    // duration, so it takes none
+    let every = &units[0].counts;
+    assert_eq!(every.core_minutes, 20);
+    assert_eq!(every.optional_minutes, 10);
+    assert_eq!(every.estimated_minutes, 30);
+    // Without a duration, the topic takes two minutes for its slide
+    let default = &units[1].counts;
+    assert_eq!((default.core_minutes, default.optional_minutes), (2, 0));
+
+    assert_eq!(stats.total.core_minutes, 22);
+    assert_eq!(stats.total.optional_minutes, 10);
+}
+
+#[test]
+fn schedule_has_the_same_durations() {
+    let _context = RunContext::default().enter();
+    let track = Track::load(DECKS_TRACK).unwrap();
+    let dir = TempDir::new();
+    let mut options = RenderOptions::new(dir.path());
+    options.schedule_md = true;
+    track.render(&options, &mut ()).unwrap();
+    let schedule = fs::read_to_string(dir.join("schedule.md")).unwrap();
+    assert!(
+        schedule.contains("| 1.1 Every placeholder | 30m | ? |"),
+        "{schedule}"
+    );
+}
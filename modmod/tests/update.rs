@@ -0,0 +1,312 @@
+//! `modmod update` of an output folder that was edited by hand, against renders of the
+//! fixture track, and `generate --clear`, which must keep those edits too
+
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{change_upstream, copy_basic_track, render, TempDir, DECK};
+
+const PAGE: &str = "book/src/first-steps.md";
+use modmod::{
+    context::RunContext,
+    update::{self, LocalEdits, CONFLICT_SUFFIX, EDITS_DIR, HASHES_FILE},
+    RenderOptions, Track,
+};
+
+/// The fixture track, copied into `dir` so that its content can be changed
+fn track_in(dir: &TempDir) -> (Track, std::path::PathBuf) {
+    let path = copy_basic_track(&dir.join("content"));
+    (Track::load(&path).unwrap(), path)
+}
+
+#[test]
+fn unchanged_upstream_leaves_everything() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, _) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let new_dir = dir.join("new");
+    render(&track, &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert!(report.had_manifest);
+    assert!(report.added.is_empty(), "{report}");
+    assert!(report.updated.is_empty(), "{report}");
+    assert!(report.kept.is_empty(), "{report}");
+    assert!(report.conflicts.is_empty(), "{report}");
+    assert!(report.unchanged > 0);
+}
+
+#[test]
+fn changed_upstream_replaces_files_that_werent_edited() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let new_dir = dir.join("new");
+    render(&change_upstream(&track_path), &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert!(report.updated.iter().any(|file| file == DECK), "{report}");
+    assert!(report.conflicts.is_empty(), "{report}");
+    let deck = fs::read_to_string(out_dir.join(DECK)).unwrap();
+    assert!(deck.contains("Hello, modmod!"));
+}
+
+#[test]
+fn user_edits_are_kept() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, _) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let edited = fs::read_to_string(out_dir.join(DECK)).unwrap() + "\nA note of my own\n";
+    fs::write(out_dir.join(DECK), &edited).unwrap();
+    let new_dir = dir.join("new");
+    render(&track, &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert_eq!(report.kept, [DECK], "{report}");
+    assert!(report.conflicts.is_empty(), "{report}");
+    assert_eq!(fs::read_to_string(out_dir.join(DECK)).unwrap(), edited);
+    assert!(!out_dir.join(format!("{DECK}{CONFLICT_SUFFIX}")).exists());
+}
+
+#[test]
+fn edits_on_both_sides_conflict() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let edited = fs::read_to_string(out_dir.join(DECK)).unwrap() + "\nA note of my own\n";
+    fs::write(out_dir.join(DECK), &edited).unwrap();
+    let new_dir = dir.join("new");
+    render(&change_upstream(&track_path), &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert_eq!(report.conflicts, [DECK], "{report}");
+    // The local version stays, with the new one next to it as `<name>.new`
+    assert_eq!(fs::read_to_string(out_dir.join(DECK)).unwrap(), edited);
+    let new = fs::read_to_string(out_dir.join(format!("{DECK}.new"))).unwrap();
+    assert!(new.contains("Hello, modmod!"));
+    assert_eq!(new, fs::read_to_string(new_dir.join(DECK)).unwrap());
+}
+
+#[test]
+fn hashes_under_the_old_name_are_read_and_renamed() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let old = out_dir.join(".modmod-manifest.json");
+    fs::rename(out_dir.join(HASHES_FILE), &old).unwrap();
+    let deck = out_dir.join(DECK);
+    fs::write(&deck, fs::read_to_string(&deck).unwrap() + "\nMy notes\n").unwrap();
+    let new_dir = dir.join("new");
+    render(&change_upstream(&track_path), &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert!(report.had_manifest);
+    assert!(report.conflicts.iter().any(|file| file == DECK), "{report}");
+    assert!(out_dir.join(HASHES_FILE).is_file());
+    assert!(!old.exists());
+}
+
+/// The FNV-1a hashes that versions of modmod before SHA-256 wrote
+fn fnv1a(content: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+#[test]
+fn fnv_hashes_of_older_versions_still_tell_edits() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let hashes_path = out_dir.join(HASHES_FILE);
+    let mut hashes: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&hashes_path).unwrap()).unwrap();
+    for (key, hash) in hashes["files"].as_object_mut().unwrap() {
+        assert_eq!(hash.as_str().unwrap().len(), 64);
+        *hash = fnv1a(&fs::read(out_dir.join(key)).unwrap()).into();
+    }
+    fs::write(&hashes_path, hashes.to_string()).unwrap();
+    assert!(LocalEdits::of_dir(&out_dir).unwrap().is_empty());
+    let deck = out_dir.join(DECK);
+    fs::write(&deck, fs::read_to_string(&deck).unwrap() + "\nMy notes\n").unwrap();
+    assert!(!LocalEdits::of_dir(&out_dir).unwrap().is_empty());
+    let new_dir = dir.join("new");
+    render(&change_upstream(&track_path), &new_dir);
+
+    let report = update::update(&new_dir, &out_dir).unwrap();
+    assert_eq!(report.conflicts, [DECK], "{report}");
+    assert!(report.kept.is_empty(), "{report}");
+    assert!(report.unchanged > 0);
+    let hashes: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&hashes_path).unwrap()).unwrap();
+    for hash in hashes["files"].as_object().unwrap().values() {
+        assert_eq!(hash.as_str().unwrap().len(), 64, "{hash}");
+    }
+}
+
+/// Renders `track` into `out_dir` again like `generate --clear` does, putting back the files
+/// that were changed by hand
+fn generate_again(track: &Track, out_dir: &Path) -> update::UpdateReport {
+    render(track, out_dir).local_edits.unwrap_or_default()
+}
+
+#[test]
+fn generating_again_keeps_user_edits() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let deck = out_dir.join(DECK);
+    let edited = format!(
+        "{}\n<!-- Ask who has used Rust before -->\n",
+        fs::read_to_string(&deck).unwrap()
+    );
+    fs::write(&deck, &edited).unwrap();
+    let main_rs = out_dir.join("exercises/1-basics/1-first-steps/1-greet/src/main.rs");
+    fs::write(&main_rs, "// Solved in class\n").unwrap();
+
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(report.kept.len(), 2, "{report:?}");
+    assert_eq!(fs::read_to_string(&deck).unwrap(), edited);
+
+    // The new version of an edited file that changed upstream is written next to it, and
+    // the edits are still known as such on the next render
+    let track = change_upstream(&track_path);
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(report.conflicts, [DECK], "{report:?}");
+    assert_eq!(fs::read_to_string(&deck).unwrap(), edited);
+    let new = fs::read_to_string(format!("{}{CONFLICT_SUFFIX}", deck.display())).unwrap();
+    assert!(new.contains("Hello, modmod!"));
+    assert!(!new.contains("Ask who has used Rust before"));
+    let report = generate_again(&track, &out_dir);
+    assert!(report.kept.iter().any(|file| file == DECK), "{report:?}");
+    assert_eq!(fs::read_to_string(&deck).unwrap(), edited);
+
+    // A file that is no longer generated is kept, as it was edited
+    let mod_toml = track_path.with_file_name("mods/A-basics/mod.toml");
+    let content = fs::read_to_string(&mod_toml).unwrap();
+    fs::write(&mod_toml, content.replace("First steps", "Getting started")).unwrap();
+    let track = Track::load(&track_path).unwrap();
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(
+        report.stale,
+        ["exercises/1-basics/1-first-steps/1-greet/src/main.rs", DECK],
+        "{report:?}"
+    );
+    assert_eq!(
+        fs::read_to_string(&main_rs).unwrap(),
+        "// Solved in class\n"
+    );
+    assert_eq!(fs::read_to_string(&deck).unwrap(), edited);
+    assert!(!main_rs.with_file_name("../Cargo.toml").exists());
+    // And still known as edited on the next render
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(report.stale.len(), 2, "{report:?}");
+}
+
+#[test]
+fn generating_again_with_force_overwrites_user_edits() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (_, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    let deck = out_dir.join(DECK);
+    let generate = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .args(["generate", "--clear", "-o"])
+            .arg(&out_dir)
+            .args(args)
+            .arg(&track_path)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert!(output.status.success(), "{stderr}");
+        stderr
+    };
+    generate(&[]);
+    let rendered = fs::read_to_string(&deck).unwrap();
+    fs::write(&deck, "# Edited\n").unwrap();
+    change_upstream(&track_path);
+
+    let stderr = generate(&[]);
+    assert!(
+        stderr.contains(&format!("{DECK} was changed locally, and kept")),
+        "{stderr}"
+    );
+    assert_eq!(fs::read_to_string(&deck).unwrap(), "# Edited\n");
+
+    generate(&["--force"]);
+    let deck = fs::read_to_string(&deck).unwrap();
+    assert_ne!(deck, rendered);
+    assert!(deck.contains("Hello, modmod!"));
+}
+
+#[test]
+fn failed_renders_keep_user_edits() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, track_path) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let page = out_dir.join(PAGE);
+    fs::write(&page, "# Edited\n").unwrap();
+
+    // The book page is rendered again before the deck fails
+    let slides = track_path.with_file_name("mods/A-basics/topics/ownership/slides.md");
+    let content = fs::read_to_string(&slides).unwrap();
+    fs::remove_file(&slides).unwrap();
+    let mut options = RenderOptions::new(&out_dir);
+    options.clear_output_dir = true;
+    let error = track.render(&options, &mut ()).unwrap_err();
+    assert!(format!("{error:?}").contains("slides.md"), "{error:?}");
+    assert_eq!(fs::read_to_string(&page).unwrap(), "# Edited\n");
+    assert!(!out_dir.join(EDITS_DIR).exists());
+
+    // And it's still known as edited once the content is fixed
+    fs::write(&slides, content).unwrap();
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(report.kept, [PAGE], "{report:?}");
+    assert_eq!(fs::read_to_string(&page).unwrap(), "# Edited\n");
+}
+
+#[test]
+fn interrupted_renders_keep_user_edits() {
+    let _context = RunContext::default().enter();
+    let dir = TempDir::new();
+    let (track, _) = track_in(&dir);
+    let out_dir = dir.join("out");
+    render(&track, &out_dir);
+    let deck = out_dir.join(DECK);
+    let rendered = fs::read_to_string(&deck).unwrap();
+    fs::write(&deck, "# Edited\n").unwrap();
+
+    // A render that saved the edits, overwrote the deck and was stopped
+    LocalEdits::of_dir(&out_dir)
+        .unwrap()
+        .save(&out_dir)
+        .unwrap();
+    assert!(out_dir.join(EDITS_DIR).join(DECK).is_file());
+    fs::write(&deck, &rendered).unwrap();
+
+    let report = generate_again(&track, &out_dir);
+    assert_eq!(report.kept, [DECK], "{report:?}");
+    assert_eq!(fs::read_to_string(&deck).unwrap(), "# Edited\n");
+    assert!(!out_dir.join(EDITS_DIR).exists());
+}
@@ -0,0 +1,470 @@
+//! Each warning code against a copy of the fixture track that has that warning, and no other.
+//! The copy starts out without warnings: its track has a license, and its topics were
+//! reviewed today. Library users get the same warnings as serde values, tested at the end.
+
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{copy_basic_track, TempDir};
+use modmod::{
+    context::RunContext,
+    date::Date,
+    manifest::ArtifactKind,
+    report::{Warning, WarningCode},
+    RenderOptions, Track,
+};
+use serde_json::{json, Value};
+
+const TRACK: &str = "basic.track.toml";
+const MODULE: &str = "mods/A-basics/mod.toml";
+const TEMPLATE: &str = "mods/A-basics/intro.md";
+const HELLO: &str = "mods/A-basics/topics/hello";
+const OWNERSHIP: &str = "mods/A-basics/topics/ownership";
+
+const LICENSE: &str = "
+[license]
+spdx = \"CC-BY-SA-4.0\"
+attribution = \"The authors of the modmod tests\"
+text = \"LICENSE\"
+";
+
+struct Fixture(TempDir);
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = TempDir::new();
+        copy_basic_track(dir.path());
+        let fixture = Self(dir);
+        fixture.edit(TRACK, |track| track + LICENSE);
+        fixture.write("LICENSE", "Licensed under CC BY-SA 4.0\n");
+        for topic in [HELLO, OWNERSHIP] {
+            fixture.prepend(
+                &format!("{topic}/topic.toml"),
+                &format!("last_reviewed = {}\n", Date::today()),
+            );
+        }
+        fixture
+    }
+
+    fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn edit(&self, path: &str, edit: impl FnOnce(String) -> String) {
+        let content = fs::read_to_string(self.0.join(path)).unwrap();
+        self.write(path, edit(content));
+    }
+
+    /// Puts `text` at the start of a file, before the tables of a definition
+    fn prepend(&self, path: &str, text: &str) {
+        self.edit(path, |content| format!("{text}{content}"));
+    }
+
+    fn append(&self, path: &str, text: &str) {
+        self.edit(path, |content| content + text);
+    }
+
+    fn replace(&self, path: &str, from: &str, to: &str) {
+        self.edit(path, |content| {
+            assert!(content.contains(from), "{path} has no {from:?}");
+            content.replace(from, to)
+        });
+    }
+
+    /// Runs modmod in the folder of the copy, which must succeed, and gives the codes of the
+    /// warnings it had
+    fn run(&self, args: &[&str]) -> Vec<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_modmod"))
+            .arg("--error-format=json")
+            .args(args)
+            .current_dir(self.0.path())
+            .env("MODMOD_CACHE_DIR", self.0.join("cache"))
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{args:?}: {stderr}");
+        let summary: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+        summary["warnings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|warning| warning["warning"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn check(&self) -> Vec<String> {
+        self.run(&["check", TRACK])
+    }
+
+    fn check_reviews(&self) -> Vec<String> {
+        self.run(&["check", TRACK, "--review-max-age", "365"])
+    }
+
+    fn generate(&self, args: &[&str]) -> Vec<String> {
+        let mut all = vec!["generate", "-o", "out", TRACK];
+        all.extend(args);
+        self.run(&all)
+    }
+}
+
+/// Adds a second unit to the module, with the ownership topic, which has no exercises
+fn add_unit(fixture: &Fixture, definition: &str) {
+    fixture.append(
+        MODULE,
+        &format!(
+            "\n[[units]]\nname = \"More\"\n{definition}topics = [\"topics/ownership/topic.toml\"]\n"
+        ),
+    );
+}
+
+/// A file of `size` bytes of markdown text
+fn large_text(size: usize) -> String {
+    format!("# Large\n\n{}\n", "a".repeat(size))
+}
+
+type Case = fn(&Fixture) -> Vec<String>;
+
+/// The cases, each with the code it is about and the other codes it has as well
+const CASES: &[(WarningCode, &[WarningCode], Case)] = &[
+    (WarningCode::MissingContent, &[], |f| {
+        fs::remove_file(f.0.join(OWNERSHIP).join("slides.md")).unwrap();
+        f.generate(&["--lenient"])
+    }),
+    (WarningCode::EmptyTopic, &[], |f| {
+        f.write(&format!("{OWNERSHIP}/slides.md"), "");
+        f.generate(&["--lenient"])
+    }),
+    (WarningCode::InvalidModmodVersion, &[], |f| {
+        f.prepend(TRACK, "modmod_version = \"banana\"\n");
+        f.check()
+    }),
+    (WarningCode::InvalidCourseVersion, &[], |f| {
+        f.prepend(TRACK, "version = \"banana\"\n");
+        f.check()
+    }),
+    (WarningCode::CourseVersionDowngrade, &[], |f| {
+        f.generate(&["--course-version", "2025.2"]);
+        f.run(&["update", "--course-version", "2025.1", "-o", "out", TRACK])
+    }),
+    (WarningCode::NoLicense, &[], |f| {
+        f.replace(TRACK, LICENSE, "");
+        f.check()
+    }),
+    (WarningCode::NoExercises, &[], |f| {
+        add_unit(f, "");
+        f.check()
+    }),
+    (WarningCode::UnexpectedExercises, &[], |f| {
+        f.replace(MODULE, "template = ", "no_exercises = true\ntemplate = ");
+        f.check()
+    }),
+    (WarningCode::ReviewOverdue, &[], |f| {
+        f.replace(
+            &format!("{OWNERSHIP}/topic.toml"),
+            &format!("last_reviewed = {}", Date::today()),
+            "last_reviewed = 2020-01-01",
+        );
+        f.check_reviews()
+    }),
+    (WarningCode::NeverReviewed, &[], |f| {
+        f.replace(
+            &format!("{OWNERSHIP}/topic.toml"),
+            &format!("last_reviewed = {}\n", Date::today()),
+            "",
+        );
+        f.check_reviews()
+    }),
+    (WarningCode::UnreferencedImage, &[], |f| {
+        f.replace(
+            &format!("{HELLO}/slides.md"),
+            "![Ferris](images/ferris.svg)",
+            "",
+        );
+        f.check()
+    }),
+    (WarningCode::IndexGap, &[], |f| {
+        add_unit(f, "index = 3\nno_exercises = true\n");
+        f.check()
+    }),
+    (WarningCode::AliasLink, &[], |f| {
+        f.prepend(
+            &format!("{OWNERSHIP}/topic.toml"),
+            "aliases = [\"owning\"]\n",
+        );
+        f.append(
+            &format!("{HELLO}/slides.md"),
+            "\nSee [ownership](https://example.com/book/owning.html)\n",
+        );
+        f.check()
+    }),
+    (WarningCode::DuplicateTemplate, &[], |f| {
+        fs::copy(f.0.join(TEMPLATE), f.0.join("copy.md")).unwrap();
+        f.prepend(TRACK, "default_slide_template = \"copy.md\"\n");
+        f.check()
+    }),
+    (WarningCode::TemplateOverrideIgnored, &[], |f| {
+        fs::copy(f.0.join(TEMPLATE), f.0.join("copy.md")).unwrap();
+        f.generate(&["--slide-template", "copy.md"])
+    }),
+    (WarningCode::BrokenLink, &[], |f| {
+        f.append(&format!("{HELLO}/slides.md"), "\nSee [notes](notes.md)\n");
+        f.check()
+    }),
+    (WarningCode::UnreachableLink, &[], |f| {
+        // Nothing listens on the discard port
+        f.append(
+            &format!("{HELLO}/slides.md"),
+            "\nSee [docs](http://127.0.0.1:9/docs)\n",
+        );
+        f.run(&["check", "--check-external", TRACK])
+    }),
+    (WarningCode::LinkCache, &[], |f| {
+        f.write("cache/external-links.json", "not json");
+        f.run(&["check", "--check-external", TRACK])
+    }),
+    (WarningCode::EmptyPlaceholder, &[], |f| {
+        f.append(TEMPLATE, "\n#[modmod:objectives]\n");
+        f.run(&["template", "inspect", "--deck", "1_1", TRACK])
+    }),
+    (WarningCode::MissingCourseField, &[], |f| {
+        f.append(TEMPLATE, "\n#[modmod:course_code]\n");
+        f.generate(&[])
+    }),
+    (WarningCode::UnresolvedPlaceholder, &[], |f| {
+        f.append(&format!("{HELLO}/slides.md"), "\n#[modmod:if(std)]\n");
+        f.generate(&[])
+    }),
+    (WarningCode::RepeatedObjective, &[], |f| {
+        for topic in [HELLO, OWNERSHIP] {
+            f.prepend(
+                &format!("{topic}/topic.toml"),
+                "objectives = [\"Say hello\"]\n",
+            );
+        }
+        f.generate(&[])
+    }),
+    (WarningCode::NoContentPlaceholder, &[], |f| {
+        f.replace(TEMPLATE, "#[modmod:content]", "");
+        f.generate(&[])
+    }),
+    (WarningCode::UntranslatableTopic, &[], |f| {
+        f.replace(
+            MODULE,
+            "    \"topics/ownership/topic.toml\",\n",
+            "    \"topics/ownership/topic.toml\",\n    { name = \"Cargo\", content = \"# Cargo\" },\n",
+        );
+        f.run(&["i18n", "extract", TRACK, "--locale", "nl", "-o", "nl"])
+    }),
+    (WarningCode::UnknownTranslation, &[], |f| {
+        translate(f);
+        f.replace(
+            "nl/ownership.toml",
+            "topics/ownership/topic.toml",
+            "topics/gone/topic.toml",
+        );
+        f.run(&["i18n", "apply", TRACK, "nl"])
+    }),
+    (
+        WarningCode::OutdatedTranslation,
+        // The string of the outdated translation isn't translated
+        &[WarningCode::Untranslated],
+        |f| {
+            translate(f);
+            f.replace(&format!("{HELLO}/slides.md"), "# Hello, world", "# Hello");
+            f.run(&["i18n", "apply", TRACK, "nl"])
+        },
+    ),
+    (WarningCode::Untranslated, &[], |f| {
+        f.run(&["i18n", "extract", TRACK, "--locale", "nl", "-o", "nl"]);
+        f.run(&["i18n", "apply", TRACK, "nl"])
+    }),
+    (WarningCode::UnknownConfigKey, &[], |f| {
+        f.write(".modmod.toml", "colour = \"red\"\n");
+        f.generate(&[])
+    }),
+    (WarningCode::LargeAsset, &[], |f| {
+        f.write(&format!("{HELLO}/images/large.svg"), large_text(1 << 20));
+        f.generate(&["--max-asset-size", "1"])
+    }),
+    (WarningCode::KeptScaffold, &[], |f| {
+        f.generate(&[]);
+        f.write("out/.github/workflows/deploy-course.yml", "name: ours\n");
+        f.generate(&[
+            "--clear",
+            "--deploy-workflow",
+            "github",
+            "--base-url",
+            "https://example.com/",
+        ])
+    }),
+    (WarningCode::ThumbnailsSkipped, &[], |f| {
+        f.generate(&["--thumbnails"])
+    }),
+    (WarningCode::SitemapSkipped, &[], |f| {
+        f.generate(&["--deploy-workflow", "github"])
+    }),
+    (WarningCode::QrCodeSkipped, &[], |f| {
+        f.generate(&["--qr-code", "feedback"])
+    }),
+    (WarningCode::LargeSection, &[], |f| {
+        f.write(&format!("{OWNERSHIP}/slides.md"), large_text(1 << 20));
+        f.generate(&["--max-section-size", "1"])
+    }),
+    (WarningCode::AttributionUnknown, &[], |f| {
+        // Topics without authors would be credited with the attribution of the track's license
+        f.replace(TRACK, LICENSE, "");
+        f.generate(&["--credits"])
+    }),
+];
+
+/// Extracts the strings of the topics, and translates all of them
+fn translate(fixture: &Fixture) {
+    fixture.run(&["i18n", "extract", TRACK, "--locale", "nl", "-o", "nl"]);
+    for topic in ["hello", "ownership"] {
+        fixture.edit(&format!("nl/{topic}.toml"), |file| {
+            file.replace("translation = \"\"", "translation = \"Vertaald\"")
+        });
+    }
+}
+
+#[test]
+fn the_fixture_has_no_warnings() {
+    let fixture = Fixture::new();
+    assert_eq!(fixture.check(), Vec::<String>::new());
+    assert_eq!(fixture.generate(&[]), Vec::<String>::new());
+}
+
+#[test]
+fn reviews_are_only_checked_with_a_max_age() {
+    let fixture = Fixture::new();
+    for topic in [HELLO, OWNERSHIP] {
+        fixture.replace(
+            &format!("{topic}/topic.toml"),
+            &format!("last_reviewed = {}\n", Date::today()),
+            "",
+        );
+    }
+    fixture.prepend(
+        &format!("{HELLO}/topic.toml"),
+        "last_reviewed = 2020-01-01\n",
+    );
+    assert_eq!(fixture.check(), Vec::<String>::new());
+    assert_eq!(
+        fixture.check_reviews(),
+        ["review-overdue", "never-reviewed"]
+    );
+}
+
+#[test]
+fn each_warning_code_has_a_case() {
+    for code in WarningCode::ALL {
+        assert!(
+            CASES.iter().any(|(c, _, _)| c == code),
+            "No case has a {code} warning"
+        );
+    }
+}
+
+#[test]
+fn each_case_has_its_warning() {
+    for (code, also, case) in CASES {
+        let fixture = Fixture::new();
+        let mut codes = case(&fixture);
+        codes.sort();
+        codes.dedup();
+        let mut expected: Vec<String> = std::iter::once(code)
+            .chain(also.iter())
+            .map(|code| code.to_string())
+            .collect();
+        expected.sort();
+        assert_eq!(codes, expected, "{code}");
+    }
+}
+
+#[test]
+fn warnings_serialize_with_their_artifact() {
+    let _context = RunContext::default().enter();
+    let fixture = Fixture::new();
+    fixture.prepend(TRACK, "modmod_version = \"banana\"\n");
+    fixture.append(&format!("{HELLO}/slides.md"), "\n#[modmod:nope]\n");
+    let track = Track::load(fixture.0.join(TRACK)).unwrap();
+    let out_dir = fixture.0.join("out");
+    let report = track
+        .render(&RenderOptions::new(&out_dir), &mut ())
+        .unwrap();
+
+    // The warnings of loading the track come first, and didn't come up rendering an artifact
+    let [loading, rendering] = report.warnings.as_slice() else {
+        panic!("{:#?}", report.warnings);
+    };
+    assert_eq!(track.warnings, std::slice::from_ref(loading));
+    assert_eq!(
+        serde_json::to_value(loading).unwrap(),
+        json!({
+            "code": "invalid-modmod-version",
+            "message": loading.message,
+            "source": fixture.0.join(TRACK).canonicalize().unwrap(),
+            "line": null,
+        })
+    );
+    let deck = out_dir.canonicalize().unwrap().join(common::DECK);
+    assert_eq!(
+        serde_json::to_value(rendering).unwrap(),
+        json!({
+            "code": "unresolved-placeholder",
+            "message": "Unresolved placeholder `#[modmod:nope]`",
+            "source": deck,
+            "line": 25,
+            "artifact": "deck",
+        })
+    );
+
+    for warning in [loading, rendering] {
+        let json = serde_json::to_string(warning).unwrap();
+        assert_eq!(&serde_json::from_str::<Warning>(&json).unwrap(), warning);
+    }
+    let report: Value = serde_json::to_value(&report).unwrap();
+    assert_eq!(report["warnings"][1]["artifact"], "deck");
+}
+
+#[test]
+fn warnings_deserialize_from_their_codes() {
+    let warning: Warning = serde_json::from_value(json!({
+        "code": "index-gap",
+        "message": "Module 2 is missing",
+        "source": null,
+        "line": null,
+    }))
+    .unwrap();
+    assert_eq!(
+        warning,
+        Warning::new(WarningCode::IndexGap, "Module 2 is missing", None)
+    );
+    let warning: Warning = serde_json::from_value(json!({
+        "code": "empty-topic",
+        "message": "Topic 'Ownership' has no slides",
+        "source": "topics/ownership/slides.md",
+        "line": 3,
+        "artifact": "chapter",
+    }))
+    .unwrap();
+    assert_eq!(warning.artifact, Some(ArtifactKind::Chapter));
+    assert_eq!(warning.line, Some(3));
+
+    let error = serde_json::from_value::<Warning>(json!({
+        "code": "W0102",
+        "message": "Topic 'Ownership' has no slides",
+        "source": null,
+        "line": null,
+    }))
+    .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Unknown warning code 'W0102'"),
+        "{error}"
+    );
+}